@@ -35,6 +35,11 @@ resource "aws_dynamodb_table" "events" {
     type = "S"
   }
 
+  attribute {
+    name = "namespace"
+    type = "S"
+  }
+
   # Global secondary index for querying by timestamp (most recent first)
   global_secondary_index {
     name            = "TimestampIndex"
@@ -51,6 +56,14 @@ resource "aws_dynamodb_table" "events" {
     projection_type = "ALL"
   }
 
+  # Global secondary index for querying a tenant namespace (`events_query`)
+  global_secondary_index {
+    name            = "namespace-index"
+    hash_key        = "namespace"
+    range_key       = "timestamp"
+    projection_type = "ALL"
+  }
+
   point_in_time_recovery {
     enabled = false
   }
@@ -1,13 +1,87 @@
+use async_trait::async_trait;
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use aws_sdk_dynamodb::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_dynamodb::operation::{
+    batch_write_item::BatchWriteItemError, delete_item::DeleteItemError,
+    describe_table::DescribeTableError, get_item::GetItemError, put_item::PutItemError,
+    query::QueryError, scan::ScanError, transact_write_items::TransactWriteItemsError,
+    update_item::UpdateItemError,
+};
 use aws_sdk_dynamodb::Client as DynamoDbClient;
+use aws_sdk_eventbridge::operation::put_events::PutEventsError;
 use aws_sdk_eventbridge::Client as EventBridgeClient;
+use aws_sdk_kms::Client as KmsClient;
+use aws_sdk_lambda::Client as LambdaClient;
+use aws_sdk_s3::operation::{
+    copy_object::CopyObjectError, delete_object::DeleteObjectError,
+    get_bucket_lifecycle_configuration::GetBucketLifecycleConfigurationError,
+    get_object::GetObjectError, get_object_tagging::GetObjectTaggingError,
+    head_object::HeadObjectError, list_objects_v2::ListObjectsV2Error,
+    put_bucket_lifecycle_configuration::PutBucketLifecycleConfigurationError,
+    put_object::PutObjectError,
+};
 use aws_sdk_s3::Client as S3Client;
 use aws_sdk_secretsmanager::Client as SecretsManagerClient;
+use aws_sdk_sfn::operation::{
+    describe_execution::DescribeExecutionError, start_execution::StartExecutionError,
+    stop_execution::StopExecutionError,
+};
+use aws_sdk_sfn::Client as SfnClient;
+use aws_sdk_sns::operation::{
+    get_topic_attributes::GetTopicAttributesError, publish::PublishError,
+};
+use aws_sdk_sns::Client as SnsClient;
+use aws_sdk_sqs::operation::{
+    change_message_visibility::ChangeMessageVisibilityError, delete_message::DeleteMessageError,
+    get_queue_url::GetQueueUrlError, receive_message::ReceiveMessageError,
+    send_message::SendMessageError,
+};
+use aws_sdk_sqs::Client as SqsClient;
+use base64::{engine::general_purpose, Engine as _};
 use serde_json::{json, Value};
-use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use thiserror::Error;
 
+use crate::config::{AwsConfig, Backend, Config};
+use crate::crypto::{EnvelopeEncryptor, KmsDataKeyProvider, SealedValue};
 use crate::tenant::TenantSession;
 
+pub mod alerts;
+pub mod artifacts;
+pub mod credentials;
+pub mod event_buffer;
+pub mod event_queries;
+pub mod internal_keys;
+pub mod kv_cache;
+pub mod latency;
+pub mod memory;
+pub mod namespaces;
+pub mod queues;
+pub mod resources;
+pub mod tenant_roles;
+pub mod transactions;
+pub mod workflows;
+
+use alerts::NewAlertSubscription;
+use artifacts::{
+    ArtifactDeletion, ArtifactLifecycleRule, ArtifactMetadata, ArtifactPosition, RetentionDays,
+    TtlClass,
+};
+use event_buffer::{EventBridgeSink, EventBuffer, KvDeadLetters};
+use event_queries::{EventQuery, EventQueryPlan, IndexQuery, IndexQueryStats};
+pub use internal_keys::validate_direct_key;
+use kv_cache::{KvCache, KvCacheStats};
+use latency::{AwsCall, AwsLatency, LatencyStats};
+use memory::MemoryAwsService;
+use namespaces::{ArtifactMigration, EventScope};
+use queues::QueueMessage;
+use resources::{AwsResourceProber, ResourceReport};
+use tenant_roles::{StsRoleAssumer, TenantClients};
+use transactions::{ItemCondition, KvWriteOp, TransactionFailure};
+use workflows::WorkflowExecution;
+
 #[derive(Error, Debug)]
 pub enum AwsError {
     #[error("DynamoDB error: {0}")]
@@ -15,15 +89,380 @@ pub enum AwsError {
     #[error("S3 error: {0}")]
     S3(String),
     #[error("EventBridge error: {0}")]
-    #[allow(dead_code)]
     EventBridge(String),
     #[error("SecretsManager error: {0}")]
     #[allow(dead_code)]
     SecretsManager(String),
+    #[error("KMS error: {0}")]
+    Kms(String),
+    #[error("Encryption error: {0}")]
+    Encryption(String),
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     #[error("AWS configuration error: {0}")]
     Config(String),
+    #[error("Key {0:?} is not an internal key")]
+    InvalidKey(String),
+    #[error("Invalid secret name {0:?}: use 1-128 letters, digits or _+=.@-")]
+    InvalidSecretName(String),
+    #[error("Step Functions error: {0}")]
+    StepFunctions(String),
+    /// A workflow request the caller got wrong, e.g. a state machine that isn't allowed
+    #[error("Invalid workflow request: {0}")]
+    InvalidWorkflow(String),
+    #[error("SNS error: {0}")]
+    Sns(String),
+    #[error("Invalid SNS topic ARN {0}")]
+    InvalidTopic(String),
+    #[error("SQS error: {0}")]
+    Sqs(String),
+    #[error("Invalid queue name {0:?}: use letters, digits or _, up to 80 characters with the tenant prefix")]
+    InvalidQueueName(String),
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+    /// A transaction was canceled and none of its writes applied
+    #[error("Transaction canceled: {}", transactions::describe_failures(.0))]
+    TransactionCanceled(Vec<TransactionFailure>),
+    /// The object or item asked for doesn't exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+    /// A conditional write's condition didn't hold
+    #[error("Condition failed: {0}")]
+    ConditionFailed(String),
+    /// The configured table doesn't exist (not provisioned, or the wrong name or region)
+    #[error("Table missing: {0}")]
+    TableMissing(String),
+    /// The server's own AWS credentials may not make the call
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+    /// The tenant's own AWS role (see `TenantContext::aws_role`) couldn't be assumed
+    #[error("Could not assume the tenant's AWS role {role_arn}: {reason}")]
+    RoleAssumption { role_arn: String, reason: String },
+    #[error("AWS throttled {service_key} requests ({code})")]
+    Throttled {
+        /// Rate-limit bucket of the throttled operation (see `AwsOperation::service_key`)
+        service_key: &'static str,
+        code: String,
+        /// How long the caller should wait before retrying
+        retry_after_hint: Duration,
+    },
+}
+
+/// Items read per page when `kv_list` scans the table
+const KV_LIST_PAGE_SIZE: i32 = 100;
+
+/// Most items one `BatchWriteItem` call takes
+const BATCH_WRITE_SIZE: usize = 25;
+
+/// Calls made for one batch before its unprocessed items are reported as an error
+const BATCH_WRITE_ATTEMPTS: u32 = 4;
+
+/// Error codes AWS services return when they throttle a caller
+const THROTTLING_ERROR_CODES: &[&str] = &[
+    "ProvisionedThroughputExceededException",
+    "RequestLimitExceeded",
+    "ThrottlingException",
+    "Throttling",
+    "ThrottledException",
+    "TooManyRequestsException",
+    "RequestThrottled",
+    "RequestThrottledException",
+    "SlowDown",
+];
+
+/// How long to wait after AWS throttles a call before trying again
+const THROTTLE_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// S3 returns `SlowDown` when a prefix's request rate is too high, and asks for a longer
+/// pause while it scales the prefix out
+const SLOW_DOWN_RETRY_AFTER: Duration = Duration::from_secs(2);
+
+fn throttle_retry_hint(code: &str) -> Duration {
+    match code {
+        "SlowDown" => SLOW_DOWN_RETRY_AFTER,
+        _ => THROTTLE_RETRY_AFTER,
+    }
+}
+
+/// Service errors that mean the same whichever call raised them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorMeaning {
+    NotFound,
+    ConditionFailed,
+    TableMissing,
+    /// The service throttled the caller; carries the exception's name
+    Throttled(&'static str),
+}
+
+/// An operation's typed service error, classified by its variant rather than its message
+trait ServiceError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        None
+    }
+}
+
+impl ServiceError for GetItemError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for DescribeTableError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for PutItemError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ConditionalCheckFailedException(_) => Some(ErrorMeaning::ConditionFailed),
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for DeleteItemError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ConditionalCheckFailedException(_) => Some(ErrorMeaning::ConditionFailed),
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for UpdateItemError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ConditionalCheckFailedException(_) => Some(ErrorMeaning::ConditionFailed),
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for QueryError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for ScanError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for BatchWriteItemError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ResourceNotFoundException(_) => Some(ErrorMeaning::TableMissing),
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for GetObjectError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_no_such_key().then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for TransactWriteItemsError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::ProvisionedThroughputExceededException(_) => Some(ErrorMeaning::Throttled(
+                "ProvisionedThroughputExceededException",
+            )),
+            Self::RequestLimitExceeded(_) => Some(ErrorMeaning::Throttled("RequestLimitExceeded")),
+            Self::ThrottlingException(_) => Some(ErrorMeaning::Throttled("ThrottlingException")),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for PutObjectError {}
+
+impl ServiceError for HeadObjectError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_not_found().then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for GetObjectTaggingError {}
+
+impl ServiceError for GetBucketLifecycleConfigurationError {}
+
+impl ServiceError for PutBucketLifecycleConfigurationError {}
+
+impl ServiceError for ListObjectsV2Error {}
+
+impl ServiceError for CopyObjectError {}
+
+impl ServiceError for DeleteObjectError {}
+
+impl ServiceError for PutEventsError {}
+
+impl ServiceError for StartExecutionError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        match self {
+            Self::StateMachineDoesNotExist(_) => Some(ErrorMeaning::NotFound),
+            Self::ExecutionAlreadyExists(_) => Some(ErrorMeaning::ConditionFailed),
+            _ => None,
+        }
+    }
+}
+
+impl ServiceError for DescribeExecutionError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_execution_does_not_exist()
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for StopExecutionError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_execution_does_not_exist()
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for GetQueueUrlError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_queue_does_not_exist()
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for SendMessageError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_queue_does_not_exist()
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for ReceiveMessageError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_queue_does_not_exist()
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for DeleteMessageError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        (self.is_queue_does_not_exist() || self.is_receipt_handle_is_invalid())
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for ChangeMessageVisibilityError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        (self.is_queue_does_not_exist()
+            || self.is_receipt_handle_is_invalid()
+            || self.is_message_not_inflight())
+        .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for GetTopicAttributesError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_not_found_exception()
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+impl ServiceError for PublishError {
+    fn meaning(&self) -> Option<ErrorMeaning> {
+        self.is_not_found_exception()
+            .then_some(ErrorMeaning::NotFound)
+    }
+}
+
+/// Convert an SDK error. Service errors with a meaning of their own become `NotFound`,
+/// `ConditionFailed`, `TableMissing` or `Throttled`. Operations without a typed
+/// throttling variant (S3's `SlowDown`, EventBridge's `ThrottlingException`) are
+/// recognised by their AWS error code, so every throttle can be fed back into the rate
+/// limiter; anything else is wrapped with `wrap`.
+fn sdk_error<E, R>(
+    err: SdkError<E, R>,
+    service_key: &'static str,
+    wrap: fn(String) -> AwsError,
+) -> AwsError
+where
+    E: ServiceError + ProvideErrorMetadata + std::fmt::Display,
+{
+    if let Some((service_err, meaning)) =
+        err.as_service_error().and_then(|e| Some((e, e.meaning()?)))
+    {
+        let message = service_err.to_string();
+        return match meaning {
+            ErrorMeaning::NotFound => AwsError::NotFound(message),
+            ErrorMeaning::ConditionFailed => AwsError::ConditionFailed(message),
+            ErrorMeaning::TableMissing => AwsError::TableMissing(message),
+            ErrorMeaning::Throttled(code) => AwsError::Throttled {
+                service_key,
+                code: code.to_string(),
+                retry_after_hint: throttle_retry_hint(code),
+            },
+        };
+    }
+    match err.code() {
+        Some(code) if THROTTLING_ERROR_CODES.contains(&code) => AwsError::Throttled {
+            service_key,
+            code: code.to_string(),
+            retry_after_hint: throttle_retry_hint(code),
+        },
+        _ => wrap(err.to_string()),
+    }
 }
 
 pub struct AwsClients {
@@ -31,218 +470,1092 @@ pub struct AwsClients {
     pub s3: S3Client,
     pub eventbridge: EventBridgeClient,
     pub secrets_manager: SecretsManagerClient,
+    pub kms: KmsClient,
+    pub lambda: LambdaClient,
+    /// Only created when metrics publishing is enabled (`MCP_CLOUDWATCH_NAMESPACE`)
+    pub cloudwatch: Option<CloudWatchClient>,
+    pub sfn: SfnClient,
+    pub sqs: SqsClient,
+    pub sns: SnsClient,
+}
+
+/// Endpoint every client uses instead of AWS, e.g. `http://localhost:4566`
+pub const LOCALSTACK_ENDPOINT_ENV: &str = "LOCALSTACK_ENDPOINT";
+
+/// Where each client sends its requests; `None` is the service's AWS endpoint
+#[derive(Debug, Clone, Default)]
+pub struct ServiceEndpoints {
+    dynamodb: Option<String>,
+    s3: Option<String>,
+    eventbridge: Option<String>,
+    secrets_manager: Option<String>,
+    kms: Option<String>,
+    lambda: Option<String>,
+    cloudwatch: Option<String>,
+    sfn: Option<String>,
+    sqs: Option<String>,
+    sns: Option<String>,
+}
+
+impl ServiceEndpoints {
+    /// `MCP_<SERVICE>_ENDPOINT` per service, falling back to `LOCALSTACK_ENDPOINT`
+    pub fn resolve(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let localstack = lookup(LOCALSTACK_ENDPOINT_ENV);
+        let endpoint = |service: &str| {
+            lookup(&format!("MCP_{}_ENDPOINT", service))
+                .or_else(|| localstack.clone())
+                .filter(|endpoint| !endpoint.is_empty())
+        };
+        Self {
+            dynamodb: endpoint("DYNAMODB"),
+            s3: endpoint("S3"),
+            eventbridge: endpoint("EVENTBRIDGE"),
+            secrets_manager: endpoint("SECRETSMANAGER"),
+            kms: endpoint("KMS"),
+            lambda: endpoint("LAMBDA"),
+            cloudwatch: endpoint("CLOUDWATCH"),
+            sfn: endpoint("SFN"),
+            sqs: endpoint("SQS"),
+            sns: endpoint("SNS"),
+        }
+    }
+
+    fn all(&self) -> [(&'static str, &Option<String>); 10] {
+        [
+            ("DynamoDB", &self.dynamodb),
+            ("S3", &self.s3),
+            ("EventBridge", &self.eventbridge),
+            ("Secrets Manager", &self.secrets_manager),
+            ("KMS", &self.kms),
+            ("Lambda", &self.lambda),
+            ("CloudWatch", &self.cloudwatch),
+            ("Step Functions", &self.sfn),
+            ("SQS", &self.sqs),
+            ("SNS", &self.sns),
+        ]
+    }
+
+    fn any_overridden(&self) -> bool {
+        self.all().iter().any(|(_, endpoint)| endpoint.is_some())
+    }
+
+    /// What's wrong when some of the services holding tenant data are sent to an
+    /// emulator and others to AWS, e.g. a LocalStack DynamoDB beside the real S3
+    pub fn mixed_storage(&self) -> Option<String> {
+        let (emulated, aws): (Vec<_>, Vec<_>) = self
+            .all()
+            .into_iter()
+            .filter(|(service, _)| STORAGE_SERVICES.contains(service))
+            .partition(|(_, endpoint)| endpoint.is_some());
+        if emulated.is_empty() || aws.is_empty() {
+            return None;
+        }
+        let names = |services: Vec<(&str, &Option<String>)>| {
+            services
+                .iter()
+                .map(|(service, _)| *service)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        Some(format!(
+            "{} would go to an emulator but {} to AWS; point all of them at the same place, \
+             e.g. with LOCALSTACK_ENDPOINT",
+            names(emulated),
+            names(aws)
+        ))
+    }
+}
+
+/// Services holding tenant data, which must be all on AWS or all on an emulator
+const STORAGE_SERVICES: [&str; 3] = ["DynamoDB", "S3", "EventBridge"];
+
+/// The SDK config every client is built from: the environment's AWS config with the
+/// configured region as the fallback region, and dummy credentials when endpoints are
+/// overridden and none are configured
+async fn load_sdk_config(config: &AwsConfig) -> aws_config::SdkConfig {
+    let mut loader = aws_config::from_env().region(
+        aws_config::meta::region::RegionProviderChain::default_provider()
+            .or_else(aws_config::Region::new(config.region.clone())),
+    );
+    let has_credentials =
+        std::env::var("AWS_ACCESS_KEY_ID").is_ok() || std::env::var("AWS_PROFILE").is_ok();
+    if config.endpoints.any_overridden() && !has_credentials {
+        loader = loader.credentials_provider(aws_sdk_dynamodb::config::Credentials::new(
+            "test",
+            "test",
+            None,
+            None,
+            "localstack",
+        ));
+    }
+    loader.load().await
 }
 
 impl AwsClients {
-    pub async fn new(_region: &str) -> Result<Self, AwsError> {
-        let config = aws_config::load_from_env().await;
+    /// Clients built from `sdk_config`. Endpoint overrides (see
+    /// [`ServiceEndpoints::resolve`]) point clients at LocalStack or another emulator; S3
+    /// then uses path-style addressing.
+    pub fn new(sdk_config: &aws_config::SdkConfig, config: &AwsConfig) -> Self {
+        let endpoints = &config.endpoints;
 
-        Ok(Self {
-            dynamodb: DynamoDbClient::new(&config),
-            s3: S3Client::new(&config),
-            eventbridge: EventBridgeClient::new(&config),
-            secrets_manager: SecretsManagerClient::new(&config),
-        })
+        let mut dynamodb = aws_sdk_dynamodb::config::Builder::from(sdk_config);
+        dynamodb.set_endpoint_url(endpoints.dynamodb.clone());
+        let mut s3 =
+            aws_sdk_s3::config::Builder::from(sdk_config).force_path_style(endpoints.s3.is_some());
+        s3.set_endpoint_url(endpoints.s3.clone());
+        let mut eventbridge = aws_sdk_eventbridge::config::Builder::from(sdk_config);
+        eventbridge.set_endpoint_url(endpoints.eventbridge.clone());
+        let mut secrets_manager = aws_sdk_secretsmanager::config::Builder::from(sdk_config);
+        secrets_manager.set_endpoint_url(endpoints.secrets_manager.clone());
+        let mut kms = aws_sdk_kms::config::Builder::from(sdk_config);
+        kms.set_endpoint_url(endpoints.kms.clone());
+        let mut lambda = aws_sdk_lambda::config::Builder::from(sdk_config);
+        lambda.set_endpoint_url(endpoints.lambda.clone());
+        let cloudwatch = config.cloudwatch_namespace.is_some().then(|| {
+            let mut cloudwatch = aws_sdk_cloudwatch::config::Builder::from(sdk_config);
+            cloudwatch.set_endpoint_url(endpoints.cloudwatch.clone());
+            CloudWatchClient::from_conf(cloudwatch.build())
+        });
+        let mut sfn = aws_sdk_sfn::config::Builder::from(sdk_config);
+        sfn.set_endpoint_url(endpoints.sfn.clone());
+        let mut sqs = aws_sdk_sqs::config::Builder::from(sdk_config);
+        sqs.set_endpoint_url(endpoints.sqs.clone());
+        let mut sns = aws_sdk_sns::config::Builder::from(sdk_config);
+        sns.set_endpoint_url(endpoints.sns.clone());
+
+        Self {
+            dynamodb: DynamoDbClient::from_conf(dynamodb.build()),
+            s3: S3Client::from_conf(s3.build()),
+            eventbridge: EventBridgeClient::from_conf(eventbridge.build()),
+            secrets_manager: SecretsManagerClient::from_conf(secrets_manager.build()),
+            kms: KmsClient::from_conf(kms.build()),
+            lambda: LambdaClient::from_conf(lambda.build()),
+            cloudwatch,
+            sfn: SfnClient::from_conf(sfn.build()),
+            sqs: SqsClient::from_conf(sqs.build()),
+            sns: SnsClient::from_conf(sns.build()),
+        }
+    }
+}
+
+/// An events table item as JSON; numbers that aren't JSON numbers are kept as strings
+fn event_item_json(item: &HashMap<String, aws_sdk_dynamodb::types::AttributeValue>) -> Value {
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    let mut event = serde_json::Map::new();
+    for (key, value) in item {
+        let json_value = match value {
+            AttributeValue::S(s) => Value::String(s.clone()),
+            AttributeValue::N(n) => {
+                if let Ok(num) = n.parse::<i64>() {
+                    Value::Number(num.into())
+                } else if let Ok(num) = n.parse::<f64>() {
+                    Value::Number(serde_json::Number::from_f64(num).unwrap_or(0.into()))
+                } else {
+                    Value::String(n.clone())
+                }
+            }
+            AttributeValue::Bool(b) => Value::Bool(*b),
+            _ => Value::String(format!("{:?}", value)),
+        };
+        event.insert(key.clone(), json_value);
+    }
+    Value::Object(event)
+}
+
+/// Event detail with the session's `tenant_id`, `user_id` and `namespace` added. The
+/// event store copies `namespace` onto the event record, where it scopes `events_query`.
+fn tenant_event_detail(session: &TenantSession, detail: Value) -> Value {
+    let mut detail = detail;
+    if let Value::Object(ref mut map) = detail {
+        map.insert(
+            "tenant_id".to_string(),
+            Value::String(session.context.tenant_id.clone()),
+        );
+        map.insert(
+            "user_id".to_string(),
+            Value::String(session.context.user_id.clone()),
+        );
+        map.insert(
+            "namespace".to_string(),
+            Value::String(session.context.get_namespace_prefix()),
+        );
     }
+    detail
 }
 
 pub struct AwsService {
     clients: Arc<AwsClients>,
+    /// Clients for tenants keeping their data in their own AWS account
+    tenant_clients: TenantClients,
     kv_table: String,
+    events_table: String,
+    event_rules_table: String,
+    subscriptions_table: String,
     artifacts_bucket: String,
     event_bus: String,
+    queue_prefix: String,
+    /// Check that alert topics exist before subscribing to them
+    verify_alert_topics: bool,
+    encryptor: Option<Arc<EnvelopeEncryptor>>,
+    /// What the startup check found, when `MCP_VALIDATE_RESOURCES` is set
+    resource_report: Option<ResourceReport>,
+    /// Queue URLs by queue name, as resolved by `GetQueueUrl`
+    queue_urls: Mutex<HashMap<String, String>>,
+    /// Write-behind buffer for `send_event_buffered`
+    event_buffer: Arc<EventBuffer>,
+    /// Whether events are buffered unless a call says otherwise (`AGENT_MESH_BUFFER_EVENTS`)
+    buffer_events: bool,
+    /// Read cache in front of `kv_get`, when `AGENT_MESH_KV_CACHE_SIZE` is set
+    kv_cache: Option<KvCache>,
+    /// How long each service's calls take
+    latency: Arc<AwsLatency>,
 }
 
 impl AwsService {
+    /// The service [`Config::load`] describes, with `region` as the fallback region
+    #[allow(dead_code)]
     pub async fn new(region: &str) -> Result<Self, AwsError> {
-        let clients = Arc::new(AwsClients::new(region).await?);
+        let mut config = Config::load()
+            .map_err(|e| AwsError::Config(e.to_string()))?
+            .aws;
+        config.region = region.to_string();
+        Self::from_config(&config).await
+    }
 
-        let kv_table =
-            std::env::var("AGENT_MESH_KV_TABLE").unwrap_or_else(|_| "agent-mesh-kv".to_string());
-        let artifacts_bucket = std::env::var("AGENT_MESH_ARTIFACTS_BUCKET")
-            .unwrap_or_else(|_| "agent-mesh-artifacts".to_string());
-        let event_bus = std::env::var("AGENT_MESH_EVENT_BUS")
-            .unwrap_or_else(|_| "agent-mesh-events".to_string());
+    /// With `validate_resources` the configured tables, bucket and event bus are checked
+    /// first; unusable ones are logged, or fail the call when `validate_resources_strict`
+    /// is also set.
+    pub async fn from_config(config: &AwsConfig) -> Result<Self, AwsError> {
+        let names = config.resource_names.clone();
 
         eprintln!("[MCP Server] AWS Configuration:");
-        eprintln!("[MCP Server]   KV Table: {}", kv_table);
-        eprintln!("[MCP Server]   Artifacts Bucket: {}", artifacts_bucket);
-        eprintln!("[MCP Server]   Event Bus: {}", event_bus);
+        eprintln!("[MCP Server]   KV Table: {}", names.kv_table);
+        eprintln!("[MCP Server]   Events Table: {}", names.events_table);
+        eprintln!(
+            "[MCP Server]   Event Rules Table: {}",
+            names.event_rules_table
+        );
+        eprintln!(
+            "[MCP Server]   Subscriptions Table: {}",
+            names.subscriptions_table
+        );
+        eprintln!(
+            "[MCP Server]   Dead-letter Table: {}",
+            names.dead_letter_table
+        );
+        eprintln!(
+            "[MCP Server]   Artifacts Bucket: {}",
+            names.artifacts_bucket
+        );
+        eprintln!("[MCP Server]   Event Bus: {}", names.event_bus);
+
+        let sdk_config = load_sdk_config(config).await;
+        for (service, endpoint) in config.endpoints.all() {
+            eprintln!(
+                "[MCP Server]   {} endpoint: {}",
+                service,
+                endpoint.as_deref().unwrap_or("AWS")
+            );
+        }
+        let clients = Arc::new(AwsClients::new(&sdk_config, config));
+        let tenant_clients = TenantClients::new(
+            sdk_config.clone(),
+            config,
+            clients.clone(),
+            Arc::new(StsRoleAssumer::new(aws_sdk_sts::Client::new(&sdk_config))),
+        );
+
+        let resource_report = if config.validate_resources {
+            let prober = AwsResourceProber::new(clients.clone());
+            let report = resources::validate_resources(&prober, &names).await;
+            report.enforce(config.validate_resources_strict)?;
+            Some(report)
+        } else {
+            None
+        };
+
+        // Envelope encryption for sensitive KV values is enabled when a KMS key is configured
+        let encryptor = config.kms_key_id.clone().map(|key_id| {
+            eprintln!("[MCP Server]   KMS Key: {}", key_id);
+            Arc::new(EnvelopeEncryptor::new(Arc::new(KmsDataKeyProvider::new(
+                clients.kms.clone(),
+                key_id,
+            ))))
+        });
+
+        let latency = Arc::new(AwsLatency::new());
+        let event_buffer = EventBuffer::spawn(
+            Arc::new(EventBridgeSink::new(
+                clients.eventbridge.clone(),
+                &names.event_bus,
+                latency.clone(),
+            )),
+            Arc::new(KvDeadLetters::new(
+                clients.dynamodb.clone(),
+                &names.dead_letter_table,
+                latency.clone(),
+            )),
+        );
+        let buffer_events = config.buffer_events;
 
         Ok(Self {
             clients,
-            kv_table,
-            artifacts_bucket,
-            event_bus,
+            tenant_clients,
+            kv_table: names.kv_table,
+            events_table: names.events_table,
+            event_rules_table: names.event_rules_table,
+            subscriptions_table: names.subscriptions_table,
+            artifacts_bucket: names.artifacts_bucket,
+            event_bus: names.event_bus,
+            queue_prefix: names.queue_prefix,
+            verify_alert_topics: config.verify_alert_topics,
+            encryptor,
+            resource_report,
+            queue_urls: Mutex::default(),
+            event_buffer,
+            buffer_events,
+            kv_cache: config.kv_cache(),
+            latency,
         })
     }
 
-    // KV Store operations
-    pub async fn kv_get(
-        &self,
-        session: &TenantSession,
-        key: &str,
-    ) -> Result<Option<String>, AwsError> {
-        // Use context-aware namespacing
-        let tenant_key = format!("{}:{}", session.context.get_namespace_prefix(), key);
+    /// Bucket holding the tenants' artifacts
+    pub fn artifacts_bucket(&self) -> &str {
+        &self.artifacts_bucket
+    }
 
-        let result = self
-            .clients
-            .dynamodb
-            .get_item()
-            .table_name(&self.kv_table)
-            .key(
-                "key",
-                aws_sdk_dynamodb::types::AttributeValue::S(tenant_key),
+    /// Replace the envelope encryptor (e.g. with a static-key provider for local dev)
+    #[allow(dead_code)]
+    pub fn with_encryptor(mut self, encryptor: Arc<EnvelopeEncryptor>) -> Self {
+        self.encryptor = Some(encryptor);
+        self
+    }
+
+    /// Lambda client for the default region; callers override the region per function
+    pub fn lambda_client(&self) -> LambdaClient {
+        self.clients.lambda.clone()
+    }
+
+    /// A call to `operation` of `service` on `resource`, traced and timed
+    fn call(&self, service: &'static str, operation: &'static str, resource: &str) -> AwsCall<'_> {
+        AwsCall::new(&self.latency, service, operation, resource)
+    }
+
+    /// Latency percentiles of the calls made so far, by service
+    pub fn latency_stats(&self) -> BTreeMap<String, LatencyStats> {
+        self.latency.stats()
+    }
+
+    /// Cheapest round trip showing DynamoDB is reachable: describe the KV table
+    pub async fn ping(&self) -> Result<(), AwsError> {
+        self.call("dynamodb", "DescribeTable", &self.kv_table)
+            .run(
+                self.clients
+                    .dynamodb
+                    .describe_table()
+                    .table_name(&self.kv_table)
+                    .send(),
             )
-            .send()
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
+            .map_err(|e| sdk_error(e, "dynamodb_read", AwsError::DynamoDb))?;
+        Ok(())
+    }
 
-        if let Some(item) = result.item {
-            if let Some(value) = item.get("value") {
-                if let Ok(s_val) = value.as_s() {
-                    return Ok(Some(s_val.clone()));
-                }
-            }
-        }
+    fn require_encryptor(&self) -> Result<&EnvelopeEncryptor, AwsError> {
+        self.encryptor.as_deref().ok_or_else(|| {
+            AwsError::Config(
+                "Sensitive values require AGENT_MESH_KMS_KEY_ID to be configured".to_string(),
+            )
+        })
+    }
 
-        Ok(None)
+    /// The clients the session's tenant's KV items, artifacts and events are reached
+    /// with: the server's own, or its role's in the tenant's AWS account
+    async fn clients_for(&self, session: &TenantSession) -> Result<Arc<AwsClients>, AwsError> {
+        self.tenant_clients.for_context(&session.context).await
     }
 
-    pub async fn kv_set(
+    /// Write a KV item with `clients`, sealing the value for `owner_tenant` when one is
+    /// given
+    async fn put_kv_item(
         &self,
-        session: &TenantSession,
-        key: &str,
+        clients: &AwsClients,
+        key: String,
         value: &str,
         ttl_hours: Option<u32>,
+        owner_tenant: Option<&str>,
     ) -> Result<(), AwsError> {
-        // Use context-aware namespacing
-        let tenant_key = format!("{}:{}", session.context.get_namespace_prefix(), key);
+        use aws_sdk_dynamodb::primitives::Blob;
+        use aws_sdk_dynamodb::types::AttributeValue;
+
         let now = chrono::Utc::now().timestamp();
+        let call = self
+            .call("dynamodb", "PutItem", &self.kv_table)
+            .key(&key)
+            .size(value.len());
 
-        // Prepare DynamoDB item
-        let mut put_request = self
-            .clients
+        let mut put_request = clients
             .dynamodb
             .put_item()
             .table_name(&self.kv_table)
-            .item(
-                "key",
-                aws_sdk_dynamodb::types::AttributeValue::S(tenant_key),
-            )
-            .item(
-                "value",
-                aws_sdk_dynamodb::types::AttributeValue::S(value.to_string()),
-            )
-            .item(
-                "created_at",
-                aws_sdk_dynamodb::types::AttributeValue::N(now.to_string()),
-            );
+            .item("key", AttributeValue::S(key))
+            .item("created_at", AttributeValue::N(now.to_string()));
+
+        put_request = match owner_tenant {
+            Some(tenant_id) => {
+                let sealed = self
+                    .require_encryptor()?
+                    .seal(tenant_id, value.as_bytes())
+                    .await?;
+                put_request
+                    .item(
+                        "value",
+                        AttributeValue::S(general_purpose::STANDARD.encode(&sealed.ciphertext)),
+                    )
+                    .item("nonce", AttributeValue::B(Blob::new(sealed.nonce)))
+                    .item(
+                        "wrapped_key",
+                        AttributeValue::B(Blob::new(sealed.wrapped_key)),
+                    )
+                    .item("encrypted_for", AttributeValue::S(tenant_id.to_string()))
+            }
+            None => put_request.item("value", AttributeValue::S(value.to_string())),
+        };
 
         if let Some(ttl) = ttl_hours {
             let expiry = now + (ttl as i64 * 3600);
-            put_request = put_request.item(
-                "expires_at",
-                aws_sdk_dynamodb::types::AttributeValue::N(expiry.to_string()),
-            );
+            put_request = put_request.item("expires_at", AttributeValue::N(expiry.to_string()));
         }
 
-        put_request
-            .send()
+        call.run(put_request.send())
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb))?;
         Ok(())
     }
 
-    // Artifacts operations
-    pub async fn artifacts_put(
+    /// Extract the value from a KV item, decrypting it if it was stored as sensitive.
+    /// `expected_tenant` is the tenant the caller acts for; direct (internal) reads pass
+    /// None and decrypt under the owner recorded on the item.
+    async fn read_kv_value(
         &self,
-        session: &TenantSession,
-        key: &str,
-        content: &[u8],
-        content_type: &str,
-    ) -> Result<(), AwsError> {
-        let tenant_key = format!("{}/{}", session.context.get_context_id(), key);
-
-        self.clients
-            .s3
-            .put_object()
-            .bucket(&self.artifacts_bucket)
-            .key(tenant_key)
-            .body(aws_sdk_s3::primitives::ByteStream::from(content.to_vec()))
-            .content_type(content_type)
-            .send()
-            .await
-            .map_err(|e| AwsError::S3(e.to_string()))?;
+        item: HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+        expected_tenant: Option<&str>,
+    ) -> Result<Option<String>, AwsError> {
+        let value = match item.get("value").and_then(|v| v.as_s().ok()) {
+            Some(value) => value.clone(),
+            None => return Ok(None),
+        };
 
-        Ok(())
+        let wrapped_key = match item.get("wrapped_key").and_then(|v| v.as_b().ok()) {
+            Some(wrapped_key) => wrapped_key.as_ref().to_vec(),
+            None => return Ok(Some(value)),
+        };
+
+        let owner = item
+            .get("encrypted_for")
+            .and_then(|v| v.as_s().ok())
+            .cloned()
+            .unwrap_or_default();
+        let tenant_id = expected_tenant.unwrap_or(&owner);
+
+        let sealed = SealedValue {
+            ciphertext: general_purpose::STANDARD
+                .decode(&value)
+                .map_err(|e| AwsError::Encryption(format!("Corrupt ciphertext: {}", e)))?,
+            nonce: item
+                .get("nonce")
+                .and_then(|v| v.as_b().ok())
+                .map(|b| b.as_ref().to_vec())
+                .unwrap_or_default(),
+            wrapped_key,
+        };
+
+        let plaintext = self.require_encryptor()?.open(tenant_id, &sealed).await?;
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|e| AwsError::Encryption(format!("Decrypted value is not UTF-8: {}", e)))
     }
 
-    pub async fn artifacts_get(
+    // KV Store operations
+    pub async fn kv_get(
         &self,
         session: &TenantSession,
         key: &str,
-    ) -> Result<Option<Vec<u8>>, AwsError> {
-        let tenant_key = format!("{}/{}", session.context.get_context_id(), key);
-
-        match self
-            .clients
-            .s3
-            .get_object()
-            .bucket(&self.artifacts_bucket)
-            .key(tenant_key)
-            .send()
-            .await
+    ) -> Result<Option<String>, AwsError> {
+        // Use context-aware namespacing
+        let tenant_key = format!("{}:{}", session.context.get_namespace_prefix(), key);
+        if let Some(cached) = self
+            .kv_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&tenant_key))
         {
-            Ok(result) => {
-                let body = result
-                    .body
-                    .collect()
-                    .await
-                    .map_err(|e| AwsError::Config(e.to_string()))?;
-                Ok(Some(body.into_bytes().to_vec()))
-            }
-            Err(e) if e.to_string().contains("NoSuchKey") => Ok(None),
-            Err(e) => Err(AwsError::S3(e.to_string())),
+            return Ok(cached);
         }
+        self.kv_read(session, tenant_key).await
     }
 
-    pub async fn artifacts_list(
+    /// `kv_get` straight from the table, refreshing the cached value
+    pub async fn kv_get_fresh(
         &self,
         session: &TenantSession,
-        prefix: Option<&str>,
-    ) -> Result<Vec<String>, AwsError> {
-        let tenant_prefix = match prefix {
-            Some(p) => format!("{}/{}", session.context.get_context_id(), p),
-            None => format!("{}/", session.context.get_context_id()),
-        };
+        key: &str,
+    ) -> Result<Option<String>, AwsError> {
+        let tenant_key = format!("{}:{}", session.context.get_namespace_prefix(), key);
+        self.kv_read(session, tenant_key).await
+    }
 
+    /// Read a namespaced item and cache its value. Sensitive values are never cached,
+    /// so their plaintext isn't kept in memory.
+    async fn kv_read(
+        &self,
+        session: &TenantSession,
+        tenant_key: String,
+    ) -> Result<Option<String>, AwsError> {
+        let clients = self.clients_for(session).await?;
+        let ticket = self.kv_cache.as_ref().map(KvCache::ticket);
+        let call = self
+            .call("dynamodb", "GetItem", &self.kv_table)
+            .key(&tenant_key);
+        let result = call
+            .run(
+                clients
+                    .dynamodb
+                    .get_item()
+                    .table_name(&self.kv_table)
+                    .key(
+                        "key",
+                        aws_sdk_dynamodb::types::AttributeValue::S(tenant_key.clone()),
+                    )
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "dynamodb_read", AwsError::DynamoDb))?;
+        call.record_size(stored_value_size(result.item.as_ref()));
+
+        let sensitive = result
+            .item
+            .as_ref()
+            .is_some_and(|item| item.contains_key("wrapped_key"));
+        let value = match result.item {
+            Some(item) => {
+                self.read_kv_value(item, Some(&session.context.tenant_id))
+                    .await?
+            }
+            None => None,
+        };
+        if let (Some(cache), Some(ticket)) = (&self.kv_cache, ticket) {
+            if !sensitive {
+                cache.insert(&tenant_key, value.clone(), ticket);
+            }
+        }
+        Ok(value)
+    }
+
+    /// Hit and miss counts of the `kv_get` cache, when it's enabled
+    pub fn kv_cache_stats(&self) -> Option<KvCacheStats> {
+        self.kv_cache.as_ref().map(KvCache::stats)
+    }
+
+    /// Set a value in the tenant's namespace. Sensitive values are envelope-encrypted
+    /// with the tenant's data key before they reach DynamoDB.
+    pub async fn kv_set(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+        sensitive: bool,
+    ) -> Result<(), AwsError> {
+        // Use context-aware namespacing
+        let tenant_key = format!("{}:{}", session.context.get_namespace_prefix(), key);
+        let owner = sensitive.then_some(session.context.tenant_id.as_str());
+
+        let clients = self.clients_for(session).await?;
         let result = self
-            .clients
-            .s3
-            .list_objects_v2()
-            .bucket(&self.artifacts_bucket)
-            .prefix(tenant_prefix)
-            .send()
+            .put_kv_item(&clients, tenant_key.clone(), value, ttl_hours, owner)
+            .await;
+        if let Some(cache) = &self.kv_cache {
+            cache.invalidate(&tenant_key);
+        }
+        result
+    }
+
+    // Artifacts operations
+
+    /// Store an artifact, tagged with its TTL class when it has one
+    pub async fn artifacts_put(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        content: &[u8],
+        content_type: &str,
+        ttl_class: Option<TtlClass>,
+    ) -> Result<(), AwsError> {
+        let object_key = namespaces::artifact_key(session, key);
+        let clients = self.clients_for(session).await?;
+        self.call("s3", "PutObject", &self.artifacts_bucket)
+            .key(&object_key)
+            .size(content.len())
+            .run(
+                clients
+                    .s3
+                    .put_object()
+                    .bucket(&self.artifacts_bucket)
+                    .key(&object_key)
+                    .body(aws_sdk_s3::primitives::ByteStream::from(content.to_vec()))
+                    .content_type(content_type)
+                    .set_tagging(ttl_class.map(|class| class.tagging()))
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "s3_put", AwsError::S3))?;
+
+        Ok(())
+    }
+
+    /// Artifacts not yet migrated are read from their legacy key
+    pub async fn artifacts_get(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, AwsError> {
+        let tenant_key = namespaces::artifact_key(session, key);
+        let clients = self.clients_for(session).await?;
+        match self
+            .s3_get_with(&clients, &self.artifacts_bucket, &tenant_key)
+            .await?
+        {
+            Some(content) => Ok(Some(content)),
+            None => {
+                let legacy_key = namespaces::legacy_artifact_key(session, key);
+                self.s3_get_with(&clients, &self.artifacts_bucket, &legacy_key)
+                    .await
+            }
+        }
+    }
+
+    /// An artifact's size, content type, modification time and TTL class, without its
+    /// content; artifacts not yet migrated are read from their legacy key
+    pub async fn artifacts_head(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<ArtifactMetadata>, AwsError> {
+        let tenant_key = namespaces::artifact_key(session, key);
+        let clients = self.clients_for(session).await?;
+        match self.artifact_metadata(&clients, &tenant_key).await? {
+            Some(metadata) => Ok(Some(metadata)),
+            None => {
+                let legacy_key = namespaces::legacy_artifact_key(session, key);
+                self.artifact_metadata(&clients, &legacy_key).await
+            }
+        }
+    }
+
+    async fn artifact_metadata(
+        &self,
+        clients: &AwsClients,
+        key: &str,
+    ) -> Result<Option<ArtifactMetadata>, AwsError> {
+        let head = self
+            .call("s3", "HeadObject", &self.artifacts_bucket)
+            .key(key)
+            .run(
+                clients
+                    .s3
+                    .head_object()
+                    .bucket(&self.artifacts_bucket)
+                    .key(key)
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "s3_get", AwsError::S3));
+        let head = match head {
+            Ok(head) => head,
+            Err(AwsError::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let tagging = self
+            .call("s3", "GetObjectTagging", &self.artifacts_bucket)
+            .key(key)
+            .run(
+                clients
+                    .s3
+                    .get_object_tagging()
+                    .bucket(&self.artifacts_bucket)
+                    .key(key)
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "s3_get", AwsError::S3))?;
+        let ttl_class = tagging
+            .tag_set()
+            .iter()
+            .find(|tag| tag.key() == artifacts::TTL_CLASS_TAG)
+            .and_then(|tag| TtlClass::parse(tag.value()));
+        Ok(Some(ArtifactMetadata {
+            size: head.content_length().unwrap_or_default().max(0) as u64,
+            content_type: head.content_type().map(str::to_string),
+            last_modified: head
+                .last_modified()
+                .and_then(|date| chrono::DateTime::from_timestamp(date.secs(), date.subsec_nanos()))
+                .map(|date| date.to_rfc3339()),
+            ttl_class,
+        }))
+    }
+
+    /// Install the rules expiring the session's tenant's artifacts by TTL class,
+    /// replacing the tenant's earlier rules and keeping the bucket's other rules
+    pub async fn configure_artifact_lifecycle(
+        &self,
+        session: &TenantSession,
+        retention: &RetentionDays,
+    ) -> Result<Vec<ArtifactLifecycleRule>, AwsError> {
+        use aws_sdk_s3::types::BucketLifecycleConfiguration;
+
+        let bucket = &self.artifacts_bucket;
+        let access_denied = |operation: &str| {
+            AwsError::AccessDenied(format!(
+                "the server may not {} lifecycle rules on bucket {}; grant it \
+                 s3:GetLifecycleConfiguration and s3:PutLifecycleConfiguration",
+                operation, bucket
+            ))
+        };
+        let rules = artifacts::tenant_lifecycle_rules(
+            &artifacts::tenant_artifact_prefix(session),
+            retention,
+        );
+        let clients = self.clients_for(session).await?;
+
+        let existing = match self
+            .call("s3", "GetBucketLifecycleConfiguration", bucket)
+            .run(
+                clients
+                    .s3
+                    .get_bucket_lifecycle_configuration()
+                    .bucket(bucket)
+                    .send(),
+            )
             .await
+        {
+            Ok(output) => output.rules().to_vec(),
+            Err(e) if e.code() == Some("NoSuchLifecycleConfiguration") => Vec::new(),
+            Err(e) if e.code() == Some("AccessDenied") => return Err(access_denied("read")),
+            Err(e) => return Err(sdk_error(e, "s3_get", AwsError::S3)),
+        };
+        let s3_rules = rules
+            .iter()
+            .map(ArtifactLifecycleRule::to_s3)
+            .collect::<Result<Vec<_>, _>>()?;
+        let configuration = BucketLifecycleConfiguration::builder()
+            .set_rules(Some(artifacts::merge_lifecycle_rules(&existing, s3_rules)))
+            .build()
             .map_err(|e| AwsError::S3(e.to_string()))?;
 
+        match self
+            .call("s3", "PutBucketLifecycleConfiguration", bucket)
+            .run(
+                clients
+                    .s3
+                    .put_bucket_lifecycle_configuration()
+                    .bucket(bucket)
+                    .lifecycle_configuration(configuration)
+                    .send(),
+            )
+            .await
+        {
+            Ok(_) => Ok(rules),
+            Err(e) if e.code() == Some("AccessDenied") => Err(access_denied("update")),
+            Err(e) => Err(sdk_error(e, "s3_put", AwsError::S3)),
+        }
+    }
+
+    /// Read an object outside the tenant namespaces, e.g. server-wide configuration
+    pub async fn s3_get(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, AwsError> {
+        self.s3_get_with(&self.clients, bucket, key).await
+    }
+
+    async fn s3_get_with(
+        &self,
+        clients: &AwsClients,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, AwsError> {
+        let call = self.call("s3", "GetObject", bucket).key(key);
+        match call
+            .run(clients.s3.get_object().bucket(bucket).key(key).send())
+            .await
+        {
+            Ok(result) => {
+                let body = result
+                    .body
+                    .collect()
+                    .await
+                    .map_err(|e| AwsError::Config(e.to_string()))?;
+                let content = body.into_bytes().to_vec();
+                call.record_size(content.len());
+                Ok(Some(content))
+            }
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(GetObjectError::is_no_such_key) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(sdk_error(e, "s3_get", AwsError::S3)),
+        }
+    }
+
+    /// Keys relative to the namespace, including artifacts not yet migrated
+    pub async fn artifacts_list(
+        &self,
+        session: &TenantSession,
+        prefix: Option<&str>,
+    ) -> Result<Vec<String>, AwsError> {
+        let prefix = prefix.unwrap_or_default();
+        let clients = self.clients_for(session).await?;
+        let mut keys = self
+            .list_artifact_keys(&clients, &namespaces::artifact_key(session, ""), prefix)
+            .await?;
+        keys.extend(
+            self.list_artifact_keys(
+                &clients,
+                &namespaces::legacy_artifact_key(session, ""),
+                prefix,
+            )
+            .await?,
+        );
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    /// A page of up to `limit` of the session's artifact keys, relative to the namespace,
+    /// and the position the next page starts from (`None` after the last). The namespace
+    /// is listed first, then the artifacts not yet migrated from the legacy prefix, less
+    /// those the namespace also has; a position holds the S3 continuation token of the
+    /// listing it's in (see [`ArtifactPosition`]).
+    pub async fn artifacts_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError> {
+        let position = start.map(ArtifactPosition::parse).transpose()?;
+        let legacy = position.as_ref().is_some_and(|position| position.legacy);
+        let continuation_token = position.and_then(|position| position.token);
+        let root = if legacy {
+            namespaces::legacy_artifact_key(session, "")
+        } else {
+            namespaces::artifact_key(session, "")
+        };
+        let clients = self.clients_for(session).await?;
+        let result = self
+            .call("s3", "ListObjectsV2", &self.artifacts_bucket)
+            .run(
+                clients
+                    .s3
+                    .list_objects_v2()
+                    .bucket(&self.artifacts_bucket)
+                    .prefix(&root)
+                    .max_keys(limit.clamp(1, 1000) as i32)
+                    .set_continuation_token(continuation_token)
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "s3_list", AwsError::S3))?;
+
         let mut keys = Vec::new();
-        if let Some(contents) = result.contents {
-            for object in contents {
-                if let Some(key) = object.key {
-                    // Remove tenant prefix from key
-                    if let Some(relative_key) =
-                        key.strip_prefix(&format!("{}/", session.context.tenant_id))
-                    {
-                        keys.push(relative_key.to_string());
-                    }
+        for object in result.contents.unwrap_or_default() {
+            let Some(key) = object.key.as_deref().and_then(|k| k.strip_prefix(&root)) else {
+                continue;
+            };
+            if legacy
+                && self
+                    .artifact_exists(&clients, &namespaces::artifact_key(session, key))
+                    .await?
+            {
+                continue;
+            }
+            keys.push(key.to_string());
+        }
+        let next = match result.next_continuation_token {
+            Some(token) => Some(ArtifactPosition {
+                legacy,
+                token: Some(token),
+            }),
+            None if !legacy => Some(ArtifactPosition {
+                legacy: true,
+                token: None,
+            }),
+            None => None,
+        };
+        Ok((keys, next.map(|next| next.to_string())))
+    }
+
+    async fn artifact_exists(&self, clients: &AwsClients, key: &str) -> Result<bool, AwsError> {
+        let head = self
+            .call("s3", "HeadObject", &self.artifacts_bucket)
+            .key(key)
+            .run(
+                clients
+                    .s3
+                    .head_object()
+                    .bucket(&self.artifacts_bucket)
+                    .key(key)
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "s3_get", AwsError::S3));
+        match head {
+            Ok(_) => Ok(true),
+            Err(AwsError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Keys under `root` (ending in `/`) that start with `prefix`, relative to `root`,
+    /// followed page by page to the end
+    async fn list_artifact_keys(
+        &self,
+        clients: &AwsClients,
+        root: &str,
+        prefix: &str,
+    ) -> Result<Vec<String>, AwsError> {
+        Ok(self
+            .list_artifact_objects(clients, root, prefix)
+            .await?
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect())
+    }
+
+    /// Like [`Self::list_artifact_keys`], with each object's size in bytes
+    async fn list_artifact_objects(
+        &self,
+        clients: &AwsClients,
+        root: &str,
+        prefix: &str,
+    ) -> Result<Vec<(String, u64)>, AwsError> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let result = self
+                .call("s3", "ListObjectsV2", &self.artifacts_bucket)
+                .run(
+                    clients
+                        .s3
+                        .list_objects_v2()
+                        .bucket(&self.artifacts_bucket)
+                        .prefix(format!("{}{}", root, prefix))
+                        .set_continuation_token(continuation_token)
+                        .send(),
+                )
+                .await
+                .map_err(|e| sdk_error(e, "s3_list", AwsError::S3))?;
+
+            for object in result.contents.unwrap_or_default() {
+                if let Some(relative_key) = object.key.as_deref().and_then(|k| k.strip_prefix(root))
+                {
+                    let size = object.size.unwrap_or_default().max(0) as u64;
+                    objects.push((relative_key.to_string(), size));
+                }
+            }
+            continuation_token = result.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Delete the session's artifacts whose keys start with `prefix`, in its namespace
+    /// and under the legacy prefix; with `dry_run`, only report what would be deleted
+    pub async fn artifacts_delete_prefix(
+        &self,
+        session: &TenantSession,
+        prefix: &str,
+        dry_run: bool,
+    ) -> Result<ArtifactDeletion, AwsError> {
+        let clients = self.clients_for(session).await?;
+        let mut deletion = ArtifactDeletion::default();
+        for root in [
+            namespaces::artifact_key(session, ""),
+            namespaces::legacy_artifact_key(session, ""),
+        ] {
+            for (key, size) in self.list_artifact_objects(&clients, &root, prefix).await? {
+                if !dry_run {
+                    let object_key = format!("{}{}", root, key);
+                    self.call("s3", "DeleteObject", &self.artifacts_bucket)
+                        .key(&object_key)
+                        .run(
+                            clients
+                                .s3
+                                .delete_object()
+                                .bucket(&self.artifacts_bucket)
+                                .key(&object_key)
+                                .send(),
+                        )
+                        .await
+                        .map_err(|e| sdk_error(e, "s3_put", AwsError::S3))?;
                 }
+                deletion.bytes += size;
+                deletion.keys.push(key);
             }
         }
+        deletion.keys.sort();
+        Ok(deletion)
+    }
 
-        Ok(keys)
+    /// Move the session's artifacts from their legacy `{context_id}/` keys under its
+    /// namespace. Keys the namespace already has are left where they are.
+    pub async fn migrate_artifacts(
+        &self,
+        session: &TenantSession,
+    ) -> Result<ArtifactMigration, AwsError> {
+        let namespace_root = namespaces::artifact_key(session, "");
+        let legacy_root = namespaces::legacy_artifact_key(session, "");
+        let clients = self.clients_for(session).await?;
+        let existing = self
+            .list_artifact_keys(&clients, &namespace_root, "")
+            .await?;
+        let mut migration = ArtifactMigration::default();
+        for key in self.list_artifact_keys(&clients, &legacy_root, "").await? {
+            if existing.contains(&key) {
+                migration.skipped.push(key);
+                continue;
+            }
+            let legacy_key = format!("{}{}", legacy_root, key);
+            let namespace_key = format!("{}{}", namespace_root, key);
+            self.call("s3", "CopyObject", &self.artifacts_bucket)
+                .key(&namespace_key)
+                .run(
+                    clients
+                        .s3
+                        .copy_object()
+                        .bucket(&self.artifacts_bucket)
+                        .copy_source(namespaces::copy_source(&self.artifacts_bucket, &legacy_key))
+                        .key(&namespace_key)
+                        .send(),
+                )
+                .await
+                .map_err(|e| sdk_error(e, "s3_put", AwsError::S3))?;
+            self.call("s3", "DeleteObject", &self.artifacts_bucket)
+                .key(&legacy_key)
+                .run(
+                    clients
+                        .s3
+                        .delete_object()
+                        .bucket(&self.artifacts_bucket)
+                        .key(&legacy_key)
+                        .send(),
+                )
+                .await
+                .map_err(|e| sdk_error(e, "s3_put", AwsError::S3))?;
+            migration.moved.push(key);
+        }
+        Ok(migration)
     }
 
     // Event operations
@@ -252,37 +1565,150 @@ impl AwsService {
         detail_type: &str,
         detail: Value,
     ) -> Result<(), AwsError> {
-        let mut event_detail = detail;
-        if let Value::Object(ref mut map) = event_detail {
-            map.insert(
-                "tenant_id".to_string(),
-                Value::String(session.context.tenant_id.clone()),
-            );
-            map.insert(
-                "user_id".to_string(),
-                Value::String(session.context.user_id.clone()),
-            );
-        }
+        let event_detail = serde_json::to_string(&tenant_event_detail(session, detail))?;
+        let clients = self.clients_for(session).await?;
 
         let result = self
-            .clients
-            .eventbridge
-            .put_events()
-            .entries(
-                aws_sdk_eventbridge::types::PutEventsRequestEntry::builder()
-                    .source("mcp-rust")
-                    .detail_type(detail_type)
-                    .detail(serde_json::to_string(&event_detail)?)
-                    .event_bus_name(&self.event_bus)
-                    .build(),
+            .call("eventbridge", "PutEvents", &self.event_bus)
+            .size(event_detail.len())
+            .run(
+                clients
+                    .eventbridge
+                    .put_events()
+                    .entries(
+                        aws_sdk_eventbridge::types::PutEventsRequestEntry::builder()
+                            .source("mcp-rust")
+                            .detail_type(detail_type)
+                            .detail(&event_detail)
+                            .event_bus_name(&self.event_bus)
+                            .build(),
+                    )
+                    .send(),
             )
-            .send()
             .await;
 
         match result {
             Ok(_) => {}
-            Err(e) => return Err(AwsError::Config(format!("EventBridge error: {}", e))),
+            Err(e) => {
+                return Err(sdk_error(e, "eventbridge_put", |msg| {
+                    AwsError::Config(format!("EventBridge error: {}", msg))
+                }))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publish `events` as `(detailType, detail)` in one `PutEvents` call, at most
+    /// [`event_buffer::EVENT_BATCH_SIZE`]. Returns each event's error, in order (`None` once
+    /// published); `Err` if the call itself failed.
+    pub async fn send_events(
+        &self,
+        session: &TenantSession,
+        events: &[(String, Value)],
+    ) -> Result<Vec<Option<String>>, AwsError> {
+        let mut size = 0;
+        let entries = events
+            .iter()
+            .map(|(detail_type, detail)| {
+                let detail = serde_json::to_string(&tenant_event_detail(session, detail.clone()))?;
+                size += detail.len();
+                Ok(aws_sdk_eventbridge::types::PutEventsRequestEntry::builder()
+                    .source("mcp-rust")
+                    .detail_type(detail_type)
+                    .detail(detail)
+                    .event_bus_name(&self.event_bus)
+                    .build())
+            })
+            .collect::<Result<Vec<_>, AwsError>>()?;
+        let clients = self.clients_for(session).await?;
+        let output = self
+            .call("eventbridge", "PutEvents", &self.event_bus)
+            .size(size)
+            .run(
+                clients
+                    .eventbridge
+                    .put_events()
+                    .set_entries(Some(entries))
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "eventbridge_put", AwsError::EventBridge))?;
+        let mut errors: Vec<Option<String>> = output
+            .entries()
+            .iter()
+            .map(|entry| {
+                entry
+                    .error_code()
+                    .map(|code| format!("{}: {}", code, entry.error_message().unwrap_or_default()))
+            })
+            .collect();
+        // A missing result counts as a failure, as EventBridge reports one per entry
+        errors.resize(events.len(), Some("no result for the event".to_string()));
+        Ok(errors)
+    }
+
+    /// Queue an event in the write-behind buffer and return its provisional id; it's
+    /// sent with the tenant's next batch. Once the buffer is shut down events are sent
+    /// at once instead (`None`), as are those of tenants with their own AWS role, which
+    /// the buffer doesn't send with.
+    pub async fn send_event_buffered(
+        &self,
+        session: &TenantSession,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<Option<String>, AwsError> {
+        if session.context.aws_role.is_some() {
+            self.send_event(session, detail_type, detail).await?;
+            return Ok(None);
+        }
+        let detail = tenant_event_detail(session, detail);
+        match self
+            .event_buffer
+            .push(&session.context.tenant_id, "mcp-rust", detail_type, detail)
+        {
+            Ok(event_id) => Ok(Some(event_id)),
+            Err(detail) => {
+                self.send_event(session, detail_type, detail).await?;
+                Ok(None)
+            }
         }
+    }
+
+    /// Send every buffered event and stop buffering
+    pub async fn flush_events(&self) {
+        let pending = self.event_buffer.pending_count();
+        if pending > 0 {
+            eprintln!("[MCP Server] Flushing {} buffered event(s)", pending);
+        }
+        self.event_buffer.shutdown().await;
+    }
+
+    /// Publish a server-level event that isn't tied to a tenant session
+    pub async fn send_system_event(
+        &self,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<(), AwsError> {
+        let detail = serde_json::to_string(&detail)?;
+        self.call("eventbridge", "PutEvents", &self.event_bus)
+            .size(detail.len())
+            .run(
+                self.clients
+                    .eventbridge
+                    .put_events()
+                    .entries(
+                        aws_sdk_eventbridge::types::PutEventsRequestEntry::builder()
+                            .source("mcp-rust.system")
+                            .detail_type(detail_type)
+                            .detail(detail)
+                            .event_bus_name(&self.event_bus)
+                            .build(),
+                    )
+                    .send(),
+            )
+            .await
+            .map_err(|e| sdk_error(e, "eventbridge_put", AwsError::EventBridge))?;
 
         Ok(())
     }
@@ -291,168 +1717,149 @@ impl AwsService {
     #[allow(clippy::too_many_arguments)]
     pub async fn query_events(
         &self,
+        session: &TenantSession,
+        scope: Option<&EventScope>,
         user_id: Option<String>,
         organization_id: Option<String>,
         source: Option<String>,
         detail_type: Option<String>,
         priority: Option<String>,
+        link_id: Option<String>,
         start_time: Option<String>,
         end_time: Option<String>,
         limit: i32,
         exclusive_start_key: Option<String>,
         ascending: bool,
     ) -> Result<Value, AwsError> {
-        use aws_sdk_dynamodb::types::AttributeValue;
-
-        // Determine table name from environment
-        let events_table = std::env::var("AGENT_MESH_EVENTS_TABLE")
-            .unwrap_or_else(|_| "agent-mesh-dev-events".to_string());
-
-        // Build query based on available filters
-        // Priority: user_id > source > table scan
-        let mut query_builder = if let Some(uid) = user_id.as_ref() {
-            // Use user-index GSI
-            self.clients
-                .dynamodb
-                .query()
-                .table_name(&events_table)
-                .index_name("user-index")
-                .key_condition_expression("#userId = :userId")
-                .expression_attribute_names("#userId", "userId")
-                .expression_attribute_values(":userId", AttributeValue::S(uid.clone()))
-        } else if let Some(src) = source.as_ref() {
-            // Use timestamp-index GSI
-            self.clients
-                .dynamodb
-                .query()
-                .table_name(&events_table)
-                .index_name("timestamp-index")
-                .key_condition_expression("#source = :source")
-                .expression_attribute_names("#source", "source")
-                .expression_attribute_values(":source", AttributeValue::S(src.clone()))
-        } else {
-            // Fall back to scan (expensive!)
-            // In production, we should always have userId or source filter
-            return Err(AwsError::Config(
-                "Query requires userId or source filter to avoid expensive scan".to_string(),
-            ));
+        let query = EventQuery {
+            scope: scope.cloned(),
+            user_id,
+            organization_id,
+            source,
+            detail_type,
+            priority,
+            link_id,
+            start_time,
+            end_time,
+            limit,
+            exclusive_start_key,
+            ascending,
         };
+        let plan = EventQueryPlan::new(&query)?;
+        let clients = self.clients_for(session).await?;
+        Ok(self
+            .run_event_query(&clients, &query, &plan, false)
+            .await?
+            .0)
+    }
 
-        // Add time range filter if provided
-        if let (Some(start), Some(end)) = (start_time.as_ref(), end_time.as_ref()) {
-            if user_id.is_some() {
-                query_builder = query_builder
-                    .key_condition_expression(
-                        "#userId = :userId AND #timestamp BETWEEN :start AND :end",
-                    )
-                    .expression_attribute_names("#timestamp", "timestamp")
-                    .expression_attribute_values(":start", AttributeValue::S(start.clone()))
-                    .expression_attribute_values(":end", AttributeValue::S(end.clone()));
-            } else if source.is_some() {
-                query_builder = query_builder
-                    .key_condition_expression(
-                        "#source = :source AND #timestamp BETWEEN :start AND :end",
-                    )
-                    .expression_attribute_names("#timestamp", "timestamp")
-                    .expression_attribute_values(":start", AttributeValue::S(start.clone()))
-                    .expression_attribute_values(":end", AttributeValue::S(end.clone()));
-            }
-        } else if let Some(start) = start_time.as_ref() {
-            if user_id.is_some() {
-                query_builder = query_builder
-                    .key_condition_expression("#userId = :userId AND #timestamp >= :start")
-                    .expression_attribute_names("#timestamp", "timestamp")
-                    .expression_attribute_values(":start", AttributeValue::S(start.clone()));
-            } else if source.is_some() {
-                query_builder = query_builder
-                    .key_condition_expression("#source = :source AND #timestamp >= :start")
-                    .expression_attribute_names("#timestamp", "timestamp")
-                    .expression_attribute_values(":start", AttributeValue::S(start.clone()));
-            }
-        }
-
-        // Add filter expressions for additional filters
-        let mut filter_expression_parts = Vec::new();
-
-        if let Some(dt) = detail_type.as_ref() {
-            filter_expression_parts.push("#detailType = :detailType".to_string());
-            query_builder = query_builder
-                .expression_attribute_names("#detailType", "detailType")
-                .expression_attribute_values(":detailType", AttributeValue::S(dt.clone()));
+    /// `query_events` with the plan as `explain`, and what each of its index queries
+    /// read, including the read capacity DynamoDB reports; with `explain_only` the plan
+    /// alone, without running it
+    pub async fn query_events_explained(
+        &self,
+        session: &TenantSession,
+        query: &EventQuery,
+        explain_only: bool,
+    ) -> Result<Value, AwsError> {
+        let plan = EventQueryPlan::new(query)?;
+        if explain_only {
+            return Ok(json!({"explain": plan.explain(None)}));
         }
+        let clients = self.clients_for(session).await?;
+        let (mut result, stats) = self.run_event_query(&clients, query, &plan, true).await?;
+        result["explain"] = plan.explain(Some(&stats));
+        Ok(result)
+    }
 
-        if let Some(prio) = priority.as_ref() {
-            filter_expression_parts.push("#priority = :priority".to_string());
-            query_builder = query_builder
-                .expression_attribute_names("#priority", "priority")
-                .expression_attribute_values(":priority", AttributeValue::S(prio.clone()));
+    /// The events `plan` finds for `query` with `clients`, merged and cut to its limit
+    /// when there's more than one index query, and what each index query read
+    async fn run_event_query(
+        &self,
+        clients: &AwsClients,
+        query: &EventQuery,
+        plan: &EventQueryPlan,
+        consumed_capacity: bool,
+    ) -> Result<(Value, Vec<IndexQueryStats>), AwsError> {
+        let mut events = Vec::new();
+        let mut stats = Vec::new();
+        let mut last_evaluated_key = None;
+        for index_query in &plan.queries {
+            let (page, next, page_stats) = self
+                .query_event_index(clients, index_query, consumed_capacity)
+                .await?;
+            events.extend(page);
+            last_evaluated_key = next;
+            stats.push(page_stats);
         }
 
-        if let Some(org_id) = organization_id.as_ref() {
-            filter_expression_parts.push("#organizationId = :organizationId".to_string());
-            query_builder = query_builder
-                .expression_attribute_names("#organizationId", "organizationId")
-                .expression_attribute_values(":organizationId", AttributeValue::S(org_id.clone()));
+        // TODO: continue from `query.exclusive_start_key` (needs proper key
+        // deserialization)
+        if plan.queries.len() > 1 {
+            events.sort_by(|a, b| a["timestamp"].as_str().cmp(&b["timestamp"].as_str()));
+            if !query.ascending {
+                events.reverse();
+            }
+            events.truncate(query.limit.max(0) as usize);
         }
-
-        if !filter_expression_parts.is_empty() {
-            query_builder = query_builder.filter_expression(filter_expression_parts.join(" AND "));
+        // Scoped queries aren't paged
+        if query.scope.is_some() {
+            last_evaluated_key = None;
         }
+        Ok((
+            json!({
+                "events": events,
+                "count": events.len(),
+                "lastEvaluatedKey": last_evaluated_key
+            }),
+            stats,
+        ))
+    }
 
-        // Set limit
-        query_builder = query_builder.limit(limit);
-
-        // Set scan direction
-        query_builder = query_builder.scan_index_forward(ascending);
-
-        // Add pagination cursor if provided
-        if let Some(_start_key) = exclusive_start_key {
-            // TODO: Parse start key (simplified - in production would be proper DynamoDB key)
-            // For now, we'll skip this since it requires proper key deserialization
-        }
+    /// One page of `query` on an index of the events table, the position of the next
+    /// page if there is one, and what the page read
+    async fn query_event_index(
+        &self,
+        clients: &AwsClients,
+        query: &IndexQuery,
+        consumed_capacity: bool,
+    ) -> Result<(Vec<Value>, Option<String>, IndexQueryStats), AwsError> {
+        use aws_sdk_dynamodb::types::ReturnConsumedCapacity;
 
-        // Execute query
-        let result = query_builder
-            .send()
+        let request = clients
+            .dynamodb
+            .query()
+            .table_name(&self.events_table)
+            .index_name(query.index)
+            .key_condition_expression(query.key_condition())
+            .set_filter_expression(query.filter_expression())
+            .set_expression_attribute_names(Some(query.attribute_names()))
+            .set_expression_attribute_values(Some(query.attribute_values()))
+            .limit(query.limit)
+            .scan_index_forward(query.ascending)
+            .set_return_consumed_capacity(
+                consumed_capacity.then_some(ReturnConsumedCapacity::Total),
+            );
+        let result = self
+            .call("dynamodb", "Query", &self.events_table)
+            .run(request.send())
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
-
-        // Convert DynamoDB items to JSON
-        let mut events = Vec::new();
-        if let Some(ref items) = result.items {
-            for item in items {
-                let mut event = serde_json::Map::new();
-
-                for (key, value) in item {
-                    let json_value = match value {
-                        AttributeValue::S(s) => Value::String(s.clone()),
-                        AttributeValue::N(n) => {
-                            if let Ok(num) = n.parse::<i64>() {
-                                Value::Number(num.into())
-                            } else if let Ok(num) = n.parse::<f64>() {
-                                Value::Number(serde_json::Number::from_f64(num).unwrap_or(0.into()))
-                            } else {
-                                Value::String(n.clone())
-                            }
-                        }
-                        AttributeValue::Bool(b) => Value::Bool(*b),
-                        _ => Value::String(format!("{:?}", value)),
-                    };
-                    event.insert(key.clone(), json_value);
-                }
+            .map_err(|e| sdk_error(e, "dynamodb_query", AwsError::DynamoDb))?;
 
-                events.push(Value::Object(event));
-            }
-        }
-
-        // Build response
-        let response = serde_json::json!({
-            "events": events,
-            "count": events.len(),
-            "lastEvaluatedKey": result.last_evaluated_key().map(|k| format!("{:?}", k))
-        });
-
-        Ok(response)
+        let stats = IndexQueryStats {
+            pages_fetched: 1,
+            items_scanned: result.scanned_count().max(0) as u64,
+            items_matched: result.count().max(0) as u64,
+            consumed_read_capacity: result
+                .consumed_capacity()
+                .and_then(|capacity| capacity.capacity_units()),
+        };
+        let next = result.last_evaluated_key().map(|k| format!("{:?}", k));
+        Ok((
+            result.items().iter().map(event_item_json).collect(),
+            next,
+            stats,
+        ))
     }
 
     // Analytics query for event metrics
@@ -467,21 +1874,12 @@ impl AwsService {
         metrics: Vec<String>,
         granularity: String,
     ) -> Result<Value, AwsError> {
-        // Generate cache key
-        let scope = if let Some(org_id) = &organization_id {
-            format!("org-{}", org_id)
-        } else if let Some(uid) = &user_id {
-            format!("user-{}", uid)
-        } else {
-            format!("user-{}", session.context.user_id)
-        };
-
-        let time_range = format!(
-            "{}-{}",
+        let scope = analytics_scope(session, user_id.as_deref(), organization_id.as_deref());
+        let cache_key = internal_keys::analytics_cache_key(
+            &scope,
             start_time.as_deref().unwrap_or("24h"),
-            end_time.as_deref().unwrap_or("now")
+            end_time.as_deref().unwrap_or("now"),
         );
-        let cache_key = format!("analytics-{}-{}", scope, time_range);
 
         // Check cache (5 minute TTL)
         if let Ok(Some(cached)) = self.kv_get_direct(&cache_key).await {
@@ -492,29 +1890,12 @@ impl AwsService {
         }
 
         // Query events for analytics
-        let events_table = std::env::var("AGENT_MESH_EVENTS_TABLE")
-            .unwrap_or_else(|_| "agent-mesh-dev-events".to_string());
-
-        // Default time window: last 24 hours
-        let end_dt = if let Some(et) = end_time {
-            chrono::DateTime::parse_from_rfc3339(&et)
-                .map_err(|e| AwsError::Config(format!("Invalid endTime: {}", e)))?
-                .with_timezone(&chrono::Utc)
-        } else {
-            chrono::Utc::now()
-        };
-
-        let start_dt = if let Some(st) = start_time {
-            chrono::DateTime::parse_from_rfc3339(&st)
-                .map_err(|e| AwsError::Config(format!("Invalid startTime: {}", e)))?
-                .with_timezone(&chrono::Utc)
-        } else {
-            end_dt - chrono::Duration::hours(24)
-        };
+        let events_table = self.events_table.clone();
+        let (start_dt, end_dt) = analytics_window(start_time, end_time)?;
 
         // Query events using timestamp-index
-        let mut query_builder = self
-            .clients
+        let clients = self.clients_for(session).await?;
+        let mut query_builder = clients
             .dynamodb
             .query()
             .table_name(&events_table)
@@ -549,117 +1930,25 @@ impl AwsService {
                 );
         }
 
-        let result = query_builder
-            .send()
+        let result = self
+            .call("dynamodb", "Query", &events_table)
+            .run(query_builder.send())
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
+            .map_err(|e| sdk_error(e, "dynamodb_query", AwsError::DynamoDb))?;
 
         // Process events for analytics
-        let mut volume_buckets: std::collections::HashMap<String, i32> =
-            std::collections::HashMap::new();
-        let mut source_counts: std::collections::HashMap<String, i32> =
-            std::collections::HashMap::new();
-        let mut priority_counts: std::collections::HashMap<String, i32> =
-            std::collections::HashMap::new();
-        let mut event_type_counts: std::collections::HashMap<String, i32> =
-            std::collections::HashMap::new();
-
-        if let Some(ref items) = result.items {
-            for item in items {
-                // Extract timestamp for volume buckets
-                if let Some(timestamp_attr) = item.get("timestamp") {
-                    if let Ok(ts_str) = timestamp_attr.as_s() {
-                        if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(ts_str) {
-                            let bucket_key = if granularity == "hourly" {
-                                ts.format("%Y-%m-%d %H:00").to_string()
-                            } else {
-                                ts.format("%Y-%m-%d").to_string()
-                            };
-                            *volume_buckets.entry(bucket_key).or_insert(0) += 1;
-                        }
-                    }
-                }
-
-                // Count sources
-                if let Some(source_attr) = item.get("source") {
-                    if let Ok(source) = source_attr.as_s() {
-                        *source_counts.entry(source.clone()).or_insert(0) += 1;
-                    }
-                }
-
-                // Count priorities
-                if let Some(priority_attr) = item.get("priority") {
-                    if let Ok(priority) = priority_attr.as_s() {
-                        *priority_counts.entry(priority.clone()).or_insert(0) += 1;
-                    }
-                }
-
-                // Count event types
-                if let Some(detail_type_attr) = item.get("detailType") {
-                    if let Ok(detail_type) = detail_type_attr.as_s() {
-                        *event_type_counts.entry(detail_type.clone()).or_insert(0) += 1;
-                    }
-                }
-            }
-        }
-
-        // Build response based on requested metrics
-        let mut analytics = serde_json::Map::new();
-
-        if metrics.contains(&"volume".to_string()) {
-            let mut buckets: Vec<_> = volume_buckets
-                .into_iter()
-                .map(|(bucket, count)| json!({ "bucket": bucket, "count": count }))
-                .collect();
-            buckets.sort_by(|a, b| a["bucket"].as_str().cmp(&b["bucket"].as_str()));
-            analytics.insert(
-                "volume".to_string(),
-                json!({
-                    "granularity": granularity,
-                    "buckets": buckets
-                }),
-            );
-        }
-
-        if metrics.contains(&"topSources".to_string()) {
-            let mut sources: Vec<_> = source_counts.into_iter().collect();
-            sources.sort_by(|a, b| b.1.cmp(&a.1)); // Descending by count
-            let top_sources: Vec<_> = sources
-                .into_iter()
-                .map(|(source, count)| json!({ "source": source, "count": count }))
-                .collect();
-            analytics.insert("topSources".to_string(), json!(top_sources));
-        }
-
-        if metrics.contains(&"priority".to_string()) {
-            analytics.insert(
-                "priority".to_string(),
-                json!({
-                    "low": priority_counts.get("low").unwrap_or(&0),
-                    "medium": priority_counts.get("medium").unwrap_or(&0),
-                    "high": priority_counts.get("high").unwrap_or(&0),
-                    "critical": priority_counts.get("critical").unwrap_or(&0)
-                }),
+        let mut counts = EventCounts::default();
+        for item in result.items.unwrap_or_default() {
+            let field = |name: &str| item.get(name).and_then(|value| value.as_s().ok()).cloned();
+            counts.add(
+                field("timestamp").as_deref(),
+                field("source").as_deref(),
+                field("priority").as_deref(),
+                field("detailType").as_deref(),
+                &granularity,
             );
         }
-
-        if metrics.contains(&"eventTypes".to_string()) {
-            let mut types: Vec<_> = event_type_counts.into_iter().collect();
-            types.sort_by(|a, b| b.1.cmp(&a.1)); // Descending by count
-            let event_types: Vec<_> = types
-                .into_iter()
-                .map(|(event_type, count)| json!({ "eventType": event_type, "count": count }))
-                .collect();
-            analytics.insert("eventTypes".to_string(), json!(event_types));
-        }
-
-        let response = json!({
-            "scope": scope,
-            "startTime": start_dt.to_rfc3339(),
-            "endTime": end_dt.to_rfc3339(),
-            "analytics": analytics,
-            "cached": false
-        });
+        let response = analytics_response(scope, start_dt, end_dt, counts, &metrics, &granularity);
 
         // Cache the result (5 minute TTL = 300 seconds)
         let cache_value = serde_json::to_string(&response).unwrap();
@@ -674,6 +1963,175 @@ impl AwsService {
         Ok(response)
     }
 
+    /// Run a `TransactWriteItems` call with `clients`; `operations` describe the items,
+    /// in order, for the error if the transaction is canceled
+    async fn transact_write(
+        &self,
+        clients: &AwsClients,
+        items: Vec<aws_sdk_dynamodb::types::TransactWriteItem>,
+        operations: &[String],
+    ) -> Result<(), AwsError> {
+        transactions::check_op_count(items.len())?;
+        let tables = transact_tables(&items);
+        self.call("dynamodb", "TransactWriteItems", &tables)
+            .run(
+                clients
+                    .dynamodb
+                    .transact_write_items()
+                    .set_transact_items(Some(items))
+                    .send(),
+            )
+            .await
+            .map_err(|e| transaction_error(e, operations))?;
+        Ok(())
+    }
+
+    /// Apply up to 25 writes to the tenant's KV items atomically: either all of them
+    /// apply, or none do and `TransactionCanceled` lists the operations that failed
+    pub async fn kv_transact_write(
+        &self,
+        session: &TenantSession,
+        ops: &[KvWriteOp],
+    ) -> Result<(), AwsError> {
+        transactions::validate_kv_ops(ops)?;
+        let prefix = session.context.get_namespace_prefix();
+        let items = ops
+            .iter()
+            .map(|op| kv_transact_item(&self.kv_table, op, format!("{}:{}", prefix, op.key())))
+            .collect::<Result<Vec<_>, _>>()?;
+        let operations: Vec<String> = ops.iter().map(KvWriteOp::describe).collect();
+        let clients = self.clients_for(session).await?;
+        let result = self.transact_write(&clients, items, &operations).await;
+        if let Some(cache) = &self.kv_cache {
+            for op in ops {
+                cache.invalidate(&format!("{}:{}", prefix, op.key()));
+            }
+        }
+        result
+    }
+
+    /// Write a KV item in the tenant's namespace only if its value is still
+    /// `expected`, or with None only if it doesn't exist yet. False when the
+    /// condition failed because another writer got there first.
+    pub async fn kv_compare_and_swap(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        let tenant_key = format!("{}:{}", session.context.get_namespace_prefix(), key);
+        let clients = self.clients_for(session).await?;
+        self.swap_kv_item(&clients, &tenant_key, expected, value, ttl_hours)
+            .await
+    }
+
+    /// Write `value` under the table key `key` with `clients` if the item's value is
+    /// `expected`, or there's no item when `None`; `false` if it wasn't
+    async fn swap_kv_item(
+        &self,
+        clients: &AwsClients,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let now = chrono::Utc::now().timestamp();
+        let call = self
+            .call("dynamodb", "PutItem", &self.kv_table)
+            .key(key)
+            .size(value.len());
+
+        let mut put_request = clients
+            .dynamodb
+            .put_item()
+            .table_name(&self.kv_table)
+            .item("key", AttributeValue::S(key.to_string()))
+            .item("value", AttributeValue::S(value.to_string()))
+            .item("created_at", AttributeValue::N(now.to_string()));
+        if let Some(ttl) = ttl_hours {
+            let expiry = now + (ttl as i64 * 3600);
+            put_request = put_request.item("expires_at", AttributeValue::N(expiry.to_string()));
+        }
+        put_request = match expected {
+            Some(expected) => put_request
+                .condition_expression("#value = :expected")
+                .expression_attribute_names("#value", "value")
+                .expression_attribute_values(":expected", AttributeValue::S(expected.to_string())),
+            None => put_request
+                .condition_expression(ItemCondition::Absent.expression())
+                .expression_attribute_names("#key", "key"),
+        };
+
+        let result = call
+            .run(put_request.send())
+            .await
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb));
+        if let Some(cache) = &self.kv_cache {
+            cache.invalidate(key);
+        }
+        match result {
+            Ok(_) => Ok(true),
+            Err(AwsError::ConditionFailed(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// A page of the session's KV keys, relative to its namespace, and the position the
+    /// next page starts from (`None` after the last). The table is scanned, so pages
+    /// aren't in key order, and `limit` is a floor rather than a cap: a page ends with
+    /// the scan page that reached it.
+    pub async fn kv_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let prefix = format!("{}:", session.context.get_namespace_prefix());
+        let position =
+            |key: &str| HashMap::from([("key".to_string(), AttributeValue::S(key.to_string()))]);
+        let mut keys = Vec::new();
+        let mut start_key = start.map(position);
+        let clients = self.clients_for(session).await?;
+        loop {
+            let scan = clients
+                .dynamodb
+                .scan()
+                .table_name(&self.kv_table)
+                .filter_expression("begins_with(#k, :prefix)")
+                .expression_attribute_names("#k", "key")
+                .expression_attribute_values(":prefix", AttributeValue::S(prefix.clone()))
+                .projection_expression("#k")
+                .limit(KV_LIST_PAGE_SIZE)
+                .set_exclusive_start_key(start_key);
+            let result = self
+                .call("dynamodb", "Scan", &self.kv_table)
+                .run(scan.send())
+                .await
+                .map_err(|e| sdk_error(e, "dynamodb_read", AwsError::DynamoDb))?;
+
+            for item in result.items.unwrap_or_default() {
+                if let Some(Ok(key)) = item.get("key").map(|key| key.as_s()) {
+                    if let Some(key) = key.strip_prefix(&prefix) {
+                        keys.push(key.to_string());
+                    }
+                }
+            }
+            let next = result
+                .last_evaluated_key
+                .and_then(|key| key.get("key").and_then(|key| key.as_s().ok()).cloned());
+            match next {
+                Some(next) if keys.len() < limit => start_key = Some(position(&next)),
+                next => return Ok((keys, next)),
+            }
+        }
+    }
+
     // Create event rule
     pub async fn create_event_rule(
         &self,
@@ -683,72 +2141,24 @@ impl AwsService {
         description: Option<String>,
         enabled: bool,
     ) -> Result<Value, AwsError> {
-        let event_rules_table = std::env::var("AGENT_MESH_EVENT_RULES_TABLE")
-            .unwrap_or_else(|_| "agent-mesh-dev-event-rules".to_string());
-
-        // Generate unique rule ID
-        let rule_id = format!("rule-{}-{}", session.context.user_id, uuid::Uuid::new_v4());
-        let timestamp = chrono::Utc::now().to_rfc3339();
+        let (item, rule) = event_rule_item(session, name, pattern, description, enabled)?;
 
         // Store rule in DynamoDB
-        let mut put_item = self
-            .clients
-            .dynamodb
-            .put_item()
-            .table_name(&event_rules_table)
-            .item(
-                "ruleId",
-                aws_sdk_dynamodb::types::AttributeValue::S(rule_id.clone()),
-            )
-            .item(
-                "userId",
-                aws_sdk_dynamodb::types::AttributeValue::S(session.context.user_id.clone()),
-            )
-            .item(
-                "organizationId",
-                aws_sdk_dynamodb::types::AttributeValue::S(session.context.organization_id.clone()),
+        let table = self.event_rules_table.clone();
+        let clients = self.clients_for(session).await?;
+        self.call("dynamodb", "PutItem", &table)
+            .run(
+                clients
+                    .dynamodb
+                    .put_item()
+                    .table_name(&table)
+                    .set_item(Some(item))
+                    .send(),
             )
-            .item(
-                "name",
-                aws_sdk_dynamodb::types::AttributeValue::S(name.to_string()),
-            )
-            .item(
-                "pattern",
-                aws_sdk_dynamodb::types::AttributeValue::S(serde_json::to_string(&pattern)?),
-            )
-            .item(
-                "enabled",
-                aws_sdk_dynamodb::types::AttributeValue::Bool(enabled),
-            )
-            .item(
-                "createdAt",
-                aws_sdk_dynamodb::types::AttributeValue::S(timestamp.clone()),
-            )
-            .item(
-                "updatedAt",
-                aws_sdk_dynamodb::types::AttributeValue::S(timestamp.clone()),
-            );
-
-        if let Some(desc) = description.as_ref() {
-            put_item = put_item.item(
-                "description",
-                aws_sdk_dynamodb::types::AttributeValue::S(desc.clone()),
-            );
-        }
-
-        put_item
-            .send()
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
-
-        Ok(json!({
-            "ruleId": rule_id,
-            "name": name,
-            "pattern": pattern,
-            "description": description,
-            "enabled": enabled,
-            "createdAt": timestamp
-        }))
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb))?;
+
+        Ok(rule)
     }
 
     // Create alert subscription
@@ -763,102 +2173,399 @@ impl AwsService {
         email_address: Option<String>,
         enabled: bool,
     ) -> Result<Value, AwsError> {
-        let subscriptions_table = std::env::var("AGENT_MESH_SUBSCRIPTIONS_TABLE")
-            .unwrap_or_else(|_| "agent-mesh-dev-subscriptions".to_string());
-
-        // Generate unique subscription ID
-        let subscription_id = format!("sub-{}-{}", session.context.user_id, uuid::Uuid::new_v4());
-        let timestamp = chrono::Utc::now().to_rfc3339();
+        let alert = NewAlertSubscription {
+            name: name.to_string(),
+            notification_method: notification_method.to_string(),
+            sns_topic_arn,
+            email_address,
+            enabled,
+        };
+        let (item, subscription) = alert_subscription_item(session, rule_id, &alert);
 
         // Store subscription in DynamoDB
-        let mut put_item = self
-            .clients
-            .dynamodb
-            .put_item()
-            .table_name(&subscriptions_table)
-            .item(
-                "subscriptionId",
-                aws_sdk_dynamodb::types::AttributeValue::S(subscription_id.clone()),
-            )
-            .item(
-                "userId",
-                aws_sdk_dynamodb::types::AttributeValue::S(session.context.user_id.clone()),
+        let table = self.subscriptions_table.clone();
+        let clients = self.clients_for(session).await?;
+        self.call("dynamodb", "PutItem", &table)
+            .run(
+                clients
+                    .dynamodb
+                    .put_item()
+                    .table_name(&table)
+                    .set_item(Some(item))
+                    .send(),
             )
-            .item(
-                "organizationId",
-                aws_sdk_dynamodb::types::AttributeValue::S(session.context.organization_id.clone()),
-            )
-            .item(
-                "name",
-                aws_sdk_dynamodb::types::AttributeValue::S(name.to_string()),
-            )
-            .item(
-                "ruleId",
-                aws_sdk_dynamodb::types::AttributeValue::S(rule_id.to_string()),
-            )
-            .item(
-                "notificationMethod",
-                aws_sdk_dynamodb::types::AttributeValue::S(notification_method.to_string()),
+            .await
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb))?;
+
+        Ok(subscription)
+    }
+
+    /// Create a rule and its alert subscription together, so neither is stored without
+    /// the other; returns the rule with the subscription as `alert`
+    pub async fn create_event_rule_with_alert(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        pattern: Value,
+        description: Option<String>,
+        enabled: bool,
+        alert: &NewAlertSubscription,
+    ) -> Result<Value, AwsError> {
+        use aws_sdk_dynamodb::types::{Put, TransactWriteItem};
+
+        let (rule_item, mut rule) = event_rule_item(session, name, pattern, description, enabled)?;
+        let rule_id = rule["ruleId"].as_str().unwrap_or_default().to_string();
+        let (subscription_item, subscription) = alert_subscription_item(session, &rule_id, alert);
+
+        // Fresh ids, so the conditions only fail if an id is somehow reused
+        let put = |table: String, item, key: &str| {
+            Put::builder()
+                .table_name(table)
+                .set_item(Some(item))
+                .condition_expression(format!("attribute_not_exists({})", key))
+                .build()
+                .map(|put| TransactWriteItem::builder().put(put).build())
+                .map_err(|e| AwsError::DynamoDb(e.to_string()))
+        };
+        let items = vec![
+            put(self.event_rules_table.clone(), rule_item, "ruleId")?,
+            put(
+                self.subscriptions_table.clone(),
+                subscription_item,
+                "subscriptionId",
+            )?,
+        ];
+        let clients = self.clients_for(session).await?;
+        self.transact_write(
+            &clients,
+            items,
+            &[
+                format!("put rule {:?}", rule_id),
+                format!("put subscription {:?}", subscription["subscriptionId"]),
+            ],
+        )
+        .await?;
+
+        rule["alert"] = subscription;
+        Ok(rule)
+    }
+
+    /// Every item of `table`'s `user-index` for the session's user in its organization,
+    /// followed page by page
+    async fn query_user_items(
+        &self,
+        table: &str,
+        session: &TenantSession,
+    ) -> Result<Vec<Value>, AwsError> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let clients = self.clients_for(session).await?;
+        let mut items = Vec::new();
+        let mut start_key = None;
+        loop {
+            let query = clients
+                .dynamodb
+                .query()
+                .table_name(table)
+                .index_name("user-index")
+                .key_condition_expression("#userId = :userId")
+                .filter_expression("#organizationId = :organizationId")
+                .expression_attribute_names("#userId", "userId")
+                .expression_attribute_names("#organizationId", "organizationId")
+                .expression_attribute_values(
+                    ":userId",
+                    AttributeValue::S(session.context.user_id.clone()),
+                )
+                .expression_attribute_values(
+                    ":organizationId",
+                    AttributeValue::S(session.context.organization_id.clone()),
+                )
+                .set_exclusive_start_key(start_key);
+            let result = self
+                .call("dynamodb", "Query", table)
+                .run(query.send())
+                .await
+                .map_err(|e| sdk_error(e, "dynamodb_query", AwsError::DynamoDb))?;
+            items.extend(result.items().iter().map(event_item_json));
+            start_key = result.last_evaluated_key.filter(|key| !key.is_empty());
+            if start_key.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Delete an item of `table` keyed by `(attribute, id)` if it belongs to the session's
+    /// user and organization; `NotFound` otherwise
+    async fn delete_user_item(
+        &self,
+        table: &str,
+        (attribute, id): (&str, &str),
+        session: &TenantSession,
+    ) -> Result<(), AwsError> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let request = self
+            .clients_for(session)
+            .await?
+            .dynamodb
+            .delete_item()
+            .table_name(table)
+            .key(attribute, AttributeValue::S(id.to_string()))
+            .condition_expression("#userId = :userId AND #organizationId = :organizationId")
+            .expression_attribute_names("#userId", "userId")
+            .expression_attribute_names("#organizationId", "organizationId")
+            .expression_attribute_values(
+                ":userId",
+                AttributeValue::S(session.context.user_id.clone()),
             )
-            .item(
-                "enabled",
-                aws_sdk_dynamodb::types::AttributeValue::Bool(enabled),
+            .expression_attribute_values(
+                ":organizationId",
+                AttributeValue::S(session.context.organization_id.clone()),
+            );
+        let result = self
+            .call("dynamodb", "DeleteItem", table)
+            .key(id)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb));
+        match result {
+            Ok(_) => Ok(()),
+            Err(AwsError::ConditionFailed(_)) => {
+                Err(AwsError::NotFound(format!("{} {:?}", attribute, id)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The event rules the session's user created in its organization
+    pub async fn event_rules(&self, session: &TenantSession) -> Result<Vec<Value>, AwsError> {
+        self.query_user_items(&self.event_rules_table, session)
+            .await
+    }
+
+    /// The alert subscriptions the session's user created in its organization
+    pub async fn alert_subscriptions(
+        &self,
+        session: &TenantSession,
+    ) -> Result<Vec<Value>, AwsError> {
+        self.query_user_items(&self.subscriptions_table, session)
+            .await
+    }
+
+    /// Users with data in the session's organization (see [`AwsApi::namespace_users`]).
+    /// Every table is scanned and the bucket listed, so this is for rare admin jobs only.
+    pub async fn namespace_users(&self, session: &TenantSession) -> Result<Vec<String>, AwsError> {
+        use aws_sdk_dynamodb::types::AttributeValue;
+
+        let mut users = BTreeSet::from([session.context.user_id.clone()]);
+        let Some(prefix) = namespaces::organization_namespace_prefix(session) else {
+            return Ok(users.into_iter().collect());
+        };
+        let clients = self.clients_for(session).await?;
+        let begins_with = "begins_with(#f, :v)";
+        let namespaced = AttributeValue::S(prefix.clone());
+        let mut values = self
+            .scan_attribute(
+                &clients,
+                &self.kv_table,
+                "key",
+                begins_with,
+                "key",
+                namespaced.clone(),
             )
-            .item(
-                "createdAt",
-                aws_sdk_dynamodb::types::AttributeValue::S(timestamp.clone()),
+            .await?;
+        values.extend(
+            self.scan_attribute(
+                &clients,
+                &self.events_table,
+                "namespace",
+                begins_with,
+                "namespace",
+                namespaced,
             )
-            .item(
-                "updatedAt",
-                aws_sdk_dynamodb::types::AttributeValue::S(timestamp.clone()),
-            );
+            .await?,
+        );
 
-        if let Some(arn) = sns_topic_arn.as_ref() {
-            put_item = put_item.item(
-                "snsTopicArn",
-                aws_sdk_dynamodb::types::AttributeValue::S(arn.clone()),
+        let mut continuation_token = None;
+        loop {
+            let result = self
+                .call("s3", "ListObjectsV2", &self.artifacts_bucket)
+                .run(
+                    clients
+                        .s3
+                        .list_objects_v2()
+                        .bucket(&self.artifacts_bucket)
+                        .prefix(&prefix)
+                        .delimiter("/")
+                        .set_continuation_token(continuation_token)
+                        .send(),
+                )
+                .await
+                .map_err(|e| sdk_error(e, "s3_list", AwsError::S3))?;
+            values.extend(
+                result
+                    .common_prefixes()
+                    .iter()
+                    .filter_map(|common| common.prefix().map(str::to_string)),
             );
+            continuation_token = result.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
         }
+        users.extend(
+            values
+                .iter()
+                .filter_map(|value| namespaces::namespace_user(&prefix, value))
+                .map(str::to_string),
+        );
 
-        if let Some(email) = email_address.as_ref() {
-            put_item = put_item.item(
-                "emailAddress",
-                aws_sdk_dynamodb::types::AttributeValue::S(email.clone()),
+        let organization = AttributeValue::S(session.context.organization_id.clone());
+        for table in [&self.event_rules_table, &self.subscriptions_table] {
+            users.extend(
+                self.scan_attribute(
+                    &clients,
+                    table,
+                    "userId",
+                    "#f = :v",
+                    "organizationId",
+                    organization.clone(),
+                )
+                .await?,
             );
         }
+        Ok(users.into_iter().collect())
+    }
+
+    /// The `attribute` of every item of `table` whose `filter_attribute` passes
+    /// `condition` (naming it `#f`, and `value` as `:v`), scanned page by page
+    async fn scan_attribute(
+        &self,
+        clients: &AwsClients,
+        table: &str,
+        attribute: &str,
+        condition: &str,
+        filter_attribute: &str,
+        value: aws_sdk_dynamodb::types::AttributeValue,
+    ) -> Result<Vec<String>, AwsError> {
+        let mut values = Vec::new();
+        let mut start_key = None;
+        loop {
+            let scan = clients
+                .dynamodb
+                .scan()
+                .table_name(table)
+                .filter_expression(condition)
+                .projection_expression("#a")
+                .expression_attribute_names("#a", attribute)
+                .expression_attribute_names("#f", filter_attribute)
+                .expression_attribute_values(":v", value.clone())
+                .set_exclusive_start_key(start_key);
+            let result = self
+                .call("dynamodb", "Scan", table)
+                .run(scan.send())
+                .await
+                .map_err(|e| sdk_error(e, "dynamodb_read", AwsError::DynamoDb))?;
+            values.extend(result.items().iter().filter_map(|item| {
+                item.get(attribute)
+                    .and_then(|value| value.as_s().ok())
+                    .cloned()
+            }));
+            start_key = result.last_evaluated_key.filter(|key| !key.is_empty());
+            if start_key.is_none() {
+                return Ok(values);
+            }
+        }
+    }
 
-        put_item
-            .send()
+    pub async fn delete_event_rule(
+        &self,
+        session: &TenantSession,
+        rule_id: &str,
+    ) -> Result<(), AwsError> {
+        self.delete_user_item(&self.event_rules_table, ("ruleId", rule_id), session)
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
+    }
 
-        Ok(json!({
-            "subscriptionId": subscription_id,
-            "name": name,
-            "ruleId": rule_id,
-            "notificationMethod": notification_method,
-            "snsTopicArn": sns_topic_arn,
-            "emailAddress": email_address,
-            "enabled": enabled,
-            "createdAt": timestamp
-        }))
+    pub async fn delete_alert_subscription(
+        &self,
+        session: &TenantSession,
+        subscription_id: &str,
+    ) -> Result<(), AwsError> {
+        self.delete_user_item(
+            &self.subscriptions_table,
+            ("subscriptionId", subscription_id),
+            session,
+        )
+        .await
+    }
+
+    /// Delete the session's tenant's event records by `eventId`, 25 to a
+    /// `BatchWriteItem` call, retrying the items DynamoDB leaves unprocessed
+    pub async fn delete_events(
+        &self,
+        session: &TenantSession,
+        event_ids: &[String],
+    ) -> Result<(), AwsError> {
+        use aws_sdk_dynamodb::types::{AttributeValue, DeleteRequest, WriteRequest};
+
+        let clients = self.clients_for(session).await?;
+        for batch in event_ids.chunks(BATCH_WRITE_SIZE) {
+            let mut requests = batch
+                .iter()
+                .map(|event_id| {
+                    DeleteRequest::builder()
+                        .key("eventId", AttributeValue::S(event_id.clone()))
+                        .build()
+                        .map(|delete| WriteRequest::builder().delete_request(delete).build())
+                        .map_err(|e| AwsError::DynamoDb(e.to_string()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for attempt in 0..BATCH_WRITE_ATTEMPTS {
+                if attempt > 0 {
+                    tokio::time::sleep(Duration::from_millis(50 << attempt)).await;
+                }
+                let result = self
+                    .call("dynamodb", "BatchWriteItem", &self.events_table)
+                    .run(
+                        clients
+                            .dynamodb
+                            .batch_write_item()
+                            .request_items(&self.events_table, requests)
+                            .send(),
+                    )
+                    .await
+                    .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb))?;
+                requests = result
+                    .unprocessed_items
+                    .and_then(|mut unprocessed| unprocessed.remove(&self.events_table))
+                    .unwrap_or_default();
+                if requests.is_empty() {
+                    break;
+                }
+            }
+            if !requests.is_empty() {
+                return Err(AwsError::DynamoDb(format!(
+                    "{} event deletions were left unprocessed",
+                    requests.len()
+                )));
+            }
+        }
+        Ok(())
     }
 
     // Events health check
     pub async fn events_health_check(&self, session: &TenantSession) -> Result<Value, AwsError> {
-        let events_table = std::env::var("AGENT_MESH_EVENTS_TABLE")
-            .unwrap_or_else(|_| "agent-mesh-dev-events".to_string());
-        let rules_table = std::env::var("AGENT_MESH_EVENT_RULES_TABLE")
-            .unwrap_or_else(|_| "agent-mesh-dev-event-rules".to_string());
-        let subscriptions_table = std::env::var("AGENT_MESH_SUBSCRIPTIONS_TABLE")
-            .unwrap_or_else(|_| "agent-mesh-dev-subscriptions".to_string());
+        let events_table = self.events_table.clone();
+        let rules_table = self.event_rules_table.clone();
+        let subscriptions_table = self.subscriptions_table.clone();
 
         // Check events table - count user's events from last 24 hours
         let end_time = chrono::Utc::now();
         let start_time = end_time - chrono::Duration::hours(24);
+        let clients = self.clients_for(session).await?;
 
-        let events_result = self
-            .clients
+        let events_query = clients
             .dynamodb
             .query()
             .table_name(&events_table)
@@ -879,15 +2586,16 @@ impl AwsService {
                 ":end",
                 aws_sdk_dynamodb::types::AttributeValue::S(end_time.to_rfc3339()),
             )
-            .select(aws_sdk_dynamodb::types::Select::Count)
-            .send()
-            .await;
+            .select(aws_sdk_dynamodb::types::Select::Count);
 
+        let events_result = self
+            .call("dynamodb", "Query", &events_table)
+            .run(events_query.send())
+            .await;
         let events_count = events_result.map(|r| r.count()).unwrap_or(0);
 
         // Check rules table - count user's rules
-        let rules_result = self
-            .clients
+        let rules_query = clients
             .dynamodb
             .query()
             .table_name(&rules_table)
@@ -898,15 +2606,16 @@ impl AwsService {
                 ":userId",
                 aws_sdk_dynamodb::types::AttributeValue::S(session.context.user_id.clone()),
             )
-            .select(aws_sdk_dynamodb::types::Select::Count)
-            .send()
-            .await;
+            .select(aws_sdk_dynamodb::types::Select::Count);
 
+        let rules_result = self
+            .call("dynamodb", "Query", &rules_table)
+            .run(rules_query.send())
+            .await;
         let rules_count = rules_result.map(|r| r.count()).unwrap_or(0);
 
         // Check subscriptions table - count user's subscriptions
-        let subscriptions_result = self
-            .clients
+        let subscriptions_query = clients
             .dynamodb
             .query()
             .table_name(&subscriptions_table)
@@ -917,66 +2626,55 @@ impl AwsService {
                 ":userId",
                 aws_sdk_dynamodb::types::AttributeValue::S(session.context.user_id.clone()),
             )
-            .select(aws_sdk_dynamodb::types::Select::Count)
-            .send()
-            .await;
+            .select(aws_sdk_dynamodb::types::Select::Count);
 
+        let subscriptions_result = self
+            .call("dynamodb", "Query", &subscriptions_table)
+            .run(subscriptions_query.send())
+            .await;
         let subscriptions_count = subscriptions_result.map(|r| r.count()).unwrap_or(0);
 
-        // Determine overall health status
-        let status = if events_count > 0 || rules_count > 0 || subscriptions_count > 0 {
-            "healthy"
-        } else {
-            "idle" // No data yet, but system is operational
-        };
-
-        Ok(json!({
-            "status": status,
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-            "checks": {
-                "eventsTable": {
-                    "name": events_table,
-                    "count24h": events_count,
-                    "status": "ok"
-                },
-                "rulesTable": {
-                    "name": rules_table,
-                    "count": rules_count,
-                    "status": "ok"
-                },
-                "subscriptionsTable": {
-                    "name": subscriptions_table,
-                    "count": subscriptions_count,
-                    "status": "ok"
-                }
+        let mut report = events_health_report(
+            (&events_table, events_count),
+            (&rules_table, rules_count),
+            (&subscriptions_table, subscriptions_count),
+        );
+        // The startup resource check, if it ran
+        if let Some(resources) = &self.resource_report {
+            if !resources.is_ok() {
+                report["status"] = json!("degraded");
             }
-        }))
+            report["resources"] = json!(resources);
+        }
+        Ok(report)
     }
 
-    // Direct KV operations without session (for internal use)
+    // Direct KV operations without session (for internal use); keys are not prefixed
+    // with a tenant namespace, so they must be internal keys (see `internal_keys`)
     pub async fn kv_get_direct(&self, key: &str) -> Result<Option<String>, AwsError> {
-        let result = self
-            .clients
-            .dynamodb
-            .get_item()
-            .table_name(&self.kv_table)
-            .key(
-                "key",
-                aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
+        validate_direct_key(key)?;
+        let call = self.call("dynamodb", "GetItem", &self.kv_table).key(key);
+        let result = call
+            .run(
+                self.clients
+                    .dynamodb
+                    .get_item()
+                    .table_name(&self.kv_table)
+                    .key(
+                        "key",
+                        aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
+                    )
+                    .send(),
             )
-            .send()
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
+            .map_err(|e| sdk_error(e, "dynamodb_read", AwsError::DynamoDb))?;
+        call.record_size(stored_value_size(result.item.as_ref()));
 
-        if let Some(item) = result.item {
-            if let Some(value) = item.get("value") {
-                if let Ok(s_val) = value.as_s() {
-                    return Ok(Some(s_val.clone()));
-                }
-            }
+        // Sensitive items are decrypted under the tenant recorded when they were sealed
+        match result.item {
+            Some(item) => self.read_kv_value(item, None).await,
+            None => Ok(None),
         }
-
-        Ok(None)
     }
 
     pub async fn kv_set_direct(
@@ -985,84 +2683,100 @@ impl AwsService {
         value: &str,
         ttl_hours: Option<u32>,
     ) -> Result<(), AwsError> {
-        let now = chrono::Utc::now().timestamp();
-
-        // Prepare DynamoDB item
-        let mut put_request = self
-            .clients
-            .dynamodb
-            .put_item()
-            .table_name(&self.kv_table)
-            .item(
-                "key",
-                aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
-            )
-            .item(
-                "value",
-                aws_sdk_dynamodb::types::AttributeValue::S(value.to_string()),
-            )
-            .item(
-                "created_at",
-                aws_sdk_dynamodb::types::AttributeValue::N(now.to_string()),
-            );
-
-        if let Some(ttl) = ttl_hours {
-            let expiry = now + (ttl as i64 * 3600);
-            put_request = put_request.item(
-                "expires_at",
-                aws_sdk_dynamodb::types::AttributeValue::N(expiry.to_string()),
-            );
-        }
-
-        put_request
-            .send()
+        validate_direct_key(key)?;
+        self.put_kv_item(&self.clients, key.to_string(), value, ttl_hours, None)
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
-        Ok(())
     }
 
-    pub async fn kv_list(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
-        let result = self
-            .clients
-            .dynamodb
-            .scan()
-            .table_name(&self.kv_table)
-            .filter_expression("begins_with(#k, :prefix)")
-            .expression_attribute_names("#k", "key")
-            .expression_attribute_values(
-                ":prefix",
-                aws_sdk_dynamodb::types::AttributeValue::S(prefix.to_string()),
-            )
-            .send()
+    pub async fn kv_compare_and_swap_direct(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        validate_direct_key(key)?;
+        self.swap_kv_item(&self.clients, key, expected, value, ttl_hours)
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
+    }
+
+    /// Direct KV write that envelope-encrypts the value with `tenant_id`'s data key
+    pub async fn kv_set_direct_sensitive(
+        &self,
+        tenant_id: &str,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError> {
+        validate_direct_key(key)?;
+        self.put_kv_item(
+            &self.clients,
+            key.to_string(),
+            value,
+            ttl_hours,
+            Some(tenant_id),
+        )
+        .await
+    }
 
+    /// Keys starting with `prefix`. The table is keyed by `key` alone, so this is a
+    /// filtered Scan, followed page by page to the end.
+    pub async fn kv_list(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
+        validate_direct_key(prefix)?;
         let mut keys = Vec::new();
-        if let Some(items) = result.items {
-            for item in items {
-                if let Some(key_attr) = item.get("key") {
-                    if let Ok(key) = key_attr.as_s() {
-                        keys.push(key.clone());
-                    }
+        let mut start_key = None;
+        loop {
+            let scan = self
+                .clients
+                .dynamodb
+                .scan()
+                .table_name(&self.kv_table)
+                .filter_expression("begins_with(#k, :prefix)")
+                .expression_attribute_names("#k", "key")
+                .expression_attribute_values(
+                    ":prefix",
+                    aws_sdk_dynamodb::types::AttributeValue::S(prefix.to_string()),
+                )
+                .projection_expression("#k")
+                .limit(KV_LIST_PAGE_SIZE)
+                .set_exclusive_start_key(start_key);
+            let result = self
+                .call("dynamodb", "Scan", &self.kv_table)
+                .run(scan.send())
+                .await
+                .map_err(|e| sdk_error(e, "dynamodb_read", AwsError::DynamoDb))?;
+
+            for item in result.items.unwrap_or_default() {
+                if let Some(Ok(key)) = item.get("key").map(|key| key.as_s()) {
+                    keys.push(key.clone());
                 }
             }
+            start_key = result.last_evaluated_key.filter(|key| !key.is_empty());
+            if start_key.is_none() {
+                break;
+            }
         }
 
         Ok(keys)
     }
 
     pub async fn kv_delete(&self, key: &str) -> Result<(), AwsError> {
-        self.clients
-            .dynamodb
-            .delete_item()
-            .table_name(&self.kv_table)
-            .key(
-                "key",
-                aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
+        validate_direct_key(key)?;
+        self.call("dynamodb", "DeleteItem", &self.kv_table)
+            .key(key)
+            .run(
+                self.clients
+                    .dynamodb
+                    .delete_item()
+                    .table_name(&self.kv_table)
+                    .key(
+                        "key",
+                        aws_sdk_dynamodb::types::AttributeValue::S(key.to_string()),
+                    )
+                    .send(),
             )
-            .send()
             .await
-            .map_err(|e| AwsError::DynamoDb(e.to_string()))?;
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb))?;
 
         Ok(())
     }
@@ -1078,14 +2792,16 @@ impl AwsService {
         description: Option<&str>,
     ) -> Result<String, AwsError> {
         // Try to create the secret first
-        let create_result = self
+        let create_request = self
             .clients
             .secrets_manager
             .create_secret()
             .name(secret_name)
             .secret_string(secret_value)
-            .set_description(description.map(|s| s.to_string()))
-            .send()
+            .set_description(description.map(|s| s.to_string()));
+        let create_result = self
+            .call("secretsmanager", "CreateSecret", secret_name)
+            .run(create_request.send())
             .await;
 
         match create_result {
@@ -1096,15 +2812,18 @@ impl AwsService {
             }
             Err(e) => {
                 // If secret already exists, update it instead
-                let error_str = e.to_string();
-                if error_str.contains("ResourceExistsException") {
-                    let update_result = self
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_resource_exists_exception())
+                {
+                    let update_request = self
                         .clients
                         .secrets_manager
                         .put_secret_value()
                         .secret_id(secret_name)
-                        .secret_string(secret_value)
-                        .send()
+                        .secret_string(secret_value);
+                    let update_result = self
+                        .call("secretsmanager", "PutSecretValue", secret_name)
+                        .run(update_request.send())
                         .await
                         .map_err(|e| AwsError::SecretsManager(e.to_string()))?;
 
@@ -1119,21 +2838,111 @@ impl AwsService {
     }
 
     /// Retrieve a secret value from AWS Secrets Manager
-    #[allow(dead_code)]
     pub async fn secret_get(&self, secret_name: &str) -> Result<Option<String>, AwsError> {
-        let result = self
+        let request = self
             .clients
             .secrets_manager
             .get_secret_value()
-            .secret_id(secret_name)
-            .send()
+            .secret_id(secret_name);
+        let result = self
+            .call("secretsmanager", "GetSecretValue", secret_name)
+            .run(request.send())
             .await;
 
         match result {
             Ok(output) => Ok(output.secret_string().map(|s| s.to_string())),
             Err(e) => {
-                let error_str = e.to_string();
-                if error_str.contains("ResourceNotFoundException") {
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_resource_not_found_exception())
+                {
+                    Ok(None)
+                } else {
+                    Err(AwsError::SecretsManager(e.to_string()))
+                }
+            }
+        }
+    }
+
+    /// Names of the secrets starting with `prefix`
+    pub async fn secret_names(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
+        use aws_sdk_secretsmanager::types::{Filter, FilterNameStringType};
+
+        let mut names = Vec::new();
+        let mut next_token = None;
+        loop {
+            let request = self
+                .clients
+                .secrets_manager
+                .list_secrets()
+                .filters(
+                    Filter::builder()
+                        .key(FilterNameStringType::Name)
+                        .values(prefix)
+                        .build(),
+                )
+                .set_next_token(next_token);
+            let output = self
+                .call("secretsmanager", "ListSecrets", prefix)
+                .run(request.send())
+                .await
+                .map_err(|e| AwsError::SecretsManager(e.to_string()))?;
+
+            names.extend(
+                output
+                    .secret_list()
+                    .iter()
+                    .filter_map(|secret| secret.name())
+                    // The name filter matches words anywhere in the name; keep true prefixes
+                    .filter(|name| name.starts_with(prefix))
+                    .map(str::to_string),
+            );
+            next_token = output.next_token().map(str::to_string);
+            if next_token.is_none() {
+                return Ok(names);
+            }
+        }
+    }
+
+    /// Schedule a secret's deletion after `recovery_window_days` (7 to 30), during which
+    /// it can be restored. Returns when it will be deleted, or `None` if there's no such
+    /// secret.
+    pub async fn secret_schedule_deletion(
+        &self,
+        secret_name: &str,
+        recovery_window_days: i64,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>, AwsError> {
+        let request = self
+            .clients
+            .secrets_manager
+            .delete_secret()
+            .secret_id(secret_name)
+            .recovery_window_in_days(recovery_window_days);
+        let result = self
+            .call("secretsmanager", "DeleteSecret", secret_name)
+            .run(request.send())
+            .await;
+
+        match result {
+            Ok(output) => {
+                tracing::info!(
+                    "Scheduled deletion of secret {} in {} days",
+                    secret_name,
+                    recovery_window_days
+                );
+                let deletion_date = output
+                    .deletion_date()
+                    .and_then(|date| {
+                        chrono::DateTime::from_timestamp(date.secs(), date.subsec_nanos())
+                    })
+                    .unwrap_or_else(|| {
+                        chrono::Utc::now() + chrono::Duration::days(recovery_window_days)
+                    });
+                Ok(Some(deletion_date))
+            }
+            Err(e) => {
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_resource_not_found_exception())
+                {
                     Ok(None)
                 } else {
                     Err(AwsError::SecretsManager(e.to_string()))
@@ -1162,7 +2971,10 @@ impl AwsService {
             request = request.recovery_window_in_days(7);
         }
 
-        let result = request.send().await;
+        let result = self
+            .call("secretsmanager", "DeleteSecret", secret_name)
+            .run(request.send())
+            .await;
 
         match result {
             Ok(_) => {
@@ -1170,9 +2982,10 @@ impl AwsService {
                 Ok(())
             }
             Err(e) => {
-                let error_str = e.to_string();
                 // Ignore if secret doesn't exist
-                if error_str.contains("ResourceNotFoundException") {
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_resource_not_found_exception())
+                {
                     Ok(())
                 } else {
                     Err(AwsError::SecretsManager(e.to_string()))
@@ -1181,70 +2994,3219 @@ impl AwsService {
         }
     }
 
-    /// Store integration credentials securely in Secrets Manager
-    /// Creates a structured secret with all credential key-value pairs
-    pub async fn store_integration_credentials(
+    // Workflow operations
+
+    /// Start an execution of `state_machine_arn` called `name` with the JSON `input`;
+    /// returns the execution's ARN
+    pub async fn workflow_start(
         &self,
-        tenant_id: &str,
-        user_id: &str,
-        service_id: &str,
-        connection_id: &str,
-        credentials: &std::collections::HashMap<String, String>,
+        state_machine_arn: &str,
+        name: &str,
+        input: &str,
     ) -> Result<String, AwsError> {
-        let secret_name = format!(
-            "mcp-credentials/{}/{}/{}/{}",
-            tenant_id, user_id, service_id, connection_id
-        );
+        let request = self
+            .clients
+            .sfn
+            .start_execution()
+            .state_machine_arn(state_machine_arn)
+            .name(name)
+            .input(input);
+        let output = self
+            .call("sfn", "StartExecution", state_machine_arn)
+            .size(input.len())
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "aws_api", AwsError::StepFunctions))?;
+        tracing::info!("Started execution {}", output.execution_arn());
+        Ok(output.execution_arn().to_string())
+    }
 
-        let secret_value = serde_json::to_string(credentials).map_err(AwsError::Serialization)?;
+    /// The execution's status, and its output or error once it has finished
+    pub async fn workflow_describe(
+        &self,
+        execution_arn: &str,
+    ) -> Result<WorkflowExecution, AwsError> {
+        let request = self
+            .clients
+            .sfn
+            .describe_execution()
+            .execution_arn(execution_arn);
+        let output = self
+            .call("sfn", "DescribeExecution", execution_arn)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "aws_api", AwsError::StepFunctions))?;
+        Ok(WorkflowExecution {
+            execution_arn: output.execution_arn().to_string(),
+            state_machine_arn: output.state_machine_arn().to_string(),
+            status: output.status().as_str().to_string(),
+            start_date: sfn_date(output.start_date()),
+            stop_date: output.stop_date().map(sfn_date),
+            output: output.output().map(workflows::parse_output),
+            error: output.error().map(str::to_string),
+            cause: output.cause().map(str::to_string),
+        })
+    }
 
-        let description = format!(
-            "MCP integration credentials for service {} (user: {}, connection: {})",
-            service_id, user_id, connection_id
-        );
+    /// Stop a running execution; returns when it stopped
+    pub async fn workflow_stop(
+        &self,
+        execution_arn: &str,
+        error: Option<&str>,
+        cause: Option<&str>,
+    ) -> Result<chrono::DateTime<chrono::Utc>, AwsError> {
+        let request = self
+            .clients
+            .sfn
+            .stop_execution()
+            .execution_arn(execution_arn)
+            .set_error(error.map(str::to_string))
+            .set_cause(cause.map(str::to_string));
+        let output = self
+            .call("sfn", "StopExecution", execution_arn)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "aws_api", AwsError::StepFunctions))?;
+        tracing::info!("Stopped execution {}", execution_arn);
+        Ok(sfn_date(output.stop_date()))
+    }
 
-        self.secret_store(&secret_name, &secret_value, Some(&description))
+    // Queue operations
+
+    /// The queue's URL, looked up once per queue
+    async fn queue_url(&self, queue_name: &str) -> Result<String, AwsError> {
+        if let Some(url) = self.queue_urls.lock().unwrap().get(queue_name) {
+            return Ok(url.clone());
+        }
+        let output = self
+            .call("sqs", "GetQueueUrl", queue_name)
+            .run(
+                self.clients
+                    .sqs
+                    .get_queue_url()
+                    .queue_name(queue_name)
+                    .send(),
+            )
             .await
+            .map_err(|e| sdk_error(e, "sqs_receive", AwsError::Sqs))?;
+        let url = output
+            .queue_url()
+            .ok_or_else(|| AwsError::Sqs(format!("no URL returned for queue {}", queue_name)))?
+            .to_string();
+        self.queue_urls
+            .lock()
+            .unwrap()
+            .insert(queue_name.to_string(), url.clone());
+        Ok(url)
     }
 
-    /// Retrieve integration credentials from Secrets Manager
-    #[allow(dead_code)]
-    pub async fn get_integration_credentials(
+    /// Send a message with string `attributes`, delivered after `delay_seconds` (or the
+    /// queue's delay); returns its message id
+    pub async fn queue_send(
         &self,
-        tenant_id: &str,
-        user_id: &str,
-        service_id: &str,
-        connection_id: &str,
-    ) -> Result<Option<std::collections::HashMap<String, String>>, AwsError> {
-        let secret_name = format!(
-            "mcp-credentials/{}/{}/{}/{}",
-            tenant_id, user_id, service_id, connection_id
-        );
+        queue_name: &str,
+        body: &str,
+        attributes: &HashMap<String, String>,
+        delay_seconds: Option<i32>,
+    ) -> Result<String, AwsError> {
+        use aws_sdk_sqs::types::MessageAttributeValue;
 
-        match self.secret_get(&secret_name).await? {
-            Some(secret_value) => {
-                let credentials: std::collections::HashMap<String, String> =
-                    serde_json::from_str(&secret_value).map_err(AwsError::Serialization)?;
-                Ok(Some(credentials))
-            }
-            None => Ok(None),
+        let mut request = self
+            .clients
+            .sqs
+            .send_message()
+            .queue_url(self.queue_url(queue_name).await?)
+            .message_body(body)
+            .set_delay_seconds(delay_seconds);
+        for (name, value) in attributes {
+            let value = MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value(value)
+                .build()
+                .map_err(|e| AwsError::Sqs(e.to_string()))?;
+            request = request.message_attributes(name, value);
         }
+        let output = self
+            .call("sqs", "SendMessage", queue_name)
+            .size(body.len())
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "sqs_send", AwsError::Sqs))?;
+        Ok(output.message_id().unwrap_or_default().to_string())
     }
 
-    /// Delete integration credentials from Secrets Manager
-    pub async fn delete_integration_credentials(
+    /// Up to `max_messages` messages, waiting up to `wait_seconds` for the first one;
+    /// they're hidden from other receivers for `visibility_timeout` seconds (or the
+    /// queue's timeout). Empty when no message arrived in time.
+    pub async fn queue_receive(
         &self,
-        tenant_id: &str,
-        user_id: &str,
-        service_id: &str,
-        connection_id: &str,
-        force_delete: bool,
+        queue_name: &str,
+        max_messages: i32,
+        wait_seconds: i32,
+        visibility_timeout: Option<i32>,
+    ) -> Result<Vec<QueueMessage>, AwsError> {
+        use aws_sdk_sqs::types::MessageSystemAttributeName;
+
+        let request = self
+            .clients
+            .sqs
+            .receive_message()
+            .queue_url(self.queue_url(queue_name).await?)
+            .max_number_of_messages(max_messages)
+            .wait_time_seconds(wait_seconds)
+            .set_visibility_timeout(visibility_timeout)
+            .message_attribute_names("All")
+            .message_system_attribute_names(MessageSystemAttributeName::ApproximateReceiveCount);
+        let output = self
+            .call("sqs", "ReceiveMessage", queue_name)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "sqs_receive", AwsError::Sqs))?;
+
+        Ok(output
+            .messages()
+            .iter()
+            .map(|message| QueueMessage {
+                message_id: message.message_id().unwrap_or_default().to_string(),
+                receipt_handle: message.receipt_handle().unwrap_or_default().to_string(),
+                body: message.body().unwrap_or_default().to_string(),
+                attributes: message
+                    .message_attributes()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(name, value)| {
+                        Some((name.clone(), value.string_value()?.to_string()))
+                    })
+                    .collect(),
+                receive_count: message
+                    .attributes()
+                    .and_then(|attributes| {
+                        attributes.get(&MessageSystemAttributeName::ApproximateReceiveCount)
+                    })
+                    .and_then(|count| count.parse().ok())
+                    .unwrap_or(1),
+            })
+            .collect())
+    }
+
+    /// Delete a received message; `NotFound` if the receipt handle is no longer valid
+    pub async fn queue_delete(
+        &self,
+        queue_name: &str,
+        receipt_handle: &str,
+    ) -> Result<(), AwsError> {
+        let request = self
+            .clients
+            .sqs
+            .delete_message()
+            .queue_url(self.queue_url(queue_name).await?)
+            .receipt_handle(receipt_handle);
+        self.call("sqs", "DeleteMessage", queue_name)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "sqs_receive", AwsError::Sqs))?;
+        Ok(())
+    }
+
+    /// Make a received message visible again after `visibility_timeout` seconds (0 for
+    /// at once); `NotFound` if it isn't in flight under this receipt handle
+    pub async fn queue_change_visibility(
+        &self,
+        queue_name: &str,
+        receipt_handle: &str,
+        visibility_timeout: i32,
+    ) -> Result<(), AwsError> {
+        let request = self
+            .clients
+            .sqs
+            .change_message_visibility()
+            .queue_url(self.queue_url(queue_name).await?)
+            .receipt_handle(receipt_handle)
+            .visibility_timeout(visibility_timeout);
+        self.call("sqs", "ChangeMessageVisibility", queue_name)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "sqs_receive", AwsError::Sqs))?;
+        Ok(())
+    }
+
+    // Alert operations
+
+    /// Whether the SNS topic exists (and the server may read its attributes)
+    pub async fn sns_topic_exists(&self, topic_arn: &str) -> Result<bool, AwsError> {
+        let request = self.clients.sns.get_topic_attributes().topic_arn(topic_arn);
+        match self
+            .call("sns", "GetTopicAttributes", topic_arn)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "aws_api", AwsError::Sns))
+        {
+            Ok(_) => Ok(true),
+            Err(AwsError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Publish `message` to the topic with string message attributes; returns the
+    /// message id
+    #[allow(dead_code)]
+    pub async fn sns_publish(
+        &self,
+        topic_arn: &str,
+        message: &str,
+        attributes: &HashMap<String, String>,
+    ) -> Result<String, AwsError> {
+        let mut request = self
+            .clients
+            .sns
+            .publish()
+            .topic_arn(topic_arn)
+            .message(message);
+        for (name, value) in attributes {
+            let attribute = aws_sdk_sns::types::MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value(value)
+                .build()
+                .map_err(|e| AwsError::Sns(e.to_string()))?;
+            request = request.message_attributes(name, attribute);
+        }
+        let output = self
+            .call("sns", "Publish", topic_arn)
+            .size(message.len())
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "aws_api", AwsError::Sns))?;
+        Ok(output.message_id().unwrap_or_default().to_string())
+    }
+
+    /// Record why the subscription's latest alert couldn't be delivered
+    #[allow(dead_code)]
+    pub async fn record_alert_error(
+        &self,
+        session: &TenantSession,
+        subscription_id: &str,
+        error: &str,
     ) -> Result<(), AwsError> {
-        let secret_name = format!(
-            "mcp-credentials/{}/{}/{}/{}",
-            tenant_id, user_id, service_id, connection_id
+        let table = self.subscriptions_table.clone();
+        let request = self
+            .clients_for(session)
+            .await?
+            .dynamodb
+            .update_item()
+            .table_name(&table)
+            .key(
+                "subscriptionId",
+                aws_sdk_dynamodb::types::AttributeValue::S(subscription_id.to_string()),
+            )
+            .update_expression("SET lastError = :error, lastErrorAt = :at")
+            .condition_expression("attribute_exists(subscriptionId)")
+            .expression_attribute_values(
+                ":error",
+                aws_sdk_dynamodb::types::AttributeValue::S(error.to_string()),
+            )
+            .expression_attribute_values(
+                ":at",
+                aws_sdk_dynamodb::types::AttributeValue::S(chrono::Utc::now().to_rfc3339()),
+            );
+        let result = self
+            .call("dynamodb", "UpdateItem", &table)
+            .key(subscription_id)
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb));
+        match result {
+            Ok(_) => Ok(()),
+            Err(AwsError::ConditionFailed(_)) => Err(AwsError::NotFound(format!(
+                "subscription {:?}",
+                subscription_id
+            ))),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Size of a KV item's stored value, for its span's size class
+fn stored_value_size(
+    item: Option<&HashMap<String, aws_sdk_dynamodb::types::AttributeValue>>,
+) -> usize {
+    item.and_then(|item| item.get("value"))
+        .and_then(|value| value.as_s().ok())
+        .map_or(0, String::len)
+}
+
+/// The tables a transaction writes to, comma-separated in first-use order
+fn transact_tables(items: &[aws_sdk_dynamodb::types::TransactWriteItem]) -> String {
+    let mut tables: Vec<&str> = Vec::new();
+    for item in items {
+        let table = item
+            .put()
+            .map(|put| put.table_name())
+            .or_else(|| item.update().map(|update| update.table_name()))
+            .or_else(|| item.delete().map(|delete| delete.table_name()))
+            .or_else(|| item.condition_check().map(|check| check.table_name()));
+        if let Some(table) = table.filter(|table| !tables.contains(table)) {
+            tables.push(table);
+        }
+    }
+    tables.join(",")
+}
+
+/// A new event rule's DynamoDB item, and the rule as returned to the caller
+fn event_rule_item(
+    session: &TenantSession,
+    name: &str,
+    pattern: Value,
+    description: Option<String>,
+    enabled: bool,
+) -> Result<
+    (
+        HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+        Value,
+    ),
+    AwsError,
+> {
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    // Generate unique rule ID
+    let rule_id = format!("rule-{}-{}", session.context.user_id, uuid::Uuid::new_v4());
+    let timestamp = chrono::Utc::now().to_rfc3339();
+
+    let mut item = HashMap::from([
+        ("ruleId".to_string(), AttributeValue::S(rule_id.clone())),
+        (
+            "userId".to_string(),
+            AttributeValue::S(session.context.user_id.clone()),
+        ),
+        (
+            "organizationId".to_string(),
+            AttributeValue::S(session.context.organization_id.clone()),
+        ),
+        ("name".to_string(), AttributeValue::S(name.to_string())),
+        (
+            "pattern".to_string(),
+            AttributeValue::S(serde_json::to_string(&pattern)?),
+        ),
+        ("enabled".to_string(), AttributeValue::Bool(enabled)),
+        (
+            "createdAt".to_string(),
+            AttributeValue::S(timestamp.clone()),
+        ),
+        (
+            "updatedAt".to_string(),
+            AttributeValue::S(timestamp.clone()),
+        ),
+    ]);
+    if let Some(desc) = description.as_ref() {
+        item.insert("description".to_string(), AttributeValue::S(desc.clone()));
+    }
+
+    let rule = json!({
+        "ruleId": rule_id,
+        "name": name,
+        "pattern": pattern,
+        "description": description,
+        "enabled": enabled,
+        "createdAt": timestamp
+    });
+    Ok((item, rule))
+}
+
+/// A new alert subscription's DynamoDB item, and the subscription as returned to the
+/// caller
+fn alert_subscription_item(
+    session: &TenantSession,
+    rule_id: &str,
+    alert: &NewAlertSubscription,
+) -> (
+    HashMap<String, aws_sdk_dynamodb::types::AttributeValue>,
+    Value,
+) {
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    // Generate unique subscription ID
+    let subscription_id = format!("sub-{}-{}", session.context.user_id, uuid::Uuid::new_v4());
+    let timestamp = chrono::Utc::now().to_rfc3339();
+
+    let mut item = HashMap::from([
+        (
+            "subscriptionId".to_string(),
+            AttributeValue::S(subscription_id.clone()),
+        ),
+        (
+            "userId".to_string(),
+            AttributeValue::S(session.context.user_id.clone()),
+        ),
+        (
+            "organizationId".to_string(),
+            AttributeValue::S(session.context.organization_id.clone()),
+        ),
+        ("name".to_string(), AttributeValue::S(alert.name.clone())),
+        ("ruleId".to_string(), AttributeValue::S(rule_id.to_string())),
+        (
+            "notificationMethod".to_string(),
+            AttributeValue::S(alert.notification_method.clone()),
+        ),
+        ("enabled".to_string(), AttributeValue::Bool(alert.enabled)),
+        (
+            "createdAt".to_string(),
+            AttributeValue::S(timestamp.clone()),
+        ),
+        (
+            "updatedAt".to_string(),
+            AttributeValue::S(timestamp.clone()),
+        ),
+    ]);
+    if let Some(arn) = alert.sns_topic_arn.as_ref() {
+        item.insert("snsTopicArn".to_string(), AttributeValue::S(arn.clone()));
+    }
+    if let Some(email) = alert.email_address.as_ref() {
+        item.insert("emailAddress".to_string(), AttributeValue::S(email.clone()));
+    }
+
+    let subscription = json!({
+        "subscriptionId": subscription_id,
+        "name": alert.name,
+        "ruleId": rule_id,
+        "notificationMethod": alert.notification_method,
+        "snsTopicArn": alert.sns_topic_arn,
+        "emailAddress": alert.email_address,
+        "enabled": alert.enabled,
+        "createdAt": timestamp
+    });
+    (item, subscription)
+}
+
+/// A KV transaction operation as a `TransactWriteItems` entry on `table`, with the key
+/// already namespaced
+fn kv_transact_item(
+    table: &str,
+    op: &KvWriteOp,
+    tenant_key: String,
+) -> Result<aws_sdk_dynamodb::types::TransactWriteItem, AwsError> {
+    use aws_sdk_dynamodb::types::{
+        AttributeValue, ConditionCheck, Delete, Put, TransactWriteItem, Update,
+    };
+
+    let key = HashMap::from([("key".to_string(), AttributeValue::S(tenant_key))]);
+    let condition = |condition: Option<ItemCondition>| {
+        condition.map(|condition| condition.expression().to_string())
+    };
+    let key_name = || condition_names(true);
+    let build_error = |e: aws_sdk_dynamodb::error::BuildError| AwsError::DynamoDb(e.to_string());
+
+    let item = match op {
+        KvWriteOp::Put {
+            value,
+            ttl_hours,
+            condition: put_condition,
+            ..
+        } => {
+            let now = chrono::Utc::now().timestamp();
+            let mut item = key;
+            item.insert("value".to_string(), AttributeValue::S(value.clone()));
+            item.insert("created_at".to_string(), AttributeValue::N(now.to_string()));
+            if let Some(ttl) = ttl_hours {
+                let expiry = now + (*ttl as i64 * 3600);
+                item.insert(
+                    "expires_at".to_string(),
+                    AttributeValue::N(expiry.to_string()),
+                );
+            }
+            let put = Put::builder()
+                .table_name(table)
+                .set_item(Some(item))
+                .set_condition_expression(condition(*put_condition))
+                .set_expression_attribute_names(condition_names(put_condition.is_some()))
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().put(put).build()
+        }
+        KvWriteOp::Update { value, .. } => {
+            let update = Update::builder()
+                .table_name(table)
+                .set_key(Some(key))
+                .update_expression("SET #value = :value")
+                .condition_expression(ItemCondition::Exists.expression())
+                .set_expression_attribute_names(key_name())
+                .expression_attribute_names("#value", "value")
+                .expression_attribute_values(":value", AttributeValue::S(value.clone()))
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().update(update).build()
+        }
+        KvWriteOp::Delete {
+            condition: delete_condition,
+            ..
+        } => {
+            let delete = Delete::builder()
+                .table_name(table)
+                .set_key(Some(key))
+                .set_condition_expression(condition(*delete_condition))
+                .set_expression_attribute_names(condition_names(delete_condition.is_some()))
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().delete(delete).build()
+        }
+        KvWriteOp::ConditionCheck {
+            condition: check, ..
+        } => {
+            let check = ConditionCheck::builder()
+                .table_name(table)
+                .set_key(Some(key))
+                .condition_expression(check.expression())
+                .set_expression_attribute_names(key_name())
+                .build()
+                .map_err(build_error)?;
+            TransactWriteItem::builder().condition_check(check).build()
+        }
+    };
+    Ok(item)
+}
+
+/// The `#key` placeholder for `key`, when a condition uses it
+fn condition_names(has_condition: bool) -> Option<HashMap<String, String>> {
+    has_condition.then(|| HashMap::from([("#key".to_string(), "key".to_string())]))
+}
+
+/// Convert a `TransactWriteItems` error. A canceled transaction lists which of
+/// `operations` failed and why; anything else is converted as by `sdk_error`.
+fn transaction_error<R>(
+    err: SdkError<TransactWriteItemsError, R>,
+    operations: &[String],
+) -> AwsError {
+    if let Some(TransactWriteItemsError::TransactionCanceledException(canceled)) =
+        err.as_service_error()
+    {
+        return AwsError::TransactionCanceled(transactions::cancellation_failures(
+            canceled.cancellation_reasons(),
+            operations,
+        ));
+    }
+    sdk_error(err, "dynamodb_write", AwsError::DynamoDb)
+}
+
+/// A Step Functions timestamp as a UTC date
+fn sfn_date(date: &aws_sdk_sfn::primitives::DateTime) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::from_timestamp(date.secs(), date.subsec_nanos()).unwrap_or_default()
+}
+
+/// Prefix of the secret ids the secret tools use; the session's namespace follows
+const TENANT_SECRET_PREFIX: &str = "mcp";
+
+/// Secret id for the session's secret `name`: `mcp/{namespace}/{name}`, with the
+/// namespace's `:` separators written as `/` (Secrets Manager names can't contain `:`),
+/// e.g. `mcp/user/alice/openai` or `mcp/org/acme/user/alice/openai`. Names can't contain
+/// `/`, so a tenant can't reach outside its namespace (or into the registry's
+/// `mcp/{tenant}/{server}/{field}` credentials).
+pub fn tenant_secret_id(session: &TenantSession, name: &str) -> Result<String, AwsError> {
+    let valid = (1..=128).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_+=.@-".contains(c));
+    if !valid {
+        return Err(AwsError::InvalidSecretName(name.to_string()));
+    }
+    Ok(format!("{}{}", tenant_secret_prefix(session), name))
+}
+
+/// Start of every secret id of the session's (see [`tenant_secret_id`]), ending in `/`
+pub fn tenant_secret_prefix(session: &TenantSession) -> String {
+    format!(
+        "{}/{}/",
+        TENANT_SECRET_PREFIX,
+        session.context.get_namespace_prefix().replace(':', "/")
+    )
+}
+
+/// Cache and report scope of `analytics_query`: the organization, else the user
+pub(crate) fn analytics_scope(
+    session: &TenantSession,
+    user_id: Option<&str>,
+    organization_id: Option<&str>,
+) -> String {
+    match (organization_id, user_id) {
+        (Some(org_id), _) => format!("org-{}", org_id),
+        (None, Some(uid)) => format!("user-{}", uid),
+        (None, None) => format!("user-{}", session.context.user_id),
+    }
+}
+
+/// `analytics_query`'s window; the default is the 24 hours up to now
+pub(crate) fn analytics_window(
+    start_time: Option<String>,
+    end_time: Option<String>,
+) -> Result<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>), AwsError> {
+    let end_dt = if let Some(et) = end_time {
+        chrono::DateTime::parse_from_rfc3339(&et)
+            .map_err(|e| AwsError::Config(format!("Invalid endTime: {}", e)))?
+            .with_timezone(&chrono::Utc)
+    } else {
+        chrono::Utc::now()
+    };
+
+    let start_dt = if let Some(st) = start_time {
+        chrono::DateTime::parse_from_rfc3339(&st)
+            .map_err(|e| AwsError::Config(format!("Invalid startTime: {}", e)))?
+            .with_timezone(&chrono::Utc)
+    } else {
+        end_dt - chrono::Duration::hours(24)
+    };
+    Ok((start_dt, end_dt))
+}
+
+/// Event tallies behind `analytics_query`
+#[derive(Default)]
+pub(crate) struct EventCounts {
+    volume_buckets: HashMap<String, i32>,
+    source_counts: HashMap<String, i32>,
+    priority_counts: HashMap<String, i32>,
+    event_type_counts: HashMap<String, i32>,
+}
+
+impl EventCounts {
+    /// Count one event from its stored attributes
+    pub(crate) fn add(
+        &mut self,
+        timestamp: Option<&str>,
+        source: Option<&str>,
+        priority: Option<&str>,
+        detail_type: Option<&str>,
+        granularity: &str,
+    ) {
+        // Extract timestamp for volume buckets
+        if let Some(ts) = timestamp.and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok()) {
+            let bucket_key = if granularity == "hourly" {
+                ts.format("%Y-%m-%d %H:00").to_string()
+            } else {
+                ts.format("%Y-%m-%d").to_string()
+            };
+            *self.volume_buckets.entry(bucket_key).or_insert(0) += 1;
+        }
+        for (value, counts) in [
+            (source, &mut self.source_counts),
+            (priority, &mut self.priority_counts),
+            (detail_type, &mut self.event_type_counts),
+        ] {
+            if let Some(value) = value {
+                *counts.entry(value.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+/// The `analytics_query` response for `counts`, with only the requested metrics
+pub(crate) fn analytics_response(
+    scope: String,
+    start_dt: chrono::DateTime<chrono::Utc>,
+    end_dt: chrono::DateTime<chrono::Utc>,
+    counts: EventCounts,
+    metrics: &[String],
+    granularity: &str,
+) -> Value {
+    let requested = |metric: &str| metrics.iter().any(|m| m == metric);
+    let mut analytics = serde_json::Map::new();
+
+    if requested("volume") {
+        let mut buckets: Vec<_> = counts
+            .volume_buckets
+            .into_iter()
+            .map(|(bucket, count)| json!({ "bucket": bucket, "count": count }))
+            .collect();
+        buckets.sort_by(|a, b| a["bucket"].as_str().cmp(&b["bucket"].as_str()));
+        analytics.insert(
+            "volume".to_string(),
+            json!({
+                "granularity": granularity,
+                "buckets": buckets
+            }),
+        );
+    }
+
+    if requested("topSources") {
+        let mut sources: Vec<_> = counts.source_counts.into_iter().collect();
+        sources.sort_by_key(|s| std::cmp::Reverse(s.1)); // Descending by count
+        let top_sources: Vec<_> = sources
+            .into_iter()
+            .map(|(source, count)| json!({ "source": source, "count": count }))
+            .collect();
+        analytics.insert("topSources".to_string(), json!(top_sources));
+    }
+
+    if requested("priority") {
+        let priority_counts = &counts.priority_counts;
+        analytics.insert(
+            "priority".to_string(),
+            json!({
+                "low": priority_counts.get("low").unwrap_or(&0),
+                "medium": priority_counts.get("medium").unwrap_or(&0),
+                "high": priority_counts.get("high").unwrap_or(&0),
+                "critical": priority_counts.get("critical").unwrap_or(&0)
+            }),
         );
+    }
+
+    if requested("eventTypes") {
+        let mut types: Vec<_> = counts.event_type_counts.into_iter().collect();
+        types.sort_by_key(|t| std::cmp::Reverse(t.1)); // Descending by count
+        let event_types: Vec<_> = types
+            .into_iter()
+            .map(|(event_type, count)| json!({ "eventType": event_type, "count": count }))
+            .collect();
+        analytics.insert("eventTypes".to_string(), json!(event_types));
+    }
+
+    json!({
+        "scope": scope,
+        "startTime": start_dt.to_rfc3339(),
+        "endTime": end_dt.to_rfc3339(),
+        "analytics": analytics,
+        "cached": false
+    })
+}
+
+/// The `events_health_check` response from each table's name and the caller's item count
+pub(crate) fn events_health_report(
+    events: (&str, i32),
+    rules: (&str, i32),
+    subscriptions: (&str, i32),
+) -> Value {
+    // Determine overall health status
+    let status = if events.1 > 0 || rules.1 > 0 || subscriptions.1 > 0 {
+        "healthy"
+    } else {
+        "idle" // No data yet, but system is operational
+    };
+
+    json!({
+        "status": status,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "checks": {
+            "eventsTable": {
+                "name": events.0,
+                "count24h": events.1,
+                "status": "ok"
+            },
+            "rulesTable": {
+                "name": rules.0,
+                "count": rules.1,
+                "status": "ok"
+            },
+            "subscriptionsTable": {
+                "name": subscriptions.0,
+                "count": subscriptions.1,
+                "status": "ok"
+            }
+        }
+    })
+}
 
-        self.secret_delete(&secret_name, force_delete).await
+/// Region used when neither `AWS_REGION` nor `AWS_DEFAULT_REGION` is set
+pub const DEFAULT_REGION: &str = "us-west-2";
+
+/// Which [`AwsApi`] backend the server runs on: `aws` (the default) or `memory`
+pub const BACKEND_ENV: &str = "MCP_BACKEND";
+
+/// The configured backend. `memory` needs no AWS access at all, for demos and offline
+/// development; its data is gone when the process exits.
+pub async fn backend_from_config(config: &AwsConfig) -> Result<Arc<dyn AwsApi>, AwsError> {
+    match config.backend {
+        Backend::Memory => {
+            eprintln!("[MCP Server] Using the in-memory backend; nothing is persisted");
+            let backend = MemoryAwsService::new()
+                .with_artifacts_bucket(&config.resource_names.artifacts_bucket);
+            Ok(Arc::new(match config.kv_cache() {
+                Some(cache) => backend.with_kv_cache(cache),
+                None => backend,
+            }))
+        }
+        Backend::Aws => Ok(Arc::new(AwsService::from_config(config).await?)),
+    }
+}
+
+/// Everything the built-in handlers and the MCP server registry need from storage and
+/// messaging: tenant KV, artifacts, events and analytics, secrets, workflows, queues and
+/// alert delivery.
+/// `AwsService` implements it over AWS; `MemoryAwsService` keeps everything in process.
+#[async_trait]
+pub trait AwsApi: DirectKvStore {
+    /// Bucket holding the tenants' artifacts
+    fn artifacts_bucket(&self) -> &str;
+
+    /// Prefix of the tenants' SQS queue names (see [`queues::tenant_queue_name`])
+    fn queue_prefix(&self) -> &str {
+        queues::DEFAULT_QUEUE_PREFIX
+    }
+
+    /// Whether an alert's SNS topic must exist to be subscribed to, rather than only
+    /// be a valid ARN
+    fn verifies_alert_topics(&self) -> bool {
+        false
+    }
+
+    /// Lambda client for the default region, or `None` when the backend can't invoke
+    /// functions
+    fn lambda_client(&self) -> Option<LambdaClient>;
+
+    /// CloudWatch client for publishing server metrics, when enabled
+    fn cloudwatch_client(&self) -> Option<CloudWatchClient> {
+        None
+    }
+
+    async fn kv_get(&self, session: &TenantSession, key: &str) -> Result<Option<String>, AwsError>;
+
+    /// `kv_get` bypassing the read cache; backends without one just read
+    async fn kv_get_fresh(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<String>, AwsError> {
+        self.kv_get(session, key).await
+    }
+
+    /// Hit and miss counts of the `kv_get` read cache, when the backend has one enabled
+    fn kv_cache_stats(&self) -> Option<KvCacheStats> {
+        None
+    }
+
+    /// Latency percentiles of the AWS calls made so far, by service, when the backend
+    /// calls AWS
+    fn latency_stats(&self) -> Option<BTreeMap<String, LatencyStats>> {
+        None
+    }
+
+    /// Tenants whose own AWS role couldn't be assumed the last time it was tried, with
+    /// why, for the `health` tool
+    fn degraded_tenants(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    /// Whether the backend answers at all, for the `health` tool
+    async fn ping(&self) -> Result<(), AwsError>;
+
+    async fn kv_set(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+        sensitive: bool,
+    ) -> Result<(), AwsError>;
+
+    /// Store an artifact; with a `ttl_class` it's tagged for the bucket's lifecycle rules
+    async fn artifacts_put(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        content: &[u8],
+        content_type: &str,
+        ttl_class: Option<TtlClass>,
+    ) -> Result<(), AwsError>;
+
+    async fn artifacts_get(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, AwsError>;
+
+    /// An artifact's metadata and TTL class, `None` if there's no such artifact
+    async fn artifacts_head(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<ArtifactMetadata>, AwsError>;
+
+    async fn artifacts_list(
+        &self,
+        session: &TenantSession,
+        prefix: Option<&str>,
+    ) -> Result<Vec<String>, AwsError>;
+
+    /// A page of up to `limit` of the session's artifact keys, relative to the namespace,
+    /// each listed once, and the position the next page starts from; `start` is a
+    /// position an earlier page returned
+    async fn artifacts_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError>;
+
+    /// Install the lifecycle rules expiring the session's tenant's artifacts by TTL
+    /// class; returns the tenant's rules
+    async fn configure_artifact_lifecycle(
+        &self,
+        session: &TenantSession,
+        retention: &RetentionDays,
+    ) -> Result<Vec<ArtifactLifecycleRule>, AwsError>;
+
+    /// Delete the session's artifacts whose keys start with `prefix`; with `dry_run`,
+    /// only report what would be deleted
+    async fn artifacts_delete_prefix(
+        &self,
+        session: &TenantSession,
+        prefix: &str,
+        dry_run: bool,
+    ) -> Result<ArtifactDeletion, AwsError>;
+
+    /// Move the session's artifacts from their legacy keys under its namespace
+    async fn migrate_artifacts(
+        &self,
+        session: &TenantSession,
+    ) -> Result<ArtifactMigration, AwsError>;
+
+    /// Read an object outside the tenant namespaces, e.g. server-wide configuration
+    async fn s3_get(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, AwsError>;
+
+    async fn send_event(
+        &self,
+        session: &TenantSession,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<(), AwsError>;
+
+    /// Publish `events` as `(detailType, detail)` in one batch of at most
+    /// [`event_buffer::EVENT_BATCH_SIZE`], unbuffered. Returns each event's error, in
+    /// order (`None` once published); `Err` if the batch couldn't be sent at all.
+    async fn send_events(
+        &self,
+        session: &TenantSession,
+        events: &[(String, Value)],
+    ) -> Result<Vec<Option<String>>, AwsError>;
+
+    /// Send an event through the write-behind buffer, returning its provisional id.
+    /// Backends without a buffer send it at once and return `None`.
+    async fn send_event_buffered(
+        &self,
+        session: &TenantSession,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<Option<String>, AwsError> {
+        self.send_event(session, detail_type, detail).await?;
+        Ok(None)
+    }
+
+    /// Whether `events_send` buffers events when the call doesn't say
+    fn buffers_events_by_default(&self) -> bool {
+        false
+    }
+
+    /// Send every buffered event; called on shutdown
+    async fn flush_events(&self) {}
+
+    /// Publish a server-level event that isn't tied to a tenant session
+    async fn send_system_event(&self, detail_type: &str, detail: Value) -> Result<(), AwsError>;
+
+    /// Events from the session's tenant's event history. With a `scope`, only the events
+    /// it contains, and `user_id` and `organization_id` are ignored; without one the
+    /// query requires `user_id` or `source`. `link_id` keeps the events of one
+    /// `events_send_linked` call, which the event store copies onto their records like
+    /// `namespace`.
+    #[allow(clippy::too_many_arguments)]
+    async fn query_events(
+        &self,
+        session: &TenantSession,
+        scope: Option<&EventScope>,
+        user_id: Option<String>,
+        organization_id: Option<String>,
+        source: Option<String>,
+        detail_type: Option<String>,
+        priority: Option<String>,
+        link_id: Option<String>,
+        start_time: Option<String>,
+        end_time: Option<String>,
+        limit: i32,
+        exclusive_start_key: Option<String>,
+        ascending: bool,
+    ) -> Result<Value, AwsError>;
+
+    /// `query_events` with how it's answered as `explain`: the index queries planned
+    /// for it and, unless `explain_only`, what running them read. Backends that don't
+    /// run the plan on DynamoDB describe it without read statistics, and with
+    /// `explain_only` don't run the query at all.
+    async fn query_events_explained(
+        &self,
+        session: &TenantSession,
+        query: &EventQuery,
+        explain_only: bool,
+    ) -> Result<Value, AwsError> {
+        let plan = EventQueryPlan::new(query)?;
+        if explain_only {
+            return Ok(json!({"explain": plan.explain(None)}));
+        }
+        let mut result = self
+            .query_events(
+                session,
+                query.scope.as_ref(),
+                query.user_id.clone(),
+                query.organization_id.clone(),
+                query.source.clone(),
+                query.detail_type.clone(),
+                query.priority.clone(),
+                query.link_id.clone(),
+                query.start_time.clone(),
+                query.end_time.clone(),
+                query.limit,
+                query.exclusive_start_key.clone(),
+                query.ascending,
+            )
+            .await?;
+        result["explain"] = plan.explain(None);
+        Ok(result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn analytics_query(
+        &self,
+        session: &TenantSession,
+        user_id: Option<String>,
+        organization_id: Option<String>,
+        start_time: Option<String>,
+        end_time: Option<String>,
+        metrics: Vec<String>,
+        granularity: String,
+    ) -> Result<Value, AwsError>;
+
+    async fn create_event_rule(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        pattern: Value,
+        description: Option<String>,
+        enabled: bool,
+    ) -> Result<Value, AwsError>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create_alert_subscription(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        rule_id: &str,
+        notification_method: &str,
+        sns_topic_arn: Option<String>,
+        email_address: Option<String>,
+        enabled: bool,
+    ) -> Result<Value, AwsError>;
+
+    async fn events_health_check(&self, session: &TenantSession) -> Result<Value, AwsError>;
+
+    /// Create or update a secret; returns its ARN (or name)
+    async fn secret_store(
+        &self,
+        secret_name: &str,
+        secret_value: &str,
+        description: Option<&str>,
+    ) -> Result<String, AwsError>;
+
+    async fn secret_get(&self, secret_name: &str) -> Result<Option<String>, AwsError>;
+
+    /// Names of the secrets starting with `prefix`
+    async fn secret_names(&self, prefix: &str) -> Result<Vec<String>, AwsError>;
+
+    async fn secret_delete(&self, secret_name: &str, force_delete: bool) -> Result<(), AwsError>;
+
+    /// Schedule a secret's deletion after a recovery window of 7 to 30 days; returns when
+    /// it will be deleted, or `None` if there's no such secret
+    async fn secret_schedule_deletion(
+        &self,
+        secret_name: &str,
+        recovery_window_days: i64,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>, AwsError>;
+
+    /// Create or update the session's secret `name` (see [`tenant_secret_id`])
+    async fn tenant_secret_put(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        value: &str,
+    ) -> Result<(), AwsError> {
+        let secret_id = tenant_secret_id(session, name)?;
+        self.secret_store(&secret_id, value, Some("Stored with the secret_put tool"))
+            .await?;
+        Ok(())
+    }
+
+    /// The session's secret `name`, or `NotFound`
+    async fn tenant_secret_get(
+        &self,
+        session: &TenantSession,
+        name: &str,
+    ) -> Result<String, AwsError> {
+        let secret_id = tenant_secret_id(session, name)?;
+        self.secret_get(&secret_id)
+            .await?
+            .ok_or_else(|| AwsError::NotFound(format!("secret {:?}", name)))
+    }
+
+    /// Schedule the session's secret `name` for deletion, or `NotFound`
+    async fn tenant_secret_delete(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        recovery_window_days: i64,
+    ) -> Result<chrono::DateTime<chrono::Utc>, AwsError> {
+        let secret_id = tenant_secret_id(session, name)?;
+        self.secret_schedule_deletion(&secret_id, recovery_window_days)
+            .await?
+            .ok_or_else(|| AwsError::NotFound(format!("secret {:?}", name)))
+    }
+
+    /// Start an execution of `state_machine_arn` called `name` with the JSON `input`;
+    /// returns the execution's ARN
+    async fn workflow_start(
+        &self,
+        state_machine_arn: &str,
+        name: &str,
+        input: &str,
+    ) -> Result<String, AwsError>;
+
+    /// The execution's status, output and error, or `NotFound`
+    async fn workflow_describe(&self, execution_arn: &str) -> Result<WorkflowExecution, AwsError>;
+
+    /// Stop a running execution, recording `error` and `cause`; returns when it stopped
+    async fn workflow_stop(
+        &self,
+        execution_arn: &str,
+        error: Option<&str>,
+        cause: Option<&str>,
+    ) -> Result<chrono::DateTime<chrono::Utc>, AwsError>;
+
+    /// Start an execution of `state_machine_arn` for the session's tenant, which must
+    /// allow the state machine in its tool policy. The execution is named after the
+    /// tenant (see [`workflows::execution_name`]); returns its ARN.
+    async fn tenant_workflow_start(
+        &self,
+        session: &TenantSession,
+        state_machine_arn: &str,
+        input: &Value,
+    ) -> Result<String, AwsError> {
+        workflows::validate_state_machine_arn(state_machine_arn)?;
+        if !session
+            .context
+            .tool_policy
+            .allows_state_machine(state_machine_arn)
+        {
+            return Err(AwsError::InvalidWorkflow(format!(
+                "state machine {:?} is not allowed for tenant {}",
+                state_machine_arn, session.context.tenant_id
+            )));
+        }
+        let name = workflows::execution_name(session)?;
+        self.workflow_start(state_machine_arn, &name, &serde_json::to_string(input)?)
+            .await
+    }
+
+    /// One of the session's tenant's executions; anyone else's is `NotFound`
+    async fn tenant_workflow_status(
+        &self,
+        session: &TenantSession,
+        execution_arn: &str,
+    ) -> Result<WorkflowExecution, AwsError> {
+        workflows::check_execution_owner(session, execution_arn)?;
+        self.workflow_describe(execution_arn).await
+    }
+
+    /// Stop one of the session's tenant's executions; anyone else's is `NotFound`
+    async fn tenant_workflow_stop(
+        &self,
+        session: &TenantSession,
+        execution_arn: &str,
+        error: Option<&str>,
+        cause: Option<&str>,
+    ) -> Result<chrono::DateTime<chrono::Utc>, AwsError> {
+        workflows::check_execution_owner(session, execution_arn)?;
+        self.workflow_stop(execution_arn, error, cause).await
+    }
+
+    /// Send a message with string `attributes`, delivered after `delay_seconds` (or the
+    /// queue's delay); returns its message id
+    async fn queue_send(
+        &self,
+        queue_name: &str,
+        body: &str,
+        attributes: &HashMap<String, String>,
+        delay_seconds: Option<i32>,
+    ) -> Result<String, AwsError>;
+
+    /// Up to `max_messages` messages, waiting up to `wait_seconds` for the first one and
+    /// hiding them from other receivers for `visibility_timeout` seconds (or the queue's
+    /// timeout); empty when none arrived in time
+    async fn queue_receive(
+        &self,
+        queue_name: &str,
+        max_messages: i32,
+        wait_seconds: i32,
+        visibility_timeout: Option<i32>,
+    ) -> Result<Vec<QueueMessage>, AwsError>;
+
+    /// Delete a received message; `NotFound` if the receipt handle is no longer valid
+    async fn queue_delete(&self, queue_name: &str, receipt_handle: &str) -> Result<(), AwsError>;
+
+    /// Make a received message visible again after `visibility_timeout` seconds;
+    /// `NotFound` if it isn't in flight under this receipt handle
+    async fn queue_change_visibility(
+        &self,
+        queue_name: &str,
+        receipt_handle: &str,
+        visibility_timeout: i32,
+    ) -> Result<(), AwsError>;
+
+    /// Send to the session's queue `name` (see [`queues::tenant_queue_name`])
+    async fn tenant_queue_send(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        body: &str,
+        attributes: &HashMap<String, String>,
+        delay_seconds: Option<i32>,
+    ) -> Result<String, AwsError> {
+        let queue_name = queues::tenant_queue_name(self.queue_prefix(), session, name)?;
+        self.queue_send(&queue_name, body, attributes, delay_seconds)
+            .await
+    }
+
+    /// Receive from the session's queue `name`
+    async fn tenant_queue_receive(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        max_messages: i32,
+        wait_seconds: i32,
+        visibility_timeout: Option<i32>,
+    ) -> Result<Vec<QueueMessage>, AwsError> {
+        let queue_name = queues::tenant_queue_name(self.queue_prefix(), session, name)?;
+        self.queue_receive(&queue_name, max_messages, wait_seconds, visibility_timeout)
+            .await
+    }
+
+    /// Acknowledge (delete) a message received from the session's queue `name`
+    async fn tenant_queue_ack(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        receipt_handle: &str,
+    ) -> Result<(), AwsError> {
+        let queue_name = queues::tenant_queue_name(self.queue_prefix(), session, name)?;
+        self.queue_delete(&queue_name, receipt_handle).await
+    }
+
+    /// Return a message received from the session's queue `name`, to be delivered again
+    /// after `delay_seconds`
+    async fn tenant_queue_nack(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        receipt_handle: &str,
+        delay_seconds: i32,
+    ) -> Result<(), AwsError> {
+        let queue_name = queues::tenant_queue_name(self.queue_prefix(), session, name)?;
+        self.queue_change_visibility(&queue_name, receipt_handle, delay_seconds)
+            .await
+    }
+
+    /// Apply writes to the session's KV items atomically (see
+    /// [`AwsService::kv_transact_write`]); a canceled transaction is
+    /// `TransactionCanceled`, listing the failed operations
+    #[allow(dead_code)]
+    async fn kv_transact_write(
+        &self,
+        session: &TenantSession,
+        ops: &[KvWriteOp],
+    ) -> Result<(), AwsError>;
+
+    /// Write a KV item only if its value is still `expected` (see
+    /// [`AwsService::kv_compare_and_swap`]); false when another writer changed it first
+    async fn kv_compare_and_swap(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError>;
+
+    /// Create a rule and its alert subscription atomically; the rule is returned with
+    /// the subscription as `alert`
+    async fn create_event_rule_with_alert(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        pattern: Value,
+        description: Option<String>,
+        enabled: bool,
+        alert: &NewAlertSubscription,
+    ) -> Result<Value, AwsError>;
+
+    /// Whether the SNS topic exists
+    async fn sns_topic_exists(&self, topic_arn: &str) -> Result<bool, AwsError>;
+
+    /// Publish `message` to an SNS topic with string message attributes; returns the
+    /// message id
+    async fn sns_publish(
+        &self,
+        topic_arn: &str,
+        message: &str,
+        attributes: &HashMap<String, String>,
+    ) -> Result<String, AwsError>;
+
+    /// Set the `lastError` (and `lastErrorAt`) of one of the session's tenant's
+    /// subscriptions; `NotFound` if there is no such subscription
+    async fn record_alert_error(
+        &self,
+        session: &TenantSession,
+        subscription_id: &str,
+        error: &str,
+    ) -> Result<(), AwsError>;
+
+    /// A page of at least `limit` (unless it's the last) of the session's KV keys,
+    /// relative to its namespace, in no particular order, and the position the next page
+    /// starts from; `start` is a position an earlier page returned
+    async fn kv_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError>;
+
+    /// The event rules the session's user created in its organization, as stored
+    async fn event_rules(&self, session: &TenantSession) -> Result<Vec<Value>, AwsError>;
+
+    /// The alert subscriptions the session's user created in its organization, as stored
+    async fn alert_subscriptions(&self, session: &TenantSession) -> Result<Vec<Value>, AwsError>;
+
+    /// Users with KV items, artifacts, events, event rules or alert subscriptions in the
+    /// session's organization, sorted, always including the session's user; in a
+    /// personal context, that user alone
+    async fn namespace_users(&self, session: &TenantSession) -> Result<Vec<String>, AwsError>;
+
+    /// Delete one of [`AwsApi::event_rules`]; `NotFound` for anyone else's rule
+    async fn delete_event_rule(
+        &self,
+        session: &TenantSession,
+        rule_id: &str,
+    ) -> Result<(), AwsError>;
+
+    /// Delete one of [`AwsApi::alert_subscriptions`]; `NotFound` for anyone else's
+    async fn delete_alert_subscription(
+        &self,
+        session: &TenantSession,
+        subscription_id: &str,
+    ) -> Result<(), AwsError>;
+
+    /// Delete the session's tenant's event records by `eventId`, e.g. those a scoped
+    /// `query_events` returned; ids that don't exist are skipped
+    async fn delete_events(
+        &self,
+        session: &TenantSession,
+        event_ids: &[String],
+    ) -> Result<(), AwsError>;
+
+    /// Deliver `event` to one of the session's tenant's alert subscriptions its rule
+    /// matched. Enabled sns subscriptions get the event JSON as the message, with the
+    /// attributes from [`alerts::alert_message_attributes`] for SNS filter policies;
+    /// returns the message id, or `None` for subscriptions this server doesn't deliver. A
+    /// failed publish is recorded on the subscription and announced with an
+    /// `alert.delivery_failed` event before its error is returned.
+    #[allow(dead_code)]
+    async fn deliver_alert(
+        &self,
+        session: &TenantSession,
+        subscription: &Value,
+        event: &Value,
+    ) -> Result<Option<String>, AwsError> {
+        if subscription["notificationMethod"] != "sns" || subscription["enabled"] == false {
+            return Ok(None);
+        }
+        let topic_arn = subscription["snsTopicArn"].as_str().unwrap_or_default();
+        let result = match topic_arn {
+            "" => Err(AwsError::InvalidTopic("(missing)".to_string())),
+            topic_arn => {
+                let message = serde_json::to_string(event)?;
+                let attributes = alerts::alert_message_attributes(event);
+                self.sns_publish(topic_arn, &message, &attributes).await
+            }
+        };
+
+        let error = match result {
+            Ok(message_id) => return Ok(Some(message_id)),
+            Err(e) => e,
+        };
+        let subscription_id = subscription["subscriptionId"].as_str().unwrap_or_default();
+        tracing::warn!(
+            "Failed to deliver alert for subscription {}: {}",
+            subscription_id,
+            error
+        );
+        if let Err(e) = self
+            .record_alert_error(session, subscription_id, &error.to_string())
+            .await
+        {
+            tracing::warn!("Failed to record alert error on {}: {}", subscription_id, e);
+        }
+        let failure = json!({
+            "subscriptionId": subscription_id,
+            "userId": subscription["userId"],
+            "organizationId": subscription["organizationId"],
+            "snsTopicArn": topic_arn,
+            "eventDetailType": event["detailType"],
+            "error": error.to_string()
+        });
+        if let Err(e) = self
+            .send_system_event(alerts::DELIVERY_FAILED_DETAIL_TYPE, failure)
+            .await
+        {
+            tracing::warn!("Failed to publish alert.delivery_failed: {}", e);
+        }
+        Err(error)
+    }
+}
+
+#[async_trait]
+impl AwsApi for AwsService {
+    fn artifacts_bucket(&self) -> &str {
+        AwsService::artifacts_bucket(self)
+    }
+
+    fn queue_prefix(&self) -> &str {
+        &self.queue_prefix
+    }
+
+    fn verifies_alert_topics(&self) -> bool {
+        self.verify_alert_topics
+    }
+
+    fn lambda_client(&self) -> Option<LambdaClient> {
+        Some(AwsService::lambda_client(self))
+    }
+
+    fn cloudwatch_client(&self) -> Option<CloudWatchClient> {
+        self.clients.cloudwatch.clone()
+    }
+
+    async fn kv_get(&self, session: &TenantSession, key: &str) -> Result<Option<String>, AwsError> {
+        AwsService::kv_get(self, session, key).await
+    }
+
+    async fn kv_get_fresh(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<String>, AwsError> {
+        AwsService::kv_get_fresh(self, session, key).await
+    }
+
+    fn kv_cache_stats(&self) -> Option<KvCacheStats> {
+        AwsService::kv_cache_stats(self)
+    }
+
+    fn latency_stats(&self) -> Option<BTreeMap<String, LatencyStats>> {
+        Some(AwsService::latency_stats(self))
+    }
+
+    fn degraded_tenants(&self) -> BTreeMap<String, String> {
+        self.tenant_clients.degraded_tenants()
+    }
+
+    async fn ping(&self) -> Result<(), AwsError> {
+        AwsService::ping(self).await
+    }
+
+    async fn kv_set(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+        sensitive: bool,
+    ) -> Result<(), AwsError> {
+        AwsService::kv_set(self, session, key, value, ttl_hours, sensitive).await
+    }
+
+    async fn artifacts_put(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        content: &[u8],
+        content_type: &str,
+        ttl_class: Option<TtlClass>,
+    ) -> Result<(), AwsError> {
+        AwsService::artifacts_put(self, session, key, content, content_type, ttl_class).await
+    }
+
+    async fn artifacts_head(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<ArtifactMetadata>, AwsError> {
+        AwsService::artifacts_head(self, session, key).await
+    }
+
+    async fn configure_artifact_lifecycle(
+        &self,
+        session: &TenantSession,
+        retention: &RetentionDays,
+    ) -> Result<Vec<ArtifactLifecycleRule>, AwsError> {
+        AwsService::configure_artifact_lifecycle(self, session, retention).await
+    }
+
+    async fn artifacts_get(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, AwsError> {
+        AwsService::artifacts_get(self, session, key).await
+    }
+
+    async fn artifacts_list(
+        &self,
+        session: &TenantSession,
+        prefix: Option<&str>,
+    ) -> Result<Vec<String>, AwsError> {
+        AwsService::artifacts_list(self, session, prefix).await
+    }
+
+    async fn artifacts_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError> {
+        AwsService::artifacts_keys(self, session, start, limit).await
+    }
+
+    async fn artifacts_delete_prefix(
+        &self,
+        session: &TenantSession,
+        prefix: &str,
+        dry_run: bool,
+    ) -> Result<ArtifactDeletion, AwsError> {
+        AwsService::artifacts_delete_prefix(self, session, prefix, dry_run).await
+    }
+
+    async fn migrate_artifacts(
+        &self,
+        session: &TenantSession,
+    ) -> Result<ArtifactMigration, AwsError> {
+        AwsService::migrate_artifacts(self, session).await
+    }
+
+    async fn s3_get(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, AwsError> {
+        AwsService::s3_get(self, bucket, key).await
+    }
+
+    async fn send_event(
+        &self,
+        session: &TenantSession,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<(), AwsError> {
+        AwsService::send_event(self, session, detail_type, detail).await
+    }
+
+    async fn send_events(
+        &self,
+        session: &TenantSession,
+        events: &[(String, Value)],
+    ) -> Result<Vec<Option<String>>, AwsError> {
+        AwsService::send_events(self, session, events).await
+    }
+
+    async fn send_event_buffered(
+        &self,
+        session: &TenantSession,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<Option<String>, AwsError> {
+        AwsService::send_event_buffered(self, session, detail_type, detail).await
+    }
+
+    fn buffers_events_by_default(&self) -> bool {
+        self.buffer_events
+    }
+
+    async fn flush_events(&self) {
+        AwsService::flush_events(self).await
+    }
+
+    async fn send_system_event(&self, detail_type: &str, detail: Value) -> Result<(), AwsError> {
+        AwsService::send_system_event(self, detail_type, detail).await
+    }
+
+    async fn query_events(
+        &self,
+        session: &TenantSession,
+        scope: Option<&EventScope>,
+        user_id: Option<String>,
+        organization_id: Option<String>,
+        source: Option<String>,
+        detail_type: Option<String>,
+        priority: Option<String>,
+        link_id: Option<String>,
+        start_time: Option<String>,
+        end_time: Option<String>,
+        limit: i32,
+        exclusive_start_key: Option<String>,
+        ascending: bool,
+    ) -> Result<Value, AwsError> {
+        AwsService::query_events(
+            self,
+            session,
+            scope,
+            user_id,
+            organization_id,
+            source,
+            detail_type,
+            priority,
+            link_id,
+            start_time,
+            end_time,
+            limit,
+            exclusive_start_key,
+            ascending,
+        )
+        .await
+    }
+
+    async fn query_events_explained(
+        &self,
+        session: &TenantSession,
+        query: &EventQuery,
+        explain_only: bool,
+    ) -> Result<Value, AwsError> {
+        AwsService::query_events_explained(self, session, query, explain_only).await
+    }
+
+    async fn analytics_query(
+        &self,
+        session: &TenantSession,
+        user_id: Option<String>,
+        organization_id: Option<String>,
+        start_time: Option<String>,
+        end_time: Option<String>,
+        metrics: Vec<String>,
+        granularity: String,
+    ) -> Result<Value, AwsError> {
+        AwsService::analytics_query(
+            self,
+            session,
+            user_id,
+            organization_id,
+            start_time,
+            end_time,
+            metrics,
+            granularity,
+        )
+        .await
+    }
+
+    async fn create_event_rule(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        pattern: Value,
+        description: Option<String>,
+        enabled: bool,
+    ) -> Result<Value, AwsError> {
+        AwsService::create_event_rule(self, session, name, pattern, description, enabled).await
+    }
+
+    async fn create_alert_subscription(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        rule_id: &str,
+        notification_method: &str,
+        sns_topic_arn: Option<String>,
+        email_address: Option<String>,
+        enabled: bool,
+    ) -> Result<Value, AwsError> {
+        AwsService::create_alert_subscription(
+            self,
+            session,
+            name,
+            rule_id,
+            notification_method,
+            sns_topic_arn,
+            email_address,
+            enabled,
+        )
+        .await
+    }
+
+    async fn events_health_check(&self, session: &TenantSession) -> Result<Value, AwsError> {
+        AwsService::events_health_check(self, session).await
+    }
+
+    async fn secret_store(
+        &self,
+        secret_name: &str,
+        secret_value: &str,
+        description: Option<&str>,
+    ) -> Result<String, AwsError> {
+        AwsService::secret_store(self, secret_name, secret_value, description).await
+    }
+
+    async fn secret_get(&self, secret_name: &str) -> Result<Option<String>, AwsError> {
+        AwsService::secret_get(self, secret_name).await
+    }
+
+    async fn secret_names(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
+        AwsService::secret_names(self, prefix).await
+    }
+
+    async fn secret_delete(&self, secret_name: &str, force_delete: bool) -> Result<(), AwsError> {
+        AwsService::secret_delete(self, secret_name, force_delete).await
+    }
+
+    async fn secret_schedule_deletion(
+        &self,
+        secret_name: &str,
+        recovery_window_days: i64,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>, AwsError> {
+        AwsService::secret_schedule_deletion(self, secret_name, recovery_window_days).await
+    }
+
+    async fn workflow_start(
+        &self,
+        state_machine_arn: &str,
+        name: &str,
+        input: &str,
+    ) -> Result<String, AwsError> {
+        AwsService::workflow_start(self, state_machine_arn, name, input).await
+    }
+
+    async fn workflow_describe(&self, execution_arn: &str) -> Result<WorkflowExecution, AwsError> {
+        AwsService::workflow_describe(self, execution_arn).await
+    }
+
+    async fn workflow_stop(
+        &self,
+        execution_arn: &str,
+        error: Option<&str>,
+        cause: Option<&str>,
+    ) -> Result<chrono::DateTime<chrono::Utc>, AwsError> {
+        AwsService::workflow_stop(self, execution_arn, error, cause).await
+    }
+
+    async fn queue_send(
+        &self,
+        queue_name: &str,
+        body: &str,
+        attributes: &HashMap<String, String>,
+        delay_seconds: Option<i32>,
+    ) -> Result<String, AwsError> {
+        AwsService::queue_send(self, queue_name, body, attributes, delay_seconds).await
+    }
+
+    async fn queue_receive(
+        &self,
+        queue_name: &str,
+        max_messages: i32,
+        wait_seconds: i32,
+        visibility_timeout: Option<i32>,
+    ) -> Result<Vec<QueueMessage>, AwsError> {
+        AwsService::queue_receive(
+            self,
+            queue_name,
+            max_messages,
+            wait_seconds,
+            visibility_timeout,
+        )
+        .await
+    }
+
+    async fn queue_delete(&self, queue_name: &str, receipt_handle: &str) -> Result<(), AwsError> {
+        AwsService::queue_delete(self, queue_name, receipt_handle).await
+    }
+
+    async fn queue_change_visibility(
+        &self,
+        queue_name: &str,
+        receipt_handle: &str,
+        visibility_timeout: i32,
+    ) -> Result<(), AwsError> {
+        AwsService::queue_change_visibility(self, queue_name, receipt_handle, visibility_timeout)
+            .await
+    }
+
+    async fn kv_transact_write(
+        &self,
+        session: &TenantSession,
+        ops: &[KvWriteOp],
+    ) -> Result<(), AwsError> {
+        AwsService::kv_transact_write(self, session, ops).await
+    }
+
+    async fn kv_compare_and_swap(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        AwsService::kv_compare_and_swap(self, session, key, expected, value, ttl_hours).await
+    }
+
+    async fn create_event_rule_with_alert(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        pattern: Value,
+        description: Option<String>,
+        enabled: bool,
+        alert: &NewAlertSubscription,
+    ) -> Result<Value, AwsError> {
+        AwsService::create_event_rule_with_alert(
+            self,
+            session,
+            name,
+            pattern,
+            description,
+            enabled,
+            alert,
+        )
+        .await
+    }
+
+    async fn sns_topic_exists(&self, topic_arn: &str) -> Result<bool, AwsError> {
+        AwsService::sns_topic_exists(self, topic_arn).await
+    }
+
+    async fn sns_publish(
+        &self,
+        topic_arn: &str,
+        message: &str,
+        attributes: &HashMap<String, String>,
+    ) -> Result<String, AwsError> {
+        AwsService::sns_publish(self, topic_arn, message, attributes).await
+    }
+
+    async fn record_alert_error(
+        &self,
+        session: &TenantSession,
+        subscription_id: &str,
+        error: &str,
+    ) -> Result<(), AwsError> {
+        AwsService::record_alert_error(self, session, subscription_id, error).await
+    }
+
+    async fn kv_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError> {
+        AwsService::kv_keys(self, session, start, limit).await
+    }
+
+    async fn event_rules(&self, session: &TenantSession) -> Result<Vec<Value>, AwsError> {
+        AwsService::event_rules(self, session).await
+    }
+
+    async fn alert_subscriptions(&self, session: &TenantSession) -> Result<Vec<Value>, AwsError> {
+        AwsService::alert_subscriptions(self, session).await
+    }
+
+    async fn namespace_users(&self, session: &TenantSession) -> Result<Vec<String>, AwsError> {
+        AwsService::namespace_users(self, session).await
+    }
+
+    async fn delete_event_rule(
+        &self,
+        session: &TenantSession,
+        rule_id: &str,
+    ) -> Result<(), AwsError> {
+        AwsService::delete_event_rule(self, session, rule_id).await
+    }
+
+    async fn delete_alert_subscription(
+        &self,
+        session: &TenantSession,
+        subscription_id: &str,
+    ) -> Result<(), AwsError> {
+        AwsService::delete_alert_subscription(self, session, subscription_id).await
+    }
+
+    async fn delete_events(
+        &self,
+        session: &TenantSession,
+        event_ids: &[String],
+    ) -> Result<(), AwsError> {
+        AwsService::delete_events(self, session, event_ids).await
+    }
+}
+
+/// Session-less KV access for internal components such as the MCP server registry.
+/// `AwsService` implements it over DynamoDB; `MemoryKvStore` stands in for tests.
+#[async_trait]
+pub trait DirectKvStore: Send + Sync {
+    async fn kv_get_direct(&self, key: &str) -> Result<Option<String>, AwsError>;
+
+    async fn kv_set_direct(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError>;
+
+    /// `kv_set_direct` with the value envelope-encrypted for `tenant_id`, which
+    /// `kv_get_direct` decrypts again
+    async fn kv_set_direct_sensitive(
+        &self,
+        tenant_id: &str,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError>;
+
+    /// Write `value` if the key's value is `expected`, or the key is absent when
+    /// `None`; `false` if it wasn't
+    async fn kv_compare_and_swap_direct(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError>;
+
+    /// Keys starting with `prefix`
+    async fn kv_list(&self, prefix: &str) -> Result<Vec<String>, AwsError>;
+
+    async fn kv_delete(&self, key: &str) -> Result<(), AwsError>;
+}
+
+#[async_trait]
+impl DirectKvStore for AwsService {
+    async fn kv_get_direct(&self, key: &str) -> Result<Option<String>, AwsError> {
+        AwsService::kv_get_direct(self, key).await
+    }
+
+    async fn kv_set_direct(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError> {
+        AwsService::kv_set_direct(self, key, value, ttl_hours).await
+    }
+
+    async fn kv_set_direct_sensitive(
+        &self,
+        tenant_id: &str,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError> {
+        AwsService::kv_set_direct_sensitive(self, tenant_id, key, value, ttl_hours).await
+    }
+
+    async fn kv_compare_and_swap_direct(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        AwsService::kv_compare_and_swap_direct(self, key, expected, value, ttl_hours).await
+    }
+
+    async fn kv_list(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
+        AwsService::kv_list(self, prefix).await
+    }
+
+    async fn kv_delete(&self, key: &str) -> Result<(), AwsError> {
+        AwsService::kv_delete(self, key).await
+    }
+}
+
+/// In-process KV store for tests and local dev; TTLs and encryption are skipped
+#[derive(Default)]
+pub struct MemoryKvStore {
+    items: Mutex<HashMap<String, String>>,
+}
+
+impl MemoryKvStore {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DirectKvStore for MemoryKvStore {
+    async fn kv_get_direct(&self, key: &str) -> Result<Option<String>, AwsError> {
+        Ok(self.items.lock().unwrap().get(key).cloned())
+    }
+
+    async fn kv_set_direct(
+        &self,
+        key: &str,
+        value: &str,
+        _ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError> {
+        self.items
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    async fn kv_set_direct_sensitive(
+        &self,
+        _tenant_id: &str,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError> {
+        self.kv_set_direct(key, value, ttl_hours).await
+    }
+
+    async fn kv_compare_and_swap_direct(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        _ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        let mut items = self.items.lock().unwrap();
+        if items.get(key).map(String::as_str) != expected {
+            return Ok(false);
+        }
+        items.insert(key.to_string(), value.to_string());
+        Ok(true)
+    }
+
+    async fn kv_list(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
+        Ok(self
+            .items
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    async fn kv_delete(&self, key: &str) -> Result<(), AwsError> {
+        self.items.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Secrets Manager name of one stored credential field of a tenant's MCP server
+pub fn credential_secret_name(tenant_id: &str, server_id: &str, field: &str) -> String {
+    format!("mcp/{}/{}/{}", tenant_id, server_id, field)
+}
+
+/// Storage for MCP server credentials, one secret per field. Secrets Manager in
+/// production; `KvSecretStore` for environments without it (e.g. LocalStack).
+#[async_trait]
+pub trait SecretStore: Send + Sync {
+    async fn secret_put(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<(), AwsError>;
+
+    async fn secret_get(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<Option<String>, AwsError>;
+
+    async fn secret_delete(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<(), AwsError>;
+
+    /// Names of the fields stored for a server
+    async fn secret_fields(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<Vec<String>, AwsError>;
+}
+
+/// Credentials kept in AWS Secrets Manager under `mcp/{tenant}/{server}/{field}`
+pub struct SecretsManagerStore {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl SecretsManagerStore {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl SecretStore for SecretsManagerStore {
+    async fn secret_put(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<(), AwsError> {
+        let description = format!("{} credential for MCP server {}", field, server_id);
+        self.aws_service
+            .secret_store(
+                &credential_secret_name(tenant_id, server_id, field),
+                value,
+                Some(&description),
+            )
+            .await
+            .map(|_| ())
+    }
+
+    async fn secret_get(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<Option<String>, AwsError> {
+        self.aws_service
+            .secret_get(&credential_secret_name(tenant_id, server_id, field))
+            .await
+    }
+
+    async fn secret_delete(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<(), AwsError> {
+        // Skip the recovery window: a pending deletion blocks storing the same field again
+        self.aws_service
+            .secret_delete(&credential_secret_name(tenant_id, server_id, field), true)
+            .await
+    }
+
+    async fn secret_fields(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<Vec<String>, AwsError> {
+        let prefix = credential_secret_name(tenant_id, server_id, "");
+        Ok(self
+            .aws_service
+            .secret_names(&prefix)
+            .await?
+            .into_iter()
+            .filter_map(|name| {
+                let field = &name[prefix.len()..];
+                (!field.contains('/')).then(|| field.to_string())
+            })
+            .collect())
+    }
+}
+
+/// Credentials kept as KV items under `mcp-credential-{tenant}:{server}:{field}`, for
+/// environments without Secrets Manager. Values are envelope-encrypted with the tenant's
+/// data key, so storing them needs `AGENT_MESH_KMS_KEY_ID`; credentials stored in plain
+/// text before are still read until they're stored again.
+pub struct KvSecretStore {
+    kv: Arc<dyn DirectKvStore>,
+}
+
+impl KvSecretStore {
+    pub fn new(kv: Arc<dyn DirectKvStore>) -> Self {
+        Self { kv }
+    }
+
+    fn key(tenant_id: &str, server_id: &str, field: &str) -> String {
+        internal_keys::credential_key(tenant_id, server_id, field)
+    }
+}
+
+#[async_trait]
+impl SecretStore for KvSecretStore {
+    async fn secret_put(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<(), AwsError> {
+        self.kv
+            .kv_set_direct_sensitive(
+                tenant_id,
+                &Self::key(tenant_id, server_id, field),
+                value,
+                None,
+            )
+            .await
+    }
+
+    async fn secret_get(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<Option<String>, AwsError> {
+        // Decrypted under the tenant the value was sealed for
+        self.kv
+            .kv_get_direct(&Self::key(tenant_id, server_id, field))
+            .await
+    }
+
+    async fn secret_delete(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<(), AwsError> {
+        self.kv
+            .kv_delete(&Self::key(tenant_id, server_id, field))
+            .await
+    }
+
+    async fn secret_fields(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<Vec<String>, AwsError> {
+        let prefix = Self::key(tenant_id, server_id, "");
+        Ok(self
+            .kv
+            .kv_list(&prefix)
+            .await?
+            .into_iter()
+            .filter_map(|key| {
+                // Field names never contain ':'
+                let field = &key[prefix.len()..];
+                (!field.contains(':')).then(|| field.to_string())
+            })
+            .collect())
+    }
+}
+
+/// In-process secret store keyed by Secrets Manager name, for tests and local dev
+#[derive(Default)]
+pub struct MemorySecretStore {
+    secrets: Mutex<HashMap<String, String>>,
+}
+
+impl MemorySecretStore {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every stored secret name, sorted
+    #[allow(dead_code)]
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.secrets.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+#[async_trait]
+impl SecretStore for MemorySecretStore {
+    async fn secret_put(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<(), AwsError> {
+        self.secrets.lock().unwrap().insert(
+            credential_secret_name(tenant_id, server_id, field),
+            value.to_string(),
+        );
+        Ok(())
+    }
+
+    async fn secret_get(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<Option<String>, AwsError> {
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .get(&credential_secret_name(tenant_id, server_id, field))
+            .cloned())
+    }
+
+    async fn secret_delete(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        field: &str,
+    ) -> Result<(), AwsError> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .remove(&credential_secret_name(tenant_id, server_id, field));
+        Ok(())
+    }
+
+    async fn secret_fields(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<Vec<String>, AwsError> {
+        let prefix = credential_secret_name(tenant_id, server_id, "");
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .keys()
+            .filter_map(|name| name.strip_prefix(&prefix))
+            .filter(|field| !field.contains('/'))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::config::http::HttpResponse;
+    use aws_sdk_dynamodb::error::ErrorMetadata;
+    use aws_sdk_dynamodb::types::error::{
+        ConditionalCheckFailedException, ProvisionedThroughputExceededException,
+        RequestLimitExceeded, ResourceNotFoundException, ThrottlingException,
+    };
+    use aws_sdk_s3::primitives::SdkBody;
+    use aws_sdk_s3::types::error::NoSuchKey;
+
+    fn service_error<E>(err: E, status: u16) -> SdkError<E, HttpResponse> {
+        SdkError::service_error(
+            err,
+            HttpResponse::new(status.try_into().unwrap(), SdkBody::empty()),
+        )
+    }
+
+    #[test]
+    fn test_missing_object_is_not_found() {
+        let err = service_error(
+            GetObjectError::NoSuchKey(NoSuchKey::builder().message("no such key").build()),
+            404,
+        );
+        match sdk_error(err, "s3_get", AwsError::S3) {
+            AwsError::NotFound(message) => assert!(message.contains("no such key"), "{}", message),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_failed_condition_is_condition_failed() {
+        let err = service_error(
+            PutItemError::ConditionalCheckFailedException(
+                ConditionalCheckFailedException::builder()
+                    .message("The conditional request failed")
+                    .build(),
+            ),
+            400,
+        );
+        match sdk_error(err, "dynamodb_write", AwsError::DynamoDb) {
+            AwsError::ConditionFailed(message) => {
+                assert!(
+                    message.contains("conditional request failed"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("expected ConditionFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_table_is_table_missing() {
+        let not_found = || {
+            ResourceNotFoundException::builder()
+                .message("Requested resource not found")
+                .build()
+        };
+        let err = service_error(GetItemError::ResourceNotFoundException(not_found()), 400);
+        assert!(matches!(
+            sdk_error(err, "dynamodb_read", AwsError::DynamoDb),
+            AwsError::TableMissing(_)
+        ));
+        let err = service_error(DeleteItemError::ResourceNotFoundException(not_found()), 400);
+        assert!(matches!(
+            sdk_error(err, "dynamodb_write", AwsError::DynamoDb),
+            AwsError::TableMissing(_)
+        ));
+        let err = service_error(QueryError::ResourceNotFoundException(not_found()), 400);
+        assert!(matches!(
+            sdk_error(err, "dynamodb_read", AwsError::DynamoDb),
+            AwsError::TableMissing(_)
+        ));
+    }
+
+    #[test]
+    fn test_throttling_and_other_errors_keep_their_mapping() {
+        let err = service_error(
+            PutItemError::ProvisionedThroughputExceededException(
+                ProvisionedThroughputExceededException::builder()
+                    .meta(
+                        ErrorMetadata::builder()
+                            .code("ProvisionedThroughputExceededException")
+                            .build(),
+                    )
+                    .build(),
+            ),
+            400,
+        );
+        match sdk_error(err, "dynamodb_write", AwsError::DynamoDb) {
+            AwsError::Throttled {
+                service_key, code, ..
+            } => {
+                assert_eq!(service_key, "dynamodb_write");
+                assert_eq!(code, "ProvisionedThroughputExceededException");
+            }
+            other => panic!("expected Throttled, got {:?}", other),
+        }
+
+        let err = service_error(
+            GetObjectError::generic(ErrorMetadata::builder().code("AccessDenied").build()),
+            403,
+        );
+        assert!(matches!(
+            sdk_error(err, "s3_get", AwsError::S3),
+            AwsError::S3(_)
+        ));
+    }
+
+    /// The JSON-RPC error a tool call failing with `error` ends in
+    fn rpc_error(error: AwsError) -> crate::mcp::MCPErrorResponse {
+        crate::mcp::MCPError::from(crate::handlers::HandlerError::Aws(error)).into()
+    }
+
+    #[test]
+    fn test_typed_dynamodb_throttling_is_a_rate_limit() {
+        // Typed variants are recognised without relying on the error code
+        let err = service_error(
+            GetItemError::ProvisionedThroughputExceededException(
+                ProvisionedThroughputExceededException::builder()
+                    .message("The level of configured provisioned throughput was exceeded")
+                    .build(),
+            ),
+            400,
+        );
+        let throttled = sdk_error(err, "dynamodb_read", AwsError::DynamoDb);
+        assert!(matches!(
+            &throttled,
+            AwsError::Throttled {
+                service_key: "dynamodb_read",
+                code,
+                retry_after_hint,
+            } if code == "ProvisionedThroughputExceededException"
+                && *retry_after_hint == THROTTLE_RETRY_AFTER
+        ));
+        let response = rpc_error(throttled);
+        assert_eq!(response.code, -32001);
+        assert_eq!(
+            response.data.unwrap(),
+            serde_json::json!({
+                "serviceKey": "dynamodb_read",
+                "retryAfterMs": 1000,
+                "scope": "aws",
+                "status": null,
+            })
+        );
+
+        let err = service_error(
+            QueryError::RequestLimitExceeded(RequestLimitExceeded::builder().build()),
+            400,
+        );
+        assert!(matches!(
+            sdk_error(err, "dynamodb_read", AwsError::DynamoDb),
+            AwsError::Throttled { code, .. } if code == "RequestLimitExceeded"
+        ));
+        let err = service_error(
+            TransactWriteItemsError::ThrottlingException(ThrottlingException::builder().build()),
+            400,
+        );
+        let response = rpc_error(sdk_error(err, "dynamodb_write", AwsError::DynamoDb));
+        assert_eq!(response.code, -32001);
+        assert_eq!(response.data.unwrap()["serviceKey"], "dynamodb_write");
+    }
+
+    #[test]
+    fn test_s3_and_eventbridge_throttling_is_a_rate_limit() {
+        let err = service_error(
+            PutObjectError::generic(
+                ErrorMetadata::builder()
+                    .code("SlowDown")
+                    .message("Please reduce your request rate.")
+                    .build(),
+            ),
+            503,
+        );
+        let response = rpc_error(sdk_error(err, "s3_put", AwsError::S3));
+        assert_eq!(response.code, -32001);
+        let data = response.data.unwrap();
+        assert_eq!(data["serviceKey"], "s3_put");
+        assert_eq!(data["retryAfterMs"], 2000);
+        assert_eq!(data["scope"], "aws");
+
+        let err = service_error(
+            PutEventsError::generic(
+                ErrorMetadata::builder()
+                    .code("ThrottlingException")
+                    .message("Rate exceeded")
+                    .build(),
+            ),
+            400,
+        );
+        let response = rpc_error(sdk_error(err, "eventbridge_put", AwsError::EventBridge));
+        assert_eq!(response.code, -32001);
+        let data = response.data.unwrap();
+        assert_eq!(data["serviceKey"], "eventbridge_put");
+        assert_eq!(data["retryAfterMs"], 1000);
+
+        // Other failures stay handler errors
+        let err = service_error(
+            PutEventsError::generic(ErrorMetadata::builder().code("AccessDenied").build()),
+            403,
+        );
+        let response = rpc_error(sdk_error(err, "eventbridge_put", AwsError::EventBridge));
+        assert_eq!(response.code, -32003);
+    }
+
+    #[test]
+    fn test_missing_execution_is_not_found() {
+        use aws_sdk_sfn::types::error::{ExecutionDoesNotExist, StateMachineDoesNotExist};
+
+        let err = service_error(
+            DescribeExecutionError::ExecutionDoesNotExist(
+                ExecutionDoesNotExist::builder()
+                    .message("Execution Does Not Exist")
+                    .build(),
+            ),
+            400,
+        );
+        assert!(matches!(
+            sdk_error(err, "aws_api", AwsError::StepFunctions),
+            AwsError::NotFound(_)
+        ));
+
+        let err = service_error(
+            StartExecutionError::StateMachineDoesNotExist(
+                StateMachineDoesNotExist::builder().build(),
+            ),
+            400,
+        );
+        assert!(matches!(
+            sdk_error(err, "aws_api", AwsError::StepFunctions),
+            AwsError::NotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_canceled_transaction_lists_failed_operations() {
+        use aws_sdk_dynamodb::types::error::TransactionCanceledException;
+        use aws_sdk_dynamodb::types::CancellationReason;
+
+        let operations = [
+            "put \"usage\"".to_string(),
+            "condition check \"plan\"".to_string(),
+            "delete \"draft\"".to_string(),
+        ];
+        let err = service_error(
+            TransactWriteItemsError::TransactionCanceledException(
+                TransactionCanceledException::builder()
+                    .message("Transaction cancelled, please refer cancellation reasons")
+                    .cancellation_reasons(CancellationReason::builder().code("None").build())
+                    .cancellation_reasons(
+                        CancellationReason::builder()
+                            .code("ConditionalCheckFailed")
+                            .message("The conditional request failed")
+                            .build(),
+                    )
+                    .cancellation_reasons(
+                        CancellationReason::builder()
+                            .code("TransactionConflict")
+                            .build(),
+                    )
+                    .build(),
+            ),
+            400,
+        );
+        match transaction_error(err, &operations) {
+            AwsError::TransactionCanceled(failures) => {
+                assert_eq!(
+                    failures,
+                    vec![
+                        TransactionFailure {
+                            index: 1,
+                            operation: operations[1].clone(),
+                            code: "ConditionalCheckFailed".to_string(),
+                            message: Some("The conditional request failed".to_string()),
+                        },
+                        TransactionFailure {
+                            index: 2,
+                            operation: operations[2].clone(),
+                            code: "TransactionConflict".to_string(),
+                            message: None,
+                        },
+                    ]
+                );
+                let message = AwsError::TransactionCanceled(failures).to_string();
+                assert!(
+                    message.contains("op 1 (condition check \"plan\"): ConditionalCheckFailed"),
+                    "{}",
+                    message
+                );
+            }
+            other => panic!("expected TransactionCanceled, got {:?}", other),
+        }
+
+        // Other failures are converted as for single-item writes
+        let err = service_error(
+            TransactWriteItemsError::generic(
+                ErrorMetadata::builder().code("ThrottlingException").build(),
+            ),
+            400,
+        );
+        assert!(matches!(
+            transaction_error(err, &operations),
+            AwsError::Throttled {
+                service_key: "dynamodb_write",
+                ..
+            }
+        ));
+    }
+
+    /// A closed span's name and fields
+    type RecordedSpan = (String, BTreeMap<String, String>);
+
+    /// Records each span's name and fields when it closes
+    #[derive(Clone, Default)]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<RecordedSpan>>>,
+    }
+
+    #[derive(Default)]
+    struct SpanFields(BTreeMap<String, String>);
+
+    impl tracing::field::Visit for SpanFields {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl<S> tracing_subscriber::Layer<S> for SpanRecorder
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = SpanFields::default();
+            attrs.record(&mut fields);
+            ctx.span(id).unwrap().extensions_mut().insert(fields);
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if let Some(fields) = ctx
+                .span(id)
+                .unwrap()
+                .extensions_mut()
+                .get_mut::<SpanFields>()
+            {
+                values.record(fields);
+            }
+        }
+
+        fn on_close(&self, id: tracing::span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let span = ctx.span(&id).unwrap();
+            let fields = span
+                .extensions_mut()
+                .remove::<SpanFields>()
+                .unwrap_or_default();
+            self.spans
+                .lock()
+                .unwrap()
+                .push((span.name().to_string(), fields.0));
+        }
+    }
+
+    /// An endpoint answering every DynamoDB call with one item and every S3 upload with
+    /// an empty 200
+    async fn stub_aws_endpoint() -> String {
+        stub_aws_endpoint_answering(
+            r#"{"Item":{"key":{"S":"user:alice:greeting"},"value":{"S":"very secret value"}}}"#,
+        )
+        .await
+    }
+
+    /// An endpoint answering every DynamoDB call with `body`, and S3 calls with an etag
+    async fn stub_aws_endpoint_answering(body: &'static str) -> String {
+        use axum::http::Method;
+        use axum::response::IntoResponse;
+
+        let app = axum::Router::new().fallback(move |method: Method| async move {
+            if method == Method::POST {
+                ([("content-type", "application/x-amz-json-1.0")], body).into_response()
+            } else {
+                [("etag", "\"stub\"")].into_response()
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    /// An endpoint answering every DynamoDB call with no items, recording each call's
+    /// `X-Amz-Target` and the access key id it was signed with
+    async fn stub_signer_recording_endpoint() -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+        use axum::http::HeaderMap;
+
+        let calls: Arc<Mutex<Vec<(String, String)>>> = Arc::default();
+        let recorded = calls.clone();
+        let app = axum::Router::new().fallback(move |headers: HeaderMap| {
+            let calls = recorded.clone();
+            async move {
+                let header = |name: &str| {
+                    headers
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let access_key = header("authorization")
+                    .split("Credential=")
+                    .nth(1)
+                    .and_then(|credential| credential.split('/').next())
+                    .unwrap_or_default()
+                    .to_string();
+                calls
+                    .lock()
+                    .unwrap()
+                    .push((header("x-amz-target"), access_key));
+                (
+                    [("content-type", "application/x-amz-json-1.0")],
+                    r#"{"Items":[],"Count":0,"ScannedCount":0}"#,
+                )
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}", addr), calls)
+    }
+
+    fn tenant_session(aws_role: Option<crate::tenant::TenantAwsRole>) -> TenantSession {
+        use crate::tenant::{ContextType, ResourceLimits, TenantContext, ToolPolicy, UserRole};
+
+        TenantSession::new(TenantContext {
+            tenant_id: "alice-tenant".to_string(),
+            user_id: "alice".to_string(),
+            context_type: ContextType::Personal,
+            organization_id: "acme".to_string(),
+            role: UserRole::User,
+            permissions: vec![],
+            aws_region: "us-west-2".to_string(),
+            resource_limits: ResourceLimits::default(),
+            tool_policy: ToolPolicy::default(),
+            aws_role,
+        })
+    }
+
+    /// An endpoint keeping the KV items it's given by `PutItem` and answering `GetItem`
+    /// from them, with those items to look at
+    async fn stub_kv_table_endpoint() -> (String, Arc<Mutex<HashMap<String, Value>>>) {
+        use axum::http::HeaderMap;
+
+        let items: Arc<Mutex<HashMap<String, Value>>> = Arc::default();
+        let stored = items.clone();
+        let app = axum::Router::new().fallback(move |headers: HeaderMap, body: String| {
+            let items = stored.clone();
+            async move {
+                let target = headers
+                    .get("x-amz-target")
+                    .and_then(|target| target.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                let request: Value = serde_json::from_str(&body).unwrap_or_default();
+                let response = if target.ends_with(".PutItem") {
+                    let item = request["Item"].clone();
+                    let key = item["key"]["S"].as_str().unwrap_or_default().to_string();
+                    items.lock().unwrap().insert(key, item);
+                    json!({})
+                } else if target.ends_with(".GetItem") {
+                    let key = request["Key"]["key"]["S"].as_str().unwrap_or_default();
+                    match items.lock().unwrap().get(key) {
+                        Some(item) => json!({ "Item": item }),
+                        None => json!({}),
+                    }
+                } else {
+                    json!({})
+                };
+                (
+                    [("content-type", "application/x-amz-json-1.0")],
+                    response.to_string(),
+                )
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}", addr), items)
+    }
+
+    fn sealing_encryptor() -> Option<Arc<EnvelopeEncryptor>> {
+        Some(Arc::new(EnvelopeEncryptor::new(Arc::new(
+            crate::crypto::StaticKeyProvider::new([7; 32]),
+        ))))
+    }
+
+    fn stub_service(endpoint: &str) -> AwsService {
+        stub_service_assuming(endpoint, None)
+    }
+
+    /// Hands out credentials with the access key id `role-key` for every role
+    struct StubAssumer;
+
+    #[async_trait]
+    impl tenant_roles::RoleAssumer for StubAssumer {
+        async fn assume_role(
+            &self,
+            _role: &crate::tenant::TenantAwsRole,
+            _session_name: &str,
+        ) -> Result<aws_sdk_sts::config::Credentials, String> {
+            Ok(aws_sdk_sts::config::Credentials::new(
+                "role-key",
+                "role-secret",
+                Some("role-token".to_string()),
+                Some(std::time::SystemTime::now() + Duration::from_secs(3600)),
+                "stub",
+            ))
+        }
+    }
+
+    /// [`stub_service`] assuming tenant roles with `assumer` instead of STS
+    fn stub_service_assuming(
+        endpoint: &str,
+        assumer: Option<Arc<dyn tenant_roles::RoleAssumer>>,
+    ) -> AwsService {
+        let config = aws_config::SdkConfig::builder()
+            .behavior_version(aws_config::BehaviorVersion::latest())
+            .region(aws_config::Region::new("us-west-2"))
+            .credentials_provider(aws_sdk_dynamodb::config::SharedCredentialsProvider::new(
+                aws_sdk_dynamodb::config::Credentials::new("test", "test", None, None, "test"),
+            ))
+            .endpoint_url(endpoint)
+            .build();
+        let s3 = aws_sdk_s3::config::Builder::from(&config)
+            .force_path_style(true)
+            .build();
+        let clients = Arc::new(AwsClients {
+            dynamodb: DynamoDbClient::new(&config),
+            s3: S3Client::from_conf(s3),
+            eventbridge: EventBridgeClient::new(&config),
+            secrets_manager: SecretsManagerClient::new(&config),
+            kms: KmsClient::new(&config),
+            lambda: LambdaClient::new(&config),
+            cloudwatch: None,
+            sfn: SfnClient::new(&config),
+            sqs: SqsClient::new(&config),
+            sns: SnsClient::new(&config),
+        });
+        let latency = Arc::new(AwsLatency::new());
+        let event_buffer = EventBuffer::spawn(
+            Arc::new(EventBridgeSink::new(
+                clients.eventbridge.clone(),
+                "bus",
+                latency.clone(),
+            )),
+            Arc::new(KvDeadLetters::new(
+                clients.dynamodb.clone(),
+                "kv",
+                latency.clone(),
+            )),
+        );
+        // Role clients reach the stub too
+        let mut aws_config = Config::default().aws;
+        aws_config.endpoints = ServiceEndpoints::resolve(|_| Some(endpoint.to_string()));
+        let tenant_clients = TenantClients::new(
+            config.clone(),
+            &aws_config,
+            clients.clone(),
+            assumer.unwrap_or_else(|| {
+                Arc::new(StsRoleAssumer::new(aws_sdk_sts::Client::new(&config)))
+            }),
+        );
+        AwsService {
+            clients,
+            tenant_clients,
+            kv_table: "kv".to_string(),
+            events_table: "events".to_string(),
+            event_rules_table: "event-rules".to_string(),
+            subscriptions_table: "subscriptions".to_string(),
+            artifacts_bucket: "artifacts".to_string(),
+            event_bus: "bus".to_string(),
+            queue_prefix: "agent-mesh".to_string(),
+            verify_alert_topics: false,
+            encryptor: None,
+            resource_report: None,
+            queue_urls: Mutex::default(),
+            event_buffer,
+            buffer_events: false,
+            kv_cache: None,
+            latency,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aws_calls_are_traced_and_timed() {
+        use crate::tenant::{ContextType, ResourceLimits, TenantContext, ToolPolicy, UserRole};
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let recorder = SpanRecorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+        let aws = stub_service(&stub_aws_endpoint().await);
+        let session = TenantSession::new(TenantContext {
+            tenant_id: "alice-tenant".to_string(),
+            user_id: "alice".to_string(),
+            context_type: ContextType::Personal,
+            organization_id: "acme".to_string(),
+            role: UserRole::User,
+            permissions: vec![],
+            aws_region: "us-west-2".to_string(),
+            resource_limits: ResourceLimits::default(),
+            tool_policy: ToolPolicy::default(),
+            aws_role: None,
+        });
+
+        assert_eq!(
+            aws.kv_get(&session, "greeting").await.unwrap().as_deref(),
+            Some("very secret value")
+        );
+        aws.artifacts_put(&session, "report.txt", &[0; 2048], "text/plain", None)
+            .await
+            .unwrap();
+
+        let spans: Vec<BTreeMap<String, String>> = recorder
+            .spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name == latency::AWS_CALL_SPAN)
+            .map(|(_, fields)| fields.clone())
+            .collect();
+        assert_eq!(spans.len(), 2);
+        let fields = |span: &BTreeMap<String, String>| -> Vec<String> {
+            span.iter()
+                .filter(|(name, _)| name.as_str() != "aws.duration_ms")
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect()
+        };
+        assert_eq!(
+            fields(&spans[0]),
+            vec![
+                "aws.key=user:alice:greeting",
+                "aws.operation=GetItem",
+                "aws.outcome=ok",
+                "aws.resource=kv",
+                "aws.service=dynamodb",
+                "aws.size_class=small",
+            ]
+        );
+        assert_eq!(
+            fields(&spans[1]),
+            vec![
+                "aws.key=user:alice/report.txt",
+                "aws.operation=PutObject",
+                "aws.outcome=ok",
+                "aws.resource=artifacts",
+                "aws.service=s3",
+                "aws.size_class=medium",
+            ]
+        );
+        assert!(spans
+            .iter()
+            .all(|span| span.contains_key("aws.duration_ms")));
+        // Values never land in span fields
+        assert!(spans
+            .iter()
+            .all(|span| span.values().all(|value| !value.contains("secret"))));
+
+        let stats = aws.latency_stats();
+        assert_eq!(stats.keys().collect::<Vec<_>>(), vec!["dynamodb", "s3"]);
+        assert_eq!(stats["dynamodb"].count, 1);
+        assert!(stats["s3"].p99_ms >= stats["s3"].p50_ms);
+    }
+
+    #[tokio::test]
+    async fn test_explained_query_reports_what_dynamodb_read() {
+        let aws = stub_service(
+            &stub_aws_endpoint_answering(
+                r#"{"Items":[{"eventId":{"S":"e1"},"timestamp":{"S":"2026-03-01T10:00:00Z"}}],
+                    "Count":1,"ScannedCount":7,
+                    "ConsumedCapacity":{"TableName":"events","CapacityUnits":0.5}}"#,
+            )
+            .await,
+        );
+        let query = EventQuery {
+            user_id: Some("alice".to_string()),
+            detail_type: Some("task.done".to_string()),
+            start_time: Some("2026-03-01T00:00:00Z".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+
+        let result = aws
+            .query_events_explained(&tenant_session(None), &query, false)
+            .await
+            .unwrap();
+        assert_eq!(result["count"], 1);
+        let explain = &result["explain"];
+        assert_eq!(explain["executed"], true);
+        let path = &explain["accessPaths"][0];
+        assert_eq!(path["index"], "user-index");
+        assert_eq!(
+            path["keyCondition"],
+            "userId = :userId AND timestamp >= :start"
+        );
+        assert_eq!(path["filters"], json!(["detailType = :detailType"]));
+        assert_eq!(path["pagesFetched"], 1);
+        assert_eq!(path["itemsScanned"], 7);
+        assert_eq!(path["itemsMatched"], 1);
+        assert_eq!(path["consumedReadCapacity"], 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_direct_values_are_sealed_and_read_back() {
+        let (endpoint, items) = stub_kv_table_endpoint().await;
+        let mut aws = stub_service(&endpoint);
+        aws.encryptor = sealing_encryptor();
+        let key = internal_keys::connection_key("alice-tenant", "alice", "github", "default");
+
+        aws.kv_set_direct_sensitive("alice-tenant", &key, "oauth-refresh-token", None)
+            .await
+            .unwrap();
+
+        let item = items.lock().unwrap()[&key].clone();
+        let stored = item["value"]["S"].as_str().unwrap();
+        assert_ne!(stored, "oauth-refresh-token");
+        assert!(!stored.contains("oauth-refresh-token"), "{}", item);
+        assert_eq!(
+            aws.kv_get_direct(&key).await.unwrap().as_deref(),
+            Some("oauth-refresh-token")
+        );
+
+        // Without a key to seal with, nothing is written in the clear
+        let unsealed = stub_service(&endpoint);
+        let other = internal_keys::connection_key("bob-tenant", "bob", "github", "default");
+        assert!(matches!(
+            unsealed
+                .kv_set_direct_sensitive("bob-tenant", &other, "token", None)
+                .await,
+            Err(AwsError::Config(_))
+        ));
+        assert!(!items.lock().unwrap().contains_key(&other));
+    }
+
+    #[tokio::test]
+    async fn test_kv_secret_store_seals_credentials() {
+        let (endpoint, items) = stub_kv_table_endpoint().await;
+        let mut aws = stub_service(&endpoint);
+        aws.encryptor = sealing_encryptor();
+        let store = KvSecretStore::new(Arc::new(aws));
+
+        store
+            .secret_put("alice-tenant", "github", "token", "ghp_plaintext")
+            .await
+            .unwrap();
+
+        let key = internal_keys::credential_key("alice-tenant", "github", "token");
+        let item = items.lock().unwrap()[&key].clone();
+        let stored = item["value"]["S"].as_str().unwrap();
+        assert!(!stored.contains("ghp_plaintext"), "{}", item);
+        assert!(item.get("wrapped_key").is_some(), "{}", item);
+        assert_eq!(item["encrypted_for"]["S"], "alice-tenant");
+
+        assert_eq!(
+            store
+                .secret_get("alice-tenant", "github", "token")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("ghp_plaintext")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_queries_and_rules_of_a_role_tenant_use_its_role() {
+        let (endpoint, calls) = stub_signer_recording_endpoint().await;
+        let aws = stub_service_assuming(&endpoint, Some(Arc::new(StubAssumer)));
+        let role_tenant = tenant_session(Some(crate::tenant::TenantAwsRole {
+            assume_role_arn: "arn:aws:iam::210987654321:role/agent-mesh".to_string(),
+            external_id: None,
+        }));
+        let query = EventQuery {
+            user_id: Some("alice".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+
+        aws.query_events_explained(&role_tenant, &query, false)
+            .await
+            .unwrap();
+        aws.analytics_query(
+            &role_tenant,
+            Some("alice".to_string()),
+            None,
+            None,
+            None,
+            vec![],
+            "hourly".to_string(),
+        )
+        .await
+        .unwrap();
+        aws.create_event_rule(&role_tenant, "orders", json!({}), None, true)
+            .await
+            .unwrap();
+        aws.event_rules(&role_tenant).await.unwrap();
+
+        let signed_by = |target: &str| -> Vec<String> {
+            calls
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(call, _)| call.ends_with(target))
+                .map(|(_, access_key)| access_key.clone())
+                .collect()
+        };
+        // The events query, the analytics query and the rule listing
+        assert_eq!(signed_by(".Query"), vec!["role-key"; 3]);
+        // The analytics cache is an internal record, kept in the server's table; the
+        // rule is the tenant's
+        assert_eq!(signed_by(".GetItem"), vec!["test"]);
+        assert_eq!(signed_by(".PutItem"), vec!["test", "role-key"]);
+
+        calls.lock().unwrap().clear();
+        aws.query_events_explained(&tenant_session(None), &query, false)
+            .await
+            .unwrap();
+        assert_eq!(signed_by(".Query"), vec!["test"]);
     }
 }
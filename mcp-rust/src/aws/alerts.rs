@@ -0,0 +1,78 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::AwsError;
+
+/// When `true`, `events_create_alert` also checks that an sns subscription's topic
+/// exists (`GetTopicAttributes`) before storing it
+pub const VERIFY_ALERT_TOPICS_ENV: &str = "AGENT_MESH_VERIFY_ALERT_TOPICS";
+
+/// Detail type of the event published when an alert can't be delivered
+pub const DELIVERY_FAILED_DETAIL_TYPE: &str = "alert.delivery_failed";
+
+/// SNS limits topic names to 256 characters
+const MAX_TOPIC_NAME_LEN: usize = 256;
+
+/// Check that `arn` is a well-formed SNS topic ARN in `region`:
+/// `arn:{partition}:sns:{region}:{12-digit account}:{topic}`. Topics are published to
+/// with the server's regional client, so topics in other regions can't be reached.
+pub fn validate_sns_topic_arn(arn: &str, region: &str) -> Result<(), AwsError> {
+    let invalid = |reason: &str| AwsError::InvalidTopic(format!("{} ({})", arn, reason));
+    let parts: Vec<&str> = arn.split(':').collect();
+    let [prefix, partition, service, topic_region, account, topic] = parts[..] else {
+        return Err(invalid("expected arn:partition:sns:region:account:topic"));
+    };
+    if prefix != "arn" || !partition.starts_with("aws") || service != "sns" {
+        return Err(invalid("not an SNS ARN"));
+    }
+    if topic_region != region {
+        return Err(invalid(&format!("topic must be in {}", region)));
+    }
+    if account.len() != 12 || !account.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid("account must be 12 digits"));
+    }
+    let name = topic.strip_suffix(".fifo").unwrap_or(topic);
+    if name.is_empty()
+        || topic.len() > MAX_TOPIC_NAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(invalid("topic names are 1-256 letters, digits, - or _"));
+    }
+    Ok(())
+}
+
+/// String message attributes published with an alert, so SNS subscription filter
+/// policies can select on them: `detailType`, `priority` (default `medium`, as when
+/// events are stored) and `tenant`, when the event has one
+pub fn alert_message_attributes(event: &Value) -> HashMap<String, String> {
+    let detail = &event["detail"];
+    let priority = event["priority"]
+        .as_str()
+        .or_else(|| detail["priority"].as_str())
+        .unwrap_or("medium");
+    let tenant = event["tenantId"]
+        .as_str()
+        .or_else(|| detail["tenant_id"].as_str());
+
+    let mut attributes = HashMap::from([("priority".to_string(), priority.to_string())]);
+    if let Some(detail_type) = event["detailType"].as_str() {
+        attributes.insert("detailType".to_string(), detail_type.to_string());
+    }
+    if let Some(tenant) = tenant {
+        attributes.insert("tenant".to_string(), tenant.to_string());
+    }
+    attributes
+}
+
+/// An alert subscription to create along with its rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewAlertSubscription {
+    pub name: String,
+    /// `sns` or `email`
+    pub notification_method: String,
+    pub sns_topic_arn: Option<String>,
+    pub email_address: Option<String>,
+    pub enabled: bool,
+}
@@ -0,0 +1,208 @@
+use aws_sdk_s3::types::{
+    ExpirationStatus, LifecycleExpiration, LifecycleRule, LifecycleRuleAndOperator,
+    LifecycleRuleFilter, Tag,
+};
+use serde::{Deserialize, Serialize};
+
+use super::AwsError;
+use crate::tenant::TenantSession;
+
+/// Object tag the bucket's lifecycle rules expire artifacts by
+pub const TTL_CLASS_TAG: &str = "ttl-class";
+
+/// How long an artifact is kept, set with `artifacts_put`'s `ttl_class` and enforced by
+/// the bucket's lifecycle rules rather than by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TtlClass {
+    Short,
+    Medium,
+    Long,
+}
+
+impl TtlClass {
+    pub const ALL: [TtlClass; 3] = [TtlClass::Short, TtlClass::Medium, TtlClass::Long];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TtlClass::Short => "short",
+            TtlClass::Medium => "medium",
+            TtlClass::Long => "long",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.as_str() == value)
+    }
+
+    /// `PutObject`'s `x-amz-tagging` for an object of this class
+    pub fn tagging(&self) -> String {
+        format!("{}={}", TTL_CLASS_TAG, self.as_str())
+    }
+}
+
+/// Days artifacts of each TTL class are kept after they're written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RetentionDays {
+    pub short: i32,
+    pub medium: i32,
+    pub long: i32,
+}
+
+impl Default for RetentionDays {
+    fn default() -> Self {
+        Self {
+            short: 7,
+            medium: 30,
+            long: 365,
+        }
+    }
+}
+
+impl RetentionDays {
+    pub fn days(&self, class: TtlClass) -> i32 {
+        match class {
+            TtlClass::Short => self.short,
+            TtlClass::Medium => self.medium,
+            TtlClass::Long => self.long,
+        }
+    }
+
+    /// Every class keeps artifacts at least a day, and no shorter than the class before
+    pub fn validate(&self) -> Result<(), String> {
+        if self.short < 1 {
+            return Err("retention must be at least 1 day".to_string());
+        }
+        if self.short > self.medium || self.medium > self.long {
+            return Err(format!(
+                "retention must not shrink from short to long: {} / {} / {} days",
+                self.short, self.medium, self.long
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What `artifacts_head` reports about an artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtifactMetadata {
+    pub size: u64,
+    pub content_type: Option<String>,
+    /// RFC 3339
+    pub last_modified: Option<String>,
+    pub ttl_class: Option<TtlClass>,
+}
+
+/// The artifacts `artifacts_delete_prefix` deleted, or would delete in a dry run; keys
+/// are relative to the namespace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ArtifactDeletion {
+    pub keys: Vec<String>,
+    pub bytes: u64,
+}
+
+/// Where a page of `artifacts_keys` continues: in the namespace's listing or the legacy
+/// prefix's, from the continuation token of the listing (its start without one)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactPosition {
+    pub legacy: bool,
+    pub token: Option<String>,
+}
+
+impl ArtifactPosition {
+    pub fn parse(position: &str) -> Result<Self, AwsError> {
+        Ok(serde_json::from_str(position)?)
+    }
+}
+
+impl std::fmt::Display for ArtifactPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let position = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&position)
+    }
+}
+
+/// The bucket lifecycle rule expiring one TTL class under a tenant's prefix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtifactLifecycleRule {
+    pub id: String,
+    pub prefix: String,
+    pub ttl_class: TtlClass,
+    pub expiration_days: i32,
+}
+
+impl ArtifactLifecycleRule {
+    /// The S3 rule: expire objects under the prefix tagged with the class
+    pub fn to_s3(&self) -> Result<LifecycleRule, AwsError> {
+        let tag = Tag::builder()
+            .key(TTL_CLASS_TAG)
+            .value(self.ttl_class.as_str())
+            .build()
+            .map_err(|e| AwsError::S3(e.to_string()))?;
+        LifecycleRule::builder()
+            .id(&self.id)
+            .status(ExpirationStatus::Enabled)
+            .filter(
+                LifecycleRuleFilter::builder()
+                    .and(
+                        LifecycleRuleAndOperator::builder()
+                            .prefix(&self.prefix)
+                            .tags(tag)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .expiration(
+                LifecycleExpiration::builder()
+                    .days(self.expiration_days)
+                    .build(),
+            )
+            .build()
+            .map_err(|e| AwsError::S3(e.to_string()))
+    }
+}
+
+/// Key prefix of all of a tenant's artifacts: the user's namespace in a personal
+/// context, every member's namespace in an organization
+pub fn tenant_artifact_prefix(session: &TenantSession) -> String {
+    match session.context.get_org_id() {
+        Some(org_id) => format!("org:{}:user:", org_id),
+        None => format!("{}/", session.context.get_namespace_prefix()),
+    }
+}
+
+/// One rule per TTL class for the artifacts under `prefix`. Ids are derived from the
+/// prefix, so configuring a tenant again replaces its rules.
+pub fn tenant_lifecycle_rules(
+    prefix: &str,
+    retention: &RetentionDays,
+) -> Vec<ArtifactLifecycleRule> {
+    TtlClass::ALL
+        .into_iter()
+        .map(|class| ArtifactLifecycleRule {
+            id: format!("artifact-ttl:{}:{}", prefix, class.as_str()),
+            prefix: prefix.to_string(),
+            ttl_class: class,
+            expiration_days: retention.days(class),
+        })
+        .collect()
+}
+
+/// The bucket's rules after installing `rules`: existing rules with the same ids are
+/// replaced, every other rule is kept as it was
+pub fn merge_lifecycle_rules(
+    existing: &[LifecycleRule],
+    rules: Vec<LifecycleRule>,
+) -> Vec<LifecycleRule> {
+    let mut merged: Vec<LifecycleRule> = existing
+        .iter()
+        .filter(|rule| {
+            !rules
+                .iter()
+                .any(|new| new.id().is_some() && new.id() == rule.id())
+        })
+        .cloned()
+        .collect();
+    merged.extend(rules);
+    merged
+}
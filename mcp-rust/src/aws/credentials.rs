@@ -0,0 +1,122 @@
+use aws_sdk_sts::config::ProvideCredentials;
+use aws_sdk_sts::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use std::time::Duration;
+
+use super::{load_sdk_config, AwsError};
+use crate::config::{AwsConfig, Backend};
+
+/// Set to `true` to run on the in-memory backend when no usable AWS credentials are
+/// found at startup, instead of refusing to start
+pub const ALLOW_OFFLINE_ENV: &str = "MCP_ALLOW_OFFLINE";
+
+/// How long finding credentials, and then `sts:GetCallerIdentity`, may each take
+pub const IDENTITY_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the startup identity check found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentityCheck {
+    /// The credentials work, as this account and principal
+    Valid { account: String, arn: String },
+    /// No credentials in the environment, the shared config files or an instance or
+    /// task role
+    Missing(String),
+    /// AWS refused the credentials found, e.g. because they expired
+    Rejected(String),
+    /// STS couldn't be reached or didn't answer in time, so the credentials may still
+    /// work
+    Unreachable(String),
+}
+
+/// Look for credentials the way the clients will, and ask STS who they belong to
+pub async fn check_identity(config: &AwsConfig, timeout: Duration) -> IdentityCheck {
+    let sdk_config = load_sdk_config(config).await;
+    let Some(provider) = sdk_config.credentials_provider() else {
+        return IdentityCheck::Missing("no credentials provider is configured".to_string());
+    };
+    match tokio::time::timeout(timeout, provider.provide_credentials()).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => {
+            // The provider chain's error lists every provider it tried; too much for
+            // serverInfo
+            tracing::debug!("No AWS credentials: {}", DisplayErrorContext(&e));
+            return IdentityCheck::Missing(
+                "none in the environment, the shared config files or an instance or task role"
+                    .to_string(),
+            );
+        }
+        Err(_) => {
+            return IdentityCheck::Missing(format!("none found within {}s", timeout.as_secs()))
+        }
+    }
+
+    let sts = aws_sdk_sts::Client::new(&sdk_config);
+    match tokio::time::timeout(timeout, sts.get_caller_identity().send()).await {
+        Ok(Ok(identity)) => IdentityCheck::Valid {
+            account: identity.account().unwrap_or_default().to_string(),
+            arn: identity.arn().unwrap_or_default().to_string(),
+        },
+        Ok(Err(SdkError::ServiceError(e))) => IdentityCheck::Rejected(format!(
+            "{}: {}",
+            e.err().code().unwrap_or("unknown error"),
+            e.err().message().unwrap_or_default()
+        )),
+        Ok(Err(e)) => IdentityCheck::Unreachable(DisplayErrorContext(&e).to_string()),
+        Err(_) => IdentityCheck::Unreachable(format!("no answer within {}s", timeout.as_secs())),
+    }
+}
+
+/// What the server does about `check`: `Ok(None)` to run on AWS, `Ok(Some(reason))` to
+/// fall back to the in-memory backend (only with `allow_offline`), or an error saying
+/// how to fix the credentials. An unreachable STS is no reason not to try AWS.
+pub fn offline_fallback(
+    check: &IdentityCheck,
+    allow_offline: bool,
+) -> Result<Option<String>, AwsError> {
+    let problem = match check {
+        IdentityCheck::Valid { .. } | IdentityCheck::Unreachable(_) => return Ok(None),
+        IdentityCheck::Missing(detail) => format!("no AWS credentials found ({})", detail),
+        IdentityCheck::Rejected(detail) => {
+            format!("AWS rejected the configured credentials ({})", detail)
+        }
+    };
+    if allow_offline {
+        return Ok(Some(problem));
+    }
+    Err(AwsError::Config(format!(
+        "{}. Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or AWS_PROFILE, or run with \
+         an instance or task role; or run offline on the in-memory backend with \
+         --backend memory or {}=true",
+        problem, ALLOW_OFFLINE_ENV
+    )))
+}
+
+/// Check at startup that the AWS backend has usable credentials. Without them the
+/// server refuses to start, or with `allow_offline` switches `config` to the in-memory
+/// backend, recording why. The in-memory backend and emulator endpoints aren't checked.
+pub async fn ensure_credentials(config: &mut AwsConfig) -> Result<(), AwsError> {
+    if config.backend != Backend::Aws || config.endpoints.any_overridden() {
+        return Ok(());
+    }
+    match check_identity(config, IDENTITY_CHECK_TIMEOUT).await {
+        IdentityCheck::Valid { arn, .. } => {
+            eprintln!("[MCP Server] AWS identity: {}", arn);
+        }
+        IdentityCheck::Unreachable(detail) => {
+            eprintln!(
+                "[MCP Server] Could not check the AWS credentials ({}); starting anyway",
+                detail
+            );
+        }
+        check => {
+            if let Some(reason) = offline_fallback(&check, config.allow_offline)? {
+                eprintln!(
+                    "[MCP Server] Running OFFLINE on the in-memory backend: {}",
+                    reason
+                );
+                config.backend = Backend::Memory;
+                config.offline_reason = Some(reason);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,304 @@
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use aws_sdk_eventbridge::types::PutEventsRequestEntry;
+use aws_sdk_eventbridge::Client as EventBridgeClient;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use super::internal_keys;
+use super::latency::{AwsCall, AwsLatency};
+use super::{sdk_error, AwsError};
+
+/// When `true`, `events_send` buffers events unless the call passes `buffered: false`
+pub const BUFFER_EVENTS_ENV: &str = "AGENT_MESH_BUFFER_EVENTS";
+
+/// Most entries EventBridge accepts in one `PutEvents` call
+pub const EVENT_BATCH_SIZE: usize = 10;
+
+/// How long an event can wait in the buffer before it's sent
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// KV key prefix of the events that couldn't be delivered
+pub const DEAD_LETTER_PREFIX: &str = "events-dead-letter-";
+
+/// Dead letters are kept for a week
+const DEAD_LETTER_TTL_HOURS: u32 = 7 * 24;
+
+/// An event accepted by the buffer but not yet sent
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BufferedEvent {
+    /// Provisional id returned to the caller; EventBridge assigns its own on delivery
+    pub event_id: String,
+    pub tenant_id: String,
+    pub source: String,
+    pub detail_type: String,
+    pub detail: Value,
+    pub queued_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Where buffered events are sent
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Send a batch of at most [`EVENT_BATCH_SIZE`] events. Returns each event's error,
+    /// in order (`None` once delivered); `Err` if the whole batch failed.
+    async fn put_events(&self, events: &[BufferedEvent]) -> Result<Vec<Option<String>>, AwsError>;
+}
+
+/// Where events that couldn't be delivered are kept
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    async fn store(&self, event: &BufferedEvent, error: &str) -> Result<(), AwsError>;
+}
+
+/// Write-behind buffer for `PutEvents`: events are accepted at once and sent per tenant
+/// in batches of [`EVENT_BATCH_SIZE`], as soon as a batch is full or after
+/// [`FLUSH_INTERVAL`]. Events that still fail are moved to the dead-letter store.
+pub struct EventBuffer {
+    sink: Arc<dyn EventSink>,
+    dead_letters: Arc<dyn DeadLetterStore>,
+    /// Events waiting to be sent, by tenant, oldest first
+    pending: Mutex<HashMap<String, Vec<BufferedEvent>>>,
+    /// Wakes the flusher when a tenant's batch is full
+    batch_full: Notify,
+    /// Tells the flusher to drain the buffer and stop
+    stop: Notify,
+    closed: AtomicBool,
+    flusher: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventBuffer {
+    /// A buffer with its background flusher running
+    pub fn spawn(sink: Arc<dyn EventSink>, dead_letters: Arc<dyn DeadLetterStore>) -> Arc<Self> {
+        let buffer = Arc::new(Self {
+            sink,
+            dead_letters,
+            pending: Mutex::default(),
+            batch_full: Notify::new(),
+            stop: Notify::new(),
+            closed: AtomicBool::new(false),
+            flusher: Mutex::default(),
+        });
+        let handle = tokio::spawn(buffer.clone().run());
+        *buffer.flusher.lock().unwrap() = Some(handle);
+        buffer
+    }
+
+    /// Whether `shutdown` has been called; events are then no longer accepted
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Queue an event for its tenant's next batch; returns its provisional id. Once the
+    /// buffer is closed the event is handed back instead.
+    pub fn push(
+        &self,
+        tenant_id: &str,
+        source: &str,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<String, Value> {
+        if self.is_closed() {
+            return Err(detail);
+        }
+        let event = BufferedEvent {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            tenant_id: tenant_id.to_string(),
+            source: source.to_string(),
+            detail_type: detail_type.to_string(),
+            detail,
+            queued_at: chrono::Utc::now(),
+        };
+        let event_id = event.event_id.clone();
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            let batch = pending.entry(tenant_id.to_string()).or_default();
+            batch.push(event);
+            batch.len() >= EVENT_BATCH_SIZE
+        };
+        if full {
+            self.batch_full.notify_one();
+        }
+        Ok(event_id)
+    }
+
+    /// Events waiting to be sent
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    /// Stop accepting events and send everything still buffered; returns once the
+    /// buffer is empty. Safe to call more than once.
+    pub async fn shutdown(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.stop.notify_one();
+        let flusher = self.flusher.lock().unwrap().take();
+        if let Some(flusher) = flusher {
+            if let Err(e) = flusher.await {
+                warn!("Event buffer flusher failed: {}", e);
+            }
+        }
+    }
+
+    async fn run(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes at once
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = self.batch_full.notified() => self.flush(false).await,
+                _ = ticker.tick() => self.flush(true).await,
+                _ = self.stop.notified() => {
+                    self.flush(true).await;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Send the full batches, or with `all` everything buffered
+    async fn flush(&self, all: bool) {
+        let batches: Vec<Vec<BufferedEvent>> = {
+            let mut pending = self.pending.lock().unwrap();
+            let mut batches = Vec::new();
+            for events in pending.values_mut() {
+                while events.len() >= EVENT_BATCH_SIZE || (all && !events.is_empty()) {
+                    let size = events.len().min(EVENT_BATCH_SIZE);
+                    batches.push(events.drain(..size).collect());
+                }
+            }
+            pending.retain(|_, events| !events.is_empty());
+            batches
+        };
+        for batch in batches {
+            self.send(batch).await;
+        }
+    }
+
+    async fn send(&self, batch: Vec<BufferedEvent>) {
+        let errors = match self.sink.put_events(&batch).await {
+            Ok(errors) => errors,
+            Err(e) => vec![Some(e.to_string()); batch.len()],
+        };
+        for (index, event) in batch.iter().enumerate() {
+            // A missing result counts as a failure, as EventBridge reports one per entry
+            let error = match errors.get(index) {
+                Some(None) => continue,
+                Some(Some(error)) => error.clone(),
+                None => "no result for the event".to_string(),
+            };
+            warn!(
+                "Event {} for tenant {} was not delivered: {}",
+                event.event_id, event.tenant_id, error
+            );
+            if let Err(e) = self.dead_letters.store(event, &error).await {
+                warn!("Failed to dead-letter event {}: {}", event.event_id, e);
+            }
+        }
+    }
+}
+
+/// [`EventSink`] over EventBridge `PutEvents`
+pub struct EventBridgeSink {
+    client: EventBridgeClient,
+    event_bus: String,
+    latency: Arc<AwsLatency>,
+}
+
+impl EventBridgeSink {
+    pub fn new(client: EventBridgeClient, event_bus: &str, latency: Arc<AwsLatency>) -> Self {
+        Self {
+            client,
+            event_bus: event_bus.to_string(),
+            latency,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for EventBridgeSink {
+    async fn put_events(&self, events: &[BufferedEvent]) -> Result<Vec<Option<String>>, AwsError> {
+        let mut size = 0;
+        let entries = events
+            .iter()
+            .map(|event| {
+                let detail = serde_json::to_string(&event.detail)?;
+                size += detail.len();
+                Ok(PutEventsRequestEntry::builder()
+                    .source(&event.source)
+                    .detail_type(&event.detail_type)
+                    .detail(detail)
+                    .event_bus_name(&self.event_bus)
+                    .build())
+            })
+            .collect::<Result<Vec<_>, AwsError>>()?;
+        let output = AwsCall::new(&self.latency, "eventbridge", "PutEvents", &self.event_bus)
+            .size(size)
+            .run(self.client.put_events().set_entries(Some(entries)).send())
+            .await
+            .map_err(|e| sdk_error(e, "eventbridge_put", AwsError::EventBridge))?;
+        Ok(output
+            .entries()
+            .iter()
+            .map(|entry| {
+                entry
+                    .error_code()
+                    .map(|code| format!("{}: {}", code, entry.error_message().unwrap_or_default()))
+            })
+            .collect())
+    }
+}
+
+/// [`DeadLetterStore`] keeping each event as a KV item `events-dead-letter-{event_id}`
+/// for a week, with the error that stopped it
+pub struct KvDeadLetters {
+    client: DynamoDbClient,
+    kv_table: String,
+    latency: Arc<AwsLatency>,
+}
+
+impl KvDeadLetters {
+    pub fn new(client: DynamoDbClient, kv_table: &str, latency: Arc<AwsLatency>) -> Self {
+        Self {
+            client,
+            kv_table: kv_table.to_string(),
+            latency,
+        }
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for KvDeadLetters {
+    async fn store(&self, event: &BufferedEvent, error: &str) -> Result<(), AwsError> {
+        let mut record = serde_json::to_value(event)?;
+        record["error"] = Value::String(error.to_string());
+        let record = record.to_string();
+        let key = internal_keys::dead_letter_key(&event.event_id);
+        let now = chrono::Utc::now().timestamp();
+        let expiry = now + DEAD_LETTER_TTL_HOURS as i64 * 3600;
+        let request = self
+            .client
+            .put_item()
+            .table_name(&self.kv_table)
+            .item("key", AttributeValue::S(key.clone()))
+            .item("value", AttributeValue::S(record.clone()))
+            .item("created_at", AttributeValue::N(now.to_string()))
+            .item("expires_at", AttributeValue::N(expiry.to_string()));
+        AwsCall::new(&self.latency, "dynamodb", "PutItem", &self.kv_table)
+            .key(&key)
+            .size(record.len())
+            .run(request.send())
+            .await
+            .map_err(|e| sdk_error(e, "dynamodb_write", AwsError::DynamoDb))?;
+        Ok(())
+    }
+}
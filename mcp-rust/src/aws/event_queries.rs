@@ -0,0 +1,261 @@
+use aws_sdk_dynamodb::types::AttributeValue;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::namespaces::EventScope;
+use super::AwsError;
+
+/// An `events_query`, as its arguments give it
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    /// Only the events in scope; replaces `user_id` and `organization_id`
+    pub scope: Option<EventScope>,
+    pub user_id: Option<String>,
+    pub organization_id: Option<String>,
+    pub source: Option<String>,
+    pub detail_type: Option<String>,
+    pub priority: Option<String>,
+    pub link_id: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub limit: i32,
+    pub exclusive_start_key: Option<String>,
+    pub ascending: bool,
+}
+
+/// A test DynamoDB applies to the items a query reads, as part of its filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    /// The attribute has this value
+    Equals(&'static str, String),
+    /// The item has no such attribute
+    Absent(&'static str),
+}
+
+impl Predicate {
+    fn expression(&self) -> String {
+        match self {
+            Predicate::Equals(attribute, _) => format!("#{} = :{}", attribute, attribute),
+            Predicate::Absent(attribute) => format!("attribute_not_exists(#{})", attribute),
+        }
+    }
+
+    fn attribute(&self) -> &'static str {
+        match self {
+            Predicate::Equals(attribute, _) | Predicate::Absent(attribute) => attribute,
+        }
+    }
+}
+
+/// One page of a `Query` on an index of the events table: items whose `key_attribute`
+/// is `key_value`, in the timestamp range, that pass `filters`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexQuery {
+    pub index: &'static str,
+    pub key_attribute: &'static str,
+    pub key_value: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// Applied after items are read, so they cost read capacity whether they match or not
+    pub filters: Vec<Predicate>,
+    pub limit: i32,
+    pub ascending: bool,
+}
+
+impl IndexQuery {
+    /// The key condition expression, e.g. `#userId = :userId AND #timestamp >= :start`
+    pub fn key_condition(&self) -> String {
+        let key = format!("#{} = :{}", self.key_attribute, self.key_attribute);
+        match (&self.start_time, &self.end_time) {
+            (Some(_), Some(_)) => format!("{} AND #timestamp BETWEEN :start AND :end", key),
+            (Some(_), None) => format!("{} AND #timestamp >= :start", key),
+            (None, Some(_)) => format!("{} AND #timestamp <= :end", key),
+            (None, None) => key,
+        }
+    }
+
+    /// The filter expression, when there are filters
+    pub fn filter_expression(&self) -> Option<String> {
+        (!self.filters.is_empty()).then(|| {
+            self.filters
+                .iter()
+                .map(Predicate::expression)
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        })
+    }
+
+    /// `#name` placeholders of both expressions
+    pub fn attribute_names(&self) -> HashMap<String, String> {
+        let mut names = HashMap::from([(
+            format!("#{}", self.key_attribute),
+            self.key_attribute.to_string(),
+        )]);
+        if self.start_time.is_some() || self.end_time.is_some() {
+            names.insert("#timestamp".to_string(), "timestamp".to_string());
+        }
+        for filter in &self.filters {
+            names.insert(
+                format!("#{}", filter.attribute()),
+                filter.attribute().to_string(),
+            );
+        }
+        names
+    }
+
+    /// `:value` placeholders of both expressions
+    pub fn attribute_values(&self) -> HashMap<String, AttributeValue> {
+        let mut values = HashMap::from([(
+            format!(":{}", self.key_attribute),
+            AttributeValue::S(self.key_value.clone()),
+        )]);
+        if let Some(start) = &self.start_time {
+            values.insert(":start".to_string(), AttributeValue::S(start.clone()));
+        }
+        if let Some(end) = &self.end_time {
+            values.insert(":end".to_string(), AttributeValue::S(end.clone()));
+        }
+        for filter in &self.filters {
+            if let Predicate::Equals(attribute, value) = filter {
+                values.insert(format!(":{}", attribute), AttributeValue::S(value.clone()));
+            }
+        }
+        values
+    }
+
+    /// The access path for `explain`, with attribute names in place of placeholders
+    fn explain(&self) -> Value {
+        json!({
+            "index": self.index,
+            "keyCondition": self.key_condition().replace('#', ""),
+            "filters": self
+                .filters
+                .iter()
+                .map(|filter| filter.expression().replace('#', ""))
+                .collect::<Vec<_>>(),
+            "scanForward": self.ascending,
+            "limit": self.limit,
+            // One page each: `Limit` caps the items a page reads, and only the first
+            // page is fetched
+            "estimatedPages": 1,
+        })
+    }
+}
+
+/// What running one [`IndexQuery`] read
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexQueryStats {
+    pub pages_fetched: u32,
+    /// Items read from the index, before filters
+    pub items_scanned: u64,
+    /// Items that passed the filters
+    pub items_matched: u64,
+    /// Read capacity units consumed, when DynamoDB reported them
+    pub consumed_read_capacity: Option<f64>,
+}
+
+/// How an [`EventQuery`] is answered: the index queries it's run as, whose events are
+/// merged newest or oldest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventQueryPlan {
+    pub queries: Vec<IndexQuery>,
+}
+
+impl EventQueryPlan {
+    /// A scoped query reads the `namespace-index`, plus the `user-index` for the scope's
+    /// untagged records. Otherwise the `user-index` is used when there's a `user_id`, and
+    /// the `timestamp-index` (keyed by source) when there's only a `source`; without
+    /// either the query would need a table scan and is refused. Timestamps go into the
+    /// key condition; the other arguments become filters.
+    pub fn new(query: &EventQuery) -> Result<Self, AwsError> {
+        let filters = |attributes: &[(&'static str, &Option<String>)]| {
+            attributes
+                .iter()
+                .filter_map(|(attribute, value)| {
+                    value
+                        .as_ref()
+                        .map(|value| Predicate::Equals(attribute, value.clone()))
+                })
+                .collect::<Vec<_>>()
+        };
+        let index_query = |index, key_attribute, key_value: &str, filters| IndexQuery {
+            index,
+            key_attribute,
+            key_value: key_value.to_string(),
+            start_time: query.start_time.clone(),
+            end_time: query.end_time.clone(),
+            filters,
+            limit: query.limit,
+            ascending: query.ascending,
+        };
+        let source = ("source", &query.source);
+        let detail_type = ("detailType", &query.detail_type);
+        let priority = ("priority", &query.priority);
+        let link_id = ("linkId", &query.link_id);
+        let organization_id = ("organizationId", &query.organization_id);
+
+        let queries = match (&query.scope, &query.user_id, &query.source) {
+            (Some(scope), _, _) => {
+                let scoped = filters(&[source, detail_type, priority, link_id]);
+                let mut queries = vec![index_query(
+                    "namespace-index",
+                    "namespace",
+                    &scope.namespace,
+                    scoped.clone(),
+                )];
+                if let Some(user_id) = &scope.legacy_user_id {
+                    let mut untagged = scoped;
+                    untagged.push(Predicate::Absent("namespace"));
+                    queries.push(index_query("user-index", "userId", user_id, untagged));
+                }
+                queries
+            }
+            (None, Some(user_id), _) => vec![index_query(
+                "user-index",
+                "userId",
+                user_id,
+                filters(&[source, detail_type, priority, link_id, organization_id]),
+            )],
+            (None, None, Some(source)) => vec![index_query(
+                "timestamp-index",
+                "source",
+                source,
+                filters(&[detail_type, priority, link_id, organization_id]),
+            )],
+            (None, None, None) => {
+                return Err(AwsError::Config(
+                    "Query requires userId or source filter to avoid expensive scan".to_string(),
+                ))
+            }
+        };
+        Ok(Self { queries })
+    }
+
+    /// The `explain` of an `events_query` answered with this plan, with what each index
+    /// query read when it was run (`stats`, in the same order)
+    pub fn explain(&self, stats: Option<&[IndexQueryStats]>) -> Value {
+        let access_paths: Vec<Value> = self
+            .queries
+            .iter()
+            .enumerate()
+            .map(|(i, query)| {
+                let mut path = query.explain();
+                if let Some(stats) = stats.and_then(|stats| stats.get(i)) {
+                    if let (Value::Object(path), Ok(Value::Object(stats))) =
+                        (&mut path, serde_json::to_value(stats))
+                    {
+                        path.extend(stats);
+                    }
+                }
+                path
+            })
+            .collect();
+        json!({
+            "accessPaths": access_paths,
+            "estimatedPages": self.queries.len(),
+            "executed": stats.is_some(),
+        })
+    }
+}
@@ -0,0 +1,206 @@
+use super::event_buffer::DEAD_LETTER_PREFIX;
+use super::AwsError;
+
+/// Registered MCP server configs: `mcp-registry-{context_id}:{server_id}`. Context, user
+/// and organization ids may contain `-` but never `:`, and the ids after them neither, so
+/// the parts of a key can't run into each other.
+pub const REGISTRY_KEY_PREFIX: &str = "mcp-registry-";
+
+/// MCP server credentials kept in the table instead of Secrets Manager:
+/// `mcp-credential-{context_id}:{server_id}:{field}`
+pub const CREDENTIAL_KEY_PREFIX: &str = "mcp-credential-";
+
+/// Integration definitions: `integration-{service_id}`
+pub const INTEGRATION_KEY_PREFIX: &str = "integration-";
+
+/// A user's integration connections in a tenant:
+/// `connection-{tenant_id}:{user_id}:{service_id}:{connection_id}`
+pub const CONNECTION_KEY_PREFIX: &str = "connection-";
+
+/// A user's integration connections before they were kept per tenant:
+/// `user-{user_id}:integration:{service_id}:{connection_id}`. They're still read (and
+/// removed on disconnecting) until the user connects again.
+pub const USER_KEY_PREFIX: &str = "user-";
+
+/// Integration connections shared with an organization:
+/// `org-{org_id}:integration:{service_id}:{connection_id}`
+pub const ORG_KEY_PREFIX: &str = "org-";
+
+/// Cached `analytics_query` results: `analytics-{scope}:{start}:{end}`
+pub const ANALYTICS_KEY_PREFIX: &str = "analytics-";
+
+/// Responses kept for replaying tool calls with an idempotency key:
+/// `idempotency-{tenant_id}:{tool}:{key}`
+pub const IDEMPOTENCY_KEY_PREFIX: &str = "idempotency-";
+
+/// Key prefixes of the internal records the direct KV calls (`kv_get_direct`,
+/// `kv_set_direct`, `kv_list`, `kv_delete`) may touch. Everything else in the table is
+/// tenant data, reached only through the session-scoped calls.
+pub const DIRECT_KEY_PREFIXES: &[&str] = &[
+    REGISTRY_KEY_PREFIX,
+    CREDENTIAL_KEY_PREFIX,
+    INTEGRATION_KEY_PREFIX,
+    CONNECTION_KEY_PREFIX,
+    USER_KEY_PREFIX,
+    ORG_KEY_PREFIX,
+    ANALYTICS_KEY_PREFIX,
+    IDEMPOTENCY_KEY_PREFIX,
+    DEAD_LETTER_PREFIX,
+];
+
+/// Longest direct key accepted; DynamoDB allows 2KB, no internal key comes close
+const MAX_DIRECT_KEY_LEN: usize = 512;
+
+/// Longest id component (service, connection or credential field)
+pub const MAX_ID_LEN: usize = 64;
+
+/// Reject direct access to keys (or list prefixes) that aren't an internal key: one of
+/// [`DIRECT_KEY_PREFIXES`] followed by letters, digits and `-_.@:+` only. Tenant keys
+/// (`user:alice:notes`) never start with an internal prefix, and path or control
+/// characters can't sneak another record's key in.
+pub fn validate_direct_key(key: &str) -> Result<(), AwsError> {
+    let known_prefix = DIRECT_KEY_PREFIXES
+        .iter()
+        .any(|prefix| key.starts_with(prefix));
+    let safe = key.len() <= MAX_DIRECT_KEY_LEN
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | ':' | '+'));
+    if known_prefix && safe {
+        Ok(())
+    } else {
+        Err(AwsError::InvalidKey(key.to_string()))
+    }
+}
+
+/// Ids that become part of an internal key (service and connection ids) are 1-64
+/// lowercase letters, digits, `-` or `_`, so they can't carry another tenant's key or a
+/// separator in. `field` names the argument in the error.
+pub fn validate_id(field: &str, id: &str) -> Result<(), String> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} {:?} must be 1-{} lowercase letters, digits, '-' or '_'",
+            field, id, MAX_ID_LEN
+        ))
+    }
+}
+
+/// Credential field names are the environment variables the server gets, so they keep
+/// their case, and are letters, digits and `_` only (see `KvSecretStore::secret_fields`)
+pub fn validate_credential_field(field: &str) -> Result<(), String> {
+    let valid = !field.is_empty()
+        && field.len() <= MAX_ID_LEN
+        && field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "credential field {:?} must be 1-{} letters, digits or '_'",
+            field, MAX_ID_LEN
+        ))
+    }
+}
+
+pub fn registry_key(context_id: &str, server_id: &str) -> String {
+    format!("{}{}:{}", REGISTRY_KEY_PREFIX, context_id, server_id)
+}
+
+/// Prefix of every server config registered in a context
+pub fn registry_prefix(context_id: &str) -> String {
+    format!("{}{}:", REGISTRY_KEY_PREFIX, context_id)
+}
+
+/// With an empty `field`, the prefix of all of the server's credential fields
+pub fn credential_key(context_id: &str, server_id: &str, field: &str) -> String {
+    format!(
+        "{}{}:{}:{}",
+        CREDENTIAL_KEY_PREFIX, context_id, server_id, field
+    )
+}
+
+pub fn integration_key(service_id: &str) -> String {
+    format!("{}{}", INTEGRATION_KEY_PREFIX, service_id)
+}
+
+pub fn connection_key(
+    tenant_id: &str,
+    user_id: &str,
+    service_id: &str,
+    connection_id: &str,
+) -> String {
+    format!(
+        "{}{}:{}",
+        connection_prefix(tenant_id, user_id),
+        service_id,
+        connection_id
+    )
+}
+
+/// Prefix of every integration connection of a user in a tenant
+pub fn connection_prefix(tenant_id: &str, user_id: &str) -> String {
+    format!("{}{}:", tenant_connection_prefix(tenant_id), user_id)
+}
+
+/// Prefix of every integration connection of every user of a tenant
+pub fn tenant_connection_prefix(tenant_id: &str) -> String {
+    format!("{}{}:", CONNECTION_KEY_PREFIX, tenant_id)
+}
+
+/// Where a user's connection was kept before connections were kept per tenant
+pub fn legacy_connection_key(user_id: &str, service_id: &str, connection_id: &str) -> String {
+    format!(
+        "{}{}:integration:{}:{}",
+        USER_KEY_PREFIX, user_id, service_id, connection_id
+    )
+}
+
+/// Prefix of every integration connection of a user kept before connections were kept
+/// per tenant, in any tenant
+pub fn legacy_connection_prefix(user_id: &str) -> String {
+    format!("{}{}:integration:", USER_KEY_PREFIX, user_id)
+}
+
+pub fn org_connection_key(org_id: &str, service_id: &str, connection_id: &str) -> String {
+    format!(
+        "{}{}:integration:{}:{}",
+        ORG_KEY_PREFIX, org_id, service_id, connection_id
+    )
+}
+
+/// Prefix of every integration connection shared with an organization
+pub fn org_connection_prefix(org_id: &str) -> String {
+    format!("{}{}:integration:", ORG_KEY_PREFIX, org_id)
+}
+
+pub fn analytics_cache_key(scope: &str, start_time: &str, end_time: &str) -> String {
+    format!(
+        "{}{}:{}:{}",
+        ANALYTICS_KEY_PREFIX, scope, start_time, end_time
+    )
+}
+
+/// Prefix of every cached `analytics_query` result of a scope
+pub fn analytics_cache_prefix(scope: &str) -> String {
+    format!("{}{}:", ANALYTICS_KEY_PREFIX, scope)
+}
+
+/// Idempotency keys can't contain `:`, so the key is never mistaken for part of the tool
+pub fn idempotency_key(tenant_id: &str, tool: &str, key: &str) -> String {
+    format!("{}{}:{}:{}", IDEMPOTENCY_KEY_PREFIX, tenant_id, tool, key)
+}
+
+/// Prefix of every response kept for a tenant's idempotent replays
+pub fn idempotency_prefix(tenant_id: &str) -> String {
+    format!("{}{}:", IDEMPOTENCY_KEY_PREFIX, tenant_id)
+}
+
+pub fn dead_letter_key(event_id: &str) -> String {
+    format!("{}{}", DEAD_LETTER_PREFIX, event_id)
+}
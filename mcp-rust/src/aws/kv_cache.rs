@@ -0,0 +1,155 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Most `kv_get` results kept in process; unset or 0 leaves the cache off
+pub const KV_CACHE_SIZE_ENV: &str = "AGENT_MESH_KV_CACHE_SIZE";
+
+/// How long a cached `kv_get` result is served, in milliseconds
+pub const KV_CACHE_TTL_ENV: &str = "AGENT_MESH_KV_CACHE_TTL_MS";
+
+pub const DEFAULT_KV_CACHE_TTL: Duration = Duration::from_secs(5);
+
+struct CachedValue {
+    value: Option<String>,
+    expires_at: Instant,
+    /// Position in the recency order; higher is more recent
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    values: HashMap<String, CachedValue>,
+    /// Keys by `last_used`, least recently used first
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+    /// Writes seen so far, so a read that raced a write isn't cached
+    writes: u64,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        if let Some(entry) = self.values.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = self.clock;
+            self.recency.insert(self.clock, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.values.remove(key) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+}
+
+/// Counters reported by `server_stats`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KvCacheStats {
+    pub capacity: usize,
+    pub ttl_ms: u64,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// In-process LRU cache of `kv_get` results, including missing keys, for up to a short
+/// TTL. Entries are keyed by the full namespaced key (`user:alice:config`), so one
+/// tenant's reads and writes never touch another's entries. Only this process's writes
+/// invalidate entries; writes from other servers are seen once the TTL runs out.
+pub struct KvCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl KvCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The cached result for `key`: `Some(None)` for a key cached as missing, `None` on a
+    /// miss. Counts the hit or miss.
+    pub fn get(&self, key: &str) -> Option<Option<String>> {
+        let mut entries = self.entries.lock().unwrap();
+        let cached = match entries.values.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        match cached {
+            Some(value) => {
+                entries.touch(key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Taken before reading the table; pass it to `insert` with what was read
+    pub fn ticket(&self) -> u64 {
+        self.entries.lock().unwrap().writes
+    }
+
+    /// Cache what a read started at `ticket` returned, unless a write came in since, in
+    /// which case the value may already be stale. Evicts the least recently used entry
+    /// when full.
+    pub fn insert(&self, key: &str, value: Option<String>, ticket: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.writes != ticket {
+            return;
+        }
+        entries.remove(key);
+        while entries.values.len() >= self.capacity {
+            let Some((_, oldest)) = entries.recency.pop_first() else {
+                break;
+            };
+            entries.values.remove(&oldest);
+        }
+        entries.values.insert(
+            key.to_string(),
+            CachedValue {
+                value,
+                expires_at: Instant::now() + self.ttl,
+                last_used: 0,
+            },
+        );
+        entries.touch(key);
+    }
+
+    /// Drop `key`'s entry; called on every write to it
+    pub fn invalidate(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.writes += 1;
+        entries.remove(key);
+    }
+
+    pub fn stats(&self) -> KvCacheStats {
+        KvCacheStats {
+            capacity: self.capacity,
+            ttl_ms: self.ttl.as_millis() as u64,
+            entries: self.entries.lock().unwrap().values.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+/// Name of the span every AWS request is traced under
+pub const AWS_CALL_SPAN: &str = "aws_call";
+
+/// Upper bounds of the latency histogram buckets in milliseconds; slower calls land in a
+/// final overflow bucket
+const BUCKET_BOUNDS_MS: &[u64] = &[
+    1, 2, 3, 5, 7, 10, 15, 20, 30, 50, 70, 100, 150, 200, 300, 500, 700, 1_000, 1_500, 2_000,
+    3_000, 5_000, 7_000, 10_000, 15_000, 20_000, 30_000,
+];
+
+/// Coarse size of an item or object, recorded instead of anything about its content
+pub fn size_class(bytes: usize) -> &'static str {
+    match bytes {
+        0..=1_023 => "small",
+        1_024..=65_535 => "medium",
+        65_536..=1_048_575 => "large",
+        _ => "xlarge",
+    }
+}
+
+/// Latency percentiles of one service's calls, in milliseconds. Percentiles are the
+/// upper bound of the histogram bucket they fall in, capped at the slowest call seen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: vec![0; BUCKET_BOUNDS_MS.len() + 1],
+            count: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        let ms = elapsed.as_millis() as u64;
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| ms < *bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    fn percentile(&self, quantile: f64) -> u64 {
+        let max_ms = self.max.as_millis() as u64;
+        let rank = ((quantile * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = BUCKET_BOUNDS_MS.get(bucket).copied().unwrap_or(max_ms);
+                return bound.min(max_ms);
+            }
+        }
+        max_ms
+    }
+
+    fn stats(&self) -> LatencyStats {
+        LatencyStats {
+            count: self.count,
+            mean_ms: self.total.as_secs_f64() * 1000.0 / self.count.max(1) as f64,
+            p50_ms: self.percentile(0.50),
+            p95_ms: self.percentile(0.95),
+            p99_ms: self.percentile(0.99),
+            max_ms: self.max.as_millis() as u64,
+        }
+    }
+}
+
+/// Per-service latency histograms of the calls made to AWS, reported by `server_stats`
+#[derive(Default)]
+pub struct AwsLatency {
+    histograms: Mutex<HashMap<&'static str, Histogram>>,
+}
+
+impl AwsLatency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, service: &'static str, elapsed: Duration) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry(service)
+            .or_insert_with(Histogram::new)
+            .record(elapsed);
+    }
+
+    /// Percentiles by service, for the services called so far
+    pub fn stats(&self) -> BTreeMap<String, LatencyStats> {
+        self.histograms
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(service, histogram)| (service.to_string(), histogram.stats()))
+            .collect()
+    }
+}
+
+/// One request to AWS, traced as an [`AWS_CALL_SPAN`] span and timed into its service's
+/// histogram. Every span has the same fields: `aws.service`, `aws.operation`,
+/// `aws.resource` (table, bucket, bus or queue), `aws.key` for single-item calls,
+/// `aws.size_class`, `aws.duration_ms` and `aws.outcome` (`ok` or `error`). Values are
+/// never recorded, only their size class.
+pub struct AwsCall<'a> {
+    latency: &'a AwsLatency,
+    service: &'static str,
+    span: Span,
+}
+
+impl<'a> AwsCall<'a> {
+    pub fn new(
+        latency: &'a AwsLatency,
+        service: &'static str,
+        operation: &'static str,
+        resource: &str,
+    ) -> Self {
+        let span = tracing::info_span!(
+            AWS_CALL_SPAN,
+            aws.service = service,
+            aws.operation = operation,
+            aws.resource = resource,
+            aws.key = Empty,
+            aws.size_class = Empty,
+            aws.duration_ms = Empty,
+            aws.outcome = Empty,
+        );
+        Self {
+            latency,
+            service,
+            span,
+        }
+    }
+
+    /// The item or object key the call reads or writes
+    pub fn key(self, key: &str) -> Self {
+        self.span.record("aws.key", key);
+        self
+    }
+
+    /// Size of the payload written, or read once the response is in
+    pub fn size(self, bytes: usize) -> Self {
+        self.record_size(bytes);
+        self
+    }
+
+    pub fn record_size(&self, bytes: usize) {
+        self.span.record("aws.size_class", size_class(bytes));
+    }
+
+    /// Send the request inside the span, recording how long it took and whether it failed
+    pub async fn run<T, E>(&self, request: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let started = Instant::now();
+        let result = request.instrument(self.span.clone()).await;
+        let elapsed = started.elapsed();
+        self.latency.record(self.service, elapsed);
+        self.span
+            .record("aws.duration_ms", elapsed.as_millis() as u64)
+            .record("aws.outcome", if result.is_ok() { "ok" } else { "error" });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_use_bucket_bounds() {
+        let latency = AwsLatency::new();
+        for ms in [4, 4, 4, 4, 4, 4, 4, 4, 40, 400] {
+            latency.record("dynamodb", Duration::from_millis(ms));
+        }
+        latency.record("s3", Duration::from_millis(12));
+
+        let stats = latency.stats();
+        let dynamodb = &stats["dynamodb"];
+        assert_eq!(dynamodb.count, 10);
+        assert!((dynamodb.mean_ms - 47.2).abs() < 0.01);
+        assert_eq!(
+            (
+                dynamodb.p50_ms,
+                dynamodb.p95_ms,
+                dynamodb.p99_ms,
+                dynamodb.max_ms
+            ),
+            (5, 400, 400, 400)
+        );
+        // A lone call's percentiles never exceed it
+        assert_eq!(stats["s3"].p50_ms, 12);
+        assert_eq!(size_class(1_023), "small");
+        assert_eq!(size_class(1_024), "medium");
+        assert_eq!(size_class(2 << 20), "xlarge");
+    }
+}
@@ -0,0 +1,1406 @@
+use async_trait::async_trait;
+use aws_sdk_lambda::Client as LambdaClient;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::alerts::NewAlertSubscription;
+use super::artifacts::{
+    self, ArtifactDeletion, ArtifactLifecycleRule, ArtifactMetadata, ArtifactPosition,
+    RetentionDays, TtlClass,
+};
+use super::kv_cache::{KvCache, KvCacheStats};
+use super::namespaces::{self, ArtifactMigration, EventScope};
+use super::queues::{QueueMessage, DEFAULT_VISIBILITY_TIMEOUT_SECONDS};
+use super::resources::ResourceKind;
+use super::transactions::{self, KvWriteOp, TransactionFailure};
+use super::workflows::{self, WorkflowExecution};
+use super::{
+    analytics_response, analytics_scope, analytics_window, events_health_report,
+    tenant_event_detail, validate_direct_key, AwsApi, AwsError, DirectKvStore, EventCounts,
+};
+use crate::tenant::TenantSession;
+
+/// Table name reported by `events_health_check` for each in-memory collection
+const EVENTS_TABLE: &str = "memory:events";
+const RULES_TABLE: &str = "memory:event-rules";
+const SUBSCRIPTIONS_TABLE: &str = "memory:subscriptions";
+
+/// How often a waiting `queue_receive` looks for newly visible messages
+const RECEIVE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A message published to an in-memory SNS topic: its body and string attributes
+pub type PublishedMessage = (String, HashMap<String, String>);
+
+/// A message in an in-memory queue
+struct StoredMessage {
+    message_id: String,
+    body: String,
+    attributes: HashMap<String, String>,
+    /// Hidden from receivers until then (delayed, or received and in flight)
+    visible_at: Instant,
+    /// Handle from the latest receive; earlier ones no longer work
+    receipt_handle: Option<String>,
+    receive_count: u32,
+}
+
+/// [`AwsApi`] backend that keeps KV items, artifacts, events, rules, secrets, workflow
+/// executions, queues and SNS topics in process, so the server runs without AWS
+/// (`MCP_BACKEND=memory`) and handlers can be tested offline. Keys are namespaced as in
+/// AWS; TTLs, encryption and the analytics cache are skipped, executions run until
+/// finished by hand, queues and topics must be created first (as in AWS) and nothing
+/// outlives the process.
+pub struct MemoryAwsService {
+    artifacts_bucket: String,
+    /// Tenant-namespaced and direct KV items; namespaced keys contain a `:`
+    kv: Mutex<HashMap<String, String>>,
+    /// Objects by bucket and key
+    objects: Mutex<HashMap<(String, String), Vec<u8>>>,
+    /// Content type, write time and TTL class of objects stored with `artifacts_put`
+    artifact_metadata: Mutex<HashMap<(String, String), ArtifactMetadata>>,
+    /// The bucket's lifecycle rules, as `configure_artifact_lifecycle` installed them
+    lifecycle_rules: Mutex<Vec<ArtifactLifecycleRule>>,
+    /// The event history `query_events` and `analytics_query` read
+    events: Mutex<Vec<Value>>,
+    rules: Mutex<Vec<Value>>,
+    subscriptions: Mutex<Vec<Value>>,
+    secrets: Mutex<BTreeMap<String, String>>,
+    /// Workflow executions by ARN
+    executions: Mutex<HashMap<String, WorkflowExecution>>,
+    /// Queued messages by queue name, oldest first
+    queues: Mutex<HashMap<String, Vec<StoredMessage>>>,
+    /// Messages published to each SNS topic by ARN, oldest first
+    topics: Mutex<HashMap<String, Vec<PublishedMessage>>>,
+    kv_cache: Option<KvCache>,
+    /// How long `ping` takes to answer, to stand in for a slow or unreachable AWS
+    ping_delay: Mutex<Option<Duration>>,
+    /// How long `kv_compare_and_swap` takes to return once it has written, to stand in
+    /// for a slow acknowledgement
+    swap_delay: Mutex<Option<Duration>>,
+    /// Detail types `send_events` reports as failed entries
+    rejected_detail_types: Mutex<HashSet<String>>,
+}
+
+impl Default for MemoryAwsService {
+    fn default() -> Self {
+        Self {
+            artifacts_bucket: ResourceKind::ArtifactsBucket.default_name().to_string(),
+            kv: Mutex::default(),
+            objects: Mutex::default(),
+            artifact_metadata: Mutex::default(),
+            lifecycle_rules: Mutex::default(),
+            events: Mutex::default(),
+            rules: Mutex::default(),
+            subscriptions: Mutex::default(),
+            secrets: Mutex::default(),
+            executions: Mutex::default(),
+            queues: Mutex::default(),
+            topics: Mutex::default(),
+            kv_cache: None,
+            ping_delay: Mutex::default(),
+            swap_delay: Mutex::default(),
+            rejected_detail_types: Mutex::default(),
+        }
+    }
+}
+
+impl MemoryAwsService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep artifacts as if in `bucket` rather than the default bucket
+    pub fn with_artifacts_bucket(mut self, bucket: &str) -> Self {
+        self.artifacts_bucket = bucket.to_string();
+        self
+    }
+
+    /// Serve `kv_get` through a read cache, as `AwsService` does when it's configured
+    pub fn with_kv_cache(mut self, cache: KvCache) -> Self {
+        self.kv_cache = Some(cache);
+        self
+    }
+
+    /// Make `ping` take `delay` to answer (`None`: answer at once)
+    #[allow(dead_code)]
+    pub fn set_ping_delay(&self, delay: Option<Duration>) {
+        *self.ping_delay.lock().unwrap() = delay;
+    }
+
+    /// Make `kv_compare_and_swap` return `delay` after its write is visible (`None`: at
+    /// once)
+    #[allow(dead_code)]
+    pub fn set_swap_delay(&self, delay: Option<Duration>) {
+        *self.swap_delay.lock().unwrap() = delay;
+    }
+
+    /// Make `send_events` fail every entry of `detail_type`, as EventBridge reports a
+    /// failed entry in an otherwise successful `PutEvents`
+    #[allow(dead_code)]
+    pub fn reject_events(&self, detail_type: &str) {
+        self.rejected_detail_types
+            .lock()
+            .unwrap()
+            .insert(detail_type.to_string());
+    }
+
+    /// Read a namespaced item and cache its value
+    fn kv_read(&self, tenant_key: String) -> Option<String> {
+        let ticket = self.kv_cache.as_ref().map(KvCache::ticket);
+        let value = self.kv.lock().unwrap().get(&tenant_key).cloned();
+        if let (Some(cache), Some(ticket)) = (&self.kv_cache, ticket) {
+            cache.insert(&tenant_key, value.clone(), ticket);
+        }
+        value
+    }
+
+    fn invalidate_cached(&self, tenant_key: &str) {
+        if let Some(cache) = &self.kv_cache {
+            cache.invalidate(tenant_key);
+        }
+    }
+
+    /// Add an event to the history as the event store would record it (`userId`,
+    /// `organizationId`, `source`, `detailType`, `priority`, `timestamp`, `detail`), e.g.
+    /// to seed a demo. A missing `eventId` or `timestamp` is filled in.
+    pub fn record_event(&self, mut event: Value) {
+        if let Value::Object(fields) = &mut event {
+            fields
+                .entry("eventId")
+                .or_insert_with(|| json!(uuid::Uuid::new_v4().to_string()));
+            fields
+                .entry("timestamp")
+                .or_insert_with(|| json!(chrono::Utc::now().to_rfc3339()));
+        }
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// End a running execution as Step Functions would: `SUCCEEDED` with `output`, or
+    /// with `Err((error, cause))` `FAILED`. Returns false if there's no such running
+    /// execution.
+    #[allow(dead_code)]
+    pub fn finish_workflow(
+        &self,
+        execution_arn: &str,
+        result: Result<Value, (&str, &str)>,
+    ) -> bool {
+        let mut executions = self.executions.lock().unwrap();
+        let Some(execution) = executions
+            .get_mut(execution_arn)
+            .filter(|execution| execution.status == "RUNNING")
+        else {
+            return false;
+        };
+        execution.stop_date = Some(chrono::Utc::now());
+        match result {
+            Ok(output) => {
+                execution.status = "SUCCEEDED".to_string();
+                execution.output = Some(output);
+            }
+            Err((error, cause)) => {
+                execution.status = "FAILED".to_string();
+                execution.error = Some(error.to_string());
+                execution.cause = Some(cause.to_string());
+            }
+        }
+        true
+    }
+
+    /// Create an empty queue, as provisioning it in SQS would; an existing queue is kept
+    #[allow(dead_code)]
+    pub fn create_queue(&self, queue_name: &str) {
+        self.queues
+            .lock()
+            .unwrap()
+            .entry(queue_name.to_string())
+            .or_default();
+    }
+
+    /// Store an object as it is, e.g. an artifact under its legacy key
+    #[allow(dead_code)]
+    pub fn put_object(&self, bucket: &str, key: &str, content: &[u8]) {
+        self.objects
+            .lock()
+            .unwrap()
+            .insert((bucket.to_string(), key.to_string()), content.to_vec());
+    }
+
+    /// Whether the object exists
+    #[allow(dead_code)]
+    pub fn has_object(&self, bucket: &str, key: &str) -> bool {
+        self.objects
+            .lock()
+            .unwrap()
+            .contains_key(&(bucket.to_string(), key.to_string()))
+    }
+
+    /// The artifacts bucket's lifecycle rules, in the order they were installed
+    #[allow(dead_code)]
+    pub fn lifecycle_rules(&self) -> Vec<ArtifactLifecycleRule> {
+        self.lifecycle_rules.lock().unwrap().clone()
+    }
+
+    /// Create an SNS topic to publish alerts to; an existing topic is kept
+    #[allow(dead_code)]
+    pub fn create_topic(&self, topic_arn: &str) {
+        self.topics
+            .lock()
+            .unwrap()
+            .entry(topic_arn.to_string())
+            .or_default();
+    }
+
+    /// Messages published to the topic so far, oldest first
+    #[allow(dead_code)]
+    pub fn published(&self, topic_arn: &str) -> Vec<PublishedMessage> {
+        self.topics
+            .lock()
+            .unwrap()
+            .get(topic_arn)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The stored alert subscription, with its `userId`, `organizationId` and any
+    /// `lastError`
+    #[allow(dead_code)]
+    pub fn subscription(&self, subscription_id: &str) -> Option<Value> {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|subscription| subscription["subscriptionId"] == subscription_id)
+            .cloned()
+    }
+
+    /// Take up to `max_messages` visible messages, hiding them until `visible_at`
+    fn take_visible(
+        &self,
+        queue_name: &str,
+        max_messages: usize,
+        visible_at: Instant,
+    ) -> Result<Vec<QueueMessage>, AwsError> {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues
+            .get_mut(queue_name)
+            .ok_or_else(|| AwsError::NotFound(format!("queue {:?}", queue_name)))?;
+        let now = Instant::now();
+        Ok(queue
+            .iter_mut()
+            .filter(|message| message.visible_at <= now)
+            .take(max_messages)
+            .map(|message| {
+                let receipt_handle = uuid::Uuid::new_v4().to_string();
+                message.visible_at = visible_at;
+                message.receipt_handle = Some(receipt_handle.clone());
+                message.receive_count += 1;
+                QueueMessage {
+                    message_id: message.message_id.clone(),
+                    receipt_handle,
+                    body: message.body.clone(),
+                    attributes: message.attributes.clone(),
+                    receive_count: message.receive_count,
+                }
+            })
+            .collect())
+    }
+
+    fn tenant_key(session: &TenantSession, key: &str) -> String {
+        format!("{}:{}", session.context.get_namespace_prefix(), key)
+    }
+
+    /// Where a tenant's artifact is kept: the artifacts bucket, under the namespace
+    fn artifact_key(&self, session: &TenantSession, key: &str) -> (String, String) {
+        (
+            self.artifacts_bucket.clone(),
+            namespaces::artifact_key(session, key),
+        )
+    }
+
+    /// Artifact keys under `root` (ending in `/`), relative to it
+    fn artifact_keys(&self, root: &str) -> Vec<String> {
+        self.objects
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(bucket, _)| *bucket == self.artifacts_bucket)
+            .filter_map(|(_, key)| key.strip_prefix(root))
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn record(&self, session: &TenantSession, source: &str, detail_type: &str, detail: Value) {
+        let priority = detail
+            .get("priority")
+            .and_then(Value::as_str)
+            .unwrap_or("medium")
+            .to_string();
+        let mut event = json!({
+            "userId": session.context.user_id,
+            "organizationId": session.context.organization_id,
+            "source": source,
+            "detailType": detail_type,
+            "priority": priority,
+            "namespace": session.context.get_namespace_prefix(),
+        });
+        // Copied onto the record as the event store does, for the `linkId` filter
+        if let Some(link_id) = detail.get("linkId") {
+            event["linkId"] = link_id.clone();
+        }
+        event["detail"] = detail;
+        self.record_event(event);
+    }
+}
+
+/// Whether `event`'s string `field` equals `expected`, when a value is expected
+fn field_matches(event: &Value, field: &str, expected: Option<&str>) -> bool {
+    expected.is_none_or(|expected| event[field].as_str() == Some(expected))
+}
+
+/// Whether a stored rule or subscription was created by the session's user in its
+/// organization
+fn is_owned_by(item: &Value, session: &TenantSession) -> bool {
+    item["userId"] == session.context.user_id.as_str()
+        && item["organizationId"] == session.context.organization_id.as_str()
+}
+
+fn owned_by(items: &[Value], session: &TenantSession) -> Vec<Value> {
+    items
+        .iter()
+        .filter(|item| is_owned_by(item, session))
+        .cloned()
+        .collect()
+}
+
+/// Remove the session's item whose `attribute` is `id`, or `NotFound`
+fn remove_owned(
+    items: &mut Vec<Value>,
+    session: &TenantSession,
+    (attribute, id): (&str, &str),
+) -> Result<(), AwsError> {
+    let position = items
+        .iter()
+        .position(|item| item[attribute] == id && is_owned_by(item, session))
+        .ok_or_else(|| AwsError::NotFound(format!("{} {:?}", attribute, id)))?;
+    items.remove(position);
+    Ok(())
+}
+
+fn count_for_user(items: &[Value], user_id: &str) -> i32 {
+    items
+        .iter()
+        .filter(|item| item["userId"] == user_id)
+        .count() as i32
+}
+
+#[async_trait]
+impl DirectKvStore for MemoryAwsService {
+    async fn kv_get_direct(&self, key: &str) -> Result<Option<String>, AwsError> {
+        validate_direct_key(key)?;
+        Ok(self.kv.lock().unwrap().get(key).cloned())
+    }
+
+    async fn kv_set_direct(
+        &self,
+        key: &str,
+        value: &str,
+        _ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError> {
+        validate_direct_key(key)?;
+        self.kv
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    async fn kv_set_direct_sensitive(
+        &self,
+        _tenant_id: &str,
+        key: &str,
+        value: &str,
+        ttl_hours: Option<u32>,
+    ) -> Result<(), AwsError> {
+        self.kv_set_direct(key, value, ttl_hours).await
+    }
+
+    async fn kv_compare_and_swap_direct(
+        &self,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        _ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        validate_direct_key(key)?;
+        let mut kv = self.kv.lock().unwrap();
+        if kv.get(key).map(String::as_str) != expected {
+            return Ok(false);
+        }
+        kv.insert(key.to_string(), value.to_string());
+        Ok(true)
+    }
+
+    async fn kv_list(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
+        validate_direct_key(prefix)?;
+        let mut keys: Vec<String> = self
+            .kv
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn kv_delete(&self, key: &str) -> Result<(), AwsError> {
+        validate_direct_key(key)?;
+        self.kv.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AwsApi for MemoryAwsService {
+    fn artifacts_bucket(&self) -> &str {
+        &self.artifacts_bucket
+    }
+
+    fn lambda_client(&self) -> Option<LambdaClient> {
+        None
+    }
+
+    async fn ping(&self) -> Result<(), AwsError> {
+        let delay = *self.ping_delay.lock().unwrap();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        Ok(())
+    }
+
+    async fn kv_get(&self, session: &TenantSession, key: &str) -> Result<Option<String>, AwsError> {
+        let tenant_key = Self::tenant_key(session, key);
+        if let Some(cached) = self
+            .kv_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&tenant_key))
+        {
+            return Ok(cached);
+        }
+        Ok(self.kv_read(tenant_key))
+    }
+
+    async fn kv_get_fresh(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<String>, AwsError> {
+        Ok(self.kv_read(Self::tenant_key(session, key)))
+    }
+
+    fn kv_cache_stats(&self) -> Option<KvCacheStats> {
+        self.kv_cache.as_ref().map(KvCache::stats)
+    }
+
+    /// Sensitive values are kept as they are: they never leave the process
+    async fn kv_set(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        value: &str,
+        _ttl_hours: Option<u32>,
+        _sensitive: bool,
+    ) -> Result<(), AwsError> {
+        let tenant_key = Self::tenant_key(session, key);
+        self.kv
+            .lock()
+            .unwrap()
+            .insert(tenant_key.clone(), value.to_string());
+        self.invalidate_cached(&tenant_key);
+        Ok(())
+    }
+
+    async fn artifacts_put(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        content: &[u8],
+        content_type: &str,
+        ttl_class: Option<TtlClass>,
+    ) -> Result<(), AwsError> {
+        let object_key = self.artifact_key(session, key);
+        self.artifact_metadata.lock().unwrap().insert(
+            object_key.clone(),
+            ArtifactMetadata {
+                size: content.len() as u64,
+                content_type: Some(content_type.to_string()),
+                last_modified: Some(chrono::Utc::now().to_rfc3339()),
+                ttl_class,
+            },
+        );
+        self.objects
+            .lock()
+            .unwrap()
+            .insert(object_key, content.to_vec());
+        Ok(())
+    }
+
+    async fn artifacts_head(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<ArtifactMetadata>, AwsError> {
+        let objects = self.objects.lock().unwrap();
+        let legacy_key = (
+            self.artifacts_bucket.clone(),
+            namespaces::legacy_artifact_key(session, key),
+        );
+        let Some((object_key, content)) = [self.artifact_key(session, key), legacy_key]
+            .into_iter()
+            .find_map(|object_key| {
+                objects
+                    .get(&object_key)
+                    .map(|content| (object_key, content))
+            })
+        else {
+            return Ok(None);
+        };
+        let metadata = self
+            .artifact_metadata
+            .lock()
+            .unwrap()
+            .get(&object_key)
+            .cloned();
+        Ok(Some(ArtifactMetadata {
+            size: content.len() as u64,
+            ..metadata.unwrap_or(ArtifactMetadata {
+                size: 0,
+                content_type: None,
+                last_modified: None,
+                ttl_class: None,
+            })
+        }))
+    }
+
+    async fn artifacts_get(
+        &self,
+        session: &TenantSession,
+        key: &str,
+    ) -> Result<Option<Vec<u8>>, AwsError> {
+        let objects = self.objects.lock().unwrap();
+        let legacy_key = (
+            self.artifacts_bucket.clone(),
+            namespaces::legacy_artifact_key(session, key),
+        );
+        Ok(objects
+            .get(&self.artifact_key(session, key))
+            .or_else(|| objects.get(&legacy_key))
+            .cloned())
+    }
+
+    async fn artifacts_list(
+        &self,
+        session: &TenantSession,
+        prefix: Option<&str>,
+    ) -> Result<Vec<String>, AwsError> {
+        let prefix = prefix.unwrap_or_default();
+        let mut keys = self.artifact_keys(&namespaces::artifact_key(session, ""));
+        keys.extend(self.artifact_keys(&namespaces::legacy_artifact_key(session, "")));
+        keys.retain(|key| key.starts_with(prefix));
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    /// Continuation tokens are the last key of the page before
+    async fn artifacts_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError> {
+        let position = start.map(ArtifactPosition::parse).transpose()?;
+        let legacy = position.as_ref().is_some_and(|position| position.legacy);
+        let after = position.and_then(|position| position.token);
+        let namespace_keys = self.artifact_keys(&namespaces::artifact_key(session, ""));
+        let mut keys = if legacy {
+            let mut keys = self.artifact_keys(&namespaces::legacy_artifact_key(session, ""));
+            keys.retain(|key| !namespace_keys.contains(key));
+            keys
+        } else {
+            namespace_keys
+        };
+        keys.retain(|key| after.as_deref().is_none_or(|after| key.as_str() > after));
+        keys.sort();
+        let next = if keys.len() > limit {
+            Some(ArtifactPosition {
+                legacy,
+                token: Some(keys[limit - 1].clone()),
+            })
+        } else {
+            (!legacy).then_some(ArtifactPosition {
+                legacy: true,
+                token: None,
+            })
+        };
+        keys.truncate(limit);
+        Ok((keys, next.map(|next| next.to_string())))
+    }
+
+    async fn artifacts_delete_prefix(
+        &self,
+        session: &TenantSession,
+        prefix: &str,
+        dry_run: bool,
+    ) -> Result<ArtifactDeletion, AwsError> {
+        let mut objects = self.objects.lock().unwrap();
+        let mut deletion = ArtifactDeletion::default();
+        for root in [
+            namespaces::artifact_key(session, ""),
+            namespaces::legacy_artifact_key(session, ""),
+        ] {
+            let matching: Vec<(String, String)> = objects
+                .keys()
+                .filter(|(bucket, key)| {
+                    *bucket == self.artifacts_bucket
+                        && key
+                            .strip_prefix(&root)
+                            .is_some_and(|key| key.starts_with(prefix))
+                })
+                .cloned()
+                .collect();
+            for object in matching {
+                let size = if dry_run {
+                    objects[&object].len()
+                } else {
+                    self.artifact_metadata.lock().unwrap().remove(&object);
+                    objects.remove(&object).unwrap_or_default().len()
+                };
+                deletion.bytes += size as u64;
+                deletion.keys.push(object.1[root.len()..].to_string());
+            }
+        }
+        deletion.keys.sort();
+        Ok(deletion)
+    }
+
+    async fn migrate_artifacts(
+        &self,
+        session: &TenantSession,
+    ) -> Result<ArtifactMigration, AwsError> {
+        let namespace_root = namespaces::artifact_key(session, "");
+        let legacy_root = namespaces::legacy_artifact_key(session, "");
+        let mut legacy_keys = self.artifact_keys(&legacy_root);
+        legacy_keys.sort();
+
+        let mut objects = self.objects.lock().unwrap();
+        let mut migration = ArtifactMigration::default();
+        for key in legacy_keys {
+            let destination = (
+                self.artifacts_bucket.clone(),
+                format!("{}{}", namespace_root, key),
+            );
+            if objects.contains_key(&destination) {
+                migration.skipped.push(key);
+                continue;
+            }
+            let source = (
+                self.artifacts_bucket.clone(),
+                format!("{}{}", legacy_root, key),
+            );
+            if let Some(content) = objects.remove(&source) {
+                let mut metadata = self.artifact_metadata.lock().unwrap();
+                if let Some(moved) = metadata.remove(&source) {
+                    metadata.insert(destination.clone(), moved);
+                }
+                objects.insert(destination, content);
+                migration.moved.push(key);
+            }
+        }
+        Ok(migration)
+    }
+
+    async fn configure_artifact_lifecycle(
+        &self,
+        session: &TenantSession,
+        retention: &RetentionDays,
+    ) -> Result<Vec<ArtifactLifecycleRule>, AwsError> {
+        let rules = artifacts::tenant_lifecycle_rules(
+            &artifacts::tenant_artifact_prefix(session),
+            retention,
+        );
+        let mut installed = self.lifecycle_rules.lock().unwrap();
+        installed.retain(|existing| rules.iter().all(|rule| rule.id != existing.id));
+        installed.extend(rules.iter().cloned());
+        Ok(rules)
+    }
+
+    async fn s3_get(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, AwsError> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .get(&(bucket.to_string(), key.to_string()))
+            .cloned())
+    }
+
+    async fn send_event(
+        &self,
+        session: &TenantSession,
+        detail_type: &str,
+        detail: Value,
+    ) -> Result<(), AwsError> {
+        let detail = tenant_event_detail(session, detail);
+        self.record(session, "mcp-rust", detail_type, detail);
+        Ok(())
+    }
+
+    /// Events whose detail type was passed to `reject_events` fail, the rest are recorded
+    async fn send_events(
+        &self,
+        session: &TenantSession,
+        events: &[(String, Value)],
+    ) -> Result<Vec<Option<String>>, AwsError> {
+        let rejected = self.rejected_detail_types.lock().unwrap().clone();
+        Ok(events
+            .iter()
+            .map(|(detail_type, detail)| {
+                if rejected.contains(detail_type) {
+                    return Some("InternalFailure: the event was rejected".to_string());
+                }
+                let detail = tenant_event_detail(session, detail.clone());
+                self.record(session, "mcp-rust", detail_type, detail);
+                None
+            })
+            .collect())
+    }
+
+    async fn send_system_event(&self, detail_type: &str, detail: Value) -> Result<(), AwsError> {
+        self.record_event(json!({
+            "source": "mcp-rust.system",
+            "detailType": detail_type,
+            "detail": detail
+        }));
+        Ok(())
+    }
+
+    /// Pages are continued from the `eventId` returned as `lastEvaluatedKey`
+    async fn query_events(
+        &self,
+        _session: &TenantSession,
+        scope: Option<&EventScope>,
+        user_id: Option<String>,
+        organization_id: Option<String>,
+        source: Option<String>,
+        detail_type: Option<String>,
+        priority: Option<String>,
+        link_id: Option<String>,
+        start_time: Option<String>,
+        end_time: Option<String>,
+        limit: i32,
+        exclusive_start_key: Option<String>,
+        ascending: bool,
+    ) -> Result<Value, AwsError> {
+        // A scope replaces the user and organization filters
+        let (user_id, organization_id) = match scope {
+            Some(_) => (None, None),
+            None => (user_id, organization_id),
+        };
+        if scope.is_none() && user_id.is_none() && source.is_none() {
+            return Err(AwsError::Config(
+                "Query requires userId or source filter to avoid expensive scan".to_string(),
+            ));
+        }
+
+        let mut events: Vec<Value> = self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| {
+                scope.is_none_or(|scope| scope.contains(event))
+                    && field_matches(event, "userId", user_id.as_deref())
+                    && field_matches(event, "organizationId", organization_id.as_deref())
+                    && field_matches(event, "source", source.as_deref())
+                    && field_matches(event, "detailType", detail_type.as_deref())
+                    && field_matches(event, "priority", priority.as_deref())
+                    && field_matches(event, "linkId", link_id.as_deref())
+            })
+            .filter(|event| {
+                // Timestamps compare as strings, as in the DynamoDB key condition
+                let timestamp = event["timestamp"].as_str().unwrap_or_default();
+                start_time.as_deref().is_none_or(|start| timestamp >= start)
+                    && end_time.as_deref().is_none_or(|end| timestamp <= end)
+            })
+            .cloned()
+            .collect();
+        events.sort_by(|a, b| a["timestamp"].as_str().cmp(&b["timestamp"].as_str()));
+        if !ascending {
+            events.reverse();
+        }
+
+        if let Some(start_key) = exclusive_start_key {
+            let next = events
+                .iter()
+                .position(|event| event["eventId"] == start_key.as_str())
+                .map_or(0, |position| position + 1);
+            events.drain(..next);
+        }
+        let limit = limit.max(0) as usize;
+        let last_evaluated_key =
+            (limit > 0 && events.len() > limit).then(|| events[limit - 1]["eventId"].clone());
+        events.truncate(limit);
+
+        Ok(json!({
+            "events": events,
+            "count": events.len(),
+            "lastEvaluatedKey": last_evaluated_key
+        }))
+    }
+
+    async fn analytics_query(
+        &self,
+        session: &TenantSession,
+        user_id: Option<String>,
+        organization_id: Option<String>,
+        start_time: Option<String>,
+        end_time: Option<String>,
+        metrics: Vec<String>,
+        granularity: String,
+    ) -> Result<Value, AwsError> {
+        let scope = analytics_scope(session, user_id.as_deref(), organization_id.as_deref());
+        let (start_dt, end_dt) = analytics_window(start_time, end_time)?;
+
+        let mut counts = EventCounts::default();
+        for event in self.events.lock().unwrap().iter() {
+            let in_window = event["timestamp"]
+                .as_str()
+                .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+                .is_some_and(|ts| ts >= start_dt && ts <= end_dt);
+            // As in AWS, the user filter takes precedence over the organization's
+            let in_scope = match (&user_id, &organization_id) {
+                (Some(uid), _) => event["userId"] == uid.as_str(),
+                (None, Some(org_id)) => event["organizationId"] == org_id.as_str(),
+                (None, None) => true,
+            };
+            if in_window && in_scope {
+                counts.add(
+                    event["timestamp"].as_str(),
+                    event["source"].as_str(),
+                    event["priority"].as_str(),
+                    event["detailType"].as_str(),
+                    &granularity,
+                );
+            }
+        }
+
+        Ok(analytics_response(
+            scope,
+            start_dt,
+            end_dt,
+            counts,
+            &metrics,
+            &granularity,
+        ))
+    }
+
+    async fn create_event_rule(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        pattern: Value,
+        description: Option<String>,
+        enabled: bool,
+    ) -> Result<Value, AwsError> {
+        let rule = json!({
+            "ruleId": format!("rule-{}-{}", session.context.user_id, uuid::Uuid::new_v4()),
+            "name": name,
+            "pattern": pattern,
+            "description": description,
+            "enabled": enabled,
+            "createdAt": chrono::Utc::now().to_rfc3339()
+        });
+        let mut stored = rule.clone();
+        stored["userId"] = json!(session.context.user_id);
+        stored["organizationId"] = json!(session.context.organization_id);
+        self.rules.lock().unwrap().push(stored);
+        Ok(rule)
+    }
+
+    async fn create_alert_subscription(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        rule_id: &str,
+        notification_method: &str,
+        sns_topic_arn: Option<String>,
+        email_address: Option<String>,
+        enabled: bool,
+    ) -> Result<Value, AwsError> {
+        let subscription = json!({
+            "subscriptionId": format!("sub-{}-{}", session.context.user_id, uuid::Uuid::new_v4()),
+            "name": name,
+            "ruleId": rule_id,
+            "notificationMethod": notification_method,
+            "snsTopicArn": sns_topic_arn,
+            "emailAddress": email_address,
+            "enabled": enabled,
+            "createdAt": chrono::Utc::now().to_rfc3339()
+        });
+        let mut stored = subscription.clone();
+        stored["userId"] = json!(session.context.user_id);
+        stored["organizationId"] = json!(session.context.organization_id);
+        self.subscriptions.lock().unwrap().push(stored);
+        Ok(subscription)
+    }
+
+    async fn events_health_check(&self, session: &TenantSession) -> Result<Value, AwsError> {
+        let user_id = &session.context.user_id;
+        let since = (chrono::Utc::now() - chrono::Duration::hours(24)).to_rfc3339();
+        let events_count = self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event["userId"] == user_id.as_str())
+            .filter(|event| event["timestamp"].as_str() >= Some(since.as_str()))
+            .count() as i32;
+        let rules_count = count_for_user(&self.rules.lock().unwrap(), user_id);
+        let subscriptions_count = count_for_user(&self.subscriptions.lock().unwrap(), user_id);
+
+        Ok(events_health_report(
+            (EVENTS_TABLE, events_count),
+            (RULES_TABLE, rules_count),
+            (SUBSCRIPTIONS_TABLE, subscriptions_count),
+        ))
+    }
+
+    async fn secret_store(
+        &self,
+        secret_name: &str,
+        secret_value: &str,
+        _description: Option<&str>,
+    ) -> Result<String, AwsError> {
+        self.secrets
+            .lock()
+            .unwrap()
+            .insert(secret_name.to_string(), secret_value.to_string());
+        Ok(secret_name.to_string())
+    }
+
+    async fn secret_get(&self, secret_name: &str) -> Result<Option<String>, AwsError> {
+        Ok(self.secrets.lock().unwrap().get(secret_name).cloned())
+    }
+
+    async fn secret_names(&self, prefix: &str) -> Result<Vec<String>, AwsError> {
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|name| name.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    async fn secret_delete(&self, secret_name: &str, _force_delete: bool) -> Result<(), AwsError> {
+        self.secrets.lock().unwrap().remove(secret_name);
+        Ok(())
+    }
+
+    /// The secret is removed at once; there's nothing to recover it from
+    async fn secret_schedule_deletion(
+        &self,
+        secret_name: &str,
+        recovery_window_days: i64,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>, AwsError> {
+        Ok(self
+            .secrets
+            .lock()
+            .unwrap()
+            .remove(secret_name)
+            .map(|_| chrono::Utc::now() + chrono::Duration::days(recovery_window_days)))
+    }
+
+    async fn workflow_start(
+        &self,
+        state_machine_arn: &str,
+        name: &str,
+        input: &str,
+    ) -> Result<String, AwsError> {
+        workflows::validate_state_machine_arn(state_machine_arn)?;
+        serde_json::from_str::<Value>(input)?;
+        let execution_arn = workflows::execution_arn(state_machine_arn, name);
+        let mut executions = self.executions.lock().unwrap();
+        if executions.contains_key(&execution_arn) {
+            return Err(AwsError::ConditionFailed(format!(
+                "execution {:?} already exists",
+                execution_arn
+            )));
+        }
+        executions.insert(
+            execution_arn.clone(),
+            WorkflowExecution {
+                execution_arn: execution_arn.clone(),
+                state_machine_arn: state_machine_arn.to_string(),
+                status: "RUNNING".to_string(),
+                start_date: chrono::Utc::now(),
+                stop_date: None,
+                output: None,
+                error: None,
+                cause: None,
+            },
+        );
+        Ok(execution_arn)
+    }
+
+    async fn workflow_describe(&self, execution_arn: &str) -> Result<WorkflowExecution, AwsError> {
+        self.executions
+            .lock()
+            .unwrap()
+            .get(execution_arn)
+            .cloned()
+            .ok_or_else(|| AwsError::NotFound(format!("execution {:?}", execution_arn)))
+    }
+
+    async fn workflow_stop(
+        &self,
+        execution_arn: &str,
+        error: Option<&str>,
+        cause: Option<&str>,
+    ) -> Result<chrono::DateTime<chrono::Utc>, AwsError> {
+        let mut executions = self.executions.lock().unwrap();
+        let execution = executions
+            .get_mut(execution_arn)
+            .ok_or_else(|| AwsError::NotFound(format!("execution {:?}", execution_arn)))?;
+        // Stopping a finished execution leaves it as it ended
+        if execution.status == "RUNNING" {
+            execution.status = "ABORTED".to_string();
+            execution.stop_date = Some(chrono::Utc::now());
+            execution.error = error.map(str::to_string);
+            execution.cause = cause.map(str::to_string);
+        }
+        Ok(execution.stop_date.unwrap_or_else(chrono::Utc::now))
+    }
+
+    async fn queue_send(
+        &self,
+        queue_name: &str,
+        body: &str,
+        attributes: &HashMap<String, String>,
+        delay_seconds: Option<i32>,
+    ) -> Result<String, AwsError> {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues
+            .get_mut(queue_name)
+            .ok_or_else(|| AwsError::NotFound(format!("queue {:?}", queue_name)))?;
+        let message_id = uuid::Uuid::new_v4().to_string();
+        queue.push(StoredMessage {
+            message_id: message_id.clone(),
+            body: body.to_string(),
+            attributes: attributes.clone(),
+            visible_at: Instant::now() + seconds(delay_seconds.unwrap_or(0)),
+            receipt_handle: None,
+            receive_count: 0,
+        });
+        Ok(message_id)
+    }
+
+    async fn queue_receive(
+        &self,
+        queue_name: &str,
+        max_messages: i32,
+        wait_seconds: i32,
+        visibility_timeout: Option<i32>,
+    ) -> Result<Vec<QueueMessage>, AwsError> {
+        let deadline = Instant::now() + seconds(wait_seconds);
+        let visibility = seconds(visibility_timeout.unwrap_or(DEFAULT_VISIBILITY_TIMEOUT_SECONDS));
+        loop {
+            let messages = self.take_visible(
+                queue_name,
+                max_messages.max(1) as usize,
+                Instant::now() + visibility,
+            )?;
+            let now = Instant::now();
+            if !messages.is_empty() || now >= deadline {
+                return Ok(messages);
+            }
+            tokio::time::sleep(RECEIVE_POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    async fn queue_delete(&self, queue_name: &str, receipt_handle: &str) -> Result<(), AwsError> {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues
+            .get_mut(queue_name)
+            .ok_or_else(|| AwsError::NotFound(format!("queue {:?}", queue_name)))?;
+        let position = queue
+            .iter()
+            .position(|message| message.receipt_handle.as_deref() == Some(receipt_handle))
+            .ok_or_else(|| AwsError::NotFound("receipt handle is no longer valid".to_string()))?;
+        queue.remove(position);
+        Ok(())
+    }
+
+    async fn queue_change_visibility(
+        &self,
+        queue_name: &str,
+        receipt_handle: &str,
+        visibility_timeout: i32,
+    ) -> Result<(), AwsError> {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues
+            .get_mut(queue_name)
+            .ok_or_else(|| AwsError::NotFound(format!("queue {:?}", queue_name)))?;
+        let now = Instant::now();
+        let message = queue
+            .iter_mut()
+            .find(|message| {
+                message.receipt_handle.as_deref() == Some(receipt_handle)
+                    && message.visible_at > now
+            })
+            .ok_or_else(|| AwsError::NotFound("message is not in flight".to_string()))?;
+        message.visible_at = now + seconds(visibility_timeout);
+        Ok(())
+    }
+
+    async fn kv_transact_write(
+        &self,
+        session: &TenantSession,
+        ops: &[KvWriteOp],
+    ) -> Result<(), AwsError> {
+        transactions::validate_kv_ops(ops)?;
+        let mut kv = self.kv.lock().unwrap();
+        let failures: Vec<TransactionFailure> = ops
+            .iter()
+            .enumerate()
+            .filter(|(_, op)| {
+                let exists = kv.contains_key(&Self::tenant_key(session, op.key()));
+                op.condition()
+                    .is_some_and(|condition| !condition.holds(exists))
+            })
+            .map(|(index, op)| TransactionFailure {
+                index,
+                operation: op.describe(),
+                code: "ConditionalCheckFailed".to_string(),
+                message: Some("The conditional request failed".to_string()),
+            })
+            .collect();
+        if !failures.is_empty() {
+            return Err(AwsError::TransactionCanceled(failures));
+        }
+
+        for op in ops {
+            let key = Self::tenant_key(session, op.key());
+            self.invalidate_cached(&key);
+            match op {
+                KvWriteOp::Put { value, .. } | KvWriteOp::Update { value, .. } => {
+                    kv.insert(key, value.clone());
+                }
+                KvWriteOp::Delete { .. } => {
+                    kv.remove(&key);
+                }
+                KvWriteOp::ConditionCheck { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn kv_compare_and_swap(
+        &self,
+        session: &TenantSession,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        _ttl_hours: Option<u32>,
+    ) -> Result<bool, AwsError> {
+        let tenant_key = Self::tenant_key(session, key);
+        {
+            let mut kv = self.kv.lock().unwrap();
+            if kv.get(&tenant_key).map(String::as_str) != expected {
+                return Ok(false);
+            }
+            kv.insert(tenant_key.clone(), value.to_string());
+        }
+        self.invalidate_cached(&tenant_key);
+        let delay = *self.swap_delay.lock().unwrap();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        Ok(true)
+    }
+
+    async fn create_event_rule_with_alert(
+        &self,
+        session: &TenantSession,
+        name: &str,
+        pattern: Value,
+        description: Option<String>,
+        enabled: bool,
+        alert: &NewAlertSubscription,
+    ) -> Result<Value, AwsError> {
+        // Nothing here can fail between the two writes
+        let mut rule = self
+            .create_event_rule(session, name, pattern, description, enabled)
+            .await?;
+        let rule_id = rule["ruleId"].as_str().unwrap_or_default().to_string();
+        rule["alert"] = self
+            .create_alert_subscription(
+                session,
+                &alert.name,
+                &rule_id,
+                &alert.notification_method,
+                alert.sns_topic_arn.clone(),
+                alert.email_address.clone(),
+                alert.enabled,
+            )
+            .await?;
+        Ok(rule)
+    }
+
+    async fn sns_topic_exists(&self, topic_arn: &str) -> Result<bool, AwsError> {
+        Ok(self.topics.lock().unwrap().contains_key(topic_arn))
+    }
+
+    async fn sns_publish(
+        &self,
+        topic_arn: &str,
+        message: &str,
+        attributes: &HashMap<String, String>,
+    ) -> Result<String, AwsError> {
+        self.topics
+            .lock()
+            .unwrap()
+            .get_mut(topic_arn)
+            .ok_or_else(|| AwsError::NotFound(format!("topic {:?}", topic_arn)))?
+            .push((message.to_string(), attributes.clone()));
+        Ok(uuid::Uuid::new_v4().to_string())
+    }
+
+    async fn record_alert_error(
+        &self,
+        _session: &TenantSession,
+        subscription_id: &str,
+        error: &str,
+    ) -> Result<(), AwsError> {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let subscription = subscriptions
+            .iter_mut()
+            .find(|subscription| subscription["subscriptionId"] == subscription_id)
+            .ok_or_else(|| AwsError::NotFound(format!("subscription {:?}", subscription_id)))?;
+        subscription["lastError"] = json!(error);
+        subscription["lastErrorAt"] = json!(chrono::Utc::now().to_rfc3339());
+        Ok(())
+    }
+
+    /// Pages are in key order; positions are full table keys, as in DynamoDB
+    async fn kv_keys(
+        &self,
+        session: &TenantSession,
+        start: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<String>, Option<String>), AwsError> {
+        let prefix = Self::tenant_key(session, "");
+        let mut keys: Vec<String> = self
+            .kv
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(&prefix))
+            .filter(|key| start.is_none_or(|start| key.as_str() > start))
+            .cloned()
+            .collect();
+        keys.sort();
+        let next = (keys.len() > limit).then(|| keys[limit - 1].clone());
+        keys.truncate(limit);
+        Ok((
+            keys.iter()
+                .map(|key| key[prefix.len()..].to_string())
+                .collect(),
+            next,
+        ))
+    }
+
+    async fn event_rules(&self, session: &TenantSession) -> Result<Vec<Value>, AwsError> {
+        Ok(owned_by(&self.rules.lock().unwrap(), session))
+    }
+
+    async fn alert_subscriptions(&self, session: &TenantSession) -> Result<Vec<Value>, AwsError> {
+        Ok(owned_by(&self.subscriptions.lock().unwrap(), session))
+    }
+
+    async fn namespace_users(&self, session: &TenantSession) -> Result<Vec<String>, AwsError> {
+        let mut users = BTreeSet::from([session.context.user_id.clone()]);
+        let Some(prefix) = namespaces::organization_namespace_prefix(session) else {
+            return Ok(users.into_iter().collect());
+        };
+        let user = |value: &str| namespaces::namespace_user(&prefix, value).map(str::to_string);
+        users.extend(self.kv.lock().unwrap().keys().filter_map(|key| user(key)));
+        users.extend(self.artifact_keys("").iter().filter_map(|key| user(key)));
+        users.extend(
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|event| event["namespace"].as_str().and_then(user)),
+        );
+        for items in [&self.rules, &self.subscriptions] {
+            users.extend(
+                items
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|item| {
+                        item["organizationId"] == session.context.organization_id.as_str()
+                    })
+                    .filter_map(|item| item["userId"].as_str().map(str::to_string)),
+            );
+        }
+        Ok(users.into_iter().collect())
+    }
+
+    async fn delete_event_rule(
+        &self,
+        session: &TenantSession,
+        rule_id: &str,
+    ) -> Result<(), AwsError> {
+        remove_owned(
+            &mut self.rules.lock().unwrap(),
+            session,
+            ("ruleId", rule_id),
+        )
+    }
+
+    async fn delete_alert_subscription(
+        &self,
+        session: &TenantSession,
+        subscription_id: &str,
+    ) -> Result<(), AwsError> {
+        remove_owned(
+            &mut self.subscriptions.lock().unwrap(),
+            session,
+            ("subscriptionId", subscription_id),
+        )
+    }
+
+    async fn delete_events(
+        &self,
+        _session: &TenantSession,
+        event_ids: &[String],
+    ) -> Result<(), AwsError> {
+        let event_ids: HashSet<&str> = event_ids.iter().map(String::as_str).collect();
+        self.events.lock().unwrap().retain(|event| {
+            !event["eventId"]
+                .as_str()
+                .is_some_and(|id| event_ids.contains(id))
+        });
+        Ok(())
+    }
+}
+
+fn seconds(seconds: i32) -> Duration {
+    Duration::from_secs(seconds.max(0) as u64)
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::tenant::TenantSession;
+
+/// Where a session's artifact is kept: `{namespace}/{key}`, under the same namespace
+/// prefix as its KV items (`user:alice` or `org:acme:user:alice`)
+pub fn artifact_key(session: &TenantSession, key: &str) -> String {
+    format!("{}/{}", session.context.get_namespace_prefix(), key)
+}
+
+/// Where artifacts were kept before they moved under the namespace prefix:
+/// `{context_id}/{key}` (`personal-alice` or `org-acme`). They're still read from there
+/// until `namespace_migrate` relocates them.
+pub fn legacy_artifact_key(session: &TenantSession, key: &str) -> String {
+    format!("{}/{}", session.context.get_context_id(), key)
+}
+
+/// What the namespaces of every user of the session's organization start with
+/// (`org:acme:user:`); `None` in a personal context, whose namespace is one user's
+pub fn organization_namespace_prefix(session: &TenantSession) -> Option<String> {
+    session
+        .context
+        .get_org_id()
+        .map(|org_id| format!("org:{}:user:", org_id))
+}
+
+/// The user a KV key, artifact key or event namespace under `prefix` (see
+/// [`organization_namespace_prefix`]) belongs to: `bob` of `org:acme:user:bob:notes`
+pub fn namespace_user<'a>(prefix: &str, key: &'a str) -> Option<&'a str> {
+    key.strip_prefix(prefix)?
+        .split([':', '/'])
+        .next()
+        .filter(|user_id| !user_id.is_empty())
+}
+
+/// Which event records a session's `events_query` may return: those tagged with its
+/// namespace and, in a personal context, the user's records from before events were
+/// tagged. Organization contexts only see tagged records, so a user's personal and
+/// organization events never mix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventScope {
+    pub namespace: String,
+    /// User whose untagged records are in scope
+    pub legacy_user_id: Option<String>,
+}
+
+impl EventScope {
+    pub fn for_session(session: &TenantSession) -> Self {
+        Self {
+            namespace: session.context.get_namespace_prefix(),
+            legacy_user_id: session
+                .context
+                .is_personal()
+                .then(|| session.context.user_id.clone()),
+        }
+    }
+
+    /// Whether an event record (as stored, with `namespace` and `userId`) is in scope
+    pub fn contains(&self, event: &Value) -> bool {
+        match event["namespace"].as_str() {
+            Some(namespace) => namespace == self.namespace,
+            None => self
+                .legacy_user_id
+                .as_deref()
+                .is_some_and(|user_id| event["userId"] == user_id),
+        }
+    }
+}
+
+/// What `namespace_migrate` did with a session's legacy artifacts; keys are relative to
+/// the namespace
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ArtifactMigration {
+    pub moved: Vec<String>,
+    /// Left in place because the namespace already has an artifact with that key
+    pub skipped: Vec<String>,
+}
+
+/// `CopyObject`'s `x-amz-copy-source`: the bucket and key, URL-encoded except for `/`
+pub(crate) fn copy_source(bucket: &str, key: &str) -> String {
+    let mut source = format!("{}/", bucket);
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                source.push(byte as char)
+            }
+            _ => source.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    source
+}
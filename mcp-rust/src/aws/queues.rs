@@ -0,0 +1,72 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use super::AwsError;
+use crate::tenant::TenantSession;
+
+/// Prefix of the SQS queues the queue tools use (default `agent-mesh`); the tenant id
+/// and the queue's own name follow
+pub const QUEUE_PREFIX_ENV: &str = "AGENT_MESH_QUEUE_PREFIX";
+
+pub const DEFAULT_QUEUE_PREFIX: &str = "agent-mesh";
+
+/// SQS limits queue names to 80 characters
+const MAX_QUEUE_NAME_LEN: usize = 80;
+
+/// Messages `queue_receive` can return at once
+pub const MAX_MESSAGES: RangeInclusive<i32> = 1..=10;
+
+/// Seconds `queue_receive` can wait for a message (long polling)
+pub const WAIT_SECONDS: RangeInclusive<i32> = 0..=20;
+
+/// Seconds a received message can stay hidden from other receivers
+pub const VISIBILITY_TIMEOUT_SECONDS: RangeInclusive<i32> = 0..=43_200;
+
+/// Seconds a sent message can be delayed before it's first delivered
+pub const DELAY_SECONDS: RangeInclusive<i32> = 0..=900;
+
+/// Visibility timeout of queues that don't set their own, as in SQS
+pub const DEFAULT_VISIBILITY_TIMEOUT_SECONDS: i32 = 30;
+
+/// A received message. It stays hidden from other receivers until its visibility
+/// timeout ends; acknowledging it with the receipt handle deletes it, otherwise it's
+/// delivered again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueMessage {
+    pub message_id: String,
+    pub receipt_handle: String,
+    pub body: String,
+    /// String message attributes given to `queue_send`
+    pub attributes: HashMap<String, String>,
+    /// How many times the message has been received, this time included
+    pub receive_count: u32,
+}
+
+/// Whether `name` is made of characters SQS queue names allow, optionally without `-`
+fn is_name_safe(name: &str, allow_dash: bool) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || (allow_dash && c == '-'))
+}
+
+/// SQS queue name for the session's queue `name`: `{prefix}-{tenant_id}-{name}`, so a
+/// tenant can only reach queues under its own prefix. Names can't contain `-`, so
+/// everything before the last `-` is the tenant's prefix and two tenants can't name the
+/// same queue.
+pub fn tenant_queue_name(
+    prefix: &str,
+    session: &TenantSession,
+    name: &str,
+) -> Result<String, AwsError> {
+    let queue_name = format!("{}-{}-{}", prefix, session.context.tenant_id, name);
+    if !is_name_safe(name, false)
+        || !is_name_safe(&session.context.tenant_id, true)
+        || queue_name.len() > MAX_QUEUE_NAME_LEN
+    {
+        return Err(AwsError::InvalidQueueName(name.to_string()));
+    }
+    Ok(queue_name)
+}
@@ -0,0 +1,425 @@
+use async_trait::async_trait;
+use aws_sdk_dynamodb::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_dynamodb::operation::describe_table::DescribeTableError;
+use aws_sdk_eventbridge::operation::describe_event_bus::DescribeEventBusError;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::operation::head_bucket::HeadBucketError;
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::queues::{DEFAULT_QUEUE_PREFIX, QUEUE_PREFIX_ENV};
+use super::{AwsClients, AwsError};
+
+/// Set to `true` to check the configured tables, bucket and event bus at startup
+pub const VALIDATE_RESOURCES_ENV: &str = "MCP_VALIDATE_RESOURCES";
+
+/// Set to `true` to refuse to start when a checked resource isn't usable; otherwise
+/// problems are logged as warnings
+pub const VALIDATE_RESOURCES_STRICT_ENV: &str = "MCP_VALIDATE_RESOURCES_STRICT";
+
+/// Deployment environment (`dev`, `staging`, `prod`, ...) every resource name not set
+/// by its own variable is derived from: `agent-mesh-{environment}-{resource}`
+pub const ENVIRONMENT_ENV: &str = "AGENT_MESH_ENVIRONMENT";
+
+/// Error codes meaning the caller's credentials may not use the resource
+const FORBIDDEN_ERROR_CODES: &[&str] = &[
+    "AccessDenied",
+    "AccessDeniedException",
+    "Forbidden",
+    "UnauthorizedOperation",
+    "UnrecognizedClientException",
+];
+
+/// The names of the AWS resources the server uses, as configured by the environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceNames {
+    pub kv_table: String,
+    pub events_table: String,
+    pub event_rules_table: String,
+    pub subscriptions_table: String,
+    /// Where events that couldn't be published are kept; the KV table unless set
+    pub dead_letter_table: String,
+    pub artifacts_bucket: String,
+    pub event_bus: String,
+    /// Prefix of the tenants' SQS queue names; not derived from the environment
+    pub queue_prefix: String,
+}
+
+impl ResourceNames {
+    /// Each name is, in order: its own variable (e.g. `AGENT_MESH_EVENTS_TABLE`),
+    /// `agent-mesh-{environment}-{resource}` when [`ENVIRONMENT_ENV`] is set, or the
+    /// built-in default. The dead-letter table falls back to the KV table instead. A
+    /// variable set to an empty name is an error rather than a fallback, so a
+    /// misconfigured deployment doesn't quietly use another environment's resources.
+    pub fn resolve(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, AwsError> {
+        let configured = |var: &str| match lookup(var) {
+            Some(value) if value.trim().is_empty() => Err(AwsError::Config(format!(
+                "{} is set but empty; unset it to use the default",
+                var
+            ))),
+            value => Ok(value),
+        };
+        let environment = configured(ENVIRONMENT_ENV)?;
+        let name = |kind: ResourceKind| -> Result<String, AwsError> {
+            Ok(
+                configured(kind.env_var())?.unwrap_or_else(|| match &environment {
+                    Some(environment) => format!("agent-mesh-{}-{}", environment, kind.suffix()),
+                    None => kind.default_name().to_string(),
+                }),
+            )
+        };
+        let kv_table = name(ResourceKind::KvTable)?;
+        Ok(Self {
+            dead_letter_table: configured(ResourceKind::DeadLetterTable.env_var())?
+                .unwrap_or_else(|| kv_table.clone()),
+            kv_table,
+            events_table: name(ResourceKind::EventsTable)?,
+            event_rules_table: name(ResourceKind::EventRulesTable)?,
+            subscriptions_table: name(ResourceKind::SubscriptionsTable)?,
+            artifacts_bucket: name(ResourceKind::ArtifactsBucket)?,
+            event_bus: name(ResourceKind::EventBus)?,
+            queue_prefix: configured(QUEUE_PREFIX_ENV)?
+                .unwrap_or_else(|| DEFAULT_QUEUE_PREFIX.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    KvTable,
+    EventsTable,
+    EventRulesTable,
+    SubscriptionsTable,
+    DeadLetterTable,
+    ArtifactsBucket,
+    EventBus,
+}
+
+impl ResourceKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::KvTable => "KV table",
+            Self::EventsTable => "Events table",
+            Self::EventRulesTable => "Event rules table",
+            Self::SubscriptionsTable => "Subscriptions table",
+            Self::DeadLetterTable => "Dead-letter table",
+            Self::ArtifactsBucket => "Artifacts bucket",
+            Self::EventBus => "Event bus",
+        }
+    }
+
+    /// The environment variable naming this resource
+    pub fn env_var(self) -> &'static str {
+        match self {
+            Self::KvTable => "AGENT_MESH_KV_TABLE",
+            Self::EventsTable => "AGENT_MESH_EVENTS_TABLE",
+            Self::EventRulesTable => "AGENT_MESH_EVENT_RULES_TABLE",
+            Self::SubscriptionsTable => "AGENT_MESH_SUBSCRIPTIONS_TABLE",
+            Self::DeadLetterTable => "AGENT_MESH_DEAD_LETTER_TABLE",
+            Self::ArtifactsBucket => "AGENT_MESH_ARTIFACTS_BUCKET",
+            Self::EventBus => "AGENT_MESH_EVENT_BUS",
+        }
+    }
+
+    /// The resource's part of a name derived from [`ENVIRONMENT_ENV`]
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::KvTable => "kv",
+            Self::EventsTable => "events",
+            Self::EventRulesTable => "event-rules",
+            Self::SubscriptionsTable => "subscriptions",
+            Self::DeadLetterTable => "kv",
+            Self::ArtifactsBucket => "artifacts",
+            Self::EventBus => "events",
+        }
+    }
+
+    /// The name used when neither the resource's variable nor the environment is set
+    pub fn default_name(self) -> &'static str {
+        match self {
+            Self::KvTable => "agent-mesh-kv",
+            Self::EventsTable => "agent-mesh-dev-events",
+            Self::EventRulesTable => "agent-mesh-dev-event-rules",
+            Self::SubscriptionsTable => "agent-mesh-dev-subscriptions",
+            Self::DeadLetterTable => "agent-mesh-kv",
+            Self::ArtifactsBucket => "agent-mesh-artifacts",
+            Self::EventBus => "agent-mesh-events",
+        }
+    }
+}
+
+/// What describing a resource found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "message", rename_all = "snake_case")]
+pub enum ResourceStatus {
+    Ok,
+    Missing,
+    /// The credentials may not describe (and so probably not use) the resource
+    Forbidden,
+    /// The check itself failed, e.g. the endpoint couldn't be reached
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResourceCheck {
+    pub kind: ResourceKind,
+    /// The exact name that was checked
+    pub name: String,
+    #[serde(flatten)]
+    pub status: ResourceStatus,
+}
+
+impl ResourceCheck {
+    /// One line saying what's wrong and which variable to fix, or `None` if the
+    /// resource is usable
+    pub fn problem(&self) -> Option<String> {
+        let what = match &self.status {
+            ResourceStatus::Ok => return None,
+            ResourceStatus::Missing => "doesn't exist".to_string(),
+            ResourceStatus::Forbidden => "isn't accessible with these credentials".to_string(),
+            ResourceStatus::Error(message) => format!("couldn't be checked ({})", message),
+        };
+        Some(format!(
+            "{} {:?} {} (set by {})",
+            self.kind.label(),
+            self.name,
+            what,
+            self.kind.env_var()
+        ))
+    }
+}
+
+/// The result of checking every configured resource
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResourceReport {
+    pub checked_at: chrono::DateTime<chrono::Utc>,
+    pub checks: Vec<ResourceCheck>,
+}
+
+impl ResourceReport {
+    pub fn is_ok(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status == ResourceStatus::Ok)
+    }
+
+    pub fn problems(&self) -> Vec<String> {
+        self.checks
+            .iter()
+            .filter_map(ResourceCheck::problem)
+            .collect()
+    }
+
+    /// Log each problem as a warning, or with `strict` fail with all of them
+    pub fn enforce(&self, strict: bool) -> Result<(), AwsError> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            eprintln!(
+                "[MCP Server] All {} AWS resources are available",
+                self.checks.len()
+            );
+            return Ok(());
+        }
+        if strict {
+            return Err(AwsError::Config(format!(
+                "AWS resources unavailable: {}",
+                problems.join("; ")
+            )));
+        }
+        for problem in problems {
+            eprintln!("[MCP Server] Warning: {}", problem);
+        }
+        Ok(())
+    }
+}
+
+/// Describes one resource of each kind; the AWS implementation calls `DescribeTable`,
+/// `HeadBucket` and `DescribeEventBus`
+#[async_trait]
+pub trait ResourceProber: Send + Sync {
+    async fn describe_table(&self, name: &str) -> ResourceStatus;
+    async fn head_bucket(&self, name: &str) -> ResourceStatus;
+    async fn describe_event_bus(&self, name: &str) -> ResourceStatus;
+}
+
+/// Check every resource in `names`; the dead-letter table only when it isn't the KV
+/// table
+pub async fn validate_resources(
+    prober: &dyn ResourceProber,
+    names: &ResourceNames,
+) -> ResourceReport {
+    let mut tables = vec![
+        (ResourceKind::KvTable, &names.kv_table),
+        (ResourceKind::EventsTable, &names.events_table),
+        (ResourceKind::EventRulesTable, &names.event_rules_table),
+        (ResourceKind::SubscriptionsTable, &names.subscriptions_table),
+    ];
+    if names.dead_letter_table != names.kv_table {
+        tables.push((ResourceKind::DeadLetterTable, &names.dead_letter_table));
+    }
+    let mut checks = Vec::with_capacity(tables.len() + 2);
+    for (kind, name) in tables {
+        checks.push(ResourceCheck {
+            kind,
+            name: name.clone(),
+            status: prober.describe_table(name).await,
+        });
+    }
+    checks.push(ResourceCheck {
+        kind: ResourceKind::ArtifactsBucket,
+        name: names.artifacts_bucket.clone(),
+        status: prober.head_bucket(&names.artifacts_bucket).await,
+    });
+    checks.push(ResourceCheck {
+        kind: ResourceKind::EventBus,
+        name: names.event_bus.clone(),
+        status: prober.describe_event_bus(&names.event_bus).await,
+    });
+    ResourceReport {
+        checked_at: chrono::Utc::now(),
+        checks,
+    }
+}
+
+/// How a describe call's error reads as a [`ResourceStatus`]; `missing` recognises the
+/// operation's not-found error
+fn probe_status<E>(err: SdkError<E, HttpResponse>, missing: fn(&E) -> bool) -> ResourceStatus
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    if err.as_service_error().is_some_and(missing) {
+        return ResourceStatus::Missing;
+    }
+    let http_status = err
+        .raw_response()
+        .map(|response| response.status().as_u16());
+    if err
+        .code()
+        .is_some_and(|code| FORBIDDEN_ERROR_CODES.contains(&code))
+        || http_status == Some(403)
+    {
+        return ResourceStatus::Forbidden;
+    }
+    ResourceStatus::Error(
+        err.message()
+            .map(str::to_string)
+            .unwrap_or_else(|| DisplayErrorContext(&err).to_string()),
+    )
+}
+
+/// [`ResourceProber`] over the server's AWS clients
+pub struct AwsResourceProber {
+    clients: Arc<AwsClients>,
+}
+
+impl AwsResourceProber {
+    pub fn new(clients: Arc<AwsClients>) -> Self {
+        Self { clients }
+    }
+}
+
+#[async_trait]
+impl ResourceProber for AwsResourceProber {
+    async fn describe_table(&self, name: &str) -> ResourceStatus {
+        match self
+            .clients
+            .dynamodb
+            .describe_table()
+            .table_name(name)
+            .send()
+            .await
+        {
+            Ok(_) => ResourceStatus::Ok,
+            Err(e) => probe_status(e, DescribeTableError::is_resource_not_found_exception),
+        }
+    }
+
+    async fn head_bucket(&self, name: &str) -> ResourceStatus {
+        match self.clients.s3.head_bucket().bucket(name).send().await {
+            Ok(_) => ResourceStatus::Ok,
+            Err(e) => probe_status(e, HeadBucketError::is_not_found),
+        }
+    }
+
+    async fn describe_event_bus(&self, name: &str) -> ResourceStatus {
+        match self
+            .clients
+            .eventbridge
+            .describe_event_bus()
+            .name(name)
+            .send()
+            .await
+        {
+            Ok(_) => ResourceStatus::Ok,
+            Err(e) => probe_status(e, DescribeEventBusError::is_resource_not_found_exception),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::error::ErrorMetadata;
+    use aws_sdk_dynamodb::types::error::ResourceNotFoundException;
+    use aws_sdk_s3::primitives::SdkBody;
+
+    fn service_error<E>(err: E, status: u16) -> SdkError<E, HttpResponse> {
+        SdkError::service_error(
+            err,
+            HttpResponse::new(status.try_into().unwrap(), SdkBody::empty()),
+        )
+    }
+
+    #[test]
+    fn test_probe_status_from_sdk_errors() {
+        let missing = service_error(
+            DescribeTableError::ResourceNotFoundException(
+                ResourceNotFoundException::builder().build(),
+            ),
+            400,
+        );
+        assert_eq!(
+            probe_status(missing, DescribeTableError::is_resource_not_found_exception),
+            ResourceStatus::Missing
+        );
+
+        let denied = service_error(
+            DescribeTableError::generic(
+                ErrorMetadata::builder()
+                    .code("AccessDeniedException")
+                    .message("not authorized to perform dynamodb:DescribeTable")
+                    .build(),
+            ),
+            400,
+        );
+        assert_eq!(
+            probe_status(denied, DescribeTableError::is_resource_not_found_exception),
+            ResourceStatus::Forbidden
+        );
+
+        // HEAD responses have no body, so S3 only says 403
+        let denied = service_error(
+            HeadBucketError::generic(ErrorMetadata::builder().build()),
+            403,
+        );
+        assert_eq!(
+            probe_status(denied, HeadBucketError::is_not_found),
+            ResourceStatus::Forbidden
+        );
+
+        let failed = service_error(
+            DescribeTableError::generic(
+                ErrorMetadata::builder()
+                    .code("InternalServerError")
+                    .message("try again")
+                    .build(),
+            ),
+            500,
+        );
+        assert_eq!(
+            probe_status(failed, DescribeTableError::is_resource_not_found_exception),
+            ResourceStatus::Error("try again".to_string())
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use async_trait::async_trait;
+use aws_sdk_sts::config::{Credentials, SharedCredentialsProvider};
+use aws_sdk_sts::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use super::{AwsClients, AwsError};
+use crate::config::AwsConfig;
+use crate::tenant::{TenantAwsRole, TenantContext};
+
+/// How long assumed-role credentials are asked for
+pub const ROLE_SESSION_DURATION: Duration = Duration::from_secs(3600);
+
+/// Credentials are replaced once they're this close to expiring
+pub const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(300);
+
+/// Longest role session name STS accepts
+const MAX_ROLE_SESSION_NAME: usize = 64;
+
+/// Exchanges the server's own credentials for a tenant role's temporary ones
+#[async_trait]
+pub trait RoleAssumer: Send + Sync {
+    /// Credentials for `role`, with their expiry, or why STS wouldn't give them
+    async fn assume_role(
+        &self,
+        role: &TenantAwsRole,
+        session_name: &str,
+    ) -> Result<Credentials, String>;
+}
+
+/// [`RoleAssumer`] over STS `AssumeRole`
+pub struct StsRoleAssumer {
+    client: aws_sdk_sts::Client,
+}
+
+impl StsRoleAssumer {
+    pub fn new(client: aws_sdk_sts::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl RoleAssumer for StsRoleAssumer {
+    async fn assume_role(
+        &self,
+        role: &TenantAwsRole,
+        session_name: &str,
+    ) -> Result<Credentials, String> {
+        let output = self
+            .client
+            .assume_role()
+            .role_arn(&role.assume_role_arn)
+            .set_external_id(role.external_id.clone())
+            .role_session_name(session_name)
+            .duration_seconds(ROLE_SESSION_DURATION.as_secs() as i32)
+            .send()
+            .await
+            .map_err(|e| match e {
+                SdkError::ServiceError(e) => format!(
+                    "{}: {}",
+                    e.err().code().unwrap_or("unknown error"),
+                    e.err().message().unwrap_or_default()
+                ),
+                e => DisplayErrorContext(&e).to_string(),
+            })?;
+        let credentials = output
+            .credentials()
+            .ok_or_else(|| "STS returned no credentials".to_string())?;
+        let expires_at =
+            SystemTime::try_from(*credentials.expiration()).map_err(|e| e.to_string())?;
+        Ok(Credentials::new(
+            credentials.access_key_id(),
+            credentials.secret_access_key(),
+            Some(credentials.session_token().to_string()),
+            Some(expires_at),
+            "sts-assume-role",
+        ))
+    }
+}
+
+/// `mcp-<tenant_id>`, with characters STS doesn't allow in a role session name replaced
+pub fn role_session_name(tenant_id: &str) -> String {
+    format!("mcp-{}", tenant_id)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "+=,.@_-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(MAX_ROLE_SESSION_NAME)
+        .collect()
+}
+
+/// A tenant's clients, signing with credentials for `role` until `expires_at`
+struct RoleClients {
+    role: TenantAwsRole,
+    expires_at: SystemTime,
+    clients: Arc<AwsClients>,
+}
+
+/// The AWS clients each tenant's operations go through: the server's own, or for
+/// tenants with an `aws_role`, clients signing with that role's credentials. Those are
+/// assumed on first use and again shortly before they expire, once per tenant however
+/// many requests are waiting. A tenant whose role can't be assumed is degraded until it
+/// next can; credentials it still holds are used until they expire.
+pub struct TenantClients {
+    sdk_config: aws_config::SdkConfig,
+    config: AwsConfig,
+    default: Arc<AwsClients>,
+    assumer: Arc<dyn RoleAssumer>,
+    roles: Mutex<HashMap<String, Arc<tokio::sync::Mutex<Option<RoleClients>>>>>,
+    /// Why each degraded tenant's role couldn't be assumed
+    failures: Mutex<BTreeMap<String, String>>,
+}
+
+impl TenantClients {
+    /// Role clients are built like `default`, from `sdk_config` with the role's
+    /// credentials and the endpoints in `config`
+    pub fn new(
+        sdk_config: aws_config::SdkConfig,
+        config: &AwsConfig,
+        default: Arc<AwsClients>,
+        assumer: Arc<dyn RoleAssumer>,
+    ) -> Self {
+        Self {
+            sdk_config,
+            config: config.clone(),
+            default,
+            assumer,
+            roles: Mutex::default(),
+            failures: Mutex::default(),
+        }
+    }
+
+    /// The clients for the tenant of `context`
+    pub async fn for_context(&self, context: &TenantContext) -> Result<Arc<AwsClients>, AwsError> {
+        self.for_context_at(context, SystemTime::now()).await
+    }
+
+    /// [`Self::for_context`] as of `now`
+    pub async fn for_context_at(
+        &self,
+        context: &TenantContext,
+        now: SystemTime,
+    ) -> Result<Arc<AwsClients>, AwsError> {
+        let tenant_id = &context.tenant_id;
+        let Some(role) = &context.aws_role else {
+            self.failures.lock().unwrap().remove(tenant_id);
+            return Ok(self.default.clone());
+        };
+        let slot = self
+            .roles
+            .lock()
+            .unwrap()
+            .entry(tenant_id.clone())
+            .or_default()
+            .clone();
+        let mut slot = slot.lock().await;
+        let cached = slot
+            .as_ref()
+            .filter(|cached| cached.role == *role)
+            .map(|cached| (cached.expires_at, cached.clients.clone()));
+        if let Some((expires_at, clients)) = &cached {
+            if now + REFRESH_BEFORE_EXPIRY < *expires_at {
+                return Ok(clients.clone());
+            }
+        }
+
+        match self
+            .assumer
+            .assume_role(role, &role_session_name(tenant_id))
+            .await
+        {
+            Ok(credentials) => {
+                let expires_at = credentials.expiry().unwrap_or(now + ROLE_SESSION_DURATION);
+                let sdk_config = self
+                    .sdk_config
+                    .to_builder()
+                    .credentials_provider(SharedCredentialsProvider::new(credentials))
+                    .build();
+                let clients = Arc::new(AwsClients::new(&sdk_config, &self.config));
+                *slot = Some(RoleClients {
+                    role: role.clone(),
+                    expires_at,
+                    clients: clients.clone(),
+                });
+                if self.failures.lock().unwrap().remove(tenant_id).is_some() {
+                    info!(
+                        "Assumed {} for tenant {} again",
+                        role.assume_role_arn, tenant_id
+                    );
+                }
+                Ok(clients)
+            }
+            Err(reason) => {
+                warn!(
+                    "Could not assume {} for tenant {}: {}",
+                    role.assume_role_arn, tenant_id, reason
+                );
+                self.failures
+                    .lock()
+                    .unwrap()
+                    .insert(tenant_id.clone(), reason.clone());
+                match cached {
+                    Some((expires_at, clients)) if now < expires_at => Ok(clients),
+                    _ => Err(AwsError::RoleAssumption {
+                        role_arn: role.assume_role_arn.clone(),
+                        reason,
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Tenants whose role couldn't be assumed the last time it was tried, with why
+    pub fn degraded_tenants(&self) -> BTreeMap<String, String> {
+        self.failures.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,166 @@
+use aws_sdk_dynamodb::types::CancellationReason;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::AwsError;
+
+/// Most operations DynamoDB accepts in one `TransactWriteItems` call
+pub const MAX_TRANSACT_OPS: usize = 25;
+
+/// Whether the item must already exist for an operation to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum ItemCondition {
+    Exists,
+    Absent,
+}
+
+impl ItemCondition {
+    /// Condition expression on the KV table's `key` attribute (a reserved word, hence
+    /// the `#key` placeholder)
+    pub(crate) fn expression(self) -> &'static str {
+        match self {
+            ItemCondition::Exists => "attribute_exists(#key)",
+            ItemCondition::Absent => "attribute_not_exists(#key)",
+        }
+    }
+
+    /// Whether the condition holds for an item that does or doesn't exist
+    pub fn holds(self, exists: bool) -> bool {
+        exists == (self == ItemCondition::Exists)
+    }
+}
+
+/// One write in a KV transaction. Keys are the caller's own; they're namespaced for the
+/// session's tenant as in `kv_set`. Values are stored as plain strings, never encrypted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum KvWriteOp {
+    /// Create or replace the item
+    Put {
+        key: String,
+        value: String,
+        ttl_hours: Option<u32>,
+        condition: Option<ItemCondition>,
+    },
+    /// Replace the value of an existing item, keeping its TTL
+    Update { key: String, value: String },
+    Delete {
+        key: String,
+        condition: Option<ItemCondition>,
+    },
+    /// Write nothing, but cancel the transaction unless the condition holds
+    ConditionCheck {
+        key: String,
+        condition: ItemCondition,
+    },
+}
+
+impl KvWriteOp {
+    pub fn key(&self) -> &str {
+        match self {
+            KvWriteOp::Put { key, .. }
+            | KvWriteOp::Update { key, .. }
+            | KvWriteOp::Delete { key, .. }
+            | KvWriteOp::ConditionCheck { key, .. } => key,
+        }
+    }
+
+    /// What the item must be like for the operation to apply; updates need the item
+    pub fn condition(&self) -> Option<ItemCondition> {
+        match self {
+            KvWriteOp::Put { condition, .. } | KvWriteOp::Delete { condition, .. } => *condition,
+            KvWriteOp::Update { .. } => Some(ItemCondition::Exists),
+            KvWriteOp::ConditionCheck { condition, .. } => Some(*condition),
+        }
+    }
+
+    /// The operation as it's named in a cancellation, e.g. `put "usage"`
+    pub fn describe(&self) -> String {
+        let kind = match self {
+            KvWriteOp::Put { .. } => "put",
+            KvWriteOp::Update { .. } => "update",
+            KvWriteOp::Delete { .. } => "delete",
+            KvWriteOp::ConditionCheck { .. } => "condition check",
+        };
+        format!("{} {:?}", kind, self.key())
+    }
+}
+
+/// Why one operation of a canceled transaction failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionFailure {
+    /// Position of the operation in the transaction
+    pub index: usize,
+    /// The operation, as described by its caller
+    pub operation: String,
+    /// DynamoDB's cancellation code, e.g. `ConditionalCheckFailed` or `TransactionConflict`
+    pub code: String,
+    pub message: Option<String>,
+}
+
+/// Check a transaction's size against DynamoDB's limits
+pub fn check_op_count(count: usize) -> Result<(), AwsError> {
+    if count == 0 || count > MAX_TRANSACT_OPS {
+        return Err(AwsError::InvalidTransaction(format!(
+            "a transaction has 1-{} operations, not {}",
+            MAX_TRANSACT_OPS, count
+        )));
+    }
+    Ok(())
+}
+
+/// Check a KV transaction as DynamoDB would: 1-25 operations, each on a different key
+pub fn validate_kv_ops(ops: &[KvWriteOp]) -> Result<(), AwsError> {
+    check_op_count(ops.len())?;
+    let mut keys = HashSet::new();
+    match ops.iter().find(|op| !keys.insert(op.key())) {
+        Some(op) => Err(AwsError::InvalidTransaction(format!(
+            "key {:?} appears more than once",
+            op.key()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// The failed operations of a canceled transaction. DynamoDB gives one reason per
+/// operation, in order, with code `None` for those that would have succeeded.
+pub fn cancellation_failures(
+    reasons: &[CancellationReason],
+    operations: &[String],
+) -> Vec<TransactionFailure> {
+    reasons
+        .iter()
+        .enumerate()
+        .filter_map(|(index, reason)| {
+            let code = reason.code().filter(|code| *code != "None")?;
+            Some(TransactionFailure {
+                index,
+                operation: operations
+                    .get(index)
+                    .cloned()
+                    .unwrap_or_else(|| format!("operation {}", index)),
+                code: code.to_string(),
+                message: reason.message().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// `op 1 (put "usage"): ConditionalCheckFailed, ...` for error messages
+pub fn describe_failures(failures: &[TransactionFailure]) -> String {
+    if failures.is_empty() {
+        return "no reason given".to_string();
+    }
+    failures
+        .iter()
+        .map(|failure| {
+            format!(
+                "op {} ({}): {}",
+                failure.index, failure.operation, failure.code
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::AwsError;
+use crate::tenant::TenantSession;
+
+/// Step Functions limits execution names to 80 characters
+const MAX_EXECUTION_NAME_LEN: usize = 80;
+
+/// Separates the tenant id from the unique suffix in execution names
+const OWNER_SEPARATOR: &str = "--";
+
+/// Length of the unique suffix (a simple-format UUID)
+const EXECUTION_ID_LEN: usize = 32;
+
+/// An execution as `workflow_status` reports it: the status, and the output or the
+/// error once it has finished
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowExecution {
+    pub execution_arn: String,
+    pub state_machine_arn: String,
+    /// `RUNNING`, `SUCCEEDED`, `FAILED`, `TIMED_OUT`, `ABORTED` or `PENDING_REDRIVE`
+    pub status: String,
+    pub start_date: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_date: Option<DateTime<Utc>>,
+    /// The execution's output, as JSON when it parses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+}
+
+/// Output as Step Functions returns it (a JSON document) read back into a value; output
+/// that doesn't parse is kept as a string
+pub fn parse_output(output: &str) -> Value {
+    serde_json::from_str(output).unwrap_or_else(|_| Value::String(output.to_string()))
+}
+
+/// Whether `name` is usable in Step Functions names and ARNs
+fn is_name_safe(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Check that `arn` is a state machine ARN,
+/// `arn:<partition>:states:<region>:<account>:stateMachine:<name>[:<version or alias>]`
+pub fn validate_state_machine_arn(arn: &str) -> Result<(), AwsError> {
+    let parts: Vec<&str> = arn.split(':').collect();
+    let valid = matches!(parts.len(), 7 | 8)
+        && parts[0] == "arn"
+        && parts[1].starts_with("aws")
+        && parts[2] == "states"
+        && !parts[3].is_empty()
+        && parts[4].len() == 12
+        && parts[4].chars().all(|c| c.is_ascii_digit())
+        && parts[5] == "stateMachine"
+        && parts[6..].iter().all(|part| !part.is_empty());
+    if valid {
+        Ok(())
+    } else {
+        Err(AwsError::InvalidWorkflow(format!(
+            "{:?} is not a state machine ARN",
+            arn
+        )))
+    }
+}
+
+/// Name for a new execution started by `session`: the tenant id, `--`, and a unique
+/// suffix. Step Functions executions can't be tagged, so the name is what records the
+/// owner (see [`execution_owner`]).
+pub fn execution_name(session: &TenantSession) -> Result<String, AwsError> {
+    let tenant_id = &session.context.tenant_id;
+    let max_tenant_len = MAX_EXECUTION_NAME_LEN - OWNER_SEPARATOR.len() - EXECUTION_ID_LEN;
+    if !is_name_safe(tenant_id) || tenant_id.len() > max_tenant_len {
+        return Err(AwsError::InvalidWorkflow(format!(
+            "tenant id {:?} can't be used in execution names (at most {} letters, digits, - or _)",
+            tenant_id, max_tenant_len
+        )));
+    }
+    Ok(format!(
+        "{}{}{}",
+        tenant_id,
+        OWNER_SEPARATOR,
+        uuid::Uuid::new_v4().simple()
+    ))
+}
+
+/// The tenant that started the execution `execution_arn`
+/// (`arn:<partition>:states:<region>:<account>:execution:<state machine>:<name>`), if it
+/// was named by [`execution_name`]
+pub fn execution_owner(execution_arn: &str) -> Option<&str> {
+    let parts: Vec<&str> = execution_arn.split(':').collect();
+    if parts.len() != 8 || parts[0] != "arn" || parts[2] != "states" || parts[5] != "execution" {
+        return None;
+    }
+    let (owner, id) = parts[7].rsplit_once(OWNER_SEPARATOR)?;
+    (id.len() == EXECUTION_ID_LEN && is_name_safe(owner)).then_some(owner)
+}
+
+/// Check that `execution_arn` is one of the session's tenant's executions. Anyone
+/// else's (or a malformed ARN) reads as not found, so other tenants' executions can't
+/// be probed for.
+pub fn check_execution_owner(session: &TenantSession, execution_arn: &str) -> Result<(), AwsError> {
+    if execution_owner(execution_arn) == Some(session.context.tenant_id.as_str()) {
+        Ok(())
+    } else {
+        Err(AwsError::NotFound(format!("execution {:?}", execution_arn)))
+    }
+}
+
+/// The ARN Step Functions gives the execution `name` of `state_machine_arn`, which must
+/// have passed [`validate_state_machine_arn`]
+pub fn execution_arn(state_machine_arn: &str, name: &str) -> String {
+    let mut parts: Vec<&str> = state_machine_arn.split(':').take(7).collect();
+    parts[5] = "execution";
+    parts.push(name);
+    parts.join(":")
+}
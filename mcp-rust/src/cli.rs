@@ -0,0 +1,144 @@
+use clap::{Parser, ValueEnum};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::aws::AwsService;
+use crate::config::{Backend, Config, ConfigError, Override};
+use crate::self_test;
+
+/// Crate version and the commit it was built from
+pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("MCP_GIT_HASH"), ")");
+
+/// Command-line flags. Each one that maps to a setting overrides its environment
+/// variable and the config file.
+#[derive(Parser, Debug, Clone, PartialEq, Eq)]
+#[command(name = "mcp-multi-tenant", version = VERSION, about = "Multi-tenant MCP server")]
+pub struct Cli {
+    /// How clients connect [env: MCP_TRANSPORT]
+    #[arg(long, value_enum)]
+    pub transport: Option<TransportArg>,
+
+    /// Address the HTTP or WebSocket transport listens on [env: MCP_HTTP_ADDR]
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<SocketAddr>,
+
+    /// Config file to read instead of $MCP_CONFIG or ./mcp.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Create the demo tenant; never in production [env: DEV_MODE]
+    #[arg(long)]
+    pub dev: bool,
+
+    /// Most verbose level logged [env: MCP_LOG_LEVEL]
+    #[arg(long, value_enum)]
+    pub log_level: Option<LogLevelArg>,
+
+    /// Storage backend [env: MCP_BACKEND]
+    #[arg(long, value_enum)]
+    pub backend: Option<BackendArg>,
+
+    /// AWS region [env: AWS_REGION]
+    #[arg(long)]
+    pub region: Option<String>,
+
+    /// Check the configuration and that the AWS resources are usable, then exit
+    #[arg(long)]
+    pub validate_config: bool,
+
+    /// Run the core tools once in process on the in-memory backend, print a report and
+    /// exit non-zero if any step failed
+    #[arg(long)]
+    pub self_test: bool,
+
+    /// Run the self-test against the configured AWS resources instead
+    #[arg(long)]
+    pub self_test_live: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportArg {
+    Stdio,
+    Http,
+    Websocket,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendArg {
+    Aws,
+    Memory,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevelArg {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// The name clap accepts for `value`, which is also the config file's spelling
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .expect("no skipped values")
+        .get_name()
+        .to_string()
+}
+
+impl Cli {
+    /// The settings the flags set
+    pub fn overrides(&self) -> Vec<Override> {
+        let mut overrides = Vec::new();
+        let mut set = |key, flag, value: Option<String>| {
+            if let Some(value) = value {
+                overrides.push(Override { key, value, flag });
+            }
+        };
+        set(
+            "server.transport",
+            "--transport",
+            self.transport.map(value_name),
+        );
+        set(
+            "server.http_addr",
+            "--listen",
+            self.listen.map(|addr| addr.to_string()),
+        );
+        set("dev_mode", "--dev", self.dev.then(|| "true".to_string()));
+        set("log_level", "--log-level", self.log_level.map(value_name));
+        set("aws.backend", "--backend", self.backend.map(value_name));
+        set("aws.region", "--region", self.region.clone());
+        overrides
+    }
+
+    /// The config the flags, environment and config file describe
+    pub fn load_config(&self) -> Result<Config, ConfigError> {
+        Config::load_with(self.config.as_deref(), &self.overrides())
+    }
+}
+
+/// `--validate-config`: the config has already loaded, so check the AWS resources it
+/// names (strictly, whatever `validate_resources` says). The in-memory backend has none.
+pub async fn validate(config: &Config) -> anyhow::Result<()> {
+    if config.aws.backend == Backend::Aws {
+        let mut aws = config.aws.clone();
+        aws.validate_resources = true;
+        aws.validate_resources_strict = true;
+        AwsService::from_config(&aws).await?;
+    }
+    println!("Configuration is valid");
+    Ok(())
+}
+
+/// `--self-test` and `--self-test-live`: print the report and whether every step passed
+pub async fn self_test(config: &Config, live: bool) -> anyhow::Result<bool> {
+    let mut config = self_test::self_test_config(config, live);
+    if live {
+        crate::aws::credentials::ensure_credentials(&mut config.aws).await?;
+    }
+    let report = self_test::run(config).await?;
+    println!("{}", report.render());
+    Ok(report.passed())
+}
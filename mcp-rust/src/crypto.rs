@@ -0,0 +1,311 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use async_trait::async_trait;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::DataKeySpec;
+use aws_sdk_kms::Client as KmsClient;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::aws::AwsError;
+
+/// Encryption context key binding a data key (and every value sealed with it) to a tenant
+const TENANT_CONTEXT_KEY: &str = "tenant_id";
+
+/// A per-tenant data key: the plaintext key used for AES-GCM and its KMS-wrapped form
+/// which is stored alongside every value sealed with it.
+pub struct DataKey {
+    pub plaintext: Vec<u8>,
+    pub wrapped: Vec<u8>,
+}
+
+/// Source of per-tenant data keys (KMS in production, a static master key in tests)
+#[async_trait]
+pub trait DataKeyProvider: Send + Sync {
+    /// Generate a fresh AES-256 data key bound to the tenant
+    async fn generate_data_key(&self, tenant_id: &str) -> Result<DataKey, AwsError>;
+
+    /// Unwrap a previously generated data key; fails if the key belongs to another tenant
+    async fn decrypt_data_key(&self, tenant_id: &str, wrapped: &[u8]) -> Result<Vec<u8>, AwsError>;
+}
+
+/// KMS-backed data key provider using `generate_data_key` with a tenant encryption context
+pub struct KmsDataKeyProvider {
+    client: KmsClient,
+    key_id: String,
+}
+
+impl KmsDataKeyProvider {
+    pub fn new(client: KmsClient, key_id: String) -> Self {
+        Self { client, key_id }
+    }
+}
+
+#[async_trait]
+impl DataKeyProvider for KmsDataKeyProvider {
+    async fn generate_data_key(&self, tenant_id: &str) -> Result<DataKey, AwsError> {
+        let output = self
+            .client
+            .generate_data_key()
+            .key_id(&self.key_id)
+            .key_spec(DataKeySpec::Aes256)
+            .encryption_context(TENANT_CONTEXT_KEY, tenant_id)
+            .send()
+            .await
+            .map_err(|e| AwsError::Kms(e.to_string()))?;
+
+        let plaintext = output
+            .plaintext()
+            .ok_or_else(|| AwsError::Kms("KMS returned no plaintext data key".to_string()))?;
+        let wrapped = output
+            .ciphertext_blob()
+            .ok_or_else(|| AwsError::Kms("KMS returned no wrapped data key".to_string()))?;
+
+        Ok(DataKey {
+            plaintext: plaintext.as_ref().to_vec(),
+            wrapped: wrapped.as_ref().to_vec(),
+        })
+    }
+
+    async fn decrypt_data_key(&self, tenant_id: &str, wrapped: &[u8]) -> Result<Vec<u8>, AwsError> {
+        let output = self
+            .client
+            .decrypt()
+            .key_id(&self.key_id)
+            .ciphertext_blob(Blob::new(wrapped.to_vec()))
+            .encryption_context(TENANT_CONTEXT_KEY, tenant_id)
+            .send()
+            .await
+            .map_err(|e| AwsError::Kms(e.to_string()))?;
+
+        output
+            .plaintext()
+            .map(|p| p.as_ref().to_vec())
+            .ok_or_else(|| AwsError::Kms("KMS returned no plaintext data key".to_string()))
+    }
+}
+
+/// Data key provider that wraps keys under a fixed master key, with the tenant id as
+/// associated data. Mirrors KMS encryption-context semantics for tests and local dev.
+pub struct StaticKeyProvider {
+    master_key: [u8; 32],
+}
+
+impl StaticKeyProvider {
+    #[allow(dead_code)]
+    pub fn new(master_key: [u8; 32]) -> Self {
+        Self { master_key }
+    }
+}
+
+#[async_trait]
+impl DataKeyProvider for StaticKeyProvider {
+    async fn generate_data_key(&self, tenant_id: &str) -> Result<DataKey, AwsError> {
+        let plaintext = Aes256Gcm::generate_key(OsRng).to_vec();
+        let sealed = seal_bytes(&self.master_key, tenant_id, &plaintext)?;
+
+        // Wrapped form is nonce || ciphertext
+        let mut wrapped = sealed.nonce;
+        wrapped.extend_from_slice(&sealed.ciphertext);
+
+        Ok(DataKey { plaintext, wrapped })
+    }
+
+    async fn decrypt_data_key(&self, tenant_id: &str, wrapped: &[u8]) -> Result<Vec<u8>, AwsError> {
+        if wrapped.len() < NONCE_LEN {
+            return Err(AwsError::Encryption(
+                "Wrapped data key is truncated".to_string(),
+            ));
+        }
+        let (nonce, ciphertext) = wrapped.split_at(NONCE_LEN);
+        open_bytes(&self.master_key, tenant_id, nonce, ciphertext)
+    }
+}
+
+const NONCE_LEN: usize = 12;
+
+/// An encrypted value as persisted in DynamoDB
+#[derive(Debug, Clone)]
+pub struct SealedValue {
+    pub ciphertext: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub wrapped_key: Vec<u8>,
+}
+
+/// Envelope encryption with one cached data key per tenant.
+///
+/// Values are sealed with AES-256-GCM using the tenant id as associated data, so a
+/// ciphertext copied into another tenant's namespace fails authentication even before
+/// the provider refuses to unwrap the data key under the wrong tenant context.
+pub struct EnvelopeEncryptor {
+    provider: Arc<dyn DataKeyProvider>,
+    data_keys: RwLock<HashMap<String, Arc<DataKey>>>,
+}
+
+impl EnvelopeEncryptor {
+    pub fn new(provider: Arc<dyn DataKeyProvider>) -> Self {
+        Self {
+            provider,
+            data_keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    async fn data_key_for(&self, tenant_id: &str) -> Result<Arc<DataKey>, AwsError> {
+        if let Some(key) = self.data_keys.read().await.get(tenant_id) {
+            return Ok(key.clone());
+        }
+
+        let mut keys = self.data_keys.write().await;
+        // Another task may have generated the key while we waited for the write lock
+        if let Some(key) = keys.get(tenant_id) {
+            return Ok(key.clone());
+        }
+
+        let key = Arc::new(self.provider.generate_data_key(tenant_id).await?);
+        keys.insert(tenant_id.to_string(), key.clone());
+        Ok(key)
+    }
+
+    /// Encrypt a value for a tenant
+    pub async fn seal(&self, tenant_id: &str, plaintext: &[u8]) -> Result<SealedValue, AwsError> {
+        let data_key = self.data_key_for(tenant_id).await?;
+        let mut sealed = seal_bytes(&data_key.plaintext, tenant_id, plaintext)?;
+        sealed.wrapped_key = data_key.wrapped.clone();
+        Ok(sealed)
+    }
+
+    /// Decrypt a value previously sealed for the same tenant
+    pub async fn open(&self, tenant_id: &str, sealed: &SealedValue) -> Result<Vec<u8>, AwsError> {
+        let cached = self
+            .data_keys
+            .read()
+            .await
+            .get(tenant_id)
+            .filter(|key| key.wrapped == sealed.wrapped_key)
+            .map(|key| key.plaintext.clone());
+
+        let key = match cached {
+            Some(key) => key,
+            None => {
+                self.provider
+                    .decrypt_data_key(tenant_id, &sealed.wrapped_key)
+                    .await?
+            }
+        };
+
+        open_bytes(&key, tenant_id, &sealed.nonce, &sealed.ciphertext)
+    }
+}
+
+fn cipher_for(key: &[u8]) -> Result<Aes256Gcm, AwsError> {
+    Aes256Gcm::new_from_slice(key).map_err(|_| {
+        AwsError::Encryption(format!(
+            "Expected a 256-bit data key, got {} bytes",
+            key.len()
+        ))
+    })
+}
+
+fn seal_bytes(key: &[u8], tenant_id: &str, plaintext: &[u8]) -> Result<SealedValue, AwsError> {
+    let cipher = cipher_for(key)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: tenant_id.as_bytes(),
+            },
+        )
+        .map_err(|_| AwsError::Encryption("Failed to encrypt value".to_string()))?;
+
+    Ok(SealedValue {
+        ciphertext,
+        nonce: nonce.to_vec(),
+        wrapped_key: Vec::new(),
+    })
+}
+
+fn open_bytes(
+    key: &[u8],
+    tenant_id: &str,
+    nonce: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, AwsError> {
+    let nonce: [u8; NONCE_LEN] = nonce
+        .try_into()
+        .map_err(|_| AwsError::Encryption("Invalid nonce length".to_string()))?;
+    let cipher = cipher_for(key)?;
+    cipher
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: tenant_id.as_bytes(),
+            },
+        )
+        .map_err(|_| {
+            AwsError::Encryption(format!("Failed to decrypt value for tenant {}", tenant_id))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_encryptor() -> EnvelopeEncryptor {
+        EnvelopeEncryptor::new(Arc::new(StaticKeyProvider::new([7u8; 32])))
+    }
+
+    #[tokio::test]
+    async fn test_seal_open_round_trip() {
+        let encryptor = test_encryptor();
+
+        let sealed = encryptor
+            .seal("tenant-a", b"oauth-refresh-token")
+            .await
+            .unwrap();
+        assert_ne!(sealed.ciphertext, b"oauth-refresh-token".to_vec());
+        assert!(!sealed.wrapped_key.is_empty());
+
+        let opened = encryptor.open("tenant-a", &sealed).await.unwrap();
+        assert_eq!(opened, b"oauth-refresh-token".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_open_without_cached_key_unwraps_via_provider() {
+        let provider: Arc<dyn DataKeyProvider> = Arc::new(StaticKeyProvider::new([7u8; 32]));
+        let writer = EnvelopeEncryptor::new(provider.clone());
+        let sealed = writer.seal("tenant-a", b"secret").await.unwrap();
+
+        // A fresh encryptor (e.g. after restart) has no cached data key
+        let reader = EnvelopeEncryptor::new(provider);
+        assert_eq!(reader.open("tenant-a", &sealed).await.unwrap(), b"secret");
+    }
+
+    #[tokio::test]
+    async fn test_tenant_b_cannot_decrypt_tenant_a_ciphertext() {
+        let encryptor = test_encryptor();
+
+        let sealed = encryptor.seal("tenant-a", b"secret").await.unwrap();
+        // Give tenant B its own cached key as well
+        encryptor.seal("tenant-b", b"other").await.unwrap();
+
+        let result = encryptor.open("tenant-b", &sealed).await;
+        assert!(matches!(result, Err(AwsError::Encryption(_))));
+    }
+
+    #[tokio::test]
+    async fn test_data_key_is_cached_per_tenant() {
+        let encryptor = test_encryptor();
+
+        let first = encryptor.seal("tenant-a", b"one").await.unwrap();
+        let second = encryptor.seal("tenant-a", b"two").await.unwrap();
+        let other = encryptor.seal("tenant-b", b"three").await.unwrap();
+
+        assert_eq!(first.wrapped_key, second.wrapped_key);
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.wrapped_key, other.wrapped_key);
+    }
+}
@@ -3,27 +3,123 @@ use base64::{engine::general_purpose, Engine as _};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tracing::debug;
-
-use crate::aws::{AwsError, AwsService};
-use crate::registry::MCPServerRegistry;
-use crate::tenant::{Permission, TenantSession};
+use tracing::field::Empty;
+use tracing::{debug, info, info_span, warn, Instrument};
+
+use crate::aws::artifacts::TtlClass;
+use crate::aws::transactions::KvWriteOp;
+use crate::aws::{self, internal_keys, AwsApi, AwsError};
+use crate::config::{Backend, Config, RegistryConfig};
+use crate::handlers::arguments::ArgumentSchemas;
+use crate::handlers::concurrency::{ToolConcurrencyLimiter, ToolConcurrencyPolicy};
+use crate::handlers::content::HandlerOutput;
+use crate::handlers::dry_run::DryRun;
+use crate::handlers::idempotency::{
+    IdempotencyRecord, IdempotencyStore, KvIdempotencyStore, MemoryIdempotencyStore,
+    IDEMPOTENCY_TTL,
+};
+use crate::handlers::integrations::catalog::IntegrationCatalog;
+use crate::handlers::jobs::{JobManager, JobOptions};
+use crate::handlers::middleware::{HandlerMiddleware, MiddlewareChain, ToolCall};
+use crate::handlers::output_limit::OutputLimit;
+use crate::handlers::response_cache::{
+    artifacts_list_invalidation, CacheInvalidation, ResponseCache,
+};
+use crate::handlers::schema_versions::{Deprecation, ToolVersion};
+use crate::registry::{MCPServerRegistry, HEALTH_CHECK_TICK};
+use crate::tenant::{glob_match, Permission, TenantSession, UserRole};
+
+/// Set to `true` to list connected servers' tools in `tools/list` as `server_id.tool`
+pub const MERGE_DOWNSTREAM_TOOLS_ENV: &str = "AGENT_MESH_MERGE_DOWNSTREAM_TOOLS";
+
+/// Name of the span each tool call is traced under, inside the request's span
+pub const TOOL_CALL_SPAN: &str = "tool_call";
+
+/// Comma-separated tool names or `*` globs the operator has switched off, e.g.
+/// `integration_*,mcp_proxy`
+pub const DISABLED_TOOLS_ENV: &str = "MCP_DISABLED_TOOLS";
 
 // Re-export handler modules
+pub mod arguments;
+pub mod artifact_deletion;
+pub mod concurrency;
+pub mod content;
+pub mod diagnostics;
+pub mod dry_run;
+pub mod events_export;
+pub mod events_linked;
+pub mod health;
+pub mod idempotency;
 pub mod integrations;
+pub mod jobs;
+pub mod lifecycle;
 pub mod mcp_proxy;
+pub mod middleware;
+pub mod namespaces;
+pub mod output_limit;
+pub mod permissions;
+pub mod queues;
+pub mod rate_limits;
+pub mod response_cache;
+pub mod schema_versions;
+pub mod secrets;
+pub mod server_stats;
+pub mod tasks;
+pub mod tenant_data;
+pub mod usage;
+pub mod usage_thresholds;
+pub mod workflows;
 
 #[derive(Error, Debug)]
 pub enum HandlerError {
     #[error("Permission denied: required {0:?}")]
     PermissionDenied(Permission),
+    #[error("Permission denied: tool '{0}' is not permitted by tenant policy")]
+    ToolBlocked(String),
     #[error("Invalid arguments: {0}")]
     InvalidArguments(String),
+    /// Arguments that don't match the tool's input schema, caught before it ran
+    #[error("Invalid arguments for {tool} at {pointer}: {message}")]
+    SchemaViolation {
+        tool: String,
+        /// JSON pointer into the arguments, e.g. `/limit`
+        pointer: String,
+        /// The JSON type the schema wants there, when it says
+        expected: Option<String>,
+        message: String,
+    },
     #[error("AWS error: {0}")]
     Aws(#[from] AwsError),
     #[error("Handler not found: {0}")]
     NotFound(String),
+    /// A replayed idempotency key whose arguments differ from the original call's
+    #[error("Idempotency key '{key}' was already used for {tool} with different arguments")]
+    IdempotencyConflict { tool: String, key: String },
+    /// A call with the same idempotency key is still running
+    #[error("A call to {tool} with idempotency key '{key}' is still in progress")]
+    IdempotencyInProgress { tool: String, key: String },
+    /// A tool the operator switched off for this server
+    #[error("Tool '{0}' is disabled by the server administrator")]
+    ToolDisabled(String),
+    #[error("Tool '{tool}' is busy: {in_flight}/{max_concurrency} calls in flight")]
+    ToolBusy {
+        tool: String,
+        in_flight: usize,
+        max_concurrency: usize,
+    },
+    #[error("Tool '{tool}' timed out after {}ms", elapsed.as_millis())]
+    Timeout { tool: String, elapsed: Duration },
+    /// A result over the registry's output limit that couldn't be truncated to fit
+    #[error("Result of {tool} is {size} bytes, over the {max_bytes} byte limit")]
+    ResultTooLarge {
+        tool: String,
+        size: usize,
+        max_bytes: usize,
+        /// The arguments that ask the tool for less
+        hint: Option<String>,
+    },
     #[error("Internal handler error: {0}")]
     Internal(String),
 }
@@ -37,17 +133,186 @@ pub trait Handler: Send + Sync {
     ) -> Result<Value, HandlerError>;
     fn required_permission(&self) -> Option<Permission>;
     fn tool_schema(&self) -> Value;
+
+    /// The result as the registry sends it: [`Handler::handle`]'s JSON unless the tool
+    /// overrides this to return text or binary content blocks (see [`content`])
+    async fn handle_output(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<HandlerOutput, HandlerError> {
+        self.handle(session, arguments)
+            .await
+            .map(HandlerOutput::from)
+    }
+
+    /// Cap on concurrent calls of this tool per tenant, for expensive tools
+    fn max_concurrency(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether the tool takes `dry_run: true` and then only reports what it would
+    /// change (see [`dry_run`])
+    fn supports_dry_run(&self) -> bool {
+        false
+    }
+
+    /// Whether calls may carry an `idempotency_key`, so a retried call replays the
+    /// first one's response instead of running again
+    fn accepts_idempotency_key(&self) -> bool {
+        false
+    }
+
+    /// JSON pointers of arrays in the result, e.g. `/events`, that may be cut short
+    /// when the result is over the output limit (see [`output_limit`])
+    fn truncatable_fields(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// The arguments that ask the tool for less, named when its result is too large,
+    /// e.g. "`limit` and `exclusiveStartKey`"
+    fn paging_hint(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// How long the response cache, when enabled, may answer a repeated call by the
+    /// same caller with the same arguments; only for tools without side effects
+    fn cache_ttl(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Cached responses of other tools that a successful call with `arguments` makes
+    /// stale, e.g. `artifacts_list` results covering the key `artifacts_put` wrote
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        Vec::new()
+    }
+
+    /// Version of the tool's argument shape, and whether the tool is deprecated, as
+    /// listed (see [`schema_versions`])
+    fn tool_version(&self) -> ToolVersion {
+        ToolVersion::default()
+    }
+
+    /// Why a call with `arguments` uses something deprecated, if it does; by default
+    /// the tool's own deprecation. The caller is warned in the result's `_meta`.
+    fn deprecation(&self, _arguments: &Value) -> Option<Deprecation> {
+        self.tool_version().deprecation
+    }
+}
+
+/// How a [`HandlerRegistry`] is set up, besides the backend it runs on
+#[derive(Clone)]
+pub struct RegistryOptions {
+    /// List connected servers' tools in `list_tools` as `server_id.tool`
+    pub merge_downstream_tools: bool,
+    /// What happens to calls over a tool's `max_concurrency`
+    pub tool_concurrency_policy: ToolConcurrencyPolicy,
+    /// Runs around every tool call
+    pub middleware: MiddlewareChain,
+    /// Check connected servers' health in the background
+    pub health_monitor: bool,
+    /// Cap on the serialized size of tool results
+    pub output_limit: OutputLimit,
+    /// Tool names or `*` globs that are neither listed nor callable
+    pub disabled_tools: Vec<String>,
+    /// Where responses are kept for idempotent replays; `None` keeps them in the
+    /// backend's KV table
+    pub idempotency_store: Option<Arc<dyn IdempotencyStore>>,
+    /// Settings of the downstream server registry
+    pub server_registry: RegistryConfig,
+    /// Answers repeated calls of tools with a `cache_ttl`; `None` leaves caching off
+    pub response_cache: Option<Arc<ResponseCache>>,
+    /// How background jobs such as `events_export` run
+    pub jobs: JobOptions,
+}
+
+impl Default for RegistryOptions {
+    fn default() -> Self {
+        Self {
+            merge_downstream_tools: false,
+            tool_concurrency_policy: ToolConcurrencyPolicy::default(),
+            middleware: MiddlewareChain::default(),
+            health_monitor: true,
+            output_limit: OutputLimit::default(),
+            disabled_tools: Vec::new(),
+            idempotency_store: None,
+            server_registry: RegistryConfig::default(),
+            response_cache: None,
+            jobs: JobOptions::default(),
+        }
+    }
+}
+
+impl RegistryOptions {
+    /// The defaults, with the server's, the server registry's and the response cache's
+    /// settings from `config`. The in-memory backend keeps idempotency records in
+    /// process too.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            merge_downstream_tools: config.server.merge_downstream_tools,
+            tool_concurrency_policy: config.server.tool_concurrency_policy,
+            output_limit: config.server.output_limit,
+            disabled_tools: config.server.disabled_tools.clone(),
+            idempotency_store: (config.aws.backend == Backend::Memory)
+                .then(|| Arc::new(MemoryIdempotencyStore::default()) as Arc<dyn IdempotencyStore>),
+            server_registry: config.registry.clone(),
+            response_cache: config.server.response_cache().map(Arc::new),
+            jobs: JobOptions {
+                allow_loopback_webhooks: config.server.allow_loopback_webhooks,
+                ..JobOptions::default()
+            },
+            ..Self::default()
+        }
+    }
 }
 
 pub struct HandlerRegistry {
     handlers: HashMap<String, Arc<dyn Handler>>,
-    _registry: Arc<MCPServerRegistry>,
+    /// Every handler's compiled input schema
+    argument_schemas: ArgumentSchemas,
+    registry: Arc<MCPServerRegistry>,
+    aws_service: Arc<dyn AwsApi>,
+    /// Templates `integration_register` can start from
+    catalog: Arc<IntegrationCatalog>,
+    tool_concurrency: Arc<ToolConcurrencyLimiter>,
+    /// Runs around every tool call, permission checks included
+    middleware: MiddlewareChain,
+    output_limit: OutputLimit,
+    /// Patterns of the tools switched off for this server
+    disabled_tools: Vec<String>,
+    idempotency: Arc<dyn IdempotencyStore>,
+    response_cache: Option<Arc<ResponseCache>>,
+    /// Background jobs started by tools such as `events_export`
+    jobs: Arc<JobManager>,
+    /// Whether downstream tools appear in `list_tools`, always as `server_id.tool` so
+    /// they can't shadow a built-in
+    merge_downstream_tools: bool,
 }
 
 impl HandlerRegistry {
+    /// Handlers for the config [`Config::load`] finds
+    #[allow(dead_code)]
     pub async fn new() -> anyhow::Result<Self> {
-        let aws_service = Arc::new(AwsService::new("us-west-2").await?);
-        let registry = Arc::new(MCPServerRegistry::new(aws_service.clone()));
+        Self::from_config(&Config::load()?).await
+    }
+
+    /// Handlers on the configured backend (AWS unless set to `memory`), with options from
+    /// `config` and the environment
+    pub async fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let backend = aws::backend_from_config(&config.aws).await?;
+        Ok(Self::with_backend(backend, RegistryOptions::from_config(config)).await)
+    }
+
+    /// Handlers storing and publishing through `aws_service`, e.g. a
+    /// [`MemoryAwsService`](crate::aws::memory::MemoryAwsService) when embedding or testing
+    pub async fn with_backend(aws_service: Arc<dyn AwsApi>, options: RegistryOptions) -> Self {
+        let registry = Arc::new(MCPServerRegistry::with_config(
+            aws_service.clone(),
+            &options.server_registry,
+        ));
+        if options.health_monitor {
+            registry.spawn_health_monitor(HEALTH_CHECK_TICK);
+        }
         let mut handlers: HashMap<String, Arc<dyn Handler>> = HashMap::new();
 
         // Register KV handlers
@@ -59,6 +324,10 @@ impl HandlerRegistry {
             "kv_set".to_string(),
             Arc::new(KvSetHandler::new(aws_service.clone())),
         );
+        handlers.insert(
+            "kv_delete".to_string(),
+            Arc::new(KvDeleteHandler::new(aws_service.clone())),
+        );
 
         // Register artifacts handlers
         handlers.insert(
@@ -69,16 +338,44 @@ impl HandlerRegistry {
             "artifacts_put".to_string(),
             Arc::new(ArtifactsPutHandler::new(aws_service.clone())),
         );
+        handlers.insert(
+            "artifacts_head".to_string(),
+            Arc::new(ArtifactsHeadHandler::new(aws_service.clone())),
+        );
         handlers.insert(
             "artifacts_list".to_string(),
             Arc::new(ArtifactsListHandler::new(aws_service.clone())),
         );
+        handlers.insert(
+            "artifacts_delete_prefix".to_string(),
+            Arc::new(artifact_deletion::ArtifactsDeletePrefixHandler::new(
+                aws_service.clone(),
+            )),
+        );
+        handlers.insert(
+            "artifacts_configure_lifecycle".to_string(),
+            Arc::new(lifecycle::ArtifactsConfigureLifecycleHandler::new(
+                aws_service.clone(),
+            )),
+        );
+        handlers.insert(
+            "namespace_migrate".to_string(),
+            Arc::new(namespaces::NamespaceMigrateHandler::new(
+                aws_service.clone(),
+            )),
+        );
 
         // Register event handlers
         handlers.insert(
             "events_send".to_string(),
             Arc::new(EventsSendHandler::new(aws_service.clone())),
         );
+        handlers.insert(
+            "events_send_linked".to_string(),
+            Arc::new(events_linked::EventsSendLinkedHandler::new(
+                aws_service.clone(),
+            )),
+        );
         handlers.insert(
             "events_query".to_string(),
             Arc::new(EventsQueryHandler::new(aws_service.clone())),
@@ -87,6 +384,10 @@ impl HandlerRegistry {
             "events_analytics".to_string(),
             Arc::new(EventsAnalyticsHandler::new(aws_service.clone())),
         );
+        handlers.insert(
+            "tool_usage_report".to_string(),
+            Arc::new(usage::ToolUsageReportHandler::new(aws_service.clone())),
+        );
         handlers.insert(
             "events_create_rule".to_string(),
             Arc::new(EventsCreateRuleHandler::new(aws_service.clone())),
@@ -100,60 +401,269 @@ impl HandlerRegistry {
             Arc::new(EventsHealthCheckHandler::new(aws_service.clone())),
         );
 
-        // Register integration management handlers
+        // Register secret handlers
         handlers.insert(
-            "integration_register".to_string(),
-            Arc::new(integrations::IntegrationRegisterHandler::new(
-                aws_service.clone(),
-                registry.clone(),
-            )),
+            "secret_get".to_string(),
+            Arc::new(secrets::SecretGetHandler::new(aws_service.clone())),
         );
         handlers.insert(
-            "integration_connect".to_string(),
-            Arc::new(integrations::IntegrationConnectHandler::new(
-                aws_service.clone(),
-                registry.clone(),
-            )),
+            "secret_put".to_string(),
+            Arc::new(secrets::SecretPutHandler::new(aws_service.clone())),
         );
         handlers.insert(
-            "integration_list".to_string(),
-            Arc::new(integrations::IntegrationListHandler::new(
-                aws_service.clone(),
-                registry.clone(),
-            )),
+            "secret_delete".to_string(),
+            Arc::new(secrets::SecretDeleteHandler::new(aws_service.clone())),
         );
+
+        // Register workflow handlers
         handlers.insert(
-            "integration_disconnect".to_string(),
-            Arc::new(integrations::IntegrationDisconnectHandler::new(
-                aws_service.clone(),
-                registry.clone(),
-            )),
+            "workflow_start".to_string(),
+            Arc::new(workflows::WorkflowStartHandler::new(aws_service.clone())),
+        );
+        handlers.insert(
+            "workflow_status".to_string(),
+            Arc::new(workflows::WorkflowStatusHandler::new(aws_service.clone())),
         );
         handlers.insert(
-            "integration_test".to_string(),
-            Arc::new(integrations::IntegrationTestHandler::new(registry.clone())),
+            "workflow_stop".to_string(),
+            Arc::new(workflows::WorkflowStopHandler::new(aws_service.clone())),
         );
 
-        // Register MCP proxy handlers
+        // Register queue handlers
         handlers.insert(
-            "mcp_proxy".to_string(),
-            Arc::new(mcp_proxy::MCPProxyHandler::new(registry.clone())),
+            "queue_send".to_string(),
+            Arc::new(queues::QueueSendHandler::new(aws_service.clone())),
+        );
+        handlers.insert(
+            "queue_receive".to_string(),
+            Arc::new(queues::QueueReceiveHandler::new(aws_service.clone())),
+        );
+        handlers.insert(
+            "queue_ack".to_string(),
+            Arc::new(queues::QueueAckHandler::new(aws_service.clone())),
+        );
+        handlers.insert(
+            "queue_nack".to_string(),
+            Arc::new(queues::QueueNackHandler::new(aws_service.clone())),
+        );
+
+        // Register task queue handlers, kept in the tenant's KV namespace
+        let task_queues = tasks::TaskQueues::new(aws_service.clone());
+        handlers.insert(
+            "task_create".to_string(),
+            Arc::new(tasks::TaskCreateHandler::new(task_queues.clone())),
+        );
+        handlers.insert(
+            "task_claim".to_string(),
+            Arc::new(tasks::TaskClaimHandler::new(task_queues.clone())),
+        );
+        handlers.insert(
+            "task_complete".to_string(),
+            Arc::new(tasks::TaskCompleteHandler::new(task_queues.clone())),
+        );
+        handlers.insert(
+            "task_fail".to_string(),
+            Arc::new(tasks::TaskFailHandler::new(task_queues.clone())),
+        );
+        handlers.insert(
+            "task_list".to_string(),
+            Arc::new(tasks::TaskListHandler::new(task_queues)),
+        );
+
+        // Register the background job handlers, and the tools that run as jobs
+        let jobs = Arc::new(JobManager::new(aws_service.clone(), options.jobs));
+        handlers.insert(
+            "jobs_status".to_string(),
+            Arc::new(jobs::JobsStatusHandler::new(jobs.clone())),
+        );
+        handlers.insert(
+            "jobs_list".to_string(),
+            Arc::new(jobs::JobsListHandler::new(jobs.clone())),
+        );
+        handlers.insert(
+            "jobs_cancel".to_string(),
+            Arc::new(jobs::JobsCancelHandler::new(jobs.clone())),
+        );
+        handlers.insert(
+            "events_export".to_string(),
+            Arc::new(events_export::EventsExportHandler::new(
+                aws_service.clone(),
+                jobs.clone(),
+            )),
+        );
+
+        // Register the integration catalog handler
+        let catalog = Arc::new(
+            IntegrationCatalog::load(
+                aws_service.as_ref(),
+                options.server_registry.integration_catalog.as_deref(),
+            )
+            .await,
         );
         handlers.insert(
-            "mcp_list_tools".to_string(),
-            Arc::new(mcp_proxy::MCPListToolsHandler::new(registry.clone())),
+            "integration_catalog_list".to_string(),
+            Arc::new(integrations::IntegrationCatalogListHandler::new(
+                catalog.clone(),
+            )),
         );
 
-        Ok(Self {
+        insert_registry_handlers(&mut handlers, &aws_service, &registry, &catalog, &jobs);
+
+        let idempotency = options
+            .idempotency_store
+            .unwrap_or_else(|| Arc::new(KvIdempotencyStore::new(aws_service.clone())));
+        let handler_registry = Self {
+            argument_schemas: compile_argument_schemas(&handlers),
             handlers,
-            _registry: registry,
-        })
+            registry,
+            aws_service,
+            catalog,
+            tool_concurrency: Arc::new(ToolConcurrencyLimiter::new(
+                options.tool_concurrency_policy,
+            )),
+            middleware: options.middleware,
+            output_limit: options.output_limit,
+            disabled_tools: options.disabled_tools,
+            idempotency,
+            response_cache: options.response_cache,
+            jobs,
+            merge_downstream_tools: options.merge_downstream_tools,
+        };
+        handler_registry.log_disabled_tools();
+        handler_registry
+    }
+
+    fn log_disabled_tools(&self) {
+        if self.disabled_tools.is_empty() {
+            return;
+        }
+        for pattern in &self.disabled_tools {
+            if !self.handlers.keys().any(|name| glob_match(pattern, name)) {
+                warn!(
+                    "{} pattern {:?} matches no tool",
+                    DISABLED_TOOLS_ENV, pattern
+                );
+            }
+        }
+        info!("Disabled tools: {}", self.disabled_tools().join(", "));
+    }
+
+    /// The registered tools switched off by the disabled-tools patterns, sorted
+    pub fn disabled_tools(&self) -> Vec<String> {
+        let mut disabled: Vec<String> = self
+            .handlers
+            .keys()
+            .filter(|name| self.is_disabled(name))
+            .cloned()
+            .collect();
+        disabled.sort();
+        disabled
+    }
+
+    fn is_disabled(&self, tool_name: &str) -> bool {
+        self.disabled_tools
+            .iter()
+            .any(|pattern| glob_match(pattern, tool_name))
+    }
+
+    /// Enable or disable merging downstream tools into `list_tools`
+    #[allow(dead_code)]
+    pub fn with_merged_downstream_tools(mut self, enabled: bool) -> Self {
+        self.merge_downstream_tools = enabled;
+        self
+    }
+
+    /// Serve the integration and proxy tools from `registry` instead of the default
+    /// DynamoDB-backed one
+    #[allow(dead_code)]
+    pub fn with_mcp_registry(mut self, registry: Arc<MCPServerRegistry>) -> Self {
+        insert_registry_handlers(
+            &mut self.handlers,
+            &self.aws_service,
+            &registry,
+            &self.catalog,
+            &self.jobs,
+        );
+        self.argument_schemas = compile_argument_schemas(&self.handlers);
+        self.registry = registry;
+        self
+    }
+
+    /// Replace how calls over a tool's `max_concurrency` are handled
+    #[allow(dead_code)]
+    pub fn with_tool_concurrency_policy(mut self, policy: ToolConcurrencyPolicy) -> Self {
+        self.tool_concurrency = Arc::new(ToolConcurrencyLimiter::new(policy));
+        self
+    }
+
+    /// Replace the middleware chain, in the order given (see [`MiddlewareChain`]). The
+    /// default chain is audit logging followed by permission checks.
+    #[allow(dead_code)]
+    pub fn with_middlewares(mut self, middlewares: Vec<Arc<dyn HandlerMiddleware>>) -> Self {
+        self.middleware = MiddlewareChain::new(middlewares);
+        self
+    }
+
+    /// Run `middleware` after the ones already in the chain
+    #[allow(dead_code)]
+    pub fn with_middleware(mut self, middleware: Arc<dyn HandlerMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Run `middleware` before the ones already in the chain
+    pub fn with_outermost_middleware(mut self, middleware: Arc<dyn HandlerMiddleware>) -> Self {
+        self.middleware.wrap(middleware);
+        self
+    }
+
+    /// Names of the middlewares, in the order they run
+    #[allow(dead_code)]
+    pub fn middleware_names(&self) -> Vec<&'static str> {
+        self.middleware.names()
+    }
+
+    /// Per-tool concurrency state, for diagnostics
+    pub fn tool_concurrency(&self) -> Arc<ToolConcurrencyLimiter> {
+        self.tool_concurrency.clone()
+    }
+
+    /// Downstream MCP server registry, for diagnostics
+    pub fn mcp_registry(&self) -> Arc<MCPServerRegistry> {
+        self.registry.clone()
+    }
+
+    /// Backend shared by the built-in handlers
+    pub fn aws_service(&self) -> Arc<dyn AwsApi> {
+        self.aws_service.clone()
+    }
+
+    /// Background jobs, stopped at shutdown
+    pub fn jobs(&self) -> Arc<JobManager> {
+        self.jobs.clone()
+    }
+
+    /// Tool response cache, if enabled, for diagnostics
+    pub fn response_cache(&self) -> Option<Arc<ResponseCache>> {
+        self.response_cache.clone()
+    }
+
+    /// Register an additional tool handler, e.g. one that needs server-level state
+    /// such as the tenant manager. Replaces any existing handler with the same name.
+    pub fn register(&mut self, name: &str, handler: Arc<dyn Handler>) {
+        self.argument_schemas.insert(name, handler.as_ref());
+        self.handlers.insert(name.to_string(), handler);
     }
 
     pub async fn list_tools(&self, session: &TenantSession) -> Result<Vec<Value>, HandlerError> {
         let mut tools = Vec::new();
 
         for (name, handler) in &self.handlers {
+            // Hide tools excluded by the operator or by the tenant's allow/deny lists
+            if self.is_disabled(name) || !session.context.tool_policy.allows(name) {
+                continue;
+            }
+
             // Check if user has permission for this tool
             if let Some(required_perm) = handler.required_permission() {
                 if !session.has_permission(&required_perm) {
@@ -161,7 +671,8 @@ impl HandlerRegistry {
                 }
             }
 
-            let mut tool_schema = handler.tool_schema();
+            let mut tool_schema = dry_run::tool_schema(handler.as_ref());
+            schema_versions::annotate(&mut tool_schema, &handler.tool_version());
             if let Value::Object(ref mut tool_obj) = tool_schema {
                 tool_obj.insert("name".to_string(), Value::String(name.clone()));
             }
@@ -169,42 +680,392 @@ impl HandlerRegistry {
             tools.push(tool_schema);
         }
 
+        if self.merge_downstream_tools && self.proxy_allowed(session) {
+            let downstream = mcp_proxy::downstream_tool_schemas(
+                &self.registry,
+                &session.context.get_context_id(),
+            )
+            .await
+            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+            tools.extend(downstream);
+        }
+
         Ok(tools)
     }
 
+    /// Whether the session may call downstream tools through `mcp_proxy`
+    fn proxy_allowed(&self, session: &TenantSession) -> bool {
+        let Some(proxy) = self.handlers.get("mcp_proxy") else {
+            return false;
+        };
+        if self.is_disabled("mcp_proxy") {
+            return false;
+        }
+        session.context.tool_policy.allows("mcp_proxy")
+            && proxy
+                .required_permission()
+                .is_none_or(|permission| session.has_permission(&permission))
+    }
+
+    #[allow(dead_code)]
     pub async fn handle_tool_call(
         &self,
         session: &TenantSession,
         tool_name: &str,
         arguments: Value,
     ) -> Result<Value, HandlerError> {
+        self.handle_tool_call_with_key(session, tool_name, arguments, None)
+            .await
+    }
+
+    /// [`handle_tool_call`](Self::handle_tool_call) with the call's `idempotency_key`:
+    /// a repeated call with the same key and arguments gets the first successful
+    /// call's response without the tool running again
+    pub async fn handle_tool_call_with_key(
+        &self,
+        session: &TenantSession,
+        tool_name: &str,
+        arguments: Value,
+        idempotency_key: Option<&str>,
+    ) -> Result<Value, HandlerError> {
+        if self.is_disabled(tool_name) {
+            return Err(HandlerError::ToolDisabled(tool_name.to_string()));
+        }
+        // Built-ins have bare names and server ids can't contain dots, so a dotted name
+        // is always a downstream tool, called through the proxy under its policy
+        let (tool_name, arguments) = if tool_name.contains('.') {
+            (
+                "mcp_proxy",
+                json!({"tool_name": tool_name, "arguments": arguments}),
+            )
+        } else {
+            (tool_name, arguments)
+        };
+
+        if self.is_disabled(tool_name) {
+            return Err(HandlerError::ToolDisabled(tool_name.to_string()));
+        }
         let handler = self
             .handlers
             .get(tool_name)
             .ok_or_else(|| HandlerError::NotFound(tool_name.to_string()))?;
 
-        // Check permissions
-        if let Some(required_perm) = handler.required_permission() {
-            if !session.has_permission(&required_perm) {
-                return Err(HandlerError::PermissionDenied(required_perm));
+        let span = info_span!(
+            TOOL_CALL_SPAN,
+            tool = tool_name,
+            duration_ms = Empty,
+            outcome = Empty,
+        );
+        let mut call = ToolCall {
+            session,
+            tool: tool_name,
+            arguments,
+            required_permission: handler.required_permission(),
+            idempotency_key,
+        };
+        let started = Instant::now();
+        let result = async {
+            let (entered, rejected) = self.middleware.before(&mut call).await;
+            let result = match rejected {
+                Some(error) => Err(error),
+                None => self.execute(handler.as_ref(), &call).await,
+            };
+            self.middleware
+                .after(entered, &call, &result, started.elapsed())
+                .await;
+            result
+        }
+        .instrument(span.clone())
+        .await;
+        span.record("duration_ms", started.elapsed().as_millis() as u64)
+            .record("outcome", if result.is_ok() { "ok" } else { "error" });
+        result
+    }
+
+    /// Validate the arguments the middlewares let through and run the handler
+    async fn execute(
+        &self,
+        handler: &dyn Handler,
+        call: &ToolCall<'_>,
+    ) -> Result<Value, HandlerError> {
+        let (session, tool_name) = (call.session, call.tool);
+        let mut arguments = call.arguments.clone();
+        let dry_run = dry_run::prepare(
+            tool_name,
+            handler,
+            &session.context.tool_policy,
+            &mut arguments,
+        )?;
+        self.argument_schemas.validate(tool_name, &arguments)?;
+
+        let cache = self
+            .response_cache
+            .as_ref()
+            .zip(handler.cache_ttl())
+            .filter(|_| dry_run == DryRun::Off);
+        let cache_ticket = match cache {
+            Some((cache, _)) => match cache.get(session, tool_name, &arguments) {
+                Some((mut response, age)) => {
+                    response_cache::mark_cached(&mut response, age);
+                    return Ok(response);
+                }
+                None => Some(cache.ticket(session)),
+            },
+            None => None,
+        };
+
+        let keep_for_replay = match call.idempotency_key {
+            Some(key) => match self.idempotent_replay(handler, call, key).await? {
+                Replay::Stored(response) => return Ok(response),
+                Replay::FirstCall {
+                    store_key,
+                    arguments_hash,
+                } => Some((store_key, arguments_hash)),
+            },
+            None => None,
+        };
+
+        let result = async {
+            // Held until the handler finishes
+            let _permit = match handler.max_concurrency() {
+                Some(max_concurrency) => Some(
+                    self.tool_concurrency
+                        .acquire(&session.context.tenant_id, tool_name, max_concurrency)
+                        .await?,
+                ),
+                None => None,
+            };
+
+            debug!(
+                "Executing tool {} for tenant {}",
+                tool_name, session.context.tenant_id
+            );
+            let invalidations = match (&self.response_cache, dry_run) {
+                (Some(_), DryRun::Off) => handler.cache_invalidations(&arguments),
+                _ => Vec::new(),
+            };
+            let cache_arguments = cache_ticket.is_some().then(|| arguments.clone());
+            let deprecation = handler.deprecation(&arguments);
+            let mut result = handler
+                .handle_output(session, arguments)
+                .await?
+                .into_value();
+            dry_run::mark(&mut result, dry_run);
+            self.output_limit.apply(tool_name, handler, &mut result)?;
+            if let Some(deprecation) = deprecation {
+                warn!(
+                    "Tenant {} called deprecated {}: {}",
+                    session.context.tenant_id, tool_name, deprecation.message
+                );
+                schema_versions::mark(&mut result, tool_name, &deprecation);
+            }
+
+            if let Some(response_cache) = &self.response_cache {
+                for invalidation in &invalidations {
+                    response_cache.invalidate(session, invalidation);
+                }
+            }
+            if let (Some((cache, ttl)), Some(ticket), Some(arguments)) =
+                (cache, cache_ticket, cache_arguments)
+            {
+                cache.insert(session, tool_name, &arguments, result.clone(), ttl, ticket);
             }
+            Ok(result)
         }
+        .await;
+
+        match (keep_for_replay, &result) {
+            (Some((store_key, arguments_hash)), Ok(result)) => {
+                let record =
+                    IdempotencyRecord::new(arguments_hash, result.clone(), IDEMPOTENCY_TTL);
+                // The call already happened; failing it now would only invite a second run
+                if let Err(e) = self.idempotency.put(&store_key, record).await {
+                    warn!(
+                        "Failed to keep the response of {} for replay: {}",
+                        tool_name, e
+                    );
+                }
+            }
+            // Nothing to replay, so a retry may run the call again
+            (Some((store_key, _)), Err(_)) => {
+                if let Err(e) = self.idempotency.release(&store_key).await {
+                    warn!("Failed to release idempotency key {}: {}", store_key, e);
+                }
+            }
+            (None, _) => {}
+        }
+        result
+    }
 
-        debug!(
-            "Executing tool {} for tenant {}",
-            tool_name, session.context.tenant_id
-        );
-        handler.handle(session, arguments).await
+    /// Claim the call's idempotency `key` before it runs. A key used before with other
+    /// arguments is a conflict, and one whose call is still running a retryable one.
+    async fn idempotent_replay(
+        &self,
+        handler: &dyn Handler,
+        call: &ToolCall<'_>,
+        key: &str,
+    ) -> Result<Replay, HandlerError> {
+        if !handler.accepts_idempotency_key() {
+            return Err(HandlerError::InvalidArguments(format!(
+                "{} doesn't take an idempotency_key",
+                call.tool
+            )));
+        }
+        idempotency::validate_key(key)?;
+        let store_key =
+            internal_keys::idempotency_key(&call.session.context.tenant_id, call.tool, key);
+        let arguments_hash = idempotency::arguments_hash(&call.arguments);
+        let claim = IdempotencyRecord::in_progress(arguments_hash.clone());
+        match self.idempotency.claim(&store_key, claim).await? {
+            Some(record) if record.in_progress => Err(HandlerError::IdempotencyInProgress {
+                tool: call.tool.to_string(),
+                key: key.to_string(),
+            }),
+            Some(record) if record.arguments_hash == arguments_hash => {
+                debug!("Replaying {} for idempotency key {}", call.tool, key);
+                Ok(Replay::Stored(record.response))
+            }
+            Some(_) => Err(HandlerError::IdempotencyConflict {
+                tool: call.tool.to_string(),
+                key: key.to_string(),
+            }),
+            None => Ok(Replay::FirstCall {
+                store_key,
+                arguments_hash,
+            }),
+        }
+    }
+}
+
+/// What an idempotency key lookup found
+enum Replay {
+    /// The response of the earlier call with the same key and arguments
+    Stored(Value),
+    /// No earlier call; this one claimed `store_key` and its response is kept there
+    FirstCall {
+        store_key: String,
+        arguments_hash: String,
+    },
+}
+
+fn compile_argument_schemas(handlers: &HashMap<String, Arc<dyn Handler>>) -> ArgumentSchemas {
+    let mut schemas = ArgumentSchemas::default();
+    for (name, handler) in handlers {
+        schemas.insert(name, handler.as_ref());
     }
+    schemas
+}
+
+/// Insert the handlers that manage or call downstream MCP servers through `registry`
+fn insert_registry_handlers(
+    handlers: &mut HashMap<String, Arc<dyn Handler>>,
+    aws_service: &Arc<dyn AwsApi>,
+    registry: &Arc<MCPServerRegistry>,
+    catalog: &Arc<IntegrationCatalog>,
+    jobs: &Arc<JobManager>,
+) {
+    // Register integration management handlers
+    handlers.insert(
+        "integration_register".to_string(),
+        Arc::new(integrations::IntegrationRegisterHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+            catalog.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_connect".to_string(),
+        Arc::new(integrations::IntegrationConnectHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_list".to_string(),
+        Arc::new(integrations::IntegrationListHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_disconnect".to_string(),
+        Arc::new(integrations::IntegrationDisconnectHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_unregister".to_string(),
+        Arc::new(integrations::IntegrationUnregisterHandler::new(
+            registry.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_export".to_string(),
+        Arc::new(integrations::IntegrationExportHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_import".to_string(),
+        Arc::new(integrations::IntegrationImportHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_update".to_string(),
+        Arc::new(integrations::IntegrationUpdateHandler::new(
+            registry.clone(),
+        )),
+    );
+    handlers.insert(
+        "integration_logs".to_string(),
+        Arc::new(integrations::IntegrationLogsHandler::new(registry.clone())),
+    );
+    handlers.insert(
+        "integration_test".to_string(),
+        Arc::new(integrations::IntegrationTestHandler::new(registry.clone())),
+    );
+
+    // Register MCP proxy handlers
+    handlers.insert(
+        "mcp_proxy".to_string(),
+        Arc::new(mcp_proxy::MCPProxyHandler::new(registry.clone())),
+    );
+    handlers.insert(
+        "mcp_list_tools".to_string(),
+        Arc::new(mcp_proxy::MCPListToolsHandler::new(registry.clone())),
+    );
+
+    // Register the per-tenant export and erasure jobs, which reach the tenant's
+    // integrations through the registry
+    handlers.insert(
+        "tenant_data_export".to_string(),
+        Arc::new(tenant_data::TenantDataExportHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+            jobs.clone(),
+        )),
+    );
+    handlers.insert(
+        "tenant_data_delete".to_string(),
+        Arc::new(tenant_data::TenantDataDeleteHandler::new(
+            aws_service.clone(),
+            registry.clone(),
+            jobs.clone(),
+        )),
+    );
 }
 
 // KV Handlers
 pub struct KvGetHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl KvGetHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -221,7 +1082,16 @@ impl Handler for KvGetHandler {
             .and_then(|v| v.as_str())
             .ok_or_else(|| HandlerError::InvalidArguments("Missing 'key' parameter".to_string()))?;
 
-        match self.aws_service.kv_get(session, key).await? {
+        let fresh = arguments
+            .get("fresh")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let value = if fresh {
+            self.aws_service.kv_get_fresh(session, key).await?
+        } else {
+            self.aws_service.kv_get(session, key).await?
+        };
+        match value {
             Some(value) => Ok(serde_json::json!({"value": value})),
             None => Ok(serde_json::json!({"value": null})),
         }
@@ -240,6 +1110,10 @@ impl Handler for KvGetHandler {
                     "key": {
                         "type": "string",
                         "description": "The key to retrieve"
+                    },
+                    "fresh": {
+                        "type": "boolean",
+                        "description": "Read from the table even if the value is cached"
                     }
                 },
                 "required": ["key"]
@@ -249,11 +1123,11 @@ impl Handler for KvGetHandler {
 }
 
 pub struct KvSetHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl KvSetHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -282,8 +1156,13 @@ impl Handler for KvSetHandler {
             .and_then(|v| v.as_u64())
             .map(|v| v as u32);
 
+        let sensitive = arguments
+            .get("sensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
         self.aws_service
-            .kv_set(session, key, value, ttl_hours)
+            .kv_set(session, key, value, ttl_hours, sensitive)
             .await?;
         Ok(serde_json::json!({"success": true}))
     }
@@ -292,6 +1171,10 @@ impl Handler for KvSetHandler {
         Some(Permission::WriteKV)
     }
 
+    fn accepts_idempotency_key(&self) -> bool {
+        true
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "Set a value in the key-value store",
@@ -309,6 +1192,10 @@ impl Handler for KvSetHandler {
                     "ttl_hours": {
                         "type": "number",
                         "description": "Time to live in hours (default: 24)"
+                    },
+                    "sensitive": {
+                        "type": "boolean",
+                        "description": "Encrypt the value with the tenant's data key (default: false)"
                     }
                 },
                 "required": ["key", "value"]
@@ -317,13 +1204,65 @@ impl Handler for KvSetHandler {
     }
 }
 
+pub struct KvDeleteHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl KvDeleteHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for KvDeleteHandler {
+    /// Deleting a key that doesn't exist succeeds
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let key = arguments
+            .get("key")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| HandlerError::InvalidArguments("Missing 'key' parameter".to_string()))?;
+
+        let ops = [KvWriteOp::Delete {
+            key: key.to_string(),
+            condition: None,
+        }];
+        self.aws_service.kv_transact_write(session, &ops).await?;
+        Ok(serde_json::json!({"success": true}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::DeleteKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "Delete a value from the key-value store",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "key": {
+                        "type": "string",
+                        "description": "The key to delete"
+                    }
+                },
+                "required": ["key"]
+            }
+        })
+    }
+}
+
 // Artifacts Handlers
 pub struct ArtifactsGetHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl ArtifactsGetHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -335,20 +1274,40 @@ impl Handler for ArtifactsGetHandler {
         session: &TenantSession,
         arguments: Value,
     ) -> Result<Value, HandlerError> {
+        self.handle_output(session, arguments)
+            .await
+            .map(HandlerOutput::into_value)
+    }
+
+    /// Images come back as an `image` content block, anything else as base64 JSON
+    async fn handle_output(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<HandlerOutput, HandlerError> {
         let key = arguments
             .get("key")
             .and_then(|v| v.as_str())
             .ok_or_else(|| HandlerError::InvalidArguments("Missing 'key' parameter".to_string()))?;
 
-        match self.aws_service.artifacts_get(session, key).await? {
-            Some(content) => {
-                let base64_content = general_purpose::STANDARD.encode(&content);
-                Ok(serde_json::json!({
-                    "content": base64_content,
-                    "encoding": "base64"
-                }))
-            }
-            None => Ok(serde_json::json!({"content": null})),
+        let Some(content) = self.aws_service.artifacts_get(session, key).await? else {
+            return Ok(serde_json::json!({"content": null}).into());
+        };
+        let content_type = match self.aws_service.artifacts_head(session, key).await? {
+            Some(metadata) => metadata.content_type,
+            None => None,
+        };
+        match content_type.filter(|mime| content::is_image(mime)) {
+            Some(mime) => Ok(HandlerOutput::Binary {
+                data: content,
+                mime,
+                uri: Some(format!("artifact://{}", key)),
+            }),
+            None => Ok(serde_json::json!({
+                "content": general_purpose::STANDARD.encode(&content),
+                "encoding": "base64"
+            })
+            .into()),
         }
     }
 
@@ -356,9 +1315,13 @@ impl Handler for ArtifactsGetHandler {
         Some(Permission::GetArtifacts)
     }
 
+    fn paging_hint(&self) -> Option<&'static str> {
+        Some("no range parameter; check the size with `artifacts_head` first")
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
-            "description": "Get an artifact by key",
+            "description": "Get an artifact by key. An image (by its stored content type) is returned as an MCP image content block; anything else as {content, encoding: \"base64\"}",
             "inputSchema": {
                 "type": "object",
                 "properties": {
@@ -374,11 +1337,11 @@ impl Handler for ArtifactsGetHandler {
 }
 
 pub struct ArtifactsPutHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl ArtifactsPutHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -407,13 +1370,22 @@ impl Handler for ArtifactsPutHandler {
             .and_then(|v| v.as_str())
             .unwrap_or("text/plain");
 
+        let ttl_class = match arguments.get("ttl_class") {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(value.as_str().and_then(TtlClass::parse).ok_or_else(|| {
+                HandlerError::InvalidArguments(
+                    "ttl_class must be one of short, medium, long".to_string(),
+                )
+            })?),
+        };
+
         // Decode base64 content
         let decoded_content = general_purpose::STANDARD.decode(content).map_err(|e| {
             HandlerError::InvalidArguments(format!("Invalid base64 content: {}", e))
         })?;
 
         self.aws_service
-            .artifacts_put(session, key, &decoded_content, content_type)
+            .artifacts_put(session, key, &decoded_content, content_type, ttl_class)
             .await?;
         Ok(serde_json::json!({"success": true}))
     }
@@ -422,6 +1394,10 @@ impl Handler for ArtifactsPutHandler {
         Some(Permission::PutArtifacts)
     }
 
+    fn cache_invalidations(&self, arguments: &Value) -> Vec<CacheInvalidation> {
+        artifacts_list_invalidation(arguments, "key")
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "Store an artifact",
@@ -439,6 +1415,11 @@ impl Handler for ArtifactsPutHandler {
                     "content_type": {
                         "type": "string",
                         "description": "The content type (default: text/plain)"
+                    },
+                    "ttl_class": {
+                        "type": "string",
+                        "enum": ["short", "medium", "long"],
+                        "description": "Tag the artifact for the bucket's lifecycle rules, which expire it after that class's retention (see artifacts_configure_lifecycle). Untagged artifacts are kept"
                     }
                 },
                 "required": ["key", "content"]
@@ -447,12 +1428,69 @@ impl Handler for ArtifactsPutHandler {
     }
 }
 
+pub struct ArtifactsHeadHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl ArtifactsHeadHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for ArtifactsHeadHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let key = arguments
+            .get("key")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| HandlerError::InvalidArguments("Missing 'key' parameter".to_string()))?;
+
+        let metadata = self
+            .aws_service
+            .artifacts_head(session, key)
+            .await?
+            .ok_or_else(|| AwsError::NotFound(format!("artifact {:?}", key)))?;
+        Ok(serde_json::json!({
+            "key": key,
+            "size": metadata.size,
+            "content_type": metadata.content_type,
+            "last_modified": metadata.last_modified,
+            "ttl_class": metadata.ttl_class
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::GetArtifacts)
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "Get an artifact's size, content type, last modification time and TTL class without its content",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "key": {
+                        "type": "string",
+                        "description": "The artifact key"
+                    }
+                },
+                "required": ["key"]
+            }
+        })
+    }
+}
+
 pub struct ArtifactsListHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl ArtifactsListHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -474,6 +1512,18 @@ impl Handler for ArtifactsListHandler {
         Some(Permission::ListArtifacts)
     }
 
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(30))
+    }
+
+    fn truncatable_fields(&self) -> &'static [&'static str] {
+        &["/keys"]
+    }
+
+    fn paging_hint(&self) -> Option<&'static str> {
+        Some("a longer `prefix`")
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "List artifacts with optional prefix",
@@ -492,62 +1542,175 @@ impl Handler for ArtifactsListHandler {
 
 // Events Handler
 pub struct EventsSendHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl EventsSendHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
 
-#[async_trait]
-impl Handler for EventsSendHandler {
-    async fn handle(
-        &self,
-        session: &TenantSession,
-        arguments: Value,
-    ) -> Result<Value, HandlerError> {
-        let detail_type = arguments
+/// Most events one `events_send` call may carry, as in an EventBridge `PutEvents`
+pub const MAX_EVENTS_PER_SEND: usize = 10;
+
+/// The single-event `events_send` shape, kept working for clients built against
+/// version 1 of the tool
+const SINGLE_EVENT_SEND: Deprecation = Deprecation {
+    message: "top-level detailType and detail are deprecated; send events: [{detailType, detail}] instead",
+    replaced_by: None,
+};
+
+impl EventsSendHandler {
+    /// The `(detailType, detail)` of each event the call carries
+    fn events(arguments: &Value) -> Result<Vec<(String, Value)>, HandlerError> {
+        let Some(events) = arguments.get("events") else {
+            return Ok(vec![Self::event(arguments)?]);
+        };
+        let events = events.as_array().ok_or_else(|| {
+            HandlerError::InvalidArguments("'events' must be an array".to_string())
+        })?;
+        if events.is_empty() || events.len() > MAX_EVENTS_PER_SEND {
+            return Err(HandlerError::InvalidArguments(format!(
+                "'events' must have 1-{} entries",
+                MAX_EVENTS_PER_SEND
+            )));
+        }
+        events.iter().map(Self::event).collect()
+    }
+
+    fn event(event: &Value) -> Result<(String, Value), HandlerError> {
+        let detail_type = event
             .get("detailType")
             .and_then(|v| v.as_str())
             .ok_or_else(|| {
                 HandlerError::InvalidArguments("Missing 'detailType' parameter".to_string())
             })?;
-
-        let detail = arguments
+        let detail = event
             .get("detail")
             .ok_or_else(|| {
                 HandlerError::InvalidArguments("Missing 'detail' parameter".to_string())
             })?
             .clone();
+        Ok((detail_type.to_string(), detail))
+    }
+}
 
-        self.aws_service
-            .send_event(session, detail_type, detail)
-            .await?;
-        Ok(serde_json::json!({"success": true}))
+#[async_trait]
+impl Handler for EventsSendHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let events = Self::events(&arguments)?;
+        let buffered = arguments
+            .get("buffered")
+            .and_then(|v| v.as_bool())
+            .unwrap_or_else(|| self.aws_service.buffers_events_by_default());
+
+        let count = events.len();
+        let mut event_ids = Vec::new();
+        for (detail_type, detail) in events {
+            if buffered {
+                event_ids.push(
+                    self.aws_service
+                        .send_event_buffered(session, &detail_type, detail)
+                        .await?,
+                );
+            } else {
+                self.aws_service
+                    .send_event(session, &detail_type, detail)
+                    .await?;
+            }
+        }
+
+        if arguments.get("events").is_some() {
+            let mut result = json!({"success": true, "count": count});
+            if buffered {
+                result["buffered"] = json!(event_ids.iter().all(Option::is_some));
+                result["eventIds"] = json!(event_ids);
+            }
+            return Ok(result);
+        }
+        match event_ids.pop() {
+            Some(event_id) => Ok(serde_json::json!({
+                "success": true,
+                "buffered": event_id.is_some(),
+                "eventId": event_id
+            })),
+            None => Ok(serde_json::json!({"success": true})),
+        }
     }
 
     fn required_permission(&self) -> Option<Permission> {
         Some(Permission::SendEvents)
     }
 
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("events_analytics")]
+    }
+
+    fn accepts_idempotency_key(&self) -> bool {
+        true
+    }
+
+    /// Version 2 takes a batch of `events`
+    fn tool_version(&self) -> ToolVersion {
+        ToolVersion {
+            version: 2,
+            deprecation: None,
+        }
+    }
+
+    fn deprecation(&self, arguments: &Value) -> Option<Deprecation> {
+        arguments
+            .get("events")
+            .is_none()
+            .then_some(SINGLE_EVENT_SEND)
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
-            "description": "Send an event",
+            "description": "Send up to 10 events",
             "inputSchema": {
                 "type": "object",
                 "properties": {
+                    "events": {
+                        "type": "array",
+                        "minItems": 1,
+                        "maxItems": MAX_EVENTS_PER_SEND,
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "detailType": {
+                                    "type": "string",
+                                    "description": "The event type"
+                                },
+                                "detail": {
+                                    "type": "object",
+                                    "description": "The event details"
+                                }
+                            },
+                            "required": ["detailType", "detail"]
+                        },
+                        "description": "The events to send, in order"
+                    },
                     "detailType": {
                         "type": "string",
-                        "description": "The event type"
+                        "deprecated": true,
+                        "description": "The event type of a single event; deprecated, use events"
                     },
                     "detail": {
                         "type": "object",
-                        "description": "The event details"
+                        "deprecated": true,
+                        "description": "The event details of a single event; deprecated, use events"
+                    },
+                    "buffered": {
+                        "type": "boolean",
+                        "description": "Queue the events and return provisional event IDs; they're sent within 200ms, in a batch with the tenant's other events"
                     }
-                },
-                "required": ["detailType", "detail"]
+                }
             }
         })
     }
@@ -555,20 +1718,22 @@ impl Handler for EventsSendHandler {
 
 // Events Query Handler
 pub struct EventsQueryHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl EventsQueryHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
 
 #[async_trait]
 impl Handler for EventsQueryHandler {
+    /// Admins may query any user's or source's events; everyone else sees their own
+    /// namespace's
     async fn handle(
         &self,
-        _session: &TenantSession,
+        session: &TenantSession,
         arguments: Value,
     ) -> Result<Value, HandlerError> {
         // Extract query parameters
@@ -597,6 +1762,11 @@ impl Handler for EventsQueryHandler {
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
 
+        let link_id = arguments
+            .get("linkId")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
         let start_time = arguments
             .get("startTime")
             .and_then(|v| v.as_str())
@@ -622,15 +1792,52 @@ impl Handler for EventsQueryHandler {
             .and_then(|v| v.as_str())
             .unwrap_or("desc");
 
+        let explain_only = arguments
+            .get("explain_only")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let explain = explain_only
+            || arguments
+                .get("explain")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+        let scope = (session.context.role != UserRole::Admin)
+            .then(|| aws::namespaces::EventScope::for_session(session));
+
+        if explain {
+            let query = aws::event_queries::EventQuery {
+                scope,
+                user_id,
+                organization_id,
+                source,
+                detail_type,
+                priority,
+                link_id,
+                start_time,
+                end_time,
+                limit,
+                exclusive_start_key,
+                ascending: sort_order == "asc",
+            };
+            return Ok(self
+                .aws_service
+                .query_events_explained(session, &query, explain_only)
+                .await?);
+        }
+
         // Query events from DynamoDB
         let result = self
             .aws_service
             .query_events(
+                session,
+                scope.as_ref(),
                 user_id,
                 organization_id,
                 source,
                 detail_type,
                 priority,
+                link_id,
                 start_time,
                 end_time,
                 limit,
@@ -646,6 +1853,14 @@ impl Handler for EventsQueryHandler {
         Some(Permission::SendEvents) // Reuse SendEvents permission for now
     }
 
+    fn truncatable_fields(&self) -> &'static [&'static str] {
+        &["/events"]
+    }
+
+    fn paging_hint(&self) -> Option<&'static str> {
+        Some("a smaller `limit`, paging with `exclusiveStartKey`")
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "Query events from the event history",
@@ -672,6 +1887,10 @@ impl Handler for EventsQueryHandler {
                         "type": "string",
                         "description": "Filter by priority (low, medium, high, critical)"
                     },
+                    "linkId": {
+                        "type": "string",
+                        "description": "Only the events of one events_send_linked call, including its event.link_aborted records"
+                    },
                     "startTime": {
                         "type": "string",
                         "description": "Start timestamp (ISO 8601)"
@@ -691,6 +1910,14 @@ impl Handler for EventsQueryHandler {
                     "sortOrder": {
                         "type": "string",
                         "description": "Sort order: 'asc' or 'desc' (default: 'desc')"
+                    },
+                    "explain": {
+                        "type": "boolean",
+                        "description": "Also return how the query was answered as `explain`: each index queried with its key condition, the arguments applied as filters, estimated and fetched pages, items scanned versus matched, and consumed read capacity when DynamoDB reports it"
+                    },
+                    "explain_only": {
+                        "type": "boolean",
+                        "description": "Return only the `explain` of the planned query, without running it"
                     }
                 }
             }
@@ -702,11 +1929,11 @@ impl Handler for EventsQueryHandler {
 // MCP Tool: events_analytics
 // Provides event analytics and aggregations (volume, top sources, priority distribution)
 pub struct EventsAnalyticsHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl EventsAnalyticsHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -825,17 +2052,21 @@ impl Handler for EventsAnalyticsHandler {
     fn required_permission(&self) -> Option<Permission> {
         Some(Permission::SendEvents) // Reuse SendEvents permission for analytics
     }
+
+    fn cache_ttl(&self) -> Option<Duration> {
+        Some(Duration::from_secs(60))
+    }
 }
 
 // EventsCreateRuleHandler
 // MCP Tool: events_create_rule
 // Creates event filtering rules stored in DynamoDB
 pub struct EventsCreateRuleHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl EventsCreateRuleHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -873,11 +2104,27 @@ impl Handler for EventsCreateRuleHandler {
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
 
-        // Create the rule
-        let result = self
-            .aws_service
-            .create_event_rule(session, &name, pattern, description, enabled)
-            .await?;
+        // Create the rule, together with its alert subscription if one is given
+        let result = match rule_alert(self.aws_service.as_ref(), session, &arguments, &name).await?
+        {
+            Some(alert) => {
+                self.aws_service
+                    .create_event_rule_with_alert(
+                        session,
+                        &name,
+                        pattern,
+                        description,
+                        enabled,
+                        &alert,
+                    )
+                    .await?
+            }
+            None => {
+                self.aws_service
+                    .create_event_rule(session, &name, pattern, description, enabled)
+                    .await?
+            }
+        };
 
         Ok(result)
     }
@@ -904,6 +2151,18 @@ impl Handler for EventsCreateRuleHandler {
                     "enabled": {
                         "type": "boolean",
                         "description": "Whether the rule is enabled (default: true)"
+                    },
+                    "alert": {
+                        "type": "object",
+                        "description": "Alert subscription to create with the rule, as for events_create_alert (its name defaults to the rule's); both are stored or neither is",
+                        "properties": {
+                            "name": {"type": "string"},
+                            "notificationMethod": {"type": "string", "enum": ["sns", "email"]},
+                            "snsTopicArn": {"type": "string"},
+                            "emailAddress": {"type": "string"},
+                            "enabled": {"type": "boolean"}
+                        },
+                        "required": ["notificationMethod"]
                     }
                 },
                 "required": ["name", "pattern"]
@@ -916,15 +2175,82 @@ impl Handler for EventsCreateRuleHandler {
     }
 }
 
+/// sns alerts are published with the server's regional client, so the topic must be a
+/// valid ARN in the tenant's region (and, with `aws.verify_alert_topics`,
+/// exist)
+async fn validate_alert_topic(
+    aws_service: &dyn AwsApi,
+    session: &TenantSession,
+    notification_method: &str,
+    sns_topic_arn: Option<&str>,
+) -> Result<(), HandlerError> {
+    if notification_method != "sns" {
+        return Ok(());
+    }
+    let topic_arn = sns_topic_arn.ok_or_else(|| {
+        HandlerError::InvalidArguments(
+            "'snsTopicArn' is required when notificationMethod is 'sns'".to_string(),
+        )
+    })?;
+    aws::alerts::validate_sns_topic_arn(topic_arn, &session.context.aws_region)
+        .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+    if aws_service.verifies_alert_topics() && !aws_service.sns_topic_exists(topic_arn).await? {
+        return Err(HandlerError::InvalidArguments(format!(
+            "SNS topic {} does not exist",
+            topic_arn
+        )));
+    }
+    Ok(())
+}
+
+/// The optional `alert` of `events_create_rule`: a subscription created with the rule,
+/// named after it unless it has a name of its own
+async fn rule_alert(
+    aws_service: &dyn AwsApi,
+    session: &TenantSession,
+    arguments: &Value,
+    rule_name: &str,
+) -> Result<Option<aws::alerts::NewAlertSubscription>, HandlerError> {
+    let alert = match arguments.get("alert") {
+        None | Some(Value::Null) => return Ok(None),
+        Some(alert) => alert,
+    };
+    let field = |name: &str| alert.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    let notification_method = field("notificationMethod").ok_or_else(|| {
+        HandlerError::InvalidArguments(
+            "Missing required field 'alert.notificationMethod'".to_string(),
+        )
+    })?;
+    let sns_topic_arn = field("snsTopicArn");
+    validate_alert_topic(
+        aws_service,
+        session,
+        &notification_method,
+        sns_topic_arn.as_deref(),
+    )
+    .await?;
+
+    Ok(Some(aws::alerts::NewAlertSubscription {
+        name: field("name").unwrap_or_else(|| rule_name.to_string()),
+        notification_method,
+        sns_topic_arn,
+        email_address: field("emailAddress"),
+        enabled: alert
+            .get("enabled")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true),
+    }))
+}
+
 // EventsCreateAlertHandler
 // MCP Tool: events_create_alert
 // Creates alert subscriptions (SNS/email) for event rules
 pub struct EventsCreateAlertHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl EventsCreateAlertHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -978,6 +2304,14 @@ impl Handler for EventsCreateAlertHandler {
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
 
+        validate_alert_topic(
+            self.aws_service.as_ref(),
+            session,
+            &notification_method,
+            sns_topic_arn.as_deref(),
+        )
+        .await?;
+
         // Create the alert subscription
         let result = self
             .aws_service
@@ -1017,7 +2351,7 @@ impl Handler for EventsCreateAlertHandler {
                     },
                     "snsTopicArn": {
                         "type": "string",
-                        "description": "SNS topic ARN (required if notificationMethod is 'sns')"
+                        "description": "SNS topic ARN in the tenant's region (required if notificationMethod is 'sns')"
                     },
                     "emailAddress": {
                         "type": "string",
@@ -1042,11 +2376,11 @@ impl Handler for EventsCreateAlertHandler {
 // MCP Tool: events_health_check
 // Performs health checks on event system components
 pub struct EventsHealthCheckHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
 }
 
 impl EventsHealthCheckHandler {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
         Self { aws_service }
     }
 }
@@ -0,0 +1,106 @@
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use jsonschema::{ValidationError, Validator};
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::handlers::{dry_run, Handler, HandlerError};
+
+/// The compiled `inputSchema` of every registered tool. Arguments are checked against
+/// it before the handler runs, so type and presence errors read the same for every tool;
+/// handlers only check what a schema can't express. Tools whose schema doesn't compile
+/// aren't checked.
+#[derive(Default)]
+pub struct ArgumentSchemas {
+    schemas: HashMap<String, CompiledSchema>,
+}
+
+struct CompiledSchema {
+    schema: Value,
+    validator: Validator,
+}
+
+impl ArgumentSchemas {
+    /// Compile the schema of `tool`, replacing any it had
+    pub fn insert(&mut self, tool: &str, handler: &dyn Handler) {
+        self.schemas.remove(tool);
+        let Some(schema) = dry_run::tool_schema(handler).get("inputSchema").cloned() else {
+            return;
+        };
+        match jsonschema::validator_for(&schema) {
+            Ok(validator) => {
+                self.schemas
+                    .insert(tool.to_string(), CompiledSchema { schema, validator });
+            }
+            Err(e) => warn!(
+                "Not validating arguments of {}: its input schema is unusable ({})",
+                tool, e
+            ),
+        }
+    }
+
+    /// The first way `arguments` break the tool's schema, if they do
+    pub fn validate(&self, tool: &str, arguments: &Value) -> Result<(), HandlerError> {
+        let Some(compiled) = self.schemas.get(tool) else {
+            return Ok(());
+        };
+        compiled
+            .validator
+            .validate(arguments)
+            .map_err(|error| schema_violation(tool, &compiled.schema, &error))
+    }
+}
+
+fn schema_violation(tool: &str, schema: &Value, error: &ValidationError) -> HandlerError {
+    let at = error.instance_path().as_str().to_string();
+    let (pointer, expected) = match error.kind() {
+        ValidationErrorKind::Type { kind } => (at, Some(type_names(kind))),
+        // Point at the missing property itself, expecting the type its schema declares
+        ValidationErrorKind::Required { property } => {
+            let property = property.as_str().unwrap_or_default();
+            let declared = at
+                .is_empty()
+                .then(|| declared_type(schema, property))
+                .flatten();
+            (format!("{}/{}", at, property), declared)
+        }
+        _ => (at, None),
+    };
+    HandlerError::SchemaViolation {
+        tool: tool.to_string(),
+        pointer: if pointer.is_empty() {
+            "/".to_string()
+        } else {
+            pointer
+        },
+        expected,
+        message: error.to_string(),
+    }
+}
+
+fn type_names(kind: &TypeKind) -> String {
+    match kind {
+        TypeKind::Single(json_type) => json_type.to_string(),
+        TypeKind::Multiple(types) => types
+            .iter()
+            .map(|json_type| json_type.to_string())
+            .collect::<Vec<_>>()
+            .join(" or "),
+    }
+}
+
+/// The `type` a top-level property is declared with
+fn declared_type(schema: &Value, property: &str) -> Option<String> {
+    let declared = schema.get("properties")?.get(property)?.get("type")?;
+    match declared {
+        Value::String(name) => Some(name.clone()),
+        Value::Array(names) => Some(
+            names
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(" or "),
+        ),
+        _ => None,
+    }
+}
@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::aws::AwsApi;
+use crate::handlers::dry_run::DRY_RUN_ARG;
+use crate::handlers::response_cache::{artifacts_list_invalidation, CacheInvalidation};
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// Deletes every artifact of the caller's whose key starts with a prefix. With
+/// `dry_run: true` it lists what would go, and how many bytes, without deleting.
+pub struct ArtifactsDeletePrefixHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl ArtifactsDeletePrefixHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for ArtifactsDeletePrefixHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let prefix = arguments
+            .get("prefix")
+            .and_then(|v| v.as_str())
+            .filter(|prefix| !prefix.is_empty())
+            .ok_or_else(|| {
+                HandlerError::InvalidArguments("Missing 'prefix' parameter".to_string())
+            })?;
+        let dry_run = arguments.get(DRY_RUN_ARG) == Some(&Value::Bool(true));
+
+        let deletion = self
+            .aws_service
+            .artifacts_delete_prefix(session, prefix, dry_run)
+            .await?;
+        if !dry_run {
+            info!(
+                "Deleted {} artifacts ({} bytes) under {} for tenant {}",
+                deletion.keys.len(),
+                deletion.bytes,
+                prefix,
+                session.context.tenant_id
+            );
+        }
+
+        Ok(json!({
+            "prefix": prefix,
+            "count": deletion.keys.len(),
+            "bytes": deletion.bytes,
+            "keys": deletion.keys
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::PutArtifacts)
+    }
+
+    fn cache_invalidations(&self, arguments: &Value) -> Vec<CacheInvalidation> {
+        artifacts_list_invalidation(arguments, "prefix")
+    }
+
+    fn supports_dry_run(&self) -> bool {
+        true
+    }
+
+    fn truncatable_fields(&self) -> &'static [&'static str] {
+        &["/keys"]
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Delete every artifact whose key starts with a prefix",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "prefix": {
+                        "type": "string",
+                        "minLength": 1,
+                        "description": "Key prefix of the artifacts to delete"
+                    }
+                },
+                "required": ["prefix"]
+            }
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::handlers::HandlerError;
+
+/// Suggested retry delay when a tool call is rejected for being over its concurrency cap
+pub const TOOL_BUSY_RETRY_AFTER: Duration = Duration::from_millis(500);
+
+/// What to do with a call that arrives while its tool is at `max_concurrency`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolConcurrencyPolicy {
+    /// Wait up to the given time for a slot, then reject
+    Queue(Duration),
+    /// Reject immediately
+    Reject,
+}
+
+/// Set to `reject` to reject calls over a tool's `max_concurrency` at once rather than
+/// queue them
+pub const TOOL_CONCURRENCY_POLICY_ENV: &str = "AGENT_MESH_TOOL_CONCURRENCY_POLICY";
+
+/// How long, in milliseconds, a queued call waits for a slot (default 2000)
+pub const TOOL_QUEUE_TIMEOUT_ENV: &str = "AGENT_MESH_TOOL_QUEUE_TIMEOUT_MS";
+
+/// How long a call queues for a slot unless configured otherwise
+pub const DEFAULT_QUEUE_TIMEOUT_MS: u64 = 2000;
+
+impl Default for ToolConcurrencyPolicy {
+    fn default() -> Self {
+        ToolConcurrencyPolicy::Queue(Duration::from_millis(DEFAULT_QUEUE_TIMEOUT_MS))
+    }
+}
+
+/// In-flight calls of one tool for one tenant
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolInFlight {
+    pub tenant_id: String,
+    pub tool: String,
+    pub in_flight: usize,
+    pub max_concurrency: usize,
+}
+
+/// A tool's slots for one tenant, with the cap they were created with
+type ToolSlots = (Arc<Semaphore>, usize);
+
+/// Caps concurrent calls per tool and tenant for handlers that declare `max_concurrency`.
+/// A tool's slots are forgotten once no call holds or waits for one.
+pub struct ToolConcurrencyLimiter {
+    policy: ToolConcurrencyPolicy,
+    // (tenant_id, tool) -> slots
+    semaphores: Mutex<HashMap<(String, String), ToolSlots>>,
+}
+
+impl ToolConcurrencyLimiter {
+    pub fn new(policy: ToolConcurrencyPolicy) -> Self {
+        Self {
+            policy,
+            semaphores: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a slot for `tool`; the slot is released when the permit is dropped
+    pub async fn acquire(
+        &self,
+        tenant_id: &str,
+        tool: &str,
+        max_concurrency: usize,
+    ) -> Result<OwnedSemaphorePermit, HandlerError> {
+        let semaphore = {
+            let mut semaphores = self.semaphores.lock().unwrap();
+            prune_idle(&mut semaphores);
+            let entry = semaphores
+                .entry((tenant_id.to_string(), tool.to_string()))
+                .or_insert_with(|| (Arc::new(Semaphore::new(max_concurrency)), max_concurrency));
+            entry.0.clone()
+        };
+
+        let permit = match self.policy {
+            ToolConcurrencyPolicy::Reject => semaphore.clone().try_acquire_owned().ok(),
+            ToolConcurrencyPolicy::Queue(timeout) => {
+                tokio::time::timeout(timeout, semaphore.clone().acquire_owned())
+                    .await
+                    .ok()
+                    .and_then(Result::ok)
+            }
+        };
+
+        permit.ok_or_else(|| HandlerError::ToolBusy {
+            tool: tool.to_string(),
+            in_flight: max_concurrency - semaphore.available_permits(),
+            max_concurrency,
+        })
+    }
+
+    /// Current in-flight counts for every capped tool with calls running or queued
+    pub fn in_flight(&self) -> Vec<ToolInFlight> {
+        let mut semaphores = self.semaphores.lock().unwrap();
+        prune_idle(&mut semaphores);
+        let mut counts: Vec<ToolInFlight> = semaphores
+            .iter()
+            .map(|((tenant_id, tool), (semaphore, max))| ToolInFlight {
+                tenant_id: tenant_id.clone(),
+                tool: tool.clone(),
+                in_flight: max - semaphore.available_permits(),
+                max_concurrency: *max,
+            })
+            .collect();
+        counts.sort_by(|a, b| (&a.tenant_id, &a.tool).cmp(&(&b.tenant_id, &b.tool)));
+        counts
+    }
+}
+
+/// Drop the slots no call holds or waits for: permits and queued calls each keep a
+/// reference to their semaphore, so only the map's is left
+fn prune_idle(semaphores: &mut HashMap<(String, String), ToolSlots>) {
+    semaphores.retain(|_, (semaphore, _)| Arc::strong_count(semaphore) > 1);
+}
@@ -0,0 +1,88 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// What a tool returns, before the registry turns it into the result sent to the
+/// client (see [`crate::handlers::Handler::handle_output`]). Most tools return JSON;
+/// binary data is sent as an MCP content block instead of base64 inside JSON, so
+/// clients can render it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandlerOutput {
+    /// Sent as it is, as every tool's result was before content blocks
+    Json(Value),
+    /// A `text` content block
+    #[allow(dead_code)]
+    Text(String),
+    /// An `image` or `audio` content block for `image/*` and `audio/*` data, else an
+    /// embedded `resource` with a `blob`; the data is base64-encoded either way
+    Binary {
+        data: Vec<u8>,
+        mime: String,
+        /// The resource's URI, e.g. `artifact://reports/q1.pdf`; without one a
+        /// `resource` block is named by the data's SHA-256
+        uri: Option<String>,
+    },
+}
+
+impl From<Value> for HandlerOutput {
+    fn from(value: Value) -> Self {
+        HandlerOutput::Json(value)
+    }
+}
+
+impl HandlerOutput {
+    /// Binary data without a URI of its own
+    #[allow(dead_code)]
+    pub fn binary(data: Vec<u8>, mime: impl Into<String>) -> Self {
+        HandlerOutput::Binary {
+            data,
+            mime: mime.into(),
+            uri: None,
+        }
+    }
+
+    /// The tool result sent to the client: JSON as it is, anything else as a
+    /// `{"content": [block]}` result
+    pub fn into_value(self) -> Value {
+        let block = match self {
+            HandlerOutput::Json(value) => return value,
+            HandlerOutput::Text(text) => json!({"type": "text", "text": text}),
+            HandlerOutput::Binary { data, mime, uri } => {
+                let encoded = general_purpose::STANDARD.encode(&data);
+                match block_type(&mime) {
+                    Some(block_type) => json!({
+                        "type": block_type,
+                        "data": encoded,
+                        "mimeType": mime
+                    }),
+                    None => {
+                        let uri = uri
+                            .unwrap_or_else(|| format!("urn:sha256:{:x}", Sha256::digest(&data)));
+                        json!({
+                            "type": "resource",
+                            "resource": {"uri": uri, "mimeType": mime, "blob": encoded}
+                        })
+                    }
+                }
+            }
+        };
+        json!({"content": [block]})
+    }
+}
+
+/// The content block MCP has for data of type `mime`, other than `resource`
+fn block_type(mime: &str) -> Option<&'static str> {
+    let top_level = mime.split('/').next().unwrap_or_default();
+    if top_level.eq_ignore_ascii_case("image") {
+        Some("image")
+    } else if top_level.eq_ignore_ascii_case("audio") {
+        Some("audio")
+    } else {
+        None
+    }
+}
+
+/// Whether data of type `mime` is sent as an `image` content block
+pub fn is_image(mime: &str) -> bool {
+    block_type(mime) == Some("image")
+}
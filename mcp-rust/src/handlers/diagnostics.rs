@@ -0,0 +1,207 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::aws::AwsApi;
+use crate::handlers::server_stats::ServerStatsHandler;
+use crate::handlers::{Handler, HandlerError};
+use crate::redaction;
+use crate::registry::MCPServerRegistry;
+use crate::tenant::{Permission, TenantManager, TenantSession};
+
+/// Captured log lines per downstream connection unless `log_lines` says otherwise
+pub const DEFAULT_LOG_LINES: usize = 50;
+
+/// Most log lines per connection a bundle carries
+pub const MAX_LOG_LINES: usize = 200;
+
+/// Prefix of the artifact keys bundles are written to when no `key` is given
+pub const DIAGNOSTICS_KEY_PREFIX: &str = "diagnostics";
+
+#[derive(Debug, Deserialize)]
+struct DiagnosticsBundleArgs {
+    log_lines: Option<usize>,
+    #[serde(default)]
+    write_artifact: bool,
+    key: Option<String>,
+}
+
+/// Collects what support asks for when the server misbehaves into one redacted JSON
+/// document: the effective config, `server_stats`, sessions, downstream connections
+/// with their last errors and log lines, and rate limit buckets
+pub struct DiagnosticsBundleHandler {
+    tenant_manager: Arc<TenantManager>,
+    server_stats: Arc<ServerStatsHandler>,
+    registry: Option<Arc<MCPServerRegistry>>,
+    aws_service: Option<Arc<dyn AwsApi>>,
+}
+
+impl DiagnosticsBundleHandler {
+    pub fn new(tenant_manager: Arc<TenantManager>, server_stats: Arc<ServerStatsHandler>) -> Self {
+        Self {
+            tenant_manager,
+            server_stats,
+            registry: None,
+            aws_service: None,
+        }
+    }
+
+    /// Also report every downstream connection's state and captured output
+    pub fn with_registry(mut self, registry: Arc<MCPServerRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Allow `write_artifact`, storing the bundle in the caller's tenant
+    pub fn with_aws_service(mut self, aws_service: Arc<dyn AwsApi>) -> Self {
+        self.aws_service = Some(aws_service);
+        self
+    }
+
+    /// The redacted bundle, with up to `log_lines` lines per connection
+    pub async fn bundle(
+        &self,
+        session: &TenantSession,
+        log_lines: usize,
+    ) -> Result<Value, HandlerError> {
+        let config: Map<String, Value> = self
+            .tenant_manager
+            .config()
+            .settings()
+            .into_iter()
+            .map(|(key, value, origin)| {
+                (key.to_string(), json!({"value": value, "origin": origin}))
+            })
+            .collect();
+
+        let mut sessions = Vec::new();
+        for tenant_session in self.tenant_manager.get_all_sessions().await {
+            sessions.push(json!({
+                "session_id": tenant_session.session_id.to_string(),
+                "tenant_id": tenant_session.context.tenant_id,
+                "user_id": tenant_session.context.user_id,
+                "role": tenant_session.context.role,
+                "created_at": tenant_session.created_at.to_rfc3339(),
+                "last_activity": tenant_session.last_activity.read().await.to_rfc3339(),
+                "active_requests": tenant_session.active_requests.load(Ordering::Relaxed),
+            }));
+        }
+        sessions.sort_by(|a, b| {
+            (a["tenant_id"].as_str(), a["created_at"].as_str())
+                .cmp(&(b["tenant_id"].as_str(), b["created_at"].as_str()))
+        });
+
+        let connections = match &self.registry {
+            Some(registry) => registry.connection_diagnostics(log_lines).await,
+            None => Vec::new(),
+        };
+
+        let rate_limit_buckets = self
+            .tenant_manager
+            .get_aws_rate_limiter()
+            .snapshot_all()
+            .await;
+        let mut bundle = json!({
+            "generated_at": chrono::Utc::now().to_rfc3339(),
+            "version": crate::cli::VERSION,
+            "config": config,
+            "server_stats": self.server_stats.handle(session, json!({})).await?,
+            "sessions": sessions,
+            "connections": connections,
+            "rate_limit_buckets": rate_limit_buckets,
+        });
+        redaction::redact(&mut bundle);
+        Ok(bundle)
+    }
+}
+
+#[async_trait]
+impl Handler for DiagnosticsBundleHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: DiagnosticsBundleArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+        let log_lines = args.log_lines.unwrap_or(DEFAULT_LOG_LINES);
+        if log_lines > MAX_LOG_LINES {
+            return Err(HandlerError::InvalidArguments(format!(
+                "log_lines must be at most {}",
+                MAX_LOG_LINES
+            )));
+        }
+
+        let bundle = self.bundle(session, log_lines).await?;
+        if !args.write_artifact {
+            return Ok(bundle);
+        }
+
+        let aws_service = self.aws_service.as_ref().ok_or_else(|| {
+            HandlerError::InvalidArguments("No artifact store to write the bundle to".to_string())
+        })?;
+        let key = args.key.unwrap_or_else(|| {
+            format!(
+                "{}/{}.json",
+                DIAGNOSTICS_KEY_PREFIX,
+                chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+            )
+        });
+        let document = serde_json::to_vec_pretty(&bundle)
+            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        aws_service
+            .artifacts_put(session, &key, &document, "application/json", None)
+            .await
+            .map_err(|e| HandlerError::Internal(format!("Failed to store the bundle: {}", e)))?;
+
+        info!(
+            "Wrote a diagnostics bundle for tenant {} to {}",
+            session.context.tenant_id, key
+        );
+        Ok(json!({
+            "key": key,
+            "size": document.len(),
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        // The bundle spans every tenant on this server
+        Some(Permission::Admin)
+    }
+
+    fn truncatable_fields(&self) -> &'static [&'static str] {
+        &["/connections", "/sessions"]
+    }
+
+    fn paging_hint(&self) -> Option<&'static str> {
+        Some("a smaller `log_lines`, or `write_artifact` to store the whole bundle")
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Collect a diagnostics bundle for support: the effective config, server_stats, sessions, downstream connection states with their last errors and log lines, and rate limit buckets, with credentials redacted. Optionally store it as an artifact and return its key instead",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "log_lines": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_LOG_LINES,
+                        "description": "Most recent captured log lines per downstream connection (default: 50)"
+                    },
+                    "write_artifact": {
+                        "type": "boolean",
+                        "description": "Store the bundle as an artifact and return its key (default: false)"
+                    },
+                    "key": {
+                        "type": "string",
+                        "description": "Artifact key to store it under (default: diagnostics/<timestamp>.json)"
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::{json, Value};
+
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::ToolPolicy;
+
+/// Argument asking a destructive tool to report what it would do without doing it
+pub const DRY_RUN_ARG: &str = "dry_run";
+
+/// Argument confirming a destructive call when the tenant's policy requires confirmation:
+/// `true`, or a token from the tool's own dry run where it asks for one
+pub const CONFIRM_ARG: &str = "confirm";
+
+/// How a call is run as far as dry runs go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DryRun {
+    Off,
+    /// The caller passed `dry_run: true`
+    Requested,
+    /// The tenant requires `confirm: true`, which the call didn't carry
+    Forced,
+}
+
+/// Decide whether the call is a dry run, setting `dry_run: true` in the arguments when
+/// the tenant's policy forces one. Tools without dry-run support reject `dry_run: true`
+/// rather than silently doing the real thing.
+pub fn prepare(
+    tool: &str,
+    handler: &dyn Handler,
+    policy: &ToolPolicy,
+    arguments: &mut Value,
+) -> Result<DryRun, HandlerError> {
+    let requested = arguments.get(DRY_RUN_ARG) == Some(&Value::Bool(true));
+    if !handler.supports_dry_run() {
+        if requested {
+            return Err(HandlerError::InvalidArguments(format!(
+                "{} has no dry run",
+                tool
+            )));
+        }
+        return Ok(DryRun::Off);
+    }
+    if requested {
+        return Ok(DryRun::Requested);
+    }
+    let confirmed = match arguments.get(CONFIRM_ARG) {
+        Some(Value::Bool(confirmed)) => *confirmed,
+        Some(Value::String(token)) => !token.is_empty(),
+        _ => false,
+    };
+    if policy.require_confirmation && !confirmed {
+        if let Value::Object(fields) = arguments {
+            fields.insert(DRY_RUN_ARG.to_string(), Value::Bool(true));
+            return Ok(DryRun::Forced);
+        }
+    }
+    Ok(DryRun::Off)
+}
+
+/// Mark a dry run's result with `dryRun: true`, and `confirmationRequired: true` when
+/// the policy forced it
+pub fn mark(result: &mut Value, dry_run: DryRun) {
+    let Value::Object(fields) = result else {
+        return;
+    };
+    if dry_run != DryRun::Off {
+        fields.insert("dryRun".to_string(), Value::Bool(true));
+    }
+    if dry_run == DryRun::Forced {
+        fields.insert("confirmationRequired".to_string(), Value::Bool(true));
+    }
+}
+
+/// The tool's schema as listed and validated: for tools with dry-run support, with
+/// the standard `dry_run` and `confirm` arguments added
+pub fn tool_schema(handler: &dyn Handler) -> Value {
+    let mut schema = handler.tool_schema();
+    if !handler.supports_dry_run() {
+        return schema;
+    }
+    if let Some(Value::Object(properties)) = schema.pointer_mut("/inputSchema/properties") {
+        properties.insert(
+            DRY_RUN_ARG.to_string(),
+            json!({
+                "type": "boolean",
+                "description": "Only report what would be affected; nothing is changed"
+            }),
+        );
+        // Kept where the tool takes a token of its own instead
+        properties.entry(CONFIRM_ARG).or_insert_with(|| {
+            json!({
+                "type": "boolean",
+                "description": "Confirm the call where the tenant requires it; without it the call is a dry run"
+            })
+        });
+    }
+    schema
+}
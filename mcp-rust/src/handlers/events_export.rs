@@ -0,0 +1,247 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::aws::artifacts::TtlClass;
+use crate::aws::namespaces::EventScope;
+use crate::aws::AwsApi;
+use crate::handlers::jobs::callbacks::callback_schema;
+use crate::handlers::jobs::{JobContext, JobManager};
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession, UserRole};
+
+/// Events exported when `max_events` isn't given
+pub const DEFAULT_MAX_EVENTS: u64 = 10_000;
+
+/// Most events one export may write
+pub const MAX_EXPORT_EVENTS: u64 = 100_000;
+
+/// Events read per `query_events` page
+const PAGE_SIZE: u64 = 500;
+
+/// Content type of the exported artifact: one event per line
+pub const EXPORT_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// What an export reads: the `events_query` filters, always oldest first
+#[derive(Debug, Clone)]
+struct ExportQuery {
+    scope: Option<EventScope>,
+    user_id: Option<String>,
+    organization_id: Option<String>,
+    source: Option<String>,
+    detail_type: Option<String>,
+    priority: Option<String>,
+    link_id: Option<String>,
+    start_time: Option<String>,
+    end_time: Option<String>,
+}
+
+fn optional_str(arguments: &Value, name: &str) -> Option<String> {
+    arguments
+        .get(name)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Writes the events matching an `events_query` filter to an artifact as JSON Lines, in
+/// the background. Returns a job ID to follow with `jobs_status`; the finished job's
+/// result has the artifact key and the number of events written.
+pub struct EventsExportHandler {
+    aws_service: Arc<dyn AwsApi>,
+    jobs: Arc<JobManager>,
+}
+
+impl EventsExportHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>, jobs: Arc<JobManager>) -> Self {
+        Self { aws_service, jobs }
+    }
+}
+
+#[async_trait]
+impl Handler for EventsExportHandler {
+    /// Admins may export any user's or source's events; everyone else their own
+    /// namespace's, as with `events_query`
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        // The export ends up as an artifact, so it needs both permissions
+        if !session.has_permission(&Permission::PutArtifacts) {
+            return Err(HandlerError::PermissionDenied(Permission::PutArtifacts));
+        }
+
+        let query = ExportQuery {
+            scope: (session.context.role != UserRole::Admin)
+                .then(|| EventScope::for_session(session)),
+            user_id: optional_str(&arguments, "userId"),
+            organization_id: optional_str(&arguments, "organizationId"),
+            source: optional_str(&arguments, "source"),
+            detail_type: optional_str(&arguments, "detailType"),
+            priority: optional_str(&arguments, "priority"),
+            link_id: optional_str(&arguments, "linkId"),
+            start_time: optional_str(&arguments, "startTime"),
+            end_time: optional_str(&arguments, "endTime"),
+        };
+        let max_events = arguments
+            .get("max_events")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_MAX_EVENTS)
+            .min(MAX_EXPORT_EVENTS);
+        let key = optional_str(&arguments, "key");
+        let ttl_class = match arguments.get("ttl_class") {
+            None | Some(Value::Null) => None,
+            Some(value) => Some(value.as_str().and_then(TtlClass::parse).ok_or_else(|| {
+                HandlerError::InvalidArguments(
+                    "ttl_class must be one of short, medium, long".to_string(),
+                )
+            })?),
+        };
+
+        let callback = self.jobs.callback_from(&arguments)?;
+
+        let aws_service = self.aws_service.clone();
+        let job_session = session.clone();
+        let record = self
+            .jobs
+            .spawn(session, "events_export", callback, move |job| async move {
+                let key = key.unwrap_or_else(|| format!("exports/events-{}.jsonl", job.job_id()));
+                export(
+                    aws_service.as_ref(),
+                    &job_session,
+                    &job,
+                    &query,
+                    &key,
+                    max_events,
+                    ttl_class,
+                )
+                .await
+            })
+            .await?;
+
+        Ok(json!({
+            "job_id": record.job_id,
+            "state": record.state
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::SendEvents) // As events_query
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Export the events matching a filter to an artifact as JSON Lines, oldest first, as a background job. Returns a job_id; follow it with jobs_status, whose result has the artifact key and the number of events written",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "userId": {"type": "string", "description": "Filter by user ID"},
+                    "organizationId": {"type": "string", "description": "Filter by organization ID"},
+                    "source": {"type": "string", "description": "Filter by event source"},
+                    "detailType": {"type": "string", "description": "Filter by event detail type"},
+                    "priority": {
+                        "type": "string",
+                        "enum": ["low", "medium", "high", "critical"],
+                        "description": "Filter by priority"
+                    },
+                    "linkId": {
+                        "type": "string",
+                        "description": "Only the events of one events_send_linked call"
+                    },
+                    "startTime": {"type": "string", "description": "Start time (ISO 8601)"},
+                    "endTime": {"type": "string", "description": "End time (ISO 8601)"},
+                    "key": {
+                        "type": "string",
+                        "description": "Artifact key to write (default exports/events-{job_id}.jsonl)"
+                    },
+                    "max_events": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_EXPORT_EVENTS,
+                        "description": "Most events exported (default 10000); the result says whether more matched"
+                    },
+                    "ttl_class": {
+                        "type": "string",
+                        "enum": ["short", "medium", "long"],
+                        "description": "Retention class of the exported artifact"
+                    },
+                    "callback": callback_schema()
+                }
+            }
+        })
+    }
+}
+
+/// Page through the matching events, reporting progress per page, and store them
+async fn export(
+    aws_service: &dyn AwsApi,
+    session: &TenantSession,
+    job: &JobContext,
+    query: &ExportQuery,
+    key: &str,
+    max_events: u64,
+    ttl_class: Option<TtlClass>,
+) -> Result<Value, HandlerError> {
+    let mut lines = Vec::new();
+    let mut count = 0u64;
+    let mut cursor: Option<String> = None;
+    let truncated = loop {
+        let page_size = PAGE_SIZE.min(max_events - count + 1);
+        let page = aws_service
+            .query_events(
+                session,
+                query.scope.as_ref(),
+                query.user_id.clone(),
+                query.organization_id.clone(),
+                query.source.clone(),
+                query.detail_type.clone(),
+                query.priority.clone(),
+                query.link_id.clone(),
+                query.start_time.clone(),
+                query.end_time.clone(),
+                page_size as i32,
+                cursor.clone(),
+                true,
+            )
+            .await?;
+        let events = page["events"].as_array().cloned().unwrap_or_default();
+        let mut skipped = false;
+        for event in events {
+            if count == max_events {
+                skipped = true;
+                break;
+            }
+            let line =
+                serde_json::to_vec(&event).map_err(|e| HandlerError::Internal(e.to_string()))?;
+            lines.extend(line);
+            lines.push(b'\n');
+            count += 1;
+        }
+        job.progress(count, None, Some(format!("{} events read", count)));
+        if skipped {
+            break true;
+        }
+
+        let next = page["lastEvaluatedKey"].as_str().map(|s| s.to_string());
+        // A cursor that doesn't move would page forever
+        match next {
+            Some(next) if cursor.as_ref() != Some(&next) => {
+                if count == max_events {
+                    break true;
+                }
+                cursor = Some(next);
+            }
+            _ => break false,
+        }
+    };
+
+    aws_service
+        .artifacts_put(session, key, &lines, EXPORT_CONTENT_TYPE, ttl_class)
+        .await?;
+    job.progress(count, Some(count), Some(format!("wrote {}", key)));
+    Ok(json!({
+        "key": key,
+        "count": count,
+        "truncated": truncated
+    }))
+}
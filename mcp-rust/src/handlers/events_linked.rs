@@ -0,0 +1,249 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::aws::event_buffer::EVENT_BATCH_SIZE;
+use crate::aws::AwsApi;
+use crate::handlers::response_cache::CacheInvalidation;
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// Detail type of the records compensating a published event of an aborted link
+pub const LINK_ABORTED_DETAIL_TYPE: &str = "event.link_aborted";
+
+/// Most events one link may carry: one `PutEvents` batch
+pub const MAX_LINKED_EVENTS: usize = EVENT_BATCH_SIZE;
+
+/// Attempts at publishing an entry before the link is aborted
+pub const SEND_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry of failed entries, doubled for each one after
+const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Publishes up to 10 events sharing a generated `linkId` in one `PutEvents` batch.
+/// This is best-effort atomicity, not a transaction: consumers may see some of the
+/// events before the link is aborted. Entries that fail are retried; if any still
+/// fails, each event that was published gets an `event.link_aborted` record naming it,
+/// and the result says which entries went out so the caller can reconcile.
+pub struct EventsSendLinkedHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl EventsSendLinkedHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+
+    /// The `(detailType, detail)` of each event, with the link's fields added to the
+    /// details
+    fn events(arguments: &Value, link_id: &str) -> Result<Vec<(String, Value)>, HandlerError> {
+        let events = arguments
+            .get("events")
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                HandlerError::InvalidArguments("Missing 'events' parameter".to_string())
+            })?;
+        if events.is_empty() || events.len() > MAX_LINKED_EVENTS {
+            return Err(HandlerError::InvalidArguments(format!(
+                "'events' must have 1-{} entries",
+                MAX_LINKED_EVENTS
+            )));
+        }
+        events
+            .iter()
+            .enumerate()
+            .map(|(index, event)| {
+                let detail_type = event
+                    .get("detailType")
+                    .and_then(Value::as_str)
+                    .filter(|detail_type| !detail_type.is_empty())
+                    .ok_or_else(|| {
+                        HandlerError::InvalidArguments(format!(
+                            "events[{}] is missing 'detailType'",
+                            index
+                        ))
+                    })?;
+                let Some(Value::Object(detail)) = event.get("detail") else {
+                    return Err(HandlerError::InvalidArguments(format!(
+                        "events[{}].detail must be an object",
+                        index
+                    )));
+                };
+                if detail.contains_key("linkId") {
+                    return Err(HandlerError::InvalidArguments(format!(
+                        "events[{}].detail.linkId is set by the server",
+                        index
+                    )));
+                }
+                let mut detail = detail.clone();
+                detail.insert("linkId".to_string(), json!(link_id));
+                detail.insert("linkIndex".to_string(), json!(index));
+                detail.insert("linkSize".to_string(), json!(events.len()));
+                Ok((detail_type.to_string(), Value::Object(detail)))
+            })
+            .collect()
+    }
+
+    /// Publish `events` in one batch, retrying the entries that fail up to
+    /// [`SEND_ATTEMPTS`] times in all. Returns each event's last error, in order.
+    async fn publish(
+        &self,
+        session: &TenantSession,
+        events: &[(String, Value)],
+    ) -> Vec<Option<String>> {
+        let mut errors: Vec<Option<String>> = vec![None; events.len()];
+        let mut pending: Vec<usize> = (0..events.len()).collect();
+        for attempt in 0..SEND_ATTEMPTS {
+            if attempt > 0 {
+                tokio::time::sleep(RETRY_BACKOFF * 2u32.pow(attempt - 1)).await;
+            }
+            let batch: Vec<(String, Value)> =
+                pending.iter().map(|index| events[*index].clone()).collect();
+            let outcome = match self.aws_service.send_events(session, &batch).await {
+                Ok(outcome) => outcome,
+                Err(e) => vec![Some(e.to_string()); batch.len()],
+            };
+            for (index, error) in pending.iter().zip(outcome) {
+                errors[*index] = error;
+            }
+            pending.retain(|index| errors[*index].is_some());
+            if pending.is_empty() {
+                break;
+            }
+        }
+        errors
+    }
+}
+
+#[async_trait]
+impl Handler for EventsSendLinkedHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let link_id = uuid::Uuid::new_v4().to_string();
+        let events = Self::events(&arguments, &link_id)?;
+        let errors = self.publish(session, &events).await;
+
+        let succeeded: Vec<usize> = (0..events.len())
+            .filter(|index| errors[*index].is_none())
+            .collect();
+        if succeeded.len() == events.len() {
+            return Ok(json!({
+                "success": true,
+                "linkId": link_id,
+                "count": events.len()
+            }));
+        }
+
+        let failed: Vec<Value> = errors
+            .iter()
+            .enumerate()
+            .filter_map(|(index, error)| {
+                error.as_ref().map(
+                    |error| json!({"index": index, "detailType": events[index].0, "error": error}),
+                )
+            })
+            .collect();
+        let failed_indexes: Vec<usize> = (0..events.len())
+            .filter(|index| errors[*index].is_some())
+            .collect();
+        warn!(
+            "Aborting link {} of tenant {}: {} of {} events failed",
+            link_id,
+            session.context.tenant_id,
+            failed.len(),
+            events.len()
+        );
+
+        // One compensating record per published event, carrying the link so a
+        // `linkId` query returns them alongside what they cancel
+        let aborts: Vec<(String, Value)> = succeeded
+            .iter()
+            .map(|index| {
+                (
+                    LINK_ABORTED_DETAIL_TYPE.to_string(),
+                    json!({
+                        "linkId": link_id,
+                        "abortedIndex": index,
+                        "abortedDetailType": events[*index].0,
+                        "failed": failed_indexes,
+                    }),
+                )
+            })
+            .collect();
+        let abort_errors = if aborts.is_empty() {
+            Vec::new()
+        } else {
+            self.publish(session, &aborts).await
+        };
+        let unsent_aborts: Vec<usize> = succeeded
+            .iter()
+            .zip(&abort_errors)
+            .filter(|(_, error)| error.is_some())
+            .map(|(index, _)| *index)
+            .collect();
+        if !unsent_aborts.is_empty() {
+            warn!(
+                "Link {} of tenant {}: no abort record for events {:?}",
+                link_id, session.context.tenant_id, unsent_aborts
+            );
+        }
+
+        Ok(json!({
+            "success": false,
+            "linkId": link_id,
+            "count": events.len(),
+            "succeeded": succeeded,
+            "failed": failed,
+            "aborted": true,
+            "abortRecords": {
+                "sent": aborts.len() - unsent_aborts.len(),
+                "unsent": unsent_aborts
+            }
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::SendEvents)
+    }
+
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("events_analytics")]
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Send up to 10 related events (e.g. a parent and its children) as one linked batch sharing a generated linkId, found with events_query's linkId filter. Best-effort atomicity, not a transaction: the events are published in one PutEvents batch and failed entries are retried up to 3 times; if any still fails, consumers may already have seen the others, so each published event gets an event.link_aborted record ({linkId, abortedIndex, abortedDetailType, failed}) and the result has success: false with the succeeded indexes, the failed entries and their errors, and which abort records couldn't be sent, for the caller to reconcile",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "events": {
+                        "type": "array",
+                        "minItems": 1,
+                        "maxItems": MAX_LINKED_EVENTS,
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "detailType": {
+                                    "type": "string",
+                                    "description": "The event type"
+                                },
+                                "detail": {
+                                    "type": "object",
+                                    "description": "The event details; linkId, linkIndex and linkSize are added"
+                                }
+                            },
+                            "required": ["detailType", "detail"]
+                        },
+                        "description": "The linked events, in order"
+                    }
+                },
+                "required": ["events"]
+            }
+        })
+    }
+}
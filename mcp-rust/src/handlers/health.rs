@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::aws::AwsApi;
+use crate::handlers::{Handler, HandlerError};
+use crate::registry::MCPServerRegistry;
+use crate::tenant::{Permission, TenantManager, TenantSession};
+
+/// How long each sub-check may take before it's reported `unknown`
+pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Commit the binary was built from, when `GIT_COMMIT` was set at build time
+const BUILD_COMMIT: Option<&str> = option_env!("GIT_COMMIT");
+
+/// Whether this server process is healthy, for orchestrators and the dashboard. Every
+/// sub-check (sessions, rate limiter, downstream connections, backend) runs
+/// concurrently under its own timeout, so the answer comes back within one timeout even
+/// when AWS is down; a check that doesn't finish in time is `unknown`.
+pub struct HealthHandler {
+    started: Instant,
+    tenant_manager: Arc<TenantManager>,
+    registry: Arc<MCPServerRegistry>,
+    aws_service: Arc<dyn AwsApi>,
+    check_timeout: Duration,
+}
+
+impl HealthHandler {
+    pub fn new(
+        tenant_manager: Arc<TenantManager>,
+        registry: Arc<MCPServerRegistry>,
+        aws_service: Arc<dyn AwsApi>,
+    ) -> Self {
+        Self {
+            started: Instant::now(),
+            tenant_manager,
+            registry,
+            aws_service,
+            check_timeout: DEFAULT_CHECK_TIMEOUT,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_check_timeout(mut self, check_timeout: Duration) -> Self {
+        self.check_timeout = check_timeout;
+        self
+    }
+
+    /// Run `check` under the timeout: its fields with `status: ok`, its error with
+    /// `status: error`, or `status: unknown` when it didn't answer in time
+    async fn check(&self, check: impl Future<Output = Result<Value, String>>) -> Value {
+        let mut report = match tokio::time::timeout(self.check_timeout, check).await {
+            Ok(Ok(report)) => report,
+            Ok(Err(error)) => return json!({"status": "error", "error": error}),
+            Err(_) => {
+                return json!({
+                    "status": "unknown",
+                    "error": format!("no answer within {}ms", self.check_timeout.as_millis())
+                })
+            }
+        };
+        report["status"] = json!("ok");
+        report
+    }
+}
+
+#[async_trait]
+impl Handler for HealthHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let limiter = self.tenant_manager.get_aws_rate_limiter();
+        let (sessions, rate_limiter, connections, backend) = tokio::join!(
+            self.check(async {
+                Ok(json!({"active": self.tenant_manager.get_all_sessions().await.len()}))
+            }),
+            self.check(async { Ok(json!({"buckets": limiter.bucket_count().await})) }),
+            self.check(async {
+                Ok(json!({"by_status": self.registry.connection_status_counts().await}))
+            }),
+            self.check(async {
+                let started = Instant::now();
+                self.aws_service.ping().await.map_err(|e| e.to_string())?;
+                Ok(json!({"latency_ms": started.elapsed().as_millis() as u64}))
+            }),
+        );
+
+        // Degraded for the caller when its own tenant's AWS role can't be assumed;
+        // other tenants' failures are only counted
+        let degraded_tenants = self.aws_service.degraded_tenants();
+        let tenant_credentials = match degraded_tenants.get(&session.context.tenant_id) {
+            Some(reason) => json!({
+                "status": "degraded",
+                "degraded_tenants": degraded_tenants.len(),
+                "error": reason,
+            }),
+            None => json!({"status": "ok", "degraded_tenants": degraded_tenants.len()}),
+        };
+
+        let checks = json!({
+            "sessions": sessions,
+            "rate_limiter": rate_limiter,
+            "connections": connections,
+            "backend": backend,
+            "tenant_credentials": tenant_credentials,
+        });
+        let healthy = checks
+            .as_object()
+            .into_iter()
+            .flat_map(|checks| checks.values())
+            .all(|check| check["status"] == "ok");
+        Ok(json!({
+            "status": if healthy { "ok" } else { "degraded" },
+            "version": env!("CARGO_PKG_VERSION"),
+            "commit": BUILD_COMMIT,
+            "uptime_secs": self.started.elapsed().as_secs(),
+            "active_requests": self.tenant_manager.total_active_requests(),
+            "backend": self.tenant_manager.config().aws.mode(),
+            "checks": checks,
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        // Reports counts only, nothing about other tenants
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Check that this server is healthy: uptime, active sessions and requests, rate limiter buckets, downstream connections by status, backend mode (aws, memory, or offline when it fell back to memory without AWS credentials) and reachability, whether your tenant's own AWS role can be assumed, and build version. Checks that don't answer in time are reported unknown and the status degraded",
+            "inputSchema": {
+                "type": "object",
+                "properties": {}
+            }
+        })
+    }
+}
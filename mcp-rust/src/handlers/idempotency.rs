@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::aws::AwsApi;
+use crate::handlers::HandlerError;
+
+/// How long a response is kept for replay
+pub const IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a call's claim on its key holds if the call never finishes, e.g. because
+/// its server went away; then a retry may run it again
+pub const IN_PROGRESS_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Responses the in-memory store keeps before dropping the oldest
+const DEFAULT_MEMORY_CAPACITY: usize = 10_000;
+
+/// Longest idempotency key accepted
+const MAX_KEY_LEN: usize = 128;
+
+/// A successful call's response, kept under its idempotency key, or the claim of a
+/// call still running
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    /// [`arguments_hash`] of the arguments the call was made with
+    pub arguments_hash: String,
+    pub response: Value,
+    /// Unix seconds after which the record no longer replays
+    pub expires_at: i64,
+    /// The call hasn't finished; there's no response yet
+    #[serde(default)]
+    pub in_progress: bool,
+}
+
+impl IdempotencyRecord {
+    pub fn new(arguments_hash: String, response: Value, ttl: Duration) -> Self {
+        Self {
+            arguments_hash,
+            response,
+            expires_at: chrono::Utc::now().timestamp() + ttl.as_secs() as i64,
+            in_progress: false,
+        }
+    }
+
+    /// The claim a call puts under its key before it runs, for [`IN_PROGRESS_TTL`]
+    pub fn in_progress(arguments_hash: String) -> Self {
+        Self {
+            in_progress: true,
+            ..Self::new(arguments_hash, Value::Null, IN_PROGRESS_TTL)
+        }
+    }
+
+    fn expired(&self) -> bool {
+        chrono::Utc::now().timestamp() >= self.expires_at
+    }
+}
+
+/// Where responses to calls with an idempotency key are kept, by
+/// [`idempotency_key`](crate::aws::internal_keys::idempotency_key)
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Put `claim` (an [`IdempotencyRecord::in_progress`]) under `key` unless an
+    /// unexpired record is there, in one conditional step so only one of several
+    /// concurrent calls gets it. `None` once claimed; otherwise the record that was there.
+    async fn claim(
+        &self,
+        key: &str,
+        claim: IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>, HandlerError>;
+
+    /// Keep the finished call's response under its claimed `key`
+    async fn put(&self, key: &str, record: IdempotencyRecord) -> Result<(), HandlerError>;
+
+    /// Drop the claim of a call that failed, so a retry can run it
+    async fn release(&self, key: &str) -> Result<(), HandlerError>;
+}
+
+/// Keeps records in the backend's KV table, shared by every replica; DynamoDB's TTL
+/// removes them a while after they expire
+pub struct KvIdempotencyStore {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl KvIdempotencyStore {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for KvIdempotencyStore {
+    async fn put(&self, key: &str, record: IdempotencyRecord) -> Result<(), HandlerError> {
+        let ttl_hours = (IDEMPOTENCY_TTL.as_secs() / 3600) as u32;
+        let stored =
+            serde_json::to_string(&record).map_err(|e| HandlerError::Internal(e.to_string()))?;
+        self.aws_service
+            .kv_set_direct(key, &stored, Some(ttl_hours))
+            .await?;
+        Ok(())
+    }
+
+    async fn claim(
+        &self,
+        key: &str,
+        claim: IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>, HandlerError> {
+        let ttl_hours = (IDEMPOTENCY_TTL.as_secs() / 3600) as u32;
+        let claimed =
+            serde_json::to_string(&claim).map_err(|e| HandlerError::Internal(e.to_string()))?;
+        let mut expected = None;
+        // Once for an absent key, and once more to take over an expired record
+        for _ in 0..2 {
+            if self
+                .aws_service
+                .kv_compare_and_swap_direct(key, expected.as_deref(), &claimed, Some(ttl_hours))
+                .await?
+            {
+                return Ok(None);
+            }
+            let Some(stored) = self.aws_service.kv_get_direct(key).await? else {
+                expected = None;
+                continue;
+            };
+            let record: IdempotencyRecord = serde_json::from_str(&stored)
+                .map_err(|e| HandlerError::Internal(format!("Bad idempotency record: {}", e)))?;
+            if !record.expired() {
+                return Ok(Some(record));
+            }
+            expected = Some(stored);
+        }
+        // Another call took the key over in between, and is running now
+        Ok(Some(claim))
+    }
+
+    async fn release(&self, key: &str) -> Result<(), HandlerError> {
+        self.aws_service.kv_delete(key).await?;
+        Ok(())
+    }
+}
+
+/// Keeps records in process, for the in-memory backend: up to `capacity` of them, each
+/// for `ttl`, dropping the oldest when full
+pub struct MemoryIdempotencyStore {
+    ttl: Duration,
+    capacity: usize,
+    records: Mutex<MemoryRecords>,
+}
+
+impl Default for MemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::new(IDEMPOTENCY_TTL, DEFAULT_MEMORY_CAPACITY)
+    }
+}
+
+impl MemoryIdempotencyStore {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records kept, expired ones included until the next `put`
+    #[allow(dead_code)]
+    pub fn record_count(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for MemoryIdempotencyStore {
+    async fn put(&self, key: &str, record: IdempotencyRecord) -> Result<(), HandlerError> {
+        self.insert(&mut self.records.lock().unwrap(), key, record);
+        Ok(())
+    }
+
+    async fn claim(
+        &self,
+        key: &str,
+        claim: IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>, HandlerError> {
+        let mut records = self.records.lock().unwrap();
+        if let Some(record) = self.unexpired(&mut records, key) {
+            return Ok(Some(record));
+        }
+        self.insert(&mut records, key, claim);
+        Ok(None)
+    }
+
+    async fn release(&self, key: &str) -> Result<(), HandlerError> {
+        self.records.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Stored records by key, with when they were stored
+type MemoryRecords = HashMap<String, (Instant, IdempotencyRecord)>;
+
+impl MemoryIdempotencyStore {
+    /// The record under `key`, dropping it once expired
+    fn unexpired(&self, records: &mut MemoryRecords, key: &str) -> Option<IdempotencyRecord> {
+        match records.get(key) {
+            Some((stored_at, record)) if stored_at.elapsed() >= self.ttl || record.expired() => {
+                records.remove(key);
+                None
+            }
+            Some((_, record)) => Some(record.clone()),
+            None => None,
+        }
+    }
+
+    fn insert(&self, records: &mut MemoryRecords, key: &str, record: IdempotencyRecord) {
+        records.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+        if records.len() >= self.capacity && !records.contains_key(key) {
+            let oldest = records
+                .iter()
+                .min_by_key(|(_, (stored_at, _))| *stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                records.remove(&oldest);
+            }
+        }
+        records.insert(key.to_string(), (Instant::now(), record));
+    }
+}
+
+/// Idempotency keys are 1-128 letters, digits, `-`, `_` or `.`
+pub fn validate_key(key: &str) -> Result<(), HandlerError> {
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(HandlerError::InvalidArguments(format!(
+            "idempotency_key must be 1-{} letters, digits, '-', '_' or '.'",
+            MAX_KEY_LEN
+        )))
+    }
+}
+
+/// SHA-256 of the arguments with object keys sorted, so the same arguments always hash
+/// the same wherever they're serialized
+pub fn arguments_hash(arguments: &Value) -> String {
+    let digest = Sha256::digest(canonical(arguments).to_string().as_bytes());
+    format!("{:x}", digest)
+}
+
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut sorted: Vec<_> = fields.iter().collect();
+            sorted.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                sorted
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+        other => other.clone(),
+    }
+}
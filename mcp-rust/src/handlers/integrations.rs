@@ -2,15 +2,36 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, info};
 
-use crate::aws::AwsService;
+use crate::aws::{internal_keys, AwsApi};
+use crate::handlers::response_cache::CacheInvalidation;
 use crate::handlers::{Handler, HandlerError};
+use crate::registry::export::{IntegrationExport, OnConflict};
+use crate::registry::logs::LOG_BUFFER_LINES;
 use crate::registry::{
-    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType,
+    instance_id, validate_connection_id, AuthMethod, DeploymentConfig, MCPServerConfig,
+    MCPServerRegistry, MCPServerType, MCPServerUpdate, RegistryError, RestartPolicy,
+    DEFAULT_CONNECTION,
 };
 use crate::tenant::{Permission, TenantSession};
 
+pub mod catalog;
+
+use catalog::IntegrationCatalog;
+
+/// Service and connection ids end up in internal KV keys and registry instance ids, so
+/// they're checked before anything is stored under them
+fn validate_ids(service_id: &str, connection_id: Option<&str>) -> Result<(), HandlerError> {
+    internal_keys::validate_id("service_id", service_id).map_err(HandlerError::InvalidArguments)?;
+    if let Some(connection_id) = connection_id {
+        internal_keys::validate_id("connection_id", connection_id)
+            .map_err(HandlerError::InvalidArguments)?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IntegrationConfig {
     pub id: String,
@@ -33,15 +54,21 @@ pub struct ConfigField {
 }
 
 pub struct IntegrationRegisterHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
     registry: Arc<MCPServerRegistry>,
+    catalog: Arc<IntegrationCatalog>,
 }
 
 impl IntegrationRegisterHandler {
-    pub fn new(aws_service: Arc<AwsService>, registry: Arc<MCPServerRegistry>) -> Self {
+    pub fn new(
+        aws_service: Arc<dyn AwsApi>,
+        registry: Arc<MCPServerRegistry>,
+        catalog: Arc<IntegrationCatalog>,
+    ) -> Self {
         Self {
             aws_service,
             registry,
+            catalog,
         }
     }
 }
@@ -53,8 +80,10 @@ impl Handler for IntegrationRegisterHandler {
         session: &TenantSession,
         arguments: Value,
     ) -> Result<Value, HandlerError> {
+        let arguments = self.catalog.resolve(arguments)?;
         let args: IntegrationRegisterArgs = serde_json::from_value(arguments)
             .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+        validate_ids(&args.service_id, None)?;
 
         info!(
             "Registering integration {} for tenant {}",
@@ -70,6 +99,10 @@ impl Handler for IntegrationRegisterHandler {
                 volumes: docker.volumes.unwrap_or_default(),
                 network: docker.network,
                 runtime: docker.runtime,
+                memory_limit: docker.memory_limit,
+                cpu_limit: docker.cpu_limit,
+                pids_limit: docker.pids_limit,
+                read_only_rootfs: docker.read_only_rootfs.unwrap_or(false),
             }
         } else {
             DeploymentConfig::Process {
@@ -89,16 +122,23 @@ impl Handler for IntegrationRegisterHandler {
             capabilities: args.capabilities.clone(),
             health_check_interval_secs: 60,
             auto_reconnect: true,
+            tool_timeout_secs: args.tool_timeout_secs,
+            restart_policy: args.restart_policy,
         };
 
         // Register the server
         self.registry
             .register_server(&session.context.get_context_id(), server_config)
             .await
-            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+            .map_err(|e| match e {
+                RegistryError::InvalidConfig { .. } => {
+                    HandlerError::InvalidArguments(e.to_string())
+                }
+                e => HandlerError::Internal(e.to_string()),
+            })?;
 
         // Store integration config in KV
-        let key = format!("integration-{}", args.service_id);
+        let key = internal_keys::integration_key(&args.service_id);
         let config = IntegrationConfig {
             id: args.service_id.clone(),
             name: args.name,
@@ -127,12 +167,20 @@ impl Handler for IntegrationRegisterHandler {
         Some(Permission::Admin)
     }
 
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("integration_list")]
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
-            "description": "Register a new MCP server integration",
+            "description": "Register a new MCP server integration, from scratch or from a catalog template",
             "inputSchema": {
                 "type": "object",
                 "properties": {
+                    "template": {
+                        "type": "string",
+                        "description": "Optional: id of an integration_catalog_list template to start from; the other arguments override its fields, and env and docker_config are merged key by key"
+                    },
                     "service_id": {
                         "type": "string",
                         "description": "Unique identifier for the service"
@@ -172,7 +220,20 @@ impl Handler for IntegrationRegisterHandler {
                             "ports": { "type": "array", "items": { "type": "string" } },
                             "volumes": { "type": "array", "items": { "type": "string" } },
                             "network": { "type": "string" },
-                            "runtime": { "type": "string" }
+                            "runtime": { "type": "string" },
+                            "memory_limit": {
+                                "type": "string",
+                                "description": "Memory limit with an optional b, k, m or g unit, e.g. '512m'"
+                            },
+                            "cpu_limit": {
+                                "type": "string",
+                                "description": "Number of CPUs, e.g. '1.5'"
+                            },
+                            "pids_limit": { "type": "integer", "minimum": 1 },
+                            "read_only_rootfs": {
+                                "type": "boolean",
+                                "description": "Mount the container's root filesystem read-only"
+                            }
                         }
                     },
                     "env": {
@@ -180,8 +241,8 @@ impl Handler for IntegrationRegisterHandler {
                         "description": "Environment variables"
                     },
                     "auth_method": {
-                        "type": "object",
-                        "description": "Authentication method configuration"
+                        "type": ["string", "object"],
+                        "description": "Authentication method configuration; o_auth2 takes an optional token_endpoint for refreshing access tokens"
                     },
                     "configuration_schema": {
                         "type": "array",
@@ -191,9 +252,27 @@ impl Handler for IntegrationRegisterHandler {
                         "type": "array",
                         "items": { "type": "string" },
                         "description": "List of capabilities"
+                    },
+                    "tool_timeout_secs": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Longest a tool call may take (default 30)"
+                    },
+                    "restart_policy": {
+                        "type": "object",
+                        "description": "How a crashed server is restarted; omitted fields take the defaults shown",
+                        "properties": {
+                            "max_restarts": { "type": "integer", "minimum": 0, "default": 5 },
+                            "backoff_initial_ms": { "type": "integer", "minimum": 0, "default": 2000 },
+                            "backoff_max_ms": { "type": "integer", "minimum": 0, "default": 300000 },
+                            "reset_after_secs": { "type": "integer", "minimum": 0, "default": 60 }
+                        }
                     }
                 },
-                "required": ["service_id", "name", "auth_method"]
+                "anyOf": [
+                    { "required": ["template"] },
+                    { "required": ["service_id", "name", "auth_method"] }
+                ]
             }
         })
     }
@@ -213,9 +292,11 @@ struct IntegrationRegisterArgs {
     auth_method: AuthMethod,
     configuration_schema: Vec<ConfigField>,
     capabilities: Vec<String>,
+    tool_timeout_secs: Option<u64>,
+    restart_policy: Option<RestartPolicy>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct DockerConfig {
     image: String,
     tag: String,
@@ -223,15 +304,82 @@ struct DockerConfig {
     volumes: Option<Vec<String>>,
     network: Option<String>,
     runtime: Option<String>,
+    memory_limit: Option<String>,
+    cpu_limit: Option<String>,
+    pids_limit: Option<u32>,
+    read_only_rootfs: Option<bool>,
+}
+
+pub struct IntegrationCatalogListHandler {
+    catalog: Arc<IntegrationCatalog>,
+}
+
+impl IntegrationCatalogListHandler {
+    pub fn new(catalog: Arc<IntegrationCatalog>) -> Self {
+        Self { catalog }
+    }
+}
+
+#[async_trait]
+impl Handler for IntegrationCatalogListHandler {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: IntegrationCatalogListArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+
+        let templates: Vec<_> = self
+            .catalog
+            .templates()
+            .iter()
+            .filter(|template| {
+                args.category
+                    .as_ref()
+                    .is_none_or(|category| template.integration.category == *category)
+            })
+            .collect();
+
+        Ok(serde_json::json!({
+            "templates": templates,
+            "count": templates.len()
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Read)
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "List the curated integrations that integration_register can enable by template id",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "category": {
+                        "type": "string",
+                        "description": "Optional: only list templates in this category"
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IntegrationCatalogListArgs {
+    #[serde(default)]
+    category: Option<String>,
 }
 
 pub struct IntegrationConnectHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
     registry: Arc<MCPServerRegistry>,
 }
 
 impl IntegrationConnectHandler {
-    pub fn new(aws_service: Arc<AwsService>, registry: Arc<MCPServerRegistry>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>, registry: Arc<MCPServerRegistry>) -> Self {
         Self {
             aws_service,
             registry,
@@ -254,52 +402,85 @@ impl Handler for IntegrationConnectHandler {
             args.service_id, session.context.user_id, session.context.tenant_id
         );
 
-        let connection_id = args.connection_id.unwrap_or_else(|| "default".to_string());
-
-        // Store credentials securely in AWS Secrets Manager (not DynamoDB!)
-        let credentials_secret_ref = if let Some(credentials) = &args.credentials {
-            if !credentials.is_empty() {
-                let secret_arn = self
-                    .aws_service
-                    .store_integration_credentials(
-                        &session.context.tenant_id,
-                        &session.context.user_id,
-                        &args.service_id,
-                        &connection_id,
-                        credentials,
-                    )
-                    .await
-                    .map_err(|e| {
-                        HandlerError::Internal(format!(
-                            "Failed to store credentials in Secrets Manager: {}",
-                            e
-                        ))
-                    })?;
-
-                info!(
-                    "Stored credentials in Secrets Manager for integration {} connection {}",
-                    args.service_id, connection_id
-                );
-
-                Some(secret_arn)
-            } else {
-                None
+        let connection_id = args
+            .connection_id
+            .unwrap_or_else(|| DEFAULT_CONNECTION.to_string());
+        validate_connection_id(&connection_id)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+        validate_ids(&args.service_id, Some(&connection_id))?;
+        for field in args
+            .credentials
+            .iter()
+            .flat_map(|credentials| credentials.keys())
+        {
+            internal_keys::validate_credential_field(field)
+                .map_err(HandlerError::InvalidArguments)?;
+        }
+        // Each connection is its own server instance with its own credentials
+        let instance = instance_id(&args.service_id, Some(&connection_id));
+
+        // Where the connection's metadata lives, checked before anything is stored
+        let key = match args.scope {
+            ConnectionScope::Personal => {
+                refuse_shared(
+                    &*self.aws_service,
+                    session,
+                    &args.service_id,
+                    &connection_id,
+                )
+                .await?;
+                internal_keys::connection_key(
+                    &session.context.tenant_id,
+                    &session.context.user_id,
+                    &args.service_id,
+                    &connection_id,
+                )
+            }
+            ConnectionScope::Organization => {
+                let org_id = member_org_id(session)?;
+                // Connecting again replaces the credentials, as the creator's call
+                if let Some(shared) = org_connection(
+                    &*self.aws_service,
+                    &org_id,
+                    &args.service_id,
+                    &connection_id,
+                )
+                .await?
+                {
+                    check_can_manage(session, &shared)?;
+                }
+                internal_keys::org_connection_key(&org_id, &args.service_id, &connection_id)
             }
-        } else {
-            None
         };
 
-        // Store connection metadata in KV (WITHOUT credentials - only the secret reference)
-        let key = format!(
-            "user-{}-integration-{}-{}",
-            session.context.user_id, args.service_id, connection_id
-        );
+        // Credentials go to the registry's secret store (Secrets Manager), never the KV
+        // table. A member's context is the organization's, so everything stored for an
+        // organization connection is under the organization's namespace.
+        let context_id = session.context.get_context_id();
+        let mut credential_fields = Vec::new();
+        if let Some(credentials) = args.credentials.as_ref().filter(|c| !c.is_empty()) {
+            self.registry
+                .store_credentials(&context_id, &instance, credentials)
+                .await
+                .map_err(|e| {
+                    HandlerError::Internal(format!("Failed to store credentials: {}", e))
+                })?;
+
+            info!(
+                "Stored credentials for integration {} connection {}",
+                args.service_id, connection_id
+            );
+
+            credential_fields = credentials.keys().cloned().collect();
+            credential_fields.sort();
+        }
 
+        // Store connection metadata in KV (WITHOUT credentials - only their field names)
         let connection_data = UserIntegrationConnection {
             service_id: args.service_id.clone(),
             connection_id: connection_id.clone(),
             connection_name: args.connection_name.clone(),
-            credentials_secret_ref,
+            credential_fields,
             settings: args.settings.clone(),
             created_at: chrono::Utc::now().to_rfc3339(),
             user_id: session.context.user_id.clone(),
@@ -313,28 +494,48 @@ impl Handler for IntegrationConnectHandler {
             .kv_set_direct(&key, &value, Some(24 * 30)) // 30 days TTL
             .await
             .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        if args.scope == ConnectionScope::Personal {
+            remove_legacy_connection(
+                &*self.aws_service,
+                session,
+                &args.service_id,
+                &connection_id,
+            )
+            .await?;
+        }
 
         // Connect to the MCP server
         self.registry
-            .connect_server(
-                &session.context.get_context_id(),
-                &args.service_id,
-                args.credentials,
-            )
+            .connect_server(&context_id, &instance, args.credentials)
             .await
-            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+            .map_err(|e| match e {
+                RegistryError::InvalidConfig { .. } => {
+                    HandlerError::InvalidArguments(e.to_string())
+                }
+                e => HandlerError::Internal(e.to_string()),
+            })?;
 
         Ok(serde_json::json!({
             "success": true,
             "connection_id": connection_id,
-            "service_id": args.service_id
+            "service_id": args.service_id,
+            "scope": args.scope
         }))
     }
 
+    /// Organization connections also need the caller to be a member of one
     fn required_permission(&self) -> Option<Permission> {
         Some(Permission::Write)
     }
 
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("integration_list")]
+    }
+
+    fn accepts_idempotency_key(&self) -> bool {
+        true
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "Connect to an MCP server integration",
@@ -347,7 +548,7 @@ impl Handler for IntegrationConnectHandler {
                     },
                     "connection_id": {
                         "type": "string",
-                        "description": "Optional connection ID for multiple connections"
+                        "description": "Optional connection ID (letters, digits, '_' or '-'); each connection runs its own server instance with its own credentials"
                     },
                     "connection_name": {
                         "type": "string",
@@ -360,6 +561,11 @@ impl Handler for IntegrationConnectHandler {
                     "settings": {
                         "type": "object",
                         "description": "Additional settings"
+                    },
+                    "scope": {
+                        "type": "string",
+                        "enum": ["personal", "organization"],
+                        "description": "Who can see and use the connection: the caller alone (default), or every member of the caller's organization"
                     }
                 },
                 "required": ["service_id"]
@@ -375,6 +581,110 @@ struct IntegrationConnectArgs {
     connection_name: Option<String>,
     credentials: Option<std::collections::HashMap<String, String>>,
     settings: Option<std::collections::HashMap<String, String>>,
+    #[serde(default)]
+    scope: ConnectionScope,
+}
+
+/// Who an integration connection belongs to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ConnectionScope {
+    /// The user who connected it; teammates don't see it
+    #[default]
+    Personal,
+    /// Every member of the organization, which lists and uses it; only its creator
+    /// or an organization admin may replace or disconnect it
+    Organization,
+}
+
+/// The caller's organization, which organization connections need
+fn member_org_id(session: &TenantSession) -> Result<String, HandlerError> {
+    session.context.get_org_id().ok_or_else(|| {
+        HandlerError::InvalidArguments(
+            "scope 'organization' needs a session in an organization".to_string(),
+        )
+    })
+}
+
+/// The connection shared with organization `org_id` as `connection_id` of `service_id`
+async fn org_connection(
+    aws_service: &dyn AwsApi,
+    org_id: &str,
+    service_id: &str,
+    connection_id: &str,
+) -> Result<Option<UserIntegrationConnection>, HandlerError> {
+    let key = internal_keys::org_connection_key(org_id, service_id, connection_id);
+    let Some(value) = aws_service
+        .kv_get_direct(&key)
+        .await
+        .map_err(|e| HandlerError::Internal(e.to_string()))?
+    else {
+        return Ok(None);
+    };
+    serde_json::from_str(&value)
+        .map(Some)
+        .map_err(|e| HandlerError::Internal(format!("Malformed connection {}: {}", key, e)))
+}
+
+/// A member's personal connection would be the same server instance as the
+/// organization's connection of the same id, so it can't be made or removed
+/// alongside one
+async fn refuse_shared(
+    aws_service: &dyn AwsApi,
+    session: &TenantSession,
+    service_id: &str,
+    connection_id: &str,
+) -> Result<(), HandlerError> {
+    let Some(org_id) = session.context.get_org_id() else {
+        return Ok(());
+    };
+    match org_connection(aws_service, &org_id, service_id, connection_id).await? {
+        Some(shared) => Err(HandlerError::InvalidArguments(format!(
+            "connection '{}' of {} is shared with the organization by {}; use scope 'organization'",
+            connection_id, service_id, shared.user_id
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Remove the caller's connection of `service_id` kept under its key from before
+/// connections were kept per tenant, if it was made in the caller's tenant
+async fn remove_legacy_connection(
+    aws_service: &dyn AwsApi,
+    session: &TenantSession,
+    service_id: &str,
+    connection_id: &str,
+) -> Result<(), HandlerError> {
+    let key =
+        internal_keys::legacy_connection_key(&session.context.user_id, service_id, connection_id);
+    let Some(value) = aws_service
+        .kv_get_direct(&key)
+        .await
+        .map_err(|e| HandlerError::Internal(e.to_string()))?
+    else {
+        return Ok(());
+    };
+    let in_tenant = serde_json::from_str::<UserIntegrationConnection>(&value)
+        .is_ok_and(|connection| connection.tenant_id == session.context.tenant_id);
+    if in_tenant {
+        aws_service
+            .kv_delete(&key)
+            .await
+            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Only the creator of an organization connection or an organization admin manages it
+fn check_can_manage(
+    session: &TenantSession,
+    connection: &UserIntegrationConnection,
+) -> Result<(), HandlerError> {
+    if connection.user_id == session.context.user_id || session.has_permission(&Permission::Admin) {
+        Ok(())
+    } else {
+        Err(HandlerError::PermissionDenied(Permission::Admin))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -382,23 +692,25 @@ struct UserIntegrationConnection {
     service_id: String,
     connection_id: String,
     connection_name: Option<String>,
-    /// Reference to credentials stored in AWS Secrets Manager (secret ARN)
-    /// Credentials are NOT stored in DynamoDB for security
-    credentials_secret_ref: Option<String>,
+    /// Names of the credential fields kept in the secret store
+    /// Credential values are NOT stored in DynamoDB for security
+    #[serde(default)]
+    credential_fields: Vec<String>,
     /// Non-sensitive settings can still be stored directly
     settings: Option<std::collections::HashMap<String, String>>,
     created_at: String,
+    /// Who connected it, for an organization connection its creator
     user_id: String,
     tenant_id: String,
 }
 
 pub struct IntegrationListHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
     registry: Arc<MCPServerRegistry>,
 }
 
 impl IntegrationListHandler {
-    pub fn new(aws_service: Arc<AwsService>, registry: Arc<MCPServerRegistry>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>, registry: Arc<MCPServerRegistry>) -> Self {
         Self {
             aws_service,
             registry,
@@ -425,17 +737,53 @@ impl Handler for IntegrationListHandler {
             .await
             .map_err(|e| HandlerError::Internal(e.to_string()))?;
 
-        // Get user connections
-        let prefix = format!("user-{}-integration-", session.context.user_id);
-        let connections = self
-            .aws_service
-            .kv_list(&prefix)
-            .await
-            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        // Get user connections, including those kept from before connections were kept
+        // per tenant
+        let mut connections = Vec::new();
+        for prefix in [
+            internal_keys::connection_prefix(&session.context.tenant_id, &session.context.user_id),
+            internal_keys::legacy_connection_prefix(&session.context.user_id),
+        ] {
+            connections.extend(
+                self.aws_service
+                    .kv_list(&prefix)
+                    .await
+                    .map_err(|e| HandlerError::Internal(e.to_string()))?,
+            );
+        }
+
+        // And the ones shared with the caller's organization, with who connected them
+        let mut organization_connections = Vec::new();
+        if let Some(org_id) = session.context.get_org_id() {
+            let keys = self
+                .aws_service
+                .kv_list(&internal_keys::org_connection_prefix(&org_id))
+                .await
+                .map_err(|e| HandlerError::Internal(e.to_string()))?;
+            for key in keys {
+                let value = self
+                    .aws_service
+                    .kv_get_direct(&key)
+                    .await
+                    .map_err(|e| HandlerError::Internal(e.to_string()))?;
+                let Some(value) = value else { continue };
+                match serde_json::from_str::<UserIntegrationConnection>(&value) {
+                    Ok(connection) => organization_connections.push(serde_json::json!({
+                        "service_id": connection.service_id,
+                        "connection_id": connection.connection_id,
+                        "connection_name": connection.connection_name,
+                        "created_by": connection.user_id,
+                        "created_at": connection.created_at
+                    })),
+                    Err(e) => debug!("Skipping malformed connection {}: {}", key, e),
+                }
+            }
+        }
 
         Ok(serde_json::json!({
             "servers": servers,
-            "user_connections": connections
+            "user_connections": connections,
+            "organization_connections": organization_connections
         }))
     }
 
@@ -443,6 +791,11 @@ impl Handler for IntegrationListHandler {
         Some(Permission::Read)
     }
 
+    fn cache_ttl(&self) -> Option<Duration> {
+        // Short, as connections also change state on their own
+        Some(Duration::from_secs(10))
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "List available MCP server integrations",
@@ -455,12 +808,12 @@ impl Handler for IntegrationListHandler {
 }
 
 pub struct IntegrationDisconnectHandler {
-    aws_service: Arc<AwsService>,
+    aws_service: Arc<dyn AwsApi>,
     registry: Arc<MCPServerRegistry>,
 }
 
 impl IntegrationDisconnectHandler {
-    pub fn new(aws_service: Arc<AwsService>, registry: Arc<MCPServerRegistry>) -> Self {
+    pub fn new(aws_service: Arc<dyn AwsApi>, registry: Arc<MCPServerRegistry>) -> Self {
         Self {
             aws_service,
             registry,
@@ -483,49 +836,83 @@ impl Handler for IntegrationDisconnectHandler {
             args.service_id, session.context.user_id, session.context.tenant_id
         );
 
-        // Disconnect from the MCP server
+        let connection_id = args
+            .connection_id
+            .unwrap_or_else(|| DEFAULT_CONNECTION.to_string());
+        validate_ids(&args.service_id, Some(&connection_id))?;
+        let instance = instance_id(&args.service_id, Some(&connection_id));
+
+        let key = match args.scope {
+            ConnectionScope::Personal => {
+                refuse_shared(
+                    &*self.aws_service,
+                    session,
+                    &args.service_id,
+                    &connection_id,
+                )
+                .await?;
+                internal_keys::connection_key(
+                    &session.context.tenant_id,
+                    &session.context.user_id,
+                    &args.service_id,
+                    &connection_id,
+                )
+            }
+            ConnectionScope::Organization => {
+                let org_id = member_org_id(session)?;
+                let shared = org_connection(
+                    &*self.aws_service,
+                    &org_id,
+                    &args.service_id,
+                    &connection_id,
+                )
+                .await?
+                .ok_or_else(|| {
+                    HandlerError::InvalidArguments(format!(
+                        "no connection '{}' of {} is shared with the organization",
+                        connection_id, args.service_id
+                    ))
+                })?;
+                check_can_manage(session, &shared)?;
+                internal_keys::org_connection_key(&org_id, &args.service_id, &connection_id)
+            }
+        };
+
+        // Disconnect this connection only; the service's others keep running
         self.registry
-            .disconnect_server(&session.context.get_context_id(), &args.service_id)
+            .disconnect_server(&session.context.get_context_id(), &instance)
             .await
             .map_err(|e| HandlerError::Internal(e.to_string()))?;
 
-        let connection_id = args.connection_id.unwrap_or_else(|| "default".to_string());
-
-        // Delete credentials from AWS Secrets Manager
-        // Using force_delete=false to allow 7-day recovery window
+        // Delete the connection's credentials from the secret store
         if let Err(e) = self
-            .aws_service
-            .delete_integration_credentials(
-                &session.context.tenant_id,
-                &session.context.user_id,
-                &args.service_id,
-                &connection_id,
-                false, // Use recovery window, not force delete
-            )
+            .registry
+            .delete_credentials(&session.context.get_context_id(), &instance)
             .await
         {
-            // Log but don't fail - the secret may not exist
-            debug!(
-                "Could not delete credentials from Secrets Manager: {} (may not exist)",
-                e
-            );
+            // Log but don't fail - the metadata below should still be removed
+            debug!("Could not delete credentials: {}", e);
         } else {
             info!(
-                "Deleted credentials from Secrets Manager for integration {} connection {}",
+                "Deleted credentials for integration {} connection {}",
                 args.service_id, connection_id
             );
         }
 
-        // Remove user connection metadata from KV
-        let key = format!(
-            "user-{}-integration-{}-{}",
-            session.context.user_id, args.service_id, connection_id
-        );
-
+        // Remove the connection metadata from KV
         self.aws_service
             .kv_delete(&key)
             .await
             .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        if args.scope == ConnectionScope::Personal {
+            remove_legacy_connection(
+                &*self.aws_service,
+                session,
+                &args.service_id,
+                &connection_id,
+            )
+            .await?;
+        }
 
         Ok(serde_json::json!({
             "success": true,
@@ -538,6 +925,10 @@ impl Handler for IntegrationDisconnectHandler {
         Some(Permission::Write)
     }
 
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("integration_list")]
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "Disconnect from an MCP server integration",
@@ -551,6 +942,11 @@ impl Handler for IntegrationDisconnectHandler {
                     "connection_id": {
                         "type": "string",
                         "description": "Optional connection ID"
+                    },
+                    "scope": {
+                        "type": "string",
+                        "enum": ["personal", "organization"],
+                        "description": "The scope it was connected with (default: personal); only its creator or an organization admin may disconnect an organization connection"
                     }
                 },
                 "required": ["service_id"]
@@ -563,6 +959,363 @@ impl Handler for IntegrationDisconnectHandler {
 struct IntegrationDisconnectArgs {
     service_id: String,
     connection_id: Option<String>,
+    #[serde(default)]
+    scope: ConnectionScope,
+}
+
+pub struct IntegrationUnregisterHandler {
+    registry: Arc<MCPServerRegistry>,
+}
+
+impl IntegrationUnregisterHandler {
+    pub fn new(registry: Arc<MCPServerRegistry>) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl Handler for IntegrationUnregisterHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: IntegrationUnregisterArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+
+        info!(
+            "Unregistering integration {} for tenant {}",
+            args.service_id, session.context.tenant_id
+        );
+
+        self.registry
+            .unregister_server(&session.context.get_context_id(), &args.service_id)
+            .await
+            .map_err(|e| match e {
+                RegistryError::ServerNotFound(_) => HandlerError::InvalidArguments(e.to_string()),
+                e => HandlerError::Internal(e.to_string()),
+            })?;
+
+        Ok(serde_json::json!({
+            "success": true,
+            "service_id": args.service_id
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("integration_list")]
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "Remove an MCP server integration, disconnecting it and deleting its stored configuration and credentials",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "service_id": {
+                        "type": "string",
+                        "description": "ID of the service to remove"
+                    }
+                },
+                "required": ["service_id"]
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IntegrationUnregisterArgs {
+    service_id: String,
+}
+
+/// Where `integration_export` stores the document unless given a key
+const EXPORT_KEY_PREFIX: &str = "integration-exports";
+
+pub struct IntegrationExportHandler {
+    aws_service: Arc<dyn AwsApi>,
+    registry: Arc<MCPServerRegistry>,
+}
+
+impl IntegrationExportHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>, registry: Arc<MCPServerRegistry>) -> Self {
+        Self {
+            aws_service,
+            registry,
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for IntegrationExportHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: IntegrationExportArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+
+        let export = self
+            .registry
+            .export_servers(&session.context.get_context_id())
+            .await
+            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        let key = args.key.unwrap_or_else(|| {
+            format!(
+                "{}/{}.json",
+                EXPORT_KEY_PREFIX,
+                export.exported_at.format("%Y%m%dT%H%M%SZ")
+            )
+        });
+        let document = serde_json::to_vec_pretty(&export)
+            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        self.aws_service
+            .artifacts_put(session, &key, &document, "application/json", None)
+            .await
+            .map_err(|e| HandlerError::Internal(format!("Failed to store the export: {}", e)))?;
+
+        info!(
+            "Exported {} integrations of tenant {} to {}",
+            export.servers.len(),
+            session.context.tenant_id,
+            key
+        );
+        Ok(serde_json::json!({
+            "key": key,
+            "count": export.servers.len(),
+            "export": export
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "Export every registered integration's configuration as a JSON artifact, with credentials replaced by ${credential:name} placeholders, for integration_import in another environment",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "key": {
+                        "type": "string",
+                        "description": "Artifact key to store the export under (default integration-exports/<timestamp>.json)"
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IntegrationExportArgs {
+    key: Option<String>,
+}
+
+pub struct IntegrationImportHandler {
+    aws_service: Arc<dyn AwsApi>,
+    registry: Arc<MCPServerRegistry>,
+}
+
+impl IntegrationImportHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>, registry: Arc<MCPServerRegistry>) -> Self {
+        Self {
+            aws_service,
+            registry,
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for IntegrationImportHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: IntegrationImportArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+
+        let export: IntegrationExport = match (args.document, args.key) {
+            (Some(document), None) => serde_json::from_value(document)
+                .map_err(|e| HandlerError::InvalidArguments(format!("Invalid export: {}", e)))?,
+            (None, Some(key)) => {
+                let document = self
+                    .aws_service
+                    .artifacts_get(session, &key)
+                    .await
+                    .map_err(|e| HandlerError::Internal(e.to_string()))?
+                    .ok_or_else(|| {
+                        HandlerError::InvalidArguments(format!("No artifact {}", key))
+                    })?;
+                serde_json::from_slice(&document)
+                    .map_err(|e| HandlerError::InvalidArguments(format!("Invalid export: {}", e)))?
+            }
+            _ => {
+                return Err(HandlerError::InvalidArguments(
+                    "Give either key or document".to_string(),
+                ))
+            }
+        };
+
+        let report = self
+            .registry
+            .import_servers(&session.context.get_context_id(), export, args.on_conflict)
+            .await
+            .map_err(|e| match e {
+                RegistryError::InvalidConfig { .. } => {
+                    HandlerError::InvalidArguments(e.to_string())
+                }
+                e => HandlerError::Internal(e.to_string()),
+            })?;
+
+        serde_json::to_value(report).map_err(|e| HandlerError::Internal(e.to_string()))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("integration_list")]
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "Register the integrations of an integration_export document and report which credentials still have to be supplied with integration_connect",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "key": {
+                        "type": "string",
+                        "description": "Artifact key of the export"
+                    },
+                    "document": {
+                        "type": "object",
+                        "description": "The export itself, instead of an artifact key"
+                    },
+                    "on_conflict": {
+                        "type": "string",
+                        "enum": ["skip", "overwrite"],
+                        "description": "What to do with integrations already registered under the same id (default skip)"
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IntegrationImportArgs {
+    key: Option<String>,
+    document: Option<Value>,
+    #[serde(default)]
+    on_conflict: OnConflict,
+}
+
+pub struct IntegrationUpdateHandler {
+    registry: Arc<MCPServerRegistry>,
+}
+
+impl IntegrationUpdateHandler {
+    pub fn new(registry: Arc<MCPServerRegistry>) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl Handler for IntegrationUpdateHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: IntegrationUpdateArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+
+        info!(
+            "Updating integration {} for tenant {}",
+            args.service_id, session.context.tenant_id
+        );
+
+        let applied = self
+            .registry
+            .update_server(
+                &session.context.get_context_id(),
+                &args.service_id,
+                args.update,
+            )
+            .await
+            .map_err(|e| match e {
+                RegistryError::ServerNotFound(_) | RegistryError::InvalidConfig { .. } => {
+                    HandlerError::InvalidArguments(e.to_string())
+                }
+                e => HandlerError::Internal(e.to_string()),
+            })?;
+
+        Ok(serde_json::json!({
+            "success": true,
+            "service_id": args.service_id,
+            "applied": applied
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        vec![CacheInvalidation::Tool("integration_list")]
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "Change a registered MCP server integration's configuration, keeping its credentials. Connected instances apply health_check_interval_secs, auto_reconnect, tool_timeout_secs and restart_policy in place and are restarted for env, image, tag, command or args; 'applied' reports which happened (stored, applied_in_place or reconnected)",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "service_id": {
+                        "type": "string",
+                        "description": "ID of the service to update"
+                    },
+                    "name": { "type": "string" },
+                    "description": { "type": "string" },
+                    "health_check_interval_secs": { "type": "integer", "minimum": 0 },
+                    "auto_reconnect": { "type": "boolean" },
+                    "tool_timeout_secs": { "type": "integer", "minimum": 1 },
+                    "restart_policy": {
+                        "type": "object",
+                        "description": "Replaces the restart policy; see integration_register"
+                    },
+                    "env": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Replaces the whole environment"
+                    },
+                    "image": { "type": "string", "description": "Docker deployments only" },
+                    "tag": { "type": "string", "description": "Docker deployments only" },
+                    "command": { "type": "string", "description": "Process deployments only" },
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Process deployments only"
+                    }
+                },
+                "required": ["service_id"]
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IntegrationUpdateArgs {
+    service_id: String,
+    #[serde(flatten)]
+    update: MCPServerUpdate,
 }
 
 pub struct IntegrationTestHandler {
@@ -605,11 +1358,27 @@ impl Handler for IntegrationTestHandler {
             })?;
 
         let is_connected = server_info.status == "Connected";
+        let logs = self
+            .registry
+            .server_logs(&session.context.get_context_id(), &args.service_id)
+            .await
+            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+        let skip = logs.len().saturating_sub(TEST_LOG_LINES);
+        let oauth = self
+            .registry
+            .token_status(&session.context.get_context_id(), &args.service_id)
+            .await
+            .map_err(|e| HandlerError::Internal(e.to_string()))?;
 
         Ok(serde_json::json!({
             "success": is_connected,
             "status": server_info.status,
             "tool_count": server_info.tool_count,
+            "last_error": server_info.last_error,
+            "container_status": server_info.container_status,
+            "metrics": server_info.metrics,
+            "oauth": oauth,
+            "recent_logs": logs[skip..].iter().map(|log| &log.line).collect::<Vec<_>>(),
             "message": if is_connected {
                 "Integration is connected and healthy"
             } else {
@@ -643,3 +1412,90 @@ impl Handler for IntegrationTestHandler {
 struct IntegrationTestArgs {
     service_id: String,
 }
+
+/// Log lines included in an `integration_test` response
+const TEST_LOG_LINES: usize = 20;
+
+pub struct IntegrationLogsHandler {
+    registry: Arc<MCPServerRegistry>,
+}
+
+impl IntegrationLogsHandler {
+    pub fn new(registry: Arc<MCPServerRegistry>) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl Handler for IntegrationLogsHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: IntegrationLogsArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+
+        debug!(
+            "Reading logs of integration {} for tenant {}",
+            args.service_id, session.context.tenant_id
+        );
+
+        let context_id = session.context.get_context_id();
+        let logs = self
+            .registry
+            .server_logs(&context_id, &args.service_id)
+            .await
+            .map_err(|e| match e {
+                RegistryError::ServerNotFound(_) => HandlerError::InvalidArguments(e.to_string()),
+                e => HandlerError::Internal(e.to_string()),
+            })?;
+        let server = self
+            .registry
+            .list_servers(&context_id)
+            .await
+            .map_err(|e| HandlerError::Internal(e.to_string()))?
+            .into_iter()
+            .find(|s| s.id == args.service_id);
+
+        let lines = args.lines.unwrap_or(LOG_BUFFER_LINES).min(LOG_BUFFER_LINES);
+        let skip = logs.len().saturating_sub(lines);
+
+        Ok(serde_json::json!({
+            "service_id": args.service_id,
+            "status": server.as_ref().map(|s| s.status.clone()),
+            "container_status": server.and_then(|s| s.container_status),
+            "logs": logs[skip..]
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Read)
+    }
+
+    fn tool_schema(&self) -> Value {
+        serde_json::json!({
+            "description": "Show the recent output of an MCP server integration (a process's stderr, or a container's output), each line with its timestamp and stream",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "service_id": {
+                        "type": "string",
+                        "description": "ID of the service"
+                    },
+                    "lines": {
+                        "type": "integer",
+                        "description": format!("Number of trailing lines (default and maximum {})", LOG_BUFFER_LINES)
+                    }
+                },
+                "required": ["service_id"]
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct IntegrationLogsArgs {
+    service_id: String,
+    lines: Option<usize>,
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use super::{DockerConfig, IntegrationConfig};
+use crate::aws::AwsApi;
+use crate::handlers::HandlerError;
+use crate::registry::MCPServerType;
+
+/// Where to load the catalog from instead of the bundled one: `s3://bucket/key`, or a key
+/// in the artifacts bucket
+pub const CATALOG_ENV: &str = "AGENT_MESH_INTEGRATION_CATALOG";
+
+/// Known-good servers shipped with the binary
+const BUNDLED_CATALOG: &str = include_str!("catalog.json");
+
+/// A known-good integration: its `integration_register` arguments, with the catalog id
+/// as the default `service_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogTemplate {
+    #[serde(flatten)]
+    pub integration: IntegrationConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    server_type: Option<MCPServerType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    args: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    docker_config: Option<DockerConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    env: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_timeout_secs: Option<u64>,
+}
+
+/// The integrations users can enable by name with `integration_register`'s `template`
+#[derive(Debug, Clone, Default)]
+pub struct IntegrationCatalog {
+    templates: Vec<CatalogTemplate>,
+}
+
+impl IntegrationCatalog {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            templates: serde_json::from_str(json)?,
+        })
+    }
+
+    /// The catalog shipped with the binary
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_CATALOG).unwrap_or_else(|e| {
+            warn!("Bundled integration catalog is invalid: {}", e);
+            Self::default()
+        })
+    }
+
+    /// The catalog at `location` (see [`CATALOG_ENV`]) if given, else the bundled one. A
+    /// catalog that can't be loaded is logged and replaced by the bundled one rather than
+    /// failing startup.
+    pub async fn load(aws_service: &dyn AwsApi, location: Option<&str>) -> Self {
+        let Some(location) = location else {
+            return Self::bundled();
+        };
+        let (bucket, key) = match location.strip_prefix("s3://") {
+            Some(path) => path.split_once('/').unwrap_or((path, "")),
+            None => (aws_service.artifacts_bucket(), location),
+        };
+
+        let loaded = match aws_service.s3_get(bucket, key).await {
+            Ok(Some(body)) => String::from_utf8(body)
+                .map_err(|e| e.to_string())
+                .and_then(|json| Self::from_json(&json).map_err(|e| e.to_string())),
+            Ok(None) => Err("no such object".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match loaded {
+            Ok(catalog) => {
+                info!(
+                    "Loaded {} integration templates from {}",
+                    catalog.templates.len(),
+                    location
+                );
+                catalog
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to load the integration catalog from {}, using the bundled one: {}",
+                    location, e
+                );
+                Self::bundled()
+            }
+        }
+    }
+
+    pub fn templates(&self) -> &[CatalogTemplate] {
+        &self.templates
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CatalogTemplate> {
+        self.templates
+            .iter()
+            .find(|template| template.integration.id == id)
+    }
+
+    /// `integration_register` arguments naming a `template`, resolved to the template's
+    /// arguments with the given ones merged over them: the caller's fields win, and
+    /// objects such as `env` and `docker_config` are merged key by key. Arguments
+    /// without a template are returned as they are.
+    pub fn resolve(&self, mut arguments: Value) -> Result<Value, HandlerError> {
+        let Some(template_id) = arguments
+            .as_object_mut()
+            .and_then(|fields| fields.remove("template"))
+        else {
+            return Ok(arguments);
+        };
+        let template_id = template_id.as_str().ok_or_else(|| {
+            HandlerError::InvalidArguments("template must be a catalog id".to_string())
+        })?;
+        let template = self.get(template_id).ok_or_else(|| {
+            let ids: Vec<&str> = self
+                .templates
+                .iter()
+                .map(|template| template.integration.id.as_str())
+                .collect();
+            HandlerError::InvalidArguments(format!(
+                "Unknown integration template {:?}; the catalog has: {}",
+                template_id,
+                ids.join(", ")
+            ))
+        })?;
+
+        let mut resolved =
+            serde_json::to_value(template).map_err(|e| HandlerError::Internal(e.to_string()))?;
+        if let Some(fields) = resolved.as_object_mut() {
+            if let Some(id) = fields.remove("id") {
+                fields.insert("service_id".to_string(), id);
+            }
+        }
+        merge(&mut resolved, arguments);
+        Ok(resolved)
+    }
+}
+
+/// Arguments merged key by key rather than replaced
+const MERGED_FIELDS: [&str; 2] = ["env", "docker_config"];
+
+/// Merge the fields of `overrides` into `base`
+fn merge(base: &mut Value, overrides: Value) {
+    let (Value::Object(base), Value::Object(overrides)) = (base, overrides) else {
+        return;
+    };
+    for (key, value) in overrides {
+        if let (Some(Value::Object(existing)), Value::Object(value)) = (base.get_mut(&key), &value)
+        {
+            if MERGED_FIELDS.contains(&key.as_str()) {
+                existing.extend(value.clone());
+                continue;
+            }
+        }
+        base.insert(key, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_catalog_parses() {
+        let catalog = IntegrationCatalog::from_json(BUNDLED_CATALOG).unwrap();
+        for id in ["filesystem", "fetch", "github"] {
+            assert!(catalog.get(id).is_some(), "{} missing", id);
+        }
+    }
+}
@@ -0,0 +1,860 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, Semaphore};
+use tokio::task::AbortHandle;
+use tracing::{info, warn};
+
+use crate::aws::{AwsApi, AwsError};
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+pub mod callbacks;
+
+use callbacks::{CallbackDelivery, JobCallback, JobCallbacks};
+
+/// Every job of a namespace is kept in one KV item under this key, so each change to it
+/// is a single conditional write
+pub const JOBS_KEY: &str = "_jobs";
+
+/// Finished jobs kept per namespace; the oldest are dropped beyond this
+pub const MAX_FINISHED_JOBS: usize = 50;
+
+/// Jobs listed when `jobs_list` doesn't say
+pub const DEFAULT_LIST_LIMIT: usize = 20;
+
+/// Conditional writes tried before a busy jobs item is reported as a conflict
+const UPDATE_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Waiting for one of the tenant's job slots
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// The server stopped, or its process died, while the job was queued or running
+    Interrupted,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobState::Queued | JobState::Running)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobProgress {
+    /// Units of work done so far, e.g. events exported
+    pub completed: u64,
+    /// Units of work in all, when the job knows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub job_id: String,
+    /// The tool that started the job, e.g. `events_export`
+    pub job_type: String,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub state: JobState,
+    #[serde(default)]
+    pub progress: JobProgress,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Last state change, progress write or heartbeat
+    pub updated_at: DateTime<Utc>,
+    /// The server process the job runs in
+    pub instance_id: String,
+    /// Where to report that the job finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback: Option<JobCallback>,
+    /// Set once the callback has been fired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_delivery: Option<CallbackDelivery>,
+    /// How far the work got, for a later job to resume from (see
+    /// [`JobContext::checkpoint`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<Value>,
+}
+
+/// Everything stored for one namespace, oldest job first
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JobList {
+    #[serde(default)]
+    jobs: Vec<JobRecord>,
+}
+
+impl JobList {
+    /// Store `record`, keeping what's known of its callback's delivery
+    fn upsert(&mut self, mut record: JobRecord) {
+        match self.jobs.iter_mut().find(|job| job.job_id == record.job_id) {
+            Some(job) => {
+                if record.callback_delivery.is_none() {
+                    record.callback_delivery = job.callback_delivery.take();
+                }
+                *job = record;
+            }
+            None => self.jobs.push(record),
+        }
+        let finished = self
+            .jobs
+            .iter()
+            .filter(|job| job.state.is_finished())
+            .count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED_JOBS);
+        self.jobs.retain(|job| {
+            let drop = excess > 0 && job.state.is_finished();
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+    }
+}
+
+/// How a [`JobManager`] runs jobs
+#[derive(Debug, Clone)]
+pub struct JobOptions {
+    /// Jobs of one tenant running at once; more wait as `queued`
+    pub max_per_tenant: usize,
+    /// How often a running job's record is rewritten. One not rewritten for three
+    /// heartbeats by another process is taken to have died with it.
+    pub heartbeat: Duration,
+    /// How long shutdown waits for running jobs before interrupting them
+    pub shutdown_grace: Duration,
+    /// Let webhook callbacks reach loopback hosts, for local development
+    pub allow_loopback_webhooks: bool,
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        Self {
+            max_per_tenant: 2,
+            heartbeat: Duration::from_secs(15),
+            shutdown_grace: Duration::from_secs(5),
+            allow_loopback_webhooks: false,
+        }
+    }
+}
+
+/// A job running in this process
+struct LiveJob {
+    record: Arc<Mutex<JobRecord>>,
+    /// Namespace of the session that started it; only that namespace sees it
+    namespace: String,
+    session: TenantSession,
+    abort: Option<AbortHandle>,
+}
+
+/// Handed to a job's work so it can report how far it got
+#[derive(Clone)]
+pub struct JobContext {
+    record: Arc<Mutex<JobRecord>>,
+}
+
+impl JobContext {
+    pub fn job_id(&self) -> String {
+        self.record.lock().unwrap().job_id.clone()
+    }
+
+    /// Record progress; it's persisted with the next heartbeat, and `jobs_status` in
+    /// this process sees it at once
+    pub fn progress(&self, completed: u64, total: Option<u64>, message: Option<String>) {
+        let mut record = self.record.lock().unwrap();
+        record.progress = JobProgress {
+            completed,
+            total,
+            message,
+        };
+    }
+
+    /// Record how far the work got, in whatever form the job resumes from. Like
+    /// progress, it's persisted with the next heartbeat and when the job stops, however
+    /// it stops; see [`JobManager::resume_point`].
+    pub fn checkpoint(&self, checkpoint: Value) {
+        self.record.lock().unwrap().checkpoint = Some(checkpoint);
+    }
+}
+
+/// Runs long tool calls as background tasks that clients poll with `jobs_status`. Job
+/// records are kept in the caller's KV namespace (see [`JOBS_KEY`]), so they outlive
+/// the process; a job whose process is gone reads as `interrupted`.
+pub struct JobManager {
+    store: JobStore,
+    callbacks: JobCallbacks,
+    options: JobOptions,
+    /// Identifies this process in the records of the jobs it runs
+    instance_id: String,
+    live: Mutex<HashMap<String, LiveJob>>,
+    /// Job slots per tenant
+    slots: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl JobManager {
+    pub fn new(aws_service: Arc<dyn AwsApi>, options: JobOptions) -> Self {
+        let store = JobStore {
+            aws_service: aws_service.clone(),
+        };
+        Self {
+            callbacks: JobCallbacks::new(
+                store.clone(),
+                aws_service,
+                options.allow_loopback_webhooks,
+            ),
+            store,
+            options,
+            instance_id: uuid::Uuid::new_v4().to_string(),
+            live: Mutex::new(HashMap::new()),
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The `callback` argument of a job-starting tool, if given and valid
+    pub fn callback_from(&self, arguments: &Value) -> Result<Option<JobCallback>, HandlerError> {
+        JobCallback::from_arguments(arguments, self.options.allow_loopback_webhooks)
+    }
+
+    /// Jobs running or queued in this process
+    pub fn live_count(&self) -> usize {
+        self.live.lock().unwrap().len()
+    }
+
+    fn tenant_slots(&self, tenant_id: &str) -> Arc<Semaphore> {
+        self.slots
+            .lock()
+            .unwrap()
+            .entry(tenant_id.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.options.max_per_tenant)))
+            .clone()
+    }
+
+    /// Start `work` as a `job_type` job for the session and return its record, queued.
+    /// The work's result or error ends up in the record, and `callback` is fired once
+    /// the job finishes.
+    pub async fn spawn<F, Fut>(
+        self: &Arc<Self>,
+        session: &TenantSession,
+        job_type: &str,
+        callback: Option<JobCallback>,
+        work: F,
+    ) -> Result<JobRecord, HandlerError>
+    where
+        F: FnOnce(JobContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Value, HandlerError>> + Send + 'static,
+    {
+        let now = Utc::now();
+        let record = JobRecord {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            job_type: job_type.to_string(),
+            tenant_id: session.context.tenant_id.clone(),
+            user_id: session.context.user_id.clone(),
+            state: JobState::Queued,
+            progress: JobProgress::default(),
+            result: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            instance_id: self.instance_id.clone(),
+            callback,
+            callback_delivery: None,
+            checkpoint: None,
+        };
+        let job_id = record.job_id.clone();
+        let shared = Arc::new(Mutex::new(record.clone()));
+        let slots = self.tenant_slots(&session.context.tenant_id);
+        let manager = self.clone();
+        let job_session = session.clone();
+        let context = JobContext {
+            record: shared.clone(),
+        };
+        let (start, started) = oneshot::channel::<()>();
+
+        // The job is live before its record is stored, so no listing can take the
+        // stored record for an orphan; its work waits until the record is stored. The
+        // task can't remove itself from `live` before it's been added.
+        {
+            let mut live = self.live.lock().unwrap();
+            let handle = tokio::spawn(async move {
+                if started.await.is_err() {
+                    return;
+                }
+                let _slot = slots.acquire_owned().await;
+                manager
+                    .transition(&job_session, &context.record, |record| {
+                        record.state = JobState::Running;
+                    })
+                    .await;
+                let outcome = manager
+                    .with_heartbeat(&job_session, &context.record, work(context.clone()))
+                    .await;
+                let finished = manager
+                    .transition(&job_session, &context.record, |record| match outcome {
+                        Ok(result) => {
+                            record.state = JobState::Succeeded;
+                            record.result = Some(result);
+                        }
+                        Err(error) => {
+                            record.state = JobState::Failed;
+                            record.error = Some(error.to_string());
+                        }
+                    })
+                    .await;
+                let job_id = context.job_id();
+                manager.live.lock().unwrap().remove(&job_id);
+                if finished {
+                    let record = context.record.lock().unwrap().clone();
+                    info!(
+                        "Job {} ({}) for tenant {} {:?}",
+                        record.job_id, record.job_type, record.tenant_id, record.state
+                    );
+                    manager.fire_callback_logged(&job_session, &job_id).await;
+                }
+            });
+            live.insert(
+                job_id.clone(),
+                LiveJob {
+                    record: shared,
+                    namespace: session.context.get_namespace_prefix(),
+                    session: session.clone(),
+                    abort: Some(handle.abort_handle()),
+                },
+            );
+        }
+
+        // A job cancelled meanwhile has stored its own record, which is kept
+        let stored = self
+            .store
+            .update(session, |list| {
+                if !list.jobs.iter().any(|job| job.job_id == job_id) {
+                    list.upsert(record.clone());
+                }
+            })
+            .await;
+        if let Err(e) = stored {
+            if let Some(job) = self.live.lock().unwrap().remove(&job_id) {
+                if let Some(abort) = &job.abort {
+                    abort.abort();
+                }
+            }
+            return Err(e);
+        }
+        // Gone when the job was cancelled before it could start
+        let _ = start.send(());
+        Ok(record)
+    }
+
+    /// Run `work`, rewriting the job's record every heartbeat until it's done
+    async fn with_heartbeat(
+        &self,
+        session: &TenantSession,
+        record: &Arc<Mutex<JobRecord>>,
+        work: impl Future<Output = Result<Value, HandlerError>>,
+    ) -> Result<Value, HandlerError> {
+        tokio::pin!(work);
+        let mut ticker = tokio::time::interval(self.options.heartbeat);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // The first tick completes immediately; the record was just written
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                outcome = &mut work => return outcome,
+                _ = ticker.tick() => {
+                    self.transition(session, record, |_| {}).await;
+                }
+            }
+        }
+    }
+
+    /// Apply `change` to a live job's record unless it already finished (e.g. it was
+    /// cancelled meanwhile), and persist it. Returns whether the change was made.
+    /// Failures to persist are only logged: the job goes on either way.
+    async fn transition(
+        &self,
+        session: &TenantSession,
+        record: &Arc<Mutex<JobRecord>>,
+        change: impl FnOnce(&mut JobRecord),
+    ) -> bool {
+        let snapshot = {
+            let mut record = record.lock().unwrap();
+            if record.state.is_finished() {
+                return false;
+            }
+            change(&mut record);
+            record.updated_at = Utc::now();
+            record.clone()
+        };
+        if let Err(e) = self.store.persist(session, snapshot.clone()).await {
+            warn!("Failed to persist job {}: {}", snapshot.job_id, e);
+        }
+        true
+    }
+
+    /// Fire the finished job's callback, unless it has none or it was already fired.
+    /// Returns whether this call fired it.
+    pub async fn fire_callback(
+        &self,
+        session: &TenantSession,
+        job_id: &str,
+    ) -> Result<bool, HandlerError> {
+        self.callbacks.fire(session, job_id).await
+    }
+
+    async fn fire_callback_logged(&self, session: &TenantSession, job_id: &str) {
+        if let Err(e) = self.fire_callback(session, job_id).await {
+            warn!("Failed to fire the callback of job {}: {}", job_id, e);
+        }
+    }
+
+    /// Fire the callback without waiting for it, for calls that shouldn't wait on a
+    /// webhook
+    fn fire_callback_in_background(&self, session: &TenantSession, job_id: &str) {
+        let callbacks = self.callbacks.clone();
+        let session = session.clone();
+        let job_id = job_id.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = callbacks.fire(&session, &job_id).await {
+                warn!("Failed to fire the callback of job {}: {}", job_id, e);
+            }
+        });
+    }
+
+    /// The job's record as the session may see it: live from this process, else as
+    /// stored, with jobs whose process is gone marked `interrupted`
+    pub async fn status(
+        &self,
+        session: &TenantSession,
+        job_id: &str,
+    ) -> Result<JobRecord, HandlerError> {
+        if let Some(record) = self.live_record(session, job_id) {
+            return Ok(record);
+        }
+        self.list(session)
+            .await?
+            .into_iter()
+            .find(|job| job.job_id == job_id)
+            .ok_or_else(|| AwsError::NotFound(format!("job {:?}", job_id)).into())
+    }
+
+    fn live_record(&self, session: &TenantSession, job_id: &str) -> Option<JobRecord> {
+        let live = self.live.lock().unwrap();
+        let job = live.get(job_id)?;
+        (job.namespace == session.context.get_namespace_prefix())
+            .then(|| job.record.lock().unwrap().clone())
+    }
+
+    /// The session's jobs, newest first
+    pub async fn list(&self, session: &TenantSession) -> Result<Vec<JobRecord>, HandlerError> {
+        let (_, stored) = self.store.read(session).await?;
+        let mut interrupted = Vec::new();
+        let mut jobs: Vec<JobRecord> = stored
+            .jobs
+            .into_iter()
+            .map(|job| match self.live_record(session, &job.job_id) {
+                Some(live) => live,
+                None if self.orphaned(&job) => {
+                    let job = interrupt(job, "the server stopped while the job was running");
+                    interrupted.push(job.clone());
+                    job
+                }
+                None => job,
+            })
+            .collect();
+        if !interrupted.is_empty() {
+            self.store
+                .update(session, |list| {
+                    for job in &interrupted {
+                        // Only if no one else took it over meanwhile
+                        if list.jobs.iter().any(|stored| {
+                            stored.job_id == job.job_id && !stored.state.is_finished()
+                        }) {
+                            list.upsert(job.clone());
+                        }
+                    }
+                })
+                .await?;
+            for job in &interrupted {
+                self.fire_callback_in_background(session, &job.job_id);
+            }
+        }
+        jobs.reverse();
+        Ok(jobs)
+    }
+
+    /// The checkpoint of the session's `job_type` job `job_id`, for a new job to carry on
+    /// from: `None` if it stopped before recording one. Only jobs that stopped without
+    /// succeeding (failed, cancelled or interrupted) can be resumed.
+    pub async fn resume_point(
+        &self,
+        session: &TenantSession,
+        job_id: &str,
+        job_type: &str,
+    ) -> Result<Option<Value>, HandlerError> {
+        let job = self.status(session, job_id).await?;
+        if job.job_type != job_type {
+            return Err(HandlerError::InvalidArguments(format!(
+                "job {} is a {} job, not {}",
+                job_id, job.job_type, job_type
+            )));
+        }
+        match job.state {
+            JobState::Failed | JobState::Cancelled | JobState::Interrupted => Ok(job.checkpoint),
+            state => Err(HandlerError::InvalidArguments(format!(
+                "job {} is {}; only failed, cancelled or interrupted jobs can be resumed",
+                job_id,
+                format!("{:?}", state).to_lowercase()
+            ))),
+        }
+    }
+
+    /// Drop the records of the session's finished jobs other than `keep`. Returns how
+    /// many were dropped.
+    pub async fn forget_finished(
+        &self,
+        session: &TenantSession,
+        keep: &str,
+    ) -> Result<usize, HandlerError> {
+        self.store
+            .update(session, |list| {
+                let before = list.jobs.len();
+                list.jobs
+                    .retain(|job| job.job_id == keep || !job.state.is_finished());
+                before - list.jobs.len()
+            })
+            .await
+    }
+
+    /// An unfinished job that no process is running any more: one of this process's
+    /// that isn't live, or one whose process missed three heartbeats
+    fn orphaned(&self, job: &JobRecord) -> bool {
+        if job.state.is_finished() {
+            return false;
+        }
+        if job.instance_id == self.instance_id {
+            return true;
+        }
+        let silent = Utc::now().signed_duration_since(job.updated_at);
+        silent.to_std().unwrap_or_default() > self.options.heartbeat * 3
+    }
+
+    /// Stop a queued or running job of the session. Returns its record and whether it
+    /// was cancelled; finished jobs are returned as they are.
+    pub async fn cancel(
+        &self,
+        session: &TenantSession,
+        job_id: &str,
+    ) -> Result<(JobRecord, bool), HandlerError> {
+        let job = {
+            let mut live = self.live.lock().unwrap();
+            match live.get(job_id) {
+                Some(job) if job.namespace == session.context.get_namespace_prefix() => {
+                    live.remove(job_id)
+                }
+                _ => None,
+            }
+        };
+        let Some(job) = job else {
+            return Ok((self.status(session, job_id).await?, false));
+        };
+
+        let snapshot = {
+            let mut record = job.record.lock().unwrap();
+            if record.state.is_finished() {
+                return Ok((record.clone(), false));
+            }
+            record.state = JobState::Cancelled;
+            record.updated_at = Utc::now();
+            record.clone()
+        };
+        if let Some(abort) = &job.abort {
+            abort.abort();
+        }
+        self.store.persist(&job.session, snapshot.clone()).await?;
+        info!(
+            "Cancelled job {} ({}) for tenant {}",
+            snapshot.job_id, snapshot.job_type, snapshot.tenant_id
+        );
+        self.fire_callback_in_background(&job.session, &snapshot.job_id);
+        Ok((snapshot, true))
+    }
+
+    /// Wait up to the shutdown grace for running jobs, then stop the rest and mark them
+    /// `interrupted`
+    pub async fn shutdown(&self) {
+        let deadline = tokio::time::Instant::now() + self.options.shutdown_grace;
+        while self.live_count() > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let remaining: Vec<LiveJob> = self
+            .live
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, job)| job)
+            .collect();
+        for job in remaining {
+            if let Some(abort) = &job.abort {
+                abort.abort();
+            }
+            let snapshot = {
+                let mut record = job.record.lock().unwrap();
+                if record.state.is_finished() {
+                    continue;
+                }
+                *record = interrupt(record.clone(), "the server shut down");
+                record.clone()
+            };
+            warn!(
+                "Interrupted job {} ({}) for tenant {} at shutdown",
+                snapshot.job_id, snapshot.job_type, snapshot.tenant_id
+            );
+            if let Err(e) = self.store.persist(&job.session, snapshot.clone()).await {
+                warn!("Failed to persist job {}: {}", snapshot.job_id, e);
+                continue;
+            }
+            self.fire_callback_logged(&job.session, &snapshot.job_id)
+                .await;
+        }
+    }
+}
+
+/// The job lists kept in each namespace's [`JOBS_KEY`] item
+#[derive(Clone)]
+struct JobStore {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl JobStore {
+    async fn persist(
+        &self,
+        session: &TenantSession,
+        record: JobRecord,
+    ) -> Result<(), HandlerError> {
+        self.update(session, move |list| list.upsert(record.clone()))
+            .await
+    }
+
+    /// The stored value, if any, and the parsed job list
+    async fn read(
+        &self,
+        session: &TenantSession,
+    ) -> Result<(Option<String>, JobList), HandlerError> {
+        let stored = self.aws_service.kv_get_fresh(session, JOBS_KEY).await?;
+        let list = match &stored {
+            Some(value) => serde_json::from_str(value)
+                .map_err(|e| HandlerError::Internal(format!("job list is corrupt: {}", e)))?,
+            None => JobList::default(),
+        };
+        Ok((stored, list))
+    }
+
+    /// Apply `change` to the namespace's job list and write it back only if no one else
+    /// wrote in between, rereading and retrying when someone did. Returns what the
+    /// change that was written returned.
+    async fn update<T>(
+        &self,
+        session: &TenantSession,
+        change: impl Fn(&mut JobList) -> T,
+    ) -> Result<T, HandlerError> {
+        for _ in 0..UPDATE_ATTEMPTS {
+            let (stored, mut list) = self.read(session).await?;
+            let changed = change(&mut list);
+            let value =
+                serde_json::to_string(&list).map_err(|e| HandlerError::Internal(e.to_string()))?;
+            if stored.as_deref() == Some(value.as_str()) {
+                return Ok(changed);
+            }
+            if self
+                .aws_service
+                .kv_compare_and_swap(session, JOBS_KEY, stored.as_deref(), &value, None)
+                .await?
+            {
+                return Ok(changed);
+            }
+        }
+        Err(AwsError::ConditionFailed("the job list is busy; retry the call".to_string()).into())
+    }
+}
+
+fn interrupt(mut record: JobRecord, why: &str) -> JobRecord {
+    record.state = JobState::Interrupted;
+    record.error = Some(why.to_string());
+    record.updated_at = Utc::now();
+    record
+}
+
+fn job_id(arguments: &Value) -> Result<&str, HandlerError> {
+    arguments
+        .get("job_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| HandlerError::InvalidArguments("Missing 'job_id' parameter".to_string()))
+}
+
+fn to_value(record: &JobRecord) -> Result<Value, HandlerError> {
+    serde_json::to_value(record).map_err(|e| HandlerError::Internal(e.to_string()))
+}
+
+pub struct JobsStatusHandler {
+    jobs: Arc<JobManager>,
+}
+
+impl JobsStatusHandler {
+    pub fn new(jobs: Arc<JobManager>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait]
+impl Handler for JobsStatusHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let record = self.jobs.status(session, job_id(&arguments)?).await?;
+        to_value(&record)
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Show a background job's state (queued, running, succeeded, failed, cancelled or interrupted), progress, and its result or error once finished",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "job_id": {"type": "string", "description": "Job ID returned by the tool that started the job"}
+                },
+                "required": ["job_id"]
+            }
+        })
+    }
+}
+
+pub struct JobsListHandler {
+    jobs: Arc<JobManager>,
+}
+
+impl JobsListHandler {
+    pub fn new(jobs: Arc<JobManager>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait]
+impl Handler for JobsListHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let state: Option<JobState> = match arguments.get("state") {
+            None | Some(Value::Null) => None,
+            Some(state) => Some(serde_json::from_value(state.clone()).map_err(|_| {
+                HandlerError::InvalidArguments(format!("unknown job state {}", state))
+            })?),
+        };
+        let limit = arguments
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map_or(DEFAULT_LIST_LIMIT, |limit| limit as usize);
+
+        let jobs: Vec<JobRecord> = self
+            .jobs
+            .list(session)
+            .await?
+            .into_iter()
+            .filter(|job| state.is_none_or(|state| job.state == state))
+            .take(limit)
+            .collect();
+        Ok(json!({
+            "jobs": jobs,
+            "count": jobs.len()
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "List the caller's background jobs, newest first",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "state": {
+                        "type": "string",
+                        "enum": ["queued", "running", "succeeded", "failed", "cancelled", "interrupted"],
+                        "description": "Only jobs in this state"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_FINISHED_JOBS,
+                        "description": "Most jobs returned (default 20)"
+                    }
+                }
+            }
+        })
+    }
+}
+
+pub struct JobsCancelHandler {
+    jobs: Arc<JobManager>,
+}
+
+impl JobsCancelHandler {
+    pub fn new(jobs: Arc<JobManager>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait]
+impl Handler for JobsCancelHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let (record, cancelled) = self.jobs.cancel(session, job_id(&arguments)?).await?;
+        Ok(json!({
+            "job": to_value(&record)?,
+            "cancelled": cancelled
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Cancel a queued or running background job. Jobs that already finished are returned unchanged with cancelled: false",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "job_id": {"type": "string", "description": "Job ID returned by the tool that started the job"}
+                },
+                "required": ["job_id"]
+            }
+        })
+    }
+}
@@ -0,0 +1,317 @@
+use chrono::{DateTime, Utc};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::{JobRecord, JobStore};
+use crate::aws::AwsApi;
+use crate::handlers::HandlerError;
+use crate::tenant::TenantSession;
+
+/// How long a webhook may take to answer before its delivery counts as failed
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Detail type of callback events when the callback doesn't name one
+pub const DEFAULT_CALLBACK_DETAIL_TYPE: &str = "job.finished";
+
+/// Longest webhook URL accepted
+const MAX_URL_LEN: usize = 2048;
+
+/// Set to `true` to let webhooks reach loopback hosts over plain `http`, for local
+/// development
+pub const ALLOW_LOOPBACK_WEBHOOKS_ENV: &str = "MCP_ALLOW_LOOPBACK_WEBHOOKS";
+
+/// Where a job reports that it finished, once
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobCallback {
+    /// Send an event of this detail type through the tenant's event bus
+    Event {
+        #[serde(rename = "detailType", default = "default_detail_type")]
+        detail_type: String,
+    },
+    /// POST the job's outcome as JSON to this URL
+    Webhook { url: String },
+}
+
+fn default_detail_type() -> String {
+    DEFAULT_CALLBACK_DETAIL_TYPE.to_string()
+}
+
+impl JobCallback {
+    /// The `callback` argument of a job-starting tool, if given and valid. Webhooks may
+    /// only reach loopback hosts with `allow_loopback`.
+    pub fn from_arguments(
+        arguments: &Value,
+        allow_loopback: bool,
+    ) -> Result<Option<Self>, HandlerError> {
+        let callback = match arguments.get("callback") {
+            None | Some(Value::Null) => return Ok(None),
+            Some(callback) => callback,
+        };
+        let callback: JobCallback = serde_json::from_value(callback.clone())
+            .map_err(|e| HandlerError::InvalidArguments(format!("invalid callback: {}", e)))?;
+        match &callback {
+            JobCallback::Event { detail_type } if detail_type.trim().is_empty() => Err(
+                HandlerError::InvalidArguments("callback detailType is empty".to_string()),
+            ),
+            JobCallback::Webhook { url } => {
+                validate_webhook_url(url, allow_loopback)
+                    .map_err(HandlerError::InvalidArguments)?;
+                Ok(Some(callback))
+            }
+            JobCallback::Event { .. } => Ok(Some(callback)),
+        }
+    }
+}
+
+/// Check that `url` can take callbacks: an absolute `https` URL without credentials in
+/// it. Private, link-local (e.g. the instance metadata service), unspecified and, unless
+/// `allow_loopback`, loopback hosts are refused, so callbacks can't reach the server's
+/// own network; with `allow_loopback`, loopback hosts may use plain `http`. Host names
+/// are checked again once resolved, when the callback is sent.
+pub fn validate_webhook_url(url: &str, allow_loopback: bool) -> Result<(), String> {
+    let invalid = |reason: &str| format!("callback url {:?} {}", url, reason);
+    if url.len() > MAX_URL_LEN {
+        return Err(invalid(&format!(
+            "is longer than {} characters",
+            MAX_URL_LEN
+        )));
+    }
+    let parsed = Url::parse(url).map_err(|e| invalid(&format!("is not a URL ({})", e)))?;
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err(invalid("must not carry credentials"));
+    }
+    let Some(host) = parsed.host_str() else {
+        return Err(invalid("has no host"));
+    };
+    let ip = host
+        .trim_matches(|c| c == '[' || c == ']')
+        .parse::<IpAddr>()
+        .ok();
+    if ip.is_some_and(is_internal) {
+        return Err(invalid(
+            "must not be a private, link-local or unspecified address",
+        ));
+    }
+    let loopback = host.trim_end_matches('.') == "localhost" || ip.is_some_and(is_loopback);
+    if loopback && !allow_loopback {
+        return Err(invalid("must not be a loopback address"));
+    }
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        _ => Err(invalid("must be https")),
+    }
+}
+
+/// Whether `ip` is private, link-local or unspecified, also as an IPv4-mapped IPv6
+/// address
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_link_local() || ip.is_unspecified(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            None => ip.is_unique_local() || ip.is_unicast_link_local() || ip.is_unspecified(),
+        },
+    }
+}
+
+/// Whether `ip` is a loopback address, also as an IPv4-mapped IPv6 address
+fn is_loopback(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map_or(ip.is_loopback(), |v4| v4.is_loopback()),
+        IpAddr::V4(_) => ip.is_loopback(),
+    }
+}
+
+/// `addresses` a webhook host resolved to, unless one of them is refused by
+/// [`validate_webhook_url`]'s rules; a name resolving to both public and internal
+/// addresses could be connected to either
+pub fn checked_addresses(
+    host: &str,
+    addresses: Vec<SocketAddr>,
+    allow_loopback: bool,
+) -> Result<Vec<SocketAddr>, String> {
+    match addresses
+        .iter()
+        .find(|address| is_internal(address.ip()) || (!allow_loopback && is_loopback(address.ip())))
+    {
+        Some(address) => Err(format!(
+            "webhook host {} resolves to refused address {}",
+            host,
+            address.ip()
+        )),
+        None => Ok(addresses),
+    }
+}
+
+/// Resolves webhook hosts, refusing those with an address callbacks may not reach, so
+/// a DNS name can't get around the URL check
+struct WebhookResolver {
+    allow_loopback: bool,
+}
+
+impl Resolve for WebhookResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_loopback = self.allow_loopback;
+        Box::pin(async move {
+            let addresses = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            let addresses = checked_addresses(name.as_str(), addresses, allow_loopback)?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// The `callback` property of a job-starting tool's input schema
+pub fn callback_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "Notify when the job finishes, once: {\"type\": \"event\", \"detailType\": ...} sends an event through the tenant's bus, {\"type\": \"webhook\", \"url\": ...} POSTs to an https URL. Either gets the job_id, job_type, final state, result_key and error",
+        "properties": {
+            "type": {"type": "string", "enum": ["event", "webhook"]},
+            "detailType": {"type": "string", "description": "Event detail type (default job.finished)"},
+            "url": {"type": "string", "description": "Webhook URL"}
+        },
+        "required": ["type"]
+    })
+}
+
+/// What came of a job's callback
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallbackDelivery {
+    /// When delivery was claimed; the callback is never sent again after this
+    pub attempted_at: DateTime<Utc>,
+    pub delivered: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// What a callback says about the job
+pub fn callback_payload(record: &JobRecord) -> Value {
+    json!({
+        "job_id": record.job_id,
+        "job_type": record.job_type,
+        "state": record.state,
+        "result_key": record.result.as_ref().and_then(|result| result.get("key")),
+        "error": record.error
+    })
+}
+
+/// Fires finished jobs' callbacks
+#[derive(Clone)]
+pub(super) struct JobCallbacks {
+    store: JobStore,
+    aws_service: Arc<dyn AwsApi>,
+    client: reqwest::Client,
+    allow_loopback: bool,
+}
+
+impl JobCallbacks {
+    pub(super) fn new(store: JobStore, aws_service: Arc<dyn AwsApi>, allow_loopback: bool) -> Self {
+        // Redirects aren't followed: they could lead anywhere the URL check refuses
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .redirect(Policy::none())
+            .dns_resolver(Arc::new(WebhookResolver { allow_loopback }))
+            .use_rustls_tls()
+            .build()
+            .expect("rustls is built in");
+        Self {
+            store,
+            aws_service,
+            client,
+            allow_loopback,
+        }
+    }
+
+    /// Fire the finished job's callback unless it has none or it was fired before, and
+    /// record how that went. Delivery is claimed in the stored record before sending,
+    /// so retries, and other servers sharing the store, never send it twice. Returns
+    /// whether this call sent it.
+    pub(super) async fn fire(
+        &self,
+        session: &TenantSession,
+        job_id: &str,
+    ) -> Result<bool, HandlerError> {
+        let attempted_at = Utc::now();
+        let claimed = self
+            .store
+            .update(session, |list| {
+                let job = list.jobs.iter_mut().find(|job| job.job_id == job_id)?;
+                if !job.state.is_finished() || job.callback_delivery.is_some() {
+                    return None;
+                }
+                let callback = job.callback.clone()?;
+                job.callback_delivery = Some(CallbackDelivery {
+                    attempted_at,
+                    delivered: false,
+                    error: None,
+                });
+                Some((callback, job.clone()))
+            })
+            .await?;
+        let Some((callback, record)) = claimed else {
+            return Ok(false);
+        };
+
+        let outcome = self.send(session, &callback, &record).await;
+        match &outcome {
+            Ok(()) => info!("Delivered the callback of job {}", job_id),
+            Err(e) => warn!("Failed to deliver the callback of job {}: {}", job_id, e),
+        }
+        // The job's state stays as it is either way
+        self.store
+            .update(session, |list| {
+                if let Some(job) = list.jobs.iter_mut().find(|job| job.job_id == job_id) {
+                    job.callback_delivery = Some(CallbackDelivery {
+                        attempted_at,
+                        delivered: outcome.is_ok(),
+                        error: outcome.as_ref().err().cloned(),
+                    });
+                }
+            })
+            .await?;
+        Ok(true)
+    }
+
+    async fn send(
+        &self,
+        session: &TenantSession,
+        callback: &JobCallback,
+        record: &JobRecord,
+    ) -> Result<(), String> {
+        let payload = callback_payload(record);
+        match callback {
+            JobCallback::Event { detail_type } => self
+                .aws_service
+                .send_event(session, detail_type, payload)
+                .await
+                .map_err(|e| e.to_string()),
+            JobCallback::Webhook { url } => {
+                // Stored callbacks may predate the rules in force
+                validate_webhook_url(url, self.allow_loopback)?;
+                let response = self
+                    .client
+                    .post(url)
+                    .json(&payload)
+                    .send()
+                    .await
+                    .map_err(|e| format!("{:#}", anyhow::Error::from(e)))?;
+                if response.status().is_success() {
+                    Ok(())
+                } else {
+                    Err(format!("webhook answered {}", response.status()))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::aws::artifacts::{self, RetentionDays};
+use crate::aws::AwsApi;
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// Installs the artifacts bucket's lifecycle rules for the caller's tenant: one rule per
+/// TTL class, expiring the objects under the tenant's prefix tagged `ttl-class=<class>`.
+/// Running it again updates the tenant's rules; other tenants' rules are left alone.
+pub struct ArtifactsConfigureLifecycleHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl ArtifactsConfigureLifecycleHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+fn retention_days(arguments: &Value, name: &str, default: i32) -> Result<i32, HandlerError> {
+    match arguments.get(name) {
+        None | Some(Value::Null) => Ok(default),
+        Some(value) => value
+            .as_i64()
+            .and_then(|days| i32::try_from(days).ok())
+            .ok_or_else(|| {
+                HandlerError::InvalidArguments(format!("{} must be a whole number of days", name))
+            }),
+    }
+}
+
+#[async_trait]
+impl Handler for ArtifactsConfigureLifecycleHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let defaults = RetentionDays::default();
+        let retention = RetentionDays {
+            short: retention_days(&arguments, "short_days", defaults.short)?,
+            medium: retention_days(&arguments, "medium_days", defaults.medium)?,
+            long: retention_days(&arguments, "long_days", defaults.long)?,
+        };
+        retention
+            .validate()
+            .map_err(HandlerError::InvalidArguments)?;
+
+        let rules = self
+            .aws_service
+            .configure_artifact_lifecycle(session, &retention)
+            .await?;
+        let prefix = artifacts::tenant_artifact_prefix(session);
+        info!(
+            "Configured artifact lifecycle for {} on {}: {} / {} / {} days",
+            prefix,
+            self.aws_service.artifacts_bucket(),
+            retention.short,
+            retention.medium,
+            retention.long
+        );
+        Ok(json!({
+            "bucket": self.aws_service.artifacts_bucket(),
+            "prefix": prefix,
+            "rules": rules
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Install or update the artifacts bucket's lifecycle rules for this tenant, expiring artifacts put with a ttl_class after that class's retention. Needs s3:GetLifecycleConfiguration and s3:PutLifecycleConfiguration on the bucket",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "short_days": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Days to keep ttl_class short artifacts (default: 7)"
+                    },
+                    "medium_days": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Days to keep ttl_class medium artifacts (default: 30)"
+                    },
+                    "long_days": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Days to keep ttl_class long artifacts (default: 365)"
+                    }
+                }
+            }
+        })
+    }
+}
@@ -2,10 +2,11 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
 use crate::handlers::{Handler, HandlerError};
-use crate::registry::MCPServerRegistry;
+use crate::registry::{instance_id, MCPServerRegistry, RegistryError};
 use crate::tenant::{Permission, TenantSession};
 
 pub struct MCPProxyHandler {
@@ -17,36 +18,31 @@ impl MCPProxyHandler {
         Self { registry }
     }
 
+    /// Resolve `server_id.connection_id.tool_name`, `server_id.tool_name` or a bare
+    /// `tool_name` to the connection and the tool name its server knows it by
     async fn find_server_for_tool(
         &self,
         tenant_id: &str,
         tool_name: &str,
-    ) -> Result<String, HandlerError> {
-        // Parse tool name format: "server_id.tool_name" or just "tool_name"
-        if let Some(dot_pos) = tool_name.find('.') {
-            let server_id = &tool_name[..dot_pos];
-            return Ok(server_id.to_string());
+    ) -> Result<(String, String), HandlerError> {
+        if let Some((server_id, tool)) = tool_name.split_once('.') {
+            // The middle part is a connection only if the service has one by that name
+            if let Some((connection_id, connection_tool)) = tool.split_once('.') {
+                let instance = instance_id(server_id, Some(connection_id));
+                if self.registry.has_connection(tenant_id, &instance).await {
+                    return Ok((instance, connection_tool.to_string()));
+                }
+            }
+            return Ok((server_id.to_string(), tool.to_string()));
         }
 
-        // Search through all registered servers to find the tool
-        let servers = self
-            .registry
-            .list_servers(tenant_id)
-            .await
-            .map_err(|e| HandlerError::Internal(e.to_string()))?;
-
-        // For now, we'll need to enhance the registry to expose tool listings
-        // This is a simplified version - return first server if available
-        if let Some(server) = servers.first() {
-            // Try to execute on this server and see if it has the tool
-            // In production, we'd cache this mapping
-            return Ok(server.id.clone());
+        match self.registry.resolve_tool(tenant_id, tool_name).await {
+            Ok(server_id) => Ok((server_id, tool_name.to_string())),
+            Err(e @ (RegistryError::ToolNotFound(_) | RegistryError::AmbiguousTool { .. })) => {
+                Err(HandlerError::InvalidArguments(e.to_string()))
+            }
+            Err(e) => Err(HandlerError::Internal(e.to_string())),
         }
-
-        Err(HandlerError::Internal(format!(
-            "No server found for tool: {}",
-            tool_name
-        )))
     }
 }
 
@@ -59,6 +55,11 @@ impl Handler for MCPProxyHandler {
     ) -> Result<Value, HandlerError> {
         let args: MCPProxyArgs = serde_json::from_value(arguments)
             .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+        if args.timeout_secs == Some(0) {
+            return Err(HandlerError::InvalidArguments(
+                "timeout_secs must be at least 1".to_string(),
+            ));
+        }
 
         info!(
             "Proxying MCP tool call '{}' for tenant {}",
@@ -67,21 +68,39 @@ impl Handler for MCPProxyHandler {
         );
 
         // Find the server that handles this tool
-        let server_id = self
+        let (server_id, tool_name) = self
             .find_server_for_tool(&session.context.get_context_id(), &args.tool_name)
             .await?;
 
+        if !args.skip_validation {
+            if let Some(schema) = self
+                .registry
+                .tool_input_schema(&session.context.get_context_id(), &server_id, &tool_name)
+                .await
+            {
+                validate_arguments(&args.tool_name, &schema, &args.arguments)?;
+            }
+        }
+
         // Execute the tool on the target server
+        let started = Instant::now();
         let result = self
             .registry
-            .execute_tool(
+            .execute_tool_within(
                 &session.context.get_context_id(),
                 &server_id,
-                &args.tool_name,
+                &tool_name,
                 args.arguments,
+                args.timeout_secs.map(Duration::from_secs),
             )
             .await
-            .map_err(|e| HandlerError::Internal(e.to_string()))?;
+            .map_err(|e| match e {
+                RegistryError::Timeout(_) => HandlerError::Timeout {
+                    tool: args.tool_name.clone(),
+                    elapsed: started.elapsed(),
+                },
+                e => HandlerError::Internal(e.to_string()),
+            })?;
 
         Ok(result)
     }
@@ -90,6 +109,11 @@ impl Handler for MCPProxyHandler {
         Some(Permission::Execute)
     }
 
+    // Each call is a round trip to a downstream (often containerized) server
+    fn max_concurrency(&self) -> Option<usize> {
+        Some(4)
+    }
+
     fn tool_schema(&self) -> Value {
         serde_json::json!({
             "description": "Execute a tool on a registered MCP server",
@@ -98,11 +122,20 @@ impl Handler for MCPProxyHandler {
                 "properties": {
                     "tool_name": {
                         "type": "string",
-                        "description": "Name of the tool (optionally prefixed with server_id.)"
+                        "description": "Name of the tool (optionally prefixed with server_id. or server_id.connection_id.)"
                     },
                     "arguments": {
                         "type": "object",
                         "description": "Arguments to pass to the tool"
+                    },
+                    "timeout_secs": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Optional: longest to wait for the tool (default: the server's tool_timeout_secs, or 30)"
+                    },
+                    "skip_validation": {
+                        "type": "boolean",
+                        "description": "Optional: send the arguments without checking them against the tool's input schema, for servers whose schemas are wrong"
                     }
                 },
                 "required": ["tool_name"]
@@ -115,6 +148,38 @@ impl Handler for MCPProxyHandler {
 struct MCPProxyArgs {
     tool_name: String,
     arguments: Value,
+    timeout_secs: Option<u64>,
+    #[serde(default)]
+    skip_validation: bool,
+}
+
+/// Check arguments against a downstream tool's input schema. Schemas that don't
+/// compile, such as ones with `$ref`s that can't be resolved, aren't enforced.
+fn validate_arguments(
+    tool_name: &str,
+    schema: &Value,
+    arguments: &Value,
+) -> Result<(), HandlerError> {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            debug!(
+                "Not validating arguments of {}: its input schema is unusable ({})",
+                tool_name, e
+            );
+            return Ok(());
+        }
+    };
+    validator.validate(arguments).map_err(|error| {
+        let pointer = match error.instance_path().as_str() {
+            "" => "/",
+            pointer => pointer,
+        };
+        HandlerError::InvalidArguments(format!(
+            "Arguments of {} do not match its input schema at {}: {}",
+            tool_name, pointer, error
+        ))
+    })
 }
 
 pub struct MCPListToolsHandler {
@@ -143,38 +208,51 @@ impl Handler for MCPListToolsHandler {
 
         let servers = self
             .registry
-            .list_servers(&session.context.get_context_id())
+            .list_server_tools(&session.context.get_context_id())
             .await
             .map_err(|e| HandlerError::Internal(e.to_string()))?;
 
         // If specific server requested, filter to just that server
-        let filtered_servers = if let Some(args) = args {
-            if let Some(server_id) = args.server_id {
-                servers.into_iter().filter(|s| s.id == server_id).collect()
-            } else {
-                servers
-            }
-        } else {
-            servers
-        };
+        let server_filter = args.and_then(|args| args.server_id);
 
-        // Build tool list with server prefixes
+        // Tools are prefixed with their server id, and connection id for named
+        // connections, the form `mcp_proxy` accepts
         let mut all_tools = Vec::new();
-        for server in filtered_servers {
-            // For each server, we'd fetch its tools
-            // This is simplified - in production we'd get actual tools from the registry
-            let tools = vec![MCPToolInfo {
-                name: format!("{}.example_tool", server.id),
-                description: format!("Example tool from {}", server.name),
-                server_id: server.id.clone(),
-                server_name: server.name.clone(),
-            }];
+        let mut server_entries = Vec::new();
+        for (server, tools) in servers {
+            if server_filter.as_ref().is_some_and(|id| *id != server.id) {
+                continue;
+            }
 
+            let prefix = server.tool_prefix();
+            let tools: Vec<MCPToolInfo> = tools
+                .into_iter()
+                .map(|tool| MCPToolInfo {
+                    name: format!("{}.{}", prefix, tool.name),
+                    description: tool.description,
+                    input_schema: tool.input_schema,
+                    server_id: server.id.clone(),
+                    connection_id: server.connection_id.clone(),
+                    server_name: server.name.clone(),
+                })
+                .collect();
+
+            let mut entry = serde_json::json!({
+                "server_id": server.id,
+                "server_name": server.name,
+                "status": server.status,
+                "tools": tools.iter().map(|tool| tool.name.as_str()).collect::<Vec<_>>()
+            });
+            if let Some(connection_id) = &server.connection_id {
+                entry["connection_id"] = Value::from(connection_id.as_str());
+            }
+            server_entries.push(entry);
             all_tools.extend(tools);
         }
 
         Ok(serde_json::json!({
-            "tools": all_tools
+            "tools": all_tools,
+            "servers": server_entries
         }))
     }
 
@@ -207,6 +285,31 @@ struct MCPListToolsArgs {
 struct MCPToolInfo {
     name: String,
     description: String,
+    input_schema: Value,
     server_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connection_id: Option<String>,
     server_name: String,
 }
+
+/// Connected servers' tools as `tools/list` entries named `server_id.tool` (or
+/// `server_id.connection_id.tool`), for merging into the top-level catalog
+pub async fn downstream_tool_schemas(
+    registry: &MCPServerRegistry,
+    tenant_id: &str,
+) -> Result<Vec<Value>, RegistryError> {
+    let servers = registry.list_server_tools(tenant_id).await?;
+    Ok(servers
+        .into_iter()
+        .flat_map(|(server, tools)| {
+            let prefix = server.tool_prefix();
+            tools.into_iter().map(move |tool| {
+                serde_json::json!({
+                    "name": format!("{}.{}", prefix, tool.name),
+                    "description": format!("[{}] {}", server.name, tool.description),
+                    "inputSchema": tool.input_schema
+                })
+            })
+        })
+        .collect())
+}
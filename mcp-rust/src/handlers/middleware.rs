@@ -0,0 +1,228 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use crate::handlers::HandlerError;
+use crate::metrics::{CallCounters, CallOutcome, DailyUsage};
+use crate::redaction;
+use crate::tenant::{Permission, TenantSession};
+
+/// A tool call on its way through the middleware chain
+pub struct ToolCall<'a> {
+    pub session: &'a TenantSession,
+    pub tool: &'a str,
+    /// What the handler will be called with; `before` may rewrite it
+    pub arguments: Value,
+    /// The permission the tool's handler requires
+    pub required_permission: Option<Permission>,
+    /// Set when the caller asked for the call to be replayed if it's repeated
+    pub idempotency_key: Option<&'a str>,
+}
+
+/// Runs around every tool call the registry dispatches, for concerns shared by all
+/// tools. `before` runs before the handler and may reject the call; `after` sees how
+/// it ended and how long it took, counted from the first middleware's `before`.
+#[async_trait]
+pub trait HandlerMiddleware: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn before(&self, _call: &mut ToolCall<'_>) -> Result<(), HandlerError> {
+        Ok(())
+    }
+
+    async fn after(
+        &self,
+        _call: &ToolCall<'_>,
+        _result: &Result<Value, HandlerError>,
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+/// Middlewares in the order they run: `before` first to last, `after` last to first.
+/// When a `before` rejects the call, the handler and the later middlewares are
+/// skipped, and only the middlewares before it see the rejection in `after`.
+#[derive(Clone)]
+pub struct MiddlewareChain {
+    middlewares: Vec<Arc<dyn HandlerMiddleware>>,
+}
+
+impl Default for MiddlewareChain {
+    /// Audit logging, then tool policy and permission checks
+    fn default() -> Self {
+        Self::new(vec![
+            Arc::new(AuditMiddleware),
+            Arc::new(PermissionMiddleware),
+        ])
+    }
+}
+
+impl MiddlewareChain {
+    /// Leaving out [`PermissionMiddleware`] lets every session call every tool
+    pub fn new(middlewares: Vec<Arc<dyn HandlerMiddleware>>) -> Self {
+        Self { middlewares }
+    }
+
+    pub fn push(&mut self, middleware: Arc<dyn HandlerMiddleware>) {
+        self.middlewares.push(middleware);
+    }
+
+    /// Run `middleware` before every other one, so it sees every rejection too
+    pub fn wrap(&mut self, middleware: Arc<dyn HandlerMiddleware>) {
+        self.middlewares.insert(0, middleware);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.middlewares.iter().map(|m| m.name()).collect()
+    }
+
+    /// Run every `before`; the number that let the call through, and the rejection
+    /// that stopped it, if one did
+    pub async fn before(&self, call: &mut ToolCall<'_>) -> (usize, Option<HandlerError>) {
+        for (entered, middleware) in self.middlewares.iter().enumerate() {
+            if let Err(error) = middleware.before(call).await {
+                return (entered, Some(error));
+            }
+        }
+        (self.middlewares.len(), None)
+    }
+
+    /// Run `after` on the first `entered` middlewares, innermost first
+    pub async fn after(
+        &self,
+        entered: usize,
+        call: &ToolCall<'_>,
+        result: &Result<Value, HandlerError>,
+        elapsed: Duration,
+    ) {
+        for middleware in self.middlewares[..entered].iter().rev() {
+            middleware.after(call, result, elapsed).await;
+        }
+    }
+}
+
+/// Rejects calls the tenant's tool policy blocks, then calls the session lacks the
+/// handler's permission for
+pub struct PermissionMiddleware;
+
+#[async_trait]
+impl HandlerMiddleware for PermissionMiddleware {
+    fn name(&self) -> &'static str {
+        "permission"
+    }
+
+    async fn before(&self, call: &mut ToolCall<'_>) -> Result<(), HandlerError> {
+        // Tenant tool policy is evaluated before role permissions
+        if !call.session.context.tool_policy.allows(call.tool) {
+            return Err(HandlerError::ToolBlocked(call.tool.to_string()));
+        }
+        if let Some(required) = &call.required_permission {
+            if !call.session.has_permission(required) {
+                return Err(HandlerError::PermissionDenied(required.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Logs every call under the `audit` target: who called which tool, how it ended and
+/// how long it took. Arguments and results are never logged, and credentials in error
+/// messages are masked by the installed [`redaction`] policy.
+pub struct AuditMiddleware;
+
+#[async_trait]
+impl HandlerMiddleware for AuditMiddleware {
+    fn name(&self) -> &'static str {
+        "audit"
+    }
+
+    async fn after(
+        &self,
+        call: &ToolCall<'_>,
+        result: &Result<Value, HandlerError>,
+        elapsed: Duration,
+    ) {
+        let context = &call.session.context;
+        info!(
+            target: "audit",
+            tenant_id = %context.tenant_id,
+            user_id = %context.user_id,
+            tool = call.tool,
+            outcome = if result.is_ok() { "ok" } else { "error" },
+            error = result
+                .as_ref()
+                .err()
+                .map(|e| redaction::redact_text(&e.to_string()))
+                .unwrap_or_default(),
+            duration_ms = elapsed.as_millis() as u64,
+            "tool call"
+        );
+    }
+}
+
+/// What a call adds to its tenant's [`DailyUsage`]: one tool call, plus the events a
+/// successful `events_send` sent or the value bytes a successful `kv_set` wrote
+fn daily_usage(call: &ToolCall<'_>, result: &Result<Value, HandlerError>) -> DailyUsage {
+    let mut usage = DailyUsage {
+        tool_calls: 1,
+        ..Default::default()
+    };
+    let Ok(result) = result else {
+        return usage;
+    };
+    match call.tool {
+        "events_send" => usage.events = result["count"].as_u64().unwrap_or(1),
+        "kv_set" => {
+            usage.kv_bytes = call.arguments["value"]
+                .as_str()
+                .map_or(0, |value| value.len() as u64)
+        }
+        _ => {}
+    }
+    usage
+}
+
+/// Counts calls and errors by tenant and tool, call durations by tool and each
+/// tenant's [`DailyUsage`] into the server's [`CallCounters`]
+pub struct MeteringMiddleware {
+    counters: Arc<CallCounters>,
+}
+
+impl MeteringMiddleware {
+    pub fn new(counters: Arc<CallCounters>) -> Self {
+        Self { counters }
+    }
+}
+
+#[async_trait]
+impl HandlerMiddleware for MeteringMiddleware {
+    fn name(&self) -> &'static str {
+        "metering"
+    }
+
+    async fn after(
+        &self,
+        call: &ToolCall<'_>,
+        result: &Result<Value, HandlerError>,
+        elapsed: Duration,
+    ) {
+        let tenant_id = &call.session.context.tenant_id;
+        self.counters.record_duration(call.tool, elapsed);
+        self.counters.record(
+            tenant_id,
+            call.tool,
+            match result {
+                Ok(_) => CallOutcome::Ok,
+                Err(_) => CallOutcome::Error,
+            },
+        );
+        self.counters.record_usage(
+            tenant_id,
+            Utc::now().date_naive(),
+            daily_usage(call, result),
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::aws::{namespaces, AwsApi};
+use crate::handlers::response_cache::CacheInvalidation;
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// Moves a user's artifacts from the legacy `{context_id}/` keys under their namespace
+/// (`{namespace}/`), in the caller's context. Organization artifacts were kept under one
+/// prefix for the whole organization, so there they move to the first user migrated.
+pub struct NamespaceMigrateHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl NamespaceMigrateHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for NamespaceMigrateHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let mut context = session.context.clone();
+        if let Some(user_id) = arguments.get("user_id") {
+            context.user_id = user_id
+                .as_str()
+                .filter(|user_id| !user_id.is_empty())
+                .ok_or_else(|| {
+                    HandlerError::InvalidArguments("user_id must be a string".to_string())
+                })?
+                .to_string();
+        }
+        let target = TenantSession::new(context);
+
+        let migration = self.aws_service.migrate_artifacts(&target).await?;
+        info!(
+            "Migrated artifacts of {} to namespace {}: {} moved, {} skipped",
+            target.context.user_id,
+            target.context.get_namespace_prefix(),
+            migration.moved.len(),
+            migration.skipped.len()
+        );
+        Ok(json!({
+            "user_id": target.context.user_id,
+            "namespace": target.context.get_namespace_prefix(),
+            "legacy_prefix": namespaces::legacy_artifact_key(&target, ""),
+            "moved": migration.moved,
+            "skipped": migration.skipped
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn cache_invalidations(&self, _arguments: &Value) -> Vec<CacheInvalidation> {
+        // Keys move between namespaces, under any prefix
+        vec![CacheInvalidation::Tool("artifacts_list")]
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Move a user's artifacts from the legacy per-context prefix under their namespace. Keys the namespace already has are skipped and left in place",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "user_id": {
+                        "type": "string",
+                        "description": "User whose artifacts to move, in the caller's context (default: the caller)"
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use serde_json::{json, Value};
+
+use crate::handlers::{Handler, HandlerError};
+
+/// Largest serialized tool result passed back unless configured otherwise
+pub const DEFAULT_MAX_RESULT_BYTES: usize = 1024 * 1024;
+
+/// Largest serialized tool result passed back, in bytes
+pub const MAX_RESULT_BYTES_ENV: &str = "AGENT_MESH_MAX_RESULT_BYTES";
+
+/// Set to `reject` to fail oversized results rather than truncate them
+pub const OUTPUT_LIMIT_POLICY_ENV: &str = "AGENT_MESH_OUTPUT_LIMIT_POLICY";
+
+/// What happens to a result over the size limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// Cut the tool's truncatable arrays short, marking the result `truncated: true`;
+    /// reject it when that isn't enough
+    Truncate,
+    /// Reject it, naming the arguments that ask for less
+    Reject,
+}
+
+/// Cap on the serialized size of tool results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputLimit {
+    pub max_bytes: usize,
+    pub policy: OversizePolicy,
+}
+
+impl Default for OutputLimit {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_RESULT_BYTES,
+            policy: OversizePolicy::Truncate,
+        }
+    }
+}
+
+impl OutputLimit {
+    /// Bring `result` within the limit, or fail with `ResultTooLarge`. Truncation keeps
+    /// each array's leading elements, so the same result is always cut the same way;
+    /// fields such as `count` are left as the tool reported them.
+    pub fn apply(
+        &self,
+        tool: &str,
+        handler: &dyn Handler,
+        result: &mut Value,
+    ) -> Result<(), HandlerError> {
+        let size = serialized_len(result);
+        if size <= self.max_bytes {
+            return Ok(());
+        }
+        let too_large = || HandlerError::ResultTooLarge {
+            tool: tool.to_string(),
+            size,
+            max_bytes: self.max_bytes,
+            hint: handler.paging_hint().map(String::from),
+        };
+        if self.policy == OversizePolicy::Reject || handler.truncatable_fields().is_empty() {
+            return Err(too_large());
+        }
+
+        let mut truncated = result.clone();
+        let Value::Object(fields) = &mut truncated else {
+            return Err(too_large());
+        };
+        fields.insert("truncated".to_string(), Value::Bool(true));
+        let hint = handler.paging_hint().map(|hint| {
+            format!(
+                "Result cut to {} bytes; ask for less with {}",
+                self.max_bytes, hint
+            )
+        });
+        fields.insert(
+            "truncation".to_string(),
+            json!({ "fields": {}, "hint": hint }),
+        );
+        for pointer in handler.truncatable_fields() {
+            let Some(Value::Array(items)) = truncated.pointer(pointer).cloned() else {
+                continue;
+            };
+            // Sized with `kept` at its longest, so the final marker can only be shorter
+            set_marker(&mut truncated, pointer, items.len(), items.len());
+            let kept = self.longest_prefix(&mut truncated, pointer, &items);
+            set_marker(&mut truncated, pointer, kept, items.len());
+            if serialized_len(&truncated) <= self.max_bytes {
+                *result = truncated;
+                return Ok(());
+            }
+        }
+        Err(too_large())
+    }
+
+    /// Leave the array at `pointer` as the longest prefix of `items` that fits, and
+    /// return its length
+    fn longest_prefix(&self, result: &mut Value, pointer: &str, items: &[Value]) -> usize {
+        let set = |result: &mut Value, len: usize| {
+            if let Some(array) = result.pointer_mut(pointer) {
+                *array = Value::Array(items[..len].to_vec());
+            }
+        };
+        // Binary search for the largest length that fits
+        let (mut fits, mut too_long) = (0, items.len() + 1);
+        while too_long - fits > 1 {
+            let len = (fits + too_long) / 2;
+            set(result, len);
+            if serialized_len(result) <= self.max_bytes {
+                fits = len;
+            } else {
+                too_long = len;
+            }
+        }
+        set(result, fits);
+        fits
+    }
+}
+
+fn set_marker(result: &mut Value, pointer: &str, kept: usize, total: usize) {
+    if let Some(Value::Object(cut)) = result.pointer_mut("/truncation/fields") {
+        cut.insert(pointer.to_string(), json!({ "kept": kept, "total": total }));
+    }
+}
+
+fn serialized_len(value: &Value) -> usize {
+    serde_json::to_vec(value)
+        .map(|bytes| bytes.len())
+        .unwrap_or(0)
+}
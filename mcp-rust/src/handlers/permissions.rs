@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantError, TenantManager, TenantMember, TenantSession};
+
+#[derive(Debug, Deserialize)]
+struct PermissionChangeArgs {
+    user_id: String,
+    permissions: Vec<Permission>,
+}
+
+fn parse_args(arguments: Value) -> Result<PermissionChangeArgs, HandlerError> {
+    let args: PermissionChangeArgs = serde_json::from_value(arguments)
+        .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+    if args.permissions.is_empty() {
+        return Err(HandlerError::InvalidArguments(
+            "permissions must not be empty".to_string(),
+        ));
+    }
+    Ok(args)
+}
+
+fn map_tenant_error(error: TenantError) -> HandlerError {
+    match error {
+        TenantError::AdminRequired => HandlerError::PermissionDenied(Permission::Admin),
+        TenantError::UserNotFound(user_id) => HandlerError::NotFound(user_id),
+        other => HandlerError::InvalidArguments(other.to_string()),
+    }
+}
+
+fn member_response(user_id: &str, member: &TenantMember) -> Value {
+    json!({
+        "user_id": user_id,
+        "role": member.role,
+        "permissions": member.effective_permissions()
+    })
+}
+
+fn permission_change_schema(description: &str) -> Value {
+    json!({
+        "description": description,
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "user_id": {
+                    "type": "string",
+                    "description": "User within the caller's tenant"
+                },
+                "permissions": {
+                    "type": "array",
+                    "items": {
+                        "type": "string",
+                        "enum": Permission::all()
+                    },
+                    "description": "Permissions to change (Admin requires the caller to be an admin)"
+                }
+            },
+            "required": ["user_id", "permissions"]
+        }
+    })
+}
+
+pub struct PermissionGrantHandler {
+    tenant_manager: Arc<TenantManager>,
+}
+
+impl PermissionGrantHandler {
+    pub fn new(tenant_manager: Arc<TenantManager>) -> Self {
+        Self { tenant_manager }
+    }
+}
+
+#[async_trait]
+impl Handler for PermissionGrantHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args = parse_args(arguments)?;
+
+        info!(
+            "Granting {:?} to user {} in tenant {}",
+            args.permissions, args.user_id, session.context.tenant_id
+        );
+
+        let member = self
+            .tenant_manager
+            .grant_permissions(session, &args.user_id, &args.permissions)
+            .await
+            .map_err(map_tenant_error)?;
+
+        Ok(member_response(&args.user_id, &member))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ManageUsers)
+    }
+
+    fn tool_schema(&self) -> Value {
+        permission_change_schema("Grant permissions to a user in the current tenant")
+    }
+}
+
+pub struct PermissionRevokeHandler {
+    tenant_manager: Arc<TenantManager>,
+}
+
+impl PermissionRevokeHandler {
+    pub fn new(tenant_manager: Arc<TenantManager>) -> Self {
+        Self { tenant_manager }
+    }
+}
+
+#[async_trait]
+impl Handler for PermissionRevokeHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args = parse_args(arguments)?;
+
+        info!(
+            "Revoking {:?} from user {} in tenant {}",
+            args.permissions, args.user_id, session.context.tenant_id
+        );
+
+        let member = self
+            .tenant_manager
+            .revoke_permissions(session, &args.user_id, &args.permissions)
+            .await
+            .map_err(map_tenant_error)?;
+
+        Ok(member_response(&args.user_id, &member))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ManageUsers)
+    }
+
+    fn tool_schema(&self) -> Value {
+        permission_change_schema("Revoke permissions from a user in the current tenant")
+    }
+}
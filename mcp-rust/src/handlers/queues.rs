@@ -0,0 +1,380 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::aws::queues::{
+    DEFAULT_VISIBILITY_TIMEOUT_SECONDS, DELAY_SECONDS, MAX_MESSAGES, VISIBILITY_TIMEOUT_SECONDS,
+    WAIT_SECONDS,
+};
+use crate::aws::{AwsApi, AwsError};
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// SQS accepts at most this many message attributes
+const MAX_ATTRIBUTES: usize = 10;
+
+fn required_str<'a>(arguments: &'a Value, field: &str) -> Result<&'a str, HandlerError> {
+    arguments
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| HandlerError::InvalidArguments(format!("Missing '{}' parameter", field)))
+}
+
+/// The integer `field` if given, which must lie in `range`
+fn optional_int(
+    arguments: &Value,
+    field: &str,
+    range: RangeInclusive<i32>,
+) -> Result<Option<i32>, HandlerError> {
+    match arguments.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_i64()
+            .and_then(|value| i32::try_from(value).ok())
+            .filter(|value| range.contains(value))
+            .map(Some)
+            .ok_or_else(|| {
+                HandlerError::InvalidArguments(format!(
+                    "{} must be between {} and {}",
+                    field,
+                    range.start(),
+                    range.end()
+                ))
+            }),
+    }
+}
+
+/// Bad queue names are the caller's mistake rather than an AWS failure
+fn map_aws_error(error: AwsError) -> HandlerError {
+    match error {
+        AwsError::InvalidQueueName(_) => HandlerError::InvalidArguments(error.to_string()),
+        other => HandlerError::Aws(other),
+    }
+}
+
+fn queue_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Queue name within the caller's tenant (letters, digits and _)"
+    })
+}
+
+fn receipt_handle_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Receipt handle from queue_receive; only the latest receive's handle works"
+    })
+}
+
+pub struct QueueSendHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl QueueSendHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for QueueSendHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = required_str(&arguments, "queue")?;
+        let body = required_str(&arguments, "body")?;
+        let delay_seconds = optional_int(&arguments, "delay_seconds", DELAY_SECONDS)?;
+        let attributes: HashMap<String, String> = match arguments.get("attributes") {
+            None | Some(Value::Null) => HashMap::new(),
+            Some(Value::Object(attributes)) => attributes
+                .iter()
+                .map(|(name, value)| match value.as_str() {
+                    Some(value) => Ok((name.clone(), value.to_string())),
+                    None => Err(HandlerError::InvalidArguments(format!(
+                        "Attribute '{}' must be a string",
+                        name
+                    ))),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(HandlerError::InvalidArguments(
+                    "attributes must be an object of strings".to_string(),
+                ))
+            }
+        };
+        if attributes.len() > MAX_ATTRIBUTES {
+            return Err(HandlerError::InvalidArguments(format!(
+                "At most {} attributes are allowed",
+                MAX_ATTRIBUTES
+            )));
+        }
+
+        let message_id = self
+            .aws_service
+            .tenant_queue_send(session, queue, body, &attributes, delay_seconds)
+            .await
+            .map_err(map_aws_error)?;
+        info!(
+            "Sent message {} to queue {} for tenant {}",
+            message_id, queue, session.context.tenant_id
+        );
+
+        Ok(json!({"queue": queue, "messageId": message_id}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::UseQueues)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Send a message to one of the tenant's SQS queues for another agent to receive and acknowledge",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "body": {
+                        "type": "string",
+                        "description": "Message body (up to 256KB)"
+                    },
+                    "attributes": {
+                        "type": "object",
+                        "additionalProperties": {"type": "string"},
+                        "maxProperties": MAX_ATTRIBUTES,
+                        "description": "String attributes delivered with the message"
+                    },
+                    "delay_seconds": {
+                        "type": "integer",
+                        "minimum": DELAY_SECONDS.start(),
+                        "maximum": DELAY_SECONDS.end(),
+                        "description": "Seconds before the message can first be received (default: the queue's delay)"
+                    }
+                },
+                "required": ["queue", "body"]
+            }
+        })
+    }
+}
+
+pub struct QueueReceiveHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl QueueReceiveHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for QueueReceiveHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = required_str(&arguments, "queue")?;
+        let max_messages = optional_int(&arguments, "max_messages", MAX_MESSAGES)?.unwrap_or(1);
+        let wait_seconds = optional_int(&arguments, "wait_seconds", WAIT_SECONDS)?.unwrap_or(0);
+        let visibility_timeout = optional_int(
+            &arguments,
+            "visibility_timeout_seconds",
+            VISIBILITY_TIMEOUT_SECONDS,
+        )?;
+
+        let messages = self
+            .aws_service
+            .tenant_queue_receive(
+                session,
+                queue,
+                max_messages,
+                wait_seconds,
+                visibility_timeout,
+            )
+            .await
+            .map_err(map_aws_error)?;
+
+        Ok(json!({
+            "queue": queue,
+            "count": messages.len(),
+            "messages": messages
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::UseQueues)
+    }
+
+    fn max_concurrency(&self) -> Option<usize> {
+        // Long polls hold a call open for up to 20s
+        Some(4)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": format!(
+                "Receive messages from one of the tenant's SQS queues. Received messages are hidden from other receivers for the visibility timeout (default: the queue's, {}s unless configured); acknowledge each with queue_ack once processed, or return it with queue_nack, otherwise it's delivered again when the timeout ends. When no message arrives within wait_seconds the result is {{\"count\": 0, \"messages\": []}}.",
+                DEFAULT_VISIBILITY_TIMEOUT_SECONDS
+            ),
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "max_messages": {
+                        "type": "integer",
+                        "minimum": MAX_MESSAGES.start(),
+                        "maximum": MAX_MESSAGES.end(),
+                        "description": "Most messages to return (default 1)"
+                    },
+                    "wait_seconds": {
+                        "type": "integer",
+                        "minimum": WAIT_SECONDS.start(),
+                        "maximum": WAIT_SECONDS.end(),
+                        "description": "Seconds to wait for a message when none is available (long polling; default 0)"
+                    },
+                    "visibility_timeout_seconds": {
+                        "type": "integer",
+                        "minimum": VISIBILITY_TIMEOUT_SECONDS.start(),
+                        "maximum": VISIBILITY_TIMEOUT_SECONDS.end(),
+                        "description": "Seconds the received messages stay hidden from other receivers (default: the queue's)"
+                    }
+                },
+                "required": ["queue"]
+            },
+            "outputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": {"type": "string"},
+                    "count": {
+                        "type": "integer",
+                        "description": "Messages returned; 0 when none arrived in time"
+                    },
+                    "messages": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "messageId": {"type": "string"},
+                                "receiptHandle": {"type": "string"},
+                                "body": {"type": "string"},
+                                "attributes": {
+                                    "type": "object",
+                                    "additionalProperties": {"type": "string"}
+                                },
+                                "receiveCount": {
+                                    "type": "integer",
+                                    "description": "Times the message has been received, this one included"
+                                }
+                            },
+                            "required": ["messageId", "receiptHandle", "body", "attributes", "receiveCount"]
+                        }
+                    }
+                },
+                "required": ["queue", "count", "messages"]
+            }
+        })
+    }
+}
+
+pub struct QueueAckHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl QueueAckHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for QueueAckHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = required_str(&arguments, "queue")?;
+        let receipt_handle = required_str(&arguments, "receipt_handle")?;
+        self.aws_service
+            .tenant_queue_ack(session, queue, receipt_handle)
+            .await
+            .map_err(map_aws_error)?;
+        Ok(json!({"queue": queue, "acknowledged": true}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::UseQueues)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Acknowledge a received message, deleting it from the queue. Acknowledge before its visibility timeout ends; once another receiver has it, the old receipt handle no longer deletes it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "receipt_handle": receipt_handle_property()
+                },
+                "required": ["queue", "receipt_handle"]
+            }
+        })
+    }
+}
+
+pub struct QueueNackHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl QueueNackHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for QueueNackHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = required_str(&arguments, "queue")?;
+        let receipt_handle = required_str(&arguments, "receipt_handle")?;
+        let delay_seconds =
+            optional_int(&arguments, "delay_seconds", VISIBILITY_TIMEOUT_SECONDS)?.unwrap_or(0);
+        self.aws_service
+            .tenant_queue_nack(session, queue, receipt_handle, delay_seconds)
+            .await
+            .map_err(map_aws_error)?;
+        Ok(json!({"queue": queue, "redeliverInSeconds": delay_seconds}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::UseQueues)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Return a received message to the queue unprocessed, so it can be received again after delay_seconds. Fails with not found (-32006) if its visibility timeout already ended.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "receipt_handle": receipt_handle_property(),
+                    "delay_seconds": {
+                        "type": "integer",
+                        "minimum": VISIBILITY_TIMEOUT_SECONDS.start(),
+                        "maximum": VISIBILITY_TIMEOUT_SECONDS.end(),
+                        "description": "Seconds before the message can be received again (default 0: at once)"
+                    }
+                },
+                "required": ["queue", "receipt_handle"]
+            }
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantManager, TenantSession};
+
+/// Reports the caller's tenant rate limit buckets and legacy window without consuming tokens
+pub struct RateLimitStatusHandler {
+    tenant_manager: Arc<TenantManager>,
+}
+
+impl RateLimitStatusHandler {
+    pub fn new(tenant_manager: Arc<TenantManager>) -> Self {
+        Self { tenant_manager }
+    }
+}
+
+#[async_trait]
+impl Handler for RateLimitStatusHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let status = self.tenant_manager.rate_limit_status(session).await;
+        serde_json::to_value(status).map_err(|e| HandlerError::Internal(e.to_string()))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Show the current tenant's rate limit buckets (tokens, capacity, refill rate, time to full) and per-minute request window",
+            "inputSchema": {
+                "type": "object",
+                "properties": {}
+            }
+        })
+    }
+}
@@ -0,0 +1,333 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::handlers::idempotency::arguments_hash;
+use crate::tenant::TenantSession;
+
+/// Most tool responses kept in process; unset or 0 leaves the cache off
+pub const RESPONSE_CACHE_SIZE_ENV: &str = "MCP_RESPONSE_CACHE_SIZE";
+
+/// Most tool responses kept for any one tenant; unset shares the whole cache
+pub const RESPONSE_CACHE_TENANT_SIZE_ENV: &str = "MCP_RESPONSE_CACHE_TENANT_SIZE";
+
+/// Cached responses a successful call makes stale, declared by the tool through
+/// [`Handler::cache_invalidations`](crate::handlers::Handler::cache_invalidations).
+/// Applied to the caller's whole tenant, whichever of its users the entries are for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheInvalidation {
+    /// Every cached response of the tool
+    Tool(&'static str),
+    /// Cached responses of `tool` whose string `argument` overlaps `prefix`: one is a
+    /// prefix of the other. A missing argument counts as the empty prefix.
+    Prefix {
+        tool: &'static str,
+        argument: &'static str,
+        prefix: String,
+    },
+}
+
+impl CacheInvalidation {
+    fn matches(&self, tool: &str, arguments: &Value) -> bool {
+        match self {
+            CacheInvalidation::Tool(invalidated) => *invalidated == tool,
+            CacheInvalidation::Prefix {
+                tool: invalidated,
+                argument,
+                prefix,
+            } => {
+                let cached = arguments
+                    .get(*argument)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                *invalidated == tool && (prefix.starts_with(cached) || cached.starts_with(prefix))
+            }
+        }
+    }
+}
+
+/// The `artifacts_list` results a write to the artifact key, or prefix of keys, in
+/// `arguments[argument]` makes stale
+pub fn artifacts_list_invalidation(arguments: &Value, argument: &str) -> Vec<CacheInvalidation> {
+    arguments
+        .get(argument)
+        .and_then(Value::as_str)
+        .map(|prefix| CacheInvalidation::Prefix {
+            tool: "artifacts_list",
+            argument: "prefix",
+            prefix: prefix.to_string(),
+        })
+        .into_iter()
+        .collect()
+}
+
+/// The key a call's response is cached under within its tenant: the caller's namespace,
+/// the tool and a hash of the canonical arguments
+fn cache_key(session: &TenantSession, tool: &str, arguments: &Value) -> String {
+    format!(
+        "{}|{}|{}",
+        session.context.get_namespace_prefix(),
+        tool,
+        arguments_hash(arguments)
+    )
+}
+
+/// Flag a response served from the cache, with how old it is
+pub fn mark_cached(response: &mut Value, age: Duration) {
+    if let Value::Object(fields) = response {
+        fields.insert("cached".to_string(), Value::Bool(true));
+        fields.insert("cache_age_ms".to_string(), (age.as_millis() as u64).into());
+    }
+}
+
+struct CachedResponse {
+    tool: String,
+    arguments: Value,
+    response: Value,
+    stored_at: Instant,
+    expires_at: Instant,
+    /// Position in the recency order; higher is more recent
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Partition {
+    entries: HashMap<String, CachedResponse>,
+    /// Keys by `last_used`, least recently used first
+    recency: BTreeMap<u64, String>,
+    /// Invalidations applied so far, so a read that raced a write isn't cached
+    invalidations: u64,
+}
+
+#[derive(Default)]
+struct Partitions {
+    tenants: HashMap<String, Partition>,
+    /// Tenants and keys by `last_used` across every partition
+    recency: BTreeMap<u64, (String, String)>,
+    clock: u64,
+    len: usize,
+}
+
+impl Partitions {
+    fn touch(&mut self, tenant: &str, key: &str) {
+        self.clock += 1;
+        let clock = self.clock;
+        let Some(partition) = self.tenants.get_mut(tenant) else {
+            return;
+        };
+        if let Some(entry) = partition.entries.get_mut(key) {
+            partition.recency.remove(&entry.last_used);
+            self.recency.remove(&entry.last_used);
+            entry.last_used = clock;
+            partition.recency.insert(clock, key.to_string());
+            self.recency
+                .insert(clock, (tenant.to_string(), key.to_string()));
+        }
+    }
+
+    fn remove(&mut self, tenant: &str, key: &str) {
+        let Some(partition) = self.tenants.get_mut(tenant) else {
+            return;
+        };
+        if let Some(entry) = partition.entries.remove(key) {
+            partition.recency.remove(&entry.last_used);
+            self.recency.remove(&entry.last_used);
+            self.len -= 1;
+        }
+        // Keep the counter of an emptied partition; a ticket may still refer to it
+    }
+
+    fn evict_oldest(&mut self, tenant: Option<&str>) {
+        let oldest = match tenant {
+            Some(tenant) => self
+                .tenants
+                .get(tenant)
+                .and_then(|partition| partition.recency.first_key_value())
+                .map(|(_, key)| (tenant.to_string(), key.clone())),
+            None => self
+                .recency
+                .first_key_value()
+                .map(|(_, oldest)| oldest.clone()),
+        };
+        if let Some((tenant, key)) = oldest {
+            self.remove(&tenant, &key);
+        }
+    }
+}
+
+/// Counters for the response cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResponseCacheStats {
+    pub capacity: usize,
+    pub tenant_capacity: Option<usize>,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// In-process LRU cache of the responses of read-only tools, for as long as each tool's
+/// [`cache_ttl`](crate::handlers::Handler::cache_ttl). Entries are partitioned by
+/// tenant, and keyed within it by the caller's namespace, so no caller is ever served
+/// another's response. Only this process's calls invalidate entries; changes made
+/// elsewhere are seen once the TTL runs out.
+pub struct ResponseCache {
+    capacity: usize,
+    tenant_capacity: Option<usize>,
+    partitions: Mutex<Partitions>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ResponseCache {
+    /// A cache of up to `capacity` responses, of which up to `tenant_capacity` for any
+    /// one tenant
+    pub fn new(capacity: usize, tenant_capacity: Option<usize>) -> Self {
+        Self {
+            capacity,
+            tenant_capacity,
+            partitions: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The response cached for the caller's call of `tool` with `arguments` and how old
+    /// it is, `None` on a miss. Counts the hit or miss.
+    pub fn get(
+        &self,
+        session: &TenantSession,
+        tool: &str,
+        arguments: &Value,
+    ) -> Option<(Value, Duration)> {
+        let tenant = session.context.tenant_id.as_str();
+        let key = &cache_key(session, tool, arguments);
+        let mut partitions = self.partitions.lock().unwrap();
+        let now = Instant::now();
+        let cached = match partitions
+            .tenants
+            .get(tenant)
+            .and_then(|partition| partition.entries.get(key))
+        {
+            Some(entry) if entry.expires_at > now => {
+                Some((entry.response.clone(), now - entry.stored_at))
+            }
+            Some(_) => {
+                partitions.remove(tenant, key);
+                None
+            }
+            None => None,
+        };
+        match cached {
+            Some(cached) => {
+                partitions.touch(tenant, key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(cached)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Taken before calling the tool; pass it to `insert` with the response
+    pub fn ticket(&self, session: &TenantSession) -> u64 {
+        self.partitions
+            .lock()
+            .unwrap()
+            .tenants
+            .get(&session.context.tenant_id)
+            .map_or(0, |partition| partition.invalidations)
+    }
+
+    /// Cache the response of the caller's call of `tool` started at `ticket`, unless one
+    /// of the tenant's calls invalidated entries since, in which case it may already be
+    /// stale. Evicts the tenant's least recently used entry when the tenant is at its
+    /// cap, otherwise the least recently used of all when the cache is full.
+    pub fn insert(
+        &self,
+        session: &TenantSession,
+        tool: &str,
+        arguments: &Value,
+        response: Value,
+        ttl: Duration,
+        ticket: u64,
+    ) {
+        let tenant = session.context.tenant_id.as_str();
+        let key = &cache_key(session, tool, arguments);
+        let mut partitions = self.partitions.lock().unwrap();
+        if partitions
+            .tenants
+            .get(tenant)
+            .map_or(0, |partition| partition.invalidations)
+            != ticket
+        {
+            return;
+        }
+        partitions.remove(tenant, key);
+        if let Some(tenant_capacity) = self.tenant_capacity {
+            while partitions
+                .tenants
+                .get(tenant)
+                .is_some_and(|partition| partition.entries.len() >= tenant_capacity)
+            {
+                partitions.evict_oldest(Some(tenant));
+            }
+        }
+        while partitions.len >= self.capacity && partitions.len > 0 {
+            partitions.evict_oldest(None);
+        }
+
+        let now = Instant::now();
+        partitions
+            .tenants
+            .entry(tenant.to_string())
+            .or_default()
+            .entries
+            .insert(
+                key.to_string(),
+                CachedResponse {
+                    tool: tool.to_string(),
+                    arguments: arguments.clone(),
+                    response,
+                    stored_at: now,
+                    expires_at: now + ttl,
+                    last_used: 0,
+                },
+            );
+        partitions.len += 1;
+        partitions.touch(tenant, key);
+    }
+
+    /// Drop the entries of the caller's tenant that `invalidation` covers, whichever of
+    /// its users they are for
+    pub fn invalidate(&self, session: &TenantSession, invalidation: &CacheInvalidation) {
+        let tenant = session.context.tenant_id.as_str();
+        let mut partitions = self.partitions.lock().unwrap();
+        let partition = partitions.tenants.entry(tenant.to_string()).or_default();
+        partition.invalidations += 1;
+        let stale: Vec<String> = partition
+            .entries
+            .iter()
+            .filter(|(_, entry)| invalidation.matches(&entry.tool, &entry.arguments))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in stale {
+            partitions.remove(tenant, &key);
+        }
+    }
+
+    pub fn stats(&self) -> ResponseCacheStats {
+        ResponseCacheStats {
+            capacity: self.capacity,
+            tenant_capacity: self.tenant_capacity,
+            entries: self.partitions.lock().unwrap().len,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Version of the `tools/list` result's format, sent as its `schemaVersion`. Version 1
+/// gives every tool a `version` and `deprecated`, and deprecated tools a `replacedBy`
+/// when there's a successor.
+pub const TOOLS_SCHEMA_VERSION: u32 = 1;
+
+/// Where a tool's argument shape stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolVersion {
+    /// Bumped whenever the tool's arguments or result change in a way that could break
+    /// a client built against the previous version
+    pub version: u32,
+    /// Set when the whole tool is on its way out
+    pub deprecation: Option<Deprecation>,
+}
+
+impl Default for ToolVersion {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            deprecation: None,
+        }
+    }
+}
+
+/// Why something a client uses is deprecated, and what to use instead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Deprecation {
+    pub message: &'static str,
+    /// The tool to call instead, when there is one
+    #[serde(rename = "replacedBy", skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<&'static str>,
+}
+
+/// Add the tool's `version`, `deprecated` and, when deprecated in favour of another
+/// tool, `replacedBy` to its listed schema
+pub fn annotate(schema: &mut Value, tool_version: &ToolVersion) {
+    let Value::Object(fields) = schema else {
+        return;
+    };
+    fields.insert("version".to_string(), json!(tool_version.version));
+    fields.insert(
+        "deprecated".to_string(),
+        json!(tool_version.deprecation.is_some()),
+    );
+    if let Some(replaced_by) = tool_version.deprecation.and_then(|d| d.replaced_by) {
+        fields.insert("replacedBy".to_string(), json!(replaced_by));
+    }
+}
+
+/// Warn the caller of a deprecated tool, or a deprecated argument shape, with
+/// `_meta.deprecation`: `{tool, message, replacedBy}`
+pub fn mark(result: &mut Value, tool: &str, deprecation: &Deprecation) {
+    let Value::Object(fields) = result else {
+        return;
+    };
+    let meta = fields
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(serde_json::Map::new()));
+    if let Value::Object(meta) = meta {
+        let mut warning = json!(deprecation);
+        warning["tool"] = json!(tool);
+        meta.insert("deprecation".to_string(), warning);
+    }
+}
@@ -0,0 +1,229 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::aws::{AwsApi, AwsError};
+use crate::handlers::dry_run::DRY_RUN_ARG;
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// Recovery windows Secrets Manager accepts, in days
+const RECOVERY_WINDOW_DAYS: RangeInclusive<i64> = 7..=30;
+
+/// Recovery window used when `secret_delete` isn't given one
+const DEFAULT_RECOVERY_WINDOW_DAYS: i64 = 7;
+
+fn required_str<'a>(arguments: &'a Value, field: &str) -> Result<&'a str, HandlerError> {
+    arguments
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| HandlerError::InvalidArguments(format!("Missing '{}' parameter", field)))
+}
+
+/// Bad secret names are the caller's mistake rather than an AWS failure
+fn map_aws_error(error: AwsError) -> HandlerError {
+    match error {
+        AwsError::InvalidSecretName(_) => HandlerError::InvalidArguments(error.to_string()),
+        other => HandlerError::Aws(other),
+    }
+}
+
+fn name_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Secret name within the caller's namespace (letters, digits and _+=.@-)"
+    })
+}
+
+pub struct SecretGetHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl SecretGetHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for SecretGetHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let name = required_str(&arguments, "name")?;
+        let value = self
+            .aws_service
+            .tenant_secret_get(session, name)
+            .await
+            .map_err(map_aws_error)?;
+        Ok(json!({"name": name, "value": value}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ReadSecrets)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Read one of the caller's secrets from Secrets Manager",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": name_property()
+                },
+                "required": ["name"]
+            }
+        })
+    }
+}
+
+pub struct SecretPutHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl SecretPutHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for SecretPutHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let name = required_str(&arguments, "name")?;
+        let value = required_str(&arguments, "value")?;
+
+        info!(
+            "Storing secret {} for tenant {}",
+            name, session.context.tenant_id
+        );
+        self.aws_service
+            .tenant_secret_put(session, name, value)
+            .await
+            .map_err(map_aws_error)?;
+
+        // The value is never echoed back
+        Ok(json!({"name": name, "stored": true}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::WriteSecrets)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Create or update one of the caller's secrets in Secrets Manager",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": name_property(),
+                    "value": {
+                        "type": "string",
+                        "description": "Secret value; it isn't included in the response"
+                    }
+                },
+                "required": ["name", "value"]
+            }
+        })
+    }
+}
+
+pub struct SecretDeleteHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl SecretDeleteHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for SecretDeleteHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let name = required_str(&arguments, "name")?;
+        let recovery_window_days = match arguments.get("recovery_window_days") {
+            None | Some(Value::Null) => DEFAULT_RECOVERY_WINDOW_DAYS,
+            Some(days) => days
+                .as_i64()
+                .filter(|days| RECOVERY_WINDOW_DAYS.contains(days))
+                .ok_or_else(|| {
+                    HandlerError::InvalidArguments(format!(
+                        "recovery_window_days must be between {} and {}",
+                        RECOVERY_WINDOW_DAYS.start(),
+                        RECOVERY_WINDOW_DAYS.end()
+                    ))
+                })?,
+        };
+
+        if arguments.get(DRY_RUN_ARG) == Some(&Value::Bool(true)) {
+            let exists = match self.aws_service.tenant_secret_get(session, name).await {
+                Ok(_) => true,
+                Err(AwsError::NotFound(_)) => false,
+                Err(error) => return Err(map_aws_error(error)),
+            };
+            let deletion_date = chrono::Utc::now() + chrono::Duration::days(recovery_window_days);
+            return Ok(json!({
+                "name": name,
+                "exists": exists,
+                "deletion_date": exists.then(|| deletion_date.to_rfc3339()),
+                "recovery_window_days": recovery_window_days
+            }));
+        }
+
+        info!(
+            "Scheduling deletion of secret {} for tenant {}",
+            name, session.context.tenant_id
+        );
+        let deletion_date = self
+            .aws_service
+            .tenant_secret_delete(session, name, recovery_window_days)
+            .await
+            .map_err(map_aws_error)?;
+
+        Ok(json!({
+            "name": name,
+            "deletion_date": deletion_date.to_rfc3339(),
+            "recovery_window_days": recovery_window_days
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::WriteSecrets)
+    }
+
+    fn supports_dry_run(&self) -> bool {
+        true
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Schedule one of the caller's secrets for deletion; it can be restored in Secrets Manager until the recovery window ends",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": name_property(),
+                    "recovery_window_days": {
+                        "type": "integer",
+                        "minimum": RECOVERY_WINDOW_DAYS.start(),
+                        "maximum": RECOVERY_WINDOW_DAYS.end(),
+                        "description": "Days before the secret is deleted for good (default 7)"
+                    }
+                },
+                "required": ["name"]
+            }
+        })
+    }
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::aws::AwsApi;
+use crate::handlers::concurrency::ToolConcurrencyLimiter;
+use crate::handlers::response_cache::ResponseCache;
+use crate::handlers::{Handler, HandlerError};
+use crate::metrics::CallCounters;
+use crate::registry::MCPServerRegistry;
+use crate::tenant::{Permission, TenantManager, TenantSession};
+
+/// Reports process-wide counters (sessions, in-flight requests and tool calls, rate
+/// limit buckets, KV read and tool response caches, AWS call latency, downstream MCP
+/// server metrics)
+pub struct ServerStatsHandler {
+    tenant_manager: Arc<TenantManager>,
+    tool_concurrency: Arc<ToolConcurrencyLimiter>,
+    registry: Option<Arc<MCPServerRegistry>>,
+    call_counters: Option<Arc<CallCounters>>,
+    aws_service: Option<Arc<dyn AwsApi>>,
+    response_cache: Option<Arc<ResponseCache>>,
+}
+
+impl ServerStatsHandler {
+    pub fn new(
+        tenant_manager: Arc<TenantManager>,
+        tool_concurrency: Arc<ToolConcurrencyLimiter>,
+    ) -> Self {
+        Self {
+            tenant_manager,
+            tool_concurrency,
+            registry: None,
+            call_counters: None,
+            aws_service: None,
+            response_cache: None,
+        }
+    }
+
+    /// Also report every downstream connection's call and uptime metrics
+    pub fn with_registry(mut self, registry: Arc<MCPServerRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Also report tool calls, errors and rate-limit rejections by tool
+    pub fn with_call_counters(mut self, call_counters: Arc<CallCounters>) -> Self {
+        self.call_counters = Some(call_counters);
+        self
+    }
+
+    /// Also report the backend's KV read cache hits and misses
+    pub fn with_aws_service(mut self, aws_service: Arc<dyn AwsApi>) -> Self {
+        self.aws_service = Some(aws_service);
+        self
+    }
+
+    /// Also report the tool response cache's hits and misses
+    pub fn with_response_cache(mut self, response_cache: Arc<ResponseCache>) -> Self {
+        self.response_cache = Some(response_cache);
+        self
+    }
+}
+
+#[async_trait]
+impl Handler for ServerStatsHandler {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let limiter = self.tenant_manager.get_aws_rate_limiter();
+        let mcp_servers: Vec<Value> = match &self.registry {
+            Some(registry) => registry
+                .metrics_report()
+                .await
+                .into_iter()
+                .map(|(connection, metrics)| json!({"connection": connection, "metrics": metrics}))
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut tool_calls: Vec<Value> = self
+            .call_counters
+            .iter()
+            .flat_map(|counters| counters.by_tool())
+            .map(|(tool, count)| {
+                json!({
+                    "tool": tool,
+                    "calls": count.calls,
+                    "errors": count.errors,
+                    "rate_limited": count.rate_limited,
+                })
+            })
+            .collect();
+        tool_calls.sort_by(|a, b| a["tool"].as_str().cmp(&b["tool"].as_str()));
+
+        Ok(json!({
+            "active_sessions": self.tenant_manager.get_all_sessions().await.len(),
+            "active_requests": self.tenant_manager.total_active_requests(),
+            "rate_limit_buckets": limiter.bucket_count().await,
+            "max_rate_limit_buckets": limiter.max_buckets(),
+            "rate_limit_failure_policy": limiter.failure_policy(),
+            "rate_limit_degraded_decisions": limiter.degraded_decisions(),
+            "tool_concurrency": self.tool_concurrency.in_flight(),
+            "tool_calls": tool_calls,
+            "kv_cache": self.aws_service.as_ref().and_then(|aws| aws.kv_cache_stats()),
+            "response_cache": self.response_cache.as_ref().map(|cache| cache.stats()),
+            "aws_latency": self.aws_service.as_ref().and_then(|aws| aws.latency_stats()),
+            "mcp_servers": mcp_servers,
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        // Counters span every tenant on this server
+        Some(Permission::Admin)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Show server-wide counters: active sessions, in-flight requests, per-tool in-flight calls, tool calls, errors and rate-limit rejections, rate limit bucket usage, KV read cache hits and misses, AWS call latency percentiles per service and downstream MCP server metrics",
+            "inputSchema": {
+                "type": "object",
+                "properties": {}
+            }
+        })
+    }
+}
@@ -0,0 +1,668 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::aws::internal_keys;
+use crate::aws::{AwsApi, AwsError};
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// A queue's state lives in one KV item in the tenant's namespace under this prefix,
+/// so every change to it is a single conditional write
+pub const TASK_QUEUE_KEY_PREFIX: &str = "_tasks:";
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+pub const MAX_ATTEMPTS: RangeInclusive<u32> = 1..=100;
+pub const DEFAULT_LEASE_SECONDS: u32 = 60;
+pub const LEASE_SECONDS: RangeInclusive<u32> = 1..=43_200;
+pub const DELAY_SECONDS: RangeInclusive<u32> = 0..=86_400;
+/// Dead-lettered tasks kept for inspection; the oldest are dropped beyond this
+pub const MAX_DEAD_LETTERS: usize = 50;
+/// Headroom under DynamoDB's 400KB item limit for the item's other attributes
+const MAX_QUEUE_BYTES: usize = 350 * 1024;
+/// Conditional writes tried before a busy queue is reported as a conflict
+const UPDATE_ATTEMPTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Pending,
+    Claimed,
+    DeadLettered,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub task_id: String,
+    pub payload: Value,
+    pub status: TaskStatus,
+    /// Claims so far, including the current one
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub created_at: DateTime<Utc>,
+    /// Before this the task can't be claimed: its creation delay or retry backoff
+    pub visible_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl Task {
+    fn lease_expired(&self, now: DateTime<Utc>) -> bool {
+        self.status == TaskStatus::Claimed && self.lease_expires_at.is_none_or(|at| at <= now)
+    }
+}
+
+/// Everything stored for one queue: live tasks oldest first, then the dead letters
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct QueueState {
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub dead_letters: Vec<Task>,
+}
+
+impl QueueState {
+    fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.dead_letters.is_empty()
+    }
+
+    /// Return tasks whose lease ran out to the queue, or dead-letter them when they
+    /// have no attempts left
+    fn expire_leases(&mut self, now: DateTime<Utc>) {
+        let (expired, live): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|task| task.lease_expired(now) && task.attempts >= task.max_attempts);
+        self.tasks = live;
+        for task in &mut self.tasks {
+            if task.lease_expired(now) {
+                task.status = TaskStatus::Pending;
+                task.visible_at = now;
+                task.lease_id = None;
+                task.lease_expires_at = None;
+                task.last_error = Some("lease expired".to_string());
+            }
+        }
+        for task in expired {
+            self.dead_letter(task, "lease expired".to_string());
+        }
+    }
+
+    /// Move `task` to the dead letters, returning it as stored there
+    fn dead_letter(&mut self, mut task: Task, error: String) -> Task {
+        task.status = TaskStatus::DeadLettered;
+        task.lease_id = None;
+        task.lease_expires_at = None;
+        task.last_error = Some(error);
+        self.dead_letters.push(task.clone());
+        let excess = self.dead_letters.len().saturating_sub(MAX_DEAD_LETTERS);
+        self.dead_letters.drain(..excess);
+        task
+    }
+
+    /// The claimed task `task_id`, if `lease_id` still holds it
+    fn leased_task(&self, task_id: &str, lease_id: &str) -> Result<usize, HandlerError> {
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| task.task_id == task_id)
+            .ok_or_else(|| AwsError::NotFound(format!("task {:?}", task_id)))?;
+        let task = &self.tasks[index];
+        if task.status != TaskStatus::Claimed || task.lease_id.as_deref() != Some(lease_id) {
+            return Err(AwsError::ConditionFailed(format!(
+                "lease {:?} no longer holds task {:?}",
+                lease_id, task_id
+            ))
+            .into());
+        }
+        Ok(index)
+    }
+}
+
+/// Reads and conditionally rewrites the queue items behind the task tools
+#[derive(Clone)]
+pub struct TaskQueues {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl TaskQueues {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+
+    fn key(queue: &str) -> String {
+        format!("{}{}", TASK_QUEUE_KEY_PREFIX, queue)
+    }
+
+    /// The queue's stored value, if it has one, and its parsed state
+    async fn read(
+        &self,
+        session: &TenantSession,
+        queue: &str,
+    ) -> Result<(Option<String>, QueueState), HandlerError> {
+        let stored = self
+            .aws_service
+            .kv_get_fresh(session, &Self::key(queue))
+            .await?;
+        let state = match &stored {
+            Some(value) => serde_json::from_str(value).map_err(|e| {
+                HandlerError::Internal(format!("task queue {:?} is corrupt: {}", queue, e))
+            })?,
+            None => QueueState::default(),
+        };
+        Ok((stored, state))
+    }
+
+    pub async fn state(
+        &self,
+        session: &TenantSession,
+        queue: &str,
+    ) -> Result<QueueState, HandlerError> {
+        Ok(self.read(session, queue).await?.1)
+    }
+
+    /// Apply `change` to the queue and write it back only if no one else wrote in
+    /// between, rereading and retrying when someone did. An unchanged queue isn't
+    /// written at all.
+    pub async fn update<T>(
+        &self,
+        session: &TenantSession,
+        queue: &str,
+        change: impl Fn(&mut QueueState, DateTime<Utc>) -> Result<T, HandlerError>,
+    ) -> Result<T, HandlerError> {
+        let key = Self::key(queue);
+        for _ in 0..UPDATE_ATTEMPTS {
+            let (stored, mut state) = self.read(session, queue).await?;
+            let output = change(&mut state, Utc::now())?;
+            let value =
+                serde_json::to_string(&state).map_err(|e| HandlerError::Internal(e.to_string()))?;
+            if stored.as_deref() == Some(value.as_str()) || (stored.is_none() && state.is_empty()) {
+                return Ok(output);
+            }
+            if value.len() > MAX_QUEUE_BYTES {
+                return Err(HandlerError::InvalidArguments(format!(
+                    "task queue {:?} would exceed {} bytes; complete or fail some tasks first",
+                    queue, MAX_QUEUE_BYTES
+                )));
+            }
+            if self
+                .aws_service
+                .kv_compare_and_swap(session, &key, stored.as_deref(), &value, None)
+                .await?
+            {
+                return Ok(output);
+            }
+        }
+        warn!(
+            "Task queue {} for tenant {} stayed contended over {} attempts",
+            queue, session.context.tenant_id, UPDATE_ATTEMPTS
+        );
+        Err(
+            AwsError::ConditionFailed(format!("task queue {:?} is busy; retry the call", queue))
+                .into(),
+        )
+    }
+}
+
+fn required_str<'a>(arguments: &'a Value, field: &str) -> Result<&'a str, HandlerError> {
+    arguments
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| HandlerError::InvalidArguments(format!("Missing '{}' parameter", field)))
+}
+
+fn queue_name(arguments: &Value) -> Result<&str, HandlerError> {
+    let queue = required_str(arguments, "queue")?;
+    internal_keys::validate_id("queue", queue).map_err(HandlerError::InvalidArguments)?;
+    Ok(queue)
+}
+
+/// The integer `field` if given, which must lie in `range`
+fn optional_u32(
+    arguments: &Value,
+    field: &str,
+    range: RangeInclusive<u32>,
+) -> Result<Option<u32>, HandlerError> {
+    match arguments.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .and_then(|value| u32::try_from(value).ok())
+            .filter(|value| range.contains(value))
+            .map(Some)
+            .ok_or_else(|| {
+                HandlerError::InvalidArguments(format!(
+                    "{} must be between {} and {}",
+                    field,
+                    range.start(),
+                    range.end()
+                ))
+            }),
+    }
+}
+
+fn seconds(seconds: u32) -> Duration {
+    Duration::seconds(seconds as i64)
+}
+
+fn queue_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Task queue name within the caller's context (lowercase letters, digits, '-' and '_')"
+    })
+}
+
+fn lease_properties() -> (Value, Value) {
+    (
+        json!({"type": "string", "description": "Task ID from task_claim"}),
+        json!({"type": "string", "description": "Lease ID from the task_claim that returned the task"}),
+    )
+}
+
+pub struct TaskCreateHandler {
+    queues: TaskQueues,
+}
+
+impl TaskCreateHandler {
+    pub fn new(queues: TaskQueues) -> Self {
+        Self { queues }
+    }
+}
+
+#[async_trait]
+impl Handler for TaskCreateHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = queue_name(&arguments)?;
+        let payload = arguments
+            .get("payload")
+            .cloned()
+            .ok_or_else(|| HandlerError::InvalidArguments("Missing 'payload' parameter".into()))?;
+        let delay = optional_u32(&arguments, "delay_seconds", DELAY_SECONDS)?.unwrap_or(0);
+        let max_attempts =
+            optional_u32(&arguments, "max_attempts", MAX_ATTEMPTS)?.unwrap_or(DEFAULT_MAX_ATTEMPTS);
+
+        let task_id = uuid::Uuid::new_v4().to_string();
+        let task = self
+            .queues
+            .update(session, queue, |state, now| {
+                let task = Task {
+                    task_id: task_id.clone(),
+                    payload: payload.clone(),
+                    status: TaskStatus::Pending,
+                    attempts: 0,
+                    max_attempts,
+                    created_at: now,
+                    visible_at: now + seconds(delay),
+                    lease_id: None,
+                    lease_expires_at: None,
+                    last_error: None,
+                };
+                state.tasks.push(task.clone());
+                Ok(task)
+            })
+            .await?;
+        info!(
+            "Created task {} in queue {} for tenant {}",
+            task.task_id, queue, session.context.tenant_id
+        );
+
+        Ok(json!({
+            "queue": queue,
+            "task_id": task.task_id,
+            "visible_at": task.visible_at.to_rfc3339(),
+            "max_attempts": task.max_attempts
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::WriteKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Add a task to one of the context's task queues for a worker to claim with task_claim",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "payload": {
+                        "description": "What the worker needs to do the task; any JSON value"
+                    },
+                    "delay_seconds": {
+                        "type": "integer",
+                        "minimum": DELAY_SECONDS.start(),
+                        "maximum": DELAY_SECONDS.end(),
+                        "description": "Seconds before the task can first be claimed (default: 0)"
+                    },
+                    "max_attempts": {
+                        "type": "integer",
+                        "minimum": MAX_ATTEMPTS.start(),
+                        "maximum": MAX_ATTEMPTS.end(),
+                        "description": format!("Claims allowed before the task is dead-lettered (default: {})", DEFAULT_MAX_ATTEMPTS)
+                    }
+                },
+                "required": ["queue", "payload"]
+            }
+        })
+    }
+}
+
+pub struct TaskClaimHandler {
+    queues: TaskQueues,
+}
+
+impl TaskClaimHandler {
+    pub fn new(queues: TaskQueues) -> Self {
+        Self { queues }
+    }
+}
+
+#[async_trait]
+impl Handler for TaskClaimHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = queue_name(&arguments)?;
+        let lease = optional_u32(&arguments, "lease_seconds", LEASE_SECONDS)?
+            .unwrap_or(DEFAULT_LEASE_SECONDS);
+
+        let claimed = self
+            .queues
+            .update(session, queue, |state, now| {
+                state.expire_leases(now);
+                let Some(task) = state
+                    .tasks
+                    .iter_mut()
+                    .find(|task| task.status == TaskStatus::Pending && task.visible_at <= now)
+                else {
+                    return Ok(None);
+                };
+                task.status = TaskStatus::Claimed;
+                task.attempts += 1;
+                task.lease_id = Some(uuid::Uuid::new_v4().to_string());
+                task.lease_expires_at = Some(now + seconds(lease));
+                Ok(Some(task.clone()))
+            })
+            .await?;
+
+        let Some(task) = claimed else {
+            return Ok(json!({"queue": queue, "task": null}));
+        };
+        info!(
+            "Claimed task {} in queue {} for tenant {} (attempt {})",
+            task.task_id, queue, session.context.tenant_id, task.attempts
+        );
+        Ok(json!({
+            "queue": queue,
+            "task": {
+                "task_id": task.task_id,
+                "payload": task.payload,
+                "attempt": task.attempts,
+                "max_attempts": task.max_attempts,
+                "lease_id": task.lease_id,
+                "lease_expires_at": task.lease_expires_at.map(|at| at.to_rfc3339()),
+                "last_error": task.last_error
+            }
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::WriteKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Claim the oldest claimable task in a queue under a lease; finish it with task_complete or task_fail before the lease expires, or it becomes claimable again. Returns task: null when nothing is claimable.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "lease_seconds": {
+                        "type": "integer",
+                        "minimum": LEASE_SECONDS.start(),
+                        "maximum": LEASE_SECONDS.end(),
+                        "description": format!("How long the claim holds (default: {})", DEFAULT_LEASE_SECONDS)
+                    }
+                },
+                "required": ["queue"]
+            }
+        })
+    }
+}
+
+pub struct TaskCompleteHandler {
+    queues: TaskQueues,
+}
+
+impl TaskCompleteHandler {
+    pub fn new(queues: TaskQueues) -> Self {
+        Self { queues }
+    }
+}
+
+#[async_trait]
+impl Handler for TaskCompleteHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = queue_name(&arguments)?;
+        let task_id = required_str(&arguments, "task_id")?;
+        let lease_id = required_str(&arguments, "lease_id")?;
+
+        let task = self
+            .queues
+            .update(session, queue, |state, _| {
+                let index = state.leased_task(task_id, lease_id)?;
+                Ok(state.tasks.remove(index))
+            })
+            .await?;
+        info!(
+            "Completed task {} in queue {} for tenant {}",
+            task_id, queue, session.context.tenant_id
+        );
+
+        Ok(json!({
+            "queue": queue,
+            "task_id": task_id,
+            "status": "completed",
+            "attempts": task.attempts
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::WriteKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        let (task_id, lease_id) = lease_properties();
+        json!({
+            "description": "Mark a claimed task done, removing it from its queue. Fails with a conflict when the lease no longer holds the task.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "task_id": task_id,
+                    "lease_id": lease_id
+                },
+                "required": ["queue", "task_id", "lease_id"]
+            }
+        })
+    }
+}
+
+pub struct TaskFailHandler {
+    queues: TaskQueues,
+}
+
+impl TaskFailHandler {
+    pub fn new(queues: TaskQueues) -> Self {
+        Self { queues }
+    }
+}
+
+#[async_trait]
+impl Handler for TaskFailHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = queue_name(&arguments)?;
+        let task_id = required_str(&arguments, "task_id")?;
+        let lease_id = required_str(&arguments, "lease_id")?;
+        let error = arguments
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("task failed")
+            .to_string();
+        let retry_delay =
+            optional_u32(&arguments, "retry_delay_seconds", DELAY_SECONDS)?.unwrap_or(0);
+
+        let task = self
+            .queues
+            .update(session, queue, |state, now| {
+                let index = state.leased_task(task_id, lease_id)?;
+                if state.tasks[index].attempts >= state.tasks[index].max_attempts {
+                    let task = state.tasks.remove(index);
+                    return Ok(state.dead_letter(task, error.clone()));
+                }
+                let task = &mut state.tasks[index];
+                task.status = TaskStatus::Pending;
+                task.visible_at = now + seconds(retry_delay);
+                task.lease_id = None;
+                task.lease_expires_at = None;
+                task.last_error = Some(error.clone());
+                Ok(task.clone())
+            })
+            .await?;
+        info!(
+            "Failed task {} in queue {} for tenant {} (attempt {}/{}, now {:?})",
+            task_id,
+            queue,
+            session.context.tenant_id,
+            task.attempts,
+            task.max_attempts,
+            task.status
+        );
+
+        Ok(json!({
+            "queue": queue,
+            "task_id": task_id,
+            "status": task.status,
+            "attempts": task.attempts,
+            "max_attempts": task.max_attempts,
+            "visible_at": (task.status == TaskStatus::Pending).then(|| task.visible_at.to_rfc3339())
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::WriteKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        let (task_id, lease_id) = lease_properties();
+        json!({
+            "description": "Give up on a claimed task: it's retried after retry_delay_seconds, or dead-lettered once it has used max_attempts claims",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "task_id": task_id,
+                    "lease_id": lease_id,
+                    "error": {
+                        "type": "string",
+                        "description": "Why the task failed, kept on the task as last_error"
+                    },
+                    "retry_delay_seconds": {
+                        "type": "integer",
+                        "minimum": DELAY_SECONDS.start(),
+                        "maximum": DELAY_SECONDS.end(),
+                        "description": "Seconds before a retried task can be claimed again (default: 0)"
+                    }
+                },
+                "required": ["queue", "task_id", "lease_id"]
+            }
+        })
+    }
+}
+
+pub struct TaskListHandler {
+    queues: TaskQueues,
+}
+
+impl TaskListHandler {
+    pub fn new(queues: TaskQueues) -> Self {
+        Self { queues }
+    }
+}
+
+#[async_trait]
+impl Handler for TaskListHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let queue = queue_name(&arguments)?;
+        let held = arguments.get("lease_id").and_then(|v| v.as_str());
+        // Shown as claimers would find it: expired leases returned or dead-lettered,
+        // without writing that back, and lease ids only to whoever holds the lease
+        let mut state = self.queues.state(session, queue).await?;
+        state.expire_leases(Utc::now());
+        for task in &mut state.tasks {
+            if task.lease_id.as_deref() != held {
+                task.lease_id = None;
+            }
+        }
+        let count = |status| {
+            state
+                .tasks
+                .iter()
+                .filter(|task| task.status == status)
+                .count()
+        };
+
+        Ok(json!({
+            "queue": queue,
+            "pending": count(TaskStatus::Pending),
+            "claimed": count(TaskStatus::Claimed),
+            "tasks": state.tasks,
+            "dead_letters": state.dead_letters
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ReadKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": format!("Inspect a task queue: its pending and claimed tasks oldest first, and its last {} dead-lettered tasks", MAX_DEAD_LETTERS),
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queue": queue_property(),
+                    "lease_id": {
+                        "type": "string",
+                        "description": "Lease ID from task_claim; the task it holds is listed with it, other claimed tasks without theirs"
+                    }
+                },
+                "required": ["queue"]
+            }
+        })
+    }
+}
@@ -0,0 +1,1133 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::aws::namespaces::EventScope;
+use crate::aws::transactions::{ItemCondition, KvWriteOp, MAX_TRANSACT_OPS};
+use crate::aws::{self, internal_keys, AwsApi, AwsError};
+use crate::handlers::dry_run::{CONFIRM_ARG, DRY_RUN_ARG};
+use crate::handlers::jobs::callbacks::callback_schema;
+use crate::handlers::jobs::{JobContext, JobManager, JOBS_KEY};
+use crate::handlers::{Handler, HandlerError};
+use crate::redaction;
+use crate::registry::{instance_id, MCPServerRegistry, RegistryError};
+use crate::tenant::{Permission, TenantContext, TenantSession};
+
+/// Job type, and tool name, of exports
+pub const EXPORT_JOB_TYPE: &str = "tenant_data_export";
+
+/// Job type, and tool name, of deletions
+pub const DELETE_JOB_TYPE: &str = "tenant_data_delete";
+
+/// Detail type of the system event recording that a tenant's data was deleted
+pub const DATA_DELETED_DETAIL_TYPE: &str = "tenant.data_deleted";
+
+/// KV item holding the confirm token of the latest `tenant_data_delete` dry run
+pub const CONFIRMATION_KEY: &str = "_tenant_data_delete";
+
+/// How long a dry run's confirm token can be used
+pub const CONFIRMATION_TTL_MINUTES: i64 = 15;
+
+/// Layout version of the archive's manifest
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Name of the archive's manifest, under its prefix
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// Records read per page, and written per archive part
+const PAGE_SIZE: usize = 200;
+
+/// Artifacts per archive part when their content is included
+const CONTENT_PAGE_SIZE: usize = 20;
+
+/// One kind of data kept for a tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    /// KV items of each user's namespace
+    Kv,
+    /// Artifacts of each user's namespace, including those under the legacy prefix
+    Artifacts,
+    /// Event records in each user's `events_query` scope
+    Events,
+    Rules,
+    /// Alert subscriptions
+    Alerts,
+    /// Registered integrations, with `${credential:name}` placeholders for secrets
+    Integrations,
+    /// Integration connections made in this tenant, personal and organization ones
+    Connections,
+    /// Names of each user's secrets; values are never exported
+    Secrets,
+}
+
+impl Section {
+    /// The order sections are exported and counted in
+    pub const EXPORT_ORDER: [Section; 8] = [
+        Section::Kv,
+        Section::Artifacts,
+        Section::Events,
+        Section::Rules,
+        Section::Alerts,
+        Section::Integrations,
+        Section::Connections,
+        Section::Secrets,
+    ];
+
+    /// The order sections are deleted in: what references other data (running
+    /// integrations, alerts on rules) goes first, the KV namespace last
+    pub const DELETION_ORDER: [Section; 8] = [
+        Section::Integrations,
+        Section::Connections,
+        Section::Secrets,
+        Section::Alerts,
+        Section::Rules,
+        Section::Events,
+        Section::Artifacts,
+        Section::Kv,
+    ];
+
+    /// Whether the section is kept in each user's namespace, rather than once for the
+    /// tenant
+    fn per_user(self) -> bool {
+        !matches!(self, Section::Integrations | Section::Connections)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Section::Kv => "kv",
+            Section::Artifacts => "artifacts",
+            Section::Events => "events",
+            Section::Rules => "rules",
+            Section::Alerts => "alerts",
+            Section::Integrations => "integrations",
+            Section::Connections => "connections",
+            Section::Secrets => "secrets",
+        }
+    }
+}
+
+/// Where a page of a section kept per user continues: the user whose records are being
+/// read, and the cursor within them
+#[derive(Debug, Serialize, Deserialize)]
+struct UserCursor {
+    user: String,
+    cursor: Option<String>,
+}
+
+/// Where a read of the session's events continues: scoped queries can't be continued
+/// from a key on every backend, so pages go on from the last timestamp read, skipping
+/// the events at that timestamp already returned
+#[derive(Debug, Serialize, Deserialize)]
+struct EventCursor {
+    after: String,
+    seen: Vec<String>,
+}
+
+fn internal(error: impl std::fmt::Display) -> HandlerError {
+    HandlerError::Internal(error.to_string())
+}
+
+/// KV items the tools keep for themselves, neither exported nor deleted: the job list
+/// (which must outlive a deletion to report it) and the pending confirmation
+fn is_internal_key(key: &str) -> bool {
+    key == JOBS_KEY || key == CONFIRMATION_KEY
+}
+
+/// Everything stored for the session's tenant, read a page at a time: what's kept per
+/// user for each user with data in the tenant, and what's kept once for the tenant
+struct TenantData<'a> {
+    aws_service: &'a dyn AwsApi,
+    registry: &'a MCPServerRegistry,
+    session: &'a TenantSession,
+    /// Users with data in the tenant, sorted (see [`AwsApi::namespace_users`])
+    users: Vec<String>,
+    /// Artifacts under this prefix are skipped: the archive being written
+    exclude_prefix: Option<String>,
+}
+
+impl<'a> TenantData<'a> {
+    async fn new(
+        aws_service: &'a dyn AwsApi,
+        registry: &'a MCPServerRegistry,
+        session: &'a TenantSession,
+        exclude_prefix: Option<String>,
+    ) -> Result<Self, HandlerError> {
+        Ok(Self {
+            aws_service,
+            registry,
+            session,
+            users: aws_service.namespace_users(session).await?,
+            exclude_prefix,
+        })
+    }
+
+    /// A session of `user_id` in the caller's tenant and context, reaching that user's
+    /// namespace
+    fn member(&self, user_id: &str) -> TenantSession {
+        TenantSession::new(TenantContext {
+            user_id: user_id.to_string(),
+            ..self.session.context.clone()
+        })
+    }
+
+    /// One page of `section`'s records, and the cursor of the next page (`None` after
+    /// the last). Records carry what identifies them, and those kept per user the
+    /// `userId`; [`TenantData::enrich`] fills in KV values and artifact metadata for an
+    /// export.
+    async fn page(
+        &self,
+        section: Section,
+        cursor: Option<String>,
+        page_size: usize,
+    ) -> Result<(Vec<Value>, Option<String>), HandlerError> {
+        if !section.per_user() {
+            return self.tenant_page(section).await;
+        }
+        let cursor: Option<UserCursor> = cursor
+            .map(|cursor| serde_json::from_str(&cursor))
+            .transpose()
+            .map_err(internal)?;
+        // A user whose data is gone since the cursor was written is passed over
+        let index = cursor.as_ref().map_or(0, |cursor| {
+            self.users.partition_point(|user| *user < cursor.user)
+        });
+        let Some(user) = self.users.get(index) else {
+            return Ok((Vec::new(), None));
+        };
+        let inner = cursor
+            .filter(|cursor| cursor.user == *user)
+            .and_then(|cursor| cursor.cursor);
+        let (mut records, next) = self
+            .user_page(&self.member(user), section, inner, page_size)
+            .await?;
+        for record in &mut records {
+            if let Value::Object(record) = record {
+                record
+                    .entry("userId")
+                    .or_insert_with(|| json!(user.as_str()));
+            }
+        }
+        let next = match next {
+            Some(cursor) => Some(UserCursor {
+                user: user.clone(),
+                cursor: Some(cursor),
+            }),
+            None => self.users.get(index + 1).map(|user| UserCursor {
+                user: user.clone(),
+                cursor: None,
+            }),
+        };
+        let next = next
+            .map(|next| serde_json::to_string(&next))
+            .transpose()
+            .map_err(internal)?;
+        Ok((records, next))
+    }
+
+    /// One page of the records of one user (`session`) in a section kept per user
+    async fn user_page(
+        &self,
+        session: &TenantSession,
+        section: Section,
+        cursor: Option<String>,
+        page_size: usize,
+    ) -> Result<(Vec<Value>, Option<String>), HandlerError> {
+        match section {
+            Section::Kv => {
+                let (keys, next) = self
+                    .aws_service
+                    .kv_keys(session, cursor.as_deref(), page_size)
+                    .await?;
+                let records = keys
+                    .into_iter()
+                    .filter(|key| !is_internal_key(key))
+                    .map(|key| json!({ "key": key }))
+                    .collect();
+                Ok((records, next))
+            }
+            Section::Artifacts => {
+                let (mut keys, next) = self
+                    .aws_service
+                    .artifacts_keys(session, cursor.as_deref(), page_size)
+                    .await?;
+                // The archive is written to the caller's namespace
+                let exclude_prefix = self
+                    .exclude_prefix
+                    .as_deref()
+                    .filter(|_| session.context.user_id == self.session.context.user_id);
+                keys.retain(|key| exclude_prefix.is_none_or(|prefix| !key.starts_with(prefix)));
+                Ok((
+                    keys.into_iter().map(|key| json!({ "key": key })).collect(),
+                    next,
+                ))
+            }
+            Section::Events => self.events_page(session, cursor, page_size).await,
+            Section::Rules => Ok((self.aws_service.event_rules(session).await?, None)),
+            Section::Alerts => Ok((self.aws_service.alert_subscriptions(session).await?, None)),
+            Section::Secrets => {
+                let prefix = aws::tenant_secret_prefix(session);
+                let names = self
+                    .aws_service
+                    .secret_names(&prefix)
+                    .await?
+                    .iter()
+                    .filter_map(|name| name.strip_prefix(&prefix))
+                    .map(|name| json!({ "name": name }))
+                    .collect();
+                Ok((names, None))
+            }
+            Section::Integrations | Section::Connections => self.tenant_page(section).await,
+        }
+    }
+
+    /// All of the records of a section kept once for the tenant
+    async fn tenant_page(
+        &self,
+        section: Section,
+    ) -> Result<(Vec<Value>, Option<String>), HandlerError> {
+        let session = self.session;
+        let context_id = session.context.get_context_id();
+        match section {
+            Section::Integrations => {
+                let export = self
+                    .registry
+                    .export_servers(&context_id)
+                    .await
+                    .map_err(internal)?;
+                let servers = export
+                    .servers
+                    .iter()
+                    .map(serde_json::to_value)
+                    .collect::<Result<_, _>>()
+                    .map_err(internal)?;
+                Ok((servers, None))
+            }
+            Section::Connections => {
+                // Connections kept from before they were kept per tenant are found under
+                // the tenant's users, and checked to be the tenant's
+                let mut prefixes = vec![internal_keys::tenant_connection_prefix(
+                    &session.context.tenant_id,
+                )];
+                prefixes.extend(
+                    self.users
+                        .iter()
+                        .map(|user| internal_keys::legacy_connection_prefix(user)),
+                );
+                prefixes.extend(
+                    session
+                        .context
+                        .get_org_id()
+                        .map(|org_id| internal_keys::org_connection_prefix(&org_id)),
+                );
+                let mut connections = Vec::new();
+                for prefix in prefixes {
+                    for key in self.aws_service.kv_list(&prefix).await? {
+                        let Some(stored) = self.aws_service.kv_get_direct(&key).await? else {
+                            continue;
+                        };
+                        let Ok(mut connection) = serde_json::from_str::<Value>(&stored) else {
+                            warn!("Skipping malformed integration connection {}", key);
+                            continue;
+                        };
+                        if connection["tenant_id"] != session.context.tenant_id.as_str() {
+                            continue;
+                        }
+                        // Only credential field names are stored, but settings are free-form
+                        redaction::redact(&mut connection);
+                        connection["key"] = json!(key);
+                        connections.push(connection);
+                    }
+                }
+                Ok((connections, None))
+            }
+            _ => Ok((Vec::new(), None)),
+        }
+    }
+
+    /// A page of one user's events, oldest first (see [`EventCursor`])
+    async fn events_page(
+        &self,
+        session: &TenantSession,
+        cursor: Option<String>,
+        page_size: usize,
+    ) -> Result<(Vec<Value>, Option<String>), HandlerError> {
+        let cursor: Option<EventCursor> = cursor
+            .map(|cursor| serde_json::from_str(&cursor))
+            .transpose()
+            .map_err(internal)?;
+        let scope = EventScope::for_session(session);
+        let page = self
+            .aws_service
+            .query_events(
+                session,
+                Some(&scope),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                cursor.as_ref().map(|cursor| cursor.after.clone()),
+                None,
+                page_size as i32,
+                None,
+                true,
+            )
+            .await?;
+        let events = page["events"].as_array().cloned().unwrap_or_default();
+        let full = events.len() >= page_size;
+        let events: Vec<Value> = events
+            .into_iter()
+            .filter(|event| {
+                cursor.as_ref().is_none_or(|cursor| {
+                    !cursor
+                        .seen
+                        .iter()
+                        .any(|seen| event["eventId"] == seen.as_str())
+                })
+            })
+            .collect();
+
+        let Some(last) = events.last() else {
+            if full {
+                warn!(
+                    "Over {} events of user {} in tenant {} share one timestamp; the rest are skipped",
+                    page_size, session.context.user_id, session.context.tenant_id
+                );
+            }
+            return Ok((events, None));
+        };
+        let after = last["timestamp"].as_str().unwrap_or_default().to_string();
+        let mut seen = match cursor {
+            Some(cursor) if cursor.after == after => cursor.seen,
+            _ => Vec::new(),
+        };
+        seen.extend(
+            events
+                .iter()
+                .filter(|event| event["timestamp"] == after.as_str())
+                .filter_map(|event| event["eventId"].as_str().map(str::to_string)),
+        );
+        let next = full
+            .then(|| serde_json::to_string(&EventCursor { after, seen }))
+            .transpose()
+            .map_err(internal)?;
+        Ok((events, next))
+    }
+
+    /// Records as exported: KV keys with their values, and artifact keys with their
+    /// metadata and, with `include_content`, their content in base64. Anything deleted
+    /// since the page was read is left out.
+    async fn enrich(
+        &self,
+        section: Section,
+        records: Vec<Value>,
+        include_content: bool,
+    ) -> Result<Vec<Value>, HandlerError> {
+        let mut enriched = Vec::with_capacity(records.len());
+        for mut record in records {
+            let key = record["key"].as_str().unwrap_or_default().to_string();
+            let member = self.member(record["userId"].as_str().unwrap_or_default());
+            match section {
+                Section::Kv => {
+                    let Some(value) = self.aws_service.kv_get_fresh(&member, &key).await? else {
+                        continue;
+                    };
+                    record["value"] = json!(value);
+                }
+                Section::Artifacts => {
+                    let Some(metadata) = self.aws_service.artifacts_head(&member, &key).await?
+                    else {
+                        continue;
+                    };
+                    record["size"] = json!(metadata.size);
+                    record["contentType"] = json!(metadata.content_type);
+                    record["lastModified"] = json!(metadata.last_modified);
+                    if include_content {
+                        let Some(content) = self.aws_service.artifacts_get(&member, &key).await?
+                        else {
+                            continue;
+                        };
+                        record["content"] = json!(general_purpose::STANDARD.encode(content));
+                    }
+                }
+                _ => {}
+            }
+            enriched.push(record);
+        }
+        Ok(enriched)
+    }
+
+    /// Records in each section, in export order
+    async fn inventory(&self) -> Result<BTreeMap<String, u64>, HandlerError> {
+        let mut inventory = BTreeMap::new();
+        for section in Section::EXPORT_ORDER {
+            let mut count = 0;
+            let mut cursor = None;
+            loop {
+                let (records, next) = self.page(section, cursor, PAGE_SIZE).await?;
+                count += records.len() as u64;
+                match next {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+            inventory.insert(section.name().to_string(), count);
+        }
+        Ok(inventory)
+    }
+
+    /// Delete a page of `section`'s records as [`TenantData::page`] returned them, or
+    /// with artifacts every one of the page's user at once; returns how many went
+    async fn delete(&self, section: Section, records: &[Value]) -> Result<u64, HandlerError> {
+        let id =
+            |record: &Value, field: &str| record[field].as_str().unwrap_or_default().to_string();
+        let Some(first) = records.first() else {
+            return Ok(0);
+        };
+        // A page of a section kept per user is one user's
+        let member = self.member(first["userId"].as_str().unwrap_or_default());
+        let session = if section.per_user() {
+            &member
+        } else {
+            self.session
+        };
+        let context_id = session.context.get_context_id();
+        match section {
+            Section::Kv => {
+                let keys: Vec<String> = records.iter().map(|record| id(record, "key")).collect();
+                for chunk in keys.chunks(MAX_TRANSACT_OPS) {
+                    let ops: Vec<KvWriteOp> = chunk
+                        .iter()
+                        .map(|key| KvWriteOp::Delete {
+                            key: key.clone(),
+                            condition: None,
+                        })
+                        .collect();
+                    self.aws_service.kv_transact_write(session, &ops).await?;
+                }
+            }
+            Section::Artifacts => {
+                let deletion = self
+                    .aws_service
+                    .artifacts_delete_prefix(session, "", false)
+                    .await?;
+                return Ok(deletion.keys.len() as u64);
+            }
+            Section::Events => {
+                let event_ids: Vec<String> =
+                    records.iter().map(|record| id(record, "eventId")).collect();
+                self.aws_service.delete_events(session, &event_ids).await?;
+            }
+            Section::Rules => {
+                for record in records {
+                    let rule_id = id(record, "ruleId");
+                    match self.aws_service.delete_event_rule(session, &rule_id).await {
+                        Ok(()) | Err(AwsError::NotFound(_)) => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+            Section::Alerts => {
+                for record in records {
+                    let subscription_id = id(record, "subscriptionId");
+                    match self
+                        .aws_service
+                        .delete_alert_subscription(session, &subscription_id)
+                        .await
+                    {
+                        Ok(()) | Err(AwsError::NotFound(_)) => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+            Section::Integrations => {
+                // Removes each service's named connections, config and credentials too
+                for record in records {
+                    let server_id = record["config"]["id"].as_str().unwrap_or_default();
+                    match self
+                        .registry
+                        .unregister_server(&context_id, server_id)
+                        .await
+                    {
+                        Ok(()) | Err(RegistryError::ServerNotFound(_)) => {}
+                        Err(e) => return Err(internal(e)),
+                    }
+                }
+            }
+            Section::Connections => {
+                for record in records {
+                    let instance = instance_id(
+                        record["service_id"].as_str().unwrap_or_default(),
+                        record["connection_id"].as_str(),
+                    );
+                    if let Err(e) = self
+                        .registry
+                        .disconnect_server(&context_id, &instance)
+                        .await
+                    {
+                        warn!("Could not disconnect {}: {}", instance, e);
+                    }
+                    if let Err(e) = self
+                        .registry
+                        .delete_credentials(&context_id, &instance)
+                        .await
+                    {
+                        warn!("Could not delete the credentials of {}: {}", instance, e);
+                    }
+                    self.aws_service.kv_delete(&id(record, "key")).await?;
+                }
+            }
+            Section::Secrets => {
+                let prefix = aws::tenant_secret_prefix(session);
+                for record in records {
+                    let name = format!("{}{}", prefix, id(record, "name"));
+                    self.aws_service.secret_delete(&name, true).await?;
+                }
+            }
+        }
+        Ok(records.len() as u64)
+    }
+
+    /// Delete the records kept for the tenant outside any namespace: responses kept for
+    /// idempotent replays, and cached `analytics_query` results of the organization and
+    /// each user; returns how many went
+    async fn delete_internal_records(&self) -> Result<u64, HandlerError> {
+        let session = self.session;
+        let organization_id = session.context.organization_id.as_str();
+        let mut prefixes = vec![
+            internal_keys::idempotency_prefix(&session.context.tenant_id),
+            internal_keys::analytics_cache_prefix(&aws::analytics_scope(
+                session,
+                None,
+                Some(organization_id),
+            )),
+        ];
+        prefixes.extend(self.users.iter().map(|user| {
+            internal_keys::analytics_cache_prefix(&aws::analytics_scope(session, Some(user), None))
+        }));
+        let mut deleted = 0;
+        for prefix in prefixes {
+            for key in self.aws_service.kv_list(&prefix).await? {
+                self.aws_service.kv_delete(&key).await?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// How far an export got: the section it's in and the cursor of its next page, with
+/// what was written so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ExportCheckpoint {
+    /// Key prefix of the archive's parts and manifest
+    prefix: String,
+    started_at: Option<DateTime<Utc>>,
+    include_content: bool,
+    /// Index into [`Section::EXPORT_ORDER`]
+    section: usize,
+    cursor: Option<String>,
+    /// Parts written for the current section
+    part: usize,
+    sections: BTreeMap<String, SectionArchive>,
+}
+
+/// A section of the archive: how many records it has, and the parts holding them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SectionArchive {
+    count: u64,
+    parts: Vec<String>,
+}
+
+/// Write the session's data to the archive a page at a time, checkpointing after each
+/// part, then the manifest
+async fn export(
+    data: &TenantData<'_>,
+    job: &JobContext,
+    mut checkpoint: ExportCheckpoint,
+) -> Result<Value, HandlerError> {
+    let session = data.session;
+    while let Some(section) = Section::EXPORT_ORDER.get(checkpoint.section).copied() {
+        let page_size = match section {
+            Section::Artifacts if checkpoint.include_content => CONTENT_PAGE_SIZE,
+            _ => PAGE_SIZE,
+        };
+        let (records, next) = data
+            .page(section, checkpoint.cursor.clone(), page_size)
+            .await?;
+        let records = data
+            .enrich(section, records, checkpoint.include_content)
+            .await?;
+        if !records.is_empty() {
+            checkpoint.part += 1;
+            let key = format!(
+                "{}/{}/{:05}.json",
+                checkpoint.prefix,
+                section.name(),
+                checkpoint.part
+            );
+            let part = serde_json::to_vec(&records).map_err(internal)?;
+            data.aws_service
+                .artifacts_put(session, &key, &part, "application/json", None)
+                .await?;
+            let archived = checkpoint
+                .sections
+                .entry(section.name().to_string())
+                .or_default();
+            archived.count += records.len() as u64;
+            archived.parts.push(key);
+        }
+        match next {
+            Some(next) => checkpoint.cursor = Some(next),
+            None => {
+                checkpoint
+                    .sections
+                    .entry(section.name().to_string())
+                    .or_default();
+                checkpoint.section += 1;
+                checkpoint.cursor = None;
+                checkpoint.part = 0;
+            }
+        }
+        job.checkpoint(serde_json::to_value(&checkpoint).map_err(internal)?);
+        let exported: u64 = checkpoint
+            .sections
+            .values()
+            .map(|section| section.count)
+            .sum();
+        job.progress(
+            exported,
+            None,
+            Some(format!("exporting {}", section.name())),
+        );
+    }
+
+    let counts: BTreeMap<&String, u64> = checkpoint
+        .sections
+        .iter()
+        .map(|(name, section)| (name, section.count))
+        .collect();
+    let manifest = json!({
+        "version": ARCHIVE_VERSION,
+        "tenantId": session.context.tenant_id,
+        "userId": session.context.user_id,
+        "organizationId": session.context.organization_id,
+        "namespace": session.context.get_namespace_prefix(),
+        "users": data.users,
+        "startedAt": checkpoint.started_at,
+        "exportedAt": Utc::now(),
+        "includeContent": checkpoint.include_content,
+        "sections": checkpoint.sections,
+    });
+    let key = format!("{}/{}", checkpoint.prefix, MANIFEST_NAME);
+    let document = serde_json::to_vec_pretty(&manifest).map_err(internal)?;
+    data.aws_service
+        .artifacts_put(session, &key, &document, "application/json", None)
+        .await?;
+    info!(
+        "Exported the data of tenant {} to {}",
+        session.context.tenant_id, checkpoint.prefix
+    );
+    Ok(json!({
+        "key": key,
+        "prefix": checkpoint.prefix,
+        "counts": counts
+    }))
+}
+
+/// How far a deletion got: the section it's in, the cursor of its next page, and what
+/// was deleted so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DeleteCheckpoint {
+    /// Index into [`Section::DELETION_ORDER`]
+    section: usize,
+    cursor: Option<String>,
+    deleted: BTreeMap<String, u64>,
+}
+
+/// Delete the session's data a page at a time, checkpointing after each, then drop its
+/// other finished jobs and record the deletion with a system event
+async fn delete(
+    data: &TenantData<'_>,
+    jobs: &JobManager,
+    job: &JobContext,
+    mut checkpoint: DeleteCheckpoint,
+) -> Result<Value, HandlerError> {
+    let session = data.session;
+    while let Some(section) = Section::DELETION_ORDER.get(checkpoint.section).copied() {
+        let (records, next) = data
+            .page(section, checkpoint.cursor.clone(), PAGE_SIZE)
+            .await?;
+        let deleted = data.delete(section, &records).await?;
+        *checkpoint
+            .deleted
+            .entry(section.name().to_string())
+            .or_default() += deleted;
+        match next {
+            Some(next) => checkpoint.cursor = Some(next),
+            None => {
+                checkpoint.section += 1;
+                checkpoint.cursor = None;
+            }
+        }
+        job.checkpoint(serde_json::to_value(&checkpoint).map_err(internal)?);
+        job.progress(
+            checkpoint.deleted.values().sum(),
+            None,
+            Some(format!("deleting {}", section.name())),
+        );
+    }
+
+    let internal_records = data.delete_internal_records().await?;
+    checkpoint
+        .deleted
+        .insert("internal".to_string(), internal_records);
+    let job_id = job.job_id();
+    let forgotten_jobs = jobs.forget_finished(session, &job_id).await?;
+    let audit = json!({
+        "tenantId": session.context.tenant_id,
+        "userId": session.context.user_id,
+        "organizationId": session.context.organization_id,
+        "namespace": session.context.get_namespace_prefix(),
+        "jobId": job_id,
+        "deleted": checkpoint.deleted,
+        "forgottenJobs": forgotten_jobs,
+        "deletedAt": Utc::now(),
+    });
+    data.aws_service
+        .send_system_event(DATA_DELETED_DETAIL_TYPE, audit)
+        .await?;
+    info!(
+        "Deleted the data of tenant {} (job {}): {:?}",
+        session.context.tenant_id, job_id, checkpoint.deleted
+    );
+    Ok(json!({
+        "deleted": checkpoint.deleted,
+        "forgottenJobs": forgotten_jobs,
+        "auditEvent": DATA_DELETED_DETAIL_TYPE
+    }))
+}
+
+fn resume_job_id(arguments: &Value) -> Option<&str> {
+    arguments.get("resume_job_id").and_then(|v| v.as_str())
+}
+
+/// The checkpoint to start from: the resumed job's, or a fresh one
+async fn start_from<T: Default + for<'de> Deserialize<'de>>(
+    jobs: &JobManager,
+    session: &TenantSession,
+    arguments: &Value,
+    job_type: &str,
+) -> Result<(T, Option<String>), HandlerError> {
+    let Some(resumed) = resume_job_id(arguments) else {
+        return Ok((T::default(), None));
+    };
+    let checkpoint = match jobs.resume_point(session, resumed, job_type).await? {
+        Some(checkpoint) => serde_json::from_value(checkpoint).map_err(internal)?,
+        None => T::default(),
+    };
+    Ok((checkpoint, Some(resumed.to_string())))
+}
+
+fn resume_schema(job_type: &str) -> Value {
+    json!({
+        "type": "string",
+        "description": format!(
+            "Carry on from where a failed, cancelled or interrupted {} job stopped",
+            job_type
+        )
+    })
+}
+
+/// Collects everything the server keeps for the caller's tenant, across the namespaces
+/// of every user with data in it (KV items, artifact listing with optional content,
+/// stored events, rules, alert subscriptions, integration configs and connections,
+/// secret names; never credential values) into an
+/// archive in the artifacts bucket, as a background job. The archive is a set of JSON
+/// parts, one per page of a section, and a manifest listing them.
+pub struct TenantDataExportHandler {
+    aws_service: Arc<dyn AwsApi>,
+    registry: Arc<MCPServerRegistry>,
+    jobs: Arc<JobManager>,
+}
+
+impl TenantDataExportHandler {
+    pub fn new(
+        aws_service: Arc<dyn AwsApi>,
+        registry: Arc<MCPServerRegistry>,
+        jobs: Arc<JobManager>,
+    ) -> Self {
+        Self {
+            aws_service,
+            registry,
+            jobs,
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for TenantDataExportHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let (mut checkpoint, resumed_from): (ExportCheckpoint, _) =
+            start_from(&self.jobs, session, &arguments, EXPORT_JOB_TYPE).await?;
+        let prefix = arguments
+            .get("prefix")
+            .and_then(|v| v.as_str())
+            .map(|prefix| prefix.trim_end_matches('/').to_string());
+        let include_content = arguments.get("include_content") == Some(&Value::Bool(true));
+        let callback = self.jobs.callback_from(&arguments)?;
+
+        let aws_service = self.aws_service.clone();
+        let registry = self.registry.clone();
+        let job_session = session.clone();
+        let record = self
+            .jobs
+            .spawn(session, EXPORT_JOB_TYPE, callback, move |job| async move {
+                if checkpoint.prefix.is_empty() {
+                    checkpoint.prefix =
+                        prefix.unwrap_or_else(|| format!("exports/tenant-data-{}", job.job_id()));
+                    checkpoint.started_at = Some(Utc::now());
+                    checkpoint.include_content = include_content;
+                }
+                let data = TenantData::new(
+                    aws_service.as_ref(),
+                    registry.as_ref(),
+                    &job_session,
+                    Some(format!("{}/", checkpoint.prefix)),
+                )
+                .await?;
+                export(&data, &job, checkpoint).await
+            })
+            .await?;
+
+        Ok(json!({
+            "job_id": record.job_id,
+            "state": record.state,
+            "resumedFrom": resumed_from
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Export everything stored for the caller's tenant (KV items, artifacts, events, rules, alert subscriptions, integration configs and connections, secret names; never credentials) to an archive of JSON parts with a manifest in the artifacts bucket, as a background job. Returns a job_id; the finished job's result has the manifest key and the records per section. A failed or interrupted export can be resumed with resume_job_id",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "prefix": {
+                        "type": "string",
+                        "description": "Artifact key prefix of the archive (default exports/tenant-data-{job_id})"
+                    },
+                    "include_content": {
+                        "type": "boolean",
+                        "description": "Include each artifact's content, base64-encoded, not just its listing (default false)"
+                    },
+                    "resume_job_id": resume_schema(EXPORT_JOB_TYPE),
+                    "callback": callback_schema()
+                }
+            }
+        })
+    }
+}
+
+/// A dry run's confirm token, kept under [`CONFIRMATION_KEY`] until it's used or
+/// expires
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingDeletion {
+    confirm: String,
+    expires_at: DateTime<Utc>,
+    inventory: BTreeMap<String, u64>,
+}
+
+/// Deletes everything [`TenantDataExportHandler`] exports, plus the secrets themselves
+/// and the tenant's idempotency records and cached analytics, across services, as a
+/// background job, then records a `tenant.data_deleted` system
+/// event. A dry run reports what there is and returns a confirm token; only a call
+/// carrying the latest token, within 15 minutes, deletes anything. Calls made after the
+/// deletion are stored as usual, and the deletion's own job record is kept so its
+/// outcome can be read.
+pub struct TenantDataDeleteHandler {
+    aws_service: Arc<dyn AwsApi>,
+    registry: Arc<MCPServerRegistry>,
+    jobs: Arc<JobManager>,
+}
+
+impl TenantDataDeleteHandler {
+    pub fn new(
+        aws_service: Arc<dyn AwsApi>,
+        registry: Arc<MCPServerRegistry>,
+        jobs: Arc<JobManager>,
+    ) -> Self {
+        Self {
+            aws_service,
+            registry,
+            jobs,
+        }
+    }
+
+    /// Count what would be deleted and issue a confirm token for it
+    async fn dry_run(&self, session: &TenantSession) -> Result<Value, HandlerError> {
+        let data = TenantData::new(
+            self.aws_service.as_ref(),
+            self.registry.as_ref(),
+            session,
+            None,
+        )
+        .await?;
+        let pending = PendingDeletion {
+            confirm: uuid::Uuid::new_v4().to_string(),
+            expires_at: Utc::now() + chrono::Duration::minutes(CONFIRMATION_TTL_MINUTES),
+            inventory: data.inventory().await?,
+        };
+        let value = serde_json::to_string(&pending).map_err(internal)?;
+        self.aws_service
+            .kv_set(session, CONFIRMATION_KEY, &value, Some(1), false)
+            .await?;
+        Ok(json!({
+            "inventory": pending.inventory,
+            "confirm": pending.confirm,
+            "expiresAt": pending.expires_at
+        }))
+    }
+
+    /// Use up the latest dry run's token, if `token` is it and it hasn't expired
+    async fn consume(&self, session: &TenantSession, token: &str) -> Result<(), HandlerError> {
+        let rejected = || {
+            HandlerError::InvalidArguments(
+                "confirm doesn't match an unused token from the latest dry run, or it expired; run tenant_data_delete with dry_run: true for a new one".to_string(),
+            )
+        };
+        let pending: PendingDeletion = match self
+            .aws_service
+            .kv_get_fresh(session, CONFIRMATION_KEY)
+            .await?
+        {
+            Some(stored) => serde_json::from_str(&stored).map_err(internal)?,
+            None => return Err(rejected()),
+        };
+        if pending.confirm != token || pending.expires_at < Utc::now() {
+            return Err(rejected());
+        }
+        // Only one call gets to delete the token
+        let ops = [KvWriteOp::Delete {
+            key: CONFIRMATION_KEY.to_string(),
+            condition: Some(ItemCondition::Exists),
+        }];
+        match self.aws_service.kv_transact_write(session, &ops).await {
+            Ok(()) => Ok(()),
+            Err(AwsError::TransactionCanceled(_)) => Err(rejected()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// A deletion mustn't race the tenant's other jobs, which would write data back
+    async fn check_no_running_jobs(&self, session: &TenantSession) -> Result<(), HandlerError> {
+        match self
+            .jobs
+            .list(session)
+            .await?
+            .into_iter()
+            .find(|job| !job.state.is_finished())
+        {
+            Some(job) => Err(HandlerError::InvalidArguments(format!(
+                "job {} ({}) is still running; wait for it or cancel it first",
+                job.job_id, job.job_type
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for TenantDataDeleteHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        if arguments.get(DRY_RUN_ARG) == Some(&Value::Bool(true)) {
+            return self.dry_run(session).await;
+        }
+        // A resumed deletion was confirmed when it started
+        if resume_job_id(&arguments).is_none() {
+            let token = arguments
+                .get(CONFIRM_ARG)
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| {
+                    HandlerError::InvalidArguments(
+                        "Missing 'confirm': run with dry_run: true first and pass the confirm token it returns".to_string(),
+                    )
+                })?;
+            self.check_no_running_jobs(session).await?;
+            self.consume(session, token).await?;
+        } else {
+            self.check_no_running_jobs(session).await?;
+        }
+        let (checkpoint, resumed_from): (DeleteCheckpoint, _) =
+            start_from(&self.jobs, session, &arguments, DELETE_JOB_TYPE).await?;
+        let callback = self.jobs.callback_from(&arguments)?;
+
+        info!(
+            "Deleting the data of tenant {} for user {}",
+            session.context.tenant_id, session.context.user_id
+        );
+        let aws_service = self.aws_service.clone();
+        let registry = self.registry.clone();
+        let jobs = self.jobs.clone();
+        let job_session = session.clone();
+        let record = self
+            .jobs
+            .spawn(session, DELETE_JOB_TYPE, callback, move |job| async move {
+                let data =
+                    TenantData::new(aws_service.as_ref(), registry.as_ref(), &job_session, None)
+                        .await?;
+                delete(&data, &jobs, &job, checkpoint).await
+            })
+            .await?;
+
+        Ok(json!({
+            "job_id": record.job_id,
+            "state": record.state,
+            "resumedFrom": resumed_from
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn supports_dry_run(&self) -> bool {
+        true
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Delete everything stored for the caller's tenant (KV items, artifacts, events, rules, alert subscriptions, integrations and their credentials, connections, secrets) across services, as a background job, and record a tenant.data_deleted system event. First call with dry_run: true to see what there is and get a confirm token, valid for 15 minutes; then call with that token as confirm. Returns a job_id; a failed or interrupted deletion can be resumed with resume_job_id",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "confirm": {
+                        "type": "string",
+                        "description": "Token returned by the latest dry run"
+                    },
+                    "resume_job_id": resume_schema(DELETE_JOB_TYPE),
+                    "callback": callback_schema()
+                }
+            }
+        })
+    }
+}
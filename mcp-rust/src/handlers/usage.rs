@@ -0,0 +1,304 @@
+use async_trait::async_trait;
+use chrono::{Duration as DateDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::aws::AwsApi;
+use crate::config::AnalyticsConfig;
+use crate::handlers::middleware::{HandlerMiddleware, ToolCall};
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+/// Fraction of tool calls recorded as usage events, from 0 to 1 (default 1)
+pub const TOOL_USAGE_SAMPLE_RATE_ENV: &str = "MCP_TOOL_USAGE_SAMPLE_RATE";
+
+/// Comma-separated `tenant=rate` pairs overriding the sample rate for those tenants
+pub const TENANT_SAMPLE_RATES_ENV: &str = "MCP_TOOL_USAGE_TENANT_SAMPLE_RATES";
+
+/// Detail type of the per-call usage events
+pub const TOOL_USAGE_DETAIL_TYPE: &str = "mcp.tool_usage";
+
+/// Source of the events the server sends for a session
+const TOOL_USAGE_SOURCE: &str = "mcp-rust";
+
+/// Days reported when the range isn't given, ending today
+pub const DEFAULT_REPORT_DAYS: i64 = 7;
+
+/// Longest range a report may cover
+pub const MAX_REPORT_DAYS: i64 = 90;
+
+/// Most usage events read for one report; a report reaching it says `truncated`
+pub const MAX_USAGE_RECORDS: i32 = 10_000;
+
+/// Coarse bucket of a call's duration recorded instead of the exact time
+pub fn duration_bucket(elapsed: Duration) -> &'static str {
+    match elapsed.as_millis() {
+        0..100 => "<100ms",
+        100..1_000 => "100ms-1s",
+        1_000..10_000 => "1s-10s",
+        _ => ">=10s",
+    }
+}
+
+/// Records a compact `mcp.tool_usage` event per sampled tool call through the backend's
+/// write-behind event buffer. Best effort: a failed send is only logged.
+pub struct ToolUsageMiddleware {
+    aws_service: Arc<dyn AwsApi>,
+    config: AnalyticsConfig,
+}
+
+impl ToolUsageMiddleware {
+    pub fn new(aws_service: Arc<dyn AwsApi>, config: AnalyticsConfig) -> Self {
+        Self {
+            aws_service,
+            config,
+        }
+    }
+}
+
+/// Uniform in `[0, 1)`
+fn random_fraction() -> f64 {
+    (uuid::Uuid::new_v4().as_u128() as u64 >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[async_trait]
+impl HandlerMiddleware for ToolUsageMiddleware {
+    fn name(&self) -> &'static str {
+        "tool_usage"
+    }
+
+    async fn after(
+        &self,
+        call: &ToolCall<'_>,
+        result: &Result<Value, HandlerError>,
+        elapsed: Duration,
+    ) {
+        let context = &call.session.context;
+        let sample_rate = self.config.sample_rate(&context.tenant_id);
+        if sample_rate <= 0.0 || (sample_rate < 1.0 && random_fraction() >= sample_rate) {
+            return;
+        }
+        // The backend adds the tenant, user and namespace
+        let detail = json!({
+            "tool": call.tool,
+            "duration": duration_bucket(elapsed),
+            "outcome": if result.is_ok() { "ok" } else { "error" },
+            "day": Utc::now().format("%Y-%m-%d").to_string(),
+            "organization_id": context.organization_id,
+            "sample_rate": sample_rate,
+        });
+        if let Err(e) = self
+            .aws_service
+            .send_event_buffered(call.session, TOOL_USAGE_DETAIL_TYPE, detail)
+            .await
+        {
+            debug!("Failed to record usage of {}: {}", call.tool, e);
+        }
+    }
+}
+
+/// One tool's calls over a report's range. Counts are estimates: each recorded call
+/// stands for `1 / sample_rate` calls.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolUsage {
+    pub tool: String,
+    pub calls: u64,
+    pub errors: u64,
+    /// `errors / calls`
+    pub error_rate: f64,
+    /// Calls actually recorded
+    pub recorded_calls: u64,
+    /// Estimated calls by duration bucket
+    pub durations: BTreeMap<String, u64>,
+}
+
+#[derive(Default)]
+struct UsageTotals {
+    calls: f64,
+    errors: f64,
+    recorded_calls: u64,
+    durations: BTreeMap<String, f64>,
+}
+
+/// The event's detail, whether the store kept it as an object or as JSON text
+fn event_detail(event: &Value) -> Option<Value> {
+    match &event["detail"] {
+        Value::String(text) => serde_json::from_str(text).ok(),
+        Value::Object(_) => Some(event["detail"].clone()),
+        _ => None,
+    }
+}
+
+/// Usage by tool, most called first, from `mcp.tool_usage` event details
+pub fn aggregate_usage<'a>(details: impl IntoIterator<Item = &'a Value>) -> Vec<ToolUsage> {
+    let mut totals: HashMap<String, UsageTotals> = HashMap::new();
+    for detail in details {
+        let Some(tool) = detail["tool"].as_str() else {
+            continue;
+        };
+        let weight = match detail["sample_rate"].as_f64() {
+            Some(rate) if rate > 0.0 => 1.0 / rate,
+            _ => 1.0,
+        };
+        let tool = totals.entry(tool.to_string()).or_default();
+        tool.calls += weight;
+        tool.recorded_calls += 1;
+        if detail["outcome"] != "ok" {
+            tool.errors += weight;
+        }
+        if let Some(duration) = detail["duration"].as_str() {
+            *tool.durations.entry(duration.to_string()).or_default() += weight;
+        }
+    }
+
+    let mut usage: Vec<ToolUsage> = totals
+        .into_iter()
+        .map(|(tool, totals)| ToolUsage {
+            tool,
+            calls: totals.calls.round() as u64,
+            errors: totals.errors.round() as u64,
+            error_rate: totals.errors / totals.calls,
+            recorded_calls: totals.recorded_calls,
+            durations: totals
+                .durations
+                .into_iter()
+                .map(|(bucket, calls)| (bucket, calls.round() as u64))
+                .collect(),
+        })
+        .collect();
+    usage.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.tool.cmp(&b.tool)));
+    usage
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolUsageReportArgs {
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    #[serde(default)]
+    org_wide: bool,
+}
+
+/// Counts and error rates per tool over a range of days, from the `mcp.tool_usage`
+/// events of the caller's tenant, or of its whole organization for Admins
+pub struct ToolUsageReportHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl ToolUsageReportHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for ToolUsageReportHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: ToolUsageReportArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+        if args.org_wide && !session.has_permission(&Permission::Admin) {
+            return Err(HandlerError::PermissionDenied(Permission::Admin));
+        }
+        let end_date = args.end_date.unwrap_or_else(|| Utc::now().date_naive());
+        let start_date = args
+            .start_date
+            .unwrap_or(end_date - DateDuration::days(DEFAULT_REPORT_DAYS - 1));
+        if start_date > end_date {
+            return Err(HandlerError::InvalidArguments(
+                "start_date must not be after end_date".to_string(),
+            ));
+        }
+        if (end_date - start_date).num_days() >= MAX_REPORT_DAYS {
+            return Err(HandlerError::InvalidArguments(format!(
+                "The range may cover at most {} days",
+                MAX_REPORT_DAYS
+            )));
+        }
+
+        let result = self
+            .aws_service
+            .query_events(
+                session,
+                None,
+                None,
+                None,
+                Some(TOOL_USAGE_SOURCE.to_string()),
+                Some(TOOL_USAGE_DETAIL_TYPE.to_string()),
+                None,
+                None,
+                Some(format!("{}T00:00:00Z", start_date)),
+                Some(format!("{}T23:59:59.999999999Z", end_date)),
+                MAX_USAGE_RECORDS,
+                None,
+                true,
+            )
+            .await?;
+        let events = result["events"].as_array().cloned().unwrap_or_default();
+        let truncated =
+            events.len() >= MAX_USAGE_RECORDS as usize || !result["lastEvaluatedKey"].is_null();
+
+        let (scope_field, scope_id) = if args.org_wide {
+            ("organization_id", &session.context.organization_id)
+        } else {
+            ("tenant_id", &session.context.tenant_id)
+        };
+        let (start, end) = (start_date.to_string(), end_date.to_string());
+        let details: Vec<Value> = events
+            .iter()
+            .filter_map(event_detail)
+            .filter(|detail| detail[scope_field] == scope_id.as_str())
+            // The timestamp bounds are approximate; the day the call was made decides
+            .filter(|detail| {
+                detail["day"]
+                    .as_str()
+                    .is_some_and(|day| start.as_str() <= day && day <= end.as_str())
+            })
+            .collect();
+        let tools = aggregate_usage(&details);
+
+        Ok(json!({
+            scope_field: scope_id,
+            "start_date": start,
+            "end_date": end,
+            "total_calls": tools.iter().map(|tool| tool.calls).sum::<u64>(),
+            "total_errors": tools.iter().map(|tool| tool.errors).sum::<u64>(),
+            "tools": tools,
+            "truncated": truncated,
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        // Like events_analytics; `org_wide` also requires Admin
+        Some(Permission::SendEvents)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Report how often each tool was called and how often it failed over a range of days, for your tenant or, for Admins, the whole organization. Counts of sampled tenants are estimates",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "start_date": {
+                        "type": "string",
+                        "description": "First day reported, YYYY-MM-DD (default: 6 days before end_date)"
+                    },
+                    "end_date": {
+                        "type": "string",
+                        "description": "Last day reported, YYYY-MM-DD (default: today, UTC)"
+                    },
+                    "org_wide": {
+                        "type": "boolean",
+                        "description": "Report every tenant of your organization (requires Admin permission)"
+                    }
+                }
+            }
+        })
+    }
+}
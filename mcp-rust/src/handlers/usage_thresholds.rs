@@ -0,0 +1,525 @@
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::aws::transactions::{ItemCondition, KvWriteOp};
+use crate::aws::{AwsApi, AwsError};
+use crate::handlers::{Handler, HandlerError};
+use crate::metrics::{CallCounters, DailyUsage};
+use crate::tenant::{
+    Permission, TenantContext, TenantManager, TenantSession, UsageMetric, UsageThreshold,
+};
+
+/// Detail type of the event sent when a tenant's usage crosses one of its thresholds
+pub const THRESHOLD_CROSSED_DETAIL_TYPE: &str = "usage.threshold_crossed";
+
+/// How often the server checks every tenant's usage against its thresholds
+pub const EVALUATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most thresholds a tenant may have
+pub const MAX_USAGE_THRESHOLDS: usize = 20;
+
+/// KV key, in the tenant owner's namespace, marking a threshold as fired for a day
+const FIRED_KEY_PREFIX: &str = "_usage_threshold";
+
+/// Fired markers outlive their day by long enough for every evaluator to see them
+const FIRED_TTL_HOURS: u32 = 48;
+
+/// KV key, in the tenant owner's namespace, of a day's persisted metering rollup
+const ROLLUP_KEY_PREFIX: &str = "_usage_rollup";
+
+/// Rollups are kept as long as the fired markers of their day
+const ROLLUP_TTL_HOURS: u32 = FIRED_TTL_HOURS;
+
+/// Times a rollup write is retried when another server wrote it in between
+const ROLLUP_UPDATE_ATTEMPTS: usize = 5;
+
+fn rollup_key(period: NaiveDate) -> String {
+    format!("{}/{}", ROLLUP_KEY_PREFIX, period)
+}
+
+fn fired_key(threshold: &UsageThreshold, period: NaiveDate) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        FIRED_KEY_PREFIX,
+        period,
+        threshold.metric.as_str(),
+        threshold.percent
+    )
+}
+
+fn used(usage: &DailyUsage, metric: UsageMetric) -> u64 {
+    match metric {
+        UsageMetric::ToolCalls => usage.tool_calls,
+        UsageMetric::Events => usage.events,
+        UsageMetric::KvBytes => usage.kv_bytes,
+    }
+}
+
+/// A threshold a tenant's usage crossed in a period, reported by the run that fired it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThresholdCrossing {
+    pub tenant_id: String,
+    pub metric: UsageMetric,
+    pub percent: u32,
+    pub limit: u64,
+    pub used: u64,
+    /// The UTC day the usage was counted over
+    pub period: NaiveDate,
+    /// Whether the crossing was published to the threshold's alert subscription
+    pub delivered: bool,
+}
+
+/// Compares each tenant's [`DailyUsage`] with its usage thresholds. Every run first adds
+/// the usage this server metered since the last one to the tenant's persisted rollup
+/// for the day, so thresholds see the usage of every server sharing the store, and
+/// usage from before a restart. A crossing fires once per threshold and day: the run
+/// that fires it first claims a marker with a conditional KV write, so later runs, and
+/// other servers, skip it. Usage resets with the next UTC day, and so do the markers.
+pub struct UsageThresholdEvaluator {
+    tenant_manager: Arc<TenantManager>,
+    aws_service: Arc<dyn AwsApi>,
+    counters: Arc<CallCounters>,
+}
+
+impl UsageThresholdEvaluator {
+    pub fn new(
+        tenant_manager: Arc<TenantManager>,
+        aws_service: Arc<dyn AwsApi>,
+        counters: Arc<CallCounters>,
+    ) -> Self {
+        Self {
+            tenant_manager,
+            aws_service,
+            counters,
+        }
+    }
+
+    /// Fire every threshold crossed on `period` that hasn't fired for it yet
+    pub async fn evaluate(&self, period: NaiveDate) -> Vec<ThresholdCrossing> {
+        let contexts = self.tenant_manager.tenant_contexts().await;
+        self.persist_usage(&contexts).await;
+        let mut crossings = Vec::new();
+        for context in contexts {
+            let limits = &context.resource_limits;
+            if limits.usage_thresholds.is_empty() {
+                continue;
+            }
+            let session = TenantSession::new(context.clone());
+            let usage = match self.persisted_usage(&session, period).await {
+                Ok((_, usage)) => usage,
+                Err(e) => {
+                    warn!(
+                        "Failed to read the usage rollup of {}: {}",
+                        context.tenant_id, e
+                    );
+                    continue;
+                }
+            };
+            for threshold in &limits.usage_thresholds {
+                let (Some(limit), Some(level)) = (threshold.limit, threshold.trigger_level())
+                else {
+                    continue;
+                };
+                let used = used(&usage, threshold.metric);
+                if used < level || !self.claim(&session, threshold, period).await {
+                    continue;
+                }
+                let mut crossing = ThresholdCrossing {
+                    tenant_id: context.tenant_id.clone(),
+                    metric: threshold.metric,
+                    percent: threshold.percent,
+                    limit,
+                    used,
+                    period,
+                    delivered: false,
+                };
+                if self.fire(&session, threshold, &mut crossing).await {
+                    crossings.push(crossing);
+                }
+            }
+        }
+        crossings
+    }
+
+    /// Add the usage metered since the last run to the tenants' persisted rollups.
+    /// Usage that can't be written is kept for the next run; that of tenants no longer
+    /// configured is dropped.
+    async fn persist_usage(&self, contexts: &[TenantContext]) {
+        let contexts: HashMap<&str, &TenantContext> = contexts
+            .iter()
+            .map(|context| (context.tenant_id.as_str(), context))
+            .collect();
+        for (tenant_id, period, usage) in self.counters.take_unpersisted_usage() {
+            let Some(context) = contexts.get(tenant_id.as_str()) else {
+                continue;
+            };
+            let session = TenantSession::new((*context).clone());
+            if let Err(e) = self.add_to_rollup(&session, period, &usage).await {
+                warn!("Failed to persist the usage of {}: {}", tenant_id, e);
+                self.counters
+                    .return_unpersisted_usage(&tenant_id, period, usage);
+            }
+        }
+    }
+
+    /// The tenant's persisted rollup for the day, with the stored value it was read from
+    async fn persisted_usage(
+        &self,
+        session: &TenantSession,
+        period: NaiveDate,
+    ) -> Result<(Option<String>, DailyUsage), AwsError> {
+        let stored = self
+            .aws_service
+            .kv_get_fresh(session, &rollup_key(period))
+            .await?;
+        let usage = match &stored {
+            Some(value) => serde_json::from_str(value)?,
+            None => DailyUsage::default(),
+        };
+        Ok((stored, usage))
+    }
+
+    /// Add `usage` to the day's rollup, rereading and retrying when another server
+    /// wrote it in between
+    async fn add_to_rollup(
+        &self,
+        session: &TenantSession,
+        period: NaiveDate,
+        usage: &DailyUsage,
+    ) -> Result<(), AwsError> {
+        for _ in 0..ROLLUP_UPDATE_ATTEMPTS {
+            let (stored, mut rollup) = self.persisted_usage(session, period).await?;
+            rollup.add(usage);
+            let value = serde_json::to_string(&rollup)?;
+            if self
+                .aws_service
+                .kv_compare_and_swap(
+                    session,
+                    &rollup_key(period),
+                    stored.as_deref(),
+                    &value,
+                    Some(ROLLUP_TTL_HOURS),
+                )
+                .await?
+            {
+                return Ok(());
+            }
+        }
+        Err(AwsError::ConditionFailed(format!(
+            "the usage rollup for {} stayed contended",
+            period
+        )))
+    }
+
+    /// Whether this run is the first to fire the threshold for the period
+    async fn claim(
+        &self,
+        session: &TenantSession,
+        threshold: &UsageThreshold,
+        period: NaiveDate,
+    ) -> bool {
+        let ops = [KvWriteOp::Put {
+            key: fired_key(threshold, period),
+            value: Utc::now().to_rfc3339(),
+            ttl_hours: Some(FIRED_TTL_HOURS),
+            condition: Some(ItemCondition::Absent),
+        }];
+        match self.aws_service.kv_transact_write(session, &ops).await {
+            Ok(()) => true,
+            Err(AwsError::TransactionCanceled(_)) => false,
+            Err(e) => {
+                warn!(
+                    "Failed to check usage threshold of {}: {}",
+                    session.context.tenant_id, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Send the crossing's event and deliver it to the alert subscription. If the event
+    /// can't be sent the claim is released, so the next run tries again.
+    async fn fire(
+        &self,
+        session: &TenantSession,
+        threshold: &UsageThreshold,
+        crossing: &mut ThresholdCrossing,
+    ) -> bool {
+        let detail = json!({
+            "tenant_id": crossing.tenant_id,
+            "metric": crossing.metric,
+            "percent": crossing.percent,
+            "limit": crossing.limit,
+            "used": crossing.used,
+            "period": crossing.period,
+        });
+        if let Err(e) = self
+            .aws_service
+            .send_event(session, THRESHOLD_CROSSED_DETAIL_TYPE, detail.clone())
+            .await
+        {
+            warn!(
+                "Failed to send {} for {}: {}",
+                THRESHOLD_CROSSED_DETAIL_TYPE, crossing.tenant_id, e
+            );
+            let release = [KvWriteOp::Delete {
+                key: fired_key(threshold, crossing.period),
+                condition: None,
+            }];
+            if let Err(e) = self.aws_service.kv_transact_write(session, &release).await {
+                warn!("Failed to release usage threshold claim: {}", e);
+            }
+            return false;
+        }
+        info!(
+            "Tenant {} crossed {}% of its {} budget ({} of {})",
+            crossing.tenant_id,
+            crossing.percent,
+            crossing.metric.as_str(),
+            crossing.used,
+            crossing.limit
+        );
+
+        if let Some(subscription) = self.subscription(&session.context, threshold).await {
+            let event = json!({
+                "detailType": THRESHOLD_CROSSED_DETAIL_TYPE,
+                "priority": "high",
+                "detail": detail
+            });
+            // A failed delivery is recorded on the subscription by `deliver_alert`
+            crossing.delivered = matches!(
+                self.aws_service
+                    .deliver_alert(session, &subscription, &event)
+                    .await,
+                Ok(Some(_))
+            );
+        }
+        true
+    }
+
+    /// The threshold's alert subscription, looked up as the admin who set it
+    async fn subscription(
+        &self,
+        context: &TenantContext,
+        threshold: &UsageThreshold,
+    ) -> Option<Value> {
+        let subscription_id = threshold.alert_subscription_id.as_deref()?;
+        let mut subscriber = context.clone();
+        subscriber.user_id = threshold.set_by.clone()?;
+        match self
+            .aws_service
+            .alert_subscriptions(&TenantSession::new(subscriber))
+            .await
+        {
+            Ok(subscriptions) => subscriptions
+                .into_iter()
+                .find(|subscription| subscription["subscriptionId"] == subscription_id),
+            Err(e) => {
+                warn!(
+                    "Failed to look up alert subscription {}: {}",
+                    subscription_id, e
+                );
+                None
+            }
+        }
+    }
+
+    /// Evaluate today's usage every `interval` for as long as the tenant manager lives.
+    /// Failures are logged and never reach request handling.
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        let tenant_manager = Arc::downgrade(&self.tenant_manager);
+        let Self {
+            aws_service,
+            counters,
+            ..
+        } = self;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes at once
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(tenant_manager) = tenant_manager.upgrade() else {
+                    break;
+                };
+                UsageThresholdEvaluator::new(tenant_manager, aws_service.clone(), counters.clone())
+                    .evaluate(Utc::now().date_naive())
+                    .await;
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThresholdArgs {
+    metric: UsageMetric,
+    percent: u32,
+    limit: u64,
+    alert_subscription_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageThresholdsSetArgs {
+    thresholds: Vec<ThresholdArgs>,
+}
+
+/// Replaces the caller's tenant's usage thresholds; an empty list removes them
+pub struct UsageThresholdsSetHandler {
+    tenant_manager: Arc<TenantManager>,
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl UsageThresholdsSetHandler {
+    pub fn new(tenant_manager: Arc<TenantManager>, aws_service: Arc<dyn AwsApi>) -> Self {
+        Self {
+            tenant_manager,
+            aws_service,
+        }
+    }
+
+    async fn threshold(
+        &self,
+        session: &TenantSession,
+        args: ThresholdArgs,
+    ) -> Result<UsageThreshold, HandlerError> {
+        if !(1..=100).contains(&args.percent) {
+            return Err(HandlerError::InvalidArguments(
+                "percent must be between 1 and 100".to_string(),
+            ));
+        }
+        if args.limit == 0 {
+            return Err(HandlerError::InvalidArguments(
+                "limit must be positive".to_string(),
+            ));
+        }
+        if let Some(subscription_id) = &args.alert_subscription_id {
+            let subscriptions = self.aws_service.alert_subscriptions(session).await?;
+            if !subscriptions
+                .iter()
+                .any(|subscription| subscription["subscriptionId"] == subscription_id.as_str())
+            {
+                return Err(HandlerError::InvalidArguments(format!(
+                    "You have no alert subscription {:?}",
+                    subscription_id
+                )));
+            }
+        }
+        Ok(UsageThreshold {
+            metric: args.metric,
+            percent: args.percent,
+            limit: Some(args.limit),
+            alert_subscription_id: args.alert_subscription_id,
+            set_by: Some(session.context.user_id.clone()),
+        })
+    }
+}
+
+#[async_trait]
+impl Handler for UsageThresholdsSetHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let args: UsageThresholdsSetArgs = serde_json::from_value(arguments)
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+        if args.thresholds.len() > MAX_USAGE_THRESHOLDS {
+            return Err(HandlerError::InvalidArguments(format!(
+                "A tenant may have at most {} thresholds",
+                MAX_USAGE_THRESHOLDS
+            )));
+        }
+        let mut seen = HashSet::new();
+        let mut thresholds = Vec::new();
+        for args in args.thresholds {
+            if !seen.insert((args.metric, args.percent)) {
+                return Err(HandlerError::InvalidArguments(format!(
+                    "{}% of {} appears more than once",
+                    args.percent,
+                    args.metric.as_str()
+                )));
+            }
+            thresholds.push(self.threshold(session, args).await?);
+        }
+
+        let tenant_id = &session.context.tenant_id;
+        self.tenant_manager
+            .set_usage_thresholds(tenant_id, thresholds.clone())
+            .await
+            .map_err(|e| HandlerError::InvalidArguments(e.to_string()))?;
+        info!(
+            "{} set {} usage thresholds for tenant {}",
+            session.context.user_id,
+            thresholds.len(),
+            tenant_id
+        );
+
+        let thresholds: Vec<Value> = thresholds
+            .iter()
+            .map(|threshold| {
+                json!({
+                    "metric": threshold.metric,
+                    "percent": threshold.percent,
+                    "limit": threshold.limit,
+                    "alert_subscription_id": threshold.alert_subscription_id,
+                })
+            })
+            .collect();
+        Ok(json!({"tenant_id": tenant_id, "thresholds": thresholds}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::Admin)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Set your tenant's daily usage thresholds (requires Admin permission). When a day's usage reaches a threshold's percentage of its limit, a usage.threshold_crossed event is sent once for that day, and delivered to the threshold's alert subscription if it has one. Replaces the existing thresholds; an empty list removes them",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "thresholds": {
+                        "type": "array",
+                        "maxItems": MAX_USAGE_THRESHOLDS,
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "metric": {
+                                    "type": "string",
+                                    "enum": ["tool_calls", "events", "kv_bytes"],
+                                    "description": "Tool calls, events sent with events_send, or bytes of values written with kv_set, per UTC day"
+                                },
+                                "percent": {
+                                    "type": "integer",
+                                    "minimum": 1,
+                                    "maximum": 100,
+                                    "description": "Share of the limit at which the threshold is crossed"
+                                },
+                                "limit": {
+                                    "type": "integer",
+                                    "minimum": 1,
+                                    "description": "Daily budget"
+                                },
+                                "alert_subscription_id": {
+                                    "type": "string",
+                                    "description": "One of your alert subscriptions to also deliver crossings to"
+                                }
+                            },
+                            "required": ["metric", "percent", "limit"]
+                        }
+                    }
+                },
+                "required": ["thresholds"]
+            }
+        })
+    }
+}
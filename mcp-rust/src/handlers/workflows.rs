@@ -0,0 +1,233 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::aws::{AwsApi, AwsError};
+use crate::handlers::{Handler, HandlerError};
+use crate::tenant::{Permission, TenantSession};
+
+fn required_str<'a>(arguments: &'a Value, field: &str) -> Result<&'a str, HandlerError> {
+    arguments
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| HandlerError::InvalidArguments(format!("Missing '{}' parameter", field)))
+}
+
+/// Malformed or disallowed ARNs are the caller's mistake rather than an AWS failure
+fn map_aws_error(error: AwsError) -> HandlerError {
+    match error {
+        AwsError::InvalidWorkflow(_) => HandlerError::InvalidArguments(error.to_string()),
+        other => HandlerError::Aws(other),
+    }
+}
+
+/// Publish a workflow lifecycle event. The execution has already started or stopped, so
+/// a failure to publish is logged rather than failing the call.
+async fn publish(
+    aws_service: &dyn AwsApi,
+    session: &TenantSession,
+    detail_type: &str,
+    detail: Value,
+) {
+    if let Err(e) = aws_service.send_event(session, detail_type, detail).await {
+        warn!("Failed to publish {} event: {}", detail_type, e);
+    }
+}
+
+fn execution_arn_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "ARN of an execution started with workflow_start"
+    })
+}
+
+pub struct WorkflowStartHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl WorkflowStartHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for WorkflowStartHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let state_machine_arn = required_str(&arguments, "stateMachineArn")?;
+        let input = match arguments.get("input") {
+            None | Some(Value::Null) => json!({}),
+            Some(input) => input.clone(),
+        };
+
+        info!(
+            "Starting {} for tenant {}",
+            state_machine_arn, session.context.tenant_id
+        );
+        let execution_arn = self
+            .aws_service
+            .tenant_workflow_start(session, state_machine_arn, &input)
+            .await
+            .map_err(map_aws_error)?;
+
+        publish(
+            self.aws_service.as_ref(),
+            session,
+            "workflow.started",
+            json!({
+                "executionArn": execution_arn,
+                "stateMachineArn": state_machine_arn
+            }),
+        )
+        .await;
+
+        Ok(json!({"executionArn": execution_arn}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ExecuteWorkflows)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Start a Step Functions execution of one of the state machines the tenant is allowed to run",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "stateMachineArn": {
+                        "type": "string",
+                        "description": "State machine ARN; it must be in the tenant's allowed_state_machines"
+                    },
+                    "input": {
+                        "description": "JSON input for the execution (default {})"
+                    }
+                },
+                "required": ["stateMachineArn"]
+            }
+        })
+    }
+}
+
+pub struct WorkflowStatusHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl WorkflowStatusHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for WorkflowStatusHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let execution_arn = required_str(&arguments, "executionArn")?;
+        let execution = self
+            .aws_service
+            .tenant_workflow_status(session, execution_arn)
+            .await
+            .map_err(map_aws_error)?;
+        Ok(serde_json::to_value(execution).map_err(AwsError::from)?)
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ExecuteWorkflows)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Status of one of the tenant's workflow executions, with its output (SUCCEEDED) or error and cause (FAILED, TIMED_OUT, ABORTED) once it has finished",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "executionArn": execution_arn_property()
+                },
+                "required": ["executionArn"]
+            }
+        })
+    }
+}
+
+pub struct WorkflowStopHandler {
+    aws_service: Arc<dyn AwsApi>,
+}
+
+impl WorkflowStopHandler {
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self { aws_service }
+    }
+}
+
+#[async_trait]
+impl Handler for WorkflowStopHandler {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let execution_arn = required_str(&arguments, "executionArn")?;
+        let error = arguments.get("error").and_then(|v| v.as_str());
+        let cause = arguments.get("cause").and_then(|v| v.as_str());
+
+        info!(
+            "Stopping {} for tenant {}",
+            execution_arn, session.context.tenant_id
+        );
+        let stop_date = self
+            .aws_service
+            .tenant_workflow_stop(session, execution_arn, error, cause)
+            .await
+            .map_err(map_aws_error)?;
+
+        publish(
+            self.aws_service.as_ref(),
+            session,
+            "workflow.stopped",
+            json!({
+                "executionArn": execution_arn,
+                "error": error,
+                "cause": cause
+            }),
+        )
+        .await;
+
+        Ok(json!({
+            "executionArn": execution_arn,
+            "stopDate": stop_date.to_rfc3339()
+        }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ExecuteWorkflows)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Stop one of the tenant's running workflow executions",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "executionArn": execution_arn_property(),
+                    "error": {
+                        "type": "string",
+                        "description": "Error code recorded on the stopped execution"
+                    },
+                    "cause": {
+                        "type": "string",
+                        "description": "Explanation recorded on the stopped execution"
+                    }
+                },
+                "required": ["executionArn"]
+            }
+        })
+    }
+}
@@ -1,16 +1,26 @@
 pub mod aws;
+pub mod cli;
+pub mod config;
+pub mod crypto;
 pub mod handlers;
 pub mod mcp;
+pub mod metrics;
 pub mod rate_limiting;
+pub mod redaction;
 pub mod registry;
+pub mod self_test;
 pub mod tenant;
+pub mod transport;
 
 pub use aws::{AwsError, AwsService};
+pub use config::Config;
 pub use handlers::{Handler, HandlerError, HandlerRegistry};
 pub use mcp::{MCPError, MCPRequest, MCPResponse, MCPServer};
 pub use tenant::{
-    ContextType, Permission, ResourceLimits, TenantContext, TenantManager, TenantSession, UserRole,
+    ContextType, Permission, ResourceLimits, TenantContext, TenantManager, TenantSession,
+    ToolPolicy, UserRole,
 };
+pub use transport::stream::serve_connection;
 
 #[cfg(test)]
 mod tests {
@@ -27,6 +37,8 @@ mod tests {
             permissions: vec![Permission::ReadKV, Permission::WriteKV],
             aws_region: "us-west-2".to_string(),
             resource_limits: ResourceLimits::default(),
+            tool_policy: ToolPolicy::default(),
+            aws_role: None,
         };
 
         let session = TenantSession::new(context);
@@ -49,6 +61,8 @@ mod tests {
             permissions: vec![Permission::ReadKV, Permission::WriteKV],
             aws_region: "us-west-2".to_string(),
             resource_limits: ResourceLimits::default(),
+            tool_policy: ToolPolicy::default(),
+            aws_role: None,
         };
 
         let session = TenantSession::new(context);
@@ -68,6 +82,8 @@ mod tests {
             permissions: vec![], // Empty permissions, but admin should have all
             aws_region: "us-west-2".to_string(),
             resource_limits: ResourceLimits::default(),
+            tool_policy: ToolPolicy::default(),
+            aws_role: None,
         };
 
         let session = TenantSession::new(context);
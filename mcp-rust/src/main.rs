@@ -1,38 +1,101 @@
+use clap::Parser;
 use std::sync::Arc;
 use tracing::info;
 
 mod aws;
+mod cli;
+mod config;
+mod crypto;
 mod handlers;
 mod mcp;
+mod metrics;
 mod rate_limiting;
+mod redaction;
 mod registry;
+mod self_test;
 mod tenant;
+mod transport;
 
+use cli::Cli;
+use config::Transport;
 use mcp::MCPServer;
 use tenant::TenantManager;
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 4)]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    // Settings are checked together before anything starts
+    let mut config = cli.load_config()?;
+
+    // Credentials are masked in every log line, error and diagnostics bundle
+    redaction::install(config.server.redaction_policy());
+
     // Initialize tracing to stderr (stdout must be reserved for JSON-RPC)
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
         .with_ansi(false) // Disable ANSI color codes
+        .with_max_level(config.log_level)
+        .fmt_fields(redaction::log_fields())
         .init();
 
-    info!("Starting Multi-Tenant MCP Rust Server");
+    if cli.validate_config {
+        println!("{}", config.describe());
+        return cli::validate(&config).await;
+    }
+
+    if cli.self_test || cli.self_test_live {
+        let passed = cli::self_test(&config, cli.self_test_live).await?;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    info!("Starting Multi-Tenant MCP Rust Server {}", cli::VERSION);
+
+    // Fail now rather than on the first tool call, or go offline when allowed
+    aws::credentials::ensure_credentials(&mut config.aws).await?;
+    let config = Arc::new(config);
+
+    if config.print_config {
+        eprintln!(
+            "[MCP Server] Effective configuration:\n{}",
+            config.describe()
+        );
+    }
 
     // Create tenant manager
-    let tenant_manager = Arc::new(TenantManager::new().await?);
+    let tenant_manager = Arc::new(TenantManager::with_config(config.clone()).await?);
+
+    // Periodically drop idle sessions and rate limit buckets
+    tenant_manager.spawn_cleanup_task(config.server.cleanup_interval);
 
     // Create MCP server with tenant isolation
-    let server = MCPServer::new(tenant_manager.clone()).await?;
+    let server = Arc::new(MCPServer::new(tenant_manager.clone(), None).await?);
 
-    // Start the server - this will block until stdin closes or error occurs
-    let result = server.run().await;
+    // A metrics listener of its own, so stdio servers can be scraped too
+    if let Some(addr) = config.server.metrics_addr {
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = transport::http::serve_metrics(server, addr).await {
+                eprintln!("[MCP Server] Metrics listener failed: {}", e);
+            }
+        });
+    }
+
+    // Start the server - this will block until stdin closes (or ctrl-c for HTTP) or error occurs
+    let result = match config.server.transport {
+        Transport::Http => transport::http::serve(server.clone(), config.server.http_addr).await,
+        Transport::WebSocket => {
+            transport::websocket::serve(server.clone(), config.server.http_addr).await
+        }
+        Transport::Stdio => server.run().await,
+    };
 
     // Graceful shutdown
     eprintln!("[MCP Server] Shutting down gracefully...");
 
+    // Child processes and containers would otherwise outlive us
+    server.shutdown().await;
+
     // Give background tasks a moment to complete
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
@@ -1,14 +1,39 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, BufReader};
 use tokio::sync::RwLock;
-use tracing::debug;
+use tracing::field::Empty;
+use tracing::{debug, info_span, warn, Instrument};
 
-use crate::handlers::HandlerRegistry;
-use crate::rate_limiting::AwsOperation;
-use crate::tenant::{TenantManager, TenantSession};
+use crate::aws::AwsError;
+use crate::config::Config;
+use crate::handlers::concurrency::TOOL_BUSY_RETRY_AFTER;
+use crate::handlers::diagnostics::DiagnosticsBundleHandler;
+use crate::handlers::health::HealthHandler;
+use crate::handlers::middleware::MeteringMiddleware;
+use crate::handlers::permissions::{PermissionGrantHandler, PermissionRevokeHandler};
+use crate::handlers::rate_limits::RateLimitStatusHandler;
+use crate::handlers::schema_versions::TOOLS_SCHEMA_VERSION;
+use crate::handlers::server_stats::ServerStatsHandler;
+use crate::handlers::usage::ToolUsageMiddleware;
+use crate::handlers::usage_thresholds::{self, UsageThresholdEvaluator, UsageThresholdsSetHandler};
+use crate::handlers::{HandlerError, HandlerRegistry};
+use crate::metrics::prometheus::PrometheusExporter;
+use crate::metrics::{self, CallCounters, CallOutcome, CloudWatchSink, MetricsFlusher};
+use crate::rate_limiting::{
+    AwsOperation, OperationPriority, OversizedOperation, RateLimitRejection, RateLimitScope,
+    RateLimitStatus, RemainingQuota,
+};
+use crate::redaction;
+use crate::tenant::{
+    glob_match, Permission, SessionEndReason, TenantManager, TenantRequestSlot, TenantSession,
+};
+use crate::transport::replay::ReplayRejection;
+use crate::transport::stream::write_response;
 
 #[derive(Error, Debug)]
 pub enum MCPError {
@@ -16,15 +41,67 @@ pub enum MCPError {
     InvalidRequest(String),
     #[error("Method not found: {0}")]
     MethodNotFound(String),
+    /// Tool arguments that don't match the tool's input schema
+    #[error("Invalid params: {message}")]
+    InvalidParams {
+        tool: String,
+        pointer: String,
+        expected: Option<String>,
+        message: String,
+    },
     #[error("Tenant error: {0}")]
     TenantError(#[from] crate::tenant::TenantError),
     #[error("Handler error: {0}")]
     HandlerError(String),
     #[error("Permission denied: {0}")]
-    #[allow(dead_code)]
     PermissionDenied(String),
-    #[error("Rate limit exceeded")]
-    RateLimitExceeded,
+    #[error("Rate limit exceeded for {}", rejection.service_key)]
+    RateLimitExceeded {
+        rejection: RateLimitRejection,
+        // Snapshot of the tenant's limiter state at rejection time
+        status: Option<RateLimitStatus>,
+    },
+    #[error("Operation too large: costs {} {} tokens, bucket holds {}", oversized.cost, oversized.service_key, oversized.capacity)]
+    OperationTooLarge { oversized: OversizedOperation },
+    #[error("Tool '{tool}' is busy: {in_flight}/{max_concurrency} calls in flight")]
+    ToolBusy {
+        tool: String,
+        in_flight: usize,
+        max_concurrency: usize,
+    },
+    #[error("Tool '{tool}' timed out after {}ms", elapsed.as_millis())]
+    ToolTimeout { tool: String, elapsed: Duration },
+    #[error("Tool '{tool}' result is {size} bytes, over the {max_bytes} byte limit")]
+    ResultTooLarge {
+        tool: String,
+        size: usize,
+        max_bytes: usize,
+        hint: Option<String>,
+    },
+    /// An idempotency key replayed with different arguments
+    #[error("Idempotency conflict: {0}")]
+    IdempotencyConflict(String),
+    #[error("Tool disabled: {0}")]
+    ToolDisabled(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    /// A conditional write lost to a concurrent change
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    /// Backing storage isn't provisioned or configured
+    #[error("Storage unavailable: {0}")]
+    StorageUnavailable(String),
+    #[error("Concurrent request limit exceeded: {active}/{limit} in flight")]
+    ConcurrencyLimitExceeded { active: u32, limit: u32 },
+    /// A request that may be a replay of an earlier one on its session
+    #[error("Replayed request: {}", rejection.message)]
+    ReplayRejected { rejection: ReplayRejection },
+    #[error("Session not found: {session_id}")]
+    SessionNotFound {
+        session_id: String,
+        // None if the session never existed
+        ended: Option<SessionEndReason>,
+    },
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 }
@@ -62,54 +139,397 @@ pub struct MCPErrorResponse {
     pub data: Option<Value>,
 }
 
+impl From<HandlerError> for MCPError {
+    fn from(error: HandlerError) -> Self {
+        match error {
+            HandlerError::ToolBlocked(tool) => MCPError::PermissionDenied(format!(
+                "tool '{}' is not permitted by tenant policy",
+                tool
+            )),
+            HandlerError::ToolBusy {
+                tool,
+                in_flight,
+                max_concurrency,
+            } => MCPError::ToolBusy {
+                tool,
+                in_flight,
+                max_concurrency,
+            },
+            HandlerError::Timeout { tool, elapsed } => MCPError::ToolTimeout { tool, elapsed },
+            HandlerError::ToolDisabled(tool) => MCPError::ToolDisabled(tool),
+            error @ HandlerError::IdempotencyConflict { .. } => {
+                MCPError::IdempotencyConflict(error.to_string())
+            }
+            error @ HandlerError::IdempotencyInProgress { .. } => {
+                MCPError::Conflict(error.to_string())
+            }
+            HandlerError::ResultTooLarge {
+                tool,
+                size,
+                max_bytes,
+                hint,
+            } => MCPError::ResultTooLarge {
+                tool,
+                size,
+                max_bytes,
+                hint,
+            },
+            HandlerError::SchemaViolation {
+                tool,
+                pointer,
+                expected,
+                message,
+            } => MCPError::InvalidParams {
+                tool,
+                pointer,
+                expected,
+                message,
+            },
+            HandlerError::Aws(AwsError::NotFound(msg)) => MCPError::NotFound(msg),
+            HandlerError::Aws(AwsError::ConditionFailed(msg)) => MCPError::Conflict(msg),
+            HandlerError::Aws(error @ AwsError::TransactionCanceled(_)) => {
+                MCPError::Conflict(error.to_string())
+            }
+            HandlerError::Aws(AwsError::TableMissing(msg)) => MCPError::StorageUnavailable(msg),
+            HandlerError::Aws(error @ AwsError::RoleAssumption { .. }) => {
+                MCPError::StorageUnavailable(error.to_string())
+            }
+            // AWS's own throttling is reported like our rate limits, so clients back off
+            // the same way
+            HandlerError::Aws(AwsError::Throttled {
+                service_key,
+                retry_after_hint,
+                ..
+            }) => MCPError::RateLimitExceeded {
+                rejection: RateLimitRejection::aws(service_key, retry_after_hint),
+                status: None,
+            },
+            other => MCPError::HandlerError(other.to_string()),
+        }
+    }
+}
+
 impl From<MCPError> for MCPErrorResponse {
     fn from(error: MCPError) -> Self {
+        let mut data = None;
         let (code, message) = match error {
             MCPError::InvalidRequest(msg) => (-32600, format!("Invalid Request: {}", msg)),
             MCPError::MethodNotFound(method) => (-32601, format!("Method not found: {}", method)),
+            MCPError::InvalidParams {
+                tool,
+                pointer,
+                expected,
+                message,
+            } => {
+                data = Some(serde_json::json!({
+                    "tool": tool,
+                    "pointer": pointer,
+                    "expected": expected,
+                }));
+                (
+                    -32602,
+                    format!("Invalid params: {} at {}: {}", tool, pointer, message),
+                )
+            }
             MCPError::PermissionDenied(msg) => (-32000, format!("Permission denied: {}", msg)),
-            MCPError::RateLimitExceeded => (-32001, "Rate limit exceeded".to_string()),
+            MCPError::RateLimitExceeded { rejection, status } => {
+                data = Some(serde_json::json!({
+                    "serviceKey": rejection.service_key,
+                    "retryAfterMs": rejection.retry_after.as_millis() as u64,
+                    "scope": rejection.scope,
+                    "status": status,
+                }));
+                (-32001, "Rate limit exceeded".to_string())
+            }
+            MCPError::OperationTooLarge { oversized } => {
+                data = Some(serde_json::json!({
+                    "serviceKey": oversized.service_key,
+                    "cost": oversized.cost,
+                    "capacity": oversized.capacity,
+                }));
+                (
+                    -32602,
+                    "Invalid params: payload exceeds the tenant's rate limit capacity".to_string(),
+                )
+            }
+            MCPError::ConcurrencyLimitExceeded { active, limit } => {
+                data = Some(serde_json::json!({
+                    "activeRequests": active,
+                    "maxConcurrentRequests": limit,
+                }));
+                (
+                    -32001,
+                    "Rate limit exceeded: too many concurrent requests".to_string(),
+                )
+            }
+            MCPError::ToolBusy {
+                tool,
+                in_flight,
+                max_concurrency,
+            } => {
+                data = Some(serde_json::json!({
+                    "tool": tool,
+                    "inFlight": in_flight,
+                    "maxConcurrency": max_concurrency,
+                    "retryAfterMs": TOOL_BUSY_RETRY_AFTER.as_millis() as u64,
+                }));
+                (
+                    -32001,
+                    format!(
+                        "Rate limit exceeded: tool '{}' is at its concurrency limit",
+                        tool
+                    ),
+                )
+            }
+            MCPError::ToolTimeout { tool, elapsed } => {
+                data = Some(serde_json::json!({
+                    "tool": tool,
+                    "elapsedMs": elapsed.as_millis() as u64,
+                    "retryable": true,
+                }));
+                (-32005, format!("Tool '{}' timed out", tool))
+            }
+            MCPError::ResultTooLarge {
+                tool,
+                size,
+                max_bytes,
+                hint,
+            } => {
+                data = Some(serde_json::json!({
+                    "tool": tool,
+                    "size": size,
+                    "maxBytes": max_bytes,
+                    "hint": hint,
+                }));
+                (
+                    -32009,
+                    format!(
+                        "Result too large: '{}' returned {} bytes, over the {} byte limit",
+                        tool, size, max_bytes
+                    ),
+                )
+            }
+            MCPError::IdempotencyConflict(msg) => {
+                data = Some(serde_json::json!({ "retryable": false }));
+                (-32007, format!("Conflict: {}", msg))
+            }
+            MCPError::ToolDisabled(tool) => {
+                data = Some(serde_json::json!({ "tool": tool }));
+                (
+                    -32010,
+                    format!(
+                        "Tool disabled: '{}' is disabled by the server administrator",
+                        tool
+                    ),
+                )
+            }
+            MCPError::NotFound(msg) => (-32006, format!("Not found: {}", msg)),
+            MCPError::Conflict(msg) => {
+                data = Some(serde_json::json!({ "retryable": true }));
+                (-32007, format!("Conflict: {}", msg))
+            }
+            MCPError::StorageUnavailable(msg) => (-32008, format!("Storage unavailable: {}", msg)),
+            MCPError::ReplayRejected { rejection } => {
+                data = Some(serde_json::json!({ "reason": rejection.reason }));
+                (-32011, format!("Replayed request: {}", rejection.message))
+            }
             MCPError::TenantError(err) => (-32002, format!("Tenant error: {}", err)),
             MCPError::HandlerError(msg) => (-32003, format!("Handler error: {}", msg)),
+            MCPError::SessionNotFound { session_id, ended } => {
+                let reason = match ended {
+                    Some(SessionEndReason::Expired) => "expired",
+                    Some(SessionEndReason::Revoked) => "revoked",
+                    None => "unknown",
+                };
+                data = Some(serde_json::json!({
+                    "sessionId": session_id,
+                    "reason": reason,
+                    "reinitialize": true,
+                }));
+                (
+                    -32004,
+                    "Session not found: send a new initialize request".to_string(),
+                )
+            }
             MCPError::Internal(err) => (-32603, format!("Internal error: {}", err)),
         };
 
+        // Errors often quote what they failed on; credentials in it never reach clients
+        let policy = redaction::policy();
         Self {
             code,
-            message,
-            data: None,
+            message: policy.redact_text(&message),
+            data: data.map(|data| policy.redacted(&data)),
         }
     }
 }
 
+/// Name of the span each request with a session is traced under, with the tool call
+/// and AWS spans nested inside. Argument values are never recorded.
+pub const REQUEST_SPAN: &str = "mcp_request";
+
+/// Protocol-control methods that bypass the legacy per-minute limiter by default
+const DEFAULT_RATE_LIMIT_EXEMPT_METHODS: &[&str] =
+    &["initialize", "ping", "tools/list", "notifications/*"];
+
 pub struct MCPServer {
     tenant_manager: Arc<TenantManager>,
     handler_registry: HandlerRegistry,
     shutdown_flag: Arc<RwLock<bool>>,
+    // Method patterns (`*` wildcards) that skip the legacy requests_per_minute budget
+    rate_limit_exempt_methods: Vec<String>,
+    // Tool calls, errors and rate-limit rejections by tenant and tool
+    call_counters: Arc<CallCounters>,
 }
 
 impl MCPServer {
-    pub async fn new(tenant_manager: Arc<TenantManager>) -> anyhow::Result<Self> {
+    /// A server dispatching tool calls to `handler_registry`, or to one built from the
+    /// tenant manager's config (see [`HandlerRegistry::from_config`]) when it's `None`
+    pub async fn new(
+        tenant_manager: Arc<TenantManager>,
+        handler_registry: Option<HandlerRegistry>,
+    ) -> anyhow::Result<Self> {
         // Pre-initialize handler registry (including AWS clients) before starting stdio loop
         eprintln!("[MCP Server] Initializing handlers...");
-        let handler_registry = HandlerRegistry::new().await?;
+        let handler_registry = match handler_registry {
+            Some(handler_registry) => handler_registry,
+            None => HandlerRegistry::from_config(tenant_manager.config()).await?,
+        };
+        let call_counters = Arc::new(CallCounters::new());
+        let usage = ToolUsageMiddleware::new(
+            handler_registry.aws_service(),
+            tenant_manager.config().analytics.clone(),
+        );
+        // Calls are metered and their usage recorded before any other middleware, so
+        // rejected calls count as errors
+        let mut handler_registry = handler_registry
+            .with_outermost_middleware(Arc::new(usage))
+            .with_outermost_middleware(Arc::new(MeteringMiddleware::new(call_counters.clone())));
+        handler_registry.register(
+            "permission_grant",
+            Arc::new(PermissionGrantHandler::new(tenant_manager.clone())),
+        );
+        handler_registry.register(
+            "permission_revoke",
+            Arc::new(PermissionRevokeHandler::new(tenant_manager.clone())),
+        );
+        handler_registry.register(
+            "health",
+            Arc::new(HealthHandler::new(
+                tenant_manager.clone(),
+                handler_registry.mcp_registry(),
+                handler_registry.aws_service(),
+            )),
+        );
+        handler_registry.register(
+            "usage_thresholds_set",
+            Arc::new(UsageThresholdsSetHandler::new(
+                tenant_manager.clone(),
+                handler_registry.aws_service(),
+            )),
+        );
+        handler_registry.register(
+            "rate_limit_status",
+            Arc::new(RateLimitStatusHandler::new(tenant_manager.clone())),
+        );
+        let mut server_stats =
+            ServerStatsHandler::new(tenant_manager.clone(), handler_registry.tool_concurrency())
+                .with_registry(handler_registry.mcp_registry())
+                .with_call_counters(call_counters.clone())
+                .with_aws_service(handler_registry.aws_service());
+        if let Some(response_cache) = handler_registry.response_cache() {
+            server_stats = server_stats.with_response_cache(response_cache);
+        }
+        let server_stats = Arc::new(server_stats);
+        handler_registry.register("server_stats", server_stats.clone());
+        handler_registry.register(
+            "diagnostics_bundle",
+            Arc::new(
+                DiagnosticsBundleHandler::new(tenant_manager.clone(), server_stats)
+                    .with_registry(handler_registry.mcp_registry())
+                    .with_aws_service(handler_registry.aws_service()),
+            ),
+        );
         eprintln!("[MCP Server] Handlers initialized successfully");
 
+        // Publishing is best effort: the flusher only logs its failures
+        if let Some(namespace) = &tenant_manager.config().aws.cloudwatch_namespace {
+            if let Some(client) = handler_registry.aws_service().cloudwatch_client() {
+                eprintln!(
+                    "[MCP Server] Publishing metrics to CloudWatch namespace {}",
+                    namespace
+                );
+                MetricsFlusher::new(
+                    call_counters.clone(),
+                    Arc::new(CloudWatchSink::new(client)),
+                    namespace,
+                )
+                .spawn(tenant_manager.clone(), metrics::FLUSH_INTERVAL);
+            }
+        }
+
+        // Stops with the tenant manager; crossings are logged, never surfaced to callers
+        UsageThresholdEvaluator::new(
+            tenant_manager.clone(),
+            handler_registry.aws_service(),
+            call_counters.clone(),
+        )
+        .spawn(usage_thresholds::EVALUATION_INTERVAL);
+
+        let rate_limit_exempt_methods = Self::exempt_methods(tenant_manager.config());
         Ok(Self {
             tenant_manager,
             handler_registry,
             shutdown_flag: Arc::new(RwLock::new(false)),
+            rate_limit_exempt_methods,
+            call_counters,
         })
     }
 
+    /// The configured `rate_limit_exempt_methods` override the defaults; set them empty
+    /// to rate limit every method
+    fn exempt_methods(config: &Config) -> Vec<String> {
+        match &config.server.rate_limit_exempt_methods {
+            Some(methods) => methods.clone(),
+            None => DEFAULT_RATE_LIMIT_EXEMPT_METHODS
+                .iter()
+                .map(|m| m.to_string())
+                .collect(),
+        }
+    }
+
+    /// Replace the methods exempt from the legacy per-minute limiter
+    #[allow(dead_code)]
+    pub fn with_rate_limit_exempt_methods(mut self, methods: Vec<String>) -> Self {
+        self.rate_limit_exempt_methods = methods;
+        self
+    }
+
+    fn is_rate_limit_exempt(&self, method: &str) -> bool {
+        self.rate_limit_exempt_methods
+            .iter()
+            .any(|pattern| glob_match(pattern, method))
+    }
+
+    /// Serve JSON-RPC on stdin and stdout until stdin closes, then shut down (see
+    /// [`MCPServer::run_with_io`])
     pub async fn run(&self) -> anyhow::Result<()> {
         // Log to stderr - stdout is reserved for JSON-RPC protocol
         eprintln!("[MCP Server] Starting on STDIO");
+        self.run_with_io(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+            .await
+    }
 
-        let stdin = tokio::io::stdin();
-        let mut reader = BufReader::new(stdin);
-        let mut stdout = tokio::io::stdout();
-
+    /// Serve newline-delimited JSON-RPC read from `reader`, answering on `writer`,
+    /// until `reader` ends, then wait for requests in flight and shut down, as the
+    /// stdio transport does. Each request is handled as over stdio: its session comes
+    /// from its own `tenant_id` and `user_id` or the configured defaults. To serve many
+    /// connections from one server, use [`crate::transport::stream::serve_connection`].
+    pub async fn run_with_io<R, W>(&self, mut reader: R, mut writer: W) -> anyhow::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
         let mut line = String::new();
 
         loop {
@@ -117,29 +537,25 @@ impl MCPServer {
             match reader.read_line(&mut line).await {
                 Ok(0) => {
                     // EOF reached - initiate graceful shutdown
-                    eprintln!("[MCP Server] EOF detected on stdin, initiating shutdown");
+                    eprintln!("[MCP Server] EOF detected on input, initiating shutdown");
                     self.initiate_shutdown().await;
                     break;
                 }
                 Ok(_) => {
                     // Check if shutdown was initiated
-                    if *self.shutdown_flag.read().await {
+                    if self.is_shutting_down().await {
                         eprintln!("[MCP Server] Shutdown in progress, ignoring new requests");
                         break;
                     }
 
                     if let Some(response) = self.handle_request(line.trim()).await {
-                        let response_json = serde_json::to_string(&response)?;
-
-                        stdout.write_all(response_json.as_bytes()).await?;
-                        stdout.write_all(b"\n").await?;
-                        stdout.flush().await?;
+                        write_response(&mut writer, &response).await?;
                     }
                     // If None, it was a notification - no response needed
                 }
                 Err(e) => {
-                    // Log errors to stderr, not stdout
-                    eprintln!("[MCP Server] Error reading from stdin: {}", e);
+                    // Log errors to stderr, not the output stream
+                    eprintln!("[MCP Server] Error reading input: {}", e);
                     self.initiate_shutdown().await;
                     break;
                 }
@@ -150,14 +566,42 @@ impl MCPServer {
         self.wait_for_active_requests().await;
 
         eprintln!("[MCP Server] All requests completed, exiting");
+        self.shutdown().await;
         Ok(())
     }
 
+    /// Serve the Unix socket at `path` until ctrl-c, one session per connection (see
+    /// [`crate::transport::unix::serve_until`] to stop it some other way)
+    #[cfg(unix)]
+    #[allow(dead_code)]
+    pub async fn serve_unix_socket(
+        self: &Arc<Self>,
+        path: impl AsRef<std::path::Path>,
+    ) -> anyhow::Result<()> {
+        crate::transport::unix::serve(self.clone(), path).await
+    }
+
+    /// Whether [`MCPServer::shutdown`] has begun; connection loops stop reading then
+    pub async fn is_shutting_down(&self) -> bool {
+        *self.shutdown_flag.read().await
+    }
+
     async fn initiate_shutdown(&self) {
         let mut shutdown = self.shutdown_flag.write().await;
         *shutdown = true;
     }
 
+    /// Stop accepting requests, send any buffered events and stop every downstream MCP
+    /// server process and container. Safe to call more than once.
+    pub async fn shutdown(&self) {
+        self.initiate_shutdown().await;
+        eprintln!("[MCP Server] Stopping background jobs");
+        self.handler_registry.jobs().shutdown().await;
+        self.handler_registry.aws_service().flush_events().await;
+        eprintln!("[MCP Server] Stopping downstream MCP servers");
+        self.handler_registry.mcp_registry().shutdown().await;
+    }
+
     async fn wait_for_active_requests(&self) {
         // Wait up to 5 seconds for active requests to complete
         let max_wait = std::time::Duration::from_secs(5);
@@ -167,7 +611,7 @@ impl MCPServer {
         eprintln!("[MCP Server] Waiting for active requests to complete...");
 
         while start.elapsed() < max_wait {
-            let active_count = self.get_total_active_requests().await;
+            let active_count = self.tenant_manager.total_active_requests();
 
             if active_count == 0 {
                 eprintln!("[MCP Server] No active requests remaining");
@@ -181,20 +625,6 @@ impl MCPServer {
         eprintln!("[MCP Server] Timeout waiting for active requests, forcing shutdown");
     }
 
-    async fn get_total_active_requests(&self) -> u32 {
-        // Count active requests across all sessions (now lock-free with atomics)
-        let sessions = self.tenant_manager.get_all_sessions().await;
-        let mut total = 0;
-
-        for session in sessions {
-            total += session
-                .active_requests
-                .load(std::sync::atomic::Ordering::SeqCst);
-        }
-
-        total
-    }
-
     pub async fn handle_request(&self, request_line: &str) -> Option<MCPResponse> {
         // Parse the JSON-RPC request
         let request: MCPRequest = match serde_json::from_str(request_line) {
@@ -218,45 +648,177 @@ impl MCPServer {
             return None;
         }
 
-        // Handle the request with tenant context
-        match self.process_request(request).await {
-            Ok(result) => Some(MCPResponse {
+        // Create a tenant session for this request
+        match self.get_or_create_session(&request).await {
+            Ok(session) => Some(self.respond(session, request).await),
+            Err(error) => Some(Self::response(request_id, Err(error))),
+        }
+    }
+
+    /// Handle a request on an already established session (used by transports with
+    /// session affinity, where the session is looked up from `Mcp-Session-Id`)
+    pub async fn handle_session_request(
+        &self,
+        session: Arc<TenantSession>,
+        request: MCPRequest,
+    ) -> Option<MCPResponse> {
+        let request_id = request.id.clone();
+        if request_id.is_none() {
+            debug!("Received notification: {}", request.method);
+            return None;
+        }
+
+        Some(self.respond(session, request).await)
+    }
+
+    /// Create a session for the tenant/user identified by the request (or env defaults)
+    pub async fn open_session(&self, request: &MCPRequest) -> Result<Arc<TenantSession>, MCPError> {
+        self.get_or_create_session(request).await
+    }
+
+    /// Tenant manager backing this server's sessions
+    pub fn tenant_manager(&self) -> Arc<TenantManager> {
+        self.tenant_manager.clone()
+    }
+
+    /// Renders this server's counters for a Prometheus scrape
+    pub fn prometheus_exporter(&self) -> PrometheusExporter {
+        PrometheusExporter::new(self.call_counters.clone(), self.tenant_manager.clone())
+            .with_registry(self.handler_registry.mcp_registry())
+    }
+
+    /// Process the request inside its [`REQUEST_SPAN`], recording how long it took and
+    /// how it ended
+    async fn respond(&self, session: Arc<TenantSession>, request: MCPRequest) -> MCPResponse {
+        let request_id = request.id.clone();
+        let id_field = request_id
+            .as_ref()
+            .map(Value::to_string)
+            .unwrap_or_default();
+        let span = info_span!(
+            REQUEST_SPAN,
+            request.id = %id_field,
+            mcp.method = %request.method,
+            tenant.id = %session.context.tenant_id,
+            user.id = %session.context.user_id,
+            session.id = %session.session_id,
+            tool = Empty,
+            duration_ms = Empty,
+            outcome = Empty,
+            error.code = Empty,
+        );
+        if let Some(tool) = called_tool(&request) {
+            span.record("tool", tool);
+        }
+
+        let method = metered_method(&request.method);
+        let started = Instant::now();
+        let result = self
+            .process_request(session, request)
+            .instrument(span.clone())
+            .await;
+        let response = Self::response(request_id, result);
+        span.record("duration_ms", started.elapsed().as_millis() as u64);
+        self.call_counters
+            .record_request(method, response.error.as_ref().map(|error| error.code));
+        match &response.error {
+            None => span.record("outcome", "ok"),
+            Some(error) => span
+                .record("outcome", "error")
+                .record("error.code", error.code),
+        };
+        response
+    }
+
+    fn response(id: Option<Value>, result: Result<Value, MCPError>) -> MCPResponse {
+        match result {
+            Ok(result) => MCPResponse {
                 jsonrpc: "2.0".to_string(),
-                id: request_id,
+                id,
                 result: Some(result),
                 error: None,
-            }),
-            Err(error) => Some(MCPResponse {
+            },
+            Err(error) => MCPResponse {
                 jsonrpc: "2.0".to_string(),
-                id: request_id,
+                id,
                 result: None,
                 error: Some(error.into()),
-            }),
+            },
         }
     }
 
-    async fn process_request(&self, request: MCPRequest) -> Result<Value, MCPError> {
+    async fn process_request(
+        &self,
+        session: Arc<TenantSession>,
+        request: MCPRequest,
+    ) -> Result<Value, MCPError> {
         debug!("Processing request: {}", request.method);
 
-        // Create or get tenant session
-        let session = self.get_or_create_session(&request).await?;
+        // Protocol-control methods don't spend the per-minute budget (but still count
+        // as active requests below)
+        let exempt = self.is_rate_limit_exempt(&request.method);
 
         // Check legacy rate limiting first (now synchronous with atomics)
-        if !session.check_rate_limit() {
-            return Err(MCPError::RateLimitExceeded);
+        if !exempt {
+            if let Err(rejection) = session.check_rate_limit() {
+                return Err(self.rate_limited(&session, &request, rejection).await);
+            }
         }
 
+        // Enforce max_concurrent_requests across all of the tenant's sessions. Taken before
+        // the AWS check so requests waiting for capacity still count as in flight.
+        let tenant_slot = self
+            .tenant_manager
+            .try_acquire_request_slot(&session)
+            .await
+            .map_err(|active| MCPError::ConcurrencyLimitExceeded {
+                active,
+                limit: session.context.resource_limits.max_concurrent_requests,
+            })?;
+        let _active_count = session.increment_active_requests();
+
+        // Track request for cleanup
+        let _guard = RequestGuard::new(
+            session.clone(),
+            self.tenant_manager.active_request_counter(),
+            tenant_slot,
+        );
+
         // For tool calls, also check AWS-specific rate limiting
+        let mut charged_quota = Vec::new();
         if request.method == "tools/call" {
             if let Some(params) = &request.params {
                 if let Some(tool_name) = params.get("name").and_then(|v| v.as_str()) {
-                    if let Some(aws_operation) = AwsOperation::from_tool_name(tool_name, params) {
+                    let arguments = params.get("arguments").unwrap_or(&Value::Null);
+                    if let Some(aws_operation) = AwsOperation::from_tool_name(tool_name, arguments)
+                    {
+                        // Opt-in per call or per tenant: wait for capacity instead of rejecting
+                        let limits = &session.context.resource_limits;
+                        let wait_for_capacity = params
+                            .get("wait_for_capacity")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(limits.wait_for_capacity);
+                        let max_wait = wait_for_capacity
+                            .then(|| Duration::from_millis(limits.max_capacity_wait_ms));
+
                         let aws_limiter = self.tenant_manager.get_aws_rate_limiter();
-                        if !session
-                            .check_aws_operation(&aws_limiter, &aws_operation)
+                        aws_limiter
+                            .check_operation_fits(&session.context.tenant_id, &aws_operation)
+                            .await
+                            .map_err(|oversized| MCPError::OperationTooLarge { oversized })?;
+                        let priority = OperationPriority::for_tool(
+                            tool_name,
+                            arguments,
+                            session.has_permission(&Permission::Admin),
+                        );
+                        match session
+                            .check_aws_operation(&aws_limiter, &aws_operation, priority, max_wait)
                             .await
                         {
-                            return Err(MCPError::RateLimitExceeded);
+                            Ok(charged) => charged_quota = charged,
+                            Err(rejection) => {
+                                return Err(self.rate_limited(&session, &request, rejection).await)
+                            }
                         }
                     }
                 }
@@ -264,11 +826,9 @@ impl MCPServer {
         }
 
         // Increment request counters (now synchronous with atomics)
-        session.increment_request_count();
-        let _active_count = session.increment_active_requests();
-
-        // Track request for cleanup
-        let _guard = RequestGuard::new(session.clone());
+        if !exempt {
+            session.increment_request_count();
+        }
 
         // Update activity timestamp
         session.update_activity().await;
@@ -276,21 +836,55 @@ impl MCPServer {
         // Route the request to appropriate handler
         match request.method.as_str() {
             "initialize" => self.handle_initialize().await,
+            "ping" => Ok(serde_json::json!({})),
             "tools/list" => self.handle_list_tools(&session).await,
-            "tools/call" => self.handle_tool_call(&session, request.params).await,
+            "tools/call" => {
+                let include_meta = request
+                    .params
+                    .as_ref()
+                    .and_then(|params| params.get("include_meta"))
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                let result = self.handle_tool_call(&session, request.params).await?;
+                Ok(if include_meta {
+                    with_rate_limit_meta(result, &charged_quota)
+                } else {
+                    result
+                })
+            }
             "notifications/initialized" => Ok(serde_json::Value::Null),
             _ => Err(MCPError::MethodNotFound(request.method)),
         }
     }
 
+    async fn rate_limited(
+        &self,
+        session: &TenantSession,
+        request: &MCPRequest,
+        rejection: RateLimitRejection,
+    ) -> MCPError {
+        // Counted under the tool for tool calls, else under the method
+        self.call_counters.record(
+            &session.context.tenant_id,
+            called_tool(request).unwrap_or(&request.method),
+            CallOutcome::RateLimited,
+        );
+        self.call_counters.record_rejection(&rejection.service_key);
+        MCPError::RateLimitExceeded {
+            rejection,
+            status: Some(self.tenant_manager.rate_limit_status(session).await),
+        }
+    }
+
     async fn get_or_create_session(
         &self,
         request: &MCPRequest,
     ) -> Result<Arc<TenantSession>, MCPError> {
-        // Use environment defaults if not provided in request (for local dev)
+        // Use the configured defaults if not provided in request (for local dev)
+        let config = self.tenant_manager.config();
         let tenant_id = match &request.tenant_id {
             Some(id) => id.clone(),
-            None => std::env::var("DEFAULT_TENANT_ID").map_err(|_| {
+            None => config.default_tenant_id.clone().ok_or_else(|| {
                 MCPError::InvalidRequest(
                     "tenant_id is required (set DEFAULT_TENANT_ID env var for local dev)"
                         .to_string(),
@@ -300,7 +894,7 @@ impl MCPServer {
 
         let user_id = match &request.user_id {
             Some(id) => id.clone(),
-            None => std::env::var("DEFAULT_USER_ID").map_err(|_| {
+            None => config.default_user_id.clone().ok_or_else(|| {
                 MCPError::InvalidRequest(
                     "user_id is required (set DEFAULT_USER_ID env var for local dev)".to_string(),
                 )
@@ -314,7 +908,7 @@ impl MCPServer {
 
         // Create new session
         self.tenant_manager
-            .create_session(&tenant_id)
+            .create_session(&tenant_id, &user_id)
             .await
             .map_err(MCPError::TenantError)
     }
@@ -327,7 +921,8 @@ impl MCPServer {
             },
             "serverInfo": {
                 "name": "mcp-rust",
-                "version": "0.1.0"
+                "version": "0.1.0",
+                "backend": self.tenant_manager.config().aws.mode()
             }
         });
 
@@ -342,6 +937,7 @@ impl MCPServer {
             .map_err(|e| MCPError::HandlerError(e.to_string()))?;
 
         Ok(serde_json::json!({
+            "schemaVersion": TOOLS_SCHEMA_VERSION,
             "tools": tools
         }))
     }
@@ -372,24 +968,123 @@ impl MCPServer {
             tool_name, session.session_id
         );
 
+        let idempotency_key = params.get("idempotency_key").and_then(|v| v.as_str());
         let result = self
             .handler_registry
-            .handle_tool_call(session, &tool_name, arguments)
-            .await
-            .map_err(|e| MCPError::HandlerError(e.to_string()))?;
+            .handle_tool_call_with_key(session, &tool_name, arguments, idempotency_key)
+            .await;
 
-        Ok(result)
+        match &result {
+            Ok(value) => {
+                // Charge response-size costs that couldn't be known up front
+                if let Some(extra) = AwsOperation::response_adjustment(&tool_name, value) {
+                    self.tenant_manager
+                        .get_aws_rate_limiter()
+                        .charge_after(&session.context.tenant_id, &extra)
+                        .await;
+                }
+            }
+            Err(HandlerError::Aws(AwsError::Throttled { service_key, .. })) => {
+                self.report_aws_throttle(service_key).await;
+            }
+            Err(_) => {}
+        }
+
+        result.map_err(MCPError::from)
+    }
+
+    /// Feed AWS-side throttling back into the limiter, announcing when it engages
+    async fn report_aws_throttle(&self, service_key: &'static str) {
+        let limiter = self.tenant_manager.get_aws_rate_limiter();
+        if !limiter.report_throttle(service_key).await {
+            return;
+        }
+
+        let factor = limiter.throttle_factor(service_key).await;
+        let aws_service = self.handler_registry.aws_service();
+        tokio::spawn(async move {
+            let detail = serde_json::json!({
+                "service_key": service_key,
+                "refill_rate_factor": factor,
+            });
+            if let Err(e) = aws_service
+                .send_system_event("RateLimit.AdaptiveThrottleEngaged", detail)
+                .await
+            {
+                warn!("Failed to publish adaptive throttle event: {}", e);
+            }
+        });
+    }
+}
+
+/// The method as counted in request metrics: one this server handles, or `other`, so
+/// clients can't mint new series
+fn metered_method(method: &str) -> &'static str {
+    const METHODS: &[&str] = &[
+        "initialize",
+        "ping",
+        "tools/list",
+        "tools/call",
+        "notifications/initialized",
+    ];
+    METHODS
+        .iter()
+        .find(|known| **known == method)
+        .copied()
+        .unwrap_or(metrics::OTHER)
+}
+
+/// The tool a `tools/call` request names
+fn called_tool(request: &MCPRequest) -> Option<&str> {
+    if request.method != "tools/call" {
+        return None;
     }
+    request.params.as_ref()?.get("name")?.as_str()
 }
 
-// RAII guard to ensure active request count is decremented
+/// Attach the balance of the buckets a tool call was charged to as `_meta.rateLimit`
+/// (and `_meta.globalRateLimit` when an account-wide bucket was charged too)
+fn with_rate_limit_meta(mut result: Value, charged: &[RemainingQuota]) -> Value {
+    let Value::Object(fields) = &mut result else {
+        return result;
+    };
+    let meta = fields
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(serde_json::Map::new()));
+    if let Value::Object(meta) = meta {
+        for quota in charged {
+            let key = match quota.scope {
+                RateLimitScope::Tenant => "rateLimit",
+                RateLimitScope::Global => "globalRateLimit",
+                // Quotas are only reported for our own buckets
+                RateLimitScope::Aws => continue,
+            };
+            meta.insert(key.to_string(), serde_json::json!(quota));
+        }
+    }
+    result
+}
+
+// RAII guard to ensure active request counts (per-session, per-tenant and global) are decremented
 struct RequestGuard {
     session: Arc<TenantSession>,
+    global_active: Arc<AtomicU32>,
+    // Released on drop alongside the other counters
+    _tenant_slot: TenantRequestSlot,
 }
 
 impl RequestGuard {
-    fn new(session: Arc<TenantSession>) -> Self {
-        Self { session }
+    fn new(
+        session: Arc<TenantSession>,
+        global_active: Arc<AtomicU32>,
+        tenant_slot: TenantRequestSlot,
+    ) -> Self {
+        global_active.fetch_add(1, Ordering::SeqCst);
+        Self {
+            session,
+            global_active,
+            _tenant_slot: tenant_slot,
+        }
     }
 }
 
@@ -398,5 +1093,10 @@ impl Drop for RequestGuard {
         // CRITICAL FIX: Use lock-free atomic decrement (no async needed)
         // This is safe to call from any context, including Drop
         self.session.decrement_active_requests();
+        self.global_active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                current.checked_sub(1)
+            })
+            .ok();
     }
 }
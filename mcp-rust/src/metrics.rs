@@ -0,0 +1,512 @@
+use async_trait::async_trait;
+use aws_sdk_cloudwatch::types::{Dimension, MetricDatum as CloudWatchDatum, StandardUnit};
+use aws_sdk_cloudwatch::Client as CloudWatchClient;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+use crate::tenant::TenantManager;
+
+pub mod prometheus;
+
+/// Set to a CloudWatch namespace (e.g. `AgentMesh/MCP`) to publish server metrics there
+pub const CLOUDWATCH_NAMESPACE_ENV: &str = "MCP_CLOUDWATCH_NAMESPACE";
+
+/// How often counters are flushed to CloudWatch
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tenants (by calls in the flush window) published under their own id; the rest are
+/// summed as [`OTHER`]
+pub const DEFAULT_TOP_TENANTS: usize = 10;
+
+/// Tools published under their own name, likewise
+pub const DEFAULT_TOP_TOOLS: usize = 25;
+
+/// Dimension value standing in for the tenants or tools beyond the top N
+pub const OTHER: &str = "other";
+
+/// PutMetricData accepts at most this many data points per request
+const MAX_DATA_PER_REQUEST: usize = 1000;
+
+#[derive(Error, Debug)]
+pub enum MetricsError {
+    #[error("CloudWatch error: {0}")]
+    CloudWatch(String),
+}
+
+/// How a tool call ended, as far as metrics are concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallOutcome {
+    Ok,
+    Error,
+    /// Rejected by the tenant's rate limits before it ran
+    RateLimited,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallCount {
+    pub calls: u64,
+    pub errors: u64,
+    pub rate_limited: u64,
+}
+
+impl CallCount {
+    fn add(&mut self, other: &CallCount) {
+        self.calls += other.calls;
+        self.errors += other.errors;
+        self.rate_limited += other.rate_limited;
+    }
+
+    /// Counts since `earlier`, a previous reading of the same counter
+    fn since(&self, earlier: &CallCount) -> CallCount {
+        CallCount {
+            calls: self.calls - earlier.calls,
+            errors: self.errors - earlier.errors,
+            rate_limited: self.rate_limited - earlier.rate_limited,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == CallCount::default()
+    }
+}
+
+/// A tenant's metered usage over one UTC day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub tool_calls: u64,
+    /// Events sent with `events_send`
+    pub events: u64,
+    /// Bytes of values written with `kv_set`
+    pub kv_bytes: u64,
+}
+
+impl DailyUsage {
+    pub fn add(&mut self, other: &DailyUsage) {
+        self.tool_calls += other.tool_calls;
+        self.events += other.events;
+        self.kv_bytes += other.kv_bytes;
+    }
+}
+
+/// Upper bounds, in seconds, of the tool latency histogram's buckets
+pub const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Tool call durations, counted into [`LATENCY_BUCKETS`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyHistogram {
+    /// Calls in each bucket and no lower one; slower calls only count in `count`
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+impl LatencyHistogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum_seconds += seconds;
+    }
+}
+
+/// Process-wide tool call counters by tenant and tool, since startup, plus the
+/// request, latency and rate-limit counters the Prometheus endpoint exposes
+#[derive(Default)]
+pub struct CallCounters {
+    counts: Mutex<HashMap<(String, String), CallCount>>,
+    /// Requests by method and JSON-RPC error code (`ok` when there was none)
+    requests: Mutex<HashMap<(String, String), u64>>,
+    latencies: Mutex<HashMap<String, LatencyHistogram>>,
+    /// Rate-limit rejections by the bucket that rejected them
+    rejections: Mutex<HashMap<String, u64>>,
+    /// Usage by tenant and UTC day not yet added to the persisted rollups, for today
+    /// and yesterday only
+    unpersisted: Mutex<HashMap<(String, NaiveDate), DailyUsage>>,
+}
+
+impl CallCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, tenant_id: &str, tool: &str, outcome: CallOutcome) {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts
+            .entry((tenant_id.to_string(), tool.to_string()))
+            .or_default();
+        match outcome {
+            CallOutcome::Ok => count.calls += 1,
+            CallOutcome::Error => {
+                count.calls += 1;
+                count.errors += 1;
+            }
+            CallOutcome::RateLimited => count.rate_limited += 1,
+        }
+    }
+
+    /// Count a finished request; `method` must come from a bounded set
+    pub fn record_request(&self, method: &str, error_code: Option<i32>) {
+        let code = error_code.map_or_else(|| "ok".to_string(), |code| code.to_string());
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), code))
+            .or_default() += 1;
+    }
+
+    /// How long a call of `tool` took, from the middleware chain's point of view
+    pub fn record_duration(&self, tool: &str, elapsed: Duration) {
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry(tool.to_string())
+            .or_default()
+            .observe(elapsed);
+    }
+
+    pub fn record_rejection(&self, service_key: &str) {
+        *self
+            .rejections
+            .lock()
+            .unwrap()
+            .entry(service_key.to_string())
+            .or_default() += 1;
+    }
+
+    /// Add to the tenant's usage on `day`. Unpersisted usage of days before the one
+    /// preceding `day` is dropped, so it stays two days long.
+    pub fn record_usage(&self, tenant_id: &str, day: NaiveDate, usage: DailyUsage) {
+        let mut unpersisted = self.unpersisted.lock().unwrap();
+        unpersisted
+            .entry((tenant_id.to_string(), day))
+            .or_default()
+            .add(&usage);
+        if let Some(yesterday) = day.pred_opt() {
+            unpersisted.retain(|(_, recorded), _| *recorded >= yesterday);
+        }
+    }
+
+    /// Usage recorded since the last call, by tenant and day, for adding to the
+    /// persisted rollups
+    pub fn take_unpersisted_usage(&self) -> Vec<(String, NaiveDate, DailyUsage)> {
+        self.unpersisted
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|((tenant_id, day), usage)| (tenant_id, day, usage))
+            .collect()
+    }
+
+    /// Put back usage taken with [`Self::take_unpersisted_usage`] that couldn't be
+    /// persisted, so the next attempt includes it
+    pub fn return_unpersisted_usage(&self, tenant_id: &str, day: NaiveDate, usage: DailyUsage) {
+        self.unpersisted
+            .lock()
+            .unwrap()
+            .entry((tenant_id.to_string(), day))
+            .or_default()
+            .add(&usage);
+    }
+
+    /// Request counts by (method, code)
+    pub fn requests(&self) -> HashMap<(String, String), u64> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Call durations by tool
+    pub fn latencies(&self) -> HashMap<String, LatencyHistogram> {
+        self.latencies.lock().unwrap().clone()
+    }
+
+    /// Rate-limit rejections by service key
+    pub fn rejections(&self) -> HashMap<String, u64> {
+        self.rejections.lock().unwrap().clone()
+    }
+
+    /// Every (tenant, tool) counter
+    pub fn snapshot(&self) -> HashMap<(String, String), CallCount> {
+        self.counts.lock().unwrap().clone()
+    }
+
+    /// Counters summed over tenants, by tool
+    pub fn by_tool(&self) -> HashMap<String, CallCount> {
+        let mut by_tool: HashMap<String, CallCount> = HashMap::new();
+        for ((_, tool), count) in self.counts.lock().unwrap().iter() {
+            by_tool.entry(tool.clone()).or_default().add(count);
+        }
+        by_tool
+    }
+}
+
+/// One CloudWatch data point
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDatum {
+    pub name: &'static str,
+    /// `(dimension, value)` pairs; empty for server-wide totals
+    pub dimensions: Vec<(&'static str, String)>,
+    pub value: f64,
+}
+
+impl MetricDatum {
+    fn new(name: &'static str, dimensions: Vec<(&'static str, String)>, value: f64) -> Self {
+        Self {
+            name,
+            dimensions,
+            value,
+        }
+    }
+}
+
+/// Point-in-time server values published alongside the counters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ServerGauges {
+    pub active_sessions: usize,
+    pub active_requests: u32,
+}
+
+/// Where metric batches are sent: CloudWatch, or a stub in tests
+#[async_trait]
+pub trait MetricsSink: Send + Sync {
+    async fn put_metric_data(
+        &self,
+        namespace: &str,
+        data: Vec<MetricDatum>,
+    ) -> Result<(), MetricsError>;
+}
+
+/// The values of `keys` ranked by `weight` (heaviest first, then by name) mapped to
+/// themselves for the first `limit`, and to [`OTHER`] after that
+fn cap<'a>(
+    keys: impl Iterator<Item = (&'a String, u64)>,
+    limit: usize,
+) -> HashMap<&'a String, String> {
+    let mut weights: HashMap<&String, u64> = HashMap::new();
+    for (key, weight) in keys {
+        *weights.entry(key).or_default() += weight;
+    }
+    let mut ranked: Vec<(&String, u64)> = weights.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    ranked
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (key, _))| {
+            let label = if rank < limit {
+                key.clone()
+            } else {
+                OTHER.to_string()
+            };
+            (key, label)
+        })
+        .collect()
+}
+
+/// The data points for one flush window: per tenant and tool (the top `top_tenants`
+/// tenants and `top_tools` tools by calls, the rest summed as `other`), server-wide
+/// totals and the gauges. Zero counts are left out.
+pub fn build_batch(
+    deltas: &HashMap<(String, String), CallCount>,
+    gauges: ServerGauges,
+    top_tenants: usize,
+    top_tools: usize,
+) -> Vec<MetricDatum> {
+    let weight = |count: &CallCount| count.calls + count.rate_limited;
+    let tenants = cap(
+        deltas
+            .iter()
+            .map(|((tenant, _), count)| (tenant, weight(count))),
+        top_tenants,
+    );
+    let tools = cap(
+        deltas
+            .iter()
+            .map(|((_, tool), count)| (tool, weight(count))),
+        top_tools,
+    );
+
+    let mut capped: HashMap<(String, String), CallCount> = HashMap::new();
+    let mut total = CallCount::default();
+    for ((tenant, tool), count) in deltas {
+        capped
+            .entry((tenants[tenant].clone(), tools[tool].clone()))
+            .or_default()
+            .add(count);
+        total.add(count);
+    }
+    let mut capped: Vec<_> = capped.into_iter().collect();
+    capped.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut data = Vec::new();
+    let mut push_counts = |dimensions: Vec<(&'static str, String)>, count: &CallCount| {
+        for (name, value) in [
+            ("ToolCalls", count.calls),
+            ("ToolErrors", count.errors),
+            ("RateLimitRejections", count.rate_limited),
+        ] {
+            if value > 0 {
+                data.push(MetricDatum::new(name, dimensions.clone(), value as f64));
+            }
+        }
+    };
+    for ((tenant, tool), count) in &capped {
+        push_counts(
+            vec![("Tenant", tenant.clone()), ("Tool", tool.clone())],
+            count,
+        );
+    }
+    push_counts(Vec::new(), &total);
+
+    data.push(MetricDatum::new(
+        "ActiveSessions",
+        Vec::new(),
+        gauges.active_sessions as f64,
+    ));
+    data.push(MetricDatum::new(
+        "ActiveRequests",
+        Vec::new(),
+        gauges.active_requests as f64,
+    ));
+    data
+}
+
+/// Publishes what the [`CallCounters`] counted since the previous flush
+pub struct MetricsFlusher {
+    counters: Arc<CallCounters>,
+    sink: Arc<dyn MetricsSink>,
+    namespace: String,
+    top_tenants: usize,
+    top_tools: usize,
+    /// Counters as of the last successful flush
+    published: HashMap<(String, String), CallCount>,
+}
+
+impl MetricsFlusher {
+    pub fn new(counters: Arc<CallCounters>, sink: Arc<dyn MetricsSink>, namespace: &str) -> Self {
+        Self {
+            counters,
+            sink,
+            namespace: namespace.to_string(),
+            top_tenants: DEFAULT_TOP_TENANTS,
+            top_tools: DEFAULT_TOP_TOOLS,
+            published: HashMap::new(),
+        }
+    }
+
+    /// Change how many tenants and tools get their own dimension value
+    #[allow(dead_code)]
+    pub fn with_caps(mut self, top_tenants: usize, top_tools: usize) -> Self {
+        self.top_tenants = top_tenants;
+        self.top_tools = top_tools;
+        self
+    }
+
+    /// Send the counts since the last flush, in requests of at most 1000 data points.
+    /// On failure the counts are kept for the next flush.
+    pub async fn flush(&mut self, gauges: ServerGauges) -> Result<usize, MetricsError> {
+        let current = self.counters.snapshot();
+        let deltas: HashMap<(String, String), CallCount> = current
+            .iter()
+            .map(|(key, count)| {
+                let earlier = self.published.get(key).copied().unwrap_or_default();
+                (key.clone(), count.since(&earlier))
+            })
+            .filter(|(_, delta)| !delta.is_zero())
+            .collect();
+
+        let data = build_batch(&deltas, gauges, self.top_tenants, self.top_tools);
+        let sent = data.len();
+        for chunk in data.chunks(MAX_DATA_PER_REQUEST) {
+            self.sink
+                .put_metric_data(&self.namespace, chunk.to_vec())
+                .await?;
+        }
+        self.published = current;
+        Ok(sent)
+    }
+
+    /// Flush every `interval` until the process exits. Failures are logged and never
+    /// reach request handling.
+    pub fn spawn(
+        mut self,
+        tenant_manager: Arc<TenantManager>,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes at once
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let gauges = ServerGauges {
+                    active_sessions: tenant_manager.get_all_sessions().await.len(),
+                    active_requests: tenant_manager.total_active_requests(),
+                };
+                if let Err(e) = self.flush(gauges).await {
+                    eprintln!("[MCP Server] Failed to publish metrics: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// [`MetricsSink`] over CloudWatch `PutMetricData`
+pub struct CloudWatchSink {
+    client: CloudWatchClient,
+}
+
+impl CloudWatchSink {
+    pub fn new(client: CloudWatchClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl MetricsSink for CloudWatchSink {
+    async fn put_metric_data(
+        &self,
+        namespace: &str,
+        data: Vec<MetricDatum>,
+    ) -> Result<(), MetricsError> {
+        let timestamp =
+            aws_sdk_cloudwatch::primitives::DateTime::from(std::time::SystemTime::now());
+        let data = data
+            .into_iter()
+            .map(|datum| {
+                let dimensions = datum
+                    .dimensions
+                    .into_iter()
+                    .map(|(name, value)| Dimension::builder().name(name).value(value).build())
+                    .collect();
+                CloudWatchDatum::builder()
+                    .metric_name(datum.name)
+                    .set_dimensions(Some(dimensions))
+                    .value(datum.value)
+                    .unit(StandardUnit::Count)
+                    .timestamp(timestamp)
+                    .build()
+            })
+            .collect();
+        self.client
+            .put_metric_data()
+            .namespace(namespace)
+            .set_metric_data(Some(data))
+            .send()
+            .await
+            .map_err(|e| {
+                MetricsError::CloudWatch(
+                    aws_sdk_cloudwatch::error::DisplayErrorContext(&e).to_string(),
+                )
+            })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+
+use crate::metrics::{CallCounters, LATENCY_BUCKETS};
+use crate::registry::MCPServerRegistry;
+use crate::tenant::TenantManager;
+
+/// Set to an address (e.g. `0.0.0.0:9090`) to serve `/metrics` on a listener of its own,
+/// whichever transport the server runs
+pub const METRICS_ADDR_ENV: &str = "MCP_METRICS_ADDR";
+
+/// Content type of the text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Renders the counters behind `server_stats` in the Prometheus text exposition
+/// format. Labels are limited to method, error code, tool, rate-limit bucket and
+/// connection status, never tenants or users, so series stay bounded.
+#[derive(Clone)]
+pub struct PrometheusExporter {
+    counters: Arc<CallCounters>,
+    tenant_manager: Arc<TenantManager>,
+    registry: Option<Arc<MCPServerRegistry>>,
+}
+
+impl PrometheusExporter {
+    pub fn new(counters: Arc<CallCounters>, tenant_manager: Arc<TenantManager>) -> Self {
+        Self {
+            counters,
+            tenant_manager,
+            registry: None,
+        }
+    }
+
+    /// Also report downstream connections by status
+    pub fn with_registry(mut self, registry: Arc<MCPServerRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    pub async fn render(&self) -> String {
+        let mut out = String::new();
+
+        family(
+            &mut out,
+            "mcp_requests_total",
+            "counter",
+            "JSON-RPC requests by method and error code (ok when none)",
+        );
+        for ((method, code), count) in sorted(self.counters.requests()) {
+            sample(
+                &mut out,
+                "mcp_requests_total",
+                &[("method", &method), ("code", &code)],
+                count,
+            );
+        }
+
+        // Rate-limited calls are counted before the tool is looked up, so only tools
+        // that ran (and so are registered) get series here
+        let by_tool: BTreeMap<String, _> = self
+            .counters
+            .by_tool()
+            .into_iter()
+            .filter(|(_, count)| count.calls > 0)
+            .collect();
+        family(
+            &mut out,
+            "mcp_tool_calls_total",
+            "counter",
+            "Tool calls dispatched to a handler, by tool",
+        );
+        for (tool, count) in &by_tool {
+            sample(
+                &mut out,
+                "mcp_tool_calls_total",
+                &[("tool", tool)],
+                count.calls,
+            );
+        }
+        family(
+            &mut out,
+            "mcp_tool_errors_total",
+            "counter",
+            "Tool calls that failed, by tool",
+        );
+        for (tool, count) in &by_tool {
+            sample(
+                &mut out,
+                "mcp_tool_errors_total",
+                &[("tool", tool)],
+                count.errors,
+            );
+        }
+
+        family(
+            &mut out,
+            "mcp_tool_duration_seconds",
+            "histogram",
+            "Tool call duration through the middleware chain, by tool",
+        );
+        for (tool, histogram) in sorted(self.counters.latencies()) {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                sample(
+                    &mut out,
+                    "mcp_tool_duration_seconds_bucket",
+                    &[("tool", &tool), ("le", &bound.to_string())],
+                    cumulative,
+                );
+            }
+            sample(
+                &mut out,
+                "mcp_tool_duration_seconds_bucket",
+                &[("tool", &tool), ("le", "+Inf")],
+                histogram.count,
+            );
+            sample(
+                &mut out,
+                "mcp_tool_duration_seconds_sum",
+                &[("tool", &tool)],
+                histogram.sum_seconds,
+            );
+            sample(
+                &mut out,
+                "mcp_tool_duration_seconds_count",
+                &[("tool", &tool)],
+                histogram.count,
+            );
+        }
+
+        family(
+            &mut out,
+            "mcp_active_sessions",
+            "gauge",
+            "Sessions currently open",
+        );
+        sample(
+            &mut out,
+            "mcp_active_sessions",
+            &[],
+            self.tenant_manager.get_all_sessions().await.len(),
+        );
+        family(
+            &mut out,
+            "mcp_active_requests",
+            "gauge",
+            "Requests currently in flight",
+        );
+        sample(
+            &mut out,
+            "mcp_active_requests",
+            &[],
+            self.tenant_manager.total_active_requests(),
+        );
+
+        family(
+            &mut out,
+            "mcp_rate_limit_rejections_total",
+            "counter",
+            "Requests rejected by rate limits, by the bucket that rejected them",
+        );
+        for (service_key, count) in sorted(self.counters.rejections()) {
+            sample(
+                &mut out,
+                "mcp_rate_limit_rejections_total",
+                &[("service_key", &service_key)],
+                count,
+            );
+        }
+
+        if let Some(registry) = &self.registry {
+            family(
+                &mut out,
+                "mcp_registry_connections",
+                "gauge",
+                "Downstream MCP server connections by status, across tenants",
+            );
+            for (status, count) in registry.connection_status_counts().await {
+                sample(
+                    &mut out,
+                    "mcp_registry_connections",
+                    &[("status", &status)],
+                    count,
+                );
+            }
+        }
+        out
+    }
+}
+
+fn sorted<K: Ord, V>(map: std::collections::HashMap<K, V>) -> BTreeMap<K, V> {
+    map.into_iter().collect()
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl Display) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+            .collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {}", value);
+}
+
+/// Label values escape backslashes, double quotes and newlines
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
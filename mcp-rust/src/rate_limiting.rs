@@ -1,10 +1,24 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::time::Instant;
+use tracing::{info, warn};
 
-/// AWS service rate limits based on actual AWS capabilities
+pub mod config;
+pub mod distributed;
+
+use distributed::{DistributedLimiter, RateLimitFailurePolicy};
+
+/// AWS service rate limits based on actual AWS capabilities.
+///
+/// Each service gets a token bucket that refills at its steady per-second rate and holds
+/// up to `rate + burst` tokens, starting full. A quiet tenant can therefore issue a burst
+/// of `rate + burst` operations at once, after which it is throttled to the steady rate.
+/// The burst for a service comes from `service_burst` (keyed by service, e.g. `s3_get`),
+/// falling back to `aws_burst_capacity`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AwsServiceLimits {
     // DynamoDB limits (per second)
@@ -24,9 +38,38 @@ pub struct AwsServiceLimits {
     // Secrets Manager limits (per second)
     pub secrets_manager_requests_per_sec: u32, // Default: 5,000/sec
 
+    // SQS limits (per second); sends and receives have separate buckets
+    #[serde(default = "default_sqs_requests_per_sec")]
+    pub sqs_requests_per_sec: u32, // Default: 300/sec (the FIFO quota; standard queues are nearly unlimited)
+
     // General AWS API limits
     pub aws_api_calls_per_sec: u32, // Default: 2,000/sec (varies by service)
-    pub aws_burst_capacity: u32,    // Burst allowance
+    pub aws_burst_capacity: u32,    // Burst allowance on top of each service's rate
+
+    // Per-service burst overrides, keyed by service (e.g. "dynamodb_read")
+    #[serde(default)]
+    pub service_burst: HashMap<String, u32>,
+
+    // Bytes of artifact content covered by one s3_put token
+    #[serde(default = "default_s3_put_bytes_per_token")]
+    pub s3_put_bytes_per_token: u64,
+
+    // Extra pool per service, as a percentage of its capacity, that only critical
+    // operations (alert delivery, health checks) may draw on once the bucket is empty
+    #[serde(default = "default_critical_reserve_percent")]
+    pub critical_reserve_percent: u32,
+}
+
+fn default_s3_put_bytes_per_token() -> u64 {
+    1024 * 1024
+}
+
+fn default_critical_reserve_percent() -> u32 {
+    5
+}
+
+fn default_sqs_requests_per_sec() -> u32 {
+    300
 }
 
 impl Default for AwsServiceLimits {
@@ -46,12 +89,164 @@ impl Default for AwsServiceLimits {
 
             secrets_manager_requests_per_sec: 500,
 
+            sqs_requests_per_sec: default_sqs_requests_per_sec(),
+
             aws_api_calls_per_sec: 200,
             aws_burst_capacity: 1000,
+            service_burst: HashMap::new(),
+
+            s3_put_bytes_per_token: default_s3_put_bytes_per_token(),
+            critical_reserve_percent: default_critical_reserve_percent(),
+        }
+    }
+}
+
+/// How urgently an operation must get through when its tenant is rate limited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OperationPriority {
+    #[default]
+    Normal,
+    /// Alert delivery and health checks: may use the critical reserve pool
+    Critical,
+}
+
+impl OperationPriority {
+    /// Priority the server assigns to a tool call; clients can't request it directly.
+    /// Events flagged `detail.priority: "critical"` only count when `trusted_caller` (an
+    /// admin), as the flag is the caller's to set and would otherwise let anyone draw on
+    /// the reserve.
+    pub fn for_tool(tool_name: &str, args: &serde_json::Value, trusted_caller: bool) -> Self {
+        let is_critical = match tool_name {
+            "events_health_check" => true,
+            "events_send" | "events_send_linked" if trusted_caller => {
+                let critical = |event: &serde_json::Value| {
+                    event.pointer("/detail/priority").and_then(|v| v.as_str()) == Some("critical")
+                };
+                critical(args)
+                    || args
+                        .get("events")
+                        .and_then(|v| v.as_array())
+                        .is_some_and(|events| events.iter().any(critical))
+            }
+            _ => false,
+        };
+        if is_critical {
+            OperationPriority::Critical
+        } else {
+            OperationPriority::Normal
         }
     }
 }
 
+/// Which layer of limits rejected a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitScope {
+    /// The tenant's own budget
+    Tenant,
+    /// The account-wide budget shared by all tenants; backing off alone may not help
+    Global,
+    /// AWS itself throttled the operation, whatever budget was left here
+    Aws,
+}
+
+/// Why a request was rate limited and when it is worth retrying
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitRejection {
+    /// Bucket that rejected the request (e.g. `dynamodb_read`, `requests_per_minute`)
+    pub service_key: String,
+    /// Time until the bucket can satisfy the request
+    pub retry_after: Duration,
+    pub scope: RateLimitScope,
+}
+
+impl RateLimitRejection {
+    pub fn tenant(service_key: &str, retry_after: Duration) -> Self {
+        Self {
+            service_key: service_key.to_string(),
+            retry_after,
+            scope: RateLimitScope::Tenant,
+        }
+    }
+
+    pub fn global(service_key: &str, retry_after: Duration) -> Self {
+        Self {
+            service_key: service_key.to_string(),
+            retry_after,
+            scope: RateLimitScope::Global,
+        }
+    }
+
+    pub fn aws(service_key: &str, retry_after: Duration) -> Self {
+        Self {
+            service_key: service_key.to_string(),
+            retry_after,
+            scope: RateLimitScope::Aws,
+        }
+    }
+}
+
+/// An operation whose cost exceeds its bucket's capacity, so it could never be admitted
+#[derive(Debug, Clone, PartialEq)]
+pub struct OversizedOperation {
+    pub service_key: String,
+    pub cost: f64,
+    pub capacity: f64,
+}
+
+/// A bucket's balance right after an operation was charged to it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemainingQuota {
+    pub service_key: String,
+    pub remaining: f64,
+    pub capacity: f64,
+    /// Time until the bucket is full again
+    pub reset_ms: u64,
+    pub scope: RateLimitScope,
+}
+
+/// Point-in-time view of one token bucket, with refill computed up to now
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketSnapshot {
+    pub service_key: String,
+    pub tokens: f64,
+    pub capacity: f64,
+    pub refill_rate: f64,
+    pub time_to_full_ms: u64,
+}
+
+/// How the per-minute request limit counts requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAlgorithm {
+    /// Count requests in fixed one-minute windows; up to twice the limit can pass
+    /// across a window boundary
+    #[default]
+    Fixed,
+    /// Weight the previous window's count by how much of it still overlaps the last
+    /// minute, approximating a true sliding window with two counters
+    Sliding,
+}
+
+/// State of the `requests_per_minute` window for a session. For the sliding algorithm
+/// `requests` is the weighted estimate over the last minute, rounded up.
+#[derive(Debug, Clone, Serialize)]
+pub struct LegacyWindowStatus {
+    pub algorithm: RateLimitAlgorithm,
+    pub requests: u32,
+    pub limit: u32,
+    pub resets_in_ms: u64,
+}
+
+/// Everything that can throttle a tenant's requests
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitStatus {
+    pub tenant_id: String,
+    pub buckets: Vec<BucketSnapshot>,
+    pub requests_per_minute: LegacyWindowStatus,
+}
+
 /// Rate limiter bucket for tracking usage
 #[derive(Debug)]
 struct RateLimitBucket {
@@ -71,19 +266,97 @@ impl RateLimitBucket {
         }
     }
 
-    fn try_consume(&mut self, tokens: f64) -> bool {
-        self.refill();
+    /// Consume tokens, or return how long until enough tokens will have refilled
+    fn try_consume_at(&mut self, tokens: f64, now: Instant) -> Result<(), Duration> {
+        self.check_at(tokens, now)?;
+        self.tokens -= tokens;
+        Ok(())
+    }
+
+    /// Refill, then report whether `tokens` could be consumed, without consuming them
+    fn check_at(&mut self, tokens: f64, now: Instant) -> Result<(), Duration> {
+        self.refill_at(now);
 
         if self.tokens >= tokens {
-            self.tokens -= tokens;
-            true
+            Ok(())
         } else {
-            false
+            Err(self.time_until_available(tokens))
         }
     }
 
+    /// Balance as of the last refill, for reporting right after a charge
+    fn remaining(&self, service_key: &str, scope: RateLimitScope) -> RemainingQuota {
+        RemainingQuota {
+            service_key: service_key.to_string(),
+            remaining: self.tokens,
+            capacity: self.capacity,
+            reset_ms: self.time_until_available(self.capacity).as_millis() as u64,
+            scope,
+        }
+    }
+
+    /// Deduct tokens unconditionally, allowing a debt of up to one full bucket
+    fn charge(&mut self, tokens: f64) {
+        self.refill();
+        self.tokens = (self.tokens - tokens).max(-self.capacity);
+    }
+
+    /// Give back tokens charged for an operation that didn't go ahead
+    fn refund(&mut self, tokens: f64) {
+        self.refill();
+        self.tokens = (self.tokens + tokens).min(self.capacity);
+    }
+
+    /// Time until `tokens` are available, rounded up to the next millisecond.
+    /// Costs above capacity are measured against a full bucket.
+    fn time_until_available(&self, tokens: f64) -> Duration {
+        let deficit = tokens.min(self.capacity) - self.tokens;
+        if deficit <= 0.0 {
+            return Duration::ZERO;
+        }
+        if self.refill_rate <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::from_millis((deficit / self.refill_rate * 1000.0).ceil() as u64)
+    }
+
+    /// Describe the bucket as of `now` without mutating it
+    fn snapshot_at(&self, service_key: &str, now: Instant) -> BucketSnapshot {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        let tokens = (self.tokens + elapsed * self.refill_rate).min(self.capacity);
+        let virtual_bucket = RateLimitBucket {
+            tokens,
+            last_refill: now,
+            capacity: self.capacity,
+            refill_rate: self.refill_rate,
+        };
+
+        BucketSnapshot {
+            service_key: service_key.to_string(),
+            tokens,
+            capacity: self.capacity,
+            refill_rate: self.refill_rate,
+            time_to_full_ms: virtual_bucket
+                .time_until_available(self.capacity)
+                .as_millis() as u64,
+        }
+    }
+
+    /// Apply new limits; growing capacity grants the extra tokens immediately,
+    /// shrinking it clamps the current balance
+    fn resize(&mut self, capacity: f64, refill_rate: f64) {
+        self.refill();
+        let growth = (capacity - self.capacity).max(0.0);
+        self.tokens = (self.tokens + growth).min(capacity);
+        self.capacity = capacity;
+        self.refill_rate = refill_rate;
+    }
+
     fn refill(&mut self) {
-        let now = Instant::now();
+        self.refill_at(Instant::now());
+    }
+
+    fn refill_at(&mut self, now: Instant) {
         let elapsed = now.duration_since(self.last_refill).as_secs_f64();
 
         let tokens_to_add = elapsed * self.refill_rate;
@@ -92,96 +365,645 @@ impl RateLimitBucket {
     }
 }
 
-/// AWS service-specific rate limiter
-#[derive(Debug)]
+/// Bucket identity: (tenant_id, service_key)
+type BucketKey = (String, &'static str);
+
+/// Every service key a bucket can be keyed by (see `AwsOperation::service_key`)
+pub const SERVICE_KEYS: &[&str] = &[
+    "dynamodb_query",
+    "dynamodb_read",
+    "dynamodb_write",
+    "s3_get",
+    "s3_put",
+    "s3_list",
+    "eventbridge_put",
+    "secrets_get",
+    "secrets_put",
+    "sqs_send",
+    "sqs_receive",
+    "aws_api",
+];
+
+/// Account-wide limits (operations per second, keyed by service key) from
+/// `MCP_GLOBAL_LIMIT_<SERVICE_KEY>` variables, e.g. `MCP_GLOBAL_LIMIT_DYNAMODB_READ=40000`
+pub fn global_limits(lookup: impl Fn(&str) -> Option<String>) -> HashMap<String, u32> {
+    let mut limits = HashMap::new();
+    for service_key in SERVICE_KEYS {
+        let var = format!("MCP_GLOBAL_LIMIT_{}", service_key.to_uppercase());
+        let Some(value) = lookup(&var) else {
+            continue;
+        };
+        match value.trim().parse() {
+            Ok(limit) => {
+                limits.insert(service_key.to_string(), limit);
+            }
+            Err(_) => warn!("Ignoring {}: '{}' is not a whole number", var, value),
+        }
+    }
+    limits
+}
+
+/// Default cap on live buckets before least-recently-used ones are evicted
+pub const DEFAULT_MAX_BUCKETS: usize = 10_000;
+
+/// Buckets idle this long are dropped by `cleanup_expired_buckets`
+const BUCKET_IDLE_EXPIRY: Duration = Duration::from_secs(3600);
+
+/// Each AWS throttling signal multiplies a service's refill rate by this factor...
+const THROTTLE_SCALE_DOWN: f64 = 0.5;
+/// ...but never below this fraction of the configured rate
+const MIN_THROTTLE_FACTOR: f64 = 0.125;
+/// How long the reduced rate is held after the latest throttling signal
+const THROTTLE_HOLD: Duration = Duration::from_secs(30);
+/// After the hold, the rate climbs linearly back to normal over this period
+const THROTTLE_RECOVERY: Duration = Duration::from_secs(30);
+
+/// Retry-after reported when the shared store is down under `FailClosed`
+const FAIL_CLOSED_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Refill-rate reduction for a service that AWS has recently throttled
+#[derive(Debug, Clone, Copy)]
+struct AdaptiveThrottle {
+    floor: f64,
+    engaged_at: Instant,
+}
+
+impl AdaptiveThrottle {
+    /// Fraction of the configured refill rate in effect at `now`
+    fn factor_at(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.engaged_at);
+        let Some(recovering) = elapsed.checked_sub(THROTTLE_HOLD) else {
+            return self.floor;
+        };
+        let progress = recovering.as_secs_f64() / THROTTLE_RECOVERY.as_secs_f64();
+        (self.floor + (1.0 - self.floor) * progress).min(1.0)
+    }
+}
+
+/// AWS service-specific rate limiter.
+///
+/// Each tenant's buckets are sized from that tenant's `AwsServiceLimits` (set via
+/// `set_tenant_limits`), falling back to the limiter-wide defaults. In distributed mode
+/// the buckets are shared across replicas.
+///
+/// At most `max_buckets` local buckets are kept; creating one beyond that evicts the
+/// least recently used, so a flood of unknown tenant ids can't grow the map unbounded.
+///
+/// When AWS itself throttles a service (reported via `report_throttle`), that service's
+/// refill rate is scaled down for every tenant, held for 30 seconds and then recovered
+/// gradually, so account-level contention isn't made worse by retries.
+///
+/// What happens while the shared store is failing is set by the
+/// `RateLimitFailurePolicy`: fail open, fail closed, or (by default) degrade to local
+/// buckets seeded from the last state seen in the store. Every decision made while
+/// degraded is counted.
+///
+/// Services with a global limit also have an account-wide bucket shared by all tenants.
+/// An operation must fit in both its tenant bucket and the global bucket, and is charged
+/// to neither if either rejects it. Global buckets are kept per instance.
+///
+/// Critical operations that would be rejected fall back to a small per-tenant reserve
+/// pool (`critical_reserve_percent` of capacity) that normal operations never touch.
 pub struct AwsRateLimiter {
-    limits: AwsServiceLimits,
-    buckets: Arc<RwLock<HashMap<String, RateLimitBucket>>>,
+    default_limits: AwsServiceLimits,
+    tenant_limits: Arc<RwLock<HashMap<String, AwsServiceLimits>>>,
+    buckets: Arc<RwLock<HashMap<BucketKey, RateLimitBucket>>>,
+    reserve_buckets: Arc<RwLock<HashMap<BucketKey, RateLimitBucket>>>,
+    global_limits: HashMap<String, u32>,
+    global_buckets: Arc<RwLock<HashMap<&'static str, RateLimitBucket>>>,
+    distributed: Option<DistributedLimiter>,
+    failure_policy: RateLimitFailurePolicy,
+    degraded: AtomicBool,
+    degraded_decisions: AtomicU64,
+    max_buckets: usize,
+    throttles: Arc<RwLock<HashMap<&'static str, AdaptiveThrottle>>>,
+}
+
+impl std::fmt::Debug for AwsRateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsRateLimiter")
+            .field("default_limits", &self.default_limits)
+            .field("global_limits", &self.global_limits)
+            .field("distributed", &self.distributed.is_some())
+            .field("failure_policy", &self.failure_policy)
+            .field("max_buckets", &self.max_buckets)
+            .finish()
+    }
 }
 
 impl AwsRateLimiter {
-    pub fn new(limits: AwsServiceLimits) -> Self {
+    pub fn new(default_limits: AwsServiceLimits) -> Self {
         Self {
-            limits,
+            default_limits,
+            tenant_limits: Arc::new(RwLock::new(HashMap::new())),
             buckets: Arc::new(RwLock::new(HashMap::new())),
+            reserve_buckets: Arc::new(RwLock::new(HashMap::new())),
+            global_limits: HashMap::new(),
+            global_buckets: Arc::new(RwLock::new(HashMap::new())),
+            distributed: None,
+            failure_policy: RateLimitFailurePolicy::default(),
+            degraded: AtomicBool::new(false),
+            degraded_decisions: AtomicU64::new(0),
+            max_buckets: DEFAULT_MAX_BUCKETS,
+            throttles: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    /// Check if an AWS service operation is allowed
-    pub async fn check_aws_operation(&self, tenant_id: &str, operation: &AwsOperation) -> bool {
-        let bucket_key = format!("{}:{}", tenant_id, operation.service_key());
-        let (capacity, rate, cost) = self.get_limits_for_operation(operation);
+    /// Add account-wide limits (operations per second, keyed by service key) shared by
+    /// all tenants
+    pub fn with_global_limits(mut self, global_limits: HashMap<String, u32>) -> Self {
+        self.global_limits = global_limits;
+        self
+    }
+
+    /// Cap the number of local buckets (minimum 1)
+    pub fn with_max_buckets(mut self, max_buckets: usize) -> Self {
+        self.max_buckets = max_buckets.max(1);
+        self
+    }
+
+    /// Share bucket state across replicas through the given limiter
+    pub fn with_distributed(mut self, distributed: DistributedLimiter) -> Self {
+        self.distributed = Some(distributed);
+        self
+    }
+
+    /// How to decide admissions while the shared store is failing
+    pub fn with_failure_policy(mut self, policy: RateLimitFailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Set a tenant's limits, resizing any buckets it already has
+    pub async fn set_tenant_limits(&self, tenant_id: &str, limits: AwsServiceLimits) {
+        {
+            let mut buckets = self.buckets.write().await;
+            for ((bucket_tenant, service_key), bucket) in buckets.iter_mut() {
+                if bucket_tenant == tenant_id {
+                    let (capacity, rate) = bucket_params(&limits, service_key);
+                    bucket.resize(capacity, rate);
+                }
+            }
+        }
+
+        self.tenant_limits
+            .write()
+            .await
+            .insert(tenant_id.to_string(), limits);
+    }
+
+    /// Limits for tenants without their own
+    pub fn default_limits(&self) -> &AwsServiceLimits {
+        &self.default_limits
+    }
+
+    /// Limits in force for a tenant
+    pub async fn limits_for(&self, tenant_id: &str) -> AwsServiceLimits {
+        self.tenant_limits
+            .read()
+            .await
+            .get(tenant_id)
+            .cloned()
+            .unwrap_or_else(|| self.default_limits.clone())
+    }
+
+    /// Check if an AWS service operation is allowed, charging its cost if so. Returns
+    /// the balance of each bucket charged (tenant first, then global), captured under
+    /// the same lock as the charge. The tenant balance is unknown in distributed mode.
+    pub async fn check_aws_operation(
+        &self,
+        tenant_id: &str,
+        operation: &AwsOperation,
+    ) -> Result<Vec<RemainingQuota>, RateLimitRejection> {
+        let limits = self.limits_for(tenant_id).await;
+        let service_key = operation.service_key();
+        let (capacity, rate) = bucket_params(&limits, service_key);
+        let throttle_factor = self.throttle_factor(service_key).await;
+        let rate = rate * throttle_factor;
+        let cost = operation.cost(&limits);
+
+        let mut seed = None;
+        if let Some(distributed) = &self.distributed {
+            // The shared store can't lock the global bucket for us: charge it first and
+            // refund it unless the tenant's shared bucket admits the operation too
+            let global = self
+                .check_global(service_key, cost, throttle_factor, true)
+                .await?;
+            let shared_key = format!("{}#{}", tenant_id, service_key);
+            seed = match distributed
+                .try_consume(&shared_key, capacity, rate, cost)
+                .await
+            {
+                Ok(result) => {
+                    if self.degraded.swap(false, Ordering::SeqCst) {
+                        info!("Distributed rate limiting recovered");
+                    }
+                    if let Err(retry_after) = result {
+                        self.refund_global(service_key, cost).await;
+                        return Err(RateLimitRejection::tenant(service_key, retry_after));
+                    }
+                    return Ok(global.into_iter().collect());
+                }
+                Err(e) => {
+                    if !self.degraded.swap(true, Ordering::SeqCst) {
+                        warn!(
+                            "Distributed rate limiting unavailable, applying {:?} policy: {}",
+                            self.failure_policy, e
+                        );
+                    }
+                    self.degraded_decisions.fetch_add(1, Ordering::Relaxed);
+                    match self.failure_policy {
+                        RateLimitFailurePolicy::FailOpen => {
+                            return Ok(global.into_iter().collect());
+                        }
+                        RateLimitFailurePolicy::FailClosed => {
+                            self.refund_global(service_key, cost).await;
+                            return Err(RateLimitRejection::tenant(
+                                service_key,
+                                FAIL_CLOSED_RETRY_AFTER,
+                            ));
+                        }
+                        RateLimitFailurePolicy::DegradeToLocal => {
+                            // Charged again below, along with the local bucket
+                            self.refund_global(service_key, cost).await;
+                            distributed
+                                .take_last_known(&shared_key, capacity, rate)
+                                .await
+                        }
+                    }
+                }
+            };
+        }
 
         let mut buckets = self.buckets.write().await;
+        let key = (tenant_id.to_string(), service_key);
+        if !buckets.contains_key(&key) && buckets.len() >= self.max_buckets {
+            Self::evict_least_recently_used(&mut buckets);
+        }
         let bucket = buckets
-            .entry(bucket_key)
+            .entry(key)
+            .or_insert_with(|| RateLimitBucket::new(capacity, rate));
+        if bucket.refill_rate != rate {
+            bucket.resize(capacity, rate);
+        }
+        if let Some(tokens) = seed {
+            // Pick up where the shared bucket left off instead of starting full
+            bucket.refill_at(Instant::now());
+            bucket.tokens = tokens;
+        }
+
+        // Both layers must admit the operation before either is charged
+        bucket
+            .check_at(cost, Instant::now())
+            .map_err(|retry_after| RateLimitRejection::tenant(service_key, retry_after))?;
+        let global = self
+            .check_global(service_key, cost, throttle_factor, true)
+            .await?;
+        bucket.tokens -= cost;
+
+        let mut charged = vec![bucket.remaining(service_key, RateLimitScope::Tenant)];
+        charged.extend(global);
+        Ok(charged)
+    }
+
+    /// Check (and, with `consume`, charge) the account-wide bucket for a service, if
+    /// it has a global limit. Returns the global balance after a charge.
+    async fn check_global(
+        &self,
+        service_key: &'static str,
+        cost: f64,
+        throttle_factor: f64,
+        consume: bool,
+    ) -> Result<Option<RemainingQuota>, RateLimitRejection> {
+        let Some(&limit) = self.global_limits.get(service_key) else {
+            return Ok(None);
+        };
+        let capacity = limit as f64;
+        let rate = capacity * throttle_factor;
+
+        let mut global_buckets = self.global_buckets.write().await;
+        let bucket = global_buckets
+            .entry(service_key)
             .or_insert_with(|| RateLimitBucket::new(capacity, rate));
+        if bucket.refill_rate != rate {
+            bucket.resize(capacity, rate);
+        }
+
+        let now = Instant::now();
+        let result = if consume {
+            bucket.try_consume_at(cost, now)
+        } else {
+            bucket.check_at(cost, now)
+        };
+        result.map_err(|retry_after| RateLimitRejection::global(service_key, retry_after))?;
+        Ok(consume.then(|| bucket.remaining(service_key, RateLimitScope::Global)))
+    }
+
+    /// Undo a charge to the account-wide bucket for a service, if it has one
+    async fn refund_global(&self, service_key: &'static str, cost: f64) {
+        if let Some(bucket) = self.global_buckets.write().await.get_mut(service_key) {
+            bucket.refund(cost);
+        }
+    }
+
+    /// Reject operations that cost more than their bucket can ever hold, instead of
+    /// leaving them to be rate limited forever
+    pub async fn check_operation_fits(
+        &self,
+        tenant_id: &str,
+        operation: &AwsOperation,
+    ) -> Result<(), OversizedOperation> {
+        let limits = self.limits_for(tenant_id).await;
+        let service_key = operation.service_key();
+        let (capacity, _) = bucket_params(&limits, service_key);
+        let cost = operation.cost(&limits);
+
+        if cost > capacity {
+            return Err(OversizedOperation {
+                service_key: service_key.to_string(),
+                cost,
+                capacity,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether `operation` costs no more than its tenant bucket holds, nor the global one
+    /// if the service has a global limit
+    async fn can_ever_fit(&self, tenant_id: &str, operation: &AwsOperation) -> bool {
+        if self
+            .check_operation_fits(tenant_id, operation)
+            .await
+            .is_err()
+        {
+            return false;
+        }
+        let limits = self.limits_for(tenant_id).await;
+        let cost = operation.cost(&limits);
+        self.global_limits
+            .get(operation.service_key())
+            .is_none_or(|&limit| cost <= limit as f64)
+    }
+
+    /// Charge cost discovered after an operation ran (see
+    /// `AwsOperation::response_adjustment`). The work is already done, so this never
+    /// rejects; the bucket may go into debt (down to minus its capacity), delaying the
+    /// tenant's next operations instead. Applies to local buckets only.
+    pub async fn charge_after(&self, tenant_id: &str, operation: &AwsOperation) {
+        let limits = self.limits_for(tenant_id).await;
+        let service_key = operation.service_key();
+        let (capacity, rate) = bucket_params(&limits, service_key);
+        let cost = operation.cost(&limits);
 
-        bucket.try_consume(cost)
-    }
-
-    /// Get rate limits and cost for a specific AWS operation
-    fn get_limits_for_operation(&self, operation: &AwsOperation) -> (f64, f64, f64) {
-        match operation {
-            AwsOperation::DynamoDbQuery => (
-                self.limits.dynamodb_queries_per_sec as f64,
-                self.limits.dynamodb_queries_per_sec as f64,
-                1.0,
-            ),
-            AwsOperation::DynamoDbRead { read_units } => (
-                self.limits.dynamodb_read_units as f64,
-                self.limits.dynamodb_read_units as f64,
-                *read_units as f64,
-            ),
-            AwsOperation::DynamoDbWrite { write_units } => (
-                self.limits.dynamodb_write_units as f64,
-                self.limits.dynamodb_write_units as f64,
-                *write_units as f64,
-            ),
-            AwsOperation::S3Get => (
-                self.limits.s3_get_requests_per_sec as f64,
-                self.limits.s3_get_requests_per_sec as f64,
-                1.0,
-            ),
-            AwsOperation::S3Put => (
-                self.limits.s3_put_requests_per_sec as f64,
-                self.limits.s3_put_requests_per_sec as f64,
-                1.0,
-            ),
-            AwsOperation::S3List => (
-                self.limits.s3_list_requests_per_sec as f64,
-                self.limits.s3_list_requests_per_sec as f64,
-                1.0,
-            ),
-            AwsOperation::EventBridgePutEvents { event_count } => (
-                self.limits.eventbridge_put_events_per_sec as f64,
-                self.limits.eventbridge_put_events_per_sec as f64,
-                (*event_count as f64).min(self.limits.eventbridge_events_batch_size as f64),
-            ),
-            AwsOperation::SecretsManagerGet => (
-                self.limits.secrets_manager_requests_per_sec as f64,
-                self.limits.secrets_manager_requests_per_sec as f64,
-                1.0,
-            ),
-            AwsOperation::GenericAwsApi => (
-                self.limits.aws_api_calls_per_sec as f64,
-                self.limits.aws_api_calls_per_sec as f64,
-                1.0,
-            ),
-        }
-    }
-
-    /// Clean up old buckets to prevent memory leaks
-    #[allow(dead_code)]
-    pub async fn cleanup_expired_buckets(&self) {
         let mut buckets = self.buckets.write().await;
+        buckets
+            .entry((tenant_id.to_string(), service_key))
+            .or_insert_with(|| RateLimitBucket::new(capacity, rate))
+            .charge(cost);
+    }
+
+    /// Like `check_aws_operation`, but a critical operation that would be rejected is
+    /// charged to the tenant's critical reserve instead, if the reserve can cover it
+    pub async fn check_aws_operation_with_priority(
+        &self,
+        tenant_id: &str,
+        operation: &AwsOperation,
+        priority: OperationPriority,
+    ) -> Result<Vec<RemainingQuota>, RateLimitRejection> {
+        match self.check_aws_operation(tenant_id, operation).await {
+            Err(rejection) if priority == OperationPriority::Critical => self
+                .consume_reserve(tenant_id, operation)
+                .await
+                .map_err(|_| rejection),
+            result => result,
+        }
+    }
+
+    /// Charge the tenant's critical reserve for a service. The reserve bypasses the
+    /// global layer: it is small, and exists for traffic that must not be dropped.
+    async fn consume_reserve(
+        &self,
+        tenant_id: &str,
+        operation: &AwsOperation,
+    ) -> Result<Vec<RemainingQuota>, Duration> {
+        let limits = self.limits_for(tenant_id).await;
+        let service_key = operation.service_key();
+        let (capacity, rate) = bucket_params(&limits, service_key);
+        let share = limits.critical_reserve_percent as f64 / 100.0;
+        let throttle_factor = self.throttle_factor(service_key).await;
+        let (capacity, rate) = (capacity * share, rate * share * throttle_factor);
+        let cost = operation.cost(&limits);
+
+        let mut reserves = self.reserve_buckets.write().await;
+        let reserve = reserves
+            .entry((tenant_id.to_string(), service_key))
+            .or_insert_with(|| RateLimitBucket::new(capacity, rate));
+        if reserve.capacity != capacity || reserve.refill_rate != rate {
+            reserve.resize(capacity, rate);
+        }
+        reserve.try_consume_at(cost, Instant::now())?;
+        Ok(vec![reserve.remaining(service_key, RateLimitScope::Tenant)])
+    }
+
+    /// Like `check_aws_operation_with_priority`, but when `max_wait` is set, sleep until
+    /// the bucket can cover the cost instead of rejecting, as long as the total wait
+    /// stays within `max_wait`. No lock is held while sleeping. Operations that can never
+    /// be admitted (costing more than a bucket holds, or against a bucket that doesn't
+    /// refill) are rejected without waiting.
+    pub async fn check_aws_operation_waiting(
+        &self,
+        tenant_id: &str,
+        operation: &AwsOperation,
+        priority: OperationPriority,
+        max_wait: Option<Duration>,
+    ) -> Result<Vec<RemainingQuota>, RateLimitRejection> {
+        let Some(max_wait) = max_wait else {
+            return self
+                .check_aws_operation_with_priority(tenant_id, operation, priority)
+                .await;
+        };
+
+        let deadline = Instant::now() + max_wait;
+        loop {
+            match self
+                .check_aws_operation_with_priority(tenant_id, operation, priority)
+                .await
+            {
+                Ok(charged) => return Ok(charged),
+                Err(rejection) => {
+                    if !self.can_ever_fit(tenant_id, operation).await {
+                        return Err(rejection);
+                    }
+                    match Instant::now().checked_add(rejection.retry_after) {
+                        Some(retry_at) if retry_at <= deadline => {
+                            tokio::time::sleep(rejection.retry_after).await
+                        }
+                        _ => return Err(rejection),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Snapshot a tenant's active buckets (sorted by service key) without consuming
+    /// or refilling them
+    pub async fn snapshot(&self, tenant_id: &str) -> Vec<BucketSnapshot> {
         let now = Instant::now();
-        let expiry_threshold = Duration::from_secs(3600); // 1 hour
+        let buckets = self.buckets.read().await;
+        let mut snapshots: Vec<BucketSnapshot> = buckets
+            .iter()
+            .filter(|((bucket_tenant, _), _)| bucket_tenant == tenant_id)
+            .map(|((_, service_key), bucket)| bucket.snapshot_at(service_key, now))
+            .collect();
+        snapshots.sort_by(|a, b| a.service_key.cmp(&b.service_key));
+        snapshots
+    }
 
-        buckets.retain(|_, bucket| now.duration_since(bucket.last_refill) < expiry_threshold);
+    /// Snapshot every tenant's active buckets, by tenant
+    pub async fn snapshot_all(&self) -> BTreeMap<String, Vec<BucketSnapshot>> {
+        let now = Instant::now();
+        let buckets = self.buckets.read().await;
+        let mut snapshots: BTreeMap<String, Vec<BucketSnapshot>> = BTreeMap::new();
+        for ((tenant_id, service_key), bucket) in buckets.iter() {
+            snapshots
+                .entry(tenant_id.clone())
+                .or_default()
+                .push(bucket.snapshot_at(service_key, now));
+        }
+        for tenant in snapshots.values_mut() {
+            tenant.sort_by(|a, b| a.service_key.cmp(&b.service_key));
+        }
+        snapshots
+    }
+
+    /// Clean up buckets idle for over an hour to prevent memory leaks. Returns how many
+    /// were removed.
+    pub async fn cleanup_expired_buckets(&self) -> usize {
+        let mut buckets = self.buckets.write().await;
+        let now = Instant::now();
+        let before = buckets.len();
+
+        buckets.retain(|_, bucket| now.duration_since(bucket.last_refill) < BUCKET_IDLE_EXPIRY);
+        let removed = before - buckets.len();
+        drop(buckets);
+
+        self.reserve_buckets
+            .write()
+            .await
+            .retain(|_, bucket| now.duration_since(bucket.last_refill) < BUCKET_IDLE_EXPIRY);
+
+        self.throttles
+            .write()
+            .await
+            .retain(|_, throttle| throttle.factor_at(now) < 1.0);
+        removed
+    }
+
+    /// Record that AWS throttled `service_key`, scaling down its refill rate. Returns
+    /// true when this engages adaptive throttling for a service that wasn't throttled.
+    pub async fn report_throttle(&self, service_key: &'static str) -> bool {
+        let now = Instant::now();
+        let mut throttles = self.throttles.write().await;
+        let current = throttles
+            .get(service_key)
+            .map(|throttle| throttle.factor_at(now))
+            .unwrap_or(1.0);
+        let floor = (current * THROTTLE_SCALE_DOWN).max(MIN_THROTTLE_FACTOR);
+        throttles.insert(
+            service_key,
+            AdaptiveThrottle {
+                floor,
+                engaged_at: now,
+            },
+        );
+        drop(throttles);
+
+        // Rescale existing buckets now so time already elapsed refills at the old rate
+        let tenant_limits = self.tenant_limits.read().await;
+        let mut buckets = self.buckets.write().await;
+        for ((tenant_id, bucket_key), bucket) in buckets.iter_mut() {
+            if *bucket_key == service_key {
+                let limits = tenant_limits.get(tenant_id).unwrap_or(&self.default_limits);
+                let (capacity, rate) = bucket_params(limits, bucket_key);
+                bucket.resize(capacity, rate * floor);
+            }
+        }
+
+        warn!(
+            "AWS throttled {}; scaling its refill rate to {:.0}% of the configured limit",
+            service_key,
+            floor * 100.0
+        );
+        current >= 1.0
+    }
+
+    /// Fraction of `service_key`'s configured refill rate currently in effect
+    pub async fn throttle_factor(&self, service_key: &str) -> f64 {
+        let now = Instant::now();
+        self.throttles
+            .read()
+            .await
+            .get(service_key)
+            .map(|throttle| throttle.factor_at(now))
+            .unwrap_or(1.0)
+    }
+
+    /// Policy applied while the shared store is failing
+    pub fn failure_policy(&self) -> RateLimitFailurePolicy {
+        self.failure_policy
+    }
+
+    /// Admission decisions made while the shared store was failing
+    pub fn degraded_decisions(&self) -> u64 {
+        self.degraded_decisions.load(Ordering::Relaxed)
+    }
+
+    /// Number of local buckets currently held
+    pub async fn bucket_count(&self) -> usize {
+        self.buckets.read().await.len()
+    }
+
+    /// Configured cap on local buckets
+    pub fn max_buckets(&self) -> usize {
+        self.max_buckets
+    }
+
+    /// Drop the bucket that was charged least recently
+    fn evict_least_recently_used(buckets: &mut HashMap<BucketKey, RateLimitBucket>) {
+        let oldest = buckets
+            .iter()
+            .min_by_key(|(_, bucket)| bucket.last_refill)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            buckets.remove(&key);
+        }
     }
 }
 
+/// Bucket capacity (steady rate plus burst) and refill rate (tokens/sec) for a service
+fn bucket_params(limits: &AwsServiceLimits, service_key: &str) -> (f64, f64) {
+    let rate = match service_key {
+        "dynamodb_query" => limits.dynamodb_queries_per_sec,
+        "dynamodb_read" => limits.dynamodb_read_units,
+        "dynamodb_write" => limits.dynamodb_write_units,
+        "s3_get" => limits.s3_get_requests_per_sec,
+        "s3_put" => limits.s3_put_requests_per_sec,
+        "s3_list" => limits.s3_list_requests_per_sec,
+        "eventbridge_put" => limits.eventbridge_put_events_per_sec,
+        "secrets_get" | "secrets_put" => limits.secrets_manager_requests_per_sec,
+        "sqs_send" | "sqs_receive" => limits.sqs_requests_per_sec,
+        _ => limits.aws_api_calls_per_sec,
+    } as f64;
+
+    let burst = limits
+        .service_burst
+        .get(service_key)
+        .copied()
+        .unwrap_or(limits.aws_burst_capacity) as f64;
+
+    (rate + burst, rate)
+}
+
 /// AWS operations that require rate limiting
 #[derive(Debug, Clone)]
 pub enum AwsOperation {
@@ -193,40 +1015,89 @@ pub enum AwsOperation {
         write_units: u32,
     },
     S3Get,
-    S3Put,
+    S3Put {
+        bytes: u64,
+    },
     #[allow(dead_code)]
     S3List,
     EventBridgePutEvents {
         event_count: u32,
     },
-    #[allow(dead_code)]
     SecretsManagerGet,
+    /// Creating, updating or deleting a secret
+    SecretsManagerPut,
+    SqsSend,
+    /// Receiving messages, and acknowledging or returning received ones
+    SqsReceive,
     GenericAwsApi,
 }
 
 impl AwsOperation {
-    fn service_key(&self) -> &'static str {
+    pub fn service_key(&self) -> &'static str {
         match self {
             AwsOperation::DynamoDbQuery => "dynamodb_query",
             AwsOperation::DynamoDbRead { .. } => "dynamodb_read",
             AwsOperation::DynamoDbWrite { .. } => "dynamodb_write",
             AwsOperation::S3Get => "s3_get",
-            AwsOperation::S3Put => "s3_put",
+            AwsOperation::S3Put { .. } => "s3_put",
             AwsOperation::S3List => "s3_list",
             AwsOperation::EventBridgePutEvents { .. } => "eventbridge_put",
             AwsOperation::SecretsManagerGet => "secrets_get",
+            AwsOperation::SecretsManagerPut => "secrets_put",
+            AwsOperation::SqsSend => "sqs_send",
+            AwsOperation::SqsReceive => "sqs_receive",
             AwsOperation::GenericAwsApi => "aws_api",
         }
     }
 
-    /// Create operation from MCP tool name
+    /// Tokens this operation consumes from its bucket
+    fn cost(&self, limits: &AwsServiceLimits) -> f64 {
+        match self {
+            AwsOperation::DynamoDbRead { read_units } => *read_units as f64,
+            AwsOperation::DynamoDbWrite { write_units } => *write_units as f64,
+            AwsOperation::EventBridgePutEvents { event_count } => {
+                (*event_count as f64).min(limits.eventbridge_events_batch_size as f64)
+            }
+            AwsOperation::S3Put { bytes } => {
+                let per_token = limits.s3_put_bytes_per_token.max(1);
+                bytes.div_ceil(per_token).max(1) as f64
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Create operation from MCP tool name and its arguments. Writes are charged by
+    /// payload size: DynamoDB in 1KB write units, S3 puts per `s3_put_bytes_per_token`.
     pub fn from_tool_name(tool_name: &str, args: &serde_json::Value) -> Option<Self> {
         match tool_name {
             "kv_get" | "kv_list" => Some(AwsOperation::DynamoDbRead { read_units: 1 }),
-            "kv_set" | "kv_delete" => Some(AwsOperation::DynamoDbWrite { write_units: 1 }),
+            "kv_set" => {
+                let key_len = args.get("key").and_then(|v| v.as_str()).map_or(0, str::len);
+                let value_len = match args.get("value") {
+                    Some(serde_json::Value::String(value)) => value.len(),
+                    Some(value) => value.to_string().len(),
+                    None => 0,
+                };
+                Some(AwsOperation::DynamoDbWrite {
+                    write_units: units(key_len + value_len, DYNAMODB_WRITE_UNIT_BYTES),
+                })
+            }
+            "kv_delete" => Some(AwsOperation::DynamoDbWrite { write_units: 1 }),
+            // Task queue changes read the queue's item, then conditionally rewrite it
+            "task_create" | "task_claim" | "task_complete" | "task_fail" => {
+                Some(AwsOperation::DynamoDbWrite { write_units: 1 })
+            }
+            "task_list" => Some(AwsOperation::DynamoDbRead { read_units: 1 }),
             "artifacts_get" | "artifacts_list" => Some(AwsOperation::S3Get),
-            "artifacts_put" => Some(AwsOperation::S3Put),
-            "events_send" => {
+            "artifacts_put" => {
+                // Content arrives base64-encoded; every 4 characters carry 3 bytes
+                let encoded = args.get("content").and_then(|v| v.as_str()).unwrap_or("");
+                let padding = encoded.bytes().rev().take_while(|b| *b == b'=').count();
+                Some(AwsOperation::S3Put {
+                    bytes: ((encoded.len() / 4 * 3).saturating_sub(padding)) as u64,
+                })
+            }
+            "events_send" | "events_send_linked" => {
                 let event_count = args
                     .get("events")
                     .and_then(|v| v.as_array())
@@ -234,10 +1105,39 @@ impl AwsOperation {
                     .unwrap_or(1);
                 Some(AwsOperation::EventBridgePutEvents { event_count })
             }
-            "analytics_query" => Some(AwsOperation::DynamoDbQuery),
+            "analytics_query" | "tool_usage_report" => Some(AwsOperation::DynamoDbQuery),
+            "secret_get" => Some(AwsOperation::SecretsManagerGet),
+            "secret_put" | "secret_delete" => Some(AwsOperation::SecretsManagerPut),
+            "queue_send" => Some(AwsOperation::SqsSend),
+            "queue_receive" | "queue_ack" | "queue_nack" => Some(AwsOperation::SqsReceive),
+            // Introspection must stay available while the tenant is throttled
+            "rate_limit_status" | "server_stats" | "diagnostics_bundle" | "health" => None,
             _ => Some(AwsOperation::GenericAwsApi),
         }
     }
+
+    /// Extra cost only known once a tool has run, beyond what `from_tool_name` charged
+    /// up front: reads are billed in 4KB units of the returned value
+    pub fn response_adjustment(tool_name: &str, result: &serde_json::Value) -> Option<Self> {
+        match tool_name {
+            "kv_get" => {
+                let len = result.get("value").and_then(|v| v.as_str())?.len();
+                let extra = units(len, DYNAMODB_READ_UNIT_BYTES).saturating_sub(1);
+                (extra > 0).then_some(AwsOperation::DynamoDbRead { read_units: extra })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Item bytes covered by one DynamoDB write unit
+const DYNAMODB_WRITE_UNIT_BYTES: usize = 1024;
+/// Item bytes covered by one (strongly consistent) DynamoDB read unit
+const DYNAMODB_READ_UNIT_BYTES: usize = 4096;
+
+/// Capacity units needed for `bytes`, at least one
+fn units(bytes: usize, unit_bytes: usize) -> u32 {
+    bytes.div_ceil(unit_bytes).max(1).min(u32::MAX as usize) as u32
 }
 
 #[cfg(test)]
@@ -248,55 +1148,1010 @@ mod tests {
     async fn test_rate_limiter_basic() {
         let limits = AwsServiceLimits {
             dynamodb_read_units: 10,
+            aws_burst_capacity: 0,
             ..Default::default()
         };
         let limiter = AwsRateLimiter::new(limits);
 
         // Should allow initial requests
-        assert!(
+        assert!(limiter
+            .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 5 })
+            .await
+            .is_ok());
+        assert!(limiter
+            .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 5 })
+            .await
+            .is_ok());
+
+        // Should reject when limit exceeded, naming the bucket and when to retry
+        let rejection = limiter
+            .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 1 })
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.service_key, "dynamodb_read");
+        assert!(rejection.retry_after > Duration::ZERO);
+        assert!(rejection.retry_after <= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_isolation() {
+        let limits = AwsServiceLimits {
+            dynamodb_read_units: 5,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+
+        // Tenant 1 uses up their quota
+        assert!(limiter
+            .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 5 })
+            .await
+            .is_ok());
+        assert!(limiter
+            .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 1 })
+            .await
+            .is_err());
+
+        // Tenant 2 should still have their quota
+        assert!(limiter
+            .check_aws_operation("tenant2", &AwsOperation::DynamoDbRead { read_units: 5 })
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_per_tenant_limits_exhaust_at_own_thresholds() {
+        let limiter = AwsRateLimiter::new(AwsServiceLimits::default());
+        limiter
+            .set_tenant_limits(
+                "trial",
+                AwsServiceLimits {
+                    s3_put_requests_per_sec: 3,
+                    aws_burst_capacity: 0,
+                    ..Default::default()
+                },
+            )
+            .await;
+        limiter
+            .set_tenant_limits(
+                "enterprise",
+                AwsServiceLimits {
+                    s3_put_requests_per_sec: 10,
+                    aws_burst_capacity: 0,
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        let admitted = |tenant: &'static str| {
+            let limiter = &limiter;
+            async move {
+                let mut count = 0;
+                while limiter
+                    .check_aws_operation(tenant, &AwsOperation::S3Put { bytes: 1024 })
+                    .await
+                    .is_ok()
+                {
+                    count += 1;
+                    if count > 100 {
+                        break;
+                    }
+                }
+                count
+            }
+        };
+
+        assert_eq!(admitted("trial").await, 3);
+        assert_eq!(admitted("enterprise").await, 10);
+    }
+
+    #[tokio::test]
+    async fn test_resizing_tenant_limits_applies_to_existing_buckets() {
+        let limiter = AwsRateLimiter::new(AwsServiceLimits::default());
+        let trial = AwsServiceLimits {
+            dynamodb_write_units: 2,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        limiter.set_tenant_limits("tenant1", trial.clone()).await;
+
+        let write = AwsOperation::DynamoDbWrite { write_units: 2 };
+        assert!(limiter.check_aws_operation("tenant1", &write).await.is_ok());
+        assert!(limiter
+            .check_aws_operation("tenant1", &write)
+            .await
+            .is_err());
+
+        // Upgrading grants the added capacity straight away
+        limiter
+            .set_tenant_limits(
+                "tenant1",
+                AwsServiceLimits {
+                    dynamodb_write_units: 10,
+                    aws_burst_capacity: 0,
+                    ..Default::default()
+                },
+            )
+            .await;
+        for _ in 0..4 {
+            assert!(limiter.check_aws_operation("tenant1", &write).await.is_ok());
+        }
+        assert!(limiter
+            .check_aws_operation("tenant1", &write)
+            .await
+            .is_err());
+
+        // Downgrading clamps the balance to the new capacity
+        limiter.set_tenant_limits("tenant1", trial).await;
+        let buckets = limiter.buckets.read().await;
+        let bucket = &buckets[&("tenant1".to_string(), "dynamodb_write")];
+        assert_eq!(bucket.capacity, 2.0);
+        assert!(bucket.tokens <= 2.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_then_steady_rate() {
+        let limits = AwsServiceLimits {
+            s3_get_requests_per_sec: 5,
+            aws_burst_capacity: 100,
+            service_burst: HashMap::from([("s3_get".to_string(), 15)]),
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+
+        // A full bucket absorbs rate + burst requests at once
+        for _ in 0..20 {
+            assert!(limiter
+                .check_aws_operation("tenant1", &AwsOperation::S3Get)
+                .await
+                .is_ok());
+        }
+        let rejection = limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3Get)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.retry_after, Duration::from_millis(200));
+
+        // Afterwards throughput is the steady 5/sec
+        tokio::time::advance(Duration::from_secs(1)).await;
+        for _ in 0..5 {
+            assert!(limiter
+                .check_aws_operation("tenant1", &AwsOperation::S3Get)
+                .await
+                .is_ok());
+        }
+        assert!(limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3Get)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_snapshot_reflects_consumption_without_mutating() {
+        let limits = AwsServiceLimits {
+            s3_put_requests_per_sec: 4,
+            aws_burst_capacity: 6,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+
+        assert!(limiter.snapshot("tenant1").await.is_empty());
+
+        for _ in 0..3 {
             limiter
-                .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 5 })
+                .check_aws_operation("tenant1", &AwsOperation::S3Put { bytes: 1024 })
                 .await
-        );
-        assert!(
+                .unwrap();
+        }
+        limiter
+            .check_aws_operation("tenant1", &AwsOperation::DynamoDbWrite { write_units: 2 })
+            .await
+            .unwrap();
+
+        let snapshot = limiter.snapshot("tenant1").await;
+        assert_eq!(snapshot.len(), 2);
+        let s3 = snapshot.iter().find(|b| b.service_key == "s3_put").unwrap();
+        assert_eq!(s3.capacity, 10.0);
+        assert_eq!(s3.tokens, 7.0);
+        assert_eq!(s3.refill_rate, 4.0);
+        assert_eq!(s3.time_to_full_ms, 750);
+
+        // Refill is computed virtually for display; the bucket itself is untouched
+        tokio::time::advance(Duration::from_millis(500)).await;
+        let s3 = limiter
+            .snapshot("tenant1")
+            .await
+            .into_iter()
+            .find(|b| b.service_key == "s3_put")
+            .unwrap();
+        assert_eq!(s3.tokens, 9.0);
+        assert_eq!(s3.time_to_full_ms, 250);
+
+        let buckets = limiter.buckets.read().await;
+        assert_eq!(buckets[&("tenant1".to_string(), "s3_put")].tokens, 7.0);
+
+        // Other tenants are not included
+        drop(buckets);
+        assert!(limiter.snapshot("tenant2").await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_capacity_sleeps_for_the_deficit() {
+        let limits = AwsServiceLimits {
+            dynamodb_write_units: 4,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+        let write = AwsOperation::DynamoDbWrite { write_units: 3 };
+
+        limiter
+            .check_aws_operation("tenant1", &write)
+            .await
+            .unwrap();
+
+        // 1 token left, 2 more needed at 4 tokens/sec
+        let start = Instant::now();
+        limiter
+            .check_aws_operation_waiting(
+                "tenant1",
+                &write,
+                OperationPriority::Normal,
+                Some(Duration::from_secs(2)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_capacity_honors_max_wait() {
+        let limits = AwsServiceLimits {
+            s3_list_requests_per_sec: 1,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+
+        limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3List)
+            .await
+            .unwrap();
+
+        // Refill takes a full second, beyond the 300ms cap: reject without sleeping
+        let start = Instant::now();
+        let rejection = limiter
+            .check_aws_operation_waiting(
+                "tenant1",
+                &AwsOperation::S3List,
+                OperationPriority::Normal,
+                Some(Duration::from_millis(300)),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.retry_after, Duration::from_secs(1));
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // Without opting in, the behaviour is an immediate rejection as before
+        assert!(limiter
+            .check_aws_operation_waiting(
+                "tenant1",
+                &AwsOperation::S3List,
+                OperationPriority::Normal,
+                None,
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_capacity_rejects_buckets_that_never_refill() {
+        // MCP_LIMIT_S3_LIST=0 with some burst: a bucket that empties for good
+        let limits = AwsServiceLimits {
+            s3_list_requests_per_sec: 0,
+            aws_burst_capacity: 1,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+        limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3List)
+            .await
+            .unwrap();
+
+        let start = Instant::now();
+        let rejection = limiter
+            .check_aws_operation_waiting(
+                "tenant1",
+                &AwsOperation::S3List,
+                OperationPriority::Normal,
+                Some(Duration::from_secs(5)),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.retry_after, Duration::MAX);
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_capacity_rejects_operations_larger_than_the_bucket() {
+        let limits = AwsServiceLimits {
+            dynamodb_write_units: 4,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let write = AwsOperation::DynamoDbWrite { write_units: 6 };
+
+        let start = Instant::now();
+        let limiter = AwsRateLimiter::new(limits.clone());
+        assert!(limiter
+            .check_aws_operation_waiting(
+                "tenant1",
+                &write,
+                OperationPriority::Normal,
+                Some(Duration::from_secs(5)),
+            )
+            .await
+            .is_err());
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // Likewise when it's the global bucket that's too small
+        let limiter = AwsRateLimiter::new(AwsServiceLimits {
+            dynamodb_write_units: 10,
+            ..limits
+        })
+        .with_global_limits(HashMap::from([("dynamodb_write".to_string(), 4)]));
+        assert!(limiter
+            .check_aws_operation_waiting(
+                "tenant1",
+                &write,
+                OperationPriority::Normal,
+                Some(Duration::from_secs(5)),
+            )
+            .await
+            .is_err());
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cleanup_drops_idle_buckets_and_keeps_active_ones() {
+        let limiter = AwsRateLimiter::new(AwsServiceLimits::default());
+        let read = AwsOperation::DynamoDbRead { read_units: 1 };
+
+        for i in 0..50 {
             limiter
-                .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 5 })
+                .check_aws_operation(&format!("departed-{}", i), &read)
                 .await
-        );
+                .unwrap();
+        }
+        tokio::time::advance(Duration::from_secs(3000)).await;
+        limiter.check_aws_operation("active", &read).await.unwrap();
+        tokio::time::advance(Duration::from_secs(700)).await;
 
-        // Should reject when limit exceeded
-        assert!(
-            !limiter
-                .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 1 })
+        // Departed tenants have been idle 3700s, the active one only 700s
+        assert_eq!(limiter.cleanup_expired_buckets().await, 50);
+        assert_eq!(limiter.bucket_count().await, 1);
+        assert_eq!(limiter.snapshot("active").await.len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bucket_cap_evicts_least_recently_used() {
+        let limiter = AwsRateLimiter::new(AwsServiceLimits::default()).with_max_buckets(3);
+        let read = AwsOperation::DynamoDbRead { read_units: 1 };
+
+        for tenant in ["a", "b", "c"] {
+            limiter.check_aws_operation(tenant, &read).await.unwrap();
+            tokio::time::advance(Duration::from_millis(10)).await;
+        }
+        // Touch "a" so "b" becomes the least recently used
+        limiter.check_aws_operation("a", &read).await.unwrap();
+        tokio::time::advance(Duration::from_millis(10)).await;
+
+        limiter.check_aws_operation("d", &read).await.unwrap();
+
+        assert_eq!(limiter.bucket_count().await, 3);
+        assert!(limiter.snapshot("b").await.is_empty());
+        for tenant in ["a", "c", "d"] {
+            assert_eq!(
+                limiter.snapshot(tenant).await.len(),
+                1,
+                "{} evicted",
+                tenant
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_adaptive_throttle_scales_down_and_recovers() {
+        let limiter = AwsRateLimiter::new(AwsServiceLimits::default());
+
+        assert!(limiter.report_throttle("dynamodb_write").await);
+        assert_eq!(limiter.throttle_factor("dynamodb_write").await, 0.5);
+        assert_eq!(limiter.throttle_factor("dynamodb_read").await, 1.0);
+
+        // Held for 30s, then recovers linearly over the next 30s
+        tokio::time::advance(Duration::from_secs(29)).await;
+        assert_eq!(limiter.throttle_factor("dynamodb_write").await, 0.5);
+        tokio::time::advance(Duration::from_secs(16)).await;
+        assert_eq!(limiter.throttle_factor("dynamodb_write").await, 0.75);
+
+        // A second signal while recovering compounds from the current factor
+        assert!(!limiter.report_throttle("dynamodb_write").await);
+        assert_eq!(limiter.throttle_factor("dynamodb_write").await, 0.375);
+        for _ in 0..5 {
+            limiter.report_throttle("dynamodb_write").await;
+        }
+        assert_eq!(limiter.throttle_factor("dynamodb_write").await, 0.125);
+
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(limiter.throttle_factor("dynamodb_write").await, 1.0);
+        limiter.cleanup_expired_buckets().await;
+        assert!(limiter.report_throttle("dynamodb_write").await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_adaptive_throttle_slows_bucket_refill() {
+        let limits = AwsServiceLimits {
+            dynamodb_write_units: 10,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+        let write = AwsOperation::DynamoDbWrite { write_units: 10 };
+
+        limiter
+            .check_aws_operation("tenant1", &write)
+            .await
+            .unwrap();
+        limiter.report_throttle("dynamodb_write").await;
+
+        // At half rate a full refill takes two seconds, and the rejection says so
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let rejection = limiter
+            .check_aws_operation("tenant1", &write)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.retry_after, Duration::from_secs(1));
+        assert_eq!(limiter.snapshot("tenant1").await[0].refill_rate, 5.0);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(limiter.check_aws_operation("tenant1", &write).await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_global_limit_caps_sum_of_tenants() {
+        let limits = AwsServiceLimits {
+            s3_get_requests_per_sec: 10,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits)
+            .with_global_limits(HashMap::from([("s3_get".to_string(), 15)]));
+
+        for _ in 0..10 {
+            limiter
+                .check_aws_operation("tenant1", &AwsOperation::S3Get)
+                .await
+                .unwrap();
+        }
+        // tenant1's own bucket is empty: a tenant-scoped rejection
+        let rejection = limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3Get)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.scope, RateLimitScope::Tenant);
+
+        for _ in 0..5 {
+            limiter
+                .check_aws_operation("tenant2", &AwsOperation::S3Get)
                 .await
+                .unwrap();
+        }
+        // tenant2 still has budget, but the account doesn't
+        let rejection = limiter
+            .check_aws_operation("tenant2", &AwsOperation::S3Get)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.scope, RateLimitScope::Global);
+        assert_eq!(rejection.service_key, "s3_get");
+        assert_eq!(rejection.retry_after, Duration::from_millis(67));
+
+        // Services without a global limit are unaffected
+        assert!(limiter
+            .check_aws_operation("tenant2", &AwsOperation::S3List)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_global_rejection_does_not_charge_tenant_bucket() {
+        let limits = AwsServiceLimits {
+            dynamodb_write_units: 10,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits)
+            .with_global_limits(HashMap::from([("dynamodb_write".to_string(), 4)]));
+        let write = AwsOperation::DynamoDbWrite { write_units: 3 };
+
+        limiter
+            .check_aws_operation("tenant1", &write)
+            .await
+            .unwrap();
+        let rejection = limiter
+            .check_aws_operation("tenant1", &write)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.scope, RateLimitScope::Global);
+
+        // Only the admitted operation was charged to the tenant
+        assert_eq!(limiter.snapshot("tenant1").await[0].tokens, 7.0);
+
+        // And a tenant rejection leaves the global bucket untouched
+        let big = AwsOperation::DynamoDbWrite { write_units: 8 };
+        assert_eq!(
+            limiter
+                .check_aws_operation("tenant1", &big)
+                .await
+                .unwrap_err()
+                .scope,
+            RateLimitScope::Tenant
+        );
+        let one = AwsOperation::DynamoDbWrite { write_units: 1 };
+        assert!(limiter.check_aws_operation("tenant2", &one).await.is_ok());
+        assert_eq!(
+            limiter
+                .check_aws_operation("tenant2", &one)
+                .await
+                .unwrap_err()
+                .scope,
+            RateLimitScope::Global
         );
     }
 
-    #[tokio::test]
-    async fn test_tenant_isolation() {
+    #[tokio::test(start_paused = true)]
+    async fn test_critical_operations_use_reserved_pool() {
         let limits = AwsServiceLimits {
-            dynamodb_read_units: 5,
+            eventbridge_put_events_per_sec: 40,
+            aws_burst_capacity: 0,
+            critical_reserve_percent: 5,
             ..Default::default()
         };
         let limiter = AwsRateLimiter::new(limits);
+        let send = AwsOperation::EventBridgePutEvents { event_count: 1 };
 
-        // Tenant 1 uses up their quota
-        assert!(
+        // Normal traffic drains the main bucket
+        for _ in 0..40 {
             limiter
-                .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 5 })
+                .check_aws_operation_with_priority("tenant1", &send, OperationPriority::Normal)
                 .await
+                .unwrap();
+        }
+        assert!(limiter
+            .check_aws_operation_with_priority("tenant1", &send, OperationPriority::Normal)
+            .await
+            .is_err());
+
+        // Alert delivery still gets through, up to the 2-token reserve
+        for _ in 0..2 {
+            limiter
+                .check_aws_operation_with_priority("tenant1", &send, OperationPriority::Critical)
+                .await
+                .unwrap();
+        }
+        let rejection = limiter
+            .check_aws_operation_with_priority("tenant1", &send, OperationPriority::Critical)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.service_key, "eventbridge_put");
+
+        // Normal operations remain rejected throughout
+        assert!(limiter
+            .check_aws_operation_with_priority("tenant1", &send, OperationPriority::Normal)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_priority_is_assigned_by_tool() {
+        let critical_event = serde_json::json!({"detail": {"priority": "critical"}});
+        let routine_event = serde_json::json!({"detail": {"priority": "low"}});
+        assert_eq!(
+            OperationPriority::for_tool("events_send", &critical_event, true),
+            OperationPriority::Critical
         );
-        assert!(
-            !limiter
-                .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 1 })
+        assert_eq!(
+            OperationPriority::for_tool("events_send", &routine_event, true),
+            OperationPriority::Normal
+        );
+        assert_eq!(
+            OperationPriority::for_tool("events_health_check", &serde_json::json!({}), false),
+            OperationPriority::Critical
+        );
+        assert_eq!(
+            OperationPriority::for_tool("kv_set", &critical_event, true),
+            OperationPriority::Normal
+        );
+    }
+
+    #[test]
+    fn test_clients_cannot_flag_their_own_events_critical() {
+        let critical_event = serde_json::json!({"detail": {"priority": "critical"}});
+        let critical_batch = serde_json::json!({"events": [critical_event.clone()]});
+        for (tool, args) in [
+            ("events_send", &critical_event),
+            ("events_send_linked", &critical_batch),
+        ] {
+            assert_eq!(
+                OperationPriority::for_tool(tool, args, false),
+                OperationPriority::Normal,
+                "{}",
+                tool
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_charged_quota_tracks_remaining_tokens() {
+        let limits = AwsServiceLimits {
+            dynamodb_read_units: 10,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits)
+            .with_global_limits(HashMap::from([("dynamodb_read".to_string(), 100)]));
+        let read = AwsOperation::DynamoDbRead { read_units: 2 };
+
+        let mut last_remaining = f64::MAX;
+        for _ in 0..5 {
+            let charged = limiter.check_aws_operation("tenant1", &read).await.unwrap();
+            let tenant = &charged[0];
+            assert_eq!(tenant.scope, RateLimitScope::Tenant);
+            assert_eq!(tenant.service_key, "dynamodb_read");
+            assert_eq!(tenant.capacity, 10.0);
+            assert!(tenant.remaining < last_remaining);
+            last_remaining = tenant.remaining;
+        }
+        assert_eq!(last_remaining, 0.0);
+
+        let charged = limiter.check_aws_operation("tenant2", &read).await.unwrap();
+        assert_eq!(charged.len(), 2);
+        assert_eq!(charged[1].scope, RateLimitScope::Global);
+        assert_eq!(charged[1].remaining, 88.0);
+
+        // An empty 10-token bucket refilling at 10/sec is full again after a second
+        let rejection = limiter
+            .check_aws_operation("tenant1", &read)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.retry_after, Duration::from_millis(200));
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let charged = limiter.check_aws_operation("tenant1", &read).await.unwrap();
+        assert_eq!(charged[0].remaining, 8.0);
+        assert_eq!(charged[0].reset_ms, 200);
+    }
+
+    /// A distributed limiter over a mock store that has charged half of a 4-token
+    /// s3_put bucket and then gone down
+    async fn limiter_with_failed_store(
+        policy: RateLimitFailurePolicy,
+    ) -> (AwsRateLimiter, Arc<distributed::tests::MockBucketStore>) {
+        let store = Arc::new(distributed::tests::MockBucketStore::default());
+        let limits = AwsServiceLimits {
+            s3_put_requests_per_sec: 4,
+            aws_burst_capacity: 0,
+            s3_put_bytes_per_token: 1024,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits)
+            .with_distributed(DistributedLimiter::new(store.clone()))
+            .with_failure_policy(policy);
+
+        // Healthy store: requests are charged to the shared bucket
+        assert!(limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3Put { bytes: 2048 })
+            .await
+            .is_ok());
+        assert_eq!(store.writes.load(Ordering::SeqCst), 1);
+        assert_eq!(limiter.degraded_decisions(), 0);
+
+        store.unavailable.store(true, Ordering::SeqCst);
+        (limiter, store)
+    }
+
+    #[tokio::test]
+    async fn test_distributed_rejections_charge_neither_bucket() {
+        let store = Arc::new(distributed::tests::MockBucketStore::default());
+        let limits = AwsServiceLimits {
+            s3_put_requests_per_sec: 2,
+            aws_burst_capacity: 0,
+            s3_put_bytes_per_token: 1024,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits)
+            .with_distributed(DistributedLimiter::new(store.clone()))
+            .with_global_limits(HashMap::from([("s3_put".to_string(), 4)]));
+        let put = AwsOperation::S3Put { bytes: 1024 };
+        let scope = |result: Result<Vec<RemainingQuota>, RateLimitRejection>| {
+            result.err().map(|rejection| rejection.scope)
+        };
+
+        // Tenant rejections are refunded to the global bucket...
+        for _ in 0..2 {
+            assert!(limiter.check_aws_operation("tenant1", &put).await.is_ok());
+        }
+        for _ in 0..3 {
+            assert_eq!(
+                scope(limiter.check_aws_operation("tenant1", &put).await),
+                Some(RateLimitScope::Tenant)
+            );
+        }
+        for _ in 0..2 {
+            assert!(limiter.check_aws_operation("tenant2", &put).await.is_ok());
+        }
+
+        // ...and global rejections never reach the tenant's shared bucket
+        assert_eq!(
+            scope(limiter.check_aws_operation("tenant3", &put).await),
+            Some(RateLimitScope::Global)
+        );
+        assert_eq!(store.writes.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_degrade_to_local_seeds_from_last_remote_state() {
+        let (limiter, store) =
+            limiter_with_failed_store(RateLimitFailurePolicy::DegradeToLocal).await;
+
+        // Two of the four tokens are left in the shared bucket, so the local bucket
+        // admits two more, not a fresh four
+        for _ in 0..2 {
+            assert!(limiter
+                .check_aws_operation("tenant1", &AwsOperation::S3Put { bytes: 1024 })
                 .await
+                .is_ok());
+        }
+        assert!(limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3Put { bytes: 1024 })
+            .await
+            .is_err());
+        assert_eq!(limiter.degraded_decisions(), 3);
+
+        // Recovery goes back to the shared bucket and stops counting
+        store.unavailable.store(false, Ordering::SeqCst);
+        let _ = limiter
+            .check_aws_operation("tenant1", &AwsOperation::S3Put { bytes: 1024 })
+            .await;
+        assert_eq!(limiter.degraded_decisions(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fail_open_admits_everything_while_store_is_down() {
+        let (limiter, _store) = limiter_with_failed_store(RateLimitFailurePolicy::FailOpen).await;
+
+        for _ in 0..20 {
+            assert!(limiter
+                .check_aws_operation("tenant1", &AwsOperation::S3Put { bytes: 1024 })
+                .await
+                .is_ok());
+        }
+        assert_eq!(limiter.degraded_decisions(), 20);
+        assert_eq!(limiter.bucket_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fail_closed_rejects_everything_while_store_is_down() {
+        let (limiter, _store) = limiter_with_failed_store(RateLimitFailurePolicy::FailClosed).await;
+
+        let rejection = limiter
+            .check_aws_operation("tenant2", &AwsOperation::S3Put { bytes: 1024 })
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.service_key, "s3_put");
+        assert_eq!(rejection.retry_after, FAIL_CLOSED_RETRY_AFTER);
+        assert_eq!(limiter.degraded_decisions(), 1);
+    }
+
+    #[test]
+    fn test_failure_policy_parsing() {
+        assert_eq!(
+            RateLimitFailurePolicy::parse("fail_closed"),
+            Some(RateLimitFailurePolicy::FailClosed)
+        );
+        assert_eq!(
+            RateLimitFailurePolicy::parse(" Fail_Open "),
+            Some(RateLimitFailurePolicy::FailOpen)
         );
+        assert_eq!(RateLimitFailurePolicy::parse("open"), None);
+        assert_eq!(
+            RateLimitFailurePolicy::default(),
+            RateLimitFailurePolicy::DegradeToLocal
+        );
+    }
 
-        // Tenant 2 should still have their quota
+    #[test]
+    fn test_burst_falls_back_to_global_burst_capacity() {
+        let limits = AwsServiceLimits {
+            s3_put_requests_per_sec: 10,
+            aws_burst_capacity: 25,
+            service_burst: HashMap::from([("s3_get".to_string(), 5)]),
+            ..Default::default()
+        };
+
+        assert_eq!(bucket_params(&limits, "s3_put"), (35.0, 10.0));
+        assert_eq!(
+            bucket_params(&limits, "s3_get"),
+            (limits.s3_get_requests_per_sec as f64 + 5.0, 500.0)
+        );
+    }
+
+    #[test]
+    fn test_retry_after_unit_cost() {
+        let start = Instant::now();
+        let mut bucket = RateLimitBucket::new(10.0, 10.0);
+        bucket.last_refill = start;
+
+        assert!(bucket.try_consume_at(10.0, start).is_ok());
+        // One token refills every 100ms at 10 tokens/sec
+        assert_eq!(
+            bucket.try_consume_at(1.0, start),
+            Err(Duration::from_millis(100))
+        );
+        assert!(bucket
+            .try_consume_at(1.0, start + Duration::from_millis(100))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_retry_after_accounts_for_partial_tokens_and_cost() {
+        let start = Instant::now();
+        let mut bucket = RateLimitBucket::new(10.0, 2.0);
+        bucket.last_refill = start;
+
+        assert!(bucket.try_consume_at(9.0, start).is_ok());
+        // 1 token left; a cost of 5 needs 4 more at 2 tokens/sec
+        assert_eq!(
+            bucket.try_consume_at(5.0, start),
+            Err(Duration::from_secs(2))
+        );
+
+        // After 500ms, 2 tokens are available and 3 more are needed
+        assert_eq!(
+            bucket.try_consume_at(5.0, start + Duration::from_millis(500)),
+            Err(Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn test_retry_after_rounds_up_and_caps_at_capacity() {
+        let start = Instant::now();
+        let mut bucket = RateLimitBucket::new(3.0, 3.0);
+        bucket.last_refill = start;
+
+        assert!(bucket.try_consume_at(3.0, start).is_ok());
+        // 1/3 second rounds up to the next whole millisecond
+        assert_eq!(
+            bucket.try_consume_at(1.0, start),
+            Err(Duration::from_millis(334))
+        );
+        // A cost larger than capacity reports the time to a full bucket
+        assert_eq!(
+            bucket.try_consume_at(30.0, start),
+            Err(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_write_cost_scales_with_payload_size() {
+        let limits = AwsServiceLimits::default();
+        let kv_cost = |value_len: usize| {
+            let args = serde_json::json!({"key": "k", "value": "x".repeat(value_len)});
+            AwsOperation::from_tool_name("kv_set", &args)
+                .unwrap()
+                .cost(&limits)
+        };
+        // key + value bytes, in 1KB write units, never less than one
+        assert_eq!(kv_cost(0), 1.0);
+        assert_eq!(kv_cost(1023), 1.0);
+        assert_eq!(kv_cost(1024), 2.0);
+        assert_eq!(kv_cost(10 * 1024), 11.0);
+
+        // Non-string values are measured as JSON
+        let args = serde_json::json!({"key": "k", "value": {"list": vec![0; 1000]}});
+        let op = AwsOperation::from_tool_name("kv_set", &args).unwrap();
+        assert_eq!(op.cost(&limits), 2.0);
+
+        let put_cost = |bytes: usize| {
+            use base64::{engine::general_purpose, Engine as _};
+            let content = general_purpose::STANDARD.encode(vec![0u8; bytes]);
+            AwsOperation::from_tool_name("artifacts_put", &serde_json::json!({"content": content}))
+                .unwrap()
+                .cost(&limits)
+        };
+        // 1MB per token by default
+        assert_eq!(put_cost(10), 1.0);
+        assert_eq!(put_cost(1024 * 1024), 1.0);
+        assert_eq!(put_cost(1024 * 1024 + 3), 2.0);
+        assert_eq!(put_cost(5 * 1024 * 1024), 5.0);
+
+        let fine_grained = AwsServiceLimits {
+            s3_put_bytes_per_token: 1024,
+            ..Default::default()
+        };
+        assert_eq!(
+            AwsOperation::S3Put { bytes: 10 * 1024 }.cost(&fine_grained),
+            10.0
+        );
+    }
+
+    #[test]
+    fn test_read_cost_adjusts_for_response_size() {
+        let value = |len: usize| serde_json::json!({"value": "x".repeat(len)});
+
+        // The first 4KB unit was charged up front
+        assert!(AwsOperation::response_adjustment("kv_get", &value(4096)).is_none());
         assert!(
-            limiter
-                .check_aws_operation("tenant2", &AwsOperation::DynamoDbRead { read_units: 5 })
-                .await
+            AwsOperation::response_adjustment("kv_get", &serde_json::json!({"value": null}))
+                .is_none()
         );
+        match AwsOperation::response_adjustment("kv_get", &value(3 * 4096 + 1)) {
+            Some(AwsOperation::DynamoDbRead { read_units }) => assert_eq!(read_units, 3),
+            other => panic!("Expected a read adjustment, got {:?}", other),
+        }
+        assert!(AwsOperation::response_adjustment("artifacts_get", &value(100_000)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_operations_are_rejected_not_charged() {
+        let limits = AwsServiceLimits {
+            s3_put_requests_per_sec: 10,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+
+        // 10 tokens of capacity at 1MB each
+        let fits = AwsOperation::S3Put {
+            bytes: 10 * 1024 * 1024,
+        };
+        assert!(limiter.check_operation_fits("tenant1", &fits).await.is_ok());
+
+        let enormous = AwsOperation::S3Put {
+            bytes: 50 * 1024 * 1024 * 1024,
+        };
+        let oversized = limiter
+            .check_operation_fits("tenant1", &enormous)
+            .await
+            .unwrap_err();
+        assert_eq!(oversized.service_key, "s3_put");
+        assert_eq!(oversized.cost, 51200.0);
+        assert_eq!(oversized.capacity, 10.0);
+        assert!(limiter.snapshot("tenant1").await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_charge_after_puts_bucket_into_bounded_debt() {
+        let limits = AwsServiceLimits {
+            dynamodb_read_units: 10,
+            aws_burst_capacity: 0,
+            ..Default::default()
+        };
+        let limiter = AwsRateLimiter::new(limits);
+
+        limiter
+            .charge_after("tenant1", &AwsOperation::DynamoDbRead { read_units: 15 })
+            .await;
+        assert_eq!(limiter.snapshot("tenant1").await[0].tokens, -5.0);
+
+        // Debt is capped at one bucket's worth
+        limiter
+            .charge_after("tenant1", &AwsOperation::DynamoDbRead { read_units: 1000 })
+            .await;
+        assert_eq!(limiter.snapshot("tenant1").await[0].tokens, -10.0);
+
+        let rejection = limiter
+            .check_aws_operation("tenant1", &AwsOperation::DynamoDbRead { read_units: 1 })
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.retry_after, Duration::from_millis(1100));
     }
 }
@@ -0,0 +1,249 @@
+use serde_json::{Map, Value};
+use std::path::Path;
+use thiserror::Error;
+
+use super::AwsServiceLimits;
+
+/// Path to a TOML (`.toml`) or JSON file of `AwsServiceLimits` fields
+pub const CONFIG_PATH_VAR: &str = "MCP_RATE_LIMIT_CONFIG";
+
+/// Prefix of per-field overrides, e.g. `MCP_LIMIT_DYNAMODB_READ_UNITS`
+const ENV_PREFIX: &str = "MCP_LIMIT_";
+
+/// Numeric `AwsServiceLimits` fields that can be set from the environment
+const ENV_FIELDS: &[&str] = &[
+    "dynamodb_read_units",
+    "dynamodb_write_units",
+    "dynamodb_queries_per_sec",
+    "s3_get_requests_per_sec",
+    "s3_put_requests_per_sec",
+    "s3_list_requests_per_sec",
+    "eventbridge_put_events_per_sec",
+    "eventbridge_events_batch_size",
+    "secrets_manager_requests_per_sec",
+    "sqs_requests_per_sec",
+    "aws_api_calls_per_sec",
+    "aws_burst_capacity",
+    "s3_put_bytes_per_token",
+    "critical_reserve_percent",
+];
+
+#[derive(Error, Debug)]
+pub enum LimitConfigError {
+    #[error("{var} must be a non-negative whole number, got '{value}'")]
+    InvalidEnv { var: String, value: String },
+    #[error("Failed to read rate limit config {path}: {message}")]
+    Io { path: String, message: String },
+    #[error("Invalid rate limit config from {origin}: {message}")]
+    Invalid { origin: String, message: String },
+}
+
+/// Limit fields read from a config file
+#[derive(Debug, Clone)]
+pub struct LimitConfigFile {
+    pub path: String,
+    pub fields: Map<String, Value>,
+}
+
+impl LimitConfigFile {
+    /// Read and parse a config file; `.toml` files are TOML, anything else JSON
+    pub fn read(path: &Path) -> Result<Self, LimitConfigError> {
+        let display = path.display().to_string();
+        let contents = std::fs::read_to_string(path).map_err(|e| LimitConfigError::Io {
+            path: display.clone(),
+            message: e.to_string(),
+        })?;
+        let is_toml = path.extension().is_some_and(|ext| ext == "toml");
+        Self::parse(&display, &contents, is_toml)
+    }
+
+    pub fn parse(path: &str, contents: &str, is_toml: bool) -> Result<Self, LimitConfigError> {
+        let invalid = |message: String| LimitConfigError::Invalid {
+            origin: path.to_string(),
+            message,
+        };
+
+        let value = if is_toml {
+            let table: toml::Table =
+                toml::from_str(contents).map_err(|e| invalid(e.to_string()))?;
+            serde_json::to_value(table).map_err(|e| invalid(e.to_string()))?
+        } else {
+            serde_json::from_str(contents).map_err(|e| invalid(e.to_string()))?
+        };
+
+        match value {
+            Value::Object(fields) => Ok(Self {
+                path: path.to_string(),
+                fields,
+            }),
+            _ => Err(invalid("expected a table of limit fields".to_string())),
+        }
+    }
+}
+
+/// Resolve default limits with precedence file > env > compiled-in defaults. Tenant
+/// overrides are applied on top of these by `AwsRateLimiter::set_tenant_limits`.
+pub fn resolve_limits(
+    env: impl Fn(&str) -> Option<String>,
+    file: Option<&LimitConfigFile>,
+) -> Result<AwsServiceLimits, LimitConfigError> {
+    let mut fields = match serde_json::to_value(AwsServiceLimits::default()) {
+        Ok(Value::Object(fields)) => fields,
+        _ => unreachable!("AwsServiceLimits serializes to an object"),
+    };
+
+    for field in ENV_FIELDS {
+        let var = format!("{}{}", ENV_PREFIX, field.to_uppercase());
+        if let Some(value) = env(&var) {
+            let number: u64 = value
+                .trim()
+                .parse()
+                .map_err(|_| LimitConfigError::InvalidEnv {
+                    var: var.clone(),
+                    value: value.clone(),
+                })?;
+            fields.insert(field.to_string(), Value::from(number));
+        }
+    }
+    // Catch out-of-range env values before the file can mask them
+    deserialize(fields.clone(), "environment")?;
+
+    if let Some(file) = file {
+        for (field, value) in &file.fields {
+            if !fields.contains_key(field) {
+                return Err(LimitConfigError::Invalid {
+                    origin: file.path.clone(),
+                    message: format!("unknown field '{}'", field),
+                });
+            }
+            fields.insert(field.clone(), value.clone());
+        }
+        return deserialize(fields, &file.path);
+    }
+
+    deserialize(fields, "environment")
+}
+
+fn deserialize(
+    fields: Map<String, Value>,
+    origin: &str,
+) -> Result<AwsServiceLimits, LimitConfigError> {
+    serde_json::from_value(Value::Object(fields)).map_err(|e| LimitConfigError::Invalid {
+        origin: origin.to_string(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rate_limiting::AwsRateLimiter;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |var| vars.get(var).cloned()
+    }
+
+    #[test]
+    fn test_defaults_without_overrides() {
+        let limits = resolve_limits(env(&[]), None).unwrap();
+        assert_eq!(
+            limits.dynamodb_read_units,
+            AwsServiceLimits::default().dynamodb_read_units
+        );
+    }
+
+    #[test]
+    fn test_env_overrides_defaults() {
+        let limits = resolve_limits(
+            env(&[
+                ("MCP_LIMIT_DYNAMODB_READ_UNITS", "250"),
+                ("MCP_LIMIT_S3_PUT_BYTES_PER_TOKEN", "4096"),
+            ]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(limits.dynamodb_read_units, 250);
+        assert_eq!(limits.s3_put_bytes_per_token, 4096);
+        assert_eq!(
+            limits.dynamodb_write_units,
+            AwsServiceLimits::default().dynamodb_write_units
+        );
+    }
+
+    #[test]
+    fn test_file_overrides_env() {
+        let file = LimitConfigFile::parse(
+            "limits.toml",
+            "dynamodb_read_units = 75\n\n[service_burst]\ns3_get = 5\n",
+            true,
+        )
+        .unwrap();
+        let limits = resolve_limits(
+            env(&[
+                ("MCP_LIMIT_DYNAMODB_READ_UNITS", "250"),
+                ("MCP_LIMIT_S3_GET_REQUESTS_PER_SEC", "40"),
+            ]),
+            Some(&file),
+        )
+        .unwrap();
+        assert_eq!(limits.dynamodb_read_units, 75);
+        assert_eq!(limits.s3_get_requests_per_sec, 40);
+        assert_eq!(limits.service_burst.get("s3_get"), Some(&5));
+
+        let json =
+            LimitConfigFile::parse("limits.json", r#"{"aws_burst_capacity": 0}"#, false).unwrap();
+        let limits = resolve_limits(env(&[]), Some(&json)).unwrap();
+        assert_eq!(limits.aws_burst_capacity, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_override_beats_file() {
+        let file =
+            LimitConfigFile::parse("limits.json", r#"{"dynamodb_read_units": 75}"#, false).unwrap();
+        let defaults = resolve_limits(env(&[]), Some(&file)).unwrap();
+        let limiter = AwsRateLimiter::new(defaults);
+
+        let tenant_limits = AwsServiceLimits {
+            dynamodb_read_units: 5,
+            ..Default::default()
+        };
+        limiter.set_tenant_limits("tenant1", tenant_limits).await;
+
+        assert_eq!(limiter.limits_for("tenant1").await.dynamodb_read_units, 5);
+        assert_eq!(limiter.limits_for("tenant2").await.dynamodb_read_units, 75);
+    }
+
+    #[test]
+    fn test_invalid_values_name_their_source() {
+        let err =
+            resolve_limits(env(&[("MCP_LIMIT_AWS_BURST_CAPACITY", "lots")]), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MCP_LIMIT_AWS_BURST_CAPACITY must be a non-negative whole number, got 'lots'"
+        );
+
+        let err = resolve_limits(
+            env(&[("MCP_LIMIT_DYNAMODB_READ_UNITS", "99999999999")]),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("environment"), "{}", err);
+
+        let file = LimitConfigFile::parse("limits.toml", "dynamodb_reads = 1", true).unwrap();
+        let err = resolve_limits(env(&[]), Some(&file)).unwrap_err();
+        assert!(err.to_string().contains("unknown field 'dynamodb_reads'"));
+
+        let file =
+            LimitConfigFile::parse("limits.toml", "s3_put_requests_per_sec = \"fast\"", true)
+                .unwrap();
+        let err = resolve_limits(env(&[]), Some(&file)).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid rate limit config from limits.toml"));
+    }
+}
@@ -0,0 +1,536 @@
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client as DynamoDbClient;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// Conditional writes attempted before giving up on a contended bucket
+const MAX_CAS_ATTEMPTS: u32 = 5;
+
+/// Fraction of capacity leased locally when the shared bucket is clearly under its limit
+const LEASE_FRACTION: f64 = 0.1;
+
+/// How long a local lease may be spent before it is discarded
+const LEASE_TTL_MS: i64 = 1_000;
+
+/// Shared bucket items expire from DynamoDB after a day without use
+const ITEM_TTL_SECS: i64 = 86_400;
+
+#[derive(Error, Debug)]
+pub enum BucketStoreError {
+    #[error("Bucket store unavailable: {0}")]
+    Unavailable(String),
+    #[error("Bucket {0} is too contended to update")]
+    Contention(String),
+}
+
+/// How admissions are decided while the shared bucket store is failing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitFailurePolicy {
+    /// Admit everything; only the global (per-instance) limits still apply
+    FailOpen,
+    /// Reject everything until the store recovers
+    FailClosed,
+    /// Fall back to this instance's local buckets, seeded from the last state seen in
+    /// the store
+    #[default]
+    DegradeToLocal,
+}
+
+impl RateLimitFailurePolicy {
+    /// Parse `fail_open`, `fail_closed` or `degrade_to_local`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "fail_open" => Some(RateLimitFailurePolicy::FailOpen),
+            "fail_closed" => Some(RateLimitFailurePolicy::FailClosed),
+            "degrade_to_local" => Some(RateLimitFailurePolicy::DegradeToLocal),
+            _ => None,
+        }
+    }
+}
+
+/// Bucket state shared by all replicas
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredBucket {
+    pub tokens: f64,
+    pub updated_at_ms: i64,
+    pub version: u64,
+}
+
+/// Storage for shared bucket state with compare-and-swap semantics
+#[async_trait]
+pub trait BucketStore: Send + Sync {
+    async fn load(&self, key: &str) -> Result<Option<StoredBucket>, BucketStoreError>;
+
+    /// Write the bucket only if the stored version still matches `expected_version`
+    /// (`None` = the item must not exist yet). Returns false on a conflicting write.
+    async fn store_if_version(
+        &self,
+        key: &str,
+        bucket: &StoredBucket,
+        expected_version: Option<u64>,
+    ) -> Result<bool, BucketStoreError>;
+}
+
+/// DynamoDB-backed bucket store using conditional PutItem on a version attribute
+pub struct DynamoBucketStore {
+    client: DynamoDbClient,
+    table: String,
+}
+
+impl DynamoBucketStore {
+    pub fn new(client: DynamoDbClient, table: String) -> Self {
+        Self { client, table }
+    }
+}
+
+#[async_trait]
+impl BucketStore for DynamoBucketStore {
+    async fn load(&self, key: &str) -> Result<Option<StoredBucket>, BucketStoreError> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table)
+            .key("pk", AttributeValue::S(key.to_string()))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(|e| BucketStoreError::Unavailable(e.to_string()))?;
+
+        let Some(item) = output.item else {
+            return Ok(None);
+        };
+
+        fn number<T: std::str::FromStr>(
+            item: &HashMap<String, AttributeValue>,
+            key: &str,
+            name: &str,
+        ) -> Result<T, BucketStoreError> {
+            item.get(name)
+                .and_then(|v| v.as_n().ok())
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| BucketStoreError::Unavailable(format!("Malformed bucket {}", key)))
+        }
+
+        Ok(Some(StoredBucket {
+            tokens: number(&item, key, "tokens")?,
+            updated_at_ms: number(&item, key, "updated_at_ms")?,
+            version: number(&item, key, "version")?,
+        }))
+    }
+
+    async fn store_if_version(
+        &self,
+        key: &str,
+        bucket: &StoredBucket,
+        expected_version: Option<u64>,
+    ) -> Result<bool, BucketStoreError> {
+        let expires_at = bucket.updated_at_ms / 1000 + ITEM_TTL_SECS;
+        let request = self
+            .client
+            .put_item()
+            .table_name(&self.table)
+            .item("pk", AttributeValue::S(key.to_string()))
+            .item("tokens", AttributeValue::N(bucket.tokens.to_string()))
+            .item(
+                "updated_at_ms",
+                AttributeValue::N(bucket.updated_at_ms.to_string()),
+            )
+            .item("version", AttributeValue::N(bucket.version.to_string()))
+            .item("expires_at", AttributeValue::N(expires_at.to_string()));
+
+        let request = match expected_version {
+            Some(version) => request
+                .condition_expression("version = :expected")
+                .expression_attribute_values(":expected", AttributeValue::N(version.to_string())),
+            None => request.condition_expression("attribute_not_exists(pk)"),
+        };
+
+        match request.send().await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let conflict = e
+                    .as_service_error()
+                    .is_some_and(|se| se.is_conditional_check_failed_exception());
+                if conflict {
+                    Ok(false)
+                } else {
+                    Err(BucketStoreError::Unavailable(e.to_string()))
+                }
+            }
+        }
+    }
+}
+
+/// Tokens taken from the shared bucket in advance and spent locally
+#[derive(Debug)]
+struct Lease {
+    tokens: f64,
+    expires_at_ms: i64,
+}
+
+/// Token buckets shared across replicas through a `BucketStore`.
+///
+/// Every admission is charged to the shared bucket with an optimistic conditional write.
+/// To avoid a round trip per request, a replica that finds a bucket more than half full
+/// also leases a small slice of capacity and spends it locally until it runs out or the
+/// lease expires; unspent leased tokens are simply dropped.
+pub struct DistributedLimiter {
+    store: Arc<dyn BucketStore>,
+    leases: Mutex<HashMap<String, Lease>>,
+    // Last bucket state read from or written to the store, per key
+    last_known: Mutex<HashMap<String, StoredBucket>>,
+}
+
+impl DistributedLimiter {
+    pub fn new(store: Arc<dyn BucketStore>) -> Self {
+        Self {
+            store,
+            leases: Mutex::new(HashMap::new()),
+            last_known: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Tokens this replica may still spend for `key` according to the last state it saw
+    /// in the store, refilled up to now and including any unspent lease. The state is
+    /// taken, so it seeds at most one local bucket per outage.
+    pub async fn take_last_known(&self, key: &str, capacity: f64, refill_rate: f64) -> Option<f64> {
+        self.take_last_known_at(
+            key,
+            capacity,
+            refill_rate,
+            chrono::Utc::now().timestamp_millis(),
+        )
+        .await
+    }
+
+    async fn take_last_known_at(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_rate: f64,
+        now_ms: i64,
+    ) -> Option<f64> {
+        let bucket = self.last_known.lock().await.remove(key)?;
+        let elapsed_secs = (now_ms - bucket.updated_at_ms).max(0) as f64 / 1000.0;
+        let leased = self
+            .leases
+            .lock()
+            .await
+            .get(key)
+            .filter(|lease| lease.expires_at_ms > now_ms)
+            .map_or(0.0, |lease| lease.tokens);
+        Some((bucket.tokens + elapsed_secs * refill_rate + leased).min(capacity))
+    }
+
+    /// Try to consume `cost` tokens from the shared bucket. The outer error means the
+    /// store could not be used; the inner error is a normal rejection with retry-after.
+    pub async fn try_consume(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_rate: f64,
+        cost: f64,
+    ) -> Result<Result<(), Duration>, BucketStoreError> {
+        self.try_consume_at(
+            key,
+            capacity,
+            refill_rate,
+            cost,
+            chrono::Utc::now().timestamp_millis(),
+        )
+        .await
+    }
+
+    async fn try_consume_at(
+        &self,
+        key: &str,
+        capacity: f64,
+        refill_rate: f64,
+        cost: f64,
+        now_ms: i64,
+    ) -> Result<Result<(), Duration>, BucketStoreError> {
+        // Spend from a live local lease without touching the store
+        {
+            let mut leases = self.leases.lock().await;
+            if let Some(lease) = leases.get_mut(key) {
+                if lease.expires_at_ms > now_ms && lease.tokens >= cost {
+                    lease.tokens -= cost;
+                    return Ok(Ok(()));
+                }
+                leases.remove(key);
+            }
+        }
+
+        for _ in 0..MAX_CAS_ATTEMPTS {
+            let stored = self.store.load(key).await?;
+            if let Some(bucket) = &stored {
+                self.last_known
+                    .lock()
+                    .await
+                    .insert(key.to_string(), bucket.clone());
+            }
+            let (available, expected_version) = match &stored {
+                Some(bucket) => {
+                    let elapsed_secs = (now_ms - bucket.updated_at_ms).max(0) as f64 / 1000.0;
+                    (
+                        (bucket.tokens + elapsed_secs * refill_rate).min(capacity),
+                        Some(bucket.version),
+                    )
+                }
+                None => (capacity, None),
+            };
+
+            if available < cost {
+                return Ok(Err(retry_after(
+                    cost.min(capacity) - available,
+                    refill_rate,
+                )));
+            }
+
+            // Clearly under the limit: take a lease along with this request's cost
+            let lease = if available - cost > capacity / 2.0 {
+                (capacity * LEASE_FRACTION).min(available - cost - capacity / 2.0)
+            } else {
+                0.0
+            };
+
+            let updated = StoredBucket {
+                tokens: available - cost - lease,
+                updated_at_ms: now_ms,
+                version: expected_version.map_or(1, |v| v + 1),
+            };
+
+            if self
+                .store
+                .store_if_version(key, &updated, expected_version)
+                .await?
+            {
+                self.last_known
+                    .lock()
+                    .await
+                    .insert(key.to_string(), updated);
+                if lease > 0.0 {
+                    self.leases.lock().await.insert(
+                        key.to_string(),
+                        Lease {
+                            tokens: lease,
+                            expires_at_ms: now_ms + LEASE_TTL_MS,
+                        },
+                    );
+                }
+                return Ok(Ok(()));
+            }
+            // Another replica updated the bucket first; reload and retry
+        }
+
+        Err(BucketStoreError::Contention(key.to_string()))
+    }
+}
+
+fn retry_after(deficit: f64, refill_rate: f64) -> Duration {
+    if refill_rate <= 0.0 {
+        return Duration::MAX;
+    }
+    Duration::from_millis((deficit / refill_rate * 1000.0).ceil() as u64)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// In-memory bucket store with the same conditional-write semantics as DynamoDB,
+    /// plus fault injection for conflicts and outages
+    #[derive(Default)]
+    pub(crate) struct MockBucketStore {
+        items: Mutex<HashMap<String, StoredBucket>>,
+        pub conflicts_to_inject: AtomicU32,
+        pub unavailable: std::sync::atomic::AtomicBool,
+        pub writes: AtomicU32,
+    }
+
+    #[async_trait]
+    impl BucketStore for MockBucketStore {
+        async fn load(&self, key: &str) -> Result<Option<StoredBucket>, BucketStoreError> {
+            if self.unavailable.load(Ordering::SeqCst) {
+                return Err(BucketStoreError::Unavailable("mock outage".to_string()));
+            }
+            Ok(self.items.lock().await.get(key).cloned())
+        }
+
+        async fn store_if_version(
+            &self,
+            key: &str,
+            bucket: &StoredBucket,
+            expected_version: Option<u64>,
+        ) -> Result<bool, BucketStoreError> {
+            if self.unavailable.load(Ordering::SeqCst) {
+                return Err(BucketStoreError::Unavailable("mock outage".to_string()));
+            }
+
+            // Simulate another replica winning the race
+            if self
+                .conflicts_to_inject
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                let mut items = self.items.lock().await;
+                let entry = items.entry(key.to_string()).or_insert(StoredBucket {
+                    tokens: bucket.tokens,
+                    updated_at_ms: bucket.updated_at_ms,
+                    version: 0,
+                });
+                entry.version += 1;
+                return Ok(false);
+            }
+
+            let mut items = self.items.lock().await;
+            if items.get(key).map(|b| b.version) != expected_version {
+                return Ok(false);
+            }
+            items.insert(key.to_string(), bucket.clone());
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_writes_are_retried() {
+        let store = Arc::new(MockBucketStore::default());
+        store.conflicts_to_inject.store(2, Ordering::SeqCst);
+        let limiter = DistributedLimiter::new(store.clone());
+
+        let result = limiter
+            .try_consume_at("t1#s3_put", 10.0, 1.0, 1.0, 1_000)
+            .await
+            .unwrap();
+        assert!(result.is_ok());
+        assert_eq!(store.writes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_contention_is_an_error() {
+        let store = Arc::new(MockBucketStore::default());
+        store
+            .conflicts_to_inject
+            .store(MAX_CAS_ATTEMPTS, Ordering::SeqCst);
+        let limiter = DistributedLimiter::new(store);
+
+        let result = limiter
+            .try_consume_at("t1#s3_put", 10.0, 1.0, 1.0, 1_000)
+            .await;
+        assert!(matches!(result, Err(BucketStoreError::Contention(_))));
+    }
+
+    #[tokio::test]
+    async fn test_replicas_share_one_budget() {
+        let store: Arc<dyn BucketStore> = Arc::new(MockBucketStore::default());
+        let replicas = [
+            DistributedLimiter::new(store.clone()),
+            DistributedLimiter::new(store.clone()),
+            DistributedLimiter::new(store),
+        ];
+
+        // Near-zero refill so the shared capacity of 20 is the whole budget
+        let mut admitted = 0;
+        for i in 0..60 {
+            let replica = &replicas[i % replicas.len()];
+            if replica
+                .try_consume_at("t1#dynamodb_write", 20.0, 0.001, 1.0, 1_000)
+                .await
+                .unwrap()
+                .is_ok()
+            {
+                admitted += 1;
+            }
+        }
+        assert_eq!(admitted, 20);
+    }
+
+    #[tokio::test]
+    async fn test_under_limit_requests_are_served_from_lease() {
+        let store = Arc::new(MockBucketStore::default());
+        let limiter = DistributedLimiter::new(store.clone());
+
+        // First request leases 10% of capacity alongside its own cost
+        for _ in 0..11 {
+            assert!(limiter
+                .try_consume_at("t1#s3_get", 100.0, 1.0, 1.0, 1_000)
+                .await
+                .unwrap()
+                .is_ok());
+        }
+        assert_eq!(store.writes.load(Ordering::SeqCst), 1);
+
+        // Once the lease is spent, the next request goes back to the store
+        assert!(limiter
+            .try_consume_at("t1#s3_get", 100.0, 1.0, 1.0, 1_000)
+            .await
+            .unwrap()
+            .is_ok());
+        assert_eq!(store.writes.load(Ordering::SeqCst), 2);
+
+        // Expired leases are not spent
+        assert!(limiter
+            .try_consume_at("t1#s3_get", 100.0, 1.0, 1.0, 1_000 + LEASE_TTL_MS)
+            .await
+            .unwrap()
+            .is_ok());
+        assert_eq!(store.writes.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_last_known_state_is_taken_once() {
+        let store = Arc::new(MockBucketStore::default());
+        let limiter = DistributedLimiter::new(store);
+
+        assert_eq!(
+            limiter
+                .take_last_known_at("t1#s3_put", 10.0, 1.0, 1_000)
+                .await,
+            None
+        );
+        assert!(limiter
+            .try_consume_at("t1#s3_put", 10.0, 1.0, 6.0, 1_000)
+            .await
+            .unwrap()
+            .is_ok());
+
+        // 4 tokens left at 1s, plus 2s of refill
+        assert_eq!(
+            limiter
+                .take_last_known_at("t1#s3_put", 10.0, 1.0, 3_000)
+                .await,
+            Some(6.0)
+        );
+        assert_eq!(
+            limiter
+                .take_last_known_at("t1#s3_put", 10.0, 1.0, 3_000)
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejection_reports_retry_after() {
+        let store = Arc::new(MockBucketStore::default());
+        let limiter = DistributedLimiter::new(store);
+
+        assert!(limiter
+            .try_consume_at("t1#s3_list", 2.0, 4.0, 2.0, 1_000)
+            .await
+            .unwrap()
+            .is_ok());
+        let result = limiter
+            .try_consume_at("t1#s3_list", 2.0, 4.0, 1.0, 1_000)
+            .await
+            .unwrap();
+        assert_eq!(result, Err(Duration::from_millis(250)));
+    }
+}
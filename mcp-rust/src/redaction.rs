@@ -0,0 +1,229 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::sync::OnceLock;
+use tracing_subscriber::field::MakeExt;
+use tracing_subscriber::fmt::format::{self, FormatFields, Writer};
+
+use crate::tenant::glob_match;
+
+/// Extra key patterns, comma-separated, redacted on top of [`DEFAULT_PATTERNS`]
+pub const REDACT_PATTERNS_ENV: &str = "MCP_REDACT_PATTERNS";
+
+/// Key patterns whose values are always redacted. `*` matches anything; a pattern with
+/// a `.` is matched against the dotted path leading to the key, so `credentials.*`
+/// covers every field of a `credentials` object at any depth.
+pub const DEFAULT_PATTERNS: &[&str] = &[
+    "*secret*",
+    "*token*",
+    "*password*",
+    "*passwd*",
+    "*credential*",
+    "credentials.*",
+    "*authorization*",
+    "*api_key*",
+    "*apikey*",
+    "*private_key*",
+    "*access_key*",
+    "*kms_key*",
+];
+
+/// Start of every replacement; the rest is a SHA-256 prefix of the redacted value
+pub const REDACTED_PREFIX: &str = "[REDACTED:";
+
+/// Hex digits of the value's SHA-256 kept in a replacement: enough to tell values
+/// apart when correlating, too few to recover them
+const HASH_PREFIX_LEN: usize = 8;
+
+static POLICY: OnceLock<RedactionPolicy> = OnceLock::new();
+
+/// Which keys' values are credentials, and how they're scrubbed out of JSON values and
+/// text before anything is logged, returned in an error, or written to a diagnostics
+/// bundle. Keys are compared lowercased, with `-` read as `_`.
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    patterns: Vec<String>,
+}
+
+impl Default for RedactionPolicy {
+    /// [`DEFAULT_PATTERNS`] only
+    fn default() -> Self {
+        Self::new(DEFAULT_PATTERNS.iter().map(|p| p.to_string()))
+    }
+}
+
+impl RedactionPolicy {
+    /// A policy redacting exactly `patterns`
+    pub fn new(patterns: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|pattern| normalize(pattern.trim()))
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+        }
+    }
+
+    /// [`DEFAULT_PATTERNS`] and `extra`; the defaults can't be switched off
+    pub fn with_extra_patterns(extra: &[String]) -> Self {
+        Self::new(
+            DEFAULT_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .chain(extra.iter().cloned()),
+        )
+    }
+
+    /// Whether the value of the last key in `path` is a credential
+    pub fn is_sensitive(&self, path: &[&str]) -> bool {
+        let Some(key) = path.last() else {
+            return false;
+        };
+        let path: Vec<String> = path.iter().map(|key| normalize(key)).collect();
+        let key = normalize(key);
+        self.patterns.iter().any(|pattern| {
+            if pattern.contains('.') {
+                (0..path.len()).any(|from| glob_match(pattern, &path[from..].join(".")))
+            } else {
+                glob_match(pattern, &key)
+            }
+        })
+    }
+
+    /// Replace the values of credential keys, at any depth, with [`mask`]s, and scrub
+    /// credential `key=value` words out of strings. Numbers, booleans and nulls are
+    /// kept, so counters such as a bucket's `tokens` survive.
+    pub fn redact(&self, value: &mut Value) {
+        self.redact_at(&mut Vec::new(), value);
+    }
+
+    /// A redacted copy of `value`
+    pub fn redacted(&self, value: &Value) -> Value {
+        let mut value = value.clone();
+        self.redact(&mut value);
+        value
+    }
+
+    fn redact_at<'a>(&self, path: &mut Vec<&'a str>, value: &'a mut Value) {
+        match value {
+            Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    path.push(key);
+                    match field {
+                        Value::Number(_) | Value::Bool(_) | Value::Null => {}
+                        _ if self.is_sensitive(path) => *field = Value::String(mask(field)),
+                        _ => self.redact_at(path, field),
+                    }
+                    path.pop();
+                }
+            }
+            Value::Array(items) => {
+                for item in items.iter_mut() {
+                    self.redact_at(path, item);
+                }
+            }
+            Value::String(text) => *text = self.redact_text(text),
+            _ => {}
+        }
+    }
+
+    /// `text` with the values of credential `key=value`, `key: value` and
+    /// `Bearer value` words masked; the auth scheme of `Authorization: Bearer value`
+    /// is kept
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut redacted = Vec::new();
+        let mut redact_next = false;
+        for word in text.split(' ') {
+            let scheme = word.eq_ignore_ascii_case("bearer") || word.eq_ignore_ascii_case("basic");
+            if redact_next && !word.is_empty() && !scheme {
+                redacted.push(mask_text(word));
+                redact_next = false;
+                continue;
+            }
+            match word.find(['=', ':']) {
+                Some(at) if self.is_sensitive(&[&word[..at]]) => {
+                    if word[at + 1..].is_empty() {
+                        // The value is the next word: `password: hunter2`
+                        redact_next = true;
+                        redacted.push(word.to_string());
+                    } else {
+                        redacted.push(format!("{}{}", &word[..=at], mask_text(&word[at + 1..])));
+                    }
+                }
+                _ => {
+                    redact_next = scheme || (redact_next && word.is_empty());
+                    redacted.push(word.to_string());
+                }
+            }
+        }
+        redacted.join(" ")
+    }
+}
+
+fn normalize(key: &str) -> String {
+    key.to_lowercase().replace('-', "_")
+}
+
+/// `[REDACTED:<first 8 hex digits of the value's SHA-256>]`, so the same secret masks
+/// the same everywhere and can still be correlated across logs and bundles. Strings
+/// are hashed as they are, anything else as its JSON.
+pub fn mask(value: &Value) -> String {
+    match value {
+        Value::String(text) => mask_text(text),
+        other => mask_text(&other.to_string()),
+    }
+}
+
+fn mask_text(text: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(text.as_bytes()));
+    format!("{}{}]", REDACTED_PREFIX, &digest[..HASH_PREFIX_LEN])
+}
+
+/// Make `policy` the one [`policy`] returns for the rest of the process. Only the first
+/// call takes effect; returns whether this one did.
+pub fn install(policy: RedactionPolicy) -> bool {
+    POLICY.set(policy).is_ok()
+}
+
+/// The installed policy, or the default one when none was installed
+pub fn policy() -> &'static RedactionPolicy {
+    POLICY.get_or_init(RedactionPolicy::default)
+}
+
+/// [`RedactionPolicy::redact`] with the installed policy
+pub fn redact(value: &mut Value) {
+    policy().redact(value)
+}
+
+/// [`RedactionPolicy::redact_text`] with the installed policy
+pub fn redact_text(text: &str) -> String {
+    policy().redact_text(text)
+}
+
+/// Formats log event fields with the installed policy applied: credential-named fields
+/// are masked, and every other field and the message go through
+/// [`RedactionPolicy::redact_text`]
+pub fn log_fields() -> impl for<'writer> FormatFields<'writer> + Send + Sync + 'static {
+    format::debug_fn(|writer: &mut Writer<'_>, field, value| {
+        let mut text = String::new();
+        let _ = write!(text, "{:?}", value);
+        let policy = policy();
+        match field.name() {
+            "message" => write!(writer, "{}", policy.redact_text(&text)),
+            // Metadata of events forwarded from the `log` crate, skipped like the
+            // default formatter does
+            name if name.starts_with("log.") => Ok(()),
+            name if policy.is_sensitive(&[name]) => {
+                // String fields are debug-quoted; mask what was logged, so it matches
+                // the same value masked elsewhere
+                let raw = text
+                    .strip_prefix('"')
+                    .and_then(|text| text.strip_suffix('"'))
+                    .unwrap_or(&text);
+                write!(writer, "{}={}", name, mask_text(raw))
+            }
+            name => write!(writer, "{}={}", name, policy.redact_text(&text)),
+        }
+    })
+    .delimited(" ")
+}
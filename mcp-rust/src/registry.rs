@@ -1,15 +1,81 @@
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::process::{Child, Command};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
-use crate::aws::AwsService;
+use crate::aws::{
+    internal_keys, AwsApi, DirectKvStore, KvSecretStore, SecretStore, SecretsManagerStore,
+};
+use crate::config::{RegistryConfig, SecretsBackend};
 use crate::tenant::TenantSession;
 
+pub mod docker;
+pub mod export;
+pub mod http;
+pub mod lambda;
+pub mod logs;
+pub mod metrics;
+pub mod oauth;
+pub mod stdio;
+pub mod tool_index;
+pub mod websocket;
+
+use docker::{DockerCli, DockerPolicy, EnvFile, SystemDocker};
+use export::{ExportedServer, ImportReport, IntegrationExport, MissingCredentials, OnConflict};
+use http::{HttpAuth, HttpMcpClient};
+use lambda::{LambdaInvoker, LambdaMcpClient, SdkLambdaInvoker, UnavailableLambdaInvoker};
+use logs::{LogBuffer, LogLine, LogStream, LOG_BUFFER_LINES};
+use metrics::{ServerMetrics, ServerMetricsReport};
+use oauth::{OAuthTokens, TokenStatus, TOKEN_REFRESH_SKEW};
+use stdio::StdioMcpClient;
+use tool_index::ToolIndex;
+use websocket::{WebSocketMcpClient, WebSocketOptions};
+
+/// Protocol version sent in `initialize` to downstream servers
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Where server credentials are kept: `secrets_manager` (default) or `kv` for
+/// environments without Secrets Manager, such as LocalStack
+pub const SECRETS_BACKEND_ENV: &str = "AGENT_MESH_SECRETS_BACKEND";
+
+/// Set to `true` to start pulling a Docker integration's image as soon as it is
+/// registered, so the first connect doesn't wait for the download
+pub const DOCKER_PREPULL_ENV: &str = "AGENT_MESH_DOCKER_PREPULL";
+
+/// How often the health monitor looks for servers due a check or reconnect
+pub const HEALTH_CHECK_TICK: Duration = Duration::from_secs(5);
+
+/// Most connections a health sweep checks or reconnects at once
+const HEALTH_CHECK_CONCURRENCY: usize = 8;
+
+/// Timeout for each request to an HTTP MCP server
+const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest to wait for a process-based server to answer one request
+const STDIO_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest a tool call may take unless its server config or the caller says otherwise
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest to wait for a started container to report `running`
+const CONTAINER_START_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay between `docker inspect` polls while a container starts
+const CONTAINER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long shutdown waits for a server process or container to exit on its own
+/// before killing it
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPServerConfig {
     pub id: String,
@@ -22,6 +88,138 @@ pub struct MCPServerConfig {
     pub capabilities: Vec<String>,
     pub health_check_interval_secs: u64,
     pub auto_reconnect: bool,
+    /// Longest a tool call may take; `DEFAULT_TOOL_TIMEOUT` when unset
+    #[serde(default)]
+    pub tool_timeout_secs: Option<u64>,
+    /// How the health monitor restarts this server; the registry's policy when unset
+    #[serde(default)]
+    pub restart_policy: Option<RestartPolicy>,
+}
+
+impl MCPServerConfig {
+    /// How long a tool call on this server may take
+    pub fn tool_timeout(&self) -> Duration {
+        self.tool_timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TOOL_TIMEOUT)
+    }
+}
+
+/// Changes to a registered server's config; fields left unset keep their value
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MCPServerUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub health_check_interval_secs: Option<u64>,
+    pub auto_reconnect: Option<bool>,
+    pub tool_timeout_secs: Option<u64>,
+    pub restart_policy: Option<RestartPolicy>,
+    /// Replaces the whole environment
+    pub env: Option<HashMap<String, String>>,
+    /// Docker deployments only
+    pub image: Option<String>,
+    pub tag: Option<String>,
+    /// Process deployments only
+    pub command: Option<String>,
+    pub args: Option<Vec<String>>,
+}
+
+impl MCPServerUpdate {
+    /// Whether applying the update to a running server means restarting it
+    pub fn requires_restart(&self) -> bool {
+        self.env.is_some()
+            || self.image.is_some()
+            || self.tag.is_some()
+            || self.command.is_some()
+            || self.args.is_some()
+    }
+
+    fn apply(&self, config: &mut MCPServerConfig) -> Result<(), RegistryError> {
+        let wrong_deployment = |field: &str, kind: &str| RegistryError::InvalidConfig {
+            field: field.to_string(),
+            message: format!("only {} deployments have one", kind),
+        };
+        match &mut config.deployment {
+            DeploymentConfig::Docker { image, tag, .. } => {
+                if self.command.is_some() {
+                    return Err(wrong_deployment("command", "process"));
+                }
+                if self.args.is_some() {
+                    return Err(wrong_deployment("args", "process"));
+                }
+                if let Some(new_image) = &self.image {
+                    *image = new_image.clone();
+                }
+                if let Some(new_tag) = &self.tag {
+                    *tag = new_tag.clone();
+                }
+            }
+            DeploymentConfig::Process { command, args } => {
+                if self.image.is_some() {
+                    return Err(wrong_deployment("image", "Docker"));
+                }
+                if self.tag.is_some() {
+                    return Err(wrong_deployment("tag", "Docker"));
+                }
+                if let Some(new_command) = &self.command {
+                    *command = new_command.clone();
+                }
+                if let Some(new_args) = &self.args {
+                    *args = new_args.clone();
+                }
+            }
+            DeploymentConfig::Lambda { .. } => {
+                for (field, set) in [
+                    ("image", self.image.is_some()),
+                    ("tag", self.tag.is_some()),
+                    ("command", self.command.is_some()),
+                    ("args", self.args.is_some()),
+                ] {
+                    if set {
+                        return Err(RegistryError::InvalidConfig {
+                            field: field.to_string(),
+                            message: "Lambda deployments have none".to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(name) = &self.name {
+            config.name = name.clone();
+        }
+        if let Some(description) = &self.description {
+            config.description = description.clone();
+        }
+        if let Some(interval) = self.health_check_interval_secs {
+            config.health_check_interval_secs = interval;
+        }
+        if let Some(auto_reconnect) = self.auto_reconnect {
+            config.auto_reconnect = auto_reconnect;
+        }
+        if let Some(timeout) = self.tool_timeout_secs {
+            config.tool_timeout_secs = Some(timeout);
+        }
+        if let Some(policy) = &self.restart_policy {
+            config.restart_policy = Some(policy.clone());
+        }
+        if let Some(env) = &self.env {
+            config.env = env.clone();
+        }
+        Ok(())
+    }
+}
+
+/// How `update_server` brought a server in line with its new config
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateOutcome {
+    /// Nothing was connected; the new config applies from the next connect
+    Stored,
+    /// Connected instances took the change without restarting
+    AppliedInPlace,
+    /// Connected instances were restarted with the new config
+    Reconnected,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -42,6 +240,18 @@ pub enum DeploymentConfig {
         volumes: Vec<String>,
         network: Option<String>,
         runtime: Option<String>, // nvidia for GPU, etc.
+        /// `--memory`, e.g. `512m`
+        #[serde(default)]
+        memory_limit: Option<String>,
+        /// `--cpus`, e.g. `1.5`
+        #[serde(default)]
+        cpu_limit: Option<String>,
+        /// `--pids-limit`
+        #[serde(default)]
+        pids_limit: Option<u32>,
+        /// `--read-only`
+        #[serde(default)]
+        read_only_rootfs: bool,
     },
     Process {
         command: String,
@@ -60,9 +270,13 @@ pub enum AuthMethod {
     ApiKey {
         key_field: String,
     },
+    /// Tokens are stored per connection as the `access_token`, `refresh_token` and
+    /// `expires_at` credentials and refreshed at `token_endpoint` shortly before expiry
     OAuth2 {
         client_id: String,
         client_secret: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token_endpoint: Option<String>,
     },
     Basic {
         username: String,
@@ -70,15 +284,150 @@ pub enum AuthMethod {
     },
 }
 
+/// A connection behind its own lock, so slow work on one doesn't block the others
+type SharedConnection = Arc<RwLock<MCPServerConnection>>;
+
 #[derive(Debug)]
 pub struct MCPServerConnection {
     pub config: MCPServerConfig,
+    /// Set for every connection of the service but its default one
+    pub connection_id: Option<String>,
     pub process: Option<Child>,
-    pub container_id: Option<String>, // For Docker deployments
-    pub endpoint: Option<String>,     // For HTTP/WebSocket connections
+    pub stdio_client: Option<Arc<StdioMcpClient>>, // Requests to the process, multiplexed
+    pub container_id: Option<String>,              // For Docker deployments
+    pub endpoint: Option<String>,                  // For HTTP/WebSocket connections
+    pub http_client: Option<Arc<HttpMcpClient>>,   // For HTTP connections
+    pub websocket_client: Option<Arc<WebSocketMcpClient>>, // For WebSocket connections
+    pub lambda_client: Option<Arc<LambdaMcpClient>>, // For Lambda deployments
+    pub server_info: Option<Value>,                // initialize result, once negotiated
     pub status: ConnectionStatus,
     pub last_health_check: std::time::Instant,
     pub tools: Vec<MCPTool>,
+    /// Why the connection last failed
+    pub last_error: Option<String>,
+    /// Reconnects attempted since the connection last stayed up for the restart
+    /// policy's `reset_after`, or was connected by hand
+    pub reconnect_attempts: u32,
+    /// Errors of the failures counted by `reconnect_attempts`, oldest first
+    pub restart_errors: Vec<String>,
+    /// When the connection last came up
+    pub connected_at: Option<Instant>,
+    /// Earliest time the health monitor may try reconnecting again
+    pub next_reconnect_at: Option<Instant>,
+    /// `State.Status` from the last `docker inspect` of the container
+    pub container_status: Option<String>,
+    /// What the server wrote: a process's stderr as it is written, a container's
+    /// output as of the last `docker logs`
+    pub logs: LogBuffer,
+}
+
+impl MCPServerConnection {
+    fn new(config: MCPServerConfig) -> Self {
+        Self {
+            config,
+            connection_id: None,
+            process: None,
+            stdio_client: None,
+            container_id: None,
+            endpoint: None,
+            http_client: None,
+            websocket_client: None,
+            lambda_client: None,
+            server_info: None,
+            status: ConnectionStatus::Disconnected,
+            last_health_check: std::time::Instant::now(),
+            tools: Vec::new(),
+            last_error: None,
+            reconnect_attempts: 0,
+            restart_errors: Vec::new(),
+            connected_at: None,
+            next_reconnect_at: None,
+            container_status: None,
+            logs: LogBuffer::default(),
+        }
+    }
+
+    /// Mark the connection failed and schedule the next reconnect per `policy`; once
+    /// `max_attempts` reconnects have failed the status carries every error seen and
+    /// no more are scheduled
+    fn mark_failed(&mut self, error: String, policy: &ReconnectPolicy) {
+        self.restart_errors.push(error.clone());
+        self.connected_at = None;
+        if self.config.auto_reconnect && self.reconnect_attempts >= policy.max_attempts {
+            self.status = ConnectionStatus::Failed(format!(
+                "Gave up after {} restarts: {}",
+                self.reconnect_attempts,
+                self.restart_errors.join("; ")
+            ));
+            self.next_reconnect_at = None;
+        } else {
+            self.status = ConnectionStatus::Failed(error.clone());
+            self.next_reconnect_at = Some(Instant::now() + policy.backoff(self.reconnect_attempts));
+        }
+        self.last_error = Some(error);
+    }
+
+    /// Record a passed health check; the restart count is cleared once the connection
+    /// has been up for `reset_after`
+    fn mark_healthy(&mut self, reset_after: Duration) {
+        self.last_health_check = Instant::now();
+        if self
+            .connected_at
+            .is_some_and(|at| at.elapsed() >= reset_after)
+        {
+            self.reset_restarts();
+        }
+    }
+
+    /// Mark the connection down until the user authorizes again; nothing is scheduled
+    fn mark_needs_reauth(&mut self, error: String) {
+        self.status = ConnectionStatus::NeedsReauth;
+        self.connected_at = None;
+        self.next_reconnect_at = None;
+        self.last_error = Some(error);
+    }
+
+    fn reset_restarts(&mut self) {
+        self.reconnect_attempts = 0;
+        self.restart_errors.clear();
+        self.next_reconnect_at = None;
+    }
+
+    /// How the registry addresses this connection; see [`instance_id`]
+    pub fn instance_id(&self) -> String {
+        instance_id(&self.config.id, self.connection_id.as_deref())
+    }
+
+    /// Current tools; a WebSocket server's list is kept up to date by its client
+    pub fn tools(&self) -> Vec<MCPTool> {
+        match &self.websocket_client {
+            Some(client) => client.tools(),
+            None => self.tools.clone(),
+        }
+    }
+
+    fn info(&self) -> MCPServerInfo {
+        MCPServerInfo {
+            id: self.config.id.clone(),
+            connection_id: self.connection_id.clone(),
+            name: self.config.name.clone(),
+            description: self.config.description.clone(),
+            status: format!("{:?}", self.status),
+            tool_count: self.tools().len(),
+            last_error: self.last_error.clone(),
+            reconnect_attempts: self.reconnect_attempts,
+            max_restarts: 0,
+            container_status: self.container_status.clone(),
+            connections: Vec::new(),
+            metrics: ServerMetricsReport::default(),
+        }
+    }
+}
+
+/// A freshly connected HTTP or WebSocket client
+enum RemoteClient {
+    Http(Arc<HttpMcpClient>, Vec<MCPTool>),
+    WebSocket(Arc<WebSocketMcpClient>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,27 +437,244 @@ pub struct MCPTool {
     pub input_schema: Value,
 }
 
+/// How the health monitor reconnects failed servers that have `auto_reconnect`
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Attempts before giving up until the server is next connected by hand
+    pub max_attempts: u32,
+    /// Delay before the first attempt; doubles after every attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How long a reconnected server must stay up before its attempts are forgotten
+    pub reset_after: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(300),
+            reset_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before the attempt following `attempts` earlier ones
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempts))
+            .min(self.max_backoff)
+    }
+}
+
+/// A server's own [`ReconnectPolicy`], as stored in its config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    pub backoff_initial_ms: u64,
+    pub backoff_max_ms: u64,
+    pub reset_after_secs: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        let policy = ReconnectPolicy::default();
+        Self {
+            max_restarts: policy.max_attempts,
+            backoff_initial_ms: policy.initial_backoff.as_millis() as u64,
+            backoff_max_ms: policy.max_backoff.as_millis() as u64,
+            reset_after_secs: policy.reset_after.as_secs(),
+        }
+    }
+}
+
+impl From<&RestartPolicy> for ReconnectPolicy {
+    fn from(policy: &RestartPolicy) -> Self {
+        Self {
+            max_attempts: policy.max_restarts,
+            initial_backoff: Duration::from_millis(policy.backoff_initial_ms),
+            max_backoff: Duration::from_millis(policy.backoff_max_ms),
+            reset_after: Duration::from_secs(policy.reset_after_secs),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ConnectionStatus {
     Disconnected,
+    /// Downloading the server's Docker image before starting it
+    Pulling,
     Connecting,
     Connected,
     Failed(String),
+    /// The OAuth2 refresh token was rejected; not reconnected until new tokens are stored
+    NeedsReauth,
 }
 
 pub struct MCPServerRegistry {
-    servers: Arc<RwLock<HashMap<String, MCPServerConnection>>>,
-    aws_service: Arc<AwsService>,
+    /// The map lock is only held to look up, add or remove entries, never across an await
+    servers: Arc<RwLock<HashMap<String, SharedConnection>>>,
+    /// Persisted server configs
+    store: Arc<dyn DirectKvStore>,
+    /// Server credentials
+    secrets: Arc<dyn SecretStore>,
+    /// Tenants whose persisted configs have been loaded into `servers`
+    loaded_tenants: RwLock<HashSet<String>>,
+    lambda_invoker: Arc<dyn LambdaInvoker>,
+    docker: Arc<dyn DockerCli>,
+    docker_policy: DockerPolicy,
+    container_start_timeout: Duration,
+    shutdown_grace: Duration,
+    /// Set by the first `shutdown`
+    shut_down: AtomicBool,
+    /// Pull Docker images when servers are registered rather than on first connect
+    prepull_images: bool,
+    reconnect_policy: ReconnectPolicy,
+    /// Servers providing each tool, refreshed whenever a server's tools are fetched
+    tool_index: Arc<RwLock<ToolIndex>>,
+    /// Call and uptime counters, keyed like `servers` but kept across reconnects
+    metrics: RwLock<HashMap<String, ServerMetrics>>,
+    /// Held while a server's config is updated, so updates of one server don't interleave
+    update_locks: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    /// When the OAuth2 access token each connection was last given expires, so calls
+    /// only read the secret store once a refresh is due
+    token_expiry: RwLock<HashMap<String, DateTime<Utc>>>,
 }
 
 impl MCPServerRegistry {
-    pub fn new(aws_service: Arc<AwsService>) -> Self {
+    /// A registry with the default settings
+    #[allow(dead_code)]
+    pub fn new(aws_service: Arc<dyn AwsApi>) -> Self {
+        Self::with_config(aws_service, &RegistryConfig::default())
+    }
+
+    pub fn with_config(aws_service: Arc<dyn AwsApi>, config: &RegistryConfig) -> Self {
+        let lambda_invoker: Arc<dyn LambdaInvoker> = match aws_service.lambda_client() {
+            Some(client) => Arc::new(SdkLambdaInvoker::new(client)),
+            None => Arc::new(UnavailableLambdaInvoker),
+        };
+        let secrets: Arc<dyn SecretStore> = match config.secrets_backend {
+            SecretsBackend::Kv => {
+                warn!(
+                    "Storing MCP server credentials in the KV table ({}=kv)",
+                    SECRETS_BACKEND_ENV
+                );
+                Arc::new(KvSecretStore::new(aws_service.clone()))
+            }
+            SecretsBackend::SecretsManager => {
+                Arc::new(SecretsManagerStore::new(aws_service.clone()))
+            }
+        };
         Self {
             servers: Arc::new(RwLock::new(HashMap::new())),
-            aws_service,
+            store: aws_service,
+            secrets,
+            loaded_tenants: RwLock::new(HashSet::new()),
+            lambda_invoker,
+            docker: Arc::new(SystemDocker),
+            docker_policy: config.docker_policy.clone(),
+            container_start_timeout: CONTAINER_START_TIMEOUT,
+            shutdown_grace: SHUTDOWN_GRACE,
+            shut_down: AtomicBool::new(false),
+            prepull_images: config.docker_prepull,
+            reconnect_policy: ReconnectPolicy::default(),
+            tool_index: Arc::new(RwLock::new(ToolIndex::default())),
+            metrics: RwLock::new(HashMap::new()),
+            update_locks: std::sync::Mutex::new(HashMap::new()),
+            token_expiry: RwLock::new(HashMap::new()),
         }
     }
 
+    #[allow(dead_code)]
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// The server's own restart policy, or the registry's
+    fn restart_policy(&self, config: &MCPServerConfig) -> ReconnectPolicy {
+        config
+            .restart_policy
+            .as_ref()
+            .map(ReconnectPolicy::from)
+            .unwrap_or_else(|| self.reconnect_policy.clone())
+    }
+
+    /// Run `health_check` every `tick` until the registry is dropped
+    pub fn spawn_health_monitor(self: &Arc<Self>, tick: Duration) -> JoinHandle<()> {
+        let registry = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tick);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                match registry.upgrade() {
+                    Some(registry) => registry.health_check().await,
+                    None => return,
+                }
+            }
+        })
+    }
+
+    /// Replace where server configs are persisted (e.g. with an in-memory store in tests)
+    #[allow(dead_code)]
+    pub fn with_store(mut self, store: Arc<dyn DirectKvStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Replace where server credentials are kept (e.g. with an in-memory store in tests)
+    #[allow(dead_code)]
+    pub fn with_secret_store(mut self, secrets: Arc<dyn SecretStore>) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Replace how Lambda-deployed servers are invoked (e.g. with a stub in tests)
+    #[allow(dead_code)]
+    pub fn with_lambda_invoker(mut self, invoker: Arc<dyn LambdaInvoker>) -> Self {
+        self.lambda_invoker = invoker;
+        self
+    }
+
+    /// Replace how `docker` is run (e.g. with a stub in tests)
+    #[allow(dead_code)]
+    pub fn with_docker(mut self, docker: Arc<dyn DockerCli>) -> Self {
+        self.docker = docker;
+        self
+    }
+
+    /// Replace what Docker deployments may mount and configure
+    #[allow(dead_code)]
+    pub fn with_docker_policy(mut self, policy: DockerPolicy) -> Self {
+        self.docker_policy = policy;
+        self
+    }
+
+    /// Replace how long a started container has to reach `running`
+    #[allow(dead_code)]
+    pub fn with_container_start_timeout(mut self, timeout: Duration) -> Self {
+        self.container_start_timeout = timeout;
+        self
+    }
+
+    /// Replace how long `shutdown` waits for processes and containers to exit
+    #[allow(dead_code)]
+    pub fn with_shutdown_grace(mut self, grace: Duration) -> Self {
+        self.shutdown_grace = grace;
+        self
+    }
+
+    /// Enable or disable pulling Docker images at registration
+    #[allow(dead_code)]
+    pub fn with_image_prepull(mut self, enabled: bool) -> Self {
+        self.prepull_images = enabled;
+        self
+    }
+
     /// Register a server with context awareness (personal or organizational)
     #[allow(dead_code)]
     pub async fn register_server_for_context(
@@ -143,6 +709,124 @@ impl MCPServerRegistry {
         self.list_servers(&context_id).await
     }
 
+    /// Load a tenant's persisted server configs as disconnected servers, once per
+    /// tenant. Servers already registered in memory are kept; malformed records are
+    /// skipped with a warning. Returns the number of servers added.
+    pub async fn load_from_store(&self, tenant_id: &str) -> Result<usize, RegistryError> {
+        if self.loaded_tenants.read().await.contains(tenant_id) {
+            return Ok(0);
+        }
+
+        let prefix = internal_keys::registry_prefix(tenant_id);
+        let keys = self
+            .store
+            .kv_list(&prefix)
+            .await
+            .map_err(|e| RegistryError::StorageError(e.to_string()))?;
+
+        let mut configs = Vec::new();
+        for key in keys {
+            let value = match self.store.kv_get_direct(&key).await {
+                Ok(Some(value)) => value,
+                Ok(None) => continue,
+                Err(e) => return Err(RegistryError::StorageError(e.to_string())),
+            };
+            match serde_json::from_str::<MCPServerConfig>(&value) {
+                // A record naming another server than its key isn't trusted
+                Ok(config) if key == config_key(tenant_id, &config.id) => configs.push(config),
+                Ok(_) => {}
+                Err(e) => warn!("Skipping malformed server config {}: {}", key, e),
+            }
+        }
+
+        let mut loaded = 0;
+        let mut servers = self.servers.write().await;
+        for config in configs {
+            let key = connection_key(tenant_id, &config.id);
+            servers.entry(key).or_insert_with(|| {
+                loaded += 1;
+                Arc::new(RwLock::new(MCPServerConnection::new(config)))
+            });
+        }
+        drop(servers);
+
+        self.loaded_tenants
+            .write()
+            .await
+            .insert(tenant_id.to_string());
+        if loaded > 0 {
+            info!(
+                "Loaded {} stored MCP server configs for tenant {}",
+                loaded, tenant_id
+            );
+        }
+        Ok(loaded)
+    }
+
+    /// Load a tenant's stored configs before first use; a store outage is retried on
+    /// the next call rather than failing the caller
+    async fn ensure_loaded(&self, tenant_id: &str) {
+        if let Err(e) = self.load_from_store(tenant_id).await {
+            warn!(
+                "Failed to load stored MCP servers for tenant {}: {}",
+                tenant_id, e
+            );
+        }
+    }
+
+    /// Remove a server entirely: disconnect it if connected, delete its stored config
+    /// and credentials, and forget the connection
+    pub async fn unregister_server(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<(), RegistryError> {
+        self.ensure_loaded(tenant_id).await;
+
+        let key = connection_key(tenant_id, server_id);
+        if !self.servers.read().await.contains_key(&key) {
+            return Err(RegistryError::ServerNotFound(server_id.to_string()));
+        }
+
+        // Named connections share the service's config, so they go with it
+        let mut named = Vec::new();
+        for (instance_key, connection) in self.tenant_connections(tenant_id).await {
+            let connection = connection.read().await;
+            if connection.config.id == server_id
+                && connection.connection_id.is_some()
+                && instance_key == connection_key(tenant_id, &connection.instance_id())
+            {
+                named.push(connection.instance_id());
+            }
+        }
+        for instance in named {
+            self.disconnect_server(tenant_id, &instance).await?;
+            self.delete_credentials(tenant_id, &instance).await?;
+            self.metrics
+                .write()
+                .await
+                .remove(&connection_key(tenant_id, &instance));
+            self.token_expiry
+                .write()
+                .await
+                .remove(&connection_key(tenant_id, &instance));
+        }
+
+        self.disconnect_server(tenant_id, server_id).await?;
+        self.delete_server_config(tenant_id, server_id).await?;
+        self.delete_credentials(tenant_id, server_id).await?;
+        self.servers.write().await.remove(&key);
+        self.metrics.write().await.remove(&key);
+        self.update_locks.lock().unwrap().remove(&key);
+        self.token_expiry.write().await.remove(&key);
+
+        info!(
+            "Unregistered MCP server: {} for tenant: {}",
+            server_id, tenant_id
+        );
+        Ok(())
+    }
+
     pub async fn register_server(
         &self,
         tenant_id: &str,
@@ -152,176 +836,312 @@ impl MCPServerRegistry {
             "Registering MCP server: {} for tenant: {}",
             config.id, tenant_id
         );
+        validate_server_id(&config.id)?;
+        self.docker_policy.validate_config(&config)?;
 
         // Store configuration in DynamoDB
         self.store_server_config(tenant_id, &config).await?;
 
+        if self.prepull_images {
+            if let Some(image) = docker::image_ref(&config.deployment) {
+                let docker = self.docker.clone();
+                tokio::spawn(async move {
+                    let pulled = match image_present(&*docker, &image).await {
+                        Ok(true) => Ok(()),
+                        Ok(false) => pull_image(&*docker, &image).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = pulled {
+                        warn!("Pre-pulling Docker image {} failed: {}", image, e);
+                    }
+                });
+            }
+        }
+
         // Initialize connection
-        let connection = MCPServerConnection {
-            config: config.clone(),
-            process: None,
-            container_id: None,
-            endpoint: None,
-            status: ConnectionStatus::Disconnected,
-            last_health_check: std::time::Instant::now(),
-            tools: Vec::new(),
-        };
+        let connection = MCPServerConnection::new(config.clone());
 
-        let mut servers = self.servers.write().await;
-        let key = format!("{}-{}", tenant_id, config.id);
-        servers.insert(key, connection);
+        let key = connection_key(tenant_id, &config.id);
+        self.servers
+            .write()
+            .await
+            .insert(key, Arc::new(RwLock::new(connection)));
 
         Ok(())
     }
 
-    pub async fn connect_server(
+    /// Every registered server of a tenant, with secrets replaced by placeholders.
+    /// Named connections share their service's config, so only services are exported.
+    pub async fn export_servers(
         &self,
         tenant_id: &str,
-        server_id: &str,
-        credentials: Option<HashMap<String, String>>,
-    ) -> Result<(), RegistryError> {
-        let key = format!("{}-{}", tenant_id, server_id);
-
-        let mut servers = self.servers.write().await;
-        let connection = servers
-            .get_mut(&key)
-            .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
-
-        if connection.config.server_type != MCPServerType::Stdio {
-            return Err(RegistryError::UnsupportedServerType(format!(
-                "{:?}",
-                connection.config.server_type
-            )));
-        }
-
-        info!("Connecting to MCP server: {}", server_id);
-        connection.status = ConnectionStatus::Connecting;
-
-        // Build environment variables
-        let mut env_vars = connection.config.env.clone();
-
-        // Inject credentials if provided
-        if let Some(creds) = credentials {
-            for (key, value) in creds {
-                env_vars.insert(key, value);
+    ) -> Result<IntegrationExport, RegistryError> {
+        self.ensure_loaded(tenant_id).await;
+        let mut servers: Vec<ExportedServer> = Vec::new();
+        for (key, connection) in self.tenant_connections(tenant_id).await {
+            let connection = connection.read().await;
+            if connection.connection_id.is_none()
+                && key == connection_key(tenant_id, &connection.config.id)
+            {
+                servers.push(export::redact(&connection.config));
             }
         }
+        servers.sort_by(|a, b| a.config.id.cmp(&b.config.id));
+        Ok(IntegrationExport {
+            version: export::EXPORT_VERSION,
+            exported_at: Utc::now(),
+            servers,
+        })
+    }
 
-        // Inject auth credentials based on auth method
-        match &connection.config.auth_method {
-            AuthMethod::ApiKey { key_field } => {
-                if let Some(api_key) = self.get_credential(tenant_id, server_id, "api_key").await? {
-                    env_vars.insert(key_field.clone(), api_key);
+    /// Register the servers of an export. Ids already registered are left alone or,
+    /// with `OnConflict::Overwrite`, disconnected and replaced. The report lists the
+    /// credentials each imported server still needs.
+    pub async fn import_servers(
+        &self,
+        tenant_id: &str,
+        export: IntegrationExport,
+        on_conflict: OnConflict,
+    ) -> Result<ImportReport, RegistryError> {
+        export::check_version(&export)?;
+        // Validate everything first, so a bad entry doesn't leave a partial import
+        for server in &export.servers {
+            validate_server_id(&server.config.id)?;
+            self.docker_policy.validate_config(&server.config)?;
+        }
+        self.ensure_loaded(tenant_id).await;
+
+        let mut report = ImportReport::default();
+        for server in export.servers {
+            let server_id = server.config.id.clone();
+            let exists = self.has_connection(tenant_id, &server_id).await;
+            match (exists, on_conflict) {
+                (true, OnConflict::Skip) => {
+                    report.skipped.push(server_id);
+                    continue;
                 }
-            }
-            AuthMethod::OAuth2 {
-                client_id: _,
-                client_secret: _,
-            } => {
-                if let Some(stored_client_id) = self
-                    .get_credential(tenant_id, server_id, "client_id")
-                    .await?
-                {
-                    env_vars.insert("CLIENT_ID".to_string(), stored_client_id);
+                (true, OnConflict::Overwrite) => {
+                    self.disconnect_server(tenant_id, &server_id).await?;
+                    report.overwritten.push(server_id.clone());
                 }
-                if let Some(stored_client_secret) = self
-                    .get_credential(tenant_id, server_id, "client_secret")
+                (false, _) => report.imported.push(server_id.clone()),
+            }
+            self.register_server(tenant_id, server.config).await?;
+
+            let mut missing = Vec::new();
+            for credential in server.credentials {
+                if self
+                    .get_credential(tenant_id, &server_id, &credential)
                     .await?
+                    .is_none()
                 {
-                    env_vars.insert("CLIENT_SECRET".to_string(), stored_client_secret);
+                    missing.push(credential);
                 }
             }
-            AuthMethod::Basic { username, password } => {
-                env_vars.insert("USERNAME".to_string(), username.clone());
-                env_vars.insert("PASSWORD".to_string(), password.clone());
+            if !missing.is_empty() {
+                report.missing_credentials.push(MissingCredentials {
+                    server_id,
+                    credentials: missing,
+                });
             }
-            AuthMethod::None => {}
         }
 
-        // Start the MCP server based on deployment type
-        match &connection.config.deployment {
-            DeploymentConfig::Docker {
-                image,
-                tag,
-                ports,
-                volumes,
-                network,
-                runtime,
-            } => {
-                info!("Starting Docker container for MCP server: {}", server_id);
-
-                let container_name = format!("mcp-{}-{}", tenant_id, server_id);
-                let mut docker_cmd = Command::new("docker");
-
-                docker_cmd
-                    .arg("run")
-                    .arg("-d") // Detached mode
-                    .arg("--name")
-                    .arg(&container_name)
-                    .arg("--rm"); // Remove container when stopped
+        info!(
+            "Imported MCP servers for tenant {}: {} new, {} overwritten, {} skipped",
+            tenant_id,
+            report.imported.len(),
+            report.overwritten.len(),
+            report.skipped.len()
+        );
+        Ok(report)
+    }
 
-                // Add runtime if specified (e.g., nvidia for GPU)
-                if let Some(runtime) = runtime {
-                    docker_cmd.arg("--runtime").arg(runtime);
-                }
+    /// Change a registered server's stored config. Connected instances of it take
+    /// settings such as the health-check interval in place; changes to its env, image
+    /// or command restart them. Updates of one server are applied one at a time.
+    pub async fn update_server(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        update: MCPServerUpdate,
+    ) -> Result<UpdateOutcome, RegistryError> {
+        if server_id.contains('@') {
+            return Err(RegistryError::InvalidConfig {
+                field: "service_id".to_string(),
+                message: "connections share their service's config; update the service".to_string(),
+            });
+        }
+        self.ensure_loaded(tenant_id).await;
+
+        let key = connection_key(tenant_id, server_id);
+        let lock = self
+            .update_locks
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().await;
+
+        let mut config = match self.connection(&key).await {
+            Some(connection) => connection.read().await.config.clone(),
+            None => return Err(RegistryError::ServerNotFound(server_id.to_string())),
+        };
+        update.apply(&mut config)?;
+        self.docker_policy.validate_config(&config)?;
+        self.store_server_config(tenant_id, &config).await?;
 
-                // Add network if specified
-                if let Some(net) = network {
-                    docker_cmd.arg("--network").arg(net);
-                }
+        // Every instance of the service runs the same config
+        let mut connected = Vec::new();
+        for (instance_key, connection) in self.tenant_connections(tenant_id).await {
+            let mut connection = connection.write().await;
+            let instance = connection.instance_id();
+            if connection.config.id != server_id
+                || instance_key != connection_key(tenant_id, &instance)
+            {
+                continue;
+            }
+            connection.config = config.clone();
+            if connection.status == ConnectionStatus::Connected {
+                connected.push(instance);
+            }
+        }
+        info!(
+            "Updated MCP server {} for tenant {} ({} connected instance(s))",
+            server_id,
+            tenant_id,
+            connected.len()
+        );
 
-                // Add port mappings
-                for port in ports {
-                    docker_cmd.arg("-p").arg(port);
-                }
+        if connected.is_empty() {
+            return Ok(UpdateOutcome::Stored);
+        }
+        if !update.requires_restart() {
+            return Ok(UpdateOutcome::AppliedInPlace);
+        }
+        for instance in connected {
+            info!("Restarting {} to apply its new config", instance);
+            self.disconnect_server(tenant_id, &instance).await?;
+            self.connect_server(tenant_id, &instance, None).await?;
+        }
+        Ok(UpdateOutcome::Reconnected)
+    }
 
-                // Add volume mounts
-                for volume in volumes {
-                    docker_cmd.arg("-v").arg(volume);
-                }
+    /// Connect a server by hand, which also clears its restart count
+    pub async fn connect_server(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        credentials: Option<HashMap<String, String>>,
+    ) -> Result<(), RegistryError> {
+        self.update_connection(&connection_key(tenant_id, server_id), |connection| {
+            connection.reset_restarts()
+        })
+        .await;
+        self.connect_tracked(tenant_id, server_id, credentials)
+            .await
+    }
 
-                // Add environment variables
-                for (key, value) in &env_vars {
-                    docker_cmd.arg("-e").arg(format!("{}={}", key, value));
+    /// Connect, recording the outcome in the metrics and the restart state
+    async fn connect_tracked(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        credentials: Option<HashMap<String, String>>,
+    ) -> Result<(), RegistryError> {
+        let result = self
+            .open_connection(tenant_id, server_id, credentials)
+            .await;
+        if !matches!(
+            result,
+            Err(RegistryError::ServerNotFound(_) | RegistryError::InvalidConfig { .. })
+        ) {
+            let key = connection_key(tenant_id, server_id);
+            self.update_connection(&key, |connection| match &result {
+                Ok(()) => connection.connected_at = Some(Instant::now()),
+                Err(e @ RegistryError::ReauthRequired(_)) => {
+                    connection.mark_needs_reauth(e.to_string())
+                }
+                Err(e) => {
+                    let policy = self.restart_policy(&connection.config);
+                    connection.mark_failed(e.to_string(), &policy);
                 }
+            })
+            .await;
+            let mut metrics = self.metrics.write().await;
+            let metrics = metrics
+                .entry(connection_key(tenant_id, server_id))
+                .or_default();
+            match &result {
+                Ok(()) => metrics.connected(),
+                Err(e) => metrics.disconnected(Some(e.to_string())),
+            }
+        }
+        result
+    }
+
+    async fn open_connection(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        credentials: Option<HashMap<String, String>>,
+    ) -> Result<(), RegistryError> {
+        self.ensure_loaded(tenant_id).await;
+        self.ensure_connection(tenant_id, server_id).await?;
+        let key = connection_key(tenant_id, server_id);
+
+        let shared = self
+            .connection(&key)
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
+        let config = shared.read().await.config.clone();
+
+        if config.server_type != MCPServerType::Stdio {
+            return self
+                .connect_remote_server(tenant_id, server_id, &key, config, credentials)
+                .await;
+        }
 
-                // Image and tag
-                docker_cmd.arg(format!("{}:{}", image, tag));
+        info!("Connecting to MCP server: {}", server_id);
+        self.set_status(&key, ConnectionStatus::Connecting).await;
+
+        let env_vars = self
+            .server_env(tenant_id, server_id, &config, credentials)
+            .await?;
+
+        // Start the MCP server based on deployment type
+        match &config.deployment {
+            DeploymentConfig::Docker { ports, .. } => {
+                info!("Starting Docker container for MCP server: {}", server_id);
+                let endpoint = (!ports.is_empty()).then(|| published_endpoint("http", ports));
 
-                match docker_cmd.output().await {
-                    Ok(output) => {
-                        if output.status.success() {
-                            let container_id =
-                                String::from_utf8_lossy(&output.stdout).trim().to_string();
+                match self
+                    .start_container(tenant_id, server_id, &config.deployment, &env_vars)
+                    .await
+                {
+                    Ok(container_id) => {
+                        {
+                            let mut connection = shared.write().await;
                             connection.container_id = Some(container_id.clone());
                             connection.status = ConnectionStatus::Connected;
-
                             // Set endpoint for HTTP/WebSocket connections
-                            if !ports.is_empty() {
-                                let port = ports[0].split(':').nth(0).unwrap_or("8080");
-                                connection.endpoint = Some(format!("http://localhost:{}", port));
-                            }
+                            connection.endpoint = endpoint;
+                        }
 
-                            info!("Docker container started: {}", container_id);
+                        info!("Docker container started: {}", container_id);
 
-                            // Initialize the connection
-                            self.initialize_mcp_connection(&key).await?;
+                        // Initialize the connection
+                        self.initialize_mcp_connection(&key).await?;
 
-                            // Fetch available tools
-                            self.fetch_server_tools(&key).await?;
+                        // Fetch available tools
+                        self.fetch_server_tools(tenant_id, server_id).await?;
 
-                            Ok(())
-                        } else {
-                            let error = String::from_utf8_lossy(&output.stderr);
-                            error!("Failed to start Docker container: {}", error);
-                            connection.status = ConnectionStatus::Failed(error.to_string());
-                            Err(RegistryError::ConnectionFailed(error.to_string()))
-                        }
+                        Ok(())
                     }
                     Err(e) => {
-                        error!("Failed to execute Docker command: {}", e);
-                        connection.status = ConnectionStatus::Failed(e.to_string());
-                        Err(RegistryError::ConnectionFailed(e.to_string()))
+                        self.set_status(&key, ConnectionStatus::Failed(e.to_string()))
+                            .await;
+                        Err(e)
                     }
                 }
             }
@@ -339,22 +1159,50 @@ impl MCPServerRegistry {
                 }
 
                 match cmd.spawn() {
-                    Ok(child) => {
-                        connection.process = Some(child);
-                        connection.status = ConnectionStatus::Connected;
-
-                        // Initialize the connection
-                        self.initialize_mcp_connection(&key).await?;
+                    Ok(mut child) => {
+                        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take())
+                        else {
+                            let _ = child.start_kill();
+                            let error = "process stdio was not captured".to_string();
+                            self.set_status(&key, ConnectionStatus::Failed(error.clone()))
+                                .await;
+                            return Err(RegistryError::ConnectionFailed(error));
+                        };
+                        {
+                            let mut connection = shared.write().await;
+                            if let Some(stderr) = child.stderr.take() {
+                                connection
+                                    .logs
+                                    .spawn_reader(server_id, stderr, LogStream::Stderr);
+                            }
+                            connection.stdio_client = Some(Arc::new(StdioMcpClient::new(
+                                server_id,
+                                stdin,
+                                stdout,
+                                STDIO_REQUEST_TIMEOUT,
+                            )));
+                            connection.process = Some(child);
+                            connection.status = ConnectionStatus::Connected;
+                        }
 
-                        // Fetch available tools
-                        self.fetch_server_tools(&key).await?;
+                        let handshake = async {
+                            self.initialize_mcp_connection(&key).await?;
+                            self.fetch_server_tools(tenant_id, server_id).await
+                        };
+                        if let Err(e) = handshake.await {
+                            error!("MCP handshake with {} failed: {}", server_id, e);
+                            self.set_status(&key, ConnectionStatus::Failed(e.to_string()))
+                                .await;
+                            return Err(e);
+                        }
 
                         info!("Successfully connected to MCP server: {}", server_id);
                         Ok(())
                     }
                     Err(e) => {
                         error!("Failed to spawn MCP server process: {}", e);
-                        connection.status = ConnectionStatus::Failed(e.to_string());
+                        self.set_status(&key, ConnectionStatus::Failed(e.to_string()))
+                            .await;
                         Err(RegistryError::ConnectionFailed(e.to_string()))
                     }
                 }
@@ -368,32 +1216,143 @@ impl MCPServerRegistry {
                     function_name, region
                 );
 
-                // For Lambda, we just store the endpoint
-                connection.endpoint = Some(format!("lambda://{}:{}", region, function_name));
-                connection.status = ConnectionStatus::Connected;
-
-                // Initialize the connection
-                self.initialize_mcp_connection(&key).await?;
-
-                // Fetch available tools
-                self.fetch_server_tools(&key).await?;
+                // Each request is one invocation in the server's own region; there is
+                // no session to initialize
+                let client = Arc::new(LambdaMcpClient::new(
+                    self.lambda_invoker.clone(),
+                    function_name,
+                    region,
+                ));
+                {
+                    let mut connection = shared.write().await;
+                    connection.endpoint = Some(client.endpoint());
+                    connection.lambda_client = Some(client);
+                }
 
-                Ok(())
+                match self.fetch_server_tools(tenant_id, server_id).await {
+                    Ok(()) => {
+                        self.set_status(&key, ConnectionStatus::Connected).await;
+                        Ok(())
+                    }
+                    Err(e) => {
+                        error!("Failed to list tools of Lambda {}: {}", server_id, e);
+                        self.set_status(&key, ConnectionStatus::Failed(e.to_string()))
+                            .await;
+                        Err(e)
+                    }
+                }
             }
         }
     }
 
+    /// Add the entry of a named connection, from its service's config, on first connect
+    async fn ensure_connection(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<(), RegistryError> {
+        let (service_id, Some(connection_id)) = split_instance_id(server_id) else {
+            return Ok(());
+        };
+        let key = connection_key(tenant_id, server_id);
+        if self.servers.read().await.contains_key(&key) {
+            return Ok(());
+        }
+        if connection_id == DEFAULT_CONNECTION {
+            return Err(RegistryError::InvalidConfig {
+                field: "connection_id".to_string(),
+                message: format!("the default connection of {} is {}", service_id, service_id),
+            });
+        }
+        validate_connection_id(connection_id)?;
+
+        let config = self
+            .connection(&connection_key(tenant_id, service_id))
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(service_id.to_string()))?
+            .read()
+            .await
+            .config
+            .clone();
+        let mut connection = MCPServerConnection::new(config);
+        connection.connection_id = Some(connection_id.to_string());
+        // Another connect may have added it meanwhile
+        self.servers
+            .write()
+            .await
+            .entry(key)
+            .or_insert_with(|| Arc::new(RwLock::new(connection)));
+        Ok(())
+    }
+
+    /// Whether `server_id` (or `service_id@connection_id`) is a known connection
+    pub async fn has_connection(&self, tenant_id: &str, server_id: &str) -> bool {
+        self.ensure_loaded(tenant_id).await;
+        self.servers
+            .read()
+            .await
+            .contains_key(&connection_key(tenant_id, server_id))
+    }
+
+    /// The input schema a connection listed for `tool_name`, if it is known
+    pub async fn tool_input_schema(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        tool_name: &str,
+    ) -> Option<Value> {
+        let connection = self
+            .connection(&connection_key(tenant_id, server_id))
+            .await?;
+        let connection = connection.read().await;
+        connection
+            .tools()
+            .into_iter()
+            .find(|tool| tool.name == tool_name)
+            .map(|tool| tool.input_schema)
+    }
+
+    /// Tear down a connection. A named connection is forgotten entirely; the default
+    /// one stays registered, disconnected.
     pub async fn disconnect_server(
         &self,
         tenant_id: &str,
         server_id: &str,
     ) -> Result<(), RegistryError> {
-        let key = format!("{}-{}", tenant_id, server_id);
+        let key = connection_key(tenant_id, server_id);
+
+        // Take the clients, process and container out under the lock; closing them
+        // happens after it is released
+        let mut closing = None;
+        if let Some(connection) = self.connection(&key).await {
+            let mut connection = connection.write().await;
+            let http_client = connection.http_client.take();
+            let websocket_client = connection.websocket_client.take();
+            connection.lambda_client = None;
+            connection.stdio_client = None;
+            connection.server_info = None;
+            let process = connection.process.take();
+            let container = connection.container_id.take().is_some();
+            connection.container_status = None;
+            connection.status = ConnectionStatus::Disconnected;
+            connection.endpoint = None;
+            connection.tools.clear();
+            closing = Some((http_client, websocket_client, process, container));
+        }
+        if split_instance_id(server_id).1.is_some() {
+            self.servers.write().await.remove(&key);
+        }
+
+        if let Some((http_client, websocket_client, process, container)) = closing {
+            if let Some(client) = http_client {
+                client.close().await;
+            }
+            if let Some(client) = websocket_client {
+                client.close().await;
+            }
 
-        let mut servers = self.servers.write().await;
-        if let Some(connection) = servers.get_mut(&key) {
             // Handle process termination
-            if let Some(mut process) = connection.process.take() {
+            if let Some(mut process) = process {
                 match process.kill().await {
                     Ok(_) => info!("MCP server process {} terminated", server_id),
                     Err(e) => warn!("Failed to kill MCP server process: {}", e),
@@ -401,237 +1360,1542 @@ impl MCPServerRegistry {
             }
 
             // Handle Docker container termination
-            if let Some(_container_id) = &connection.container_id {
-                let container_name = format!("mcp-{}-{}", tenant_id, server_id);
-                let mut docker_cmd = Command::new("docker");
-                docker_cmd.arg("stop").arg(&container_name);
-
-                match docker_cmd.output().await {
-                    Ok(output) => {
-                        if output.status.success() {
-                            info!("Docker container {} stopped", container_name);
-                        } else {
-                            warn!(
-                                "Failed to stop Docker container: {}",
-                                String::from_utf8_lossy(&output.stderr)
-                            );
-                        }
-                    }
-                    Err(e) => warn!("Failed to execute docker stop: {}", e),
-                }
-
-                connection.container_id = None;
+            if container {
+                let container_name = container_name(tenant_id, server_id);
+                self.remove_container(&container_name).await;
             }
-
-            connection.status = ConnectionStatus::Disconnected;
-            connection.endpoint = None;
-            connection.tools.clear();
         }
+        if let Some(metrics) = self.metrics.write().await.get_mut(&key) {
+            metrics.disconnected(None);
+        }
+        self.tool_index
+            .write()
+            .await
+            .remove_server(tenant_id, server_id);
 
         Ok(())
     }
 
-    pub async fn list_servers(&self, tenant_id: &str) -> Result<Vec<MCPServerInfo>, RegistryError> {
-        let servers = self.servers.read().await;
-        let mut result = Vec::new();
-
-        for (key, connection) in servers.iter() {
-            if key.starts_with(&format!("{}-", tenant_id)) {
-                result.push(MCPServerInfo {
-                    id: connection.config.id.clone(),
-                    name: connection.config.name.clone(),
-                    description: connection.config.description.clone(),
-                    status: format!("{:?}", connection.status),
-                    tool_count: connection.tools.len(),
-                });
-            }
+    /// Stop every server process and container, for when the main server exits.
+    /// Processes get EOF on stdin and are killed if still running after the grace
+    /// period; containers are `docker stop`ped and removed. Only the first call does
+    /// anything.
+    pub async fn shutdown(&self) {
+        if self.shut_down.swap(true, Ordering::SeqCst) {
+            return;
         }
 
-        Ok(result)
-    }
-
-    pub async fn execute_tool(
-        &self,
-        tenant_id: &str,
-        server_id: &str,
-        tool_name: &str,
-        arguments: Value,
-    ) -> Result<Value, RegistryError> {
-        let key = format!("{}-{}", tenant_id, server_id);
+        let mut stopping = Vec::new();
+        for (key, connection) in self.connections().await {
+            let mut connection = connection.write().await;
+            let http_client = connection.http_client.take();
+            let websocket_client = connection.websocket_client.take();
+            connection.lambda_client = None;
+            // Dropping the client closes the process's stdin
+            connection.stdio_client = None;
+            connection.server_info = None;
+            connection.status = ConnectionStatus::Disconnected;
 
-        let servers = self.servers.read().await;
-        let connection = servers
-            .get(&key)
-            .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
+            let instance_id = connection.instance_id();
+            let container = connection.container_id.take().and_then(|_| {
+                connection_tenant(&key, &instance_id)
+                    .map(|tenant_id| container_name(tenant_id, &instance_id))
+            });
+            let process = connection.process.take();
+            drop(connection);
 
-        if connection.status != ConnectionStatus::Connected {
-            return Err(RegistryError::ServerNotConnected(server_id.to_string()));
+            if let Some(client) = http_client {
+                client.close().await;
+            }
+            if let Some(client) = websocket_client {
+                client.close().await;
+            }
+            if process.is_some() || container.is_some() {
+                stopping.push((key, process, container));
+            }
         }
 
-        // Check if tool exists
-        let tool_exists = connection.tools.iter().any(|t| t.name == tool_name);
-        if !tool_exists {
-            return Err(RegistryError::ToolNotFound(tool_name.to_string()));
+        info!("Stopping {} MCP server(s)", stopping.len());
+        futures::future::join_all(stopping.into_iter().map(
+            |(key, process, container)| async move {
+                if let Some(process) = process {
+                    self.stop_process(&key, process).await;
+                }
+                if let Some(container_name) = container {
+                    self.stop_container(&container_name).await;
+                }
+            },
+        ))
+        .await;
+    }
+
+    async fn stop_process(&self, key: &str, mut process: Child) {
+        match tokio::time::timeout(self.shutdown_grace, process.wait()).await {
+            Ok(Ok(status)) => info!("MCP server {} exited with status: {}", key, status),
+            Ok(Err(e)) => warn!("Failed to wait for MCP server {}: {}", key, e),
+            Err(_) => {
+                warn!(
+                    "MCP server {} still running after {:?}; killing it",
+                    key, self.shutdown_grace
+                );
+                if let Err(e) = process.kill().await {
+                    warn!("Failed to kill MCP server process {}: {}", key, e);
+                }
+            }
         }
+    }
 
-        // Execute tool via stdio
-        if let Some(process) = &connection.process {
-            self.execute_stdio_tool(process, tool_name, arguments).await
-        } else {
-            Err(RegistryError::ServerNotConnected(server_id.to_string()))
+    async fn stop_container(&self, container_name: &str) {
+        let grace = self.shutdown_grace.as_secs().max(1).to_string();
+        match self
+            .docker
+            .run(vec![
+                "stop".to_string(),
+                "--time".to_string(),
+                grace,
+                container_name.to_string(),
+            ])
+            .await
+        {
+            Ok(output) if output.success => info!("Docker container {} stopped", container_name),
+            Ok(output) => warn!(
+                "docker stop {} failed: {}",
+                container_name,
+                output.stderr.trim()
+            ),
+            Err(e) => warn!("Failed to stop Docker container {}: {}", container_name, e),
         }
+        self.remove_container(container_name).await;
     }
 
-    async fn initialize_mcp_connection(&self, key: &str) -> Result<(), RegistryError> {
-        let servers = self.servers.read().await;
-        let connection = servers
-            .get(key)
-            .ok_or_else(|| RegistryError::ServerNotFound(key.to_string()))?;
+    pub async fn list_servers(&self, tenant_id: &str) -> Result<Vec<MCPServerInfo>, RegistryError> {
+        self.ensure_loaded(tenant_id).await;
+        let mut infos = Vec::new();
+        for (key, connection) in self.tenant_connections(tenant_id).await {
+            let connection = connection.read().await;
+            if key != connection_key(tenant_id, &connection.instance_id()) {
+                continue;
+            }
+            let mut info = connection.info();
+            info.max_restarts = self.restart_policy(&connection.config).max_attempts;
+            infos.push((key, info));
+        }
 
-        if let Some(_process) = &connection.process {
-            let _request = serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 1,
-                "method": "initialize",
-                "params": {
-                    "protocolVersion": "2025-06-18",
-                    "capabilities": {
-                        "tools": {}
-                    }
-                }
-            });
+        let metrics = self.metrics.read().await;
+        let mut result = Vec::new();
+        let mut named = Vec::new();
+        for (key, mut info) in infos {
+            info.metrics = metrics
+                .get(&key)
+                .map(ServerMetrics::report)
+                .unwrap_or_default();
+            match info.connection_id {
+                None => result.push(info),
+                Some(_) => named.push(info),
+            }
+        }
 
-            // Send initialization request via stdin
-            // Implementation would handle stdio communication
-            debug!("Sent initialization request to MCP server");
+        // Named connections are listed under their service
+        named.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+        for info in named {
+            if let Some(service) = result.iter_mut().find(|service| service.id == info.id) {
+                service.connections.push(info);
+            }
         }
 
-        Ok(())
+        Ok(result)
     }
 
-    async fn fetch_server_tools(&self, key: &str) -> Result<(), RegistryError> {
-        let mut servers = self.servers.write().await;
-        let connection = servers
-            .get_mut(key)
-            .ok_or_else(|| RegistryError::ServerNotFound(key.to_string()))?;
-
-        if let Some(_process) = &connection.process {
-            let _request = serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 2,
-                "method": "tools/list",
-                "params": {}
-            });
-
-            // TODO: Send tool list request and parse response
-            // This would be implemented with proper stdio handling per MCP spec
-            debug!("Fetching tools from MCP server");
-
-            // For now, return empty tools
-            connection.tools = Vec::new();
+    /// A tenant's connections, default and named, with the tools each listed when it
+    /// connected. Connections that are not connected have no tools.
+    pub async fn list_server_tools(
+        &self,
+        tenant_id: &str,
+    ) -> Result<Vec<(MCPServerInfo, Vec<MCPTool>)>, RegistryError> {
+        self.ensure_loaded(tenant_id).await;
+        let mut listed = Vec::new();
+        for (key, connection) in self.tenant_connections(tenant_id).await {
+            let connection = connection.read().await;
+            if key != connection_key(tenant_id, &connection.instance_id()) {
+                continue;
+            }
+            let tools = match connection.status {
+                ConnectionStatus::Connected => connection.tools(),
+                _ => Vec::new(),
+            };
+            listed.push((key, connection.info(), tools));
         }
 
-        Ok(())
+        let metrics = self.metrics.read().await;
+        let mut result: Vec<(MCPServerInfo, Vec<MCPTool>)> = listed
+            .into_iter()
+            .map(|(key, mut info, tools)| {
+                info.metrics = metrics
+                    .get(&key)
+                    .map(ServerMetrics::report)
+                    .unwrap_or_default();
+                (info, tools)
+            })
+            .collect();
+        result.sort_by(|a, b| (&a.0.id, &a.0.connection_id).cmp(&(&b.0.id, &b.0.connection_id)));
+
+        Ok(result)
     }
 
-    async fn execute_stdio_tool(
+    /// Call a tool on a connection within its server's `tool_timeout`, counting the
+    /// call in its metrics
+    #[allow(dead_code)]
+    pub async fn execute_tool(
         &self,
-        _process: &Child,
+        tenant_id: &str,
+        server_id: &str,
         tool_name: &str,
         arguments: Value,
     ) -> Result<Value, RegistryError> {
-        let _request = serde_json::json!({
-            "jsonrpc": "2.0",
-            "id": 3,
-            "method": "tools/call",
-            "params": {
-                "name": tool_name,
-                "arguments": arguments
-            }
-        });
-
-        // TODO: Send request via stdin and read response from stdout
-        // This would be implemented with proper stdio handling per MCP spec
-        debug!("Executing tool {} via stdio", tool_name);
-
-        Ok(serde_json::json!({
-            "success": true,
-            "result": "Tool execution placeholder"
-        }))
+        self.execute_tool_within(tenant_id, server_id, tool_name, arguments, None)
+            .await
     }
 
-    async fn store_server_config(
+    /// Call a tool, failing with `RegistryError::Timeout` after `timeout` (the server's
+    /// `tool_timeout` when `None`). A timed-out call is dropped from the client's pending
+    /// requests and cancelled downstream where the transport allows.
+    pub async fn execute_tool_within(
         &self,
         tenant_id: &str,
-        config: &MCPServerConfig,
-    ) -> Result<(), RegistryError> {
-        let key = format!("mcp-registry-{}-{}", tenant_id, config.id);
-        let value = serde_json::to_string(config)
-            .map_err(|e| RegistryError::SerializationError(e.to_string()))?;
+        server_id: &str,
+        tool_name: &str,
+        arguments: Value,
+        timeout: Option<Duration>,
+    ) -> Result<Value, RegistryError> {
+        let started = Instant::now();
+        let result = self
+            .dispatch_tool(tenant_id, server_id, tool_name, arguments, timeout)
+            .await;
+        if !matches!(result, Err(RegistryError::ServerNotFound(_))) {
+            self.metrics
+                .write()
+                .await
+                .entry(connection_key(tenant_id, server_id))
+                .or_default()
+                .record_call(
+                    started.elapsed(),
+                    result.as_ref().err().map(ToString::to_string),
+                );
+        }
+        result
+    }
 
-        self.aws_service
-            .kv_set_direct(&key, &value, Some(24 * 30)) // 30 days TTL
+    /// Number of connections in each status (`Connected`, `Failed`, ...), across tenants
+    pub async fn connection_status_counts(&self) -> BTreeMap<String, usize> {
+        let connections: Vec<SharedConnection> =
+            self.servers.read().await.values().cloned().collect();
+        let mut counts = BTreeMap::new();
+        for connection in connections {
+            let status = match &connection.read().await.status {
+                ConnectionStatus::Failed(_) => "Failed".to_string(),
+                status => format!("{:?}", status),
+            };
+            *counts.entry(status).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Every connection's metrics, by registry key (`{tenant}-{server_id}`), for
+    /// server-wide diagnostics
+    pub async fn metrics_report(&self) -> Vec<(String, ServerMetricsReport)> {
+        let mut report: Vec<(String, ServerMetricsReport)> = self
+            .metrics
+            .read()
             .await
-            .map_err(|e| RegistryError::StorageError(e.to_string()))?;
+            .iter()
+            .map(|(key, metrics)| (key.clone(), metrics.report()))
+            .collect();
+        report.sort_by(|a, b| a.0.cmp(&b.0));
+        report
+    }
 
-        Ok(())
+    /// State of every connection, across tenants, with its last `log_lines` captured
+    /// lines as of now (containers' logs aren't refreshed), by registry key
+    pub async fn connection_diagnostics(&self, log_lines: usize) -> Vec<ConnectionDiagnostics> {
+        let mut diagnostics = Vec::new();
+        for (key, connection) in self.connections().await {
+            let connection = connection.read().await;
+            diagnostics.push(ConnectionDiagnostics {
+                connection: key,
+                status: format!("{:?}", connection.status),
+                last_error: connection.last_error.clone(),
+                restart_errors: connection.restart_errors.clone(),
+                reconnect_attempts: connection.reconnect_attempts,
+                container_status: connection.container_status.clone(),
+                logs: connection.logs.tail(log_lines),
+            });
+        }
+        diagnostics.sort_by(|a, b| a.connection.cmp(&b.connection));
+        diagnostics
     }
 
-    async fn get_credential(
+    async fn dispatch_tool(
         &self,
         tenant_id: &str,
         server_id: &str,
-        credential_name: &str,
-    ) -> Result<Option<String>, RegistryError> {
-        let key = format!(
-            "mcp-credential-{}-{}-{}",
-            tenant_id, server_id, credential_name
-        );
-
-        match self.aws_service.kv_get_direct(&key).await {
-            Ok(value) => Ok(value),
-            Err(e) => {
-                debug!("No credential found for {}: {}", key, e);
-                Ok(None)
-            }
-        }
-    }
+        tool_name: &str,
+        arguments: Value,
+        timeout: Option<Duration>,
+    ) -> Result<Value, RegistryError> {
+        let key = connection_key(tenant_id, server_id);
 
-    #[allow(dead_code)]
-    pub async fn health_check(&self) {
-        let mut servers = self.servers.write().await;
+        let shared = self
+            .connection(&key)
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
+        let connection = shared.read().await;
 
-        for (key, connection) in servers.iter_mut() {
-            if connection.status == ConnectionStatus::Connected {
-                let elapsed = connection.last_health_check.elapsed();
+        if connection.status != ConnectionStatus::Connected {
+            return Err(RegistryError::ServerNotConnected(server_id.to_string()));
+        }
 
-                if elapsed.as_secs() >= connection.config.health_check_interval_secs {
-                    debug!("Health check for server: {}", key);
+        // Check if tool exists
+        let tool_exists = connection.tools().iter().any(|t| t.name == tool_name);
+        if !tool_exists {
+            return Err(RegistryError::ToolNotFound(tool_name.to_string()));
+        }
 
-                    // Check if process is still running
-                    if let Some(process) = &mut connection.process {
-                        match process.try_wait() {
-                            Ok(Some(status)) => {
-                                warn!("MCP server {} exited with status: {}", key, status);
-                                connection.status =
-                                    ConnectionStatus::Failed(format!("Process exited: {}", status));
-                                connection.process = None;
-                            }
-                            Ok(None) => {
-                                // Process is still running
-                                connection.last_health_check = std::time::Instant::now();
-                            }
-                            Err(e) => {
-                                error!("Failed to check process status: {}", e);
-                            }
+        let timeout = timeout.unwrap_or_else(|| connection.config.tool_timeout());
+
+        // Remote calls run without holding the connection's lock
+        if let Some(client) = connection.http_client.clone() {
+            let auth_method = connection.config.auth_method.clone();
+            drop(connection);
+            let refresh_due = self
+                .token_expiry
+                .read()
+                .await
+                .get(&key)
+                .is_some_and(|at| oauth::expires_within(*at, Utc::now(), TOKEN_REFRESH_SKEW));
+            if refresh_due {
+                match self.oauth_tokens(tenant_id, server_id, &auth_method).await {
+                    Ok(Some(tokens)) => client.set_auth(HttpAuth::Bearer(tokens.access_token)),
+                    Ok(None) => {}
+                    Err(e) => {
+                        if let RegistryError::ReauthRequired(_) = e {
+                            shared.write().await.mark_needs_reauth(e.to_string());
                         }
+                        return Err(e);
                     }
                 }
             }
+            return client.call_tool_within(tool_name, arguments, timeout).await;
         }
-    }
-}
-
+        if let Some(client) = connection.websocket_client.clone() {
+            drop(connection);
+            return client.call_tool_within(tool_name, arguments, timeout).await;
+        }
+        if let Some(client) = connection.lambda_client.clone() {
+            drop(connection);
+            return client.call_tool_within(tool_name, arguments, timeout).await;
+        }
+
+        if let Some(client) = connection.stdio_client.clone() {
+            drop(connection);
+            return client.call_tool_within(tool_name, arguments, timeout).await;
+        }
+
+        Err(RegistryError::ServerNotConnected(server_id.to_string()))
+    }
+
+    /// Connect to an HTTP or WebSocket MCP server: initialize, then fetch its tools. The
+    /// endpoint is the `endpoint` env entry, or for Docker deployments the first published
+    /// port of the container, which is started first.
+    async fn connect_remote_server(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        key: &str,
+        config: MCPServerConfig,
+        credentials: Option<HashMap<String, String>>,
+    ) -> Result<(), RegistryError> {
+        info!(
+            "Connecting to {:?} MCP server: {}",
+            config.server_type, server_id
+        );
+        self.set_status(key, ConnectionStatus::Connecting).await;
+
+        let result = async {
+            let auth = self
+                .http_auth(
+                    tenant_id,
+                    server_id,
+                    &config.auth_method,
+                    credentials.as_ref(),
+                )
+                .await?;
+            let scheme = match config.server_type {
+                MCPServerType::WebSocket => "ws",
+                _ => "http",
+            };
+            let (endpoint, container_id) = match config.env.get("endpoint") {
+                Some(endpoint) => (endpoint.clone(), None),
+                None => match &config.deployment {
+                    DeploymentConfig::Docker { ports, .. } if !ports.is_empty() => {
+                        let env_vars = self
+                            .server_env(tenant_id, server_id, &config, credentials)
+                            .await?;
+                        let container_id = self
+                            .start_container(tenant_id, server_id, &config.deployment, &env_vars)
+                            .await?;
+                        (published_endpoint(scheme, ports), Some(container_id))
+                    }
+                    _ => {
+                        return Err(RegistryError::ConnectionFailed(format!(
+                            "{:?} server {} has no endpoint",
+                            config.server_type, server_id
+                        )))
+                    }
+                },
+            };
+            let started = container_id.is_some();
+            self.update_connection(key, |connection| connection.container_id = container_id)
+                .await
+                .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
+
+            let connected = async {
+                if config.server_type == MCPServerType::WebSocket {
+                    let options = WebSocketOptions {
+                        auto_reconnect: config.auto_reconnect,
+                        ..Default::default()
+                    };
+                    let client = WebSocketMcpClient::connect(&endpoint, auth, options).await?;
+                    return Ok(RemoteClient::WebSocket(client));
+                }
+                let client = HttpMcpClient::connect(&endpoint, auth, HTTP_REQUEST_TIMEOUT).await?;
+                let tools = client.list_tools().await?;
+                Ok(RemoteClient::Http(Arc::new(client), tools))
+            }
+            .await;
+            if connected.is_err() && started {
+                // Nothing will talk to the container we just started
+                self.stop_container(&container_name(tenant_id, server_id))
+                    .await;
+                self.update_connection(key, |connection| {
+                    connection.container_id = None;
+                    connection.container_status = None;
+                })
+                .await;
+            }
+            connected
+        }
+        .await;
+
+        match &result {
+            Ok(RemoteClient::Http(_, tools)) => self.index_tools(tenant_id, server_id, tools).await,
+            Ok(RemoteClient::WebSocket(client)) => {
+                let mut updates = client.watch_tools();
+                self.index_tools(tenant_id, server_id, &client.tools())
+                    .await;
+                // The server can change its tools at any time; follow the client's list
+                // until the client is dropped
+                let tool_index = self.tool_index.clone();
+                let (tenant_id, server_id) = (tenant_id.to_string(), server_id.to_string());
+                tokio::spawn(async move {
+                    while updates.changed().await.is_ok() {
+                        let tools = updates.borrow_and_update().clone();
+                        tool_index.write().await.set_server_tools(
+                            &tenant_id,
+                            &server_id,
+                            tools.iter().map(|tool| tool.name.as_str()),
+                        );
+                    }
+                });
+            }
+            Err(_) => {}
+        }
+
+        let connection = self
+            .connection(key)
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
+        let mut connection = connection.write().await;
+        match result {
+            Ok(RemoteClient::Http(client, tools)) => {
+                info!(
+                    "Connected to HTTP MCP server {} at {} ({} tools)",
+                    server_id,
+                    client.endpoint(),
+                    tools.len()
+                );
+                connection.endpoint = Some(client.endpoint().to_string());
+                connection.server_info = Some(client.server_info().clone());
+                connection.http_client = Some(client);
+                connection.tools = tools;
+                connection.status = ConnectionStatus::Connected;
+                Ok(())
+            }
+            Ok(RemoteClient::WebSocket(client)) => {
+                info!(
+                    "Connected to WebSocket MCP server {} at {} ({} tools)",
+                    server_id,
+                    client.url(),
+                    client.tools().len()
+                );
+                connection.endpoint = Some(client.url().to_string());
+                connection.server_info = Some(client.server_info());
+                connection.websocket_client = Some(client);
+                connection.status = ConnectionStatus::Connected;
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to connect to MCP server {}: {}", server_id, e);
+                connection.last_error = Some(e.to_string());
+                connection.status = ConnectionStatus::Failed(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Credentials for HTTP requests, from the server's auth method and the tenant's
+    /// stored credentials (connect-time `credentials` take precedence)
+    async fn http_auth(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        auth_method: &AuthMethod,
+        credentials: Option<&HashMap<String, String>>,
+    ) -> Result<HttpAuth, RegistryError> {
+        let credential = |name: &'static str| async move {
+            match credentials.and_then(|creds| creds.get(name)) {
+                Some(value) => Ok(Some(value.clone())),
+                None => self.get_credential(tenant_id, server_id, name).await,
+            }
+        };
+
+        Ok(match auth_method {
+            AuthMethod::None => HttpAuth::None,
+            AuthMethod::ApiKey { key_field } => HttpAuth::Header {
+                name: key_field.clone(),
+                value: credential("api_key")
+                    .await?
+                    .ok_or_else(|| RegistryError::MissingCredential("api_key".to_string()))?,
+            },
+            AuthMethod::Basic { username, password } => HttpAuth::Basic {
+                username: username.clone(),
+                password: self
+                    .resolve_placeholder(tenant_id, server_id, password, credentials)
+                    .await?,
+            },
+            AuthMethod::OAuth2 { .. } => match credentials
+                .and_then(|creds| creds.get(oauth::ACCESS_TOKEN_FIELD))
+            {
+                Some(access_token) => HttpAuth::Bearer(access_token.clone()),
+                None => HttpAuth::Bearer(
+                    self.oauth_tokens(tenant_id, server_id, auth_method)
+                        .await?
+                        .ok_or_else(|| {
+                            RegistryError::MissingCredential(oauth::ACCESS_TOKEN_FIELD.to_string())
+                        })?
+                        .access_token,
+                ),
+            },
+        })
+    }
+
+    /// Environment for a server process or container: its configured env, connect-time
+    /// credentials, and credentials injected for its auth method
+    async fn server_env(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        config: &MCPServerConfig,
+        credentials: Option<HashMap<String, String>>,
+    ) -> Result<HashMap<String, String>, RegistryError> {
+        let mut env_vars = HashMap::new();
+        for (key, value) in &config.env {
+            let value = self
+                .resolve_placeholder(tenant_id, server_id, value, credentials.as_ref())
+                .await?;
+            env_vars.insert(key.clone(), value);
+        }
+
+        // Inject credentials if provided
+        if let Some(creds) = credentials {
+            for (key, value) in creds {
+                env_vars.insert(key, value);
+            }
+        }
+
+        // Inject auth credentials based on auth method
+        match &config.auth_method {
+            AuthMethod::ApiKey { key_field } => {
+                if let Some(api_key) = self.get_credential(tenant_id, server_id, "api_key").await? {
+                    env_vars.insert(key_field.clone(), api_key);
+                }
+            }
+            AuthMethod::OAuth2 { .. } => {
+                if let Some(stored_client_id) = self
+                    .get_credential(tenant_id, server_id, "client_id")
+                    .await?
+                {
+                    env_vars.insert("CLIENT_ID".to_string(), stored_client_id);
+                }
+                if let Some(stored_client_secret) = self
+                    .get_credential(tenant_id, server_id, "client_secret")
+                    .await?
+                {
+                    env_vars.insert("CLIENT_SECRET".to_string(), stored_client_secret);
+                }
+                if let Some(tokens) = self
+                    .oauth_tokens(tenant_id, server_id, &config.auth_method)
+                    .await?
+                {
+                    env_vars.insert("ACCESS_TOKEN".to_string(), tokens.access_token);
+                }
+            }
+            AuthMethod::Basic { username, password } => {
+                let password = self
+                    .resolve_placeholder(tenant_id, server_id, password, None)
+                    .await?;
+                env_vars.insert("USERNAME".to_string(), username.clone());
+                env_vars.insert("PASSWORD".to_string(), password);
+            }
+            AuthMethod::None => {}
+        }
+
+        Ok(env_vars)
+    }
+
+    /// Start a server's Docker container, returning its id
+    /// Start a Docker deployment and wait for it to report `running`. A container that
+    /// exits or doesn't come up in time is removed after its log tail is captured.
+    async fn start_container(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        deployment: &DeploymentConfig,
+        env_vars: &HashMap<String, String>,
+    ) -> Result<String, RegistryError> {
+        if !matches!(deployment, DeploymentConfig::Docker { .. }) {
+            return Err(RegistryError::ConnectionFailed(format!(
+                "{} is not a Docker deployment",
+                server_id
+            )));
+        }
+
+        self.docker_policy.validate_deployment(deployment)?;
+        docker::validate_env(env_vars)?;
+
+        let key = connection_key(tenant_id, server_id);
+        let image = docker::image_ref(deployment).unwrap_or_default();
+        if !image_present(&*self.docker, &image).await? {
+            self.set_status(&key, ConnectionStatus::Pulling).await;
+            pull_image(&*self.docker, &image).await?;
+            self.set_status(&key, ConnectionStatus::Connecting).await;
+        }
+
+        let container_name = container_name(tenant_id, server_id);
+        // A container left over from an earlier failed start would block the name
+        self.remove_container(&container_name).await;
+
+        // Passed by file so credentials don't show up in the process list
+        let env_file = match env_vars.is_empty() {
+            true => None,
+            false => Some(EnvFile::write(env_vars).map_err(|e| {
+                RegistryError::ConnectionFailed(format!("Failed to write env file: {}", e))
+            })?),
+        };
+        let deployment = self.docker_policy.with_default_limits(deployment);
+        let output = self
+            .docker
+            .run(docker::run_args(
+                &container_name,
+                &deployment,
+                env_file.as_ref().map(EnvFile::path),
+            ))
+            .await?;
+        // docker has read the file by the time `run` returns
+        drop(env_file);
+        if !output.success {
+            error!("Failed to start Docker container: {}", output.stderr.trim());
+            return Err(RegistryError::ConnectionFailed(
+                output.stderr.trim().to_string(),
+            ));
+        }
+        let container_id = output.stdout.trim().to_string();
+
+        match self.wait_until_running(&key, &container_id).await {
+            Ok(()) => {
+                self.capture_logs(&key, &container_id).await;
+                Ok(container_id)
+            }
+            Err(e) => {
+                let logs = self.capture_logs(&key, &container_id).await;
+                self.remove_container(&container_name).await;
+                let last_line = logs
+                    .last()
+                    .map(|line| line.line.as_str())
+                    .unwrap_or("no output");
+                Err(RegistryError::ConnectionFailed(format!(
+                    "{} (last log line: {})",
+                    e, last_line
+                )))
+            }
+        }
+    }
+
+    /// Poll `docker inspect` until the container is running
+    async fn wait_until_running(&self, key: &str, container_id: &str) -> Result<(), String> {
+        let deadline = Instant::now() + self.container_start_timeout;
+        loop {
+            let status = self.inspect_container(key, container_id).await;
+            match status.as_deref() {
+                Some("running") => return Ok(()),
+                Some("created") | Some("restarting") if Instant::now() < deadline => {
+                    tokio::time::sleep(CONTAINER_POLL_INTERVAL).await;
+                }
+                Some("created") | Some("restarting") => {
+                    return Err(format!(
+                        "Container {} did not start within {:?}",
+                        container_id, self.container_start_timeout
+                    ))
+                }
+                Some(other) => return Err(format!("Container {} is {}", container_id, other)),
+                None => return Err(format!("Container {} is gone", container_id)),
+            }
+        }
+    }
+
+    /// The container's `State.Status`, recorded on the connection; `None` when it
+    /// can't be inspected
+    async fn inspect_container(&self, key: &str, container_id: &str) -> Option<String> {
+        let status = match self
+            .docker
+            .run(vec![
+                "inspect".to_string(),
+                "--format".to_string(),
+                "{{.State.Status}}".to_string(),
+                container_id.to_string(),
+            ])
+            .await
+        {
+            Ok(output) if output.success => Some(output.stdout.trim().to_string()),
+            Ok(output) => {
+                debug!(
+                    "docker inspect {} failed: {}",
+                    container_id,
+                    output.stderr.trim()
+                );
+                None
+            }
+            Err(e) => {
+                debug!("docker inspect {} failed: {}", container_id, e);
+                None
+            }
+        };
+        self.update_connection(key, |connection| {
+            connection.container_status = status.clone()
+        })
+        .await;
+        status
+    }
+
+    /// Add the container's output since the last capture to the connection's logs;
+    /// returns the lines `docker logs` printed
+    async fn capture_logs(&self, key: &str, container_id: &str) -> Vec<LogLine> {
+        let Some(buffer) = self
+            .read_connection(key, |connection| connection.logs.clone())
+            .await
+        else {
+            return Vec::new();
+        };
+
+        let mut args = vec!["logs".to_string(), "--timestamps".to_string()];
+        match buffer.last_timestamp() {
+            Some(since) => args.extend(["--since".to_string(), since.to_rfc3339()]),
+            None => args.extend(["--tail".to_string(), LOG_BUFFER_LINES.to_string()]),
+        }
+        args.push(container_id.to_string());
+
+        let lines = match self.docker.run(args).await {
+            Ok(output) => docker::parse_logs(&output),
+            Err(e) => {
+                debug!("docker logs {} failed: {}", container_id, e);
+                return Vec::new();
+            }
+        };
+        buffer.extend_newer(lines.clone());
+        lines
+    }
+
+    async fn remove_container(&self, container_name: &str) {
+        match self
+            .docker
+            .run(vec![
+                "rm".to_string(),
+                "-f".to_string(),
+                container_name.to_string(),
+            ])
+            .await
+        {
+            Ok(output) if output.success => {
+                debug!("Docker container {} removed", container_name)
+            }
+            Ok(output) => debug!(
+                "docker rm {} failed: {}",
+                container_name,
+                output.stderr.trim()
+            ),
+            Err(e) => warn!(
+                "Failed to remove Docker container {}: {}",
+                container_name, e
+            ),
+        }
+    }
+
+    /// The most recent output of a server, oldest first; a container's is refreshed
+    /// first
+    pub async fn server_logs(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<Vec<LogLine>, RegistryError> {
+        self.ensure_loaded(tenant_id).await;
+        let key = connection_key(tenant_id, server_id);
+        let container_id = self
+            .read_connection(&key, |connection| connection.container_id.clone())
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
+        if let Some(container_id) = container_id {
+            self.capture_logs(&key, &container_id).await;
+        }
+
+        Ok(self
+            .read_connection(&key, |connection| connection.logs.lines())
+            .await
+            .unwrap_or_default())
+    }
+
+    /// The connection at `key`; the map lock is only held for the lookup
+    async fn connection(&self, key: &str) -> Option<SharedConnection> {
+        self.servers.read().await.get(key).cloned()
+    }
+
+    /// Every connection, to be locked one at a time
+    async fn connections(&self) -> Vec<(String, SharedConnection)> {
+        self.servers
+            .read()
+            .await
+            .iter()
+            .map(|(key, connection)| (key.clone(), connection.clone()))
+            .collect()
+    }
+
+    /// The connections whose key starts with the tenant's
+    async fn tenant_connections(&self, tenant_id: &str) -> Vec<(String, SharedConnection)> {
+        let prefix = format!("{}:", tenant_id);
+        self.servers
+            .read()
+            .await
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, connection)| (key.clone(), connection.clone()))
+            .collect()
+    }
+
+    /// `read` applied to the connection at `key`, if there is one
+    async fn read_connection<T>(
+        &self,
+        key: &str,
+        read: impl FnOnce(&MCPServerConnection) -> T,
+    ) -> Option<T> {
+        let connection = self.connection(key).await?;
+        let connection = connection.read().await;
+        Some(read(&connection))
+    }
+
+    /// Apply `update` to the connection at `key`, if there is one
+    async fn update_connection<T>(
+        &self,
+        key: &str,
+        update: impl FnOnce(&mut MCPServerConnection) -> T,
+    ) -> Option<T> {
+        let connection = self.connection(key).await?;
+        let mut connection = connection.write().await;
+        Some(update(&mut connection))
+    }
+
+    async fn set_status(&self, key: &str, status: ConnectionStatus) {
+        self.update_connection(key, |connection| {
+            if let ConnectionStatus::Failed(error) = &status {
+                connection.last_error = Some(error.clone());
+            }
+            connection.status = status;
+        })
+        .await;
+    }
+
+    async fn initialize_mcp_connection(&self, key: &str) -> Result<(), RegistryError> {
+        let stdio_client = self
+            .read_connection(key, |connection| connection.stdio_client.clone())
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(key.to_string()))?;
+
+        if let Some(client) = stdio_client {
+            let info = client.initialize().await?;
+            debug!("Initialized MCP server {}", key);
+            self.update_connection(key, |connection| connection.server_info = Some(info))
+                .await;
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_server_tools(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<(), RegistryError> {
+        let key = connection_key(tenant_id, server_id);
+        let (lambda_client, stdio_client) = self
+            .read_connection(&key, |connection| {
+                (
+                    connection.lambda_client.clone(),
+                    connection.stdio_client.clone(),
+                )
+            })
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(key.to_string()))?;
+
+        // Listing runs without holding the connection's lock
+        let tools = if let Some(client) = lambda_client {
+            let tools = client.list_tools().await?;
+            debug!("Lambda {} lists {} tools", client.endpoint(), tools.len());
+            tools
+        } else if let Some(client) = stdio_client {
+            let tools = client.list_tools().await?;
+            debug!("MCP server {} lists {} tools", server_id, tools.len());
+            tools
+        } else {
+            return Ok(());
+        };
+
+        self.index_tools(tenant_id, server_id, &tools).await;
+        self.update_connection(&key, |connection| connection.tools = tools)
+            .await;
+        Ok(())
+    }
+
+    async fn index_tools(&self, tenant_id: &str, server_id: &str, tools: &[MCPTool]) {
+        self.tool_index.write().await.set_server_tools(
+            tenant_id,
+            server_id,
+            tools.iter().map(|tool| tool.name.as_str()),
+        );
+    }
+
+    /// Drop a connection's tools from the index once its server has exited, so they
+    /// aren't resolved to it until it's reconnected
+    async fn forget_tools(&self, key: &str, instance_id: &str) {
+        if let Some(tenant_id) = connection_tenant(key, instance_id) {
+            self.tool_index
+                .write()
+                .await
+                .remove_server(tenant_id, instance_id);
+        }
+    }
+
+    /// The connection providing `tool_name`, as an instance id. Fails with
+    /// `AmbiguousTool` when several connections provide it; callers then use the
+    /// `server_id.tool` or `server_id.connection_id.tool` form.
+    pub async fn resolve_tool(
+        &self,
+        tenant_id: &str,
+        tool_name: &str,
+    ) -> Result<String, RegistryError> {
+        let mut servers = self
+            .tool_index
+            .read()
+            .await
+            .servers_for(tenant_id, tool_name);
+        match servers.len() {
+            0 => Err(RegistryError::ToolNotFound(tool_name.to_string())),
+            1 => Ok(servers.remove(0)),
+            // Named in the `server_id.connection_id` form callers prefix tools with
+            _ => Err(RegistryError::AmbiguousTool {
+                tool: tool_name.to_string(),
+                servers: servers
+                    .iter()
+                    .map(|server| server.replacen('@', ".", 1))
+                    .collect(),
+            }),
+        }
+    }
+
+    async fn store_server_config(
+        &self,
+        tenant_id: &str,
+        config: &MCPServerConfig,
+    ) -> Result<(), RegistryError> {
+        let key = config_key(tenant_id, &config.id);
+        let value = serde_json::to_string(config)
+            .map_err(|e| RegistryError::SerializationError(e.to_string()))?;
+
+        self.store
+            .kv_set_direct(&key, &value, Some(24 * 30)) // 30 days TTL
+            .await
+            .map_err(|e| RegistryError::StorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_server_config(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<(), RegistryError> {
+        self.store
+            .kv_delete(&config_key(tenant_id, server_id))
+            .await
+            .map_err(|e| RegistryError::StorageError(e.to_string()))
+    }
+
+    async fn get_credential(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        credential_name: &str,
+    ) -> Result<Option<String>, RegistryError> {
+        match self
+            .secrets
+            .secret_get(tenant_id, server_id, credential_name)
+            .await
+        {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                debug!(
+                    "No credential {} found for {}/{}: {}",
+                    credential_name, tenant_id, server_id, e
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// `value`, or for an export [`export::placeholder`] the credential it names, taken
+    /// from `credentials` or the secret store
+    async fn resolve_placeholder(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        value: &str,
+        credentials: Option<&HashMap<String, String>>,
+    ) -> Result<String, RegistryError> {
+        let Some(name) = export::placeholder_name(value) else {
+            return Ok(value.to_string());
+        };
+        match credentials.and_then(|creds| creds.get(name)) {
+            Some(value) => Ok(value.clone()),
+            None => self
+                .get_credential(tenant_id, server_id, name)
+                .await?
+                .ok_or_else(|| RegistryError::MissingCredential(name.to_string())),
+        }
+    }
+
+    /// Store credential fields for a server, one secret per field
+    pub async fn store_credentials(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        credentials: &HashMap<String, String>,
+    ) -> Result<(), RegistryError> {
+        for (field, value) in credentials {
+            self.secrets
+                .secret_put(tenant_id, server_id, field, value)
+                .await
+                .map_err(|e| RegistryError::StorageError(e.to_string()))?;
+        }
+        debug!(
+            "Stored {} credential fields for {}/{}",
+            credentials.len(),
+            tenant_id,
+            server_id
+        );
+        Ok(())
+    }
+
+    /// Delete every stored credential field of a server
+    pub async fn delete_credentials(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<(), RegistryError> {
+        let fields = self
+            .secrets
+            .secret_fields(tenant_id, server_id)
+            .await
+            .map_err(|e| RegistryError::StorageError(e.to_string()))?;
+
+        for field in fields {
+            self.secrets
+                .secret_delete(tenant_id, server_id, &field)
+                .await
+                .map_err(|e| RegistryError::StorageError(e.to_string()))?;
+            debug!(
+                "Deleted credential {} of {}/{}",
+                field, tenant_id, server_id
+            );
+        }
+        Ok(())
+    }
+
+    /// The OAuth2 tokens stored for a connection, if it has an access token
+    async fn stored_tokens(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<Option<OAuthTokens>, RegistryError> {
+        let Some(access_token) = self
+            .get_credential(tenant_id, server_id, oauth::ACCESS_TOKEN_FIELD)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let refresh_token = self
+            .get_credential(tenant_id, server_id, oauth::REFRESH_TOKEN_FIELD)
+            .await?;
+        let expires_at = match self
+            .get_credential(tenant_id, server_id, oauth::EXPIRES_AT_FIELD)
+            .await?
+        {
+            Some(at) => Some(
+                DateTime::parse_from_rfc3339(&at)
+                    .map_err(|e| RegistryError::InvalidConfig {
+                        field: oauth::EXPIRES_AT_FIELD.to_string(),
+                        message: format!("{:?} is not an RFC 3339 time: {}", at, e),
+                    })?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+        Ok(Some(OAuthTokens {
+            access_token,
+            refresh_token,
+            expires_at,
+        }))
+    }
+
+    /// A connection's OAuth2 tokens, refreshed first when the access token expires within
+    /// [`TOKEN_REFRESH_SKEW`]. A token endpoint that can't be reached only fails the call
+    /// once the access token has actually expired; a rejected refresh token always does,
+    /// with `RegistryError::ReauthRequired`.
+    async fn oauth_tokens(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+        auth_method: &AuthMethod,
+    ) -> Result<Option<OAuthTokens>, RegistryError> {
+        let AuthMethod::OAuth2 {
+            client_id,
+            client_secret,
+            token_endpoint,
+        } = auth_method
+        else {
+            return Ok(None);
+        };
+        let Some(tokens) = self.stored_tokens(tenant_id, server_id).await? else {
+            return Ok(None);
+        };
+
+        let expired = tokens.expires_within(Utc::now(), Duration::ZERO);
+        let tokens = match (token_endpoint, &tokens.refresh_token) {
+            _ if !tokens.expires_within(Utc::now(), TOKEN_REFRESH_SKEW) => tokens,
+            (Some(token_endpoint), Some(refresh_token)) => {
+                // Stored client credentials take precedence, as they do in the env
+                let client_id = self
+                    .get_credential(tenant_id, server_id, "client_id")
+                    .await?
+                    .unwrap_or_else(|| client_id.clone());
+                let client_secret = self
+                    .get_credential(tenant_id, server_id, "client_secret")
+                    .await?
+                    .unwrap_or_else(|| client_secret.clone());
+                match oauth::refresh(token_endpoint, &client_id, &client_secret, refresh_token)
+                    .await
+                {
+                    Ok(fresh) => {
+                        self.store_credentials(tenant_id, server_id, &fresh.fields())
+                            .await?;
+                        info!(
+                            "Refreshed the OAuth2 access token of {} for tenant {}",
+                            server_id, tenant_id
+                        );
+                        fresh
+                    }
+                    Err(e @ RegistryError::ReauthRequired(_)) => return Err(e),
+                    Err(e) if expired => return Err(e),
+                    Err(e) => {
+                        warn!(
+                            "Failed to refresh the OAuth2 access token of {}, using it until it expires: {}",
+                            server_id, e
+                        );
+                        tokens
+                    }
+                }
+            }
+            _ if expired => {
+                return Err(RegistryError::ReauthRequired(format!(
+                    "the access token of {} has expired and can't be refreshed",
+                    server_id
+                )))
+            }
+            _ => tokens,
+        };
+
+        let key = connection_key(tenant_id, server_id);
+        match tokens.expires_at {
+            Some(expires_at) => self.token_expiry.write().await.insert(key, expires_at),
+            None => self.token_expiry.write().await.remove(&key),
+        };
+        Ok(Some(tokens))
+    }
+
+    /// Whether a connection's OAuth2 access token is usable, for `integration_test`.
+    /// `None` for servers with another auth method.
+    pub async fn token_status(
+        &self,
+        tenant_id: &str,
+        server_id: &str,
+    ) -> Result<Option<TokenStatus>, RegistryError> {
+        self.ensure_loaded(tenant_id).await;
+        let (auth_method, needs_reauth) = self
+            .read_connection(&connection_key(tenant_id, server_id), |connection| {
+                (
+                    connection.config.auth_method.clone(),
+                    connection.status == ConnectionStatus::NeedsReauth,
+                )
+            })
+            .await
+            .ok_or_else(|| RegistryError::ServerNotFound(server_id.to_string()))?;
+        let AuthMethod::OAuth2 { token_endpoint, .. } = auth_method else {
+            return Ok(None);
+        };
+
+        let tokens = self.stored_tokens(tenant_id, server_id).await?;
+        Ok(Some(TokenStatus {
+            valid: tokens
+                .as_ref()
+                .is_some_and(|tokens| !tokens.expires_within(Utc::now(), Duration::ZERO)),
+            expires_at: tokens.as_ref().and_then(|tokens| tokens.expires_at),
+            refreshable: token_endpoint.is_some()
+                && tokens
+                    .as_ref()
+                    .is_some_and(|tokens| tokens.refresh_token.is_some()),
+            needs_reauth,
+        }))
+    }
+
+    /// Check connected servers that are due (per `health_check_interval_secs`) and
+    /// reconnect failed servers with `auto_reconnect` whose backoff has elapsed. Up to
+    /// `HEALTH_CHECK_CONCURRENCY` connections are checked at once, each locked only
+    /// while deciding what to do, so calls aren't held up by a slow sweep.
+    pub async fn health_check(&self) {
+        let now = Instant::now();
+        stream::iter(self.connections().await)
+            .for_each_concurrent(HEALTH_CHECK_CONCURRENCY, |(key, connection)| async move {
+                self.check_connection(&key, &connection, now).await
+            })
+            .await;
+    }
+
+    /// One connection's part of a health sweep
+    async fn check_connection(&self, key: &str, connection: &SharedConnection, now: Instant) {
+        let mut container = None;
+        let mut reconnect = None;
+        let mut exited = None;
+        let instance_id;
+
+        {
+            let mut connection = connection.write().await;
+            let policy = self.restart_policy(&connection.config);
+            instance_id = connection.instance_id();
+            if connection.status == ConnectionStatus::Connected {
+                let elapsed = connection.last_health_check.elapsed();
+
+                if elapsed.as_secs() >= connection.config.health_check_interval_secs {
+                    debug!("Health check for server: {}", key);
+
+                    // Inspected below, without holding the lock
+                    container = connection.container_id.clone();
+
+                    // Check if process is still running
+                    if let Some(process) = &mut connection.process {
+                        match process.try_wait() {
+                            Ok(Some(status)) => {
+                                warn!("MCP server {} exited with status: {}", key, status);
+                                let error = format!("Process exited: {}", status);
+                                connection.mark_failed(error.clone(), &policy);
+                                connection.process = None;
+                                exited = Some(error);
+                            }
+                            Ok(None) => {
+                                // Process is still running
+                                connection.mark_healthy(policy.reset_after);
+                            }
+                            Err(e) => {
+                                error!("Failed to check process status: {}", e);
+                            }
+                        }
+                    }
+                }
+            } else if matches!(connection.status, ConnectionStatus::Failed(_))
+                && connection.config.auto_reconnect
+                && connection.reconnect_attempts < policy.max_attempts
+                && connection.next_reconnect_at.is_none_or(|at| now >= at)
+            {
+                if let Some(tenant_id) = connection_tenant(key, &instance_id) {
+                    connection.reconnect_attempts += 1;
+                    reconnect = Some((
+                        tenant_id.to_string(),
+                        instance_id.clone(),
+                        connection.reconnect_attempts,
+                        policy.max_attempts,
+                    ));
+                }
+            }
+        }
+
+        if let Some(error) = exited {
+            self.forget_tools(key, &instance_id).await;
+            self.metrics
+                .write()
+                .await
+                .entry(key.to_string())
+                .or_default()
+                .disconnected(Some(error));
+        }
+        if let Some(container_id) = container {
+            self.check_container(key, &container_id).await;
+        }
+        if let Some((tenant_id, server_id, attempt, max_attempts)) = reconnect {
+            self.reconnect(&tenant_id, &server_id, attempt, max_attempts)
+                .await;
+        }
+    }
+
+    /// Mark a connection failed when its container is no longer running
+    async fn check_container(&self, key: &str, container_id: &str) {
+        let status = self.inspect_container(key, container_id).await;
+        if status.as_deref() == Some("running") {
+            self.update_connection(key, |connection| {
+                let reset_after = self.restart_policy(&connection.config).reset_after;
+                connection.mark_healthy(reset_after);
+            })
+            .await;
+            // Keep the connection's copy of the output current
+            self.capture_logs(key, container_id).await;
+            return;
+        }
+
+        warn!(
+            "Container {} of MCP server {} is {}",
+            container_id,
+            key,
+            status.as_deref().unwrap_or("gone")
+        );
+        self.capture_logs(key, container_id).await;
+        let error = format!("Container {}", status.as_deref().unwrap_or("gone"));
+        let instance_id = self
+            .update_connection(key, |connection| {
+                let policy = self.restart_policy(&connection.config);
+                connection.mark_failed(error.clone(), &policy);
+                connection.container_id = None;
+                connection.instance_id()
+            })
+            .await;
+        if let Some(instance_id) = instance_id {
+            self.forget_tools(key, &instance_id).await;
+        }
+        self.metrics
+            .write()
+            .await
+            .entry(key.to_string())
+            .or_default()
+            .disconnected(Some(error));
+    }
+
+    async fn reconnect(&self, tenant_id: &str, server_id: &str, attempt: u32, max_attempts: u32) {
+        info!(
+            "Reconnecting MCP server {} for tenant {} (attempt {}/{})",
+            server_id, tenant_id, attempt, max_attempts
+        );
+
+        // Failures are recorded on the connection by `connect_tracked`
+        let result = self.connect_tracked(tenant_id, server_id, None).await;
+
+        let Some(connection) = self.connection(&connection_key(tenant_id, server_id)).await else {
+            return;
+        };
+        let mut connection = connection.write().await;
+        match result {
+            Ok(()) => {
+                info!("MCP server {} reconnected", server_id);
+                connection.last_health_check = Instant::now();
+                connection.next_reconnect_at = None;
+            }
+            Err(e) => {
+                if attempt >= max_attempts {
+                    warn!(
+                        "Giving up reconnecting MCP server {} after {} attempts: {}",
+                        server_id, attempt, e
+                    );
+                } else {
+                    warn!("Reconnect of MCP server {} failed: {}", server_id, e);
+                }
+            }
+        }
+    }
+}
+
+/// Connection id of the connection made when none is given
+pub const DEFAULT_CONNECTION: &str = "default";
+
+/// How the registry addresses one connection of a service: the service id for its
+/// default connection, `service_id@connection_id` for any other. Every method taking a
+/// `server_id` accepts either form.
+pub fn instance_id(server_id: &str, connection_id: Option<&str>) -> String {
+    match connection_id {
+        None | Some(DEFAULT_CONNECTION) => server_id.to_string(),
+        Some(connection_id) => format!("{}@{}", server_id, connection_id),
+    }
+}
+
+/// The service id and, for a named connection, the connection id of an instance id
+pub fn split_instance_id(instance_id: &str) -> (&str, Option<&str>) {
+    match instance_id.split_once('@') {
+        Some((server_id, connection_id)) => (server_id, Some(connection_id)),
+        None => (instance_id, None),
+    }
+}
+
+/// Server ids can't contain `@`, which separates connection ids, or `.`, which separates
+/// them from tool names in `server_id.tool`
+pub fn validate_server_id(server_id: &str) -> Result<(), RegistryError> {
+    match server_id.chars().find(|c| matches!(c, '@' | '.')) {
+        Some(separator) => Err(RegistryError::InvalidConfig {
+            field: "id".to_string(),
+            message: format!(
+                "must not contain '{}', which separates {}",
+                separator,
+                if separator == '@' {
+                    "connection ids"
+                } else {
+                    "server ids from tool names"
+                }
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Connection ids are letters, digits, `_` and `-`, so they can appear in container
+/// names and secret names
+pub fn validate_connection_id(connection_id: &str) -> Result<(), RegistryError> {
+    let valid = !connection_id.is_empty()
+        && connection_id.len() <= 64
+        && connection_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(RegistryError::InvalidConfig {
+            field: "connection_id".to_string(),
+            message: format!(
+                "{:?} must be 1-64 letters, digits, '_' or '-'",
+                connection_id
+            ),
+        })
+    }
+}
+
+/// Whether the Docker daemon already has `image`
+async fn image_present(docker: &dyn DockerCli, image: &str) -> Result<bool, RegistryError> {
+    let args = ["image", "inspect", "--format", "{{.Id}}", image];
+    let output = docker
+        .run(args.iter().map(|arg| arg.to_string()).collect())
+        .await?;
+    Ok(output.success)
+}
+
+/// `docker pull` an image, logging its progress; a failure carries the daemon's error
+async fn pull_image(docker: &dyn DockerCli, image: &str) -> Result<(), RegistryError> {
+    info!("Pulling Docker image {}", image);
+    let output = docker
+        .run_streaming(vec!["pull".to_string(), image.to_string()], &|line| {
+            info!("Pulling {}: {}", image, line)
+        })
+        .await?;
+    if !output.success {
+        return Err(RegistryError::ConnectionFailed(format!(
+            "Failed to pull {}: {}",
+            image,
+            output.stderr.trim()
+        )));
+    }
+    info!("Pulled Docker image {}", image);
+    Ok(())
+}
+
+/// Key of a connection in `servers`; context ids never contain `:`
+fn connection_key(tenant_id: &str, instance_id: &str) -> String {
+    format!("{}:{}", tenant_id, instance_id)
+}
+
+/// The tenant of the connection of `instance_id` at `key`
+fn connection_tenant<'a>(key: &'a str, instance_id: &str) -> Option<&'a str> {
+    key.strip_suffix(instance_id)?.strip_suffix(':')
+}
+
+/// Docker container name of a connection: `mcp-{tenant}-{server}`, with
+/// `.{connection}` appended for named connections
+fn container_name(tenant_id: &str, server_id: &str) -> String {
+    format!("mcp-{}-{}", tenant_id, server_id.replacen('@', ".", 1))
+}
+
+fn config_key(tenant_id: &str, server_id: &str) -> String {
+    internal_keys::registry_key(tenant_id, server_id)
+}
+
+/// Tools from a `tools/list` result; entries without a name are skipped
+fn parse_tools(result: &Value) -> Result<Vec<MCPTool>, RegistryError> {
+    let tools = result
+        .get("tools")
+        .and_then(Value::as_array)
+        .ok_or_else(|| RegistryError::Protocol("tools/list result has no tools".to_string()))?;
+
+    Ok(tools
+        .iter()
+        .filter_map(|tool| {
+            Some(MCPTool {
+                name: tool.get("name")?.as_str()?.to_string(),
+                description: tool
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                input_schema: tool
+                    .get("inputSchema")
+                    .cloned()
+                    .unwrap_or_else(|| serde_json::json!({"type": "object"})),
+            })
+        })
+        .collect())
+}
+
+/// Local URL of a container's first published port (`host:container` mapping)
+fn published_endpoint(scheme: &str, ports: &[String]) -> String {
+    let port = ports[0].split(':').next().unwrap_or("8080");
+    format!("{}://localhost:{}", scheme, port)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MCPServerInfo {
     pub id: String,
@@ -639,6 +2903,45 @@ pub struct MCPServerInfo {
     pub description: String,
     pub status: String,
     pub tool_count: usize,
+    pub last_error: Option<String>,
+    pub reconnect_attempts: u32,
+    /// Reconnects the health monitor makes before giving up
+    #[serde(default)]
+    pub max_restarts: u32,
+    pub container_status: Option<String>,
+    /// Which of the service's connections this is; absent for the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+    /// The service's other connections, when listed by `list_servers`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connections: Vec<MCPServerInfo>,
+    /// Call counts, failures, latency and uptime
+    #[serde(default)]
+    pub metrics: ServerMetricsReport,
+}
+
+/// One connection's state for `diagnostics_bundle`
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionDiagnostics {
+    /// Registry key, `{tenant}-{server_id}`
+    pub connection: String,
+    pub status: String,
+    pub last_error: Option<String>,
+    pub restart_errors: Vec<String>,
+    pub reconnect_attempts: u32,
+    pub container_status: Option<String>,
+    pub logs: Vec<LogLine>,
+}
+
+impl MCPServerInfo {
+    /// Prefix of this connection's tools in `mcp_proxy` calls: `server_id` or
+    /// `server_id.connection_id`
+    pub fn tool_prefix(&self) -> String {
+        match &self.connection_id {
+            Some(connection_id) => format!("{}.{}", self.id, connection_id),
+            None => self.id.clone(),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -649,12 +2952,31 @@ pub enum RegistryError {
     ServerNotConnected(String),
     #[error("Tool not found: {0}")]
     ToolNotFound(String),
+    #[error("Invalid {field}: {message}")]
+    InvalidConfig { field: String, message: String },
+    #[error(
+        "Tool '{tool}' is provided by several servers ({}); call it as '<server_id>.{tool}'",
+        servers.join(", ")
+    )]
+    AmbiguousTool { tool: String, servers: Vec<String> },
     #[error("Connection failed: {0}")]
     ConnectionFailed(String),
-    #[error("Unsupported server type: {0}")]
-    UnsupportedServerType(String),
     #[error("Storage error: {0}")]
     StorageError(String),
     #[error("Serialization error: {0}")]
     SerializationError(String),
+    #[error("Request timed out: {0}")]
+    Timeout(String),
+    #[error("TLS error: {0}")]
+    Tls(String),
+    #[error("Server responded with HTTP {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+    #[error("Server returned error {code}: {message}")]
+    RemoteError { code: i64, message: String },
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+    #[error("Missing credential: {0}")]
+    MissingCredential(String),
+    #[error("Re-authorization required: {0}")]
+    ReauthRequired(String),
 }
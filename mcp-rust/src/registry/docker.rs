@@ -0,0 +1,702 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::IpAddr;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+
+use super::logs::{LogLine, LogStream};
+use super::{DeploymentConfig, MCPServerConfig, RegistryError};
+
+/// Host directory that volume mounts must live under
+pub const VOLUME_ROOT_ENV: &str = "AGENT_MESH_DOCKER_VOLUME_ROOT";
+const DEFAULT_VOLUME_ROOT: &str = "/var/lib/agent-mesh/mcp-volumes";
+
+/// Set to `true` to give containers without their own resource limits the defaults of
+/// [`DefaultLimits`]
+pub const REQUIRE_LIMITS_ENV: &str = "AGENT_MESH_DOCKER_REQUIRE_LIMITS";
+
+/// Outcome of one `docker` CLI invocation
+#[derive(Debug, Clone, Default)]
+pub struct DockerOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs `docker` subcommands (the CLI in production, a stub in tests)
+#[async_trait]
+pub trait DockerCli: Send + Sync {
+    async fn run(&self, args: Vec<String>) -> Result<DockerOutput, RegistryError>;
+
+    /// Like `run`, handing each line of stdout to `on_line` as it is printed (for
+    /// long-running commands such as `pull`)
+    async fn run_streaming(
+        &self,
+        args: Vec<String>,
+        on_line: &(dyn for<'a> Fn(&'a str) + Send + Sync),
+    ) -> Result<DockerOutput, RegistryError> {
+        let output = self.run(args).await?;
+        output.stdout.lines().for_each(on_line);
+        Ok(output)
+    }
+}
+
+/// Invokes the `docker` binary on PATH
+pub struct SystemDocker;
+
+#[async_trait]
+impl DockerCli for SystemDocker {
+    async fn run(&self, args: Vec<String>) -> Result<DockerOutput, RegistryError> {
+        let output = Command::new("docker")
+            .args(&args)
+            .output()
+            .await
+            .map_err(|e| RegistryError::ConnectionFailed(format!("Failed to run docker: {}", e)))?;
+        Ok(DockerOutput {
+            success: output.status.success(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    async fn run_streaming(
+        &self,
+        args: Vec<String>,
+        on_line: &(dyn for<'a> Fn(&'a str) + Send + Sync),
+    ) -> Result<DockerOutput, RegistryError> {
+        let failed = |e: std::io::Error| {
+            RegistryError::ConnectionFailed(format!("Failed to run docker: {}", e))
+        };
+        let mut child = Command::new("docker")
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(failed)?;
+
+        let mut stderr_pipe = child.stderr.take();
+        let read_stderr = async {
+            let mut stderr = String::new();
+            if let Some(pipe) = stderr_pipe.as_mut() {
+                let _ = pipe.read_to_string(&mut stderr).await;
+            }
+            stderr
+        };
+        let stdout_pipe = child.stdout.take();
+        let read_stdout = async {
+            let mut stdout = String::new();
+            if let Some(pipe) = stdout_pipe {
+                let mut lines = BufReader::new(pipe).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    on_line(&line);
+                    stdout.push_str(&line);
+                    stdout.push('\n');
+                }
+            }
+            stdout
+        };
+        let (stdout, stderr) = tokio::join!(read_stdout, read_stderr);
+        let status = child.wait().await.map_err(failed)?;
+
+        Ok(DockerOutput {
+            success: status.success(),
+            stdout,
+            stderr,
+        })
+    }
+}
+
+/// What a Docker deployment may ask of the host. Configs are checked when registered
+/// and again before every `docker run`, since stored configs may predate the policy.
+#[derive(Debug, Clone)]
+pub struct DockerPolicy {
+    /// Bind mounts must have their host path under this directory
+    pub volume_root: PathBuf,
+    /// Limits for containers that don't set their own; none are imposed when unset
+    pub default_limits: Option<DefaultLimits>,
+}
+
+/// Resource limits a [`DockerPolicy`] imposes on every container
+#[derive(Debug, Clone)]
+pub struct DefaultLimits {
+    pub memory_limit: String,
+    pub cpu_limit: String,
+    pub pids_limit: u32,
+}
+
+impl Default for DefaultLimits {
+    fn default() -> Self {
+        Self {
+            memory_limit: "512m".to_string(),
+            cpu_limit: "1".to_string(),
+            pids_limit: 256,
+        }
+    }
+}
+
+impl Default for DockerPolicy {
+    fn default() -> Self {
+        Self {
+            volume_root: PathBuf::from(DEFAULT_VOLUME_ROOT),
+            default_limits: None,
+        }
+    }
+}
+
+impl DockerPolicy {
+    /// Mounts under `volume_root` (the default root when `None`), with the
+    /// [`DefaultLimits`] imposed when `require_limits` is set
+    pub fn configured(volume_root: Option<PathBuf>, require_limits: bool) -> Self {
+        Self {
+            volume_root: volume_root.unwrap_or_else(|| PathBuf::from(DEFAULT_VOLUME_ROOT)),
+            default_limits: require_limits.then(DefaultLimits::default),
+        }
+    }
+
+    /// The deployment with the policy's default limits filled in where it sets none
+    pub fn with_default_limits(&self, deployment: &DeploymentConfig) -> DeploymentConfig {
+        let mut deployment = deployment.clone();
+        if let (
+            Some(defaults),
+            DeploymentConfig::Docker {
+                memory_limit,
+                cpu_limit,
+                pids_limit,
+                ..
+            },
+        ) = (&self.default_limits, &mut deployment)
+        {
+            memory_limit.get_or_insert_with(|| defaults.memory_limit.clone());
+            cpu_limit.get_or_insert_with(|| defaults.cpu_limit.clone());
+            pids_limit.get_or_insert(defaults.pids_limit);
+        }
+        deployment
+    }
+
+    /// Check a server config; only Docker deployments are restricted
+    pub fn validate_config(&self, config: &MCPServerConfig) -> Result<(), RegistryError> {
+        if !matches!(config.deployment, DeploymentConfig::Docker { .. }) {
+            return Ok(());
+        }
+        // The id becomes part of the container name
+        if !is_name(&config.id) {
+            return Err(invalid(
+                "id",
+                "must start with a letter or digit and contain only letters, digits, '_', '.' and '-'",
+            ));
+        }
+        self.validate_deployment(&config.deployment)?;
+        validate_env(&config.env)
+    }
+
+    pub fn validate_deployment(&self, deployment: &DeploymentConfig) -> Result<(), RegistryError> {
+        let DeploymentConfig::Docker {
+            image,
+            tag,
+            ports,
+            volumes,
+            network,
+            runtime,
+            memory_limit,
+            cpu_limit,
+            pids_limit,
+            ..
+        } = deployment
+        else {
+            return Ok(());
+        };
+
+        if !is_image(image) {
+            return Err(invalid(
+                "deployment.image",
+                "must be a Docker image reference such as 'ghcr.io/org/name'",
+            ));
+        }
+        if !is_tag(tag) {
+            return Err(invalid(
+                "deployment.tag",
+                "must be 1-128 letters, digits, '_', '.' or '-', not starting with '.' or '-'",
+            ));
+        }
+        for (i, port) in ports.iter().enumerate() {
+            validate_port(port)
+                .map_err(|message| invalid(&format!("deployment.ports[{}]", i), &message))?;
+        }
+        for (i, volume) in volumes.iter().enumerate() {
+            self.validate_volume(volume)
+                .map_err(|message| invalid(&format!("deployment.volumes[{}]", i), &message))?;
+        }
+        if let Some(network) = network {
+            if !is_name(network) {
+                return Err(invalid("deployment.network", "is not a valid network name"));
+            }
+            if network == "host" {
+                return Err(invalid(
+                    "deployment.network",
+                    "'host' would share the host's network stack",
+                ));
+            }
+        }
+        if let Some(runtime) = runtime {
+            if !is_name(runtime) {
+                return Err(invalid("deployment.runtime", "is not a valid runtime name"));
+            }
+        }
+        if memory_limit
+            .as_deref()
+            .is_some_and(|limit| !is_memory(limit))
+        {
+            return Err(invalid(
+                "deployment.memory_limit",
+                "must be a positive amount with an optional b, k, m or g unit, such as '512m'",
+            ));
+        }
+        if cpu_limit.as_deref().is_some_and(|limit| !is_cpus(limit)) {
+            return Err(invalid(
+                "deployment.cpu_limit",
+                "must be a positive number of CPUs, such as '1.5'",
+            ));
+        }
+        if *pids_limit == Some(0) {
+            return Err(invalid("deployment.pids_limit", "must be at least 1"));
+        }
+        Ok(())
+    }
+
+    /// `host:container[:ro|rw]` with an absolute host path under `volume_root`
+    fn validate_volume(&self, volume: &str) -> Result<(), String> {
+        let parts: Vec<&str> = volume.split(':').collect();
+        let (host, container, mode) = match parts.as_slice() {
+            [host, container] => (*host, *container, None),
+            [host, container, mode] => (*host, *container, Some(*mode)),
+            _ => return Err("must be 'host_path:container_path[:ro|rw]'".to_string()),
+        };
+        if !is_clean_absolute(host) {
+            return Err("host path must be absolute without '..'".to_string());
+        }
+        if !Path::new(host).starts_with(&self.volume_root) {
+            return Err(format!(
+                "host path must be under {}",
+                self.volume_root.display()
+            ));
+        }
+        if !is_clean_absolute(container) {
+            return Err("container path must be absolute without '..'".to_string());
+        }
+        match mode {
+            None | Some("ro") | Some("rw") => Ok(()),
+            Some(_) => Err("mode must be 'ro' or 'rw'".to_string()),
+        }
+    }
+}
+
+/// Env var names must be identifiers, and values single lines (they are written to an
+/// env file, one variable per line)
+pub fn validate_env(env: &HashMap<String, String>) -> Result<(), RegistryError> {
+    let mut keys: Vec<&String> = env.keys().collect();
+    keys.sort();
+    for key in keys {
+        let mut chars = key.chars();
+        let valid_name = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(invalid(
+                &format!("env.{}", key),
+                "name must contain only letters, digits and '_', not starting with a digit",
+            ));
+        }
+        if env[key].contains(['\n', '\r', '\0']) {
+            return Err(invalid(
+                &format!("env.{}", key),
+                "value must not contain line breaks or NUL",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn invalid(field: &str, message: &str) -> RegistryError {
+    RegistryError::InvalidConfig {
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// `[host/]path/to/name`: an optional registry host (with a '.' or port, or
+/// `localhost`) followed by lowercase path components
+fn is_image(image: &str) -> bool {
+    if image.is_empty() || image.len() > 255 {
+        return false;
+    }
+    let mut components: Vec<&str> = image.split('/').collect();
+    if components.len() > 1 {
+        let first = components[0];
+        if first.contains(['.', ':']) || first == "localhost" {
+            if !is_registry_host(first) {
+                return false;
+            }
+            components.remove(0);
+        }
+    }
+    components
+        .iter()
+        .all(|component| is_path_component(component))
+}
+
+fn is_registry_host(host: &str) -> bool {
+    let (name, port) = match host.split_once(':') {
+        Some((name, port)) => (name, Some(port)),
+        None => (host, None),
+    };
+    let valid_name = !name.is_empty()
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    valid_name && port.is_none_or(|port| port.parse::<u16>().is_ok_and(|p| p > 0))
+}
+
+/// Lowercase letters and digits, with single `.`, `_` or `-` separators between them
+fn is_path_component(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    !bytes.is_empty()
+        && bytes.first().is_some_and(u8::is_ascii_alphanumeric)
+        && bytes.last().is_some_and(u8::is_ascii_alphanumeric)
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"._-".contains(b))
+        && bytes
+            .windows(2)
+            .all(|pair| !(b"._-".contains(&pair[0]) && b"._-".contains(&pair[1])))
+}
+
+fn is_tag(tag: &str) -> bool {
+    tag.len() <= 128
+        && tag
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+}
+
+/// Container, network and runtime names
+fn is_name(name: &str) -> bool {
+    name.len() <= 64
+        && name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+}
+
+/// `[ip:]host_port:container_port[/tcp|udp]` or `container_port[/tcp|udp]`
+fn validate_port(mapping: &str) -> Result<(), String> {
+    let (ports, protocol) = match mapping.split_once('/') {
+        Some((ports, protocol)) => (ports, Some(protocol)),
+        None => (mapping, None),
+    };
+    if !matches!(protocol, None | Some("tcp") | Some("udp")) {
+        return Err("protocol must be 'tcp' or 'udp'".to_string());
+    }
+
+    let parts: Vec<&str> = ports.split(':').collect();
+    let numbers = match parts.as_slice() {
+        [container] => vec![*container],
+        [host, container] => vec![*host, *container],
+        [ip, host, container] => {
+            if ip.parse::<IpAddr>().is_err() {
+                return Err(format!("'{}' is not an IP address", ip));
+            }
+            vec![*host, *container]
+        }
+        _ => return Err("must be '[ip:]host_port:container_port'".to_string()),
+    };
+    for number in numbers {
+        let valid = !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+            && number.parse::<u16>().is_ok_and(|port| port > 0);
+        if !valid {
+            return Err(format!("'{}' is not a port number (1-65535)", number));
+        }
+    }
+    Ok(())
+}
+
+/// `512m`, `2g`, `1048576`: digits and an optional unit, not zero
+fn is_memory(limit: &str) -> bool {
+    let digits = limit
+        .strip_suffix(['b', 'k', 'm', 'g', 'B', 'K', 'M', 'G'])
+        .unwrap_or(limit);
+    digits.len() <= 18
+        && !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && digits.chars().any(|c| c != '0')
+}
+
+/// `1`, `0.5`, `1.5`: a positive decimal number
+fn is_cpus(limit: &str) -> bool {
+    limit.len() <= 16
+        && limit.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && limit.parse::<f64>().is_ok_and(|cpus| cpus > 0.0)
+}
+
+fn is_clean_absolute(path: &str) -> bool {
+    let path = Path::new(path);
+    path.is_absolute() && !path.components().any(|c| c == Component::ParentDir)
+}
+
+/// Environment for `docker run --env-file`, so values don't appear on the command
+/// line (visible in `ps`). Readable only by this user; removed when dropped.
+pub struct EnvFile {
+    path: PathBuf,
+}
+
+impl EnvFile {
+    /// Write `KEY=value` lines; values must already pass `validate_env`
+    pub fn write(env_vars: &HashMap<String, String>) -> std::io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("mcp-env-{}", uuid::Uuid::new_v4()));
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?;
+        let env_file = Self { path };
+
+        let mut env: Vec<_> = env_vars.iter().collect();
+        env.sort();
+        for (key, value) in env {
+            writeln!(file, "{}={}", key, value)?;
+        }
+        file.sync_all()?;
+        Ok(env_file)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for EnvFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// `image:tag` of a Docker deployment
+pub fn image_ref(deployment: &DeploymentConfig) -> Option<String> {
+    match deployment {
+        DeploymentConfig::Docker { image, tag, .. } => Some(format!("{}:{}", image, tag)),
+        _ => None,
+    }
+}
+
+/// Arguments for `docker run` starting a Docker deployment detached as `container_name`,
+/// with its environment read from `env_file`
+pub fn run_args(
+    container_name: &str,
+    deployment: &DeploymentConfig,
+    env_file: Option<&Path>,
+) -> Vec<String> {
+    let DeploymentConfig::Docker {
+        image,
+        tag,
+        ports,
+        volumes,
+        network,
+        runtime,
+        memory_limit,
+        cpu_limit,
+        pids_limit,
+        read_only_rootfs,
+    } = deployment
+    else {
+        return Vec::new();
+    };
+
+    // Not `--rm`: the container is kept after it exits so its logs can be read
+    let mut args = vec![
+        "run".to_string(),
+        "-d".to_string(),
+        "--name".to_string(),
+        container_name.to_string(),
+    ];
+
+    // Add runtime if specified (e.g., nvidia for GPU)
+    if let Some(runtime) = runtime {
+        args.extend(["--runtime".to_string(), runtime.clone()]);
+    }
+    if let Some(network) = network {
+        args.extend(["--network".to_string(), network.clone()]);
+    }
+    for port in ports {
+        args.extend(["-p".to_string(), port.clone()]);
+    }
+    for volume in volumes {
+        args.extend(["-v".to_string(), volume.clone()]);
+    }
+    if let Some(memory_limit) = memory_limit {
+        args.extend(["--memory".to_string(), memory_limit.clone()]);
+    }
+    if let Some(cpu_limit) = cpu_limit {
+        args.extend(["--cpus".to_string(), cpu_limit.clone()]);
+    }
+    if let Some(pids_limit) = pids_limit {
+        args.extend(["--pids-limit".to_string(), pids_limit.to_string()]);
+    }
+    if *read_only_rootfs {
+        args.push("--read-only".to_string());
+    }
+
+    if let Some(env_file) = env_file {
+        args.extend([
+            "--env-file".to_string(),
+            env_file.to_string_lossy().into_owned(),
+        ]);
+    }
+
+    // `--` so nothing after it is read as an option
+    args.push("--".to_string());
+    args.push(format!("{}:{}", image, tag));
+    args
+}
+
+/// Lines of `docker logs --timestamps` output, oldest first. A line without a
+/// timestamp is stamped with the current time.
+pub fn parse_logs(output: &DockerOutput) -> Vec<LogLine> {
+    let parse = |text: &str, stream: LogStream| -> Vec<LogLine> {
+        text.lines()
+            .map(|line| {
+                let stamped = line.split_once(' ').and_then(|(timestamp, rest)| {
+                    let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+                    Some(LogLine {
+                        timestamp: timestamp.with_timezone(&chrono::Utc),
+                        stream,
+                        line: rest.to_string(),
+                    })
+                });
+                stamped.unwrap_or_else(|| LogLine::now(stream, line))
+            })
+            .collect()
+    };
+    let mut lines = parse(&output.stdout, LogStream::Stdout);
+    lines.extend(parse(&output.stderr, LogStream::Stderr));
+    lines.sort_by_key(|line| line.timestamp);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_args() {
+        let deployment = DeploymentConfig::Docker {
+            image: "ghcr.io/acme/mcp-github".to_string(),
+            tag: "1.2".to_string(),
+            ports: vec!["8080:8080".to_string()],
+            volumes: Vec::new(),
+            network: Some("mcp".to_string()),
+            runtime: None,
+            memory_limit: Some("512m".to_string()),
+            cpu_limit: Some("1.5".to_string()),
+            pids_limit: Some(100),
+            read_only_rootfs: true,
+        };
+        assert_eq!(
+            run_args(
+                "mcp-acme-github",
+                &deployment,
+                Some(Path::new("/tmp/mcp-env-1"))
+            ),
+            vec![
+                "run",
+                "-d",
+                "--name",
+                "mcp-acme-github",
+                "--network",
+                "mcp",
+                "-p",
+                "8080:8080",
+                "--memory",
+                "512m",
+                "--cpus",
+                "1.5",
+                "--pids-limit",
+                "100",
+                "--read-only",
+                "--env-file",
+                "/tmp/mcp-env-1",
+                "--",
+                "ghcr.io/acme/mcp-github:1.2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_file_is_private_and_removed_on_drop() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let env = HashMap::from([
+            ("TOKEN".to_string(), "s3cret".to_string()),
+            ("MODE".to_string(), "ro".to_string()),
+        ]);
+        let env_file = EnvFile::write(&env).unwrap();
+        let path = env_file.path().to_path_buf();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "MODE=ro\nTOKEN=s3cret\n"
+        );
+
+        drop(env_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_parse_logs_merges_streams_by_time() {
+        let output = DockerOutput {
+            success: true,
+            stdout: "2025-01-01T00:00:01.5Z one\n2025-01-01T00:00:03Z three\n".to_string(),
+            stderr: "2025-01-01T00:00:02Z two words\n".to_string(),
+        };
+        let lines = parse_logs(&output);
+        let text: Vec<(&str, LogStream)> = lines
+            .iter()
+            .map(|line| (line.line.as_str(), line.stream))
+            .collect();
+        assert_eq!(
+            text,
+            vec![
+                ("one", LogStream::Stdout),
+                ("two words", LogStream::Stderr),
+                ("three", LogStream::Stdout)
+            ]
+        );
+        assert_eq!(
+            lines[0].timestamp.to_rfc3339(),
+            "2025-01-01T00:00:01.500+00:00"
+        );
+
+        let unstamped = DockerOutput {
+            success: true,
+            stdout: "no timestamp here\n".to_string(),
+            stderr: String::new(),
+        };
+        assert_eq!(parse_logs(&unstamped)[0].line, "no timestamp here");
+    }
+}
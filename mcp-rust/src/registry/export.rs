@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{oauth, AuthMethod, MCPServerConfig, RegistryError};
+
+/// Version of the export document; imports of other versions are refused
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Env var names containing any of these are treated as secrets and exported as
+/// placeholders
+const SENSITIVE_ENV_MARKERS: [&str; 6] =
+    ["TOKEN", "SECRET", "PASSWORD", "KEY", "CREDENTIAL", "AUTH"];
+
+/// A tenant's registered servers, without secrets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrationExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub servers: Vec<ExportedServer>,
+}
+
+/// One server's config, with each secret replaced by a [`placeholder`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedServer {
+    pub config: MCPServerConfig,
+    /// Credential fields the server needs supplied with `integration_connect`
+    pub credentials: Vec<String>,
+}
+
+/// What to do with an imported server whose id is already registered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    #[default]
+    Skip,
+    /// Disconnect the registered server and replace its config
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    /// Servers that weren't registered before
+    pub imported: Vec<String>,
+    pub overwritten: Vec<String>,
+    /// Already registered, left as they were
+    pub skipped: Vec<String>,
+    /// Per imported server, the credential fields not yet in the secret store
+    pub missing_credentials: Vec<MissingCredentials>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingCredentials {
+    pub server_id: String,
+    pub credentials: Vec<String>,
+}
+
+/// How a secret appears in an export: `${credential:name}`. At runtime the value is
+/// read from the connection's stored credential `name`.
+pub fn placeholder(name: &str) -> String {
+    format!("${{credential:{}}}", name)
+}
+
+/// The credential a [`placeholder`] refers to
+pub fn placeholder_name(value: &str) -> Option<&str> {
+    value
+        .strip_prefix("${credential:")
+        .and_then(|rest| rest.strip_suffix('}'))
+        .filter(|name| !name.is_empty())
+}
+
+fn is_sensitive_env(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SENSITIVE_ENV_MARKERS
+        .iter()
+        .any(|marker| name.contains(marker))
+}
+
+/// `config` with its secrets replaced by placeholders, and the credential fields it
+/// needs: those the placeholders name plus those its auth method reads
+pub fn redact(config: &MCPServerConfig) -> ExportedServer {
+    let mut config = config.clone();
+    let mut credentials = Vec::new();
+
+    match &mut config.auth_method {
+        AuthMethod::None => {}
+        AuthMethod::ApiKey { .. } => credentials.push("api_key".to_string()),
+        AuthMethod::OAuth2 { client_secret, .. } => {
+            *client_secret = placeholder("client_secret");
+            credentials.extend(
+                [
+                    "client_secret",
+                    oauth::ACCESS_TOKEN_FIELD,
+                    oauth::REFRESH_TOKEN_FIELD,
+                ]
+                .map(String::from),
+            );
+        }
+        AuthMethod::Basic { password, .. } => {
+            *password = placeholder("password");
+            credentials.push("password".to_string());
+        }
+    }
+    for (name, value) in config.env.iter_mut() {
+        if is_sensitive_env(name) && placeholder_name(value).is_none() {
+            *value = placeholder(name);
+        }
+        if let Some(credential) = placeholder_name(value) {
+            credentials.push(credential.to_string());
+        }
+    }
+
+    credentials.sort();
+    credentials.dedup();
+    ExportedServer {
+        config,
+        credentials,
+    }
+}
+
+/// Refuse documents written by a newer (or unknown) exporter
+pub fn check_version(export: &IntegrationExport) -> Result<(), RegistryError> {
+    if export.version != EXPORT_VERSION {
+        return Err(RegistryError::InvalidConfig {
+            field: "version".to_string(),
+            message: format!(
+                "unsupported export version {} (expected {})",
+                export.version, EXPORT_VERSION
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(placeholder("GITHUB_TOKEN"), "${credential:GITHUB_TOKEN}");
+        assert_eq!(
+            placeholder_name("${credential:GITHUB_TOKEN}"),
+            Some("GITHUB_TOKEN")
+        );
+        assert_eq!(placeholder_name("${credential:}"), None);
+        assert_eq!(placeholder_name("ghp_abc"), None);
+        assert!(is_sensitive_env("github_token"));
+        assert!(!is_sensitive_env("LOG_LEVEL"));
+    }
+}
@@ -0,0 +1,336 @@
+use reqwest::header::{HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde_json::{json, Value};
+use std::error::Error as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::debug;
+
+use super::{parse_tools, MCPTool, RegistryError, PROTOCOL_VERSION};
+
+/// Header carrying the session id a server may issue during initialize
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Longest error body kept in `RegistryError::HttpStatus`
+const MAX_ERROR_BODY: usize = 512;
+
+/// Credentials applied to every request to an HTTP MCP server
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpAuth {
+    None,
+    /// API key sent in the named header
+    Header {
+        name: String,
+        value: String,
+    },
+    Basic {
+        username: String,
+        password: String,
+    },
+    Bearer(String),
+}
+
+/// JSON-RPC client for an MCP server reachable over HTTP. Each request is a POST of a
+/// single JSON-RPC message; responses may be plain JSON or a short SSE stream.
+#[derive(Debug)]
+pub struct HttpMcpClient {
+    client: reqwest::Client,
+    endpoint: String,
+    /// Replaced when an OAuth2 access token is refreshed
+    auth: RwLock<HttpAuth>,
+    session_id: Option<String>,
+    server_info: Value,
+    next_id: AtomicU64,
+}
+
+impl HttpMcpClient {
+    /// Connect to `endpoint` and run the initialize handshake. Every request, including
+    /// this one, fails with `RegistryError::Timeout` after `timeout`.
+    pub async fn connect(
+        endpoint: &str,
+        auth: HttpAuth,
+        timeout: Duration,
+    ) -> Result<Self, RegistryError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .use_rustls_tls()
+            .build()
+            .map_err(|e| RegistryError::Tls(e.to_string()))?;
+
+        let mut connection = Self {
+            client,
+            endpoint: endpoint.to_string(),
+            auth: RwLock::new(auth),
+            session_id: None,
+            server_info: Value::Null,
+            next_id: AtomicU64::new(1),
+        };
+
+        let (result, session_id) = connection
+            .send(
+                "initialize",
+                Some(json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "mcp-rust",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                })),
+                None,
+            )
+            .await?;
+        connection.session_id = session_id;
+        connection.server_info = result;
+
+        // Servers may answer notifications with 202 and no body; nothing to check
+        if let Err(e) = connection
+            .post(
+                &json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+                None,
+            )
+            .await
+        {
+            debug!("initialized notification to {} failed: {}", endpoint, e);
+        }
+
+        Ok(connection)
+    }
+
+    /// The `initialize` result: protocol version, capabilities and server info
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Fetch the server's tools
+    pub async fn list_tools(&self) -> Result<Vec<MCPTool>, RegistryError> {
+        parse_tools(&self.request("tools/list", Some(json!({}))).await?)
+    }
+
+    /// Call a tool, returning the `tools/call` result as sent by the server
+    #[allow(dead_code)]
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, RegistryError> {
+        self.request(
+            "tools/call",
+            Some(json!({
+                "name": name,
+                "arguments": arguments
+            })),
+        )
+        .await
+    }
+
+    /// Call a tool, giving up after `timeout` instead of the client's request timeout.
+    /// Abandoning the request closes its connection, which is all HTTP offers to cancel.
+    pub async fn call_tool_within(
+        &self,
+        name: &str,
+        arguments: Value,
+        timeout: Duration,
+    ) -> Result<Value, RegistryError> {
+        self.send(
+            "tools/call",
+            Some(json!({
+                "name": name,
+                "arguments": arguments
+            })),
+            Some(timeout),
+        )
+        .await
+        .map(|(result, _)| result)
+    }
+
+    /// End the server-side session, if the server issued one. Best effort.
+    pub async fn close(&self) {
+        let Some(session_id) = &self.session_id else {
+            return;
+        };
+        let request = self
+            .authorize(self.client.delete(&self.endpoint))
+            .header(SESSION_HEADER, session_id);
+        if let Err(e) = request.send().await {
+            debug!("Failed to end session at {}: {}", self.endpoint, e);
+        }
+    }
+
+    /// Send a JSON-RPC request and return its result
+    pub async fn request(
+        &self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Value, RegistryError> {
+        self.send(method, params, None)
+            .await
+            .map(|(result, _)| result)
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        params: Option<Value>,
+        timeout: Option<Duration>,
+    ) -> Result<(Value, Option<String>), RegistryError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method
+        });
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+
+        let response = self.post(&message, timeout).await?;
+        let session_id = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let is_sse = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        let body = response.text().await.map_err(|e| self.transport_error(e))?;
+
+        let reply = if is_sse {
+            sse_reply(&body, id)?
+        } else {
+            serde_json::from_str(&body).map_err(|e| {
+                RegistryError::Protocol(format!("invalid JSON-RPC response to {}: {}", method, e))
+            })?
+        };
+
+        if let Some(error) = reply.get("error") {
+            return Err(RegistryError::RemoteError {
+                code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            });
+        }
+        let result = reply.get("result").cloned().ok_or_else(|| {
+            RegistryError::Protocol(format!("response to {} has no result", method))
+        })?;
+        Ok((result, session_id))
+    }
+
+    /// POST one message, mapping transport failures and non-2xx statuses to errors.
+    /// `timeout` overrides the client's request timeout.
+    async fn post(
+        &self,
+        message: &Value,
+        timeout: Option<Duration>,
+    ) -> Result<reqwest::Response, RegistryError> {
+        let mut request = self
+            .authorize(self.client.post(&self.endpoint))
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        if let Some(session_id) = &self.session_id {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+
+        let response = request.send().await.map_err(|e| self.transport_error(e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let mut body = response.text().await.unwrap_or_default();
+            if body.len() > MAX_ERROR_BODY {
+                let cut = (0..=MAX_ERROR_BODY)
+                    .rev()
+                    .find(|i| body.is_char_boundary(*i))
+                    .unwrap_or(0);
+                body.truncate(cut);
+            }
+            return Err(RegistryError::HttpStatus {
+                status: status.as_u16(),
+                body,
+            });
+        }
+        Ok(response)
+    }
+
+    /// Use `auth` for the requests from now on
+    pub fn set_auth(&self, auth: HttpAuth) {
+        *self.auth.write().unwrap() = auth;
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &*self.auth.read().unwrap() {
+            HttpAuth::None => request,
+            HttpAuth::Header { name, value } => {
+                match (
+                    HeaderName::from_bytes(name.as_bytes()),
+                    HeaderValue::from_str(value),
+                ) {
+                    (Ok(name), Ok(value)) => request.header(name, value),
+                    _ => {
+                        debug!("Skipping invalid API key header {}", name);
+                        request
+                    }
+                }
+            }
+            HttpAuth::Basic { username, password } => request.basic_auth(username, Some(password)),
+            HttpAuth::Bearer(token) => request.bearer_auth(token),
+        }
+    }
+
+    fn transport_error(&self, e: reqwest::Error) -> RegistryError {
+        if e.is_timeout() {
+            return RegistryError::Timeout(format!("{} did not respond in time", self.endpoint));
+        }
+
+        // reqwest doesn't expose TLS failures as a kind; look for them in the cause chain
+        let mut source = e.source();
+        while let Some(cause) = source {
+            let text = cause.to_string().to_lowercase();
+            if text.contains("certificate") || text.contains("tls") || text.contains("handshake") {
+                return RegistryError::Tls(format!("{}: {}", self.endpoint, cause));
+            }
+            source = cause.source();
+        }
+        RegistryError::ConnectionFailed(format!("{}: {}", self.endpoint, e))
+    }
+}
+
+/// Pick the JSON-RPC response with `id` out of an SSE body
+fn sse_reply(body: &str, id: u64) -> Result<Value, RegistryError> {
+    body.split("\n\n")
+        .filter_map(|event| {
+            let data: Vec<&str> = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect();
+            serde_json::from_str::<Value>(&data.join("\n")).ok()
+        })
+        .find(|message| message.get("id").and_then(Value::as_u64) == Some(id))
+        .ok_or_else(|| {
+            RegistryError::Protocol(format!("event stream has no response with id {}", id))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_reply_picks_matching_id() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\n\
+                    event: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"ok\":true}}\n\n";
+        let reply = sse_reply(body, 7).unwrap();
+        assert_eq!(reply["result"]["ok"], true);
+        assert!(matches!(
+            sse_reply(body, 8),
+            Err(RegistryError::Protocol(_))
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+use async_trait::async_trait;
+use aws_sdk_lambda::config::Region;
+use aws_sdk_lambda::primitives::Blob;
+use aws_sdk_lambda::Client as LambdaClient;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{parse_tools, MCPTool, RegistryError};
+
+/// Raw outcome of one synchronous Lambda invocation
+#[derive(Debug, Clone)]
+pub struct LambdaInvocation {
+    /// `Handled` or `Unhandled` when the function raised; the payload then holds the
+    /// error object (`errorMessage`, `errorType`)
+    pub function_error: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+/// Invokes Lambda functions (the Lambda API in production, a stub in tests)
+#[async_trait]
+pub trait LambdaInvoker: Send + Sync {
+    async fn invoke(
+        &self,
+        region: &str,
+        function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError>;
+}
+
+/// Lambda API invoker with one client per region, derived from the shared SDK config
+pub struct SdkLambdaInvoker {
+    base: LambdaClient,
+    regional: Mutex<HashMap<String, LambdaClient>>,
+}
+
+impl SdkLambdaInvoker {
+    pub fn new(base: LambdaClient) -> Self {
+        Self {
+            base,
+            regional: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn client_for(&self, region: &str) -> LambdaClient {
+        self.regional
+            .lock()
+            .unwrap()
+            .entry(region.to_string())
+            .or_insert_with(|| {
+                let config = self
+                    .base
+                    .config()
+                    .to_builder()
+                    .region(Region::new(region.to_string()))
+                    .build();
+                LambdaClient::from_conf(config)
+            })
+            .clone()
+    }
+}
+
+#[async_trait]
+impl LambdaInvoker for SdkLambdaInvoker {
+    async fn invoke(
+        &self,
+        region: &str,
+        function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let output = self
+            .client_for(region)
+            .invoke()
+            .function_name(function_name)
+            .payload(Blob::new(payload))
+            .send()
+            .await
+            .map_err(|e| {
+                RegistryError::ConnectionFailed(format!(
+                    "Lambda {} in {}: {}",
+                    function_name,
+                    region,
+                    aws_sdk_lambda::error::DisplayErrorContext(e)
+                ))
+            })?;
+
+        Ok(LambdaInvocation {
+            function_error: output.function_error().map(str::to_string),
+            payload: output
+                .payload()
+                .map(|p| p.as_ref().to_vec())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Invoker for backends without a Lambda API (`MCP_BACKEND=memory`); every invocation
+/// fails
+pub struct UnavailableLambdaInvoker;
+
+#[async_trait]
+impl LambdaInvoker for UnavailableLambdaInvoker {
+    async fn invoke(
+        &self,
+        _region: &str,
+        function_name: &str,
+        _payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        Err(RegistryError::ConnectionFailed(format!(
+            "Lambda {}: the in-memory backend can't invoke Lambda functions",
+            function_name
+        )))
+    }
+}
+
+/// MCP client for a server deployed as a Lambda function. Each JSON-RPC request is the
+/// payload of one synchronous invocation and the function returns the JSON-RPC response.
+pub struct LambdaMcpClient {
+    invoker: Arc<dyn LambdaInvoker>,
+    function_name: String,
+    region: String,
+    next_id: AtomicU64,
+}
+
+impl std::fmt::Debug for LambdaMcpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LambdaMcpClient")
+            .field("function_name", &self.function_name)
+            .field("region", &self.region)
+            .finish()
+    }
+}
+
+impl LambdaMcpClient {
+    pub fn new(invoker: Arc<dyn LambdaInvoker>, function_name: &str, region: &str) -> Self {
+        Self {
+            invoker,
+            function_name: function_name.to_string(),
+            region: region.to_string(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// `lambda://<region>:<function>`, recorded as the connection's endpoint
+    pub fn endpoint(&self) -> String {
+        format!("lambda://{}:{}", self.region, self.function_name)
+    }
+
+    /// Fetch the function's tools
+    pub async fn list_tools(&self) -> Result<Vec<MCPTool>, RegistryError> {
+        parse_tools(&self.request("tools/list", json!({})).await?)
+    }
+
+    /// Call a tool, returning the `tools/call` result as sent by the function
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, RegistryError> {
+        self.request(
+            "tools/call",
+            json!({
+                "name": name,
+                "arguments": arguments
+            }),
+        )
+        .await
+    }
+
+    /// Call a tool, failing with a timeout after `timeout`. The invocation itself runs
+    /// to completion in Lambda; only the wait is abandoned.
+    pub async fn call_tool_within(
+        &self,
+        name: &str,
+        arguments: Value,
+        timeout: Duration,
+    ) -> Result<Value, RegistryError> {
+        tokio::time::timeout(timeout, self.call_tool(name, arguments))
+            .await
+            .unwrap_or_else(|_| {
+                Err(RegistryError::Timeout(format!(
+                    "{} did not respond to tools/call in time",
+                    self.endpoint()
+                )))
+            })
+    }
+
+    /// Invoke the function with one JSON-RPC request and return its result
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, RegistryError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+
+        let invocation = self
+            .invoker
+            .invoke(
+                &self.region,
+                &self.function_name,
+                payload.to_string().into_bytes(),
+            )
+            .await?;
+        let reply: Value = serde_json::from_slice(&invocation.payload).unwrap_or(Value::Null);
+
+        if let Some(kind) = invocation.function_error {
+            let message = reply
+                .get("errorMessage")
+                .and_then(Value::as_str)
+                .unwrap_or("no error message");
+            return Err(RegistryError::ConnectionFailed(format!(
+                "Lambda {} failed ({}): {}",
+                self.function_name, kind, message
+            )));
+        }
+
+        if let Some(error) = reply.get("error") {
+            return Err(RegistryError::RemoteError {
+                code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            });
+        }
+        reply.get("result").cloned().ok_or_else(|| {
+            RegistryError::Protocol(format!(
+                "Lambda {} returned no JSON-RPC result for {}",
+                self.function_name, method
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers like an MCP server packaged as a Lambda function, recording where each
+    /// invocation went
+    #[derive(Default)]
+    struct StubInvoker {
+        calls: Mutex<Vec<(String, String, Value)>>,
+    }
+
+    #[async_trait]
+    impl LambdaInvoker for StubInvoker {
+        async fn invoke(
+            &self,
+            region: &str,
+            function_name: &str,
+            payload: Vec<u8>,
+        ) -> Result<LambdaInvocation, RegistryError> {
+            let request: Value = serde_json::from_slice(&payload).unwrap();
+            self.calls.lock().unwrap().push((
+                region.to_string(),
+                function_name.to_string(),
+                request.clone(),
+            ));
+
+            let id = request["id"].clone();
+            let (function_error, reply) = match request["method"].as_str().unwrap() {
+                "tools/list" => (
+                    None,
+                    json!({"jsonrpc": "2.0", "id": id, "result": {"tools": [
+                        {"name": "resize", "description": "Resize an image", "inputSchema": {"type": "object"}}
+                    ]}}),
+                ),
+                "tools/call" if request["params"]["name"] == "resize" => (
+                    None,
+                    json!({"jsonrpc": "2.0", "id": id, "result": {
+                        "content": [{"type": "text", "text": "resized"}]
+                    }}),
+                ),
+                "tools/call" if request["params"]["name"] == "crash" => (
+                    Some("Unhandled".to_string()),
+                    json!({"errorType": "RuntimeError", "errorMessage": "out of memory"}),
+                ),
+                _ => (
+                    None,
+                    json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32601, "message": "Method not found"}}),
+                ),
+            };
+            Ok(LambdaInvocation {
+                function_error,
+                payload: reply.to_string().into_bytes(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lists_and_calls_tools_in_the_server_region() {
+        let invoker = Arc::new(StubInvoker::default());
+        let client = LambdaMcpClient::new(invoker.clone(), "image-tools", "eu-central-1");
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "resize");
+
+        let result = client
+            .call_tool("resize", json!({"width": 100}))
+            .await
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "resized");
+
+        let calls = invoker.calls.lock().unwrap();
+        assert_eq!(calls.len(), 2);
+        for (region, function_name, _) in calls.iter() {
+            assert_eq!(region, "eu-central-1");
+            assert_eq!(function_name, "image-tools");
+        }
+        assert_eq!(calls[1].2["method"], "tools/call");
+        assert_eq!(calls[1].2["params"]["arguments"]["width"], 100);
+        assert_eq!(client.endpoint(), "lambda://eu-central-1:image-tools");
+    }
+
+    #[tokio::test]
+    async fn test_function_errors_map_to_connection_failed() {
+        let client =
+            LambdaMcpClient::new(Arc::new(StubInvoker::default()), "image-tools", "us-east-1");
+
+        match client.call_tool("crash", json!({})).await.unwrap_err() {
+            RegistryError::ConnectionFailed(message) => {
+                assert!(message.contains("Unhandled"), "{}", message);
+                assert!(message.contains("out of memory"), "{}", message);
+            }
+            other => panic!("expected ConnectionFailed, got {:?}", other),
+        }
+
+        assert!(matches!(
+            client.call_tool("missing", json!({})).await.unwrap_err(),
+            RegistryError::RemoteError { code: -32601, .. }
+        ));
+    }
+}
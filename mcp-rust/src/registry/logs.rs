@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Most log lines kept per connection
+pub const LOG_BUFFER_LINES: usize = 500;
+
+/// Which output of the server a line was written to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogLine {
+    pub timestamp: DateTime<Utc>,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+impl LogLine {
+    pub fn now(stream: LogStream, line: &str) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            stream,
+            line: line.to_string(),
+        }
+    }
+}
+
+/// The last [`LOG_BUFFER_LINES`] lines a server wrote. Clones share the lines, so a
+/// reader task can fill the buffer without the registry lock.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<LogLine>>>,
+}
+
+impl LogBuffer {
+    pub fn push(&self, line: LogLine) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == LOG_BUFFER_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Append the lines later than the last one kept; `docker logs --since` repeats
+    /// the lines at its boundary
+    pub fn extend_newer(&self, lines: Vec<LogLine>) {
+        let last = self.last_timestamp();
+        for line in lines {
+            if last.is_none_or(|last| line.timestamp > last) {
+                self.push(line);
+            }
+        }
+    }
+
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// The last `count` lines, oldest first
+    pub fn tail(&self, count: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+
+    pub fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.lines.lock().unwrap().back().map(|line| line.timestamp)
+    }
+
+    /// Read `reader` line by line into the buffer until EOF. Draining the pipe also
+    /// keeps the server from blocking once it has written a pipe buffer's worth.
+    pub fn spawn_reader<R>(&self, name: &str, reader: R, stream: LogStream) -> JoinHandle<()>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let buffer = self.clone();
+        let name = name.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) => buffer.push(LogLine::now(stream, &line)),
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!("Stopped reading {:?} of {}: {}", stream, name, e);
+                        break;
+                    }
+                }
+            }
+        })
+    }
+}
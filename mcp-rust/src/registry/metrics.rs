@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Cumulative counters of one connection. Kept apart from the connection object so a
+/// server that keeps failing and reconnecting shows its whole history.
+#[derive(Debug, Clone, Default)]
+pub struct ServerMetrics {
+    tool_calls: u64,
+    failures: u64,
+    last_error: Option<String>,
+    total_latency: Duration,
+    connected_since: Option<DateTime<Utc>>,
+}
+
+impl ServerMetrics {
+    /// Count a tool call and how long it took; `error` is set when it failed
+    pub fn record_call(&mut self, latency: Duration, error: Option<String>) {
+        self.tool_calls += 1;
+        self.total_latency += latency;
+        if let Some(error) = error {
+            self.failures += 1;
+            self.last_error = Some(error);
+        }
+    }
+
+    /// The connection came up; uptime counts from now unless it already was up
+    pub fn connected(&mut self) {
+        self.connected_since.get_or_insert_with(Utc::now);
+    }
+
+    /// The connection went down, on purpose or not
+    pub fn disconnected(&mut self, error: Option<String>) {
+        self.connected_since = None;
+        if error.is_some() {
+            self.last_error = error;
+        }
+    }
+
+    pub fn report(&self) -> ServerMetricsReport {
+        let average_latency_ms = match self.tool_calls {
+            0 => 0.0,
+            calls => self.total_latency.as_secs_f64() * 1000.0 / calls as f64,
+        };
+        ServerMetricsReport {
+            tool_calls: self.tool_calls,
+            failures: self.failures,
+            error_rate: match self.tool_calls {
+                0 => 0.0,
+                calls => self.failures as f64 / calls as f64,
+            },
+            last_error: self.last_error.clone(),
+            total_latency_ms: self.total_latency.as_millis() as u64,
+            average_latency_ms,
+            connected_since: self.connected_since.map(|since| since.to_rfc3339()),
+            uptime_secs: self
+                .connected_since
+                .map(|since| (Utc::now() - since).num_seconds().max(0) as u64),
+        }
+    }
+}
+
+/// [`ServerMetrics`] as reported by `list_servers`, `integration_test` and `server_stats`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerMetricsReport {
+    pub tool_calls: u64,
+    pub failures: u64,
+    /// `failures / tool_calls`, 0 before the first call
+    pub error_rate: f64,
+    /// Error of the last failed call or health check
+    pub last_error: Option<String>,
+    pub total_latency_ms: u64,
+    pub average_latency_ms: f64,
+    /// RFC 3339 time the connection last came up; absent while it is down
+    pub connected_since: Option<String>,
+    pub uptime_secs: Option<u64>,
+}
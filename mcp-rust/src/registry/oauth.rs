@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::RegistryError;
+
+/// Access tokens expiring within this long are refreshed before they are used
+pub const TOKEN_REFRESH_SKEW: Duration = Duration::from_secs(60);
+
+/// Longest a token endpoint has to answer a refresh
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Credential fields the tokens are stored under, per connection
+pub const ACCESS_TOKEN_FIELD: &str = "access_token";
+pub const REFRESH_TOKEN_FIELD: &str = "refresh_token";
+/// RFC 3339 time the access token expires
+pub const EXPIRES_AT_FIELD: &str = "expires_at";
+
+/// A connection's OAuth2 tokens
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// `None` when the provider didn't say; such tokens are used until rejected
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl OAuthTokens {
+    /// Whether the access token expires within `skew` of `now`
+    pub fn expires_within(&self, now: DateTime<Utc>, skew: Duration) -> bool {
+        self.expires_at
+            .is_some_and(|at| expires_within(at, now, skew))
+    }
+
+    /// The credential fields to store, keeping a refresh token the provider didn't rotate
+    pub fn fields(&self) -> HashMap<String, String> {
+        let mut fields =
+            HashMap::from([(ACCESS_TOKEN_FIELD.to_string(), self.access_token.clone())]);
+        if let Some(refresh_token) = &self.refresh_token {
+            fields.insert(REFRESH_TOKEN_FIELD.to_string(), refresh_token.clone());
+        }
+        if let Some(expires_at) = self.expires_at {
+            fields.insert(EXPIRES_AT_FIELD.to_string(), expires_at.to_rfc3339());
+        }
+        fields
+    }
+}
+
+pub fn expires_within(expires_at: DateTime<Utc>, now: DateTime<Utc>, skew: Duration) -> bool {
+    chrono::Duration::from_std(skew).is_ok_and(|skew| expires_at <= now + skew)
+}
+
+/// What `integration_test` reports about a connection's tokens
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenStatus {
+    /// An access token is stored and hasn't expired
+    pub valid: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// A refresh token is stored and the server has a token endpoint
+    pub refreshable: bool,
+    /// The last refresh was rejected; the user has to authorize again
+    pub needs_reauth: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+}
+
+/// Exchange `refresh_token` at `token_endpoint` (RFC 6749 section 6). A rejected refresh
+/// token fails with `RegistryError::ReauthRequired`; an unreachable or failing endpoint
+/// with `ConnectionFailed`, as it may work on the next try.
+pub async fn refresh(
+    token_endpoint: &str,
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<OAuthTokens, RegistryError> {
+    let client = reqwest::Client::builder()
+        .timeout(TOKEN_REQUEST_TIMEOUT)
+        .use_rustls_tls()
+        .build()
+        .map_err(|e| RegistryError::Tls(e.to_string()))?;
+    let response = client
+        .post(token_endpoint)
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+        ])
+        .send()
+        .await
+        .map_err(|e| {
+            RegistryError::ConnectionFailed(format!("Token endpoint {}: {}", token_endpoint, e))
+        })?;
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if status.is_client_error() {
+        return Err(RegistryError::ReauthRequired(format!(
+            "token endpoint rejected the refresh token (HTTP {}): {}",
+            status.as_u16(),
+            body
+        )));
+    }
+    if !status.is_success() {
+        return Err(RegistryError::HttpStatus {
+            status: status.as_u16(),
+            body,
+        });
+    }
+
+    let token: TokenResponse = serde_json::from_str(&body)
+        .map_err(|e| RegistryError::Protocol(format!("Invalid token endpoint response: {}", e)))?;
+    Ok(OAuthTokens {
+        access_token: token.access_token,
+        refresh_token: token
+            .refresh_token
+            .or_else(|| Some(refresh_token.to_string())),
+        expires_at: token
+            .expires_in
+            .map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_skew() {
+        let now = Utc::now();
+        let tokens = |expires_at| OAuthTokens {
+            access_token: "a".to_string(),
+            refresh_token: None,
+            expires_at,
+        };
+
+        assert!(!tokens(None).expires_within(now, TOKEN_REFRESH_SKEW));
+        assert!(!tokens(Some(now + chrono::Duration::seconds(120)))
+            .expires_within(now, TOKEN_REFRESH_SKEW));
+        assert!(tokens(Some(now + chrono::Duration::seconds(30)))
+            .expires_within(now, TOKEN_REFRESH_SKEW));
+        assert!(
+            tokens(Some(now - chrono::Duration::seconds(1))).expires_within(now, Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use super::{parse_tools, MCPTool, RegistryError, PROTOCOL_VERSION};
+
+/// Requests queued for the writer beyond this make callers wait
+const OUTGOING_QUEUE: usize = 256;
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// A message for the writer task
+enum Outgoing {
+    Request {
+        method: String,
+        params: Value,
+        /// Receives the id the writer assigned, so a timed-out caller can drop its entry
+        assigned: oneshot::Sender<u64>,
+        reply: oneshot::Sender<Value>,
+    },
+    Notification {
+        method: String,
+        params: Option<Value>,
+    },
+}
+
+/// JSON-RPC client for an MCP server speaking newline-delimited JSON over stdio.
+///
+/// Callers never touch the child's stdin: requests go through a channel to a single
+/// writer task, which assigns ids and registers each request in the pending map before
+/// writing its line. A reader task fulfils pending requests as responses arrive, in any
+/// order, so any number of concurrent calls share one process safely.
+pub struct StdioMcpClient {
+    name: String,
+    outgoing: mpsc::Sender<Outgoing>,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+    request_timeout: Duration,
+    reader: JoinHandle<()>,
+}
+
+impl std::fmt::Debug for StdioMcpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdioMcpClient")
+            .field("name", &self.name)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+impl StdioMcpClient {
+    /// Start the writer and reader tasks over a child's stdin and stdout. `name`
+    /// identifies the server in errors.
+    pub fn new<W, R>(name: &str, stdin: W, stdout: R, request_timeout: Duration) -> Self
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        let (outgoing, queue) = mpsc::channel(OUTGOING_QUEUE);
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn(write_loop(
+            name.to_string(),
+            stdin,
+            queue,
+            pending.clone(),
+            closed.clone(),
+        ));
+        let reader = tokio::spawn(read_loop(
+            name.to_string(),
+            stdout,
+            pending.clone(),
+            closed.clone(),
+        ));
+
+        Self {
+            name: name.to_string(),
+            outgoing,
+            pending,
+            closed,
+            request_timeout,
+            reader,
+        }
+    }
+
+    /// Run the initialize handshake, returning the server's `initialize` result
+    pub async fn initialize(&self) -> Result<Value, RegistryError> {
+        let info = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "mcp-rust",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                }),
+            )
+            .await?;
+        self.outgoing
+            .send(Outgoing::Notification {
+                method: "notifications/initialized".to_string(),
+                params: None,
+            })
+            .await
+            .map_err(|_| self.closed_error())?;
+        Ok(info)
+    }
+
+    /// Fetch the server's tools
+    pub async fn list_tools(&self) -> Result<Vec<MCPTool>, RegistryError> {
+        parse_tools(&self.request("tools/list", json!({})).await?)
+    }
+
+    /// Call a tool, returning the `tools/call` result as sent by the server
+    #[allow(dead_code)]
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, RegistryError> {
+        self.call_tool_within(name, arguments, self.request_timeout)
+            .await
+    }
+
+    /// Call a tool, giving up (and cancelling it downstream) after `timeout`
+    pub async fn call_tool_within(
+        &self,
+        name: &str,
+        arguments: Value,
+        timeout: Duration,
+    ) -> Result<Value, RegistryError> {
+        self.request_within(
+            "tools/call",
+            json!({
+                "name": name,
+                "arguments": arguments
+            }),
+            timeout,
+        )
+        .await
+    }
+
+    /// Send a JSON-RPC request and wait up to the request timeout for its result
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, RegistryError> {
+        self.request_within(method, params, self.request_timeout)
+            .await
+    }
+
+    /// Send a JSON-RPC request and wait up to `timeout` for its result. A request that
+    /// times out is forgotten and, except for `initialize`, cancelled with
+    /// `notifications/cancelled` so the server can stop working on it.
+    pub async fn request_within(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, RegistryError> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(self.closed_error());
+        }
+
+        let (assigned, id) = oneshot::channel();
+        let (reply, rx) = oneshot::channel();
+        self.outgoing
+            .send(Outgoing::Request {
+                method: method.to_string(),
+                params,
+                assigned,
+                reply,
+            })
+            .await
+            .map_err(|_| self.closed_error())?;
+        let id = id.await.map_err(|_| self.closed_error())?;
+
+        let reply = match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => {
+                return Err(RegistryError::ConnectionFailed(format!(
+                    "{} exited before responding to {}",
+                    self.name, method
+                )))
+            }
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                if method != "initialize" {
+                    let cancel = Outgoing::Notification {
+                        method: "notifications/cancelled".to_string(),
+                        params: Some(json!({"requestId": id, "reason": "request timed out"})),
+                    };
+                    // Best effort: the writer may already be gone
+                    let _ = self.outgoing.try_send(cancel);
+                }
+                return Err(RegistryError::Timeout(format!(
+                    "{} did not respond to {} in time",
+                    self.name, method
+                )));
+            }
+        };
+
+        if let Some(error) = reply.get("error") {
+            return Err(RegistryError::RemoteError {
+                code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            });
+        }
+        reply
+            .get("result")
+            .cloned()
+            .ok_or_else(|| RegistryError::Protocol(format!("response to {} has no result", method)))
+    }
+
+    /// Requests still waiting for a response
+    #[allow(dead_code)]
+    pub async fn pending_requests(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+
+    /// Whether the server's stdout or stdin has closed
+    #[allow(dead_code)]
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    fn closed_error(&self) -> RegistryError {
+        RegistryError::ServerNotConnected(self.name.clone())
+    }
+}
+
+impl Drop for StdioMcpClient {
+    fn drop(&mut self) {
+        // The writer stops once `outgoing` is dropped
+        self.reader.abort();
+    }
+}
+
+/// Write queued messages one line at a time, registering requests before they are sent
+async fn write_loop<W: AsyncWrite + Unpin>(
+    name: String,
+    mut stdin: W,
+    mut queue: mpsc::Receiver<Outgoing>,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+) {
+    let mut next_id: u64 = 1;
+    while let Some(message) = queue.recv().await {
+        let line = match message {
+            Outgoing::Request {
+                method,
+                params,
+                assigned,
+                reply,
+            } => {
+                let id = next_id;
+                next_id += 1;
+                pending.lock().await.insert(id, reply);
+                // The caller may already have given up, and no response comes once the
+                // server's output has closed
+                if assigned.send(id).is_err() || closed.load(Ordering::SeqCst) {
+                    pending.lock().await.remove(&id);
+                    continue;
+                }
+                json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params})
+            }
+            Outgoing::Notification { method, params } => {
+                let mut notification = json!({"jsonrpc": "2.0", "method": method});
+                if let Some(params) = params {
+                    notification["params"] = params;
+                }
+                notification
+            }
+        };
+
+        let mut bytes = line.to_string().into_bytes();
+        bytes.push(b'\n');
+        let written = async {
+            stdin.write_all(&bytes).await?;
+            stdin.flush().await
+        };
+        if let Err(e) = written.await {
+            warn!("Writing to {} failed: {}", name, e);
+            break;
+        }
+    }
+
+    // Dropping the pending senders fails every waiting request
+    closed.store(true, Ordering::SeqCst);
+    pending.lock().await.clear();
+}
+
+/// Route each response line to the request waiting for its id
+async fn read_loop<R: AsyncRead + Unpin>(
+    name: String,
+    stdout: R,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Reading from {} failed: {}", name, e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(_) => {
+                debug!("Ignoring non-JSON output from {}: {}", name, line);
+                continue;
+            }
+        };
+
+        match (
+            message.get("id").and_then(Value::as_u64),
+            message.get("method"),
+        ) {
+            (Some(id), None) => match pending.lock().await.remove(&id) {
+                Some(sender) => {
+                    let _ = sender.send(message);
+                }
+                None => debug!("Dropping response with unknown id {} from {}", id, name),
+            },
+            _ => debug!("Ignoring message from {}: {}", name, line),
+        }
+    }
+
+    debug!("{} closed its output", name);
+    closed.store(true, Ordering::SeqCst);
+    pending.lock().await.clear();
+}
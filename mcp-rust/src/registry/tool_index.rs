@@ -0,0 +1,75 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// Which servers provide each tool, per tenant. Lets callers name a downstream tool
+/// without its `server_id.` prefix when only one server provides it.
+#[derive(Debug, Default)]
+pub struct ToolIndex {
+    /// tenant -> tool name -> ids of servers providing it
+    tenants: HashMap<String, HashMap<String, BTreeSet<String>>>,
+}
+
+impl ToolIndex {
+    /// Replace the tools indexed for a server
+    pub fn set_server_tools<'a>(
+        &mut self,
+        tenant_id: &str,
+        server_id: &str,
+        tools: impl IntoIterator<Item = &'a str>,
+    ) {
+        self.remove_server(tenant_id, server_id);
+        let tenant = self.tenants.entry(tenant_id.to_string()).or_default();
+        for tool in tools {
+            tenant
+                .entry(tool.to_string())
+                .or_default()
+                .insert(server_id.to_string());
+        }
+    }
+
+    /// Forget every tool of a server
+    pub fn remove_server(&mut self, tenant_id: &str, server_id: &str) {
+        let Some(tenant) = self.tenants.get_mut(tenant_id) else {
+            return;
+        };
+        tenant.retain(|_, servers| {
+            servers.remove(server_id);
+            !servers.is_empty()
+        });
+        if tenant.is_empty() {
+            self.tenants.remove(tenant_id);
+        }
+    }
+
+    /// Ids of the servers providing `tool`, sorted
+    pub fn servers_for(&self, tenant_id: &str, tool: &str) -> Vec<String> {
+        self.tenants
+            .get(tenant_id)
+            .and_then(|tenant| tenant.get(tool))
+            .map(|servers| servers.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replacing_and_removing_server_tools() {
+        let mut index = ToolIndex::default();
+        index.set_server_tools("acme", "github", ["search", "create_issue"]);
+        index.set_server_tools("acme", "jira", ["search"]);
+        index.set_server_tools("other", "github", ["search"]);
+
+        assert_eq!(index.servers_for("acme", "search"), vec!["github", "jira"]);
+        assert_eq!(index.servers_for("acme", "create_issue"), vec!["github"]);
+
+        index.set_server_tools("acme", "github", ["search"]);
+        assert!(index.servers_for("acme", "create_issue").is_empty());
+
+        index.remove_server("acme", "jira");
+        assert_eq!(index.servers_for("acme", "search"), vec!["github"]);
+        assert_eq!(index.servers_for("other", "search"), vec!["github"]);
+        assert!(index.servers_for("missing", "search").is_empty());
+    }
+}
@@ -0,0 +1,464 @@
+use base64::Engine;
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock as StdRwLock, Weak};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{oneshot, watch, Mutex};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+use super::http::HttpAuth;
+use super::{parse_tools, MCPTool, RegistryError, PROTOCOL_VERSION};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Timeouts and reconnection behaviour for a WebSocket MCP connection
+#[derive(Debug, Clone)]
+pub struct WebSocketOptions {
+    /// Longest to wait for the response to any one request
+    pub request_timeout: Duration,
+    /// Reopen the socket (and re-fetch tools) when it drops
+    pub auto_reconnect: bool,
+    /// First reconnect delay; doubles after every failed attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(30),
+            auto_reconnect: true,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// JSON-RPC client for an MCP server reachable over a WebSocket.
+///
+/// A reader task correlates responses with pending requests by id and handles
+/// server-initiated messages: `notifications/tools/list_changed` refreshes the cached
+/// tool list, and `ping` requests are answered. When the socket drops, pending requests
+/// fail and, with `auto_reconnect`, the socket is reopened with exponential backoff.
+/// Each socket opened is a new generation; a reader only tears down its own.
+#[derive(Debug)]
+pub struct WebSocketMcpClient {
+    url: String,
+    auth: HttpAuth,
+    options: WebSocketOptions,
+    sink: Mutex<Option<SplitSink<Socket, Message>>>,
+    pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    tools: watch::Sender<Vec<MCPTool>>,
+    server_info: StdRwLock<Value>,
+    next_id: AtomicU64,
+    /// Generation of the socket in `sink`, bumped whenever one is opened
+    generation: AtomicU64,
+    connected: AtomicBool,
+    closed: AtomicBool,
+}
+
+impl WebSocketMcpClient {
+    /// Open the socket, run the initialize handshake and fetch tools
+    pub async fn connect(
+        url: &str,
+        auth: HttpAuth,
+        options: WebSocketOptions,
+    ) -> Result<Arc<Self>, RegistryError> {
+        let client = Arc::new(Self {
+            url: url.to_string(),
+            auth,
+            options,
+            sink: Mutex::new(None),
+            pending: Mutex::new(HashMap::new()),
+            tools: watch::Sender::new(Vec::new()),
+            server_info: StdRwLock::new(Value::Null),
+            next_id: AtomicU64::new(1),
+            generation: AtomicU64::new(0),
+            connected: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+        });
+        client.open().await?;
+        Ok(client)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The latest `initialize` result
+    pub fn server_info(&self) -> Value {
+        self.server_info.read().unwrap().clone()
+    }
+
+    /// Tools as of the last `tools/list`
+    pub fn tools(&self) -> Vec<MCPTool> {
+        self.tools.borrow().clone()
+    }
+
+    /// Notified with every tool list fetched after this call, e.g. on
+    /// `notifications/tools/list_changed` or a reconnect
+    pub fn watch_tools(&self) -> watch::Receiver<Vec<MCPTool>> {
+        self.tools.subscribe()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// Call a tool, returning the `tools/call` result as sent by the server
+    #[allow(dead_code)]
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, RegistryError> {
+        self.call_tool_within(name, arguments, self.options.request_timeout)
+            .await
+    }
+
+    /// Call a tool, giving up (and cancelling it downstream) after `timeout`
+    pub async fn call_tool_within(
+        &self,
+        name: &str,
+        arguments: Value,
+        timeout: Duration,
+    ) -> Result<Value, RegistryError> {
+        self.request_within(
+            "tools/call",
+            json!({
+                "name": name,
+                "arguments": arguments
+            }),
+            timeout,
+        )
+        .await
+    }
+
+    /// Close the socket and stop reconnecting
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        if let Some(mut sink) = self.sink.lock().await.take() {
+            let _ = sink.close().await;
+        }
+    }
+
+    /// Send a JSON-RPC request and wait up to `request_timeout` for its result
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, RegistryError> {
+        self.request_within(method, params, self.options.request_timeout)
+            .await
+    }
+
+    /// Send a JSON-RPC request and wait up to `timeout` for its result. A request that
+    /// times out is forgotten and, except for `initialize`, cancelled with
+    /// `notifications/cancelled`.
+    pub async fn request_within(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, RegistryError> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+        if let Err(e) = self.send(&message).await {
+            self.pending.lock().await.remove(&id);
+            return Err(e);
+        }
+
+        let reply = match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => {
+                return Err(RegistryError::ConnectionFailed(format!(
+                    "{} closed before responding to {}",
+                    self.url, method
+                )))
+            }
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                if method != "initialize" {
+                    let cancel = json!({
+                        "jsonrpc": "2.0",
+                        "method": "notifications/cancelled",
+                        "params": {"requestId": id, "reason": "request timed out"}
+                    });
+                    if let Err(e) = self.send(&cancel).await {
+                        debug!("Could not cancel request {} at {}: {}", id, self.url, e);
+                    }
+                }
+                return Err(RegistryError::Timeout(format!(
+                    "{} did not respond to {} in time",
+                    self.url, method
+                )));
+            }
+        };
+
+        if let Some(error) = reply.get("error") {
+            return Err(RegistryError::RemoteError {
+                code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            });
+        }
+        reply
+            .get("result")
+            .cloned()
+            .ok_or_else(|| RegistryError::Protocol(format!("response to {} has no result", method)))
+    }
+
+    async fn send(&self, message: &Value) -> Result<(), RegistryError> {
+        let mut sink = self.sink.lock().await;
+        let sink = sink
+            .as_mut()
+            .ok_or_else(|| RegistryError::ServerNotConnected(self.url.clone()))?;
+        sink.send(Message::text(message.to_string()))
+            .await
+            .map_err(|e| RegistryError::ConnectionFailed(format!("{}: {}", self.url, e)))
+    }
+
+    /// Open a socket, start its reader and run the handshake
+    async fn open(self: &Arc<Self>) -> Result<(), RegistryError> {
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| RegistryError::ConnectionFailed(format!("{}: {}", self.url, e)))?;
+        if let Some((name, value)) = auth_header(&self.auth) {
+            request.headers_mut().insert(name, value);
+        }
+
+        let connecting = tokio_tungstenite::connect_async(request);
+        let (socket, _) = tokio::time::timeout(self.options.request_timeout, connecting)
+            .await
+            .map_err(|_| RegistryError::Timeout(format!("{} did not accept in time", self.url)))?
+            .map_err(|e| connect_error(&self.url, e))?;
+        let (sink, stream) = socket.split();
+        let generation = {
+            let mut slot = self.sink.lock().await;
+            *slot = Some(sink);
+            self.generation.fetch_add(1, Ordering::SeqCst) + 1
+        };
+        self.connected.store(true, Ordering::SeqCst);
+        tokio::spawn(read_loop(Arc::downgrade(self), stream, generation));
+
+        let info = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "mcp-rust",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                }),
+            )
+            .await?;
+        *self.server_info.write().unwrap() = info;
+        self.send(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await?;
+        self.refresh_tools().await
+    }
+
+    async fn refresh_tools(&self) -> Result<(), RegistryError> {
+        let tools = parse_tools(&self.request("tools/list", json!({})).await?)?;
+        debug!("{} lists {} tools", self.url, tools.len());
+        // A list that arrives after `close` would outlive the connection's tools
+        if !self.closed.load(Ordering::SeqCst) {
+            self.tools.send_replace(tools);
+        }
+        Ok(())
+    }
+
+    /// Handle one message from the server
+    async fn dispatch(self: &Arc<Self>, message: Value) {
+        let method = message.get("method").and_then(Value::as_str);
+        match (message.get("id"), method) {
+            // A response to one of our requests
+            (Some(id), None) => {
+                let sender = match id.as_u64() {
+                    Some(id) => self.pending.lock().await.remove(&id),
+                    None => None,
+                };
+                match sender {
+                    Some(sender) => {
+                        let _ = sender.send(message);
+                    }
+                    None => debug!("Dropping response with unknown id {} from {}", id, self.url),
+                }
+            }
+            (None, Some("notifications/tools/list_changed")) => {
+                // Refresh off the reader task: the refresh waits on a response it reads
+                let client = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = client.refresh_tools().await {
+                        warn!("Failed to refresh tools from {}: {}", client.url, e);
+                    }
+                });
+            }
+            (Some(id), Some(method)) => {
+                let reply = if method == "ping" {
+                    json!({"jsonrpc": "2.0", "id": id, "result": {}})
+                } else {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": -32601, "message": format!("Method not found: {}", method)}
+                    })
+                };
+                if let Err(e) = self.send(&reply).await {
+                    debug!("Failed to answer {} from {}: {}", method, self.url, e);
+                }
+            }
+            (None, Some(method)) => debug!("Ignoring {} from {}", method, self.url),
+            (None, None) => debug!("Ignoring malformed message from {}", self.url),
+        }
+    }
+
+    /// The socket of `generation` dropped: fail pending requests and reconnect if
+    /// configured. A socket that's since been replaced, e.g. one whose handshake timed
+    /// out before a reconnect, leaves the current one alone.
+    async fn on_disconnect(self: Arc<Self>, generation: u64) {
+        {
+            let mut sink = self.sink.lock().await;
+            if self.generation.load(Ordering::SeqCst) != generation {
+                debug!("Ignoring close of a replaced WebSocket to {}", self.url);
+                return;
+            }
+            sink.take();
+        }
+        self.connected.store(false, Ordering::SeqCst);
+        // Dropping the senders fails every pending request
+        self.pending.lock().await.clear();
+
+        if self.closed.load(Ordering::SeqCst) || !self.options.auto_reconnect {
+            info!("WebSocket to {} closed", self.url);
+            return;
+        }
+
+        let weak = Arc::downgrade(&self);
+        drop(self);
+        tokio::spawn(reconnect(weak));
+    }
+}
+
+/// Read messages until the socket of `generation` closes
+async fn read_loop(
+    client: Weak<WebSocketMcpClient>,
+    mut stream: SplitStream<Socket>,
+    generation: u64,
+) {
+    while let Some(frame) = stream.next().await {
+        let Some(client) = client.upgrade() else {
+            return;
+        };
+        match frame {
+            Ok(Message::Text(text)) => match serde_json::from_str::<Value>(text.as_str()) {
+                Ok(message) => client.dispatch(message).await,
+                Err(e) => debug!("Ignoring non-JSON message from {}: {}", client.url, e),
+            },
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("WebSocket to {} failed: {}", client.url, e);
+                break;
+            }
+        }
+    }
+
+    if let Some(client) = client.upgrade() {
+        client.on_disconnect(generation).await;
+    }
+}
+
+/// Reopen the socket with exponential backoff until it succeeds, the client is closed
+/// or dropped. Boxed because opening a socket spawns the reader that may call this.
+fn reconnect(client: Weak<WebSocketMcpClient>) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let mut attempt: u32 = 0;
+        loop {
+            let Some(strong) = client.upgrade() else {
+                return;
+            };
+            let delay = strong
+                .options
+                .initial_backoff
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(strong.options.max_backoff);
+            drop(strong);
+            tokio::time::sleep(delay).await;
+
+            let Some(strong) = client.upgrade() else {
+                return;
+            };
+            if strong.closed.load(Ordering::SeqCst) {
+                return;
+            }
+            match strong.open().await {
+                Ok(()) => {
+                    info!("Reconnected to {}", strong.url);
+                    return;
+                }
+                Err(e) => {
+                    attempt = attempt.saturating_add(1);
+                    warn!(
+                        "Reconnect to {} failed (attempt {}): {}",
+                        strong.url, attempt, e
+                    );
+                }
+            }
+        }
+    })
+}
+
+/// Header for the upgrade request, if the auth method uses one
+fn auth_header(auth: &HttpAuth) -> Option<(HeaderName, HeaderValue)> {
+    let (name, value) = match auth {
+        HttpAuth::None => return None,
+        HttpAuth::Header { name, value } => (name.clone(), value.clone()),
+        HttpAuth::Basic { username, password } => {
+            let encoded = base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", username, password));
+            ("authorization".to_string(), format!("Basic {}", encoded))
+        }
+        HttpAuth::Bearer(token) => ("authorization".to_string(), format!("Bearer {}", token)),
+    };
+    match (
+        HeaderName::from_bytes(name.as_bytes()),
+        HeaderValue::from_str(&value),
+    ) {
+        (Ok(name), Ok(value)) => Some((name, value)),
+        _ => {
+            debug!("Skipping invalid auth header {}", name);
+            None
+        }
+    }
+}
+
+fn connect_error(url: &str, e: tokio_tungstenite::tungstenite::Error) -> RegistryError {
+    use tokio_tungstenite::tungstenite::Error;
+    match e {
+        Error::Http(response) => RegistryError::HttpStatus {
+            status: response.status().as_u16(),
+            body: String::new(),
+        },
+        Error::Tls(e) => RegistryError::Tls(format!("{}: {}", url, e)),
+        e => RegistryError::ConnectionFailed(format!("{}: {}", url, e)),
+    }
+}
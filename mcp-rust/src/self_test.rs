@@ -0,0 +1,353 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::{Backend, Config};
+use crate::mcp::{MCPRequest, MCPServer};
+use crate::tenant::{TenantManager, TenantSession};
+
+/// Tenant and user the self-test runs as, so its data stays out of every real tenant's
+pub const SELF_TEST_TENANT: &str = "self-test";
+pub const SELF_TEST_USER: &str = "self-test";
+
+/// Tools the scripted sequence calls, which `tools/list` must offer
+const REQUIRED_TOOLS: &[&str] = &[
+    "kv_set",
+    "kv_get",
+    "kv_delete",
+    "artifacts_put",
+    "artifacts_get",
+    "artifacts_delete_prefix",
+    "events_send",
+    "events_query",
+    "rate_limit_status",
+];
+
+const PING_DETAIL_TYPE: &str = "self_test.ping";
+
+/// How often and how far apart `events_query` looks for the ping; on AWS the event
+/// reaches the history table through EventBridge, a moment after it's sent
+const EVENT_QUERY_ATTEMPTS: u32 = 10;
+const EVENT_QUERY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How one step of the self-test went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub duration: Duration,
+    /// Why the step failed
+    pub error: Option<String>,
+}
+
+/// Every step of a self-test run, in the order they ran
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub backend: Backend,
+    pub steps: Vec<StepResult>,
+    pub duration: Duration,
+}
+
+impl SelfTestReport {
+    /// Whether every step passed
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.passed)
+    }
+
+    /// One line per step with its timing, then a summary line
+    pub fn render(&self) -> String {
+        let backend = match self.backend {
+            Backend::Aws => "aws",
+            Backend::Memory => "memory",
+        };
+        let width = self
+            .steps
+            .iter()
+            .map(|step| step.name.len())
+            .max()
+            .unwrap_or_default();
+        let mut out = format!("Self-test against the {} backend\n", backend);
+        for step in &self.steps {
+            out.push_str(&format!(
+                "  {} {:<width$} {:>6} ms",
+                if step.passed { "PASS" } else { "FAIL" },
+                step.name,
+                step.duration.as_millis(),
+                width = width
+            ));
+            if let Some(error) = &step.error {
+                out.push_str(&format!("  {}", error));
+            }
+            out.push('\n');
+        }
+        let passed = self.steps.iter().filter(|step| step.passed).count();
+        out.push_str(&format!(
+            "{}/{} steps passed in {} ms",
+            passed,
+            self.steps.len(),
+            self.duration.as_millis()
+        ));
+        out
+    }
+}
+
+/// The config a self-test runs with: `config`, on the in-memory backend unless
+/// `live`, with the self-test tenant as the default so it registers itself
+pub fn self_test_config(config: &Config, live: bool) -> Config {
+    let mut config = config.clone();
+    if !live {
+        config.aws.backend = Backend::Memory;
+    }
+    config.default_tenant_id = Some(SELF_TEST_TENANT.to_string());
+    config.default_user_id = Some(SELF_TEST_USER.to_string());
+    config
+}
+
+/// `--self-test`: boot the server in process with `config` (see [`self_test_config`])
+/// and run the scripted sequence through it as a client would: initialize,
+/// tools/list, a KV write, read and delete, an artifact write and read, an event sent
+/// and queried back and a rate limit probe, then clean up. Every step runs and is
+/// timed even after one fails. An error means the server couldn't start.
+pub async fn run(config: Config) -> anyhow::Result<SelfTestReport> {
+    let started = Instant::now();
+    let backend = config.aws.backend;
+    let tenant_manager = Arc::new(TenantManager::with_config(Arc::new(config)).await?);
+    let server = MCPServer::new(tenant_manager, None).await?;
+    let client = Client::open(&server).await?;
+    let steps = client.run_steps().await;
+    server.shutdown().await;
+    Ok(SelfTestReport {
+        backend,
+        steps,
+        duration: started.elapsed(),
+    })
+}
+
+/// The self-test's side of one session
+struct Client<'a> {
+    server: &'a MCPServer,
+    session: Arc<TenantSession>,
+    next_id: AtomicU64,
+    /// Prefix of every key written, unique to this run
+    prefix: String,
+}
+
+impl<'a> Client<'a> {
+    async fn open(server: &'a MCPServer) -> anyhow::Result<Client<'a>> {
+        let request = MCPRequest {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: "initialize".to_string(),
+            params: None,
+            tenant_id: Some(SELF_TEST_TENANT.to_string()),
+            user_id: Some(SELF_TEST_USER.to_string()),
+            session_token: None,
+        };
+        let session = server
+            .open_session(&request)
+            .await
+            .map_err(|e| anyhow::anyhow!("couldn't open the self-test session: {}", e))?;
+        Ok(Self {
+            server,
+            session,
+            next_id: AtomicU64::new(1),
+            prefix: format!("self-test/{}", uuid::Uuid::new_v4()),
+        })
+    }
+
+    async fn run_steps(&self) -> Vec<StepResult> {
+        let key = format!("{}/kv", self.prefix);
+        let artifact = format!("{}/artifact.txt", self.prefix);
+        let content = general_purpose::STANDARD.encode(self.prefix.as_bytes());
+
+        vec![
+            step("initialize", async {
+                let result = self
+                    .call(
+                        "initialize",
+                        json!({
+                            "protocolVersion": "2025-06-18",
+                            "clientInfo": {"name": "self-test", "version": crate::cli::VERSION}
+                        }),
+                    )
+                    .await?;
+                expect(
+                    result["protocolVersion"].is_string(),
+                    "no protocolVersion",
+                    &result,
+                )
+            })
+            .await,
+            step("tools/list", async {
+                let result = self.call("tools/list", json!({})).await?;
+                let listed: Vec<&str> = result["tools"]
+                    .as_array()
+                    .map(|tools| tools.iter().filter_map(|t| t["name"].as_str()).collect())
+                    .unwrap_or_default();
+                let missing: Vec<&str> = REQUIRED_TOOLS
+                    .iter()
+                    .copied()
+                    .filter(|tool| !listed.contains(tool))
+                    .collect();
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!("not listed: {}", missing.join(", ")))
+                }
+            })
+            .await,
+            step("kv_set", async {
+                self.tool("kv_set", json!({"key": key, "value": self.prefix, "ttl_hours": 1}))
+                    .await
+                    .map(drop)
+            })
+            .await,
+            step("kv_get", async {
+                let result = self.tool("kv_get", json!({"key": key})).await?;
+                expect(
+                    result["value"] == self.prefix.as_str(),
+                    "read back a different value",
+                    &result,
+                )
+            })
+            .await,
+            step("kv_delete", async {
+                self.tool("kv_delete", json!({"key": key})).await?;
+                let result = self
+                    .tool("kv_get", json!({"key": key, "fresh": true}))
+                    .await?;
+                expect(
+                    result["value"].is_null(),
+                    "the key is still there",
+                    &result,
+                )
+            })
+            .await,
+            step("artifacts_put", async {
+                self.tool(
+                    "artifacts_put",
+                    json!({
+                        "key": artifact,
+                        "content": content,
+                        "content_type": "text/plain"
+                    }),
+                )
+                .await
+                .map(drop)
+            })
+            .await,
+            step("artifacts_get", async {
+                let result = self.tool("artifacts_get", json!({"key": artifact})).await?;
+                expect(
+                    result["content"] == content.as_str(),
+                    "read back different content",
+                    &result,
+                )
+            })
+            .await,
+            step("events_send", async {
+                self.tool(
+                    "events_send",
+                    json!({
+                        "events": [{"detailType": PING_DETAIL_TYPE, "detail": {"run": self.prefix}}],
+                        "buffered": false
+                    }),
+                )
+                .await
+                .map(drop)
+            })
+            .await,
+            step("events_query", async {
+                for attempt in 1..=EVENT_QUERY_ATTEMPTS {
+                    let result = self
+                        .tool(
+                            "events_query",
+                            json!({
+                                "userId": SELF_TEST_USER,
+                                "detailType": PING_DETAIL_TYPE,
+                                "limit": 50
+                            }),
+                        )
+                        .await?;
+                    if result["events"].to_string().contains(&self.prefix) {
+                        return Ok(());
+                    }
+                    if attempt < EVENT_QUERY_ATTEMPTS {
+                        tokio::time::sleep(EVENT_QUERY_INTERVAL).await;
+                    }
+                }
+                Err("the event sent wasn't found".to_string())
+            })
+            .await,
+            step("rate_limit_status", async {
+                let result = self.tool("rate_limit_status", json!({})).await?;
+                expect(
+                    result["tenant_id"] == SELF_TEST_TENANT
+                        && result["buckets"].is_array()
+                        && result["requests_per_minute"]["requests"].as_u64() > Some(0),
+                    "this run's requests weren't counted",
+                    &result,
+                )
+            })
+            .await,
+            step("cleanup", async {
+                let prefix = format!("{}/", self.prefix);
+                self.tool("artifacts_delete_prefix", json!({"prefix": prefix}))
+                    .await
+                    .map(drop)
+            })
+            .await,
+        ]
+    }
+
+    /// The result of a JSON-RPC request in the session, or its error's message
+    async fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+        let request = MCPRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(self.next_id.fetch_add(1, Ordering::Relaxed))),
+            method: method.to_string(),
+            params: Some(params),
+            tenant_id: None,
+            user_id: None,
+            session_token: None,
+        };
+        let response = self
+            .server
+            .handle_session_request(self.session.clone(), request)
+            .await
+            .ok_or_else(|| "no response".to_string())?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(format!("{} ({})", error.message, error.code)),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err("an empty response".to_string()),
+        }
+    }
+
+    async fn tool(&self, name: &str, arguments: Value) -> Result<Value, String> {
+        self.call("tools/call", json!({"name": name, "arguments": arguments}))
+            .await
+    }
+}
+
+/// Run and time the step `name`
+async fn step(name: &'static str, run: impl Future<Output = Result<(), String>>) -> StepResult {
+    let started = Instant::now();
+    let outcome = run.await;
+    StepResult {
+        name,
+        passed: outcome.is_ok(),
+        duration: started.elapsed(),
+        error: outcome.err(),
+    }
+}
+
+fn expect(ok: bool, problem: &str, result: &Value) -> Result<(), String> {
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("{}: {}", problem, result))
+    }
+}
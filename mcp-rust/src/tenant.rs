@@ -1,10 +1,18 @@
-use crate::rate_limiting::{AwsOperation, AwsRateLimiter, AwsServiceLimits};
+use crate::config::Config;
+use crate::rate_limiting::distributed::{DistributedLimiter, DynamoBucketStore};
+use crate::rate_limiting::{
+    AwsOperation, AwsRateLimiter, AwsServiceLimits, LegacyWindowStatus, OperationPriority,
+    RateLimitAlgorithm, RateLimitRejection, RateLimitStatus, RemainingQuota,
+};
+use crate::transport::replay::ReplayWindow;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicI64, AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tracing::{info, warn};
 use uuid::Uuid;
 
@@ -17,6 +25,12 @@ pub enum TenantError {
     #[error("Tenant configuration error: {0}")]
     #[allow(dead_code)]
     ConfigError(String),
+    #[error("User not found in tenant: {0}")]
+    UserNotFound(String),
+    #[error("Only admins can grant or revoke Admin")]
+    AdminRequired,
+    #[error("Cannot revoke Admin from the last admin of tenant: {0}")]
+    LastAdmin(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +49,11 @@ pub struct TenantContext {
     pub permissions: Vec<Permission>,
     pub aws_region: String,
     pub resource_limits: ResourceLimits,
+    #[serde(default)]
+    pub tool_policy: ToolPolicy,
+    /// Role in the tenant's own AWS account its data is kept under, when not in ours
+    #[serde(default)]
+    pub aws_role: Option<TenantAwsRole>,
 }
 
 impl TenantContext {
@@ -69,7 +88,6 @@ impl TenantContext {
     }
 
     /// Get the organization ID if in organizational context
-    #[allow(dead_code)]
     pub fn get_org_id(&self) -> Option<String> {
         match &self.context_type {
             ContextType::Organization { org_id, .. } => Some(org_id.clone()),
@@ -78,7 +96,87 @@ impl TenantContext {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An IAM role the server assumes with STS to reach a tenant's KV items, artifacts and
+/// events in the tenant's own AWS account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantAwsRole {
+    pub assume_role_arn: String,
+    /// The external ID the role's trust policy requires, if any
+    #[serde(default)]
+    pub external_id: Option<String>,
+}
+
+/// Per-tenant restriction on which tools are exposed, applied before permission checks.
+/// Patterns support `*` wildcards (e.g. `integration_*`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    /// When set, only tools matching one of these patterns are available
+    #[serde(default)]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Tools matching any of these patterns are never available
+    #[serde(default)]
+    pub blocked_tools: Vec<String>,
+    /// State machine ARNs (with `*` wildcards) `workflow_start` may run; none when empty
+    #[serde(default)]
+    pub allowed_state_machines: Vec<String>,
+    /// Run destructive tools as dry runs unless the call carries `confirm: true`
+    #[serde(default)]
+    pub require_confirmation: bool,
+}
+
+impl ToolPolicy {
+    /// Returns true if the tenant may see and call this tool
+    pub fn allows(&self, tool_name: &str) -> bool {
+        if self
+            .blocked_tools
+            .iter()
+            .any(|pattern| glob_match(pattern, tool_name))
+        {
+            return false;
+        }
+
+        match &self.allowed_tools {
+            Some(allowed) => allowed.iter().any(|pattern| glob_match(pattern, tool_name)),
+            None => true,
+        }
+    }
+
+    /// Returns true if the tenant may start executions of this state machine
+    pub fn allows_state_machine(&self, state_machine_arn: &str) -> bool {
+        self.allowed_state_machines
+            .iter()
+            .any(|pattern| glob_match(pattern, state_machine_arn))
+    }
+}
+
+/// Minimal glob matching where `*` matches any (possibly empty) sequence of characters
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) || name.len() < first.len() + last.len() {
+        return false;
+    }
+
+    let mut rest = &name[first.len()..name.len() - last.len()];
+    if !name.ends_with(last) {
+        return false;
+    }
+
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum UserRole {
     Admin,
     User,
@@ -96,19 +194,84 @@ pub enum Permission {
     SendEvents,
     ExecuteWorkflows,
     ManageUsers,
+    ReadSecrets,
+    WriteSecrets,
+    UseQueues,
     Execute,
     Admin,
     Read,
     Write,
 }
 
+impl Permission {
+    /// Every permission, in declaration order
+    pub fn all() -> Vec<Permission> {
+        vec![
+            Permission::ReadKV,
+            Permission::WriteKV,
+            Permission::DeleteKV,
+            Permission::ListArtifacts,
+            Permission::GetArtifacts,
+            Permission::PutArtifacts,
+            Permission::SendEvents,
+            Permission::ExecuteWorkflows,
+            Permission::ManageUsers,
+            Permission::ReadSecrets,
+            Permission::WriteSecrets,
+            Permission::UseQueues,
+            Permission::Execute,
+            Permission::Admin,
+            Permission::Read,
+            Permission::Write,
+        ]
+    }
+}
+
+/// A user's role and explicit permissions within a tenant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantMember {
+    pub role: UserRole,
+    pub permissions: Vec<Permission>,
+}
+
+impl TenantMember {
+    pub fn is_admin(&self) -> bool {
+        self.role == UserRole::Admin
+    }
+
+    /// Permissions actually in force: admins implicitly hold every permission
+    pub fn effective_permissions(&self) -> Vec<Permission> {
+        if self.is_admin() {
+            Permission::all()
+        } else {
+            self.permissions.clone()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceLimits {
     pub max_kv_size: u64,         // Maximum KV storage in bytes
     pub max_artifacts: u32,       // Maximum number of artifacts
     pub requests_per_minute: u32, // Rate limiting (legacy)
+    // Fixed or sliding per-minute window
+    #[serde(default)]
+    pub rate_limit_algorithm: RateLimitAlgorithm,
     pub max_concurrent_requests: u32,
     pub aws_service_limits: AwsServiceLimits, // AWS-specific rate limits
+    // Wait for rate-limit capacity instead of rejecting (per-call `wait_for_capacity` overrides)
+    #[serde(default)]
+    pub wait_for_capacity: bool,
+    // Longest a request may wait for capacity before it is rejected
+    #[serde(default = "default_max_capacity_wait_ms")]
+    pub max_capacity_wait_ms: u64,
+    // Daily usage levels that raise a `usage.threshold_crossed` event
+    #[serde(default)]
+    pub usage_thresholds: Vec<UsageThreshold>,
+}
+
+fn default_max_capacity_wait_ms() -> u64 {
+    2_000
 }
 
 impl Default for ResourceLimits {
@@ -117,21 +280,98 @@ impl Default for ResourceLimits {
             max_kv_size: 100_000_000, // 100MB
             max_artifacts: 1000,
             requests_per_minute: 100, // Legacy fallback
+            rate_limit_algorithm: RateLimitAlgorithm::default(),
             max_concurrent_requests: 10,
             aws_service_limits: AwsServiceLimits::default(),
+            wait_for_capacity: false,
+            max_capacity_wait_ms: default_max_capacity_wait_ms(),
+            usage_thresholds: Vec::new(),
         }
     }
 }
 
-#[derive(Debug)]
+/// What a usage threshold measures, over one UTC day of the metering rollup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageMetric {
+    ToolCalls,
+    /// Events sent with `events_send`
+    Events,
+    /// Bytes of values written with `kv_set`
+    KvBytes,
+}
+
+impl UsageMetric {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UsageMetric::ToolCalls => "tool_calls",
+            UsageMetric::Events => "events",
+            UsageMetric::KvBytes => "kv_bytes",
+        }
+    }
+}
+
+/// A budget warning: `percent` of `limit` of `metric` in a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageThreshold {
+    pub metric: UsageMetric,
+    pub percent: u32,
+    /// Daily budget; thresholds stored without one never fire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// Alert subscription of `set_by` that crossings are also delivered to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert_subscription_id: Option<String>,
+    /// The admin who set the threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_by: Option<String>,
+}
+
+impl UsageThreshold {
+    /// Usage at which the threshold is crossed, rounded up
+    pub fn trigger_level(&self) -> Option<u64> {
+        self.limit
+            .map(|limit| (limit as u128 * self.percent as u128).div_ceil(100) as u64)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TenantSession {
     pub context: TenantContext,
     pub session_id: Uuid,
     #[allow(dead_code)]
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub last_activity: Arc<RwLock<chrono::DateTime<chrono::Utc>>>,
-    pub request_count: Arc<AtomicU32>, // Changed to atomic for lock-free increment
+    pub request_count: Arc<AtomicU32>, // Requests in the current legacy rate-limit window
     pub active_requests: Arc<AtomicU32>, // Changed to atomic for lock-free increment
+    window_started_ms: Arc<AtomicI64>, // Start of the legacy per-minute window (epoch millis)
+    previous_window_count: Arc<AtomicU32>, // Requests in the window before it (sliding only)
+    /// The `seq` and nonces used on this session, when the HTTP transport checks them
+    pub replay_window: Arc<ReplayWindow>,
+}
+
+/// Length of the legacy `requests_per_minute` window
+const LEGACY_WINDOW_MS: i64 = 60_000;
+
+/// Requests in the minute ending now, assuming the previous window's requests were
+/// spread evenly across it
+fn sliding_estimate(previous: u32, current: u32, elapsed_ms: i64) -> f64 {
+    let overlap = 1.0 - elapsed_ms as f64 / LEGACY_WINDOW_MS as f64;
+    previous as f64 * overlap + current as f64
+}
+
+/// Milliseconds until the sliding estimate drops below `limit`
+fn sliding_retry_after_ms(previous: u32, current: u32, limit: u32, elapsed_ms: i64) -> i64 {
+    let window = LEGACY_WINDOW_MS as f64;
+    if current < limit {
+        // The previous window's share has to decay by the excess
+        let threshold = window * (1.0 - (limit - current) as f64 / previous as f64);
+        threshold.floor() as i64 + 1 - elapsed_ms
+    } else {
+        // Wait for this window to become the previous one, then for its share to decay
+        let threshold = window * (1.0 - limit as f64 / current as f64);
+        LEGACY_WINDOW_MS - elapsed_ms + threshold.floor() as i64 + 1
+    }
 }
 
 impl TenantSession {
@@ -144,6 +384,9 @@ impl TenantSession {
             last_activity: Arc::new(RwLock::new(now)),
             request_count: Arc::new(AtomicU32::new(0)), // Atomic initialization
             active_requests: Arc::new(AtomicU32::new(0)), // Atomic initialization
+            window_started_ms: Arc::new(AtomicI64::new(now.timestamp_millis())),
+            previous_window_count: Arc::new(AtomicU32::new(0)),
+            replay_window: Arc::new(ReplayWindow::new()),
         }
     }
 
@@ -175,24 +418,130 @@ impl TenantSession {
             .ok(); // Ignore result
     }
 
-    pub fn check_rate_limit(&self) -> bool {
-        // Lock-free atomic reads
+    pub fn check_rate_limit(&self) -> Result<(), RateLimitRejection> {
+        self.check_rate_limit_at(chrono::Utc::now().timestamp_millis())
+    }
+
+    /// Legacy per-minute check, fixed or sliding per the tenant's `rate_limit_algorithm`.
+    /// Concurrency is enforced per tenant by TenantManager::try_acquire_request_slot,
+    /// not per session.
+    fn check_rate_limit_at(&self, now_ms: i64) -> Result<(), RateLimitRejection> {
+        let window_start = self.roll_window(now_ms);
+        let limit = self.context.resource_limits.requests_per_minute;
+
+        // Lock-free atomic read
         let count = self.request_count.load(Ordering::SeqCst);
-        let active = self.active_requests.load(Ordering::SeqCst);
+        let retry_after_ms = match self.context.resource_limits.rate_limit_algorithm {
+            RateLimitAlgorithm::Fixed => {
+                if count < limit {
+                    return Ok(());
+                }
+                window_start + LEGACY_WINDOW_MS - now_ms
+            }
+            RateLimitAlgorithm::Sliding => {
+                let previous = self.previous_window_count.load(Ordering::SeqCst);
+                let elapsed = now_ms - window_start;
+                if sliding_estimate(previous, count, elapsed) < limit as f64 {
+                    return Ok(());
+                }
+                sliding_retry_after_ms(previous, count, limit, elapsed)
+            }
+        };
+
+        Err(RateLimitRejection::tenant(
+            "requests_per_minute",
+            std::time::Duration::from_millis(retry_after_ms.max(0) as u64),
+        ))
+    }
+
+    /// Start a new window if the current one has ended, returning the current window's
+    /// start. Only the caller that wins the swap resets the count. Fixed windows restart
+    /// at the first request after they end; sliding windows stay aligned to the
+    /// session's first window and carry the last full window's count over.
+    fn roll_window(&self, now_ms: i64) -> i64 {
+        let window_start = self.window_started_ms.load(Ordering::SeqCst);
+        let elapsed = now_ms - window_start;
+        if elapsed < LEGACY_WINDOW_MS {
+            return window_start;
+        }
+
+        let sliding =
+            self.context.resource_limits.rate_limit_algorithm == RateLimitAlgorithm::Sliding;
+        let new_start = if sliding {
+            window_start + elapsed / LEGACY_WINDOW_MS * LEGACY_WINDOW_MS
+        } else {
+            now_ms
+        };
+        if self
+            .window_started_ms
+            .compare_exchange(window_start, new_start, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            let count = self.request_count.swap(0, Ordering::SeqCst);
+            // A gap of more than one window leaves nothing to carry over
+            let previous = if sliding && elapsed < 2 * LEGACY_WINDOW_MS {
+                count
+            } else {
+                0
+            };
+            self.previous_window_count.store(previous, Ordering::SeqCst);
+            new_start
+        } else {
+            self.window_started_ms.load(Ordering::SeqCst)
+        }
+    }
+
+    /// Current state of the legacy per-minute window
+    pub fn legacy_window_status(&self) -> LegacyWindowStatus {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let window_start = self.window_started_ms.load(Ordering::SeqCst);
+        let elapsed = now_ms - window_start;
+        let algorithm = self.context.resource_limits.rate_limit_algorithm;
+        let count = self.request_count.load(Ordering::SeqCst);
+
+        let (requests, resets_in_ms) = match algorithm {
+            // A window that has already rolled over is reported as fresh
+            RateLimitAlgorithm::Fixed if elapsed >= LEGACY_WINDOW_MS => (0, LEGACY_WINDOW_MS),
+            RateLimitAlgorithm::Fixed => (count, LEGACY_WINDOW_MS - elapsed),
+            RateLimitAlgorithm::Sliding => {
+                let (previous, current, elapsed) = if elapsed >= 2 * LEGACY_WINDOW_MS {
+                    (0, 0, elapsed % LEGACY_WINDOW_MS)
+                } else if elapsed >= LEGACY_WINDOW_MS {
+                    (count, 0, elapsed - LEGACY_WINDOW_MS)
+                } else {
+                    (
+                        self.previous_window_count.load(Ordering::SeqCst),
+                        count,
+                        elapsed,
+                    )
+                };
+                (
+                    sliding_estimate(previous, current, elapsed).ceil() as u32,
+                    LEGACY_WINDOW_MS - elapsed,
+                )
+            }
+        };
 
-        // Legacy rate limiting check
-        count < self.context.resource_limits.requests_per_minute
-            && active < self.context.resource_limits.max_concurrent_requests
+        LegacyWindowStatus {
+            algorithm,
+            requests,
+            limit: self.context.resource_limits.requests_per_minute,
+            resets_in_ms: resets_in_ms.max(0) as u64,
+        }
     }
 
-    /// Check if an AWS operation is allowed based on service-specific limits
+    /// Check if an AWS operation is allowed based on service-specific limits,
+    /// optionally waiting up to `max_wait` for capacity. Critical operations may draw on
+    /// the tenant's reserve pool.
     pub async fn check_aws_operation(
         &self,
         aws_limiter: &AwsRateLimiter,
         operation: &AwsOperation,
-    ) -> bool {
+        priority: OperationPriority,
+        max_wait: Option<std::time::Duration>,
+    ) -> Result<Vec<RemainingQuota>, RateLimitRejection> {
         aws_limiter
-            .check_aws_operation(&self.context.tenant_id, operation)
+            .check_aws_operation_waiting(&self.context.tenant_id, operation, priority, max_wait)
             .await
     }
 
@@ -204,21 +553,86 @@ impl TenantSession {
     }
 }
 
+/// Sessions idle for longer than this are expired
+const SESSION_IDLE_TIMEOUT_MINUTES: i64 = 30;
+
+/// How long ended session ids are remembered so clients can be told why they were rejected
+const ENDED_SESSION_RETENTION_HOURS: i64 = 24;
+
+/// Why a session id is no longer usable
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEndReason {
+    /// Idle longer than the session timeout
+    Expired,
+    /// Explicitly terminated, or invalidated by a permission change
+    Revoked,
+}
+
+/// Tombstone for an ended session: why and when it ended
+type EndedSession = (SessionEndReason, chrono::DateTime<chrono::Utc>);
+
+/// Result of looking up a session by its id
+#[derive(Debug)]
+pub enum SessionLookup {
+    Active(Arc<TenantSession>),
+    Ended(SessionEndReason),
+    Unknown,
+}
+
+/// A reserved unit of a tenant's `max_concurrent_requests`, released on drop
+#[derive(Debug)]
+pub struct TenantRequestSlot {
+    tenant_active: Arc<AtomicU32>,
+}
+
+impl Drop for TenantRequestSlot {
+    fn drop(&mut self) {
+        self.tenant_active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                current.checked_sub(1)
+            })
+            .ok();
+    }
+}
+
 pub struct TenantManager {
     sessions: Arc<RwLock<HashMap<String, Arc<TenantSession>>>>,
     // In production, this would integrate with a database
     tenant_configs: Arc<RwLock<HashMap<String, TenantContext>>>,
     aws_rate_limiter: Arc<AwsRateLimiter>,
+    // In-flight requests across all sessions, so shutdown doesn't have to walk every session
+    active_requests: Arc<AtomicU32>,
+    // In-flight requests per tenant, shared by all of the tenant's sessions
+    tenant_active_requests: Arc<RwLock<HashMap<String, Arc<AtomicU32>>>>,
+    // Tenant membership store: tenant_id -> user_id -> member
+    members: Arc<RwLock<HashMap<String, HashMap<String, TenantMember>>>>,
+    // Recently ended session ids, to distinguish expired/revoked from never-existed
+    ended_sessions: Arc<RwLock<HashMap<Uuid, EndedSession>>>,
+    config: Arc<Config>,
 }
 
 impl TenantManager {
+    /// A manager for the config [`Config::load`] finds
+    #[allow(dead_code)]
     pub async fn new() -> anyhow::Result<Self> {
+        Self::with_config(Arc::new(Config::load()?)).await
+    }
+
+    pub async fn with_config(config: Arc<Config>) -> anyhow::Result<Self> {
         let mut tenant_configs = HashMap::new();
 
+        // Deployment-wide AWS limits: config file > MCP_LIMIT_* env > compiled-in defaults
+        let default_aws_limits = config.rate_limits.default_limits.clone();
+        info!(
+            "Effective default AWS rate limits: {:?}",
+            default_aws_limits
+        );
+
         // Load tenant configs from environment or config file
         // In production, tenants should be loaded from database/config service
         // For development, check if DEV_MODE is enabled before creating demo tenant
-        if std::env::var("DEV_MODE").unwrap_or_default() == "true" {
+        if config.dev_mode {
             warn!("DEV_MODE enabled: Creating demo tenant (DO NOT USE IN PRODUCTION)");
             let demo_context = TenantContext {
                 tenant_id: "demo-tenant".to_string(),
@@ -238,9 +652,15 @@ impl TenantManager {
                     Permission::PutArtifacts,
                     Permission::SendEvents,
                     Permission::ExecuteWorkflows,
+                    Permission::UseQueues,
                 ],
                 aws_region: "us-west-2".to_string(),
-                resource_limits: ResourceLimits::default(),
+                resource_limits: ResourceLimits {
+                    aws_service_limits: default_aws_limits.clone(),
+                    ..Default::default()
+                },
+                tool_policy: ToolPolicy::default(),
+                aws_role: None,
             };
 
             tenant_configs.insert("demo-tenant".to_string(), demo_context);
@@ -248,24 +668,94 @@ impl TenantManager {
             info!("Production mode: Tenant contexts will be created from auth headers");
         }
 
-        // Create AWS rate limiter with default limits
-        let aws_rate_limiter = Arc::new(AwsRateLimiter::new(AwsServiceLimits::default()));
+        // Create AWS rate limiter with default limits, sized per tenant where configured
+        let limits = &config.rate_limits;
+        if !limits.global_limits.is_empty() {
+            info!("Account-wide rate limits: {:?}", limits.global_limits);
+        }
+        let mut aws_rate_limiter = AwsRateLimiter::new(default_aws_limits)
+            .with_global_limits(limits.global_limits.clone());
+        if let Some(max_buckets) = limits.max_buckets {
+            aws_rate_limiter = aws_rate_limiter.with_max_buckets(max_buckets);
+        }
+        if let Some(distributed) = &limits.distributed {
+            info!(
+                "Distributed rate limiting enabled (table: {}, on store failure: {:?})",
+                distributed.table, distributed.failure_policy
+            );
+            let sdk_config = aws_config::load_from_env().await;
+            let store = DynamoBucketStore::new(
+                aws_sdk_dynamodb::Client::new(&sdk_config),
+                distributed.table.clone(),
+            );
+            aws_rate_limiter = aws_rate_limiter
+                .with_distributed(DistributedLimiter::new(Arc::new(store)))
+                .with_failure_policy(distributed.failure_policy);
+        }
+        let aws_rate_limiter = Arc::new(aws_rate_limiter);
+        for (tenant_id, context) in &tenant_configs {
+            aws_rate_limiter
+                .set_tenant_limits(
+                    tenant_id,
+                    context.resource_limits.aws_service_limits.clone(),
+                )
+                .await;
+        }
+
+        // Each configured tenant starts with its owner as the only member
+        let members = tenant_configs
+            .iter()
+            .map(|(tenant_id, context)| (tenant_id.clone(), Self::owner_membership(context)))
+            .collect();
 
         Ok(Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             tenant_configs: Arc::new(RwLock::new(tenant_configs)),
             aws_rate_limiter,
+            active_requests: Arc::new(AtomicU32::new(0)),
+            tenant_active_requests: Arc::new(RwLock::new(HashMap::new())),
+            members: Arc::new(RwLock::new(members)),
+            ended_sessions: Arc::new(RwLock::new(HashMap::new())),
+            config,
         })
     }
 
-    pub async fn create_session(&self, tenant_id: &str) -> Result<Arc<TenantSession>, TenantError> {
+    /// The settings the manager was built with
+    pub fn config(&self) -> &Arc<Config> {
+        &self.config
+    }
+
+    fn owner_membership(context: &TenantContext) -> HashMap<String, TenantMember> {
+        HashMap::from([(
+            context.user_id.clone(),
+            TenantMember {
+                role: context.role.clone(),
+                permissions: context.permissions.clone(),
+            },
+        )])
+    }
+
+    /// Create a session for a member of the tenant, using that member's role and permissions
+    pub async fn create_session(
+        &self,
+        tenant_id: &str,
+        user_id: &str,
+    ) -> Result<Arc<TenantSession>, TenantError> {
         let configs = self.tenant_configs.read().await;
-        let context = configs
+        let mut context = configs
             .get(tenant_id)
             .ok_or_else(|| TenantError::NotFound(tenant_id.to_string()))?
             .clone();
         drop(configs);
 
+        let member = self
+            .get_member(tenant_id, user_id)
+            .await
+            .ok_or_else(|| TenantError::Unauthorized(tenant_id.to_string()))?;
+        context.user_id = user_id.to_string();
+        context.role = member.role;
+        context.permissions = member.permissions;
+
         let session = Arc::new(TenantSession::new(context));
         let session_key = format!("{}:{}", tenant_id, session.session_id);
 
@@ -286,10 +776,9 @@ impl TenantManager {
         sessions.values().cloned().collect()
     }
 
-    #[allow(dead_code)]
     pub async fn cleanup_expired_sessions(&self) {
         let now = chrono::Utc::now();
-        let timeout = chrono::Duration::minutes(30); // 30-minute timeout
+        let timeout = chrono::Duration::minutes(SESSION_IDLE_TIMEOUT_MINUTES);
 
         // CRITICAL FIX: Avoid deadlock by collecting keys first, then filtering
         // Don't hold write lock while calling block_on on another async lock
@@ -314,13 +803,324 @@ impl TenantManager {
         // Step 3: Remove expired sessions (write lock held briefly)
         if !expired.is_empty() {
             let mut sessions = self.sessions.write().await;
+            let mut ended = self.ended_sessions.write().await;
             for key in &expired {
-                sessions.remove(key);
+                if let Some(session) = sessions.remove(key) {
+                    ended.insert(session.session_id, (SessionEndReason::Expired, now));
+                }
             }
         }
 
+        // Forget ended sessions once clients have had ample time to notice
+        let retention = chrono::Duration::hours(ENDED_SESSION_RETENTION_HOURS);
+        self.ended_sessions
+            .write()
+            .await
+            .retain(|_, (_, ended_at)| now.signed_duration_since(*ended_at) < retention);
+
         // Also cleanup AWS rate limiter buckets
-        self.aws_rate_limiter.cleanup_expired_buckets().await;
+        let removed = self.aws_rate_limiter.cleanup_expired_buckets().await;
+        if removed > 0 {
+            info!("Removed {} idle rate limit buckets", removed);
+        }
+    }
+
+    /// Run `cleanup_expired_sessions` every `interval` for as long as the manager lives
+    pub fn spawn_cleanup_task(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately; there is nothing to clean yet
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.cleanup_expired_sessions().await;
+            }
+        })
+    }
+
+    /// Update a tenant's resource limits. New sessions pick up the limits, and the
+    /// tenant's existing AWS rate-limit buckets are resized in place.
+    #[allow(dead_code)]
+    pub async fn update_resource_limits(
+        &self,
+        tenant_id: &str,
+        limits: ResourceLimits,
+    ) -> Result<(), TenantError> {
+        {
+            let mut configs = self.tenant_configs.write().await;
+            let context = configs
+                .get_mut(tenant_id)
+                .ok_or_else(|| TenantError::NotFound(tenant_id.to_string()))?;
+            context.resource_limits = limits.clone();
+        }
+
+        self.aws_rate_limiter
+            .set_tenant_limits(tenant_id, limits.aws_service_limits)
+            .await;
+        Ok(())
+    }
+
+    /// Replace a tenant's usage thresholds; the evaluator reads them on its next run
+    pub async fn set_usage_thresholds(
+        &self,
+        tenant_id: &str,
+        thresholds: Vec<UsageThreshold>,
+    ) -> Result<(), TenantError> {
+        let mut configs = self.tenant_configs.write().await;
+        let context = configs
+            .get_mut(tenant_id)
+            .ok_or_else(|| TenantError::NotFound(tenant_id.to_string()))?;
+        context.resource_limits.usage_thresholds = thresholds;
+        Ok(())
+    }
+
+    /// Every configured tenant's context, as new sessions of its owner get it
+    pub async fn tenant_contexts(&self) -> Vec<TenantContext> {
+        self.tenant_configs.read().await.values().cloned().collect()
+    }
+
+    /// Look up a user's membership in a tenant
+    pub async fn get_member(&self, tenant_id: &str, user_id: &str) -> Option<TenantMember> {
+        self.members
+            .read()
+            .await
+            .get(tenant_id)
+            .and_then(|members| members.get(user_id))
+            .cloned()
+    }
+
+    /// Grant permissions to a user in the caller's tenant, adding them as a member if needed.
+    /// Granting `Admin` promotes the user to the Admin role and requires an admin caller.
+    pub async fn grant_permissions(
+        &self,
+        caller: &TenantSession,
+        target_user_id: &str,
+        permissions: &[Permission],
+    ) -> Result<TenantMember, TenantError> {
+        let tenant_id = &caller.context.tenant_id;
+        let grants_admin = permissions.contains(&Permission::Admin);
+        if grants_admin && caller.context.role != UserRole::Admin {
+            return Err(TenantError::AdminRequired);
+        }
+
+        let member = {
+            let mut members = self.members.write().await;
+            let member = members
+                .entry(tenant_id.clone())
+                .or_default()
+                .entry(target_user_id.to_string())
+                .or_insert_with(|| TenantMember {
+                    role: UserRole::User,
+                    permissions: Vec::new(),
+                });
+
+            for permission in permissions {
+                if !member.permissions.contains(permission) {
+                    member.permissions.push(permission.clone());
+                }
+            }
+            if grants_admin {
+                member.role = UserRole::Admin;
+            }
+            member.clone()
+        };
+
+        self.invalidate_user_sessions(tenant_id, target_user_id)
+            .await;
+        Ok(member)
+    }
+
+    /// Revoke permissions from a user in the caller's tenant.
+    /// Revoking `Admin` demotes the user to the User role; the last admin cannot be demoted.
+    pub async fn revoke_permissions(
+        &self,
+        caller: &TenantSession,
+        target_user_id: &str,
+        permissions: &[Permission],
+    ) -> Result<TenantMember, TenantError> {
+        let tenant_id = &caller.context.tenant_id;
+        let revokes_admin = permissions.contains(&Permission::Admin);
+        if revokes_admin && caller.context.role != UserRole::Admin {
+            return Err(TenantError::AdminRequired);
+        }
+
+        let member = {
+            let mut members = self.members.write().await;
+            let tenant_members = members
+                .get_mut(tenant_id)
+                .ok_or_else(|| TenantError::UserNotFound(target_user_id.to_string()))?;
+
+            if revokes_admin {
+                let target_is_admin = tenant_members
+                    .get(target_user_id)
+                    .is_some_and(TenantMember::is_admin);
+                let admin_count = tenant_members.values().filter(|m| m.is_admin()).count();
+                if target_is_admin && admin_count <= 1 {
+                    return Err(TenantError::LastAdmin(tenant_id.clone()));
+                }
+            }
+
+            let member = tenant_members
+                .get_mut(target_user_id)
+                .ok_or_else(|| TenantError::UserNotFound(target_user_id.to_string()))?;
+            member.permissions.retain(|p| !permissions.contains(p));
+            if revokes_admin {
+                member.role = UserRole::User;
+            }
+            member.clone()
+        };
+
+        self.invalidate_user_sessions(tenant_id, target_user_id)
+            .await;
+        Ok(member)
+    }
+
+    /// Drop cached sessions for a user so permission changes apply on their next request
+    async fn invalidate_user_sessions(&self, tenant_id: &str, user_id: &str) {
+        let now = chrono::Utc::now();
+        // Lock order: sessions before ended_sessions
+        let mut sessions = self.sessions.write().await;
+        let mut ended = self.ended_sessions.write().await;
+        sessions.retain(|_, session| {
+            let keep = session.context.tenant_id != tenant_id || session.context.user_id != user_id;
+            if !keep {
+                ended.insert(session.session_id, (SessionEndReason::Revoked, now));
+            }
+            keep
+        });
+    }
+
+    /// Look up a session by its id (as carried in `Mcp-Session-Id`), expiring it if idle
+    pub async fn lookup_session(&self, session_id: &str) -> SessionLookup {
+        let Ok(session_id) = Uuid::parse_str(session_id) else {
+            return SessionLookup::Unknown;
+        };
+
+        let found = self
+            .sessions
+            .read()
+            .await
+            .iter()
+            .find(|(_, session)| session.session_id == session_id)
+            .map(|(key, session)| (key.clone(), session.clone()));
+
+        if let Some((key, session)) = found {
+            let now = chrono::Utc::now();
+            let idle = now.signed_duration_since(*session.last_activity.read().await);
+            if idle < chrono::Duration::minutes(SESSION_IDLE_TIMEOUT_MINUTES) {
+                return SessionLookup::Active(session);
+            }
+
+            self.end_session(&key, SessionEndReason::Expired).await;
+            return SessionLookup::Ended(SessionEndReason::Expired);
+        }
+
+        match self.ended_sessions.read().await.get(&session_id) {
+            Some((reason, _)) => SessionLookup::Ended(*reason),
+            None => SessionLookup::Unknown,
+        }
+    }
+
+    /// Terminate a session by id; returns false if no such active session exists
+    pub async fn revoke_session(&self, session_id: &str) -> bool {
+        let Ok(session_id) = Uuid::parse_str(session_id) else {
+            return false;
+        };
+
+        let key = self
+            .sessions
+            .read()
+            .await
+            .iter()
+            .find(|(_, session)| session.session_id == session_id)
+            .map(|(key, _)| key.clone());
+
+        match key {
+            Some(key) => {
+                self.end_session(&key, SessionEndReason::Revoked).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn end_session(&self, session_key: &str, reason: SessionEndReason) {
+        let removed = self.sessions.write().await.remove(session_key);
+        if let Some(session) = removed {
+            self.ended_sessions
+                .write()
+                .await
+                .insert(session.session_id, (reason, chrono::Utc::now()));
+        }
+    }
+
+    /// Shared counter of in-flight requests across all sessions
+    pub fn active_request_counter(&self) -> Arc<AtomicU32> {
+        self.active_requests.clone()
+    }
+
+    /// Total in-flight requests across all sessions (O(1), lock-free)
+    pub fn total_active_requests(&self) -> u32 {
+        self.active_requests.load(Ordering::SeqCst)
+    }
+
+    async fn tenant_active_counter(&self, tenant_id: &str) -> Arc<AtomicU32> {
+        if let Some(counter) = self.tenant_active_requests.read().await.get(tenant_id) {
+            return counter.clone();
+        }
+
+        self.tenant_active_requests
+            .write()
+            .await
+            .entry(tenant_id.to_string())
+            .or_insert_with(|| Arc::new(AtomicU32::new(0)))
+            .clone()
+    }
+
+    /// Reserve one of the tenant's concurrent request slots, enforcing
+    /// `max_concurrent_requests` across every session of the tenant.
+    /// On rejection, returns the tenant's current in-flight request count.
+    pub async fn try_acquire_request_slot(
+        &self,
+        session: &TenantSession,
+    ) -> Result<TenantRequestSlot, u32> {
+        let limit = session.context.resource_limits.max_concurrent_requests;
+        let tenant_active = self.tenant_active_counter(&session.context.tenant_id).await;
+
+        // Compare-and-increment so concurrent callers can never overshoot the limit
+        tenant_active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                (current < limit).then_some(current + 1)
+            })
+            .map(|_| TenantRequestSlot { tenant_active })
+    }
+
+    /// In-flight requests for a tenant across all of its sessions
+    #[allow(dead_code)]
+    pub async fn tenant_active_requests(&self, tenant_id: &str) -> u32 {
+        self.tenant_active_requests
+            .read()
+            .await
+            .get(tenant_id)
+            .map(|counter| counter.load(Ordering::SeqCst))
+            .unwrap_or(0)
+    }
+
+    /// Rate limit state for the session's tenant, without consuming any tokens
+    pub async fn rate_limit_status(&self, session: &TenantSession) -> RateLimitStatus {
+        RateLimitStatus {
+            tenant_id: session.context.tenant_id.clone(),
+            buckets: self
+                .aws_rate_limiter
+                .snapshot(&session.context.tenant_id)
+                .await,
+            requests_per_minute: session.legacy_window_status(),
+        }
     }
 
     /// Get AWS rate limiter for checking service-specific limits
@@ -334,19 +1134,16 @@ impl TenantManager {
         user_id: &str,
     ) -> Result<(), TenantError> {
         // Check if tenant already exists
-        {
-            let configs = self.tenant_configs.read().await;
-            if let Some(context) = configs.get(tenant_id) {
-                // Tenant exists, validate user
-                if context.user_id != user_id {
-                    return Err(TenantError::Unauthorized(tenant_id.to_string()));
-                }
-                return Ok(());
+        if self.tenant_configs.read().await.contains_key(tenant_id) {
+            // Tenant exists, validate the user is a member
+            if self.get_member(tenant_id, user_id).await.is_none() {
+                return Err(TenantError::Unauthorized(tenant_id.to_string()));
             }
+            return Ok(());
         }
 
-        // Tenant doesn't exist - auto-register in dev mode (when DEFAULT_TENANT_ID is set)
-        if std::env::var("DEFAULT_TENANT_ID").is_ok() {
+        // Tenant doesn't exist - auto-register in dev mode (when a default tenant is set)
+        if self.config.default_tenant_id.is_some() {
             info!(
                 "Auto-registering tenant '{}' for user '{}' (dev mode)",
                 tenant_id, user_id
@@ -361,10 +1158,25 @@ impl TenantManager {
                 organization_id: tenant_id.to_string(),
                 role: UserRole::Admin,
                 permissions: vec![Permission::Admin],
-                aws_region: std::env::var("AWS_REGION").unwrap_or_else(|_| "us-west-2".to_string()),
-                resource_limits: ResourceLimits::default(),
+                aws_region: self.config.aws.region.clone(),
+                resource_limits: ResourceLimits {
+                    aws_service_limits: self.aws_rate_limiter.default_limits().clone(),
+                    ..Default::default()
+                },
+                tool_policy: ToolPolicy::default(),
+                aws_role: None,
             };
 
+            self.aws_rate_limiter
+                .set_tenant_limits(
+                    tenant_id,
+                    context.resource_limits.aws_service_limits.clone(),
+                )
+                .await;
+            self.members
+                .write()
+                .await
+                .insert(tenant_id.to_string(), Self::owner_membership(&context));
             let mut configs = self.tenant_configs.write().await;
             configs.insert(tenant_id.to_string(), context);
             Ok(())
@@ -374,3 +1186,152 @@ impl TenantManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("kv_get", "kv_get"));
+        assert!(!glob_match("kv_get", "kv_get_direct"));
+        assert!(glob_match("integration_*", "integration_register"));
+        assert!(glob_match("*_list", "artifacts_list"));
+        assert!(glob_match("*proxy*", "mcp_proxy"));
+        assert!(glob_match("events_*_rule", "events_create_rule"));
+        assert!(glob_match("*", "anything"));
+        assert!(!glob_match("integration_*", "mcp_proxy"));
+        assert!(!glob_match("a*a", "a"));
+    }
+
+    fn session_with_rpm(requests_per_minute: u32) -> TenantSession {
+        session_with_algorithm(requests_per_minute, RateLimitAlgorithm::Fixed)
+    }
+
+    fn session_with_algorithm(
+        requests_per_minute: u32,
+        rate_limit_algorithm: RateLimitAlgorithm,
+    ) -> TenantSession {
+        TenantSession::new(TenantContext {
+            tenant_id: "t".to_string(),
+            user_id: "u".to_string(),
+            context_type: ContextType::Personal,
+            organization_id: "o".to_string(),
+            role: UserRole::User,
+            permissions: vec![],
+            aws_region: "us-west-2".to_string(),
+            resource_limits: ResourceLimits {
+                requests_per_minute,
+                rate_limit_algorithm,
+                ..Default::default()
+            },
+            tool_policy: ToolPolicy::default(),
+            aws_role: None,
+        })
+    }
+
+    /// Admit requests at `now_ms` until the limit rejects one; returns how many passed
+    fn admit_burst(session: &TenantSession, now_ms: i64, max: u32) -> u32 {
+        let mut admitted = 0;
+        while admitted < max && session.check_rate_limit_at(now_ms).is_ok() {
+            session.increment_request_count();
+            admitted += 1;
+        }
+        admitted
+    }
+
+    #[test]
+    fn test_fixed_window_allows_double_burst_across_boundary() {
+        let session = session_with_algorithm(100, RateLimitAlgorithm::Fixed);
+        let start = session.window_started_ms.load(Ordering::SeqCst);
+
+        assert_eq!(admit_burst(&session, start + 59_000, 200), 100);
+        assert_eq!(admit_burst(&session, start + 61_000, 200), 100);
+    }
+
+    #[test]
+    fn test_sliding_window_smooths_boundary_burst() {
+        let session = session_with_algorithm(100, RateLimitAlgorithm::Sliding);
+        let start = session.window_started_ms.load(Ordering::SeqCst);
+
+        assert_eq!(admit_burst(&session, start + 59_000, 200), 100);
+
+        // 1s into the next window, 59/60 of the previous 100 still count
+        assert_eq!(admit_burst(&session, start + 61_000, 200), 2);
+        let rejection = session.check_rate_limit_at(start + 61_000).unwrap_err();
+        assert_eq!(rejection.service_key, "requests_per_minute");
+
+        // The estimate of ~100.3 drops below 100 once another 0.2s of the previous
+        // window (0.6s per request) has decayed
+        assert_eq!(rejection.retry_after, std::time::Duration::from_millis(201));
+        assert!(session.check_rate_limit_at(start + 61_000 + 199).is_err());
+        assert_eq!(admit_burst(&session, start + 61_000 + 201, 1), 1);
+
+        // Half way through the window, half of the previous window has decayed
+        assert_eq!(admit_burst(&session, start + 90_000, 200), 47);
+    }
+
+    #[test]
+    fn test_sliding_window_forgets_windows_older_than_a_minute() {
+        let session = session_with_algorithm(10, RateLimitAlgorithm::Sliding);
+        let start = session.window_started_ms.load(Ordering::SeqCst);
+
+        assert_eq!(admit_burst(&session, start + 1_000, 20), 10);
+        // A full current window has to end and start decaying before anything passes
+        let rejection = session.check_rate_limit_at(start + 1_000).unwrap_err();
+        assert_eq!(
+            rejection.retry_after,
+            std::time::Duration::from_millis(59_001)
+        );
+
+        assert_eq!(admit_burst(&session, start + 150_000, 20), 10);
+        assert_eq!(session.previous_window_count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_legacy_limit_reports_time_until_window_rolls() {
+        let session = session_with_rpm(2);
+        let start = session.window_started_ms.load(Ordering::SeqCst);
+
+        for _ in 0..2 {
+            assert!(session.check_rate_limit_at(start + 1_000).is_ok());
+            session.increment_request_count();
+        }
+
+        let rejection = session.check_rate_limit_at(start + 15_000).unwrap_err();
+        assert_eq!(rejection.service_key, "requests_per_minute");
+        assert_eq!(rejection.retry_after, std::time::Duration::from_secs(45));
+
+        // The next window starts fresh
+        assert!(session.check_rate_limit_at(start + 60_000).is_ok());
+        assert_eq!(session.request_count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_tool_policy_block_list() {
+        let policy = ToolPolicy {
+            allowed_tools: None,
+            blocked_tools: vec!["integration_*".to_string(), "mcp_proxy".to_string()],
+            allowed_state_machines: Vec::new(),
+            require_confirmation: false,
+        };
+
+        assert!(policy.allows("kv_get"));
+        assert!(!policy.allows("integration_register"));
+        assert!(!policy.allows("mcp_proxy"));
+    }
+
+    #[test]
+    fn test_tool_policy_allow_list_with_block_precedence() {
+        let policy = ToolPolicy {
+            allowed_tools: Some(vec!["kv_*".to_string()]),
+            blocked_tools: vec!["kv_set".to_string()],
+            allowed_state_machines: Vec::new(),
+            require_confirmation: false,
+        };
+
+        assert!(policy.allows("kv_get"));
+        assert!(!policy.allows("kv_set"));
+        assert!(!policy.allows("artifacts_get"));
+    }
+}
@@ -0,0 +1,86 @@
+// Network transports for the MCP server. Stdio is handled directly by `MCPServer::run`,
+// or `MCPServer::run_with_io` over any other byte stream; HTTP keeps sessions alive
+// across requests via `Mcp-Session-Id`, and a WebSocket, Unix socket or embedded stream
+// connection is one session. HTTP requests can be replayed by anything on the network
+// path, so that transport can require each one to be fresh.
+pub mod http;
+pub mod replay;
+pub mod stream;
+#[cfg(unix)]
+pub mod unix;
+pub mod websocket;
+
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::mcp::{MCPError, MCPErrorResponse, MCPRequest, MCPResponse, MCPServer};
+use crate::tenant::TenantSession;
+
+/// Handle one message of a connection that is one session: `initialize` opens it,
+/// every later request runs in it, and requests before it are rejected
+pub(crate) async fn handle_session_message(
+    server: &MCPServer,
+    session: &mut Option<Arc<TenantSession>>,
+    text: &str,
+) -> Option<MCPResponse> {
+    let request: MCPRequest = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                None,
+                MCPError::InvalidRequest(e.to_string()),
+            ))
+        }
+    };
+
+    if request.method == "initialize" {
+        if session.is_some() {
+            return Some(error_response(
+                request.id,
+                MCPError::InvalidRequest(
+                    "This connection already has a session; open another to start one".to_string(),
+                ),
+            ));
+        }
+        let opened = match server.open_session(&request).await {
+            Ok(opened) => opened,
+            Err(error) => return Some(error_response(request.id, error)),
+        };
+        debug!("Connection session {} initialized", opened.session_id);
+        *session = Some(opened.clone());
+        return server.handle_session_request(opened, request).await;
+    }
+
+    match session {
+        Some(session) => {
+            server
+                .handle_session_request(session.clone(), request)
+                .await
+        }
+        // Notifications get no response, not even an error
+        None if request.id.is_none() => None,
+        None => Some(error_response(
+            request.id,
+            MCPError::InvalidRequest("Send initialize first".to_string()),
+        )),
+    }
+}
+
+/// End a connection's session, if it opened one
+pub(crate) async fn close_session(server: &MCPServer, session: Option<Arc<TenantSession>>) {
+    if let Some(session) = session {
+        let session_id = session.session_id.to_string();
+        debug!("Connection session {} closed", session_id);
+        server.tenant_manager().revoke_session(&session_id).await;
+    }
+}
+
+fn error_response(id: Option<serde_json::Value>, error: MCPError) -> MCPResponse {
+    let error: MCPErrorResponse = error.into();
+    MCPResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: None,
+        error: Some(error),
+    }
+}
@@ -0,0 +1,196 @@
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::mcp::{MCPError, MCPErrorResponse, MCPRequest, MCPResponse, MCPServer};
+use crate::metrics::prometheus;
+use crate::tenant::SessionLookup;
+use crate::transport::replay::{Freshness, ReplayReason};
+
+/// Header carrying the session id issued during initialize
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Build the HTTP router: `POST /mcp` for JSON-RPC, `DELETE /mcp` to end a session,
+/// `GET /metrics` for Prometheus
+pub fn router(server: Arc<MCPServer>) -> Router {
+    Router::new()
+        .route("/mcp", post(handle_post).delete(handle_delete))
+        .route("/metrics", get(handle_metrics))
+        .with_state(server)
+}
+
+/// Just `GET /metrics`, for the listener `MCP_METRICS_ADDR` enables
+pub fn metrics_router(server: Arc<MCPServer>) -> Router {
+    Router::new()
+        .route("/metrics", get(handle_metrics))
+        .with_state(server)
+}
+
+/// Serve the HTTP transport until ctrl-c
+pub async fn serve(server: Arc<MCPServer>, addr: SocketAddr) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!(
+        "[MCP Server] Starting HTTP transport on {}",
+        listener.local_addr()?
+    );
+
+    axum::serve(listener, router(server))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+/// Serve `/metrics` alone until ctrl-c, alongside whichever transport is running
+pub async fn serve_metrics(server: Arc<MCPServer>, addr: SocketAddr) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!(
+        "[MCP Server] Serving Prometheus metrics on {}",
+        listener.local_addr()?
+    );
+
+    axum::serve(listener, metrics_router(server))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+async fn handle_metrics(State(server): State<Arc<MCPServer>>) -> Response {
+    let body = server.prometheus_exporter().render().await;
+    ([(header::CONTENT_TYPE, prometheus::CONTENT_TYPE)], body).into_response()
+}
+
+async fn handle_post(
+    State(server): State<Arc<MCPServer>>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let request: MCPRequest = match serde_json::from_str(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                None,
+                MCPError::InvalidRequest(e.to_string()),
+            )
+        }
+    };
+
+    // initialize establishes a new session and hands its id back to the client
+    if request.method == "initialize" {
+        let session = match server.open_session(&request).await {
+            Ok(session) => session,
+            Err(error) => return error_response(StatusCode::OK, request.id, error),
+        };
+        let session_id = session.session_id.to_string();
+        debug!("HTTP session {} initialized", session_id);
+
+        let mut response = into_http(server.handle_session_request(session, request).await);
+        response.headers_mut().insert(
+            SESSION_HEADER,
+            HeaderValue::from_str(&session_id).expect("UUID is a valid header value"),
+        );
+        return response;
+    }
+
+    let Some(session_id) = session_id(&headers) else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            request.id,
+            MCPError::InvalidRequest(
+                "Mcp-Session-Id header is required; send initialize first".to_string(),
+            ),
+        );
+    };
+
+    match server.tenant_manager().lookup_session(&session_id).await {
+        SessionLookup::Active(session) => {
+            let config = server.tenant_manager().config().clone();
+            if config.server.replay_protection {
+                let fresh = Freshness::from_request(&request).and_then(|freshness| {
+                    session.replay_window.check(
+                        &freshness,
+                        chrono::Utc::now().timestamp_millis(),
+                        config.server.replay_tolerance,
+                    )
+                });
+                if let Err(rejection) = fresh {
+                    let status = match rejection.reason {
+                        ReplayReason::Missing => StatusCode::BAD_REQUEST,
+                        ReplayReason::Duplicate | ReplayReason::Stale => StatusCode::CONFLICT,
+                    };
+                    debug!(
+                        "HTTP session {} refused a possible replay: {}",
+                        session_id, rejection.message
+                    );
+                    return error_response(
+                        status,
+                        request.id,
+                        MCPError::ReplayRejected { rejection },
+                    );
+                }
+            }
+            into_http(server.handle_session_request(session, request).await)
+        }
+        SessionLookup::Ended(reason) => error_response(
+            StatusCode::NOT_FOUND,
+            request.id,
+            MCPError::SessionNotFound {
+                session_id,
+                ended: Some(reason),
+            },
+        ),
+        SessionLookup::Unknown => error_response(
+            StatusCode::NOT_FOUND,
+            request.id,
+            MCPError::SessionNotFound {
+                session_id,
+                ended: None,
+            },
+        ),
+    }
+}
+
+async fn handle_delete(State(server): State<Arc<MCPServer>>, headers: HeaderMap) -> StatusCode {
+    match session_id(&headers) {
+        Some(session_id) if server.tenant_manager().revoke_session(&session_id).await => {
+            StatusCode::NO_CONTENT
+        }
+        Some(_) => StatusCode::NOT_FOUND,
+        None => StatusCode::BAD_REQUEST,
+    }
+}
+
+fn session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+fn into_http(response: Option<MCPResponse>) -> Response {
+    match response {
+        Some(response) => (StatusCode::OK, Json(response)).into_response(),
+        // Notifications are acknowledged without a body
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+fn error_response(status: StatusCode, id: Option<serde_json::Value>, error: MCPError) -> Response {
+    let error: MCPErrorResponse = error.into();
+    let response = MCPResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: None,
+        error: Some(error),
+    };
+    (status, Json(response)).into_response()
+}
@@ -0,0 +1,246 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::mcp::MCPRequest;
+
+/// Set to `true` to require a fresh `seq` or `nonce` + `timestamp` in every HTTP request
+/// after initialize
+pub const REPLAY_PROTECTION_ENV: &str = "MCP_REPLAY_PROTECTION";
+
+/// How far a request's `timestamp` may be from the server's clock, in seconds
+pub const REPLAY_TOLERANCE_ENV: &str = "MCP_REPLAY_TOLERANCE_SECS";
+
+pub const DEFAULT_REPLAY_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// How far below the highest `seq` seen a request may arrive, out of order, and still
+/// be accepted once
+pub const SEQ_WINDOW: u64 = 64;
+
+/// Most nonces a session remembers; when full, the oldest is dropped and no timestamp
+/// at or before it is accepted again
+pub const MAX_NONCES: usize = 1024;
+
+/// Longest nonce accepted
+const MAX_NONCE_LEN: usize = 128;
+
+/// What a request carries in `params._meta` to prove it isn't a replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Freshness {
+    /// A number the client increases with each request on the session
+    Seq(u64),
+    /// A value used once, and when the request was made (Unix milliseconds)
+    Nonce { nonce: String, timestamp_ms: i64 },
+}
+
+impl Freshness {
+    /// The `seq`, or `nonce` and `timestamp`, in the request's `params._meta`
+    pub fn from_request(request: &MCPRequest) -> Result<Self, ReplayRejection> {
+        let meta = request
+            .params
+            .as_ref()
+            .and_then(|params| params.get("_meta"))
+            .unwrap_or(&Value::Null);
+        if let Some(seq) = meta.get("seq") {
+            return seq
+                .as_u64()
+                .map(Freshness::Seq)
+                .ok_or_else(|| ReplayRejection::missing("_meta.seq must be a whole number"));
+        }
+        match (meta.get("nonce"), meta.get("timestamp")) {
+            (Some(nonce), Some(timestamp)) => {
+                let nonce = nonce
+                    .as_str()
+                    .filter(|nonce| !nonce.is_empty() && nonce.len() <= MAX_NONCE_LEN)
+                    .ok_or_else(|| {
+                        ReplayRejection::missing(format!(
+                            "_meta.nonce must be a string of 1-{} characters",
+                            MAX_NONCE_LEN
+                        ))
+                    })?;
+                let timestamp_ms = timestamp.as_i64().ok_or_else(|| {
+                    ReplayRejection::missing("_meta.timestamp must be Unix milliseconds")
+                })?;
+                Ok(Freshness::Nonce {
+                    nonce: nonce.to_string(),
+                    timestamp_ms,
+                })
+            }
+            _ => Err(ReplayRejection::missing(
+                "params._meta needs a seq, or a nonce and a timestamp",
+            )),
+        }
+    }
+}
+
+/// Why a request was refused as a possible replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayReason {
+    /// The request carries no usable `seq` or `nonce` + `timestamp`
+    Missing,
+    /// The `seq` or `nonce` was already used on this session
+    Duplicate,
+    /// The `seq` is too far behind the highest seen, or the `timestamp` outside the
+    /// tolerance
+    Stale,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayRejection {
+    pub reason: ReplayReason,
+    pub message: String,
+}
+
+impl ReplayRejection {
+    fn new(reason: ReplayReason, message: impl Into<String>) -> Self {
+        Self {
+            reason,
+            message: message.into(),
+        }
+    }
+
+    fn missing(message: impl Into<String>) -> Self {
+        Self::new(ReplayReason::Missing, message)
+    }
+}
+
+#[derive(Debug, Default)]
+struct WindowState {
+    /// Highest `seq` accepted so far
+    high_water: Option<u64>,
+    /// Bit `i` is set when `high_water - i` has been accepted
+    seen: u64,
+    /// Timestamp of each remembered nonce
+    nonces: HashMap<String, i64>,
+    /// Remembered nonces by timestamp, oldest first
+    by_time: BTreeSet<(i64, String)>,
+    /// Timestamps at or before this are refused, once a full cache dropped a nonce
+    /// that wasn't old enough to be refused anyway
+    floor_ms: Option<i64>,
+}
+
+/// The `seq` and nonces one session has used. It lives in the session, so it is
+/// dropped when the session ends.
+#[derive(Debug, Default)]
+pub struct ReplayWindow {
+    state: Mutex<WindowState>,
+}
+
+impl ReplayWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept `freshness` if it wasn't used before and isn't stale, remembering it
+    pub fn check(
+        &self,
+        freshness: &Freshness,
+        now_ms: i64,
+        tolerance: Duration,
+    ) -> Result<(), ReplayRejection> {
+        let mut state = self.state.lock().unwrap();
+        match freshness {
+            Freshness::Seq(seq) => state.check_seq(*seq),
+            Freshness::Nonce {
+                nonce,
+                timestamp_ms,
+            } => state.check_nonce(nonce, *timestamp_ms, now_ms, tolerance),
+        }
+    }
+
+    /// Nonces currently remembered
+    #[allow(dead_code)]
+    pub fn nonce_count(&self) -> usize {
+        self.state.lock().unwrap().nonces.len()
+    }
+}
+
+impl WindowState {
+    fn check_seq(&mut self, seq: u64) -> Result<(), ReplayRejection> {
+        let Some(high_water) = self.high_water.filter(|high_water| seq <= *high_water) else {
+            let shift = self
+                .high_water
+                .map_or(SEQ_WINDOW, |high_water| seq - high_water);
+            self.seen = if shift >= SEQ_WINDOW {
+                0
+            } else {
+                self.seen << shift
+            } | 1;
+            self.high_water = Some(seq);
+            return Ok(());
+        };
+        let behind = high_water - seq;
+        if behind >= SEQ_WINDOW {
+            return Err(ReplayRejection::new(
+                ReplayReason::Stale,
+                format!(
+                    "seq {} is more than {} behind the highest seen ({})",
+                    seq, SEQ_WINDOW, high_water
+                ),
+            ));
+        }
+        let bit = 1u64 << behind;
+        if self.seen & bit != 0 {
+            return Err(ReplayRejection::new(
+                ReplayReason::Duplicate,
+                format!("seq {} was already used", seq),
+            ));
+        }
+        self.seen |= bit;
+        Ok(())
+    }
+
+    fn check_nonce(
+        &mut self,
+        nonce: &str,
+        timestamp_ms: i64,
+        now_ms: i64,
+        tolerance: Duration,
+    ) -> Result<(), ReplayRejection> {
+        let tolerance_ms = tolerance.as_millis() as i64;
+        // Nonces older than the tolerance can go: their timestamps are refused anyway
+        while let Some((oldest, _)) = self.by_time.first() {
+            if now_ms - *oldest <= tolerance_ms {
+                break;
+            }
+            let (_, expired) = self.by_time.pop_first().expect("checked above");
+            self.nonces.remove(&expired);
+        }
+
+        // abs_diff: a timestamp near i64::MIN or MAX would overflow a subtraction
+        if now_ms.abs_diff(timestamp_ms) > tolerance_ms as u64 {
+            return Err(ReplayRejection::new(
+                ReplayReason::Stale,
+                format!(
+                    "timestamp is more than {}s from the server's clock",
+                    tolerance.as_secs()
+                ),
+            ));
+        }
+        if self.floor_ms.is_some_and(|floor| timestamp_ms <= floor) {
+            return Err(ReplayRejection::new(
+                ReplayReason::Stale,
+                "timestamp is older than the nonces this session still remembers",
+            ));
+        }
+        if self.nonces.contains_key(nonce) {
+            return Err(ReplayRejection::new(
+                ReplayReason::Duplicate,
+                format!("nonce '{}' was already used", nonce),
+            ));
+        }
+
+        if self.nonces.len() >= MAX_NONCES {
+            if let Some((oldest, dropped)) = self.by_time.pop_first() {
+                self.nonces.remove(&dropped);
+                self.floor_ms = Some(self.floor_ms.map_or(oldest, |floor| floor.max(oldest)));
+            }
+        }
+        self.nonces.insert(nonce.to_string(), timestamp_ms);
+        self.by_time.insert((timestamp_ms, nonce.to_string()));
+        Ok(())
+    }
+}
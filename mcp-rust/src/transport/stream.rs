@@ -0,0 +1,57 @@
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{close_session, handle_session_message};
+use crate::mcp::{MCPResponse, MCPServer};
+
+/// Write `response` as one line of JSON and flush it, as every byte-stream transport
+/// frames its messages
+pub async fn write_response<W>(writer: &mut W, response: &MCPResponse) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let json = serde_json::to_string(response)?;
+    writer.write_all(json.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await
+}
+
+/// Serve one connection carrying newline-delimited JSON-RPC, such as a Unix socket or
+/// an SSH channel, as one session: `initialize` opens it, every later request runs in
+/// it, and it's revoked when the peer closes its end or the server shuts down. Unlike
+/// [`MCPServer::run_with_io`], the server keeps running afterwards, so any number of
+/// connections can be served side by side.
+pub async fn serve_connection<R, W>(
+    server: &MCPServer,
+    mut reader: R,
+    mut writer: W,
+) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut session = None;
+    let mut line = String::new();
+    let result = loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(0) => break Ok(()),
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+        if server.is_shutting_down().await {
+            break Ok(());
+        }
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let Some(response) = handle_session_message(server, &mut session, text).await else {
+            continue;
+        };
+        if let Err(e) = write_response(&mut writer, &response).await {
+            break Err(e);
+        }
+    };
+    close_session(server, session).await;
+    result
+}
@@ -0,0 +1,75 @@
+use std::future::Future;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::BufReader;
+use tokio::net::UnixListener;
+use tracing::debug;
+
+use super::stream::serve_connection;
+use crate::mcp::MCPServer;
+
+/// Serve newline-delimited JSON-RPC on the Unix socket at `path` until ctrl-c, one
+/// session per connection
+#[allow(dead_code)]
+pub async fn serve(server: Arc<MCPServer>, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    serve_until(server, path, async {
+        tokio::signal::ctrl_c().await.ok();
+    })
+    .await
+}
+
+/// Serve the Unix socket at `path` until `shutdown` completes. A socket left at `path`
+/// by an earlier run is replaced, anything else there is an error, and the socket is
+/// removed on return. Connections already open carry on until they close or the
+/// server shuts down.
+#[allow(dead_code)]
+pub async fn serve_until(
+    server: Arc<MCPServer>,
+    path: impl AsRef<Path>,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    remove_stale_socket(path)?;
+    let listener = UnixListener::bind(path)?;
+    eprintln!(
+        "[MCP Server] Starting Unix socket transport on {}",
+        path.display()
+    );
+
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let server = server.clone();
+                    tokio::spawn(async move {
+                        let (reader, writer) = stream.into_split();
+                        if let Err(e) =
+                            serve_connection(&server, BufReader::new(reader), writer).await
+                        {
+                            debug!("Unix socket connection ended: {}", e);
+                        }
+                    });
+                }
+                // Such as running out of file descriptors; the connection is dropped
+                Err(e) => eprintln!("[MCP Server] Failed to accept a connection: {}", e),
+            },
+        }
+    }
+
+    if let Err(e) = std::fs::remove_file(path) {
+        debug!("Could not remove {}: {}", path.display(), e);
+    }
+    Ok(())
+}
+
+fn remove_stale_socket(path: &Path) -> anyhow::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => Ok(std::fs::remove_file(path)?),
+        Ok(_) => anyhow::bail!("{} exists and isn't a socket", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
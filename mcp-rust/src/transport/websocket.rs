@@ -0,0 +1,72 @@
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use super::{close_session, handle_session_message};
+use crate::mcp::MCPServer;
+use crate::tenant::TenantSession;
+
+/// Build the WebSocket router: `GET /mcp` upgrades to a connection carrying JSON-RPC text
+/// frames, `GET /metrics` for Prometheus
+pub fn router(server: Arc<MCPServer>) -> Router {
+    Router::new()
+        .route("/mcp", get(handle_upgrade))
+        .with_state(server.clone())
+        .merge(super::http::metrics_router(server))
+}
+
+/// Serve the WebSocket transport until ctrl-c
+pub async fn serve(server: Arc<MCPServer>, addr: SocketAddr) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!(
+        "[MCP Server] Starting WebSocket transport on {}",
+        listener.local_addr()?
+    );
+
+    axum::serve(listener, router(server))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+async fn handle_upgrade(
+    State(server): State<Arc<MCPServer>>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| connection(server, socket))
+}
+
+/// One session per connection: `initialize` opens it, every later request runs in it,
+/// and it ends when the connection closes
+async fn connection(server: Arc<MCPServer>, mut socket: WebSocket) {
+    let mut session: Option<Arc<TenantSession>> = None;
+    while let Some(Ok(message)) = socket.recv().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            // Pings are answered by axum; binary frames aren't JSON-RPC
+            _ => continue,
+        };
+        let Some(response) = handle_session_message(&server, &mut session, text.as_str()).await
+        else {
+            continue;
+        };
+        let json = match serde_json::to_string(&response) {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("[MCP Server] Failed to serialize response: {}", e);
+                continue;
+            }
+        };
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            break;
+        }
+    }
+    close_session(&server, session).await;
+}
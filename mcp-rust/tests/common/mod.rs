@@ -0,0 +1,99 @@
+// Helpers shared by the unit and integration test crates, each of which includes this
+// module; neither uses all of it
+#![allow(dead_code)]
+
+use mcp_rust::tenant::{
+    ContextType, Permission, ResourceLimits, TenantAwsRole, TenantContext, TenantSession,
+    ToolPolicy, UserRole,
+};
+
+/// Builds the tenant context a test calls tools in. Starts as `user_id`'s personal
+/// context in its own tenant (`{user_id}-tenant`) of the `acme` organization, a `User`
+/// with no permissions in `us-west-2`; set only what the test is about.
+pub struct TestTenant {
+    context: TenantContext,
+}
+
+impl TestTenant {
+    pub fn user(user_id: &str) -> Self {
+        Self {
+            context: TenantContext {
+                tenant_id: format!("{}-tenant", user_id),
+                user_id: user_id.to_string(),
+                context_type: ContextType::Personal,
+                organization_id: "acme".to_string(),
+                role: UserRole::User,
+                permissions: Vec::new(),
+                aws_region: "us-west-2".to_string(),
+                resource_limits: ResourceLimits::default(),
+                tool_policy: ToolPolicy::default(),
+                aws_role: None,
+            },
+        }
+    }
+
+    /// An `Admin` holding the `Admin` permission, who may call every tool
+    pub fn admin(user_id: &str) -> Self {
+        Self::user(user_id)
+            .role(UserRole::Admin)
+            .permissions(vec![Permission::Admin])
+    }
+
+    pub fn tenant(mut self, tenant_id: &str) -> Self {
+        self.context.tenant_id = tenant_id.to_string();
+        self
+    }
+
+    /// The organization the context belongs to, staying personal
+    pub fn organization_id(mut self, organization_id: &str) -> Self {
+        self.context.organization_id = organization_id.to_string();
+        self
+    }
+
+    /// The organization context of `org_id`, whose members share a namespace prefix
+    pub fn in_organization(mut self, org_id: &str, org_name: &str) -> Self {
+        self.context.organization_id = org_id.to_string();
+        self.context_type(ContextType::Organization {
+            org_id: org_id.to_string(),
+            org_name: org_name.to_string(),
+        })
+    }
+
+    pub fn context_type(mut self, context_type: ContextType) -> Self {
+        self.context.context_type = context_type;
+        self
+    }
+
+    pub fn role(mut self, role: UserRole) -> Self {
+        self.context.role = role;
+        self
+    }
+
+    pub fn permissions(mut self, permissions: Vec<Permission>) -> Self {
+        self.context.permissions = permissions;
+        self
+    }
+
+    pub fn region(mut self, aws_region: &str) -> Self {
+        self.context.aws_region = aws_region.to_string();
+        self
+    }
+
+    pub fn tool_policy(mut self, tool_policy: ToolPolicy) -> Self {
+        self.context.tool_policy = tool_policy;
+        self
+    }
+
+    pub fn aws_role(mut self, aws_role: Option<TenantAwsRole>) -> Self {
+        self.context.aws_role = aws_role;
+        self
+    }
+
+    pub fn context(self) -> TenantContext {
+        self.context
+    }
+
+    pub fn session(self) -> TenantSession {
+        TenantSession::new(self.context)
+    }
+}
@@ -0,0 +1,233 @@
+// Integration tests for embedding the server over arbitrary byte streams
+// Drives MCPServer through in-memory duplex pipes and a Unix socket over the in-memory
+// backend; no process is spawned and no AWS access is required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, DuplexStream, Lines,
+    ReadHalf, WriteHalf,
+};
+
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::MCPServer;
+use mcp_rust::serve_connection;
+use mcp_rust::tenant::TenantManager;
+
+async fn server() -> (Arc<MCPServer>, Arc<TenantManager>) {
+    std::env::set_var("DEV_MODE", "true");
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    let server = Arc::new(
+        MCPServer::new(tenant_manager.clone(), Some(registry))
+            .await
+            .unwrap(),
+    );
+    (server, tenant_manager)
+}
+
+/// The client's end of a newline-delimited JSON-RPC stream
+struct Client<R, W> {
+    lines: Lines<BufReader<R>>,
+    writer: W,
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> Client<R, W> {
+    fn new(reader: R, writer: W) -> Self {
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    async fn send(&mut self, message: Value) {
+        let line = format!("{}\n", message);
+        self.writer.write_all(line.as_bytes()).await.unwrap();
+    }
+
+    async fn rpc(&mut self, request: Value) -> Value {
+        self.send(request).await;
+        let line = tokio::time::timeout(Duration::from_secs(5), self.lines.next_line())
+            .await
+            .expect("no response")
+            .unwrap()
+            .expect("the stream closed");
+        serde_json::from_str(&line).unwrap()
+    }
+}
+
+fn duplex_client(stream: DuplexStream) -> Client<ReadHalf<DuplexStream>, WriteHalf<DuplexStream>> {
+    let (reader, writer) = tokio::io::split(stream);
+    Client::new(reader, writer)
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params,
+        "tenant_id": "demo-tenant",
+        "user_id": "user-demo-123"
+    })
+}
+
+/// initialize, the initialized notification, tools/list, and a kv_set then kv_get
+async fn handshake_and_tool_call<R, W>(client: &mut Client<R, W>)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let response = client
+        .rpc(request(
+            1,
+            "initialize",
+            json!({"protocolVersion": "2025-06-18"}),
+        ))
+        .await;
+    assert_eq!(response["id"], 1);
+    assert!(response["error"].is_null(), "{}", response);
+    assert!(
+        response["result"]["protocolVersion"].is_string(),
+        "{}",
+        response
+    );
+    client
+        .send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+        .await;
+
+    let response = client.rpc(request(2, "tools/list", json!({}))).await;
+    let tools = response["result"]["tools"].as_array().unwrap();
+    assert!(tools.iter().any(|tool| tool["name"] == "kv_get"));
+
+    let response = client
+        .rpc(request(
+            3,
+            "tools/call",
+            json!({"name": "kv_set", "arguments": {"key": "greeting", "value": "hello"}}),
+        ))
+        .await;
+    assert!(response["error"].is_null(), "{}", response);
+    let response = client
+        .rpc(request(
+            4,
+            "tools/call",
+            json!({"name": "kv_get", "arguments": {"key": "greeting"}}),
+        ))
+        .await;
+    assert_eq!(response["id"], 4);
+    assert!(response.to_string().contains("hello"), "{}", response);
+}
+
+#[tokio::test]
+async fn test_run_with_io_over_a_duplex_pipe() {
+    let (server, _) = server().await;
+    let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+    let (reader, writer) = tokio::io::split(server_end);
+    let running = {
+        let server = server.clone();
+        tokio::spawn(async move { server.run_with_io(BufReader::new(reader), writer).await })
+    };
+
+    let mut client = duplex_client(client_end);
+    handshake_and_tool_call(&mut client).await;
+
+    // Closing the input ends the loop and shuts the server down, as stdin closing does
+    drop(client);
+    tokio::time::timeout(Duration::from_secs(10), running)
+        .await
+        .expect("the server kept running")
+        .unwrap()
+        .unwrap();
+    assert!(server.is_shutting_down().await);
+}
+
+#[tokio::test]
+async fn test_each_connection_is_one_session() {
+    let (server, tenant_manager) = server().await;
+    let mut connections = Vec::new();
+    let mut clients = Vec::new();
+    for _ in 0..2 {
+        let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(server_end);
+        let server = server.clone();
+        connections.push(tokio::spawn(async move {
+            serve_connection(&server, BufReader::new(reader), writer).await
+        }));
+        clients.push(duplex_client(client_end));
+    }
+
+    // Requests before initialize are rejected
+    let response = clients[0].rpc(request(7, "tools/list", json!({}))).await;
+    assert_eq!(response["error"]["code"], -32600);
+
+    for client in &mut clients {
+        handshake_and_tool_call(client).await;
+    }
+    assert_eq!(tenant_manager.get_all_sessions().await.len(), 2);
+
+    // A closed connection ends its session only; the server keeps serving
+    drop(clients.remove(0));
+    connections.remove(0).await.unwrap().unwrap();
+    assert_eq!(tenant_manager.get_all_sessions().await.len(), 1);
+    assert!(!server.is_shutting_down().await);
+    let response = clients[0]
+        .rpc(request(
+            5,
+            "tools/call",
+            json!({"name": "kv_get", "arguments": {"key": "greeting"}}),
+        ))
+        .await;
+    assert!(response["error"].is_null(), "{}", response);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket_serves_each_connection() {
+    use mcp_rust::transport::unix::serve_until;
+    use tokio::net::UnixStream;
+
+    let (server, tenant_manager) = server().await;
+    let path = std::env::temp_dir().join(format!("mcp-{}.sock", uuid::Uuid::new_v4()));
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let serving = {
+        let server = server.clone();
+        let path = path.clone();
+        tokio::spawn(async move {
+            serve_until(server, path, async {
+                stopped.await.ok();
+            })
+            .await
+        })
+    };
+    for _ in 0..100 {
+        if path.exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    for _ in 0..2 {
+        let (reader, writer) = UnixStream::connect(&path).await.unwrap().into_split();
+        let mut client = Client::new(reader, writer);
+        handshake_and_tool_call(&mut client).await;
+    }
+    assert!(!tenant_manager.get_all_sessions().await.is_empty());
+
+    stop.send(()).unwrap();
+    serving.await.unwrap().unwrap();
+    assert!(!path.exists());
+
+    // Something other than a socket at the path is left alone
+    std::fs::write(&path, "not a socket").unwrap();
+    let refused = serve_until(server, &path, async {}).await;
+    assert!(refused.is_err());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+    std::fs::remove_file(&path).unwrap();
+}
@@ -1,8 +1,7 @@
+use crate::common::TestTenant;
 use mcp_rust::aws::AwsService;
 use mcp_rust::handlers::{EventsQueryHandler, Handler};
-use mcp_rust::tenant::{
-    ContextType, Permission, ResourceLimits, TenantContext, TenantSession, UserRole,
-};
+use mcp_rust::tenant::{Permission, TenantSession, UserRole};
 use serde_json::json;
 /// Integration tests for Events handlers
 /// These tests require either:
@@ -13,22 +12,16 @@ use std::sync::Arc;
 
 // Helper function to create test tenant session
 fn create_test_session() -> TenantSession {
-    let context = TenantContext {
-        tenant_id: "integration-test-tenant".to_string(),
-        user_id: "integration-test-user".to_string(),
-        context_type: ContextType::Personal,
-        organization_id: "integration-test-org".to_string(),
-        role: UserRole::Admin,
-        permissions: vec![
+    TestTenant::user("integration-test-user")
+        .tenant("integration-test-tenant")
+        .organization_id("integration-test-org")
+        .role(UserRole::Admin)
+        .permissions(vec![
             Permission::SendEvents,
             Permission::ReadKV,
             Permission::WriteKV,
-        ],
-        aws_region: "us-west-2".to_string(),
-        resource_limits: ResourceLimits::default(),
-    };
-
-    TenantSession::new(context)
+        ])
+        .session()
 }
 
 // Helper to check if we can run integration tests
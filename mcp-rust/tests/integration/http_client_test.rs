@@ -0,0 +1,229 @@
+// Integration tests for the HTTP MCP client used for `server_type: "http"` servers
+// Runs against a minimal MCP stub served by axum on a local port
+
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use mcp_rust::registry::http::{HttpAuth, HttpMcpClient};
+use mcp_rust::registry::RegistryError;
+
+const STUB_SESSION: &str = "stub-session-1";
+
+/// Speaks just enough MCP: initialize, tools/list and an `echo` tool. Requests are
+/// rejected with 401 unless they carry `expected_auth` (header name, value).
+async fn stub_server(expected_auth: Option<(&'static str, &'static str)>) -> String {
+    let app = Router::new()
+        .route(
+            "/mcp",
+            post(
+                move |headers: HeaderMap, Json(request): Json<Value>| async move {
+                    stub_handle(expected_auth, headers, request).await
+                },
+            ),
+        )
+        .route(
+            "/slow",
+            post(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                StatusCode::OK
+            }),
+        );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+async fn stub_handle(
+    expected_auth: Option<(&'static str, &'static str)>,
+    headers: HeaderMap,
+    request: Value,
+) -> Response {
+    if let Some((name, value)) = expected_auth {
+        if headers.get(name).and_then(|v| v.to_str().ok()) != Some(value) {
+            return (StatusCode::UNAUTHORIZED, "missing credentials").into_response();
+        }
+    }
+
+    let method = request["method"].as_str().unwrap_or_default();
+    if method == "notifications/initialized" {
+        return StatusCode::ACCEPTED.into_response();
+    }
+    if method != "initialize"
+        && headers.get("mcp-session-id").and_then(|v| v.to_str().ok()) != Some(STUB_SESSION)
+    {
+        return (StatusCode::NOT_FOUND, "unknown session").into_response();
+    }
+
+    let id = request["id"].clone();
+    let reply = match method {
+        "initialize" => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {"tools": {}},
+                "serverInfo": {"name": "stub", "version": "1.0"}
+            }
+        }),
+        "tools/list" => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "tools": [{
+                    "name": "echo",
+                    "description": "Echo the arguments",
+                    "inputSchema": {"type": "object"}
+                }]
+            }
+        }),
+        "tools/call" if request["params"]["name"] == "echo" => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [{"type": "text", "text": request["params"]["arguments"].to_string()}]
+            }
+        }),
+        _ => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": -32601, "message": "Method not found"}
+        }),
+    };
+
+    let mut response = Json(reply).into_response();
+    if method == "initialize" {
+        response
+            .headers_mut()
+            .insert("mcp-session-id", STUB_SESSION.parse().unwrap());
+    }
+    response
+}
+
+#[tokio::test]
+async fn test_initialize_list_and_call_tools() {
+    let base = stub_server(None).await;
+    let client = HttpMcpClient::connect(
+        &format!("{}/mcp", base),
+        HttpAuth::None,
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(client.server_info()["serverInfo"]["name"], "stub");
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].name, "echo");
+    assert_eq!(tools[0].description, "Echo the arguments");
+
+    let result = client
+        .call_tool("echo", json!({"message": "hi"}))
+        .await
+        .unwrap();
+    assert_eq!(result["content"][0]["text"], r#"{"message":"hi"}"#);
+
+    let err = client.call_tool("missing", json!({})).await.unwrap_err();
+    assert!(matches!(
+        err,
+        RegistryError::RemoteError { code: -32601, .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_auth_methods_are_applied() {
+    let base = stub_server(Some(("x-api-key", "secret"))).await;
+    let endpoint = format!("{}/mcp", base);
+
+    let client = HttpMcpClient::connect(
+        &endpoint,
+        HttpAuth::Header {
+            name: "X-API-Key".to_string(),
+            value: "secret".to_string(),
+        },
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+    assert_eq!(client.list_tools().await.unwrap().len(), 1);
+
+    let err = HttpMcpClient::connect(&endpoint, HttpAuth::None, Duration::from_secs(5))
+        .await
+        .unwrap_err();
+    match err {
+        RegistryError::HttpStatus { status, body } => {
+            assert_eq!(status, 401);
+            assert_eq!(body, "missing credentials");
+        }
+        other => panic!("expected HTTP status error, got {:?}", other),
+    }
+
+    // "user:pass" in base64
+    let base = stub_server(Some(("authorization", "Basic dXNlcjpwYXNz"))).await;
+    assert!(HttpMcpClient::connect(
+        &format!("{}/mcp", base),
+        HttpAuth::Basic {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        },
+        Duration::from_secs(5),
+    )
+    .await
+    .is_ok());
+
+    let base = stub_server(Some(("authorization", "Bearer token-123"))).await;
+    assert!(HttpMcpClient::connect(
+        &format!("{}/mcp", base),
+        HttpAuth::Bearer("token-123".to_string()),
+        Duration::from_secs(5),
+    )
+    .await
+    .is_ok());
+}
+
+#[tokio::test]
+async fn test_transport_failures_have_distinct_errors() {
+    let base = stub_server(None).await;
+
+    let err = HttpMcpClient::connect(
+        &format!("{}/slow", base),
+        HttpAuth::None,
+        Duration::from_millis(200),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, RegistryError::Timeout(_)), "{:?}", err);
+
+    let err = HttpMcpClient::connect(
+        &format!("{}/nowhere", base),
+        HttpAuth::None,
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(err, RegistryError::HttpStatus { status: 404, .. }),
+        "{:?}",
+        err
+    );
+
+    // Nothing listens on the discard port
+    let err = HttpMcpClient::connect(
+        "http://127.0.0.1:9/mcp",
+        HttpAuth::None,
+        Duration::from_secs(5),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(err, RegistryError::ConnectionFailed(_)),
+        "{:?}",
+        err
+    );
+}
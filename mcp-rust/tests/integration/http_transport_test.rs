@@ -0,0 +1,222 @@
+// Integration tests for the HTTP transport's Mcp-Session-Id session affinity
+// Drives the axum router in-process; no network or AWS access required
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use mcp_rust::config::Config;
+use mcp_rust::mcp::MCPServer;
+use mcp_rust::tenant::{Permission, SessionLookup, TenantManager};
+use mcp_rust::transport::http::{router, SESSION_HEADER};
+
+async fn test_app() -> (Router, Arc<TenantManager>) {
+    std::env::set_var("DEV_MODE", "true");
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let server = Arc::new(MCPServer::new(tenant_manager.clone(), None).await.unwrap());
+    (router(server), tenant_manager)
+}
+
+/// An app requiring a fresh `seq` or nonce in every request after initialize
+async fn replay_protected_app() -> Router {
+    let config = Config::resolve(
+        |var| match var {
+            "DEV_MODE" | "MCP_REPLAY_PROTECTION" => Some("true".to_string()),
+            "MCP_BACKEND" => Some("memory".to_string()),
+            _ => None,
+        },
+        None,
+    )
+    .unwrap();
+    let tenant_manager = Arc::new(TenantManager::with_config(Arc::new(config)).await.unwrap());
+    let server = Arc::new(MCPServer::new(tenant_manager, None).await.unwrap());
+    router(server)
+}
+
+async fn post(
+    app: &Router,
+    session_id: Option<&str>,
+    body: Value,
+) -> (StatusCode, Option<String>, Value) {
+    let mut request = Request::post("/mcp").header("content-type", "application/json");
+    if let Some(session_id) = session_id {
+        request = request.header(SESSION_HEADER, session_id);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let session_header = response
+        .headers()
+        .get(SESSION_HEADER)
+        .map(|v| v.to_str().unwrap().to_string());
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap()
+    };
+    (status, session_header, body)
+}
+
+async fn initialize(app: &Router) -> String {
+    let (status, session_id, body) = post(
+        app,
+        None,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "tenant_id": "demo-tenant",
+            "user_id": "user-demo-123"
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["result"]["protocolVersion"].is_string());
+    session_id.expect("initialize must return Mcp-Session-Id")
+}
+
+fn list_tools(id: u64) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "method": "tools/list"})
+}
+
+#[tokio::test]
+async fn test_session_persists_across_http_requests() {
+    let (app, tenant_manager) = test_app().await;
+    let session_id = initialize(&app).await;
+
+    for id in 2..=3 {
+        let (status, _, body) = post(&app, Some(&session_id), list_tools(id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["result"]["tools"].is_array());
+    }
+
+    // All three requests were served by the same TenantSession
+    assert_eq!(tenant_manager.get_all_sessions().await.len(), 1);
+    match tenant_manager.lookup_session(&session_id).await {
+        SessionLookup::Active(session) => {
+            assert_eq!(session.context.tenant_id, "demo-tenant");
+            // Protocol-control methods don't spend the per-minute budget
+            assert_eq!(
+                session
+                    .request_count
+                    .load(std::sync::atomic::Ordering::SeqCst),
+                0
+            );
+        }
+        other => panic!("Expected active session, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_missing_and_unknown_session_ids_are_rejected() {
+    let (app, _) = test_app().await;
+
+    let (status, _, body) = post(&app, None, list_tools(1)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], -32600);
+
+    let unknown = uuid::Uuid::new_v4().to_string();
+    let (status, _, body) = post(&app, Some(&unknown), list_tools(2)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"]["code"], -32004);
+    assert_eq!(body["error"]["data"]["reason"], "unknown");
+    assert_eq!(body["error"]["data"]["reinitialize"], true);
+}
+
+#[tokio::test]
+async fn test_revoked_session_is_rejected() {
+    let (app, _) = test_app().await;
+    let session_id = initialize(&app).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::delete("/mcp")
+                .header(SESSION_HEADER, &session_id)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let (status, _, body) = post(&app, Some(&session_id), list_tools(2)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"]["code"], -32004);
+    assert_eq!(body["error"]["data"]["reason"], "revoked");
+    assert_eq!(body["error"]["data"]["sessionId"], session_id.as_str());
+}
+
+#[tokio::test]
+async fn test_permission_change_revokes_http_session() {
+    let (app, tenant_manager) = test_app().await;
+    let session_id = initialize(&app).await;
+
+    let admin = match tenant_manager.lookup_session(&session_id).await {
+        SessionLookup::Active(session) => session,
+        other => panic!("Expected active session, got {:?}", other),
+    };
+    tenant_manager
+        .grant_permissions(&admin, "user-demo-123", &[Permission::ManageUsers])
+        .await
+        .unwrap();
+
+    // The client must re-initialize to pick up its new permission set
+    let (status, _, body) = post(&app, Some(&session_id), list_tools(2)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"]["data"]["reason"], "revoked");
+
+    let new_session_id = initialize(&app).await;
+    assert_ne!(new_session_id, session_id);
+    let (status, _, _) = post(&app, Some(&new_session_id), list_tools(3)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_replayed_requests_are_rejected() {
+    let app = replay_protected_app().await;
+    let session_id = initialize(&app).await;
+    let with_meta = |id: u64, meta: Value| json!({"jsonrpc": "2.0", "id": id, "method": "tools/list", "params": {"_meta": meta}});
+
+    let (status, _, body) = post(&app, Some(&session_id), list_tools(2)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], -32011);
+    assert_eq!(body["error"]["data"]["reason"], "missing");
+
+    let first = with_meta(3, json!({"seq": 1}));
+    let (status, _, body) = post(&app, Some(&session_id), first.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["result"]["tools"].is_array());
+
+    // The same request captured and sent again
+    let (status, _, body) = post(&app, Some(&session_id), first).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["error"]["code"], -32011);
+    assert_eq!(body["error"]["data"]["reason"], "duplicate");
+
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let nonce = with_meta(4, json!({"nonce": "a1b2", "timestamp": now_ms}));
+    let (status, _, _) = post(&app, Some(&session_id), nonce.clone()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, _, body) = post(&app, Some(&session_id), nonce).await;
+    assert_eq!(body["error"]["data"]["reason"], "duplicate");
+
+    // Each session has a window of its own
+    let other_session_id = initialize(&app).await;
+    let (status, _, _) = post(
+        &app,
+        Some(&other_session_id),
+        with_meta(5, json!({"seq": 1})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
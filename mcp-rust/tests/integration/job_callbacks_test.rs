@@ -0,0 +1,226 @@
+// Integration tests for webhook callbacks of background jobs
+// Runs against a stub webhook served by axum on a local port, over the in-memory backend
+
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::jobs::callbacks::JobCallback;
+use mcp_rust::handlers::jobs::{JobManager, JobOptions, JobRecord, JobState};
+use mcp_rust::handlers::{HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+/// Bodies the stub webhook received
+type Deliveries = Arc<Mutex<Vec<Value>>>;
+
+/// A webhook that records every POST and answers with `status`
+async fn webhook(status: StatusCode) -> (String, Deliveries) {
+    let deliveries: Deliveries = Arc::default();
+    let recorded = deliveries.clone();
+    let app = Router::new().route(
+        "/hook",
+        post(move |Json(body): Json<Value>| {
+            let deliveries = recorded.clone();
+            async move {
+                deliveries.lock().unwrap().push(body);
+                status
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}/hook", addr), deliveries)
+}
+
+fn session(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id)
+        .permissions(vec![Permission::SendEvents, Permission::PutArtifacts])
+        .session()
+}
+
+/// Jobs allowed to call back the loopback stub webhook
+fn local_options() -> JobOptions {
+    JobOptions {
+        allow_loopback_webhooks: true,
+        ..JobOptions::default()
+    }
+}
+
+/// The job once its callback has been sent, or has failed to be
+async fn wait_for_callback(jobs: &JobManager, session: &TenantSession, job_id: &str) -> JobRecord {
+    for _ in 0..300 {
+        let record = jobs.status(session, job_id).await.unwrap();
+        if let Some(delivery) = &record.callback_delivery {
+            if delivery.delivered || delivery.error.is_some() {
+                return record;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("the callback of job {} never fired", job_id);
+}
+
+#[tokio::test]
+async fn test_webhook_callback_is_delivered_exactly_once() {
+    let (url, deliveries) = webhook(StatusCode::OK).await;
+    let aws = Arc::new(MemoryAwsService::new());
+    let jobs = Arc::new(JobManager::new(aws.clone(), local_options()));
+    let alice = session("alice");
+
+    let record = jobs
+        .spawn(
+            &alice,
+            "test",
+            Some(JobCallback::Webhook { url }),
+            |_| async { Ok(json!({"key": "exports/out.jsonl", "count": 3})) },
+        )
+        .await
+        .unwrap();
+    let finished = wait_for_callback(&jobs, &alice, &record.job_id).await;
+    assert_eq!(finished.state, JobState::Succeeded);
+    assert!(finished.callback_delivery.unwrap().delivered);
+
+    // A retry, and a restarted server, find the delivery already claimed
+    assert!(!jobs.fire_callback(&alice, &record.job_id).await.unwrap());
+    let restarted = JobManager::new(aws, local_options());
+    assert!(!restarted
+        .fire_callback(&alice, &record.job_id)
+        .await
+        .unwrap());
+    let (first, second) = tokio::join!(
+        jobs.fire_callback(&alice, &record.job_id),
+        restarted.fire_callback(&alice, &record.job_id)
+    );
+    assert!(!first.unwrap() && !second.unwrap());
+
+    let deliveries = deliveries.lock().unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(
+        deliveries[0],
+        json!({
+            "job_id": record.job_id,
+            "job_type": "test",
+            "state": "succeeded",
+            "result_key": "exports/out.jsonl",
+            "error": null
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_failed_webhook_is_recorded_without_changing_the_job() {
+    let (url, deliveries) = webhook(StatusCode::INTERNAL_SERVER_ERROR).await;
+    let aws = Arc::new(MemoryAwsService::new());
+    let jobs = Arc::new(JobManager::new(aws, local_options()));
+    let alice = session("alice");
+
+    let record = jobs
+        .spawn(
+            &alice,
+            "test",
+            Some(JobCallback::Webhook { url }),
+            |_| async { Ok(json!({"count": 0})) },
+        )
+        .await
+        .unwrap();
+    let finished = wait_for_callback(&jobs, &alice, &record.job_id).await;
+    assert_eq!(finished.state, JobState::Succeeded);
+    assert_eq!(finished.result, Some(json!({"count": 0})));
+    let delivery = finished.callback_delivery.unwrap();
+    assert!(!delivery.delivered);
+    assert!(delivery.error.unwrap().contains("500"));
+
+    // A failed delivery isn't retried either
+    assert!(!jobs.fire_callback(&alice, &record.job_id).await.unwrap());
+    assert_eq!(deliveries.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_events_export_posts_its_artifact_key() {
+    let (url, deliveries) = webhook(StatusCode::NO_CONTENT).await;
+    let aws = Arc::new(MemoryAwsService::new());
+    let options = RegistryOptions {
+        jobs: local_options(),
+        ..RegistryOptions::default()
+    };
+    let registry = HandlerRegistry::with_backend(aws, options).await;
+    let alice = session("alice");
+    registry
+        .handle_tool_call(
+            &alice,
+            "events_send",
+            json!({"detailType": "demo.step", "detail": {}}),
+        )
+        .await
+        .unwrap();
+
+    let started = registry
+        .handle_tool_call(
+            &alice,
+            "events_export",
+            json!({"key": "exports/demo.jsonl", "callback": {"type": "webhook", "url": url}}),
+        )
+        .await
+        .unwrap();
+    let job_id = started["job_id"].as_str().unwrap().to_string();
+    for _ in 0..300 {
+        if !deliveries.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let delivered = deliveries.lock().unwrap().clone();
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0]["job_id"], job_id.as_str());
+    assert_eq!(delivered[0]["job_type"], "events_export");
+    assert_eq!(delivered[0]["result_key"], "exports/demo.jsonl");
+
+    // Webhooks must be https away from loopback
+    let rejected = registry
+        .handle_tool_call(
+            &alice,
+            "events_export",
+            json!({"callback": {"type": "webhook", "url": "http://hooks.example.com/jobs"}}),
+        )
+        .await;
+    assert!(rejected.is_err());
+}
+
+#[tokio::test]
+async fn test_loopback_webhooks_are_refused_by_default() {
+    let (url, deliveries) = webhook(StatusCode::OK).await;
+    let aws = Arc::new(MemoryAwsService::new());
+    let jobs = Arc::new(JobManager::new(aws.clone(), JobOptions::default()));
+    let alice = session("alice");
+
+    let record = jobs
+        .spawn(
+            &alice,
+            "test",
+            Some(JobCallback::Webhook { url: url.clone() }),
+            |_| async { Ok(json!({})) },
+        )
+        .await
+        .unwrap();
+    let finished = wait_for_callback(&jobs, &alice, &record.job_id).await;
+    let delivery = finished.callback_delivery.unwrap();
+    assert!(!delivery.delivered);
+    assert!(delivery.error.unwrap().contains("loopback"));
+    assert!(deliveries.lock().unwrap().is_empty());
+
+    let registry = HandlerRegistry::with_backend(aws, RegistryOptions::default()).await;
+    let rejected = registry
+        .handle_tool_call(
+            &alice,
+            "events_export",
+            json!({"callback": {"type": "webhook", "url": url}}),
+        )
+        .await;
+    assert!(rejected.is_err());
+}
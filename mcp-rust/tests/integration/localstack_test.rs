@@ -0,0 +1,82 @@
+// Integration tests for running the AWS backend against LocalStack
+// Require LocalStack (set LOCALSTACK_ENDPOINT) with the KV table and artifacts bucket created; skipped otherwise
+
+use crate::common::TestTenant;
+use mcp_rust::aws::AwsService;
+use mcp_rust::tenant::{Permission, TenantSession, UserRole};
+
+fn session() -> TenantSession {
+    TestTenant::user("localstack-user")
+        .tenant("localstack-tenant")
+        .organization_id("localstack-org")
+        .role(UserRole::Admin)
+        .permissions(vec![Permission::ReadKV, Permission::WriteKV])
+        .region("us-east-1")
+        .session()
+}
+
+async fn localstack() -> Option<AwsService> {
+    if std::env::var("LOCALSTACK_ENDPOINT").is_err() {
+        println!("⏭️  Skipping - LOCALSTACK_ENDPOINT is not set");
+        return None;
+    }
+    Some(AwsService::new("us-east-1").await.unwrap())
+}
+
+#[tokio::test]
+#[ignore] // Requires LocalStack
+async fn test_kv_round_trip() {
+    let Some(aws_service) = localstack().await else {
+        return;
+    };
+    let session = session();
+
+    aws_service
+        .kv_set(&session, "localstack-key", "hello", Some(1), false)
+        .await
+        .unwrap();
+    assert_eq!(
+        aws_service
+            .kv_get(&session, "localstack-key")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("hello")
+    );
+}
+
+#[tokio::test]
+#[ignore] // Requires LocalStack
+async fn test_artifacts_round_trip() {
+    let Some(aws_service) = localstack().await else {
+        return;
+    };
+    let session = session();
+
+    // Path-style addressing: LocalStack doesn't resolve bucket subdomains
+    aws_service
+        .artifacts_put(
+            &session,
+            "localstack/report.json",
+            b"{\"ok\":true}",
+            "application/json",
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        aws_service
+            .artifacts_get(&session, "localstack/report.json")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(&b"{\"ok\":true}"[..])
+    );
+    assert_eq!(
+        aws_service
+            .artifacts_get(&session, "localstack/missing.json")
+            .await
+            .unwrap(),
+        None
+    );
+}
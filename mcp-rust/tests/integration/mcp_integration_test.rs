@@ -35,6 +35,8 @@ fn test_mcp_server_notification_vs_request_handling() {
         .env("DEFAULT_TENANT_ID", "test")
         .env("DEFAULT_USER_ID", "test")
         .env("AWS_REGION", "us-west-2")
+        // Without credentials the server would refuse to start
+        .env("MCP_ALLOW_OFFLINE", "true")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -128,6 +130,8 @@ fn test_mcp_server_protocol_version() {
         .env("DEFAULT_TENANT_ID", "test")
         .env("DEFAULT_USER_ID", "test")
         .env("AWS_REGION", "us-west-2")
+        // Without credentials the server would refuse to start
+        .env("MCP_ALLOW_OFFLINE", "true")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -181,6 +185,8 @@ fn test_mcp_server_json_rpc_compliance() {
         .env("DEFAULT_TENANT_ID", "test")
         .env("DEFAULT_USER_ID", "test")
         .env("AWS_REGION", "us-west-2")
+        // Without credentials the server would refuse to start
+        .env("MCP_ALLOW_OFFLINE", "true")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
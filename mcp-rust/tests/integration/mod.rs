@@ -2,5 +2,18 @@
 // Tests interactions between components
 // Characteristics: Medium speed, limited external dependencies
 
+#[path = "../common/mod.rs"]
+mod common;
+
+mod embedded_io_test;
 mod events_integration_test;
+mod http_client_test;
+mod http_transport_test;
+mod job_callbacks_test;
+mod kv_direct_test;
+mod localstack_test;
 mod mcp_integration_test;
+mod oauth_refresh_test;
+mod prometheus_metrics_test;
+mod websocket_client_test;
+mod websocket_transport_test;
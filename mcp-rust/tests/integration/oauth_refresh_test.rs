@@ -0,0 +1,296 @@
+// Integration tests for refreshing the OAuth2 access tokens of downstream servers
+// Runs against a stub token endpoint (and MCP server) served by axum on a local port
+
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::post;
+use axum::{Form, Json, Router};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore, SecretStore};
+use mcp_rust::handlers::integrations::IntegrationTestHandler;
+use mcp_rust::handlers::Handler;
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-oauth-user";
+
+/// Refresh requests the stub token endpoint received
+type Requests = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+/// A token endpoint that issues `token-{n}` for the n-th refresh, valid for
+/// `expires_in` seconds, or rejects every refresh token when `expires_in` is `None`
+async fn token_endpoint(expires_in: Option<i64>) -> (String, Requests) {
+    let requests: Requests = Arc::default();
+    let recorded = requests.clone();
+    let app = Router::new().route(
+        "/token",
+        post(move |Form(form): Form<HashMap<String, String>>| {
+            let requests = recorded.clone();
+            async move {
+                let n = {
+                    let mut requests = requests.lock().unwrap();
+                    requests.push(form);
+                    requests.len()
+                };
+                match expires_in {
+                    Some(expires_in) => Json(json!({
+                        "access_token": format!("token-{}", n),
+                        "refresh_token": format!("refresh-{}", n),
+                        "token_type": "Bearer",
+                        "expires_in": expires_in
+                    }))
+                    .into_response(),
+                    None => (
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({"error": "invalid_grant"})),
+                    )
+                        .into_response(),
+                }
+            }
+        }),
+    );
+    (serve(app).await + "/token", requests)
+}
+
+/// An HTTP MCP server whose `whoami` tool answers with the request's bearer token
+async fn mcp_endpoint() -> String {
+    let app = Router::new().route(
+        "/mcp",
+        post(
+            |headers: HeaderMap, Json(request): Json<Value>| async move {
+                let id = request["id"].clone();
+                let result = match request["method"].as_str().unwrap_or_default() {
+                    "notifications/initialized" => return StatusCode::ACCEPTED.into_response(),
+                    "initialize" => json!({
+                        "protocolVersion": "2025-06-18",
+                        "capabilities": {"tools": {}},
+                        "serverInfo": {"name": "stub", "version": "1.0"}
+                    }),
+                    "tools/list" => json!({"tools": [{"name": "whoami", "inputSchema": {}}]}),
+                    _ => {
+                        let token = headers
+                            .get("authorization")
+                            .and_then(|v| v.to_str().ok())
+                            .unwrap_or_default()
+                            .trim_start_matches("Bearer ")
+                            .to_string();
+                        json!({"content": [{"type": "text", "text": token}]})
+                    }
+                };
+                Json(json!({"jsonrpc": "2.0", "id": id, "result": result})).into_response()
+            },
+        ),
+    );
+    serve(app).await + "/mcp"
+}
+
+async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+/// A process server whose `whoami` tool answers with its `ACCESS_TOKEN`
+fn process_config(token_endpoint: &str) -> MCPServerConfig {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools =
+        r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"whoami","inputSchema":{}}]}}"#;
+    let call = r#"read l; echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"'"$ACCESS_TOKEN"'"}]}}';"#;
+    let script = format!(
+        "read l; echo '{}'; read l; read l; echo '{}'; {} cat >/dev/null",
+        initialized, tools, call
+    );
+    config(
+        MCPServerType::Stdio,
+        DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script],
+        },
+        token_endpoint,
+    )
+}
+
+fn config(
+    server_type: MCPServerType,
+    deployment: DeploymentConfig,
+    token_endpoint: &str,
+) -> MCPServerConfig {
+    MCPServerConfig {
+        id: "mail".to_string(),
+        name: "Mail".to_string(),
+        description: "oauth integration".to_string(),
+        server_type,
+        deployment,
+        env: Default::default(),
+        auth_method: AuthMethod::OAuth2 {
+            client_id: "client-1".to_string(),
+            client_secret: "secret-1".to_string(),
+            token_endpoint: Some(token_endpoint.to_string()),
+        },
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: true,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+/// A registry with `config` registered and tokens that expire in `expires_in_secs`
+async fn registry(
+    config: MCPServerConfig,
+    expires_in_secs: i64,
+) -> (Arc<MCPServerRegistry>, Arc<MemorySecretStore>) {
+    let secrets = Arc::new(MemorySecretStore::new());
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(secrets.clone());
+    registry.register_server(TENANT, config).await.unwrap();
+    let expires_at = Utc::now() + Duration::seconds(expires_in_secs);
+    let tokens = HashMap::from([
+        ("access_token".to_string(), "token-0".to_string()),
+        ("refresh_token".to_string(), "refresh-0".to_string()),
+        ("expires_at".to_string(), expires_at.to_rfc3339()),
+    ]);
+    registry
+        .store_credentials(TENANT, "mail", &tokens)
+        .await
+        .unwrap();
+    (Arc::new(registry), secrets)
+}
+
+async fn whoami(registry: &MCPServerRegistry) -> Result<String, RegistryError> {
+    let result = registry
+        .execute_tool(TENANT, "mail", "whoami", json!({}))
+        .await?;
+    Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("oauth-user")
+        .tenant("oauth-tenant")
+        .organization_id("oauth-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_expiring_token_is_refreshed_before_start() {
+    let (token_endpoint, requests) = token_endpoint(Some(3600)).await;
+    let (registry, secrets) = registry(process_config(&token_endpoint), 10).await;
+
+    registry.connect_server(TENANT, "mail", None).await.unwrap();
+
+    assert_eq!(whoami(&registry).await.unwrap(), "token-1");
+    {
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0]["grant_type"], "refresh_token");
+        assert_eq!(requests[0]["refresh_token"], "refresh-0");
+        assert_eq!(requests[0]["client_id"], "client-1");
+        assert_eq!(requests[0]["client_secret"], "secret-1");
+    }
+    // The rotated refresh token replaces the old one
+    assert_eq!(
+        secrets
+            .secret_get(TENANT, "mail", "refresh_token")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("refresh-1")
+    );
+
+    let report = IntegrationTestHandler::new(registry.clone())
+        .handle(&session(), json!({"service_id": "mail"}))
+        .await
+        .unwrap();
+    assert_eq!(report["oauth"]["valid"], true);
+    assert_eq!(report["oauth"]["refreshable"], true);
+    assert_eq!(report["oauth"]["needs_reauth"], false);
+    let expires_at: chrono::DateTime<Utc> =
+        serde_json::from_value(report["oauth"]["expires_at"].clone()).unwrap();
+    assert!(expires_at > Utc::now() + Duration::minutes(59));
+
+    // A fresh token is used as is on the next start
+    registry.disconnect_server(TENANT, "mail").await.unwrap();
+    registry.connect_server(TENANT, "mail", None).await.unwrap();
+    assert_eq!(whoami(&registry).await.unwrap(), "token-1");
+    assert_eq!(requests.lock().unwrap().len(), 1);
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_http_servers_get_refreshed_tokens_per_request() {
+    let (token_endpoint, requests) = token_endpoint(Some(30)).await;
+    let mut config = config(
+        MCPServerType::Http,
+        DeploymentConfig::Process {
+            command: "true".to_string(),
+            args: Vec::new(),
+        },
+        &token_endpoint,
+    );
+    config
+        .env
+        .insert("endpoint".to_string(), mcp_endpoint().await);
+    let (registry, _) = registry(config, 3600).await;
+
+    registry.connect_server(TENANT, "mail", None).await.unwrap();
+    assert_eq!(whoami(&registry).await.unwrap(), "token-0");
+    assert!(requests.lock().unwrap().is_empty());
+
+    // Tokens valid for less than the refresh skew are refreshed before every call
+    registry
+        .store_credentials(
+            TENANT,
+            "mail",
+            &HashMap::from([(
+                "expires_at".to_string(),
+                (Utc::now() + Duration::seconds(5)).to_rfc3339(),
+            )]),
+        )
+        .await
+        .unwrap();
+    registry.disconnect_server(TENANT, "mail").await.unwrap();
+    registry.connect_server(TENANT, "mail", None).await.unwrap();
+    assert_eq!(whoami(&registry).await.unwrap(), "token-2");
+    assert_eq!(whoami(&registry).await.unwrap(), "token-3");
+}
+
+#[tokio::test]
+async fn test_rejected_refresh_needs_reauth() {
+    let (token_endpoint, requests) = token_endpoint(None).await;
+    let (registry, _) = registry(process_config(&token_endpoint), -60).await;
+
+    match registry.connect_server(TENANT, "mail", None).await {
+        Err(RegistryError::ReauthRequired(message)) => {
+            assert!(message.contains("invalid_grant"), "{}", message)
+        }
+        other => panic!("expected ReauthRequired, got {:?}", other),
+    }
+
+    let servers = registry.list_servers(TENANT).await.unwrap();
+    assert_eq!(servers[0].status, "NeedsReauth");
+    let status = registry
+        .token_status(TENANT, "mail")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!status.valid);
+    assert!(status.needs_reauth);
+
+    // Not retried by the health monitor despite auto_reconnect
+    registry.health_check().await;
+    assert_eq!(
+        registry.list_servers(TENANT).await.unwrap()[0].status,
+        "NeedsReauth"
+    );
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
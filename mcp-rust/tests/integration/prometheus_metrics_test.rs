@@ -0,0 +1,342 @@
+// Integration tests for the Prometheus /metrics endpoint
+// Drives the axum router in-process over the in-memory backend, then parses the scrape; no network or AWS access required
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::MCPServer;
+use mcp_rust::metrics::prometheus::CONTENT_TYPE;
+use mcp_rust::tenant::TenantManager;
+use mcp_rust::transport::http::{metrics_router, router, SESSION_HEADER};
+
+async fn test_server() -> Arc<MCPServer> {
+    std::env::set_var("DEV_MODE", "true");
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    Arc::new(
+        MCPServer::new(tenant_manager, Some(registry))
+            .await
+            .unwrap(),
+    )
+}
+
+async fn post(app: &Router, session_id: Option<&str>, body: Value) -> Option<String> {
+    let mut request = Request::post("/mcp").header("content-type", "application/json");
+    if let Some(session_id) = session_id {
+        request = request.header(SESSION_HEADER, session_id);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    response
+        .headers()
+        .get(SESSION_HEADER)
+        .map(|v| v.to_str().unwrap().to_string())
+}
+
+async fn scrape(app: &Router) -> String {
+    let response = app
+        .clone()
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        CONTENT_TYPE
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// A parsed sample: metric name, labels and value
+type Sample = (String, BTreeMap<String, String>, f64);
+
+fn parse_labels(labels: &str) -> BTreeMap<String, String> {
+    let mut parsed = BTreeMap::new();
+    let mut rest = labels;
+    while !rest.is_empty() {
+        let (name, after) = rest.split_once("=\"").expect("label name");
+        assert!(
+            name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "bad label name {:?}",
+            name
+        );
+        let mut value = String::new();
+        let mut chars = after.char_indices();
+        let end = loop {
+            match chars.next().expect("unterminated label value") {
+                (_, '\\') => match chars.next().expect("dangling escape").1 {
+                    'n' => value.push('\n'),
+                    escaped => value.push(escaped),
+                },
+                (index, '"') => break index,
+                (_, c) => value.push(c),
+            }
+        };
+        parsed.insert(name.to_string(), value);
+        rest = after[end + 1..].trim_start_matches(',');
+    }
+    parsed
+}
+
+/// Parse the text exposition format strictly enough to catch malformed output: every
+/// sample belongs to a family declared by `# TYPE` beforehand. Returns the families
+/// with their types, and the samples.
+fn parse(exposition: &str) -> (BTreeMap<String, String>, Vec<Sample>) {
+    let mut families = BTreeMap::new();
+    let mut samples = Vec::new();
+    for line in exposition.lines() {
+        if let Some(help) = line.strip_prefix("# HELP ") {
+            assert!(
+                help.split_once(' ').is_some(),
+                "HELP without text: {}",
+                line
+            );
+        } else if let Some(declaration) = line.strip_prefix("# TYPE ") {
+            let (name, kind) = declaration.split_once(' ').unwrap();
+            assert!(
+                ["counter", "gauge", "histogram"].contains(&kind),
+                "{}",
+                line
+            );
+            assert!(
+                families
+                    .insert(name.to_string(), kind.to_string())
+                    .is_none(),
+                "family declared twice: {}",
+                name
+            );
+        } else {
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            let value: f64 = match value {
+                "+Inf" => f64::INFINITY,
+                value => value.parse().unwrap_or_else(|_| panic!("value: {}", line)),
+            };
+            let (name, labels) = match series.split_once('{') {
+                Some((name, labels)) => (name, parse_labels(labels.strip_suffix('}').unwrap())),
+                None => (series, BTreeMap::new()),
+            };
+            let family = ["_bucket", "_sum", "_count"]
+                .iter()
+                .find_map(|suffix| {
+                    name.strip_suffix(suffix)
+                        .filter(|base| families.get(*base).map(String::as_str) == Some("histogram"))
+                })
+                .unwrap_or(name);
+            assert!(
+                families.contains_key(family),
+                "sample before its TYPE: {}",
+                line
+            );
+            samples.push((name.to_string(), labels, value));
+        }
+    }
+    (families, samples)
+}
+
+fn value(samples: &[Sample], name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+    samples
+        .iter()
+        .find(|(sample, sample_labels, _)| {
+            sample == name
+                && labels.iter().all(|(label, value)| {
+                    sample_labels.get(*label).map(String::as_str) == Some(*value)
+                })
+        })
+        .map(|(_, _, value)| *value)
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint_exposes_expected_families_after_traffic() {
+    let server = test_server().await;
+    let app = router(server);
+
+    let session_id = post(
+        &app,
+        None,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "tenant_id": "demo-tenant",
+            "user_id": "user-demo-123"
+        }),
+    )
+    .await
+    .expect("initialize returns a session id");
+    let session = Some(session_id.as_str());
+    post(
+        &app,
+        session,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+    )
+    .await;
+    post(
+        &app,
+        session,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": {"name": "kv_set", "arguments": {"key": "k", "value": "v"}}
+        }),
+    )
+    .await;
+    post(
+        &app,
+        session,
+        json!({"jsonrpc": "2.0", "id": 4, "method": "no/such-method"}),
+    )
+    .await;
+
+    let (families, samples) = parse(&scrape(&app).await);
+    let expected = BTreeSet::from([
+        "mcp_requests_total",
+        "mcp_tool_calls_total",
+        "mcp_tool_errors_total",
+        "mcp_tool_duration_seconds",
+        "mcp_active_sessions",
+        "mcp_active_requests",
+        "mcp_rate_limit_rejections_total",
+        "mcp_registry_connections",
+    ]);
+    let declared: BTreeSet<&str> = families.keys().map(String::as_str).collect();
+    assert_eq!(declared, expected);
+    assert_eq!(families["mcp_tool_duration_seconds"], "histogram");
+
+    assert_eq!(
+        value(
+            &samples,
+            "mcp_requests_total",
+            &[("method", "initialize"), ("code", "ok")]
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        value(
+            &samples,
+            "mcp_requests_total",
+            &[("method", "tools/call"), ("code", "ok")]
+        ),
+        Some(1.0)
+    );
+    // Unknown methods share one series rather than each getting their own
+    assert_eq!(
+        value(
+            &samples,
+            "mcp_requests_total",
+            &[("method", "other"), ("code", "-32601")]
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        value(&samples, "mcp_tool_calls_total", &[("tool", "kv_set")]),
+        Some(1.0)
+    );
+    assert_eq!(
+        value(
+            &samples,
+            "mcp_tool_duration_seconds_bucket",
+            &[("tool", "kv_set"), ("le", "+Inf")]
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        value(
+            &samples,
+            "mcp_tool_duration_seconds_count",
+            &[("tool", "kv_set")]
+        ),
+        Some(1.0)
+    );
+    assert_eq!(value(&samples, "mcp_active_sessions", &[]), Some(1.0));
+
+    // No per-tenant or per-user labels anywhere
+    for (_, labels, _) in &samples {
+        for label in labels.keys() {
+            assert!(
+                ["method", "code", "tool", "le", "service_key", "status"].contains(&label.as_str()),
+                "unexpected label {}",
+                label
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_histogram_buckets_are_cumulative() {
+    let server = test_server().await;
+    let app = router(server);
+    let session_id = post(
+        &app,
+        None,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "tenant_id": "demo-tenant",
+            "user_id": "user-demo-123"
+        }),
+    )
+    .await
+    .unwrap();
+    for id in 2..5 {
+        post(
+            &app,
+            Some(&session_id),
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": {"name": "kv_get", "arguments": {"key": "missing"}}
+            }),
+        )
+        .await;
+    }
+
+    let (_, samples) = parse(&scrape(&app).await);
+    let buckets: Vec<f64> = samples
+        .iter()
+        .filter(|(name, labels, _)| {
+            name == "mcp_tool_duration_seconds_bucket"
+                && labels.get("tool").map(String::as_str) == Some("kv_get")
+        })
+        .map(|(_, _, value)| *value)
+        .collect();
+    assert!(
+        buckets.windows(2).all(|pair| pair[0] <= pair[1]),
+        "{:?}",
+        buckets
+    );
+    assert_eq!(buckets.last(), Some(&3.0));
+}
+
+#[tokio::test]
+async fn test_dedicated_metrics_router_serves_only_metrics() {
+    let server = test_server().await;
+    let app = metrics_router(server);
+
+    let (families, _) = parse(&scrape(&app).await);
+    assert!(families.contains_key("mcp_requests_total"));
+
+    let response = app
+        .oneshot(Request::post("/mcp").body(Body::from("{}")).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,320 @@
+// Integration tests for the WebSocket MCP client used for `server_type: "websocket"`
+// servers. Runs against a minimal MCP stub served by tokio-tungstenite on a local port
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::Message;
+
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::registry::http::HttpAuth;
+use mcp_rust::registry::websocket::{WebSocketMcpClient, WebSocketOptions};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+
+/// Instructions pushed to every open stub connection
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    /// Send `notifications/tools/list_changed`
+    ToolsChanged,
+    /// Close the socket without a close frame
+    Drop,
+}
+
+struct Stub {
+    url: String,
+    tools: Arc<Mutex<Vec<&'static str>>>,
+    commands: broadcast::Sender<Command>,
+    /// Connections accepted so far
+    connections: Arc<AtomicUsize>,
+    /// How many of the next connections ignore `initialize`, then close after
+    /// `HUNG_HANDSHAKE_CLOSE`
+    hung_handshakes: Arc<AtomicUsize>,
+}
+
+const HUNG_HANDSHAKE_CLOSE: Duration = Duration::from_millis(800);
+
+/// Speaks just enough MCP: initialize, tools/list (from `tools`), an `echo` tool and a
+/// `hang` tool that never answers
+async fn stub_server() -> Stub {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let tools = Arc::new(Mutex::new(vec!["echo", "hang"]));
+    let (commands, _) = broadcast::channel(8);
+    let connections = Arc::new(AtomicUsize::new(0));
+    let hung_handshakes = Arc::new(AtomicUsize::new(0));
+
+    let shared_tools = tools.clone();
+    let sender = commands.clone();
+    let accepted = connections.clone();
+    let hung = hung_handshakes.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let tools = shared_tools.clone();
+            let mut commands = sender.subscribe();
+            accepted.fetch_add(1, Ordering::SeqCst);
+            let hangs = hung
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            tokio::spawn(async move {
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let hang_up = tokio::time::sleep(if hangs {
+                    HUNG_HANDSHAKE_CLOSE
+                } else {
+                    Duration::MAX
+                });
+                tokio::pin!(hang_up);
+                loop {
+                    tokio::select! {
+                        frame = socket.next() => {
+                            let Some(Ok(Message::Text(text))) = frame else { return };
+                            let request: Value = serde_json::from_str(text.as_str()).unwrap();
+                            if hangs {
+                                continue;
+                            }
+                            if let Some(reply) = stub_reply(&tools, &request) {
+                                socket.send(Message::text(reply.to_string())).await.unwrap();
+                            }
+                        }
+                        command = commands.recv() => match command {
+                            Ok(Command::ToolsChanged) => {
+                                let note = json!({
+                                    "jsonrpc": "2.0",
+                                    "method": "notifications/tools/list_changed"
+                                });
+                                socket.send(Message::text(note.to_string())).await.unwrap();
+                            }
+                            Ok(Command::Drop) | Err(_) => return,
+                        },
+                        _ = &mut hang_up => return,
+                    }
+                }
+            });
+        }
+    });
+
+    Stub {
+        url: format!("ws://{}", addr),
+        tools,
+        commands,
+        connections,
+        hung_handshakes,
+    }
+}
+
+fn stub_reply(tools: &Mutex<Vec<&'static str>>, request: &Value) -> Option<Value> {
+    let id = request.get("id")?.clone();
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "initialize" => json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": {"tools": {"listChanged": true}},
+            "serverInfo": {"name": "ws-stub", "version": "1.0"}
+        }),
+        "tools/list" => {
+            let tools: Vec<Value> = tools
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|name| json!({"name": name, "description": name, "inputSchema": {}}))
+                .collect();
+            json!({ "tools": tools })
+        }
+        "tools/call" if request["params"]["name"] == "hang" => return None,
+        "tools/call" if request["params"]["name"] == "echo" => json!({
+            "content": [{"type": "text", "text": request["params"]["arguments"].to_string()}]
+        }),
+        _ => {
+            return Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": -32601, "message": "Method not found"}
+            }))
+        }
+    };
+    Some(json!({"jsonrpc": "2.0", "id": id, "result": result}))
+}
+
+fn options() -> WebSocketOptions {
+    WebSocketOptions {
+        request_timeout: Duration::from_millis(500),
+        auto_reconnect: true,
+        initial_backoff: Duration::from_millis(20),
+        max_backoff: Duration::from_millis(200),
+    }
+}
+
+fn tool_names(client: &WebSocketMcpClient) -> Vec<String> {
+    client.tools().into_iter().map(|t| t.name).collect()
+}
+
+/// Poll until `check` passes, failing after two seconds
+async fn eventually(mut check: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if check() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("condition not met in time");
+}
+
+#[tokio::test]
+async fn test_handshake_list_and_call_tools() {
+    let stub = stub_server().await;
+    let client = WebSocketMcpClient::connect(&stub.url, HttpAuth::None, options())
+        .await
+        .unwrap();
+
+    assert_eq!(client.server_info()["serverInfo"]["name"], "ws-stub");
+    assert_eq!(tool_names(&client), vec!["echo", "hang"]);
+
+    let result = client
+        .call_tool("echo", json!({"message": "hi"}))
+        .await
+        .unwrap();
+    assert_eq!(result["content"][0]["text"], r#"{"message":"hi"}"#);
+
+    let err = client.request("unknown", json!({})).await.unwrap_err();
+    assert!(matches!(
+        err,
+        RegistryError::RemoteError { code: -32601, .. }
+    ));
+
+    client.close().await;
+}
+
+#[tokio::test]
+async fn test_request_times_out_without_blocking_others() {
+    let stub = stub_server().await;
+    let client = WebSocketMcpClient::connect(&stub.url, HttpAuth::None, options())
+        .await
+        .unwrap();
+
+    let (hung, echoed) = tokio::join!(
+        client.call_tool("hang", json!({})),
+        client.call_tool("echo", json!({"n": 1}))
+    );
+    assert!(matches!(hung, Err(RegistryError::Timeout(_))), "{:?}", hung);
+    assert!(echoed.is_ok());
+    assert!(client.is_connected());
+}
+
+#[tokio::test]
+async fn test_tools_list_changed_refreshes_tools() {
+    let stub = stub_server().await;
+    let client = WebSocketMcpClient::connect(&stub.url, HttpAuth::None, options())
+        .await
+        .unwrap();
+    assert_eq!(tool_names(&client).len(), 2);
+
+    stub.tools.lock().unwrap().push("added");
+    stub.commands.send(Command::ToolsChanged).unwrap();
+
+    eventually(|| tool_names(&client).contains(&"added".to_string())).await;
+}
+
+#[tokio::test]
+async fn test_registry_resolves_tools_the_server_adds_later() {
+    let stub = stub_server().await;
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()));
+    let config = MCPServerConfig {
+        id: "ws".to_string(),
+        name: "ws".to_string(),
+        description: "WebSocket stub".to_string(),
+        server_type: MCPServerType::WebSocket,
+        deployment: DeploymentConfig::Process {
+            command: "unused".to_string(),
+            args: Vec::new(),
+        },
+        env: [("endpoint".to_string(), stub.url.clone())].into(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    };
+    registry.register_server("acme", config).await.unwrap();
+    registry.connect_server("acme", "ws", None).await.unwrap();
+    assert!(matches!(
+        registry.resolve_tool("acme", "added").await,
+        Err(RegistryError::ToolNotFound(_))
+    ));
+
+    stub.tools.lock().unwrap().push("added");
+    stub.commands.send(Command::ToolsChanged).unwrap();
+    for _ in 0..100 {
+        if registry.resolve_tool("acme", "added").await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(registry.resolve_tool("acme", "added").await.unwrap(), "ws");
+    registry.disconnect_server("acme", "ws").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnects_and_refetches_tools_after_drop() {
+    let stub = stub_server().await;
+    let client = WebSocketMcpClient::connect(&stub.url, HttpAuth::None, options())
+        .await
+        .unwrap();
+
+    // The list changes while the socket is down; the client learns it on reconnect
+    *stub.tools.lock().unwrap() = vec!["echo", "after_reconnect"];
+    stub.commands.send(Command::Drop).unwrap();
+
+    eventually(|| tool_names(&client).contains(&"after_reconnect".to_string())).await;
+    assert!(client.is_connected());
+    assert!(client.call_tool("echo", json!({})).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_late_close_of_a_replaced_socket_leaves_the_current_one() {
+    let stub = stub_server().await;
+    let client = WebSocketMcpClient::connect(&stub.url, HttpAuth::None, options())
+        .await
+        .unwrap();
+
+    // The first reconnect's handshake times out with its socket still open; the next
+    // one succeeds, then the abandoned socket closes
+    stub.hung_handshakes.store(1, Ordering::SeqCst);
+    stub.commands.send(Command::Drop).unwrap();
+    eventually(|| stub.connections.load(Ordering::SeqCst) == 3 && client.is_connected()).await;
+
+    tokio::time::sleep(HUNG_HANDSHAKE_CLOSE).await;
+    assert!(client.is_connected());
+    assert!(client.call_tool("echo", json!({})).await.is_ok());
+    assert_eq!(stub.connections.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_no_reconnect_when_disabled() {
+    let stub = stub_server().await;
+    let options = WebSocketOptions {
+        auto_reconnect: false,
+        ..options()
+    };
+    let client = WebSocketMcpClient::connect(&stub.url, HttpAuth::None, options)
+        .await
+        .unwrap();
+
+    stub.commands.send(Command::Drop).unwrap();
+    eventually(|| !client.is_connected()).await;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!client.is_connected());
+    let err = client.call_tool("echo", json!({})).await.unwrap_err();
+    assert!(
+        matches!(err, RegistryError::ServerNotConnected(_)),
+        "{:?}",
+        err
+    );
+}
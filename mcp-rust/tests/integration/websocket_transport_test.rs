@@ -0,0 +1,115 @@
+// Integration tests for the WebSocket transport: one session per connection
+// Serves the axum router on a local port over the in-memory backend; no AWS access required
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::MCPServer;
+use mcp_rust::tenant::TenantManager;
+use mcp_rust::transport::websocket::router;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn serve() -> (String, Arc<TenantManager>) {
+    std::env::set_var("DEV_MODE", "true");
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    let server = Arc::new(
+        MCPServer::new(tenant_manager.clone(), Some(registry))
+            .await
+            .unwrap(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router(server)).await });
+    (format!("ws://{}/mcp", addr), tenant_manager)
+}
+
+async fn rpc(socket: &mut Socket, request: Value) -> Value {
+    socket
+        .send(Message::text(request.to_string()))
+        .await
+        .unwrap();
+    loop {
+        match socket.next().await.unwrap().unwrap() {
+            Message::Text(text) => return serde_json::from_str(text.as_str()).unwrap(),
+            _ => continue,
+        }
+    }
+}
+
+fn initialize() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "tenant_id": "demo-tenant",
+        "user_id": "user-demo-123"
+    })
+}
+
+#[tokio::test]
+async fn test_connection_is_one_session() {
+    let (url, tenant_manager) = serve().await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+    let response = rpc(&mut socket, initialize()).await;
+    assert!(response["error"].is_null(), "{}", response);
+    assert_eq!(tenant_manager.get_all_sessions().await.len(), 1);
+
+    let response = rpc(
+        &mut socket,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": {"name": "kv_set", "arguments": {"key": "k", "value": "v"}}
+        }),
+    )
+    .await;
+    assert_eq!(response["id"], json!(2));
+    assert!(response["error"].is_null(), "{}", response);
+
+    // A second initialize on the same connection doesn't open another session
+    let response = rpc(&mut socket, initialize()).await;
+    assert!(response["error"].is_object(), "{}", response);
+    assert_eq!(tenant_manager.get_all_sessions().await.len(), 1);
+
+    // Closing the connection ends its session
+    socket.close(None).await.unwrap();
+    for _ in 0..50 {
+        if tenant_manager.get_all_sessions().await.is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("the session outlived its connection");
+}
+
+#[tokio::test]
+async fn test_requests_before_initialize_are_rejected() {
+    let (url, tenant_manager) = serve().await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+    let response = rpc(
+        &mut socket,
+        json!({"jsonrpc": "2.0", "id": 7, "method": "tools/list"}),
+    )
+    .await;
+    assert_eq!(response["id"], json!(7));
+    assert_eq!(response["error"]["code"], json!(-32600));
+    assert!(tenant_manager.get_all_sessions().await.is_empty());
+
+    let response = rpc(&mut socket, json!("not a request")).await;
+    assert!(response["error"].is_object(), "{}", response);
+}
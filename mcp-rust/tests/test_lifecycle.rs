@@ -24,6 +24,8 @@ fn test_server_exits_on_stdin_close() {
         .env("DEFAULT_TENANT_ID", "test-tenant")
         .env("DEFAULT_USER_ID", "test-user")
         .env("AWS_REGION", "us-west-2")
+        // Without credentials the server would refuse to start
+        .env("MCP_ALLOW_OFFLINE", "true")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -98,6 +100,8 @@ fn test_server_exits_on_immediate_eof() {
 
     let mut child = Command::new(binary_path)
         .current_dir(env!("CARGO_MANIFEST_DIR"))
+        // Skip the startup credentials check, which could outlast the timeout below
+        .env("MCP_BACKEND", "memory")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
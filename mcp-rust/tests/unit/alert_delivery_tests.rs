@@ -0,0 +1,193 @@
+// Unit tests for SNS alert delivery: topic ARN validation, message attributes and failures
+// Delivered through the AwsApi trait to the in-memory backend's topics; no AWS access required
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::alerts::{alert_message_attributes, validate_sns_topic_arn};
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::{AwsApi, AwsError};
+use mcp_rust::handlers::{EventsCreateAlertHandler, Handler, HandlerError};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+const ALERTS: &str = "arn:aws:sns:us-west-2:123456789012:alerts";
+
+fn session() -> TenantSession {
+    TestTenant::user("alice")
+        .permissions(vec![Permission::WriteKV, Permission::SendEvents])
+        .session()
+}
+
+fn event() -> Value {
+    json!({
+        "detailType": "deploy.failed",
+        "priority": "critical",
+        "detail": {"service": "api", "tenant_id": "alice-tenant", "priority": "critical"}
+    })
+}
+
+/// Store an sns subscription to `topic_arn` through the backend
+async fn subscribe(aws: &MemoryAwsService, topic_arn: &str) -> Value {
+    aws.create_alert_subscription(
+        &session(),
+        "deploys",
+        "rule-1",
+        "sns",
+        Some(topic_arn.to_string()),
+        None,
+        true,
+    )
+    .await
+    .unwrap()
+}
+
+#[test]
+fn test_topic_arn_validation() {
+    assert!(validate_sns_topic_arn(ALERTS, "us-west-2").is_ok());
+    assert!(validate_sns_topic_arn(
+        "arn:aws:sns:us-west-2:123456789012:deploys_prod.fifo",
+        "us-west-2"
+    )
+    .is_ok());
+
+    for arn in [
+        "alerts",
+        // Another region's topic can't be reached with the regional client
+        "arn:aws:sns:eu-west-1:123456789012:alerts",
+        "arn:aws:sqs:us-west-2:123456789012:alerts",
+        "arn:aws:sns:us-west-2:1234:alerts",
+        "arn:aws:sns:us-west-2:123456789012:",
+        "arn:aws:sns:us-west-2:123456789012:alerts:sub-1",
+        "arn:aws:sns:us-west-2:123456789012:alerts!",
+    ] {
+        assert!(
+            matches!(
+                validate_sns_topic_arn(arn, "us-west-2"),
+                Err(AwsError::InvalidTopic(_))
+            ),
+            "{}",
+            arn
+        );
+    }
+}
+
+#[test]
+fn test_message_attributes() {
+    assert_eq!(
+        alert_message_attributes(&event()),
+        HashMap::from([
+            ("detailType".to_string(), "deploy.failed".to_string()),
+            ("priority".to_string(), "critical".to_string()),
+            ("tenant".to_string(), "alice-tenant".to_string()),
+        ])
+    );
+
+    // Events without a priority are stored as medium; the tenant is left out if unknown
+    let attributes = alert_message_attributes(&json!({"detailType": "ping", "detail": {}}));
+    assert_eq!(attributes["priority"], "medium");
+    assert!(!attributes.contains_key("tenant"));
+}
+
+#[tokio::test]
+async fn test_deliver_alert_publishes_event_with_attributes() {
+    let aws = MemoryAwsService::new();
+    aws.create_topic(ALERTS);
+    let subscription = subscribe(&aws, ALERTS).await;
+
+    let message_id = aws
+        .deliver_alert(&session(), &subscription, &event())
+        .await
+        .unwrap();
+    assert!(message_id.is_some());
+
+    let published = aws.published(ALERTS);
+    assert_eq!(published.len(), 1);
+    let (message, attributes) = &published[0];
+    assert_eq!(serde_json::from_str::<Value>(message).unwrap(), event());
+    assert_eq!(attributes, &alert_message_attributes(&event()));
+
+    // Disabled and email subscriptions aren't delivered by the server
+    let mut disabled = subscription.clone();
+    disabled["enabled"] = json!(false);
+    let email = json!({"notificationMethod": "email", "enabled": true});
+    for subscription in [disabled, email] {
+        assert_eq!(
+            aws.deliver_alert(&session(), &subscription, &event())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+    assert_eq!(aws.published(ALERTS).len(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_delivery_is_recorded() {
+    let aws = MemoryAwsService::new();
+    // The topic was deleted after the subscription was created
+    let subscription = subscribe(&aws, ALERTS).await;
+    let subscription_id = subscription["subscriptionId"].as_str().unwrap();
+
+    let result = aws.deliver_alert(&session(), &subscription, &event()).await;
+    assert!(matches!(result, Err(AwsError::NotFound(_))));
+
+    let stored = aws.subscription(subscription_id).unwrap();
+    assert!(stored["lastError"].as_str().unwrap().contains(ALERTS));
+    assert!(stored["lastErrorAt"].is_string());
+
+    let failures = aws
+        .query_events(
+            &session(),
+            None,
+            None,
+            None,
+            Some("mcp-rust.system".to_string()),
+            Some("alert.delivery_failed".to_string()),
+            None,
+            None,
+            None,
+            None,
+            10,
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+    let failures = failures["events"].as_array().unwrap();
+    assert_eq!(failures.len(), 1);
+    let detail = &failures[0]["detail"];
+    assert_eq!(detail["subscriptionId"], subscription_id);
+    assert_eq!(detail["snsTopicArn"], ALERTS);
+    assert_eq!(detail["eventDetailType"], "deploy.failed");
+}
+
+#[tokio::test]
+async fn test_create_alert_validates_topic() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let handler = EventsCreateAlertHandler::new(aws);
+    let alert = |topic: Option<&str>| {
+        json!({
+            "name": "deploys",
+            "ruleId": "rule-1",
+            "notificationMethod": "sns",
+            "snsTopicArn": topic
+        })
+    };
+
+    for topic in [None, Some("arn:aws:sns:eu-west-1:123456789012:alerts")] {
+        let result = handler.handle(&session(), alert(topic)).await;
+        assert!(
+            matches!(result, Err(HandlerError::InvalidArguments(_))),
+            "{:?}",
+            topic
+        );
+    }
+    // Whether the topic exists is only checked with aws.verify_alert_topics
+    let created = handler
+        .handle(&session(), alert(Some(ALERTS)))
+        .await
+        .unwrap();
+    assert_eq!(created["snsTopicArn"], ALERTS);
+}
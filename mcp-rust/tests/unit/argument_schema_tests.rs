@@ -0,0 +1,156 @@
+// Unit tests for validating tool arguments against each tool's inputSchema
+// HandlerRegistry over the in-memory backend, plus a counting handler to see what reaches a tool
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::{MCPError, MCPErrorResponse};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn alice() -> TenantSession {
+    TestTenant::user("alice")
+        .permissions(vec![Permission::ReadKV, Permission::SendEvents])
+        .session()
+}
+
+/// Counts the calls that reach it; takes a required integer `n`
+struct Counting {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Handler for Counting {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(arguments)
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ReadKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Count calls",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "n": { "type": "integer", "minimum": 0 }
+                },
+                "required": ["n"]
+            }
+        })
+    }
+}
+
+async fn setup() -> (HandlerRegistry, Arc<AtomicUsize>) {
+    let mut registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    let calls = Arc::new(AtomicUsize::new(0));
+    registry.register(
+        "count",
+        Arc::new(Counting {
+            calls: calls.clone(),
+        }),
+    );
+    (registry, calls)
+}
+
+fn violation(result: Result<Value, HandlerError>) -> (String, String, Option<String>) {
+    match result {
+        Err(HandlerError::SchemaViolation {
+            tool,
+            pointer,
+            expected,
+            ..
+        }) => (tool, pointer, expected),
+        other => panic!("expected SchemaViolation, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_wrong_type_names_the_argument_and_expected_type() {
+    let (registry, _) = setup().await;
+
+    let result = registry
+        .handle_tool_call(&alice(), "events_query", json!({"limit": "ten"}))
+        .await;
+    assert_eq!(
+        violation(result),
+        (
+            "events_query".to_string(),
+            "/limit".to_string(),
+            Some("number".to_string())
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_missing_required_argument_points_at_it() {
+    let (registry, _) = setup().await;
+
+    let result = registry
+        .handle_tool_call(&alice(), "kv_get", json!({}))
+        .await;
+    assert_eq!(
+        violation(result),
+        (
+            "kv_get".to_string(),
+            "/key".to_string(),
+            Some("string".to_string())
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_arguments_never_reach_the_handler() {
+    let (registry, calls) = setup().await;
+
+    for arguments in [json!({}), json!({"n": "1"}), json!({"n": -1})] {
+        let result = registry
+            .handle_tool_call(&alice(), "count", arguments)
+            .await;
+        assert!(matches!(result, Err(HandlerError::SchemaViolation { .. })));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let result = registry
+        .handle_tool_call(&alice(), "count", json!({"n": 3}))
+        .await
+        .unwrap();
+    assert_eq!(result["n"], 3);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_violation_is_reported_as_invalid_params() {
+    let (registry, _) = setup().await;
+
+    let error = registry
+        .handle_tool_call(&alice(), "count", json!({"n": true}))
+        .await
+        .unwrap_err();
+    let response = MCPErrorResponse::from(MCPError::from(error));
+    assert_eq!(response.code, -32602);
+    assert!(
+        response.message.contains("count at /n"),
+        "{}",
+        response.message
+    );
+    assert_eq!(
+        response.data,
+        Some(json!({"tool": "count", "pointer": "/n", "expected": "integer"}))
+    );
+}
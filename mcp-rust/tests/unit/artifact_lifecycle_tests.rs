@@ -0,0 +1,206 @@
+// Unit tests for artifact TTL classes: the lifecycle rule documents, merging them into a
+// bucket's rules, artifacts_put's ttl_class, artifacts_head and the admin-only configure tool
+
+use aws_sdk_s3::types::{ExpirationStatus, LifecycleExpiration, LifecycleRule};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::artifacts::{
+    merge_lifecycle_rules, tenant_lifecycle_rules, RetentionDays, TtlClass, TTL_CLASS_TAG,
+};
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{ContextType, Permission, TenantSession, UserRole};
+
+fn session(context_type: ContextType, role: UserRole) -> TenantSession {
+    TestTenant::user("alice")
+        .context_type(context_type)
+        .role(role)
+        .permissions(vec![Permission::GetArtifacts, Permission::PutArtifacts])
+        .session()
+}
+
+fn org_admin() -> TenantSession {
+    let acme = ContextType::Organization {
+        org_id: "acme".to_string(),
+        org_name: "Acme".to_string(),
+    };
+    session(acme, UserRole::Admin)
+}
+
+async fn setup() -> (Arc<MemoryAwsService>, HandlerRegistry) {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await;
+    (aws, registry)
+}
+
+fn s3_rule(id: &str) -> LifecycleRule {
+    LifecycleRule::builder()
+        .id(id)
+        .status(ExpirationStatus::Enabled)
+        .expiration(LifecycleExpiration::builder().days(1).build())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_rules_filter_by_tenant_prefix_and_ttl_tag() {
+    let rules = tenant_lifecycle_rules("org:acme:user:", &RetentionDays::default());
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0].id, "artifact-ttl:org:acme:user::short");
+    assert_eq!(
+        rules
+            .iter()
+            .map(|rule| (rule.ttl_class, rule.expiration_days))
+            .collect::<Vec<_>>(),
+        vec![
+            (TtlClass::Short, 7),
+            (TtlClass::Medium, 30),
+            (TtlClass::Long, 365)
+        ]
+    );
+
+    let s3 = rules[1].to_s3().unwrap();
+    assert_eq!(s3.id(), Some("artifact-ttl:org:acme:user::medium"));
+    assert_eq!(s3.status(), &ExpirationStatus::Enabled);
+    assert_eq!(s3.expiration().and_then(|e| e.days()), Some(30));
+    let filter = s3.filter().and_then(|f| f.and()).unwrap();
+    assert_eq!(filter.prefix(), Some("org:acme:user:"));
+    let tags = filter.tags();
+    assert_eq!(tags.len(), 1);
+    assert_eq!((tags[0].key(), tags[0].value()), (TTL_CLASS_TAG, "medium"));
+    assert_eq!(TtlClass::Medium.tagging(), "ttl-class=medium");
+}
+
+#[test]
+fn test_merge_replaces_the_tenants_rules_and_keeps_the_rest() {
+    let existing = vec![
+        s3_rule("archive-logs"),
+        s3_rule("artifact-ttl:user:alice/:short"),
+    ];
+    let rules = tenant_lifecycle_rules("user:alice/", &RetentionDays::default())
+        .iter()
+        .map(|rule| rule.to_s3().unwrap())
+        .collect();
+
+    let merged = merge_lifecycle_rules(&existing, rules);
+    let ids: Vec<_> = merged.iter().filter_map(|rule| rule.id()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "archive-logs",
+            "artifact-ttl:user:alice/:short",
+            "artifact-ttl:user:alice/:medium",
+            "artifact-ttl:user:alice/:long"
+        ]
+    );
+    assert_eq!(merged[1].expiration().and_then(|e| e.days()), Some(7));
+}
+
+#[test]
+fn test_retention_must_not_shrink() {
+    assert!(RetentionDays::default().validate().is_ok());
+    let zero = RetentionDays {
+        short: 0,
+        ..RetentionDays::default()
+    };
+    assert!(zero.validate().is_err());
+    let shrinking = RetentionDays {
+        short: 7,
+        medium: 3,
+        long: 365,
+    };
+    assert!(shrinking.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_put_ttl_class_is_reported_by_head() {
+    let (_aws, registry) = setup().await;
+    let alice = session(ContextType::Personal, UserRole::User);
+
+    registry
+        .handle_tool_call(
+            &alice,
+            "artifacts_put",
+            json!({"key": "run.log", "content": "aGVsbG8=", "ttl_class": "short"}),
+        )
+        .await
+        .unwrap();
+    let head = registry
+        .handle_tool_call(&alice, "artifacts_head", json!({"key": "run.log"}))
+        .await
+        .unwrap();
+    assert_eq!(head["size"], 5);
+    assert_eq!(head["content_type"], "text/plain");
+    assert_eq!(head["ttl_class"], "short");
+    assert!(head["last_modified"].is_string());
+
+    let invalid = registry
+        .handle_tool_call(
+            &alice,
+            "artifacts_put",
+            json!({"key": "run.log", "content": "aGVsbG8=", "ttl_class": "forever"}),
+        )
+        .await;
+    assert!(matches!(
+        invalid,
+        Err(HandlerError::SchemaViolation { ref pointer, .. }) if pointer == "/ttl_class"
+    ));
+
+    let missing = registry
+        .handle_tool_call(&alice, "artifacts_head", json!({"key": "nope"}))
+        .await;
+    assert!(matches!(missing, Err(HandlerError::Aws(_))));
+}
+
+#[tokio::test]
+async fn test_configure_lifecycle_is_admin_only() {
+    let (aws, registry) = setup().await;
+    let user = session(ContextType::Personal, UserRole::User);
+
+    let denied = registry
+        .handle_tool_call(&user, "artifacts_configure_lifecycle", json!({}))
+        .await;
+    assert!(matches!(
+        denied,
+        Err(HandlerError::PermissionDenied(Permission::Admin))
+    ));
+    assert!(aws.lifecycle_rules().is_empty());
+
+    let invalid = registry
+        .handle_tool_call(
+            &org_admin(),
+            "artifacts_configure_lifecycle",
+            json!({"short_days": 0}),
+        )
+        .await;
+    assert!(matches!(
+        invalid,
+        Err(HandlerError::SchemaViolation { ref pointer, .. }) if pointer == "/short_days"
+    ));
+
+    let configured: Value = registry
+        .handle_tool_call(
+            &org_admin(),
+            "artifacts_configure_lifecycle",
+            json!({"short_days": 1}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(configured["prefix"], "org:acme:user:");
+    assert_eq!(configured["rules"][0]["expiration_days"], 1);
+
+    // Configuring again updates the tenant's rules in place
+    registry
+        .handle_tool_call(
+            &org_admin(),
+            "artifacts_configure_lifecycle",
+            json!({"short_days": 2}),
+        )
+        .await
+        .unwrap();
+    let rules = aws.lifecycle_rules();
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0].expiration_days, 2);
+}
@@ -0,0 +1,59 @@
+// Unit tests for the internal key allow-list of the direct KV calls
+// Keys are rejected before any request is made; no AWS access required
+
+use mcp_rust::aws::{validate_direct_key, AwsError, AwsService};
+
+#[test]
+fn test_internal_prefixes_are_allowed() {
+    for key in [
+        "mcp-registry-personal-alice:github",
+        "mcp-credential-personal-alice:github:api_key",
+        "integration-github",
+        "user-alice:integration:github:default",
+        "analytics-user-alice:24h:now",
+    ] {
+        assert!(validate_direct_key(key).is_ok(), "{} was rejected", key);
+    }
+}
+
+#[test]
+fn test_arbitrary_keys_are_rejected() {
+    for key in [
+        "",
+        "mcp-",
+        "personal:alice:notes",
+        "org:acme:secrets",
+        "registry-github",
+        " user-alice",
+    ] {
+        assert!(
+            matches!(validate_direct_key(key), Err(AwsError::InvalidKey(_))),
+            "{:?} was allowed",
+            key
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_direct_calls_reject_tenant_keys() {
+    let aws_service = AwsService::new("us-west-2").await.unwrap();
+    let key = "personal:alice:notes";
+
+    assert!(matches!(
+        aws_service.kv_get_direct(key).await,
+        Err(AwsError::InvalidKey(_))
+    ));
+    assert!(matches!(
+        aws_service.kv_set_direct(key, "overwritten", None).await,
+        Err(AwsError::InvalidKey(_))
+    ));
+    assert!(matches!(
+        aws_service.kv_delete(key).await,
+        Err(AwsError::InvalidKey(_))
+    ));
+    // An empty prefix would list every tenant's keys
+    assert!(matches!(
+        aws_service.kv_list("").await,
+        Err(AwsError::InvalidKey(_))
+    ));
+}
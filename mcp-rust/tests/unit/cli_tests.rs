@@ -0,0 +1,165 @@
+// Unit tests for command-line parsing and how flags map onto the server config
+// Parses argument lists with clap's try_parse_from; no process environment or files read
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use mcp_rust::cli::{BackendArg, Cli, TransportArg, VERSION};
+use mcp_rust::config::{Backend, Config, ConfigFile, Transport};
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("mcp-multi-tenant").chain(args.iter().copied())).unwrap()
+}
+
+fn resolve(cli: &Cli, env: &[(&str, &str)], file: Option<&str>) -> Config {
+    let env: HashMap<String, String> = env
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    let file = file.map(|contents| ConfigFile::parse("mcp.toml", contents).unwrap());
+    Config::resolve_with(
+        &cli.overrides(),
+        |name| env.get(name).cloned(),
+        file.as_ref(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_command_definition_is_consistent() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn test_no_arguments_changes_nothing() {
+    let cli = parse(&[]);
+    assert!(cli.overrides().is_empty());
+    assert!(!cli.validate_config);
+    assert!(!cli.self_test && !cli.self_test_live);
+    assert_eq!(cli.config, None);
+}
+
+#[test]
+fn test_flags_parse() {
+    let cli = parse(&[
+        "--transport",
+        "websocket",
+        "--listen",
+        "0.0.0.0:9000",
+        "--config",
+        "/etc/mcp.toml",
+        "--dev",
+        "--log-level",
+        "debug",
+        "--backend",
+        "memory",
+        "--region",
+        "eu-west-1",
+        "--validate-config",
+        "--self-test",
+        "--self-test-live",
+    ]);
+    assert_eq!(cli.transport, Some(TransportArg::Websocket));
+    assert_eq!(cli.listen, Some("0.0.0.0:9000".parse().unwrap()));
+    assert_eq!(cli.config, Some(PathBuf::from("/etc/mcp.toml")));
+    assert!(cli.dev);
+    assert_eq!(cli.backend, Some(BackendArg::Memory));
+    assert_eq!(cli.region.as_deref(), Some("eu-west-1"));
+    assert!(cli.validate_config);
+    assert!(cli.self_test && cli.self_test_live);
+}
+
+#[test]
+fn test_flags_map_onto_config() {
+    let cli = parse(&[
+        "--transport",
+        "http",
+        "--listen",
+        "0.0.0.0:9000",
+        "--dev",
+        "--log-level",
+        "warn",
+        "--backend",
+        "memory",
+    ]);
+    let config = resolve(&cli, &[], None);
+    assert_eq!(config.server.transport, Transport::Http);
+    assert_eq!(config.server.http_addr.to_string(), "0.0.0.0:9000");
+    assert!(config.dev_mode);
+    assert_eq!(config.log_level, tracing::Level::WARN);
+    assert_eq!(config.aws.backend, Backend::Memory);
+    assert!(config
+        .describe()
+        .contains("server.transport = \"http\"  # --transport"));
+}
+
+#[test]
+fn test_flags_override_env_and_file() {
+    let cli = parse(&[
+        "--transport",
+        "websocket",
+        "--backend",
+        "aws",
+        "--region",
+        "eu-west-1",
+    ]);
+    let config = resolve(
+        &cli,
+        &[
+            ("MCP_TRANSPORT", "http"),
+            ("MCP_HTTP_ADDR", "127.0.0.1:7000"),
+            ("AWS_REGION", "us-east-1"),
+        ],
+        Some("[aws]\nbackend = \"memory\"\n"),
+    );
+    assert_eq!(config.server.transport, Transport::WebSocket);
+    assert_eq!(config.aws.backend, Backend::Aws);
+    assert_eq!(config.aws.region, "eu-west-1");
+    // Without --listen the environment still applies
+    assert_eq!(config.server.http_addr.to_string(), "127.0.0.1:7000");
+}
+
+#[test]
+fn test_env_applies_when_flags_are_absent() {
+    let cli = parse(&["--log-level", "error"]);
+    let config = resolve(
+        &cli,
+        &[("MCP_TRANSPORT", "websocket"), ("DEV_MODE", "true")],
+        None,
+    );
+    assert_eq!(config.server.transport, Transport::WebSocket);
+    assert!(config.dev_mode);
+    assert_eq!(config.log_level, tracing::Level::ERROR);
+}
+
+#[test]
+fn test_invalid_flag_values_are_rejected() {
+    let parse_err = |args: &[&str]| {
+        Cli::try_parse_from(std::iter::once("mcp-multi-tenant").chain(args.iter().copied()))
+            .unwrap_err()
+            .kind()
+    };
+    assert_eq!(parse_err(&["--transport", "grpc"]), ErrorKind::InvalidValue);
+    assert_eq!(
+        parse_err(&["--listen", "localhost"]),
+        ErrorKind::ValueValidation
+    );
+    assert_eq!(parse_err(&["--backend", "s3"]), ErrorKind::InvalidValue);
+    assert_eq!(parse_err(&["--port", "8080"]), ErrorKind::UnknownArgument);
+}
+
+#[test]
+fn test_version_includes_git_hash() {
+    let error = Cli::try_parse_from(["mcp-multi-tenant", "--version"]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::DisplayVersion);
+    let output = error.to_string();
+    assert!(output.contains(env!("CARGO_PKG_VERSION")), "{}", output);
+    assert!(output.contains(VERSION), "{}", output);
+    assert!(
+        VERSION.ends_with(')') && VERSION.contains(" ("),
+        "{}",
+        VERSION
+    );
+}
@@ -0,0 +1,203 @@
+// Unit tests for binary and text content blocks in tool results
+// HandlerOutput conversions and HandlerRegistry over the in-memory backend; no AWS access required
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::AwsApi;
+use mcp_rust::handlers::content::HandlerOutput;
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+/// The 8-byte PNG signature followed by a few bytes that aren't valid UTF-8
+const PNG: &[u8] = &[
+    0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0xff, 0x00, 0xfe,
+];
+
+fn session() -> TenantSession {
+    TestTenant::user("alice")
+        .permissions(vec![
+            Permission::ReadKV,
+            Permission::WriteKV,
+            Permission::GetArtifacts,
+            Permission::PutArtifacts,
+        ])
+        .session()
+}
+
+async fn registry(aws: &Arc<MemoryAwsService>) -> HandlerRegistry {
+    HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await
+}
+
+/// A tool returning whatever output it was built with
+struct FixedOutput(HandlerOutput);
+
+#[async_trait]
+impl Handler for FixedOutput {
+    async fn handle(
+        &self,
+        session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        self.handle_output(session, arguments)
+            .await
+            .map(HandlerOutput::into_value)
+    }
+
+    async fn handle_output(
+        &self,
+        _session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<HandlerOutput, HandlerError> {
+        Ok(self.0.clone())
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({"description": "Fixed output", "inputSchema": {"type": "object"}})
+    }
+}
+
+#[test]
+fn test_json_output_is_unchanged() {
+    let value = json!({"value": "hi", "content": "aGk="});
+    assert_eq!(HandlerOutput::from(value.clone()).into_value(), value);
+}
+
+#[test]
+fn test_content_block_shapes() {
+    assert_eq!(
+        HandlerOutput::Text("done".to_string()).into_value(),
+        json!({"content": [{"type": "text", "text": "done"}]})
+    );
+    assert_eq!(
+        HandlerOutput::binary(PNG.to_vec(), "image/png").into_value(),
+        json!({"content": [{
+            "type": "image",
+            "data": general_purpose::STANDARD.encode(PNG),
+            "mimeType": "image/png"
+        }]})
+    );
+    assert_eq!(
+        HandlerOutput::binary(vec![1, 2], "audio/wav").into_value()["content"][0]["type"],
+        "audio"
+    );
+
+    // Other binaries are embedded resources, named by their URI or else their hash
+    let pdf = HandlerOutput::Binary {
+        data: b"%PDF-1.7".to_vec(),
+        mime: "application/pdf".to_string(),
+        uri: Some("artifact://reports/q1.pdf".to_string()),
+    };
+    assert_eq!(
+        pdf.into_value(),
+        json!({"content": [{
+            "type": "resource",
+            "resource": {
+                "uri": "artifact://reports/q1.pdf",
+                "mimeType": "application/pdf",
+                "blob": general_purpose::STANDARD.encode("%PDF-1.7")
+            }
+        }]})
+    );
+    let unnamed = HandlerOutput::binary(vec![0, 1], "application/octet-stream").into_value();
+    let uri = unnamed["content"][0]["resource"]["uri"].as_str().unwrap();
+    assert!(uri.starts_with("urn:sha256:"), "{}", uri);
+}
+
+#[tokio::test]
+async fn test_image_artifact_is_an_image_block() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = session();
+    aws.artifacts_put(&alice, "screens/home.png", PNG, "image/png", None)
+        .await
+        .unwrap();
+
+    let result = registry
+        .handle_tool_call(&alice, "artifacts_get", json!({"key": "screens/home.png"}))
+        .await
+        .unwrap();
+    let blocks = result["content"].as_array().unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0]["type"], "image");
+    assert_eq!(blocks[0]["mimeType"], "image/png");
+    let data = general_purpose::STANDARD
+        .decode(blocks[0]["data"].as_str().unwrap())
+        .unwrap();
+    assert_eq!(data, PNG);
+}
+
+#[tokio::test]
+async fn test_other_artifacts_and_json_tools_keep_their_shape() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = session();
+    aws.artifacts_put(&alice, "notes/today.txt", b"hello", "text/plain", None)
+        .await
+        .unwrap();
+
+    let result = registry
+        .handle_tool_call(&alice, "artifacts_get", json!({"key": "notes/today.txt"}))
+        .await
+        .unwrap();
+    assert_eq!(result, json!({"content": "aGVsbG8=", "encoding": "base64"}));
+    let missing = registry
+        .handle_tool_call(&alice, "artifacts_get", json!({"key": "notes/none.png"}))
+        .await
+        .unwrap();
+    assert_eq!(missing, json!({"content": null}));
+
+    registry
+        .handle_tool_call(&alice, "kv_set", json!({"key": "k", "value": "v"}))
+        .await
+        .unwrap();
+    let value = registry
+        .handle_tool_call(&alice, "kv_get", json!({"key": "k"}))
+        .await
+        .unwrap();
+    assert_eq!(value, json!({"value": "v"}));
+}
+
+#[tokio::test]
+async fn test_registry_converts_handler_outputs() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let mut registry = registry(&aws).await;
+    registry.register(
+        "screenshot",
+        Arc::new(FixedOutput(HandlerOutput::binary(
+            PNG.to_vec(),
+            "image/png",
+        ))),
+    );
+    registry.register(
+        "summary",
+        Arc::new(FixedOutput(HandlerOutput::Text("all good".to_string()))),
+    );
+    let alice = session();
+
+    let result = registry
+        .handle_tool_call(&alice, "screenshot", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(result["content"][0]["type"], "image");
+    assert_eq!(
+        result["content"][0]["data"],
+        general_purpose::STANDARD.encode(PNG)
+    );
+    let result = registry
+        .handle_tool_call(&alice, "summary", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        json!({"content": [{"type": "text", "text": "all good"}]})
+    );
+}
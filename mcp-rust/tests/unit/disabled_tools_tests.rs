@@ -0,0 +1,97 @@
+// Unit tests for tools switched off by the operator
+// Listing omission, the distinct disabled error and glob matching
+
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::{MCPError, MCPErrorResponse};
+use mcp_rust::tenant::TenantSession;
+
+fn admin() -> TenantSession {
+    TestTenant::admin("ops-user")
+        .tenant("ops-tenant")
+        .organization_id("ops-org")
+        .session()
+}
+
+async fn registry_disabling(patterns: &[&str]) -> HandlerRegistry {
+    HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions {
+            disabled_tools: patterns.iter().map(|p| p.to_string()).collect(),
+            ..RegistryOptions::default()
+        },
+    )
+    .await
+}
+
+async fn tool_names(registry: &HandlerRegistry) -> Vec<String> {
+    registry
+        .list_tools(&admin())
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|tool| tool["name"].as_str().map(String::from))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_disabled_tools_are_not_listed() {
+    let registry = registry_disabling(&["kv_set", "mcp_proxy"]).await;
+
+    let names = tool_names(&registry).await;
+    assert!(names.contains(&"kv_get".to_string()));
+    assert!(!names.contains(&"kv_set".to_string()));
+    assert!(!names.contains(&"mcp_proxy".to_string()));
+    assert_eq!(registry.disabled_tools(), vec!["kv_set", "mcp_proxy"]);
+}
+
+#[tokio::test]
+async fn test_disabled_tool_call_fails_distinctly_from_not_found() {
+    let registry = registry_disabling(&["kv_set"]).await;
+
+    let result = registry
+        .handle_tool_call(&admin(), "kv_set", json!({"key": "k", "value": "v"}))
+        .await;
+    let error = match result {
+        Err(error @ HandlerError::ToolDisabled(_)) => error,
+        other => panic!("expected ToolDisabled, got {:?}", other),
+    };
+    assert!(error
+        .to_string()
+        .contains("disabled by the server administrator"));
+
+    let response = MCPErrorResponse::from(MCPError::from(error));
+    assert_eq!(response.code, -32010);
+    assert_eq!(response.data.unwrap()["tool"], "kv_set");
+
+    let missing = registry
+        .handle_tool_call(&admin(), "no_such_tool", json!({}))
+        .await;
+    assert!(matches!(missing, Err(HandlerError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_glob_patterns_disable_matching_tools() {
+    let registry = registry_disabling(&["integration_*", "*_export"]).await;
+
+    let names = tool_names(&registry).await;
+    assert!(!names.iter().any(|name| name.starts_with("integration_")));
+    assert!(!names.iter().any(|name| name.ends_with("_export")));
+    assert!(names.contains(&"kv_get".to_string()));
+
+    let result = registry
+        .handle_tool_call(&admin(), "integration_list", json!({}))
+        .await;
+    assert!(matches!(result, Err(HandlerError::ToolDisabled(_))));
+
+    // Downstream tools go through the proxy, so disabling it disables them too
+    let registry = registry_disabling(&["mcp_proxy"]).await;
+    let result = registry
+        .handle_tool_call(&admin(), "github.create_issue", json!({}))
+        .await;
+    assert!(matches!(result, Err(HandlerError::ToolDisabled(_))));
+}
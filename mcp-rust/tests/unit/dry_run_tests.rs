@@ -0,0 +1,190 @@
+// Unit tests for dry runs of destructive tools and the confirmation policy
+// HandlerRegistry over the in-memory backend, checking nothing changes until it should
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{TenantSession, ToolPolicy};
+
+fn session(tool_policy: ToolPolicy) -> TenantSession {
+    TestTenant::admin("dry-user")
+        .tenant("dry-tenant")
+        .organization_id("dry-org")
+        .tool_policy(tool_policy)
+        .session()
+}
+
+async fn registry_with_artifacts(session: &TenantSession) -> HandlerRegistry {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = HandlerRegistry::with_backend(aws, RegistryOptions::default()).await;
+    for (key, content) in [
+        ("reports/a.txt", "aGVsbG8="),
+        ("reports/b.txt", "aGk="),
+        ("notes/c.txt", "aGk="),
+    ] {
+        registry
+            .handle_tool_call(
+                session,
+                "artifacts_put",
+                json!({"key": key, "content": content, "content_type": "text/plain"}),
+            )
+            .await
+            .unwrap();
+    }
+    registry
+}
+
+async fn artifact_keys(registry: &HandlerRegistry, session: &TenantSession) -> Vec<Value> {
+    let listed = registry
+        .handle_tool_call(session, "artifacts_list", json!({}))
+        .await
+        .unwrap();
+    let mut keys = listed["keys"].as_array().unwrap().clone();
+    keys.sort_by_key(|key| key.as_str().unwrap().to_string());
+    keys
+}
+
+#[tokio::test]
+async fn test_delete_prefix_dry_run_previews_without_deleting() {
+    let session = session(ToolPolicy::default());
+    let registry = registry_with_artifacts(&session).await;
+
+    let preview = registry
+        .handle_tool_call(
+            &session,
+            "artifacts_delete_prefix",
+            json!({"prefix": "reports/", "dry_run": true}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(preview["dryRun"], true);
+    assert_eq!(preview["keys"], json!(["reports/a.txt", "reports/b.txt"]));
+    assert_eq!(preview["count"], 2);
+    assert_eq!(preview["bytes"], 7);
+    assert_eq!(artifact_keys(&registry, &session).await.len(), 3);
+
+    let deleted = registry
+        .handle_tool_call(
+            &session,
+            "artifacts_delete_prefix",
+            json!({"prefix": "reports/"}),
+        )
+        .await
+        .unwrap();
+    assert!(deleted.get("dryRun").is_none());
+    assert_eq!(deleted["keys"], preview["keys"]);
+    assert_eq!(
+        artifact_keys(&registry, &session).await,
+        vec![json!("notes/c.txt")]
+    );
+}
+
+#[tokio::test]
+async fn test_dry_run_rejected_by_tools_without_one() {
+    let session = session(ToolPolicy::default());
+    let registry = registry_with_artifacts(&session).await;
+
+    let result = registry
+        .handle_tool_call(
+            &session,
+            "kv_set",
+            json!({"key": "k", "value": "v", "dry_run": true}),
+        )
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+
+    let tools = registry.list_tools(&session).await.unwrap();
+    let schema_of = |name: &str| {
+        tools
+            .iter()
+            .find(|tool| tool["name"] == name)
+            .unwrap()
+            .pointer("/inputSchema/properties")
+            .unwrap()
+            .clone()
+    };
+    assert!(schema_of("artifacts_delete_prefix")
+        .get("dry_run")
+        .is_some());
+    assert!(schema_of("secret_delete").get("confirm").is_some());
+    assert!(schema_of("kv_set").get("dry_run").is_none());
+}
+
+#[tokio::test]
+async fn test_confirmation_policy_forces_dry_run_until_confirmed() {
+    let session = session(ToolPolicy {
+        require_confirmation: true,
+        ..ToolPolicy::default()
+    });
+    let registry = registry_with_artifacts(&session).await;
+
+    let unconfirmed = registry
+        .handle_tool_call(
+            &session,
+            "artifacts_delete_prefix",
+            json!({"prefix": "notes/"}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(unconfirmed["dryRun"], true);
+    assert_eq!(unconfirmed["confirmationRequired"], true);
+    assert_eq!(artifact_keys(&registry, &session).await.len(), 3);
+
+    let confirmed = registry
+        .handle_tool_call(
+            &session,
+            "artifacts_delete_prefix",
+            json!({"prefix": "notes/", "confirm": true}),
+        )
+        .await
+        .unwrap();
+    assert!(confirmed.get("dryRun").is_none());
+    assert_eq!(artifact_keys(&registry, &session).await.len(), 2);
+}
+
+#[tokio::test]
+async fn test_secret_delete_dry_run_keeps_the_secret() {
+    let session = session(ToolPolicy::default());
+    let registry = registry_with_artifacts(&session).await;
+    registry
+        .handle_tool_call(
+            &session,
+            "secret_put",
+            json!({"name": "api-key", "value": "s3cret"}),
+        )
+        .await
+        .unwrap();
+
+    let preview = registry
+        .handle_tool_call(
+            &session,
+            "secret_delete",
+            json!({"name": "api-key", "recovery_window_days": 10, "dry_run": true}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(preview["dryRun"], true);
+    assert_eq!(preview["exists"], true);
+    assert_eq!(preview["recovery_window_days"], 10);
+    assert!(preview["deletion_date"].is_string());
+
+    let secret = registry
+        .handle_tool_call(&session, "secret_get", json!({"name": "api-key"}))
+        .await
+        .unwrap();
+    assert_eq!(secret["value"], "s3cret");
+
+    let missing = registry
+        .handle_tool_call(
+            &session,
+            "secret_delete",
+            json!({"name": "absent", "dry_run": true}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(missing["exists"], false);
+    assert!(missing["deletion_date"].is_null());
+}
@@ -0,0 +1,198 @@
+// Unit tests for the EventBridge write-behind buffer: size and timer flushes, shutdown
+// and dead-lettering. Runs on paused time against recording sinks; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use mcp_rust::aws::event_buffer::{
+    BufferedEvent, DeadLetterStore, EventBuffer, EventSink, EVENT_BATCH_SIZE, FLUSH_INTERVAL,
+};
+use mcp_rust::aws::AwsError;
+
+/// Records each batch; events with detail type `fail` are rejected, and with `down` set
+/// the whole call fails
+#[derive(Default)]
+struct RecordingSink {
+    batches: Mutex<Vec<Vec<BufferedEvent>>>,
+    down: bool,
+}
+
+impl RecordingSink {
+    fn batch_sizes(&self) -> Vec<usize> {
+        self.batches.lock().unwrap().iter().map(Vec::len).collect()
+    }
+}
+
+#[async_trait]
+impl EventSink for RecordingSink {
+    async fn put_events(&self, events: &[BufferedEvent]) -> Result<Vec<Option<String>>, AwsError> {
+        self.batches.lock().unwrap().push(events.to_vec());
+        if self.down {
+            return Err(AwsError::EventBridge("service unavailable".to_string()));
+        }
+        Ok(events
+            .iter()
+            .map(|event| (event.detail_type == "fail").then(|| "InternalFailure: x".to_string()))
+            .collect())
+    }
+}
+
+#[derive(Default)]
+struct RecordingDeadLetters {
+    stored: Mutex<Vec<(String, String)>>,
+}
+
+#[async_trait]
+impl DeadLetterStore for RecordingDeadLetters {
+    async fn store(&self, event: &BufferedEvent, error: &str) -> Result<(), AwsError> {
+        self.stored
+            .lock()
+            .unwrap()
+            .push((event.event_id.clone(), error.to_string()));
+        Ok(())
+    }
+}
+
+fn push(buffer: &EventBuffer, tenant_id: &str, detail_type: &str, count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| {
+            buffer
+                .push(tenant_id, "mcp-rust", detail_type, json!({"n": i}))
+                .unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_full_batch_is_sent_at_once() {
+    let sink = Arc::new(RecordingSink::default());
+    let dead_letters = Arc::new(RecordingDeadLetters::default());
+    let buffer = EventBuffer::spawn(sink.clone(), dead_letters.clone());
+    let start = Instant::now();
+
+    let ids = push(
+        &buffer,
+        "alice-tenant",
+        "order.created",
+        EVENT_BATCH_SIZE + 2,
+    );
+    tokio::time::sleep(Duration::from_millis(1)).await;
+
+    assert!(start.elapsed() < FLUSH_INTERVAL);
+    assert_eq!(sink.batch_sizes(), vec![EVENT_BATCH_SIZE]);
+    let sent: Vec<String> = sink.batches.lock().unwrap()[0]
+        .iter()
+        .map(|event| event.event_id.clone())
+        .collect();
+    assert_eq!(sent, ids[..EVENT_BATCH_SIZE]);
+    assert_eq!(buffer.pending_count(), 2);
+
+    // A part-filled batch for another tenant isn't sent with it
+    push(&buffer, "bob-tenant", "order.created", EVENT_BATCH_SIZE - 1);
+    tokio::time::sleep(Duration::from_millis(1)).await;
+    assert_eq!(sink.batch_sizes(), vec![EVENT_BATCH_SIZE]);
+    assert_eq!(buffer.pending_count(), EVENT_BATCH_SIZE + 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_timer_sends_part_filled_batches() {
+    let sink = Arc::new(RecordingSink::default());
+    let dead_letters = Arc::new(RecordingDeadLetters::default());
+    let buffer = EventBuffer::spawn(sink.clone(), dead_letters.clone());
+
+    push(&buffer, "alice-tenant", "order.created", 3);
+    push(&buffer, "bob-tenant", "order.created", 2);
+
+    tokio::time::sleep(FLUSH_INTERVAL / 2).await;
+    assert!(sink.batch_sizes().is_empty());
+
+    tokio::time::sleep(FLUSH_INTERVAL).await;
+    let mut sizes = sink.batch_sizes();
+    sizes.sort();
+    assert_eq!(sizes, vec![2, 3]);
+    assert_eq!(buffer.pending_count(), 0);
+
+    // Each batch holds one tenant's events
+    for batch in sink.batches.lock().unwrap().iter() {
+        assert!(batch
+            .iter()
+            .all(|event| event.tenant_id == batch[0].tenant_id));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_shutdown_drains_pending_events() {
+    let sink = Arc::new(RecordingSink::default());
+    let dead_letters = Arc::new(RecordingDeadLetters::default());
+    let buffer = EventBuffer::spawn(sink.clone(), dead_letters.clone());
+    let start = Instant::now();
+
+    push(
+        &buffer,
+        "alice-tenant",
+        "order.created",
+        EVENT_BATCH_SIZE + 3,
+    );
+    push(&buffer, "bob-tenant", "order.created", 1);
+    buffer.shutdown().await;
+
+    assert!(start.elapsed() < FLUSH_INTERVAL);
+    assert_eq!(
+        sink.batch_sizes().iter().sum::<usize>(),
+        EVENT_BATCH_SIZE + 4
+    );
+    assert!(sink
+        .batch_sizes()
+        .iter()
+        .all(|size| *size <= EVENT_BATCH_SIZE));
+    assert_eq!(buffer.pending_count(), 0);
+
+    // Once shut down, events are handed back to be sent directly
+    assert!(buffer.is_closed());
+    assert_eq!(
+        buffer.push(
+            "alice-tenant",
+            "mcp-rust",
+            "order.created",
+            json!({"late": true})
+        ),
+        Err(json!({"late": true}))
+    );
+    buffer.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_failed_events_are_dead_lettered() {
+    let sink = Arc::new(RecordingSink::default());
+    let dead_letters = Arc::new(RecordingDeadLetters::default());
+    let buffer = EventBuffer::spawn(sink.clone(), dead_letters.clone());
+
+    push(&buffer, "alice-tenant", "order.created", 2);
+    let failed = push(&buffer, "alice-tenant", "fail", 1);
+    buffer.shutdown().await;
+    assert_eq!(
+        *dead_letters.stored.lock().unwrap(),
+        vec![(failed[0].clone(), "InternalFailure: x".to_string())]
+    );
+
+    // When the whole call fails, every event in the batch is dead-lettered
+    let sink = Arc::new(RecordingSink {
+        down: true,
+        ..Default::default()
+    });
+    let dead_letters = Arc::new(RecordingDeadLetters::default());
+    let buffer = EventBuffer::spawn(sink.clone(), dead_letters.clone());
+    let ids = push(&buffer, "alice-tenant", "order.created", 3);
+    tokio::time::sleep(FLUSH_INTERVAL * 2).await;
+    let stored: Vec<String> = dead_letters
+        .stored
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, _)| id.clone())
+        .collect();
+    assert_eq!(stored, ids);
+}
@@ -0,0 +1,166 @@
+// Unit tests for events_query plans and its explain mode
+// Plans are built without running them, and the handler runs over the in-memory backend;
+// no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::event_queries::{EventQuery, EventQueryPlan, Predicate};
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::namespaces::EventScope;
+use mcp_rust::aws::AwsError;
+use mcp_rust::handlers::{EventsQueryHandler, Handler};
+use mcp_rust::tenant::{Permission, TenantSession, UserRole};
+
+fn session(role: UserRole) -> TenantSession {
+    TestTenant::user("alice")
+        .tenant("acme-tenant")
+        .role(role)
+        .permissions(vec![Permission::SendEvents])
+        .session()
+}
+
+fn handler() -> EventsQueryHandler {
+    let aws = Arc::new(MemoryAwsService::new());
+    for (source, timestamp) in [
+        ("billing", "2026-03-01T10:00:00Z"),
+        ("billing", "2026-03-02T10:00:00Z"),
+        ("crm", "2026-03-02T11:00:00Z"),
+    ] {
+        aws.record_event(json!({
+            "userId": "alice",
+            "organizationId": "acme",
+            "source": source,
+            "detailType": "invoice.sent",
+            "timestamp": timestamp,
+            "detail": {}
+        }));
+    }
+    EventsQueryHandler::new(aws)
+}
+
+fn index_names(explain: &Value) -> Vec<&str> {
+    explain["accessPaths"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|path| path["index"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_user_and_source_queries_use_different_indexes() {
+    let by_user = EventQueryPlan::new(&EventQuery {
+        user_id: Some("alice".to_string()),
+        source: Some("billing".to_string()),
+        start_time: Some("2026-03-01T00:00:00Z".to_string()),
+        end_time: Some("2026-03-02T00:00:00Z".to_string()),
+        limit: 50,
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(by_user.queries.len(), 1);
+    let query = &by_user.queries[0];
+    assert_eq!(query.index, "user-index");
+    assert_eq!(
+        query.key_condition(),
+        "#userId = :userId AND #timestamp BETWEEN :start AND :end"
+    );
+    // The source can't be part of this index's key, so it's filtered on
+    assert_eq!(
+        query.filters,
+        vec![Predicate::Equals("source", "billing".to_string())]
+    );
+
+    let by_source = EventQueryPlan::new(&EventQuery {
+        source: Some("billing".to_string()),
+        organization_id: Some("acme".to_string()),
+        limit: 50,
+        ..Default::default()
+    })
+    .unwrap();
+    let query = &by_source.queries[0];
+    assert_eq!(query.index, "timestamp-index");
+    assert_eq!(query.key_condition(), "#source = :source");
+    assert_eq!(
+        query.filter_expression().as_deref(),
+        Some("#organizationId = :organizationId")
+    );
+
+    let explain = by_source.explain(None);
+    assert_eq!(index_names(&explain), ["timestamp-index"]);
+    assert_eq!(
+        explain["accessPaths"][0]["keyCondition"],
+        "source = :source"
+    );
+    assert_eq!(explain["executed"], false);
+
+    let unkeyed = EventQueryPlan::new(&EventQuery {
+        detail_type: Some("invoice.sent".to_string()),
+        ..Default::default()
+    });
+    assert!(matches!(unkeyed, Err(AwsError::Config(_))));
+}
+
+#[test]
+fn test_scoped_queries_also_read_untagged_records_of_the_user() {
+    let plan = EventQueryPlan::new(&EventQuery {
+        scope: Some(EventScope {
+            namespace: "user:alice".to_string(),
+            legacy_user_id: Some("alice".to_string()),
+        }),
+        // Ignored in favor of the scope
+        user_id: Some("bob".to_string()),
+        limit: 20,
+        ..Default::default()
+    })
+    .unwrap();
+    let explain = plan.explain(None);
+    assert_eq!(index_names(&explain), ["namespace-index", "user-index"]);
+    assert_eq!(explain["estimatedPages"], 2);
+    let legacy = &explain["accessPaths"][1];
+    assert_eq!(legacy["keyCondition"], "userId = :userId");
+    assert_eq!(
+        legacy["filters"],
+        json!(["attribute_not_exists(namespace)"])
+    );
+    assert_eq!(plan.queries[1].key_value, "alice");
+}
+
+#[tokio::test]
+async fn test_events_query_explains_alongside_or_instead_of_results() {
+    let handler = handler();
+    let admin = session(UserRole::Admin);
+
+    let result = handler
+        .handle(
+            &admin,
+            json!({"userId": "alice", "startTime": "2026-03-02T00:00:00Z", "explain": true}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["count"], 2, "{}", result);
+    assert_eq!(index_names(&result["explain"]), ["user-index"]);
+    assert_eq!(
+        result["explain"]["accessPaths"][0]["keyCondition"],
+        "userId = :userId AND timestamp >= :start"
+    );
+
+    let result = handler
+        .handle(&admin, json!({"source": "billing", "explain_only": true}))
+        .await
+        .unwrap();
+    assert!(result.get("events").is_none(), "{}", result);
+    assert_eq!(index_names(&result["explain"]), ["timestamp-index"]);
+
+    // Everyone else's queries are planned over their own namespace
+    let result = handler
+        .handle(&session(UserRole::User), json!({"explain_only": true}))
+        .await
+        .unwrap();
+    assert_eq!(
+        index_names(&result["explain"]),
+        ["namespace-index", "user-index"]
+    );
+}
@@ -0,0 +1,280 @@
+// Unit tests for the middleware chain HandlerRegistry runs around every tool call
+// Recording and rejecting middlewares over the in-memory backend; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::middleware::{
+    HandlerMiddleware, MeteringMiddleware, PermissionMiddleware, ToolCall,
+};
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::metrics::CallCounters;
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session(permissions: Vec<Permission>) -> TenantSession {
+    TestTenant::user("alice").permissions(permissions).session()
+}
+
+fn alice() -> TenantSession {
+    session(vec![Permission::ReadKV, Permission::WriteKV])
+}
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Logs `{name}.before {tool}` and `{name}.after {tool} ok|err`
+struct Recording {
+    name: &'static str,
+    log: Log,
+}
+
+#[async_trait]
+impl HandlerMiddleware for Recording {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn before(&self, call: &mut ToolCall<'_>) -> Result<(), HandlerError> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{}.before {}", self.name, call.tool));
+        Ok(())
+    }
+
+    async fn after(
+        &self,
+        call: &ToolCall<'_>,
+        result: &Result<Value, HandlerError>,
+        _elapsed: Duration,
+    ) {
+        let outcome = if result.is_ok() { "ok" } else { "err" };
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{}.after {} {}", self.name, call.tool, outcome));
+    }
+}
+
+/// Rejects every call of `tool`
+struct Reject {
+    tool: &'static str,
+}
+
+#[async_trait]
+impl HandlerMiddleware for Reject {
+    fn name(&self) -> &'static str {
+        "reject"
+    }
+
+    async fn before(&self, call: &mut ToolCall<'_>) -> Result<(), HandlerError> {
+        if call.tool == self.tool {
+            return Err(HandlerError::ToolBlocked(call.tool.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Tags the arguments before the handler sees them
+struct Tag;
+
+#[async_trait]
+impl HandlerMiddleware for Tag {
+    fn name(&self) -> &'static str {
+        "tag"
+    }
+
+    async fn before(&self, call: &mut ToolCall<'_>) -> Result<(), HandlerError> {
+        call.arguments["tagged"] = json!(true);
+        Ok(())
+    }
+}
+
+/// Always fails
+struct Failing;
+
+#[async_trait]
+impl Handler for Failing {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        Err(HandlerError::Internal(format!("failed on {}", arguments)))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({"description": "Fail", "inputSchema": {"type": "object"}})
+    }
+}
+
+fn recording(name: &'static str, log: &Log) -> Arc<Recording> {
+    Arc::new(Recording {
+        name,
+        log: log.clone(),
+    })
+}
+
+fn take(log: &Log) -> Vec<String> {
+    std::mem::take(&mut *log.lock().unwrap())
+}
+
+#[tokio::test]
+async fn test_default_chain_audits_then_checks_permissions() {
+    let registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    assert_eq!(registry.middleware_names(), vec!["audit", "permission"]);
+
+    let denied = registry
+        .handle_tool_call(
+            &session(vec![Permission::ReadKV]),
+            "kv_set",
+            json!({"key": "k", "value": "v"}),
+        )
+        .await;
+    assert!(matches!(
+        denied,
+        Err(HandlerError::PermissionDenied(Permission::WriteKV))
+    ));
+}
+
+#[tokio::test]
+async fn test_middlewares_observe_success_and_errors_in_order() {
+    let log = Log::default();
+    let mut registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await
+    .with_middleware(recording("outer", &log))
+    .with_middleware(recording("inner", &log));
+    registry.register("fail", Arc::new(Failing));
+
+    registry
+        .handle_tool_call(&alice(), "kv_set", json!({"key": "k", "value": "v"}))
+        .await
+        .unwrap();
+    assert_eq!(
+        take(&log),
+        vec![
+            "outer.before kv_set",
+            "inner.before kv_set",
+            "inner.after kv_set ok",
+            "outer.after kv_set ok",
+        ]
+    );
+
+    let failed = registry.handle_tool_call(&alice(), "fail", json!({})).await;
+    assert!(matches!(failed, Err(HandlerError::Internal(_))));
+    assert_eq!(
+        take(&log),
+        vec![
+            "outer.before fail",
+            "inner.before fail",
+            "inner.after fail err",
+            "outer.after fail err",
+        ]
+    );
+
+    // Arguments failing the schema are an error the middlewares see too
+    assert!(registry
+        .handle_tool_call(&alice(), "kv_get", json!({}))
+        .await
+        .is_err());
+    assert_eq!(
+        take(&log),
+        vec![
+            "outer.before kv_get",
+            "inner.before kv_get",
+            "inner.after kv_get err",
+            "outer.after kv_get err",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_a_rejecting_middleware_short_circuits_the_call() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let log = Log::default();
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default())
+        .await
+        .with_middlewares(vec![
+            recording("outer", &log),
+            Arc::new(Reject { tool: "kv_set" }),
+            recording("inner", &log),
+            Arc::new(PermissionMiddleware),
+        ]);
+
+    let rejected = registry
+        .handle_tool_call(&alice(), "kv_set", json!({"key": "k", "value": "v"}))
+        .await;
+    assert!(matches!(rejected, Err(HandlerError::ToolBlocked(_))));
+    // Only the middlewares before the rejection ran, and the handler never did
+    assert_eq!(
+        take(&log),
+        vec!["outer.before kv_set", "outer.after kv_set err"]
+    );
+    let value = registry
+        .handle_tool_call(&alice(), "kv_get", json!({"key": "k"}))
+        .await
+        .unwrap();
+    assert!(value["value"].is_null(), "{}", value);
+}
+
+#[tokio::test]
+async fn test_middlewares_can_rewrite_arguments() {
+    let mut registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await
+    .with_middleware(Arc::new(Tag));
+    registry.register("fail", Arc::new(Failing));
+
+    match registry.handle_tool_call(&alice(), "fail", json!({})).await {
+        Err(HandlerError::Internal(message)) => {
+            assert!(message.contains("\"tagged\":true"), "{}", message)
+        }
+        other => panic!("expected the handler's error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_metering_middleware_counts_calls_and_errors() {
+    let counters = Arc::new(CallCounters::new());
+    let registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await
+    .with_middlewares(vec![
+        Arc::new(MeteringMiddleware::new(counters.clone())),
+        Arc::new(PermissionMiddleware),
+    ]);
+
+    registry
+        .handle_tool_call(&alice(), "kv_set", json!({"key": "k", "value": "v"}))
+        .await
+        .unwrap();
+    assert!(registry
+        .handle_tool_call(
+            &session(vec![Permission::ReadKV]),
+            "kv_set",
+            json!({"key": "k", "value": "v"}),
+        )
+        .await
+        .is_err());
+
+    let by_tool = counters.by_tool();
+    assert_eq!((by_tool["kv_set"].calls, by_tool["kv_set"].errors), (2, 1));
+}
@@ -0,0 +1,103 @@
+// Unit tests for the health tool's sub-checks and its degraded path
+// HealthHandler over the in-memory backend, with a slow ping standing in for unreachable AWS
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::health::HealthHandler;
+use mcp_rust::handlers::Handler;
+use mcp_rust::rate_limiting::AwsOperation;
+use mcp_rust::registry::MCPServerRegistry;
+use mcp_rust::tenant::{TenantManager, TenantSession};
+
+const CHECK_TIMEOUT: Duration = Duration::from_millis(200);
+
+fn caller() -> TenantSession {
+    TestTenant::user("alice").session()
+}
+
+async fn health(aws: Arc<MemoryAwsService>) -> HealthHandler {
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let registry = Arc::new(MCPServerRegistry::new(aws.clone()));
+    HealthHandler::new(tenant_manager, registry, aws).with_check_timeout(CHECK_TIMEOUT)
+}
+
+#[tokio::test]
+async fn test_healthy_server_reports_every_check_ok() {
+    let handler = health(Arc::new(MemoryAwsService::new())).await;
+    assert!(handler.required_permission().is_none());
+
+    let report: Value = handler.handle(&caller(), json!({})).await.unwrap();
+    assert_eq!(report["status"], "ok", "{}", report);
+    assert_eq!(report["version"], env!("CARGO_PKG_VERSION"));
+    assert!(report["uptime_secs"].is_u64());
+    assert!(report["active_requests"].is_u64());
+    for check in [
+        "sessions",
+        "rate_limiter",
+        "connections",
+        "backend",
+        "tenant_credentials",
+    ] {
+        assert_eq!(report["checks"][check]["status"], "ok", "{}", report);
+    }
+    assert!(report["checks"]["sessions"]["active"].is_u64());
+    assert_eq!(
+        report["checks"]["tenant_credentials"]["degraded_tenants"],
+        0
+    );
+    assert_eq!(report["checks"]["connections"]["by_status"], json!({}));
+}
+
+#[tokio::test]
+async fn test_backend_timeout_degrades_without_blocking() {
+    let aws = Arc::new(MemoryAwsService::new());
+    aws.set_ping_delay(Some(Duration::from_secs(30)));
+    let handler = health(aws).await;
+
+    let started = tokio::time::Instant::now();
+    let report = handler.handle(&caller(), json!({})).await.unwrap();
+    // Bounded by one check timeout, not by the backend
+    assert!(
+        started.elapsed() < CHECK_TIMEOUT * 5,
+        "{:?}",
+        started.elapsed()
+    );
+
+    assert_eq!(report["status"], "degraded", "{}", report);
+    let backend = &report["checks"]["backend"];
+    assert_eq!(backend["status"], "unknown");
+    assert!(
+        backend["error"].as_str().unwrap().contains("200ms"),
+        "{}",
+        backend
+    );
+    // The other checks still answered
+    for check in ["sessions", "rate_limiter", "connections"] {
+        assert_eq!(report["checks"][check]["status"], "ok", "{}", report);
+    }
+}
+
+#[tokio::test]
+async fn test_backend_recovers_once_it_answers_again() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let handler = health(aws.clone()).await;
+
+    aws.set_ping_delay(Some(Duration::from_secs(30)));
+    let report = handler.handle(&caller(), json!({})).await.unwrap();
+    assert_eq!(report["status"], "degraded");
+
+    aws.set_ping_delay(None);
+    let report = handler.handle(&caller(), json!({})).await.unwrap();
+    assert_eq!(report["status"], "ok");
+    assert!(report["checks"]["backend"]["latency_ms"].is_u64());
+}
+
+#[test]
+fn test_health_is_never_rate_limited() {
+    // Orchestrators must get an answer while the tenant is throttled
+    assert!(AwsOperation::from_tool_name("health", &json!({})).is_none());
+}
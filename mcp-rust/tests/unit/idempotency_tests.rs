@@ -0,0 +1,278 @@
+// Unit tests for idempotency keys on mutating tool calls
+// A counting handler shows whether a replay reached the tool; stores are in memory
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::idempotency::{IdempotencyStore, MemoryIdempotencyStore};
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::{MCPError, MCPErrorResponse};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session(tenant_id: &str) -> TenantSession {
+    TestTenant::user("retry-user")
+        .tenant(tenant_id)
+        .organization_id("retry-org")
+        .permissions(vec![Permission::ReadKV, Permission::WriteKV])
+        .session()
+}
+
+/// Counts its runs and answers with the run number, so a replay is easy to tell apart;
+/// takes `delay` to answer, and fails when asked to with `"fail": true`
+struct Counting {
+    runs: Arc<AtomicUsize>,
+    idempotent: bool,
+    delay: Duration,
+}
+
+#[async_trait]
+impl Handler for Counting {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let run = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+        tokio::time::sleep(self.delay).await;
+        if arguments["fail"] == true {
+            return Err(HandlerError::Internal("asked to fail".to_string()));
+        }
+        Ok(json!({ "run": run, "arguments": arguments }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::WriteKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({ "description": "Count runs", "inputSchema": { "type": "object" } })
+    }
+
+    fn accepts_idempotency_key(&self) -> bool {
+        self.idempotent
+    }
+}
+
+async fn setup(store: Option<Arc<dyn IdempotencyStore>>) -> (HandlerRegistry, Arc<AtomicUsize>) {
+    let mut registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions {
+            idempotency_store: store,
+            ..RegistryOptions::default()
+        },
+    )
+    .await;
+    let runs = Arc::new(AtomicUsize::new(0));
+    registry.register(
+        "count",
+        Arc::new(Counting {
+            runs: runs.clone(),
+            idempotent: true,
+            delay: Duration::ZERO,
+        }),
+    );
+    registry.register(
+        "count_slowly",
+        Arc::new(Counting {
+            runs: runs.clone(),
+            idempotent: true,
+            delay: Duration::from_millis(50),
+        }),
+    );
+    registry.register(
+        "count_once",
+        Arc::new(Counting {
+            runs: runs.clone(),
+            idempotent: false,
+            delay: Duration::ZERO,
+        }),
+    );
+    (registry, runs)
+}
+
+#[tokio::test]
+async fn test_replay_returns_the_stored_response_without_running_again() {
+    // Records kept in the backend's KV table
+    let (registry, runs) = setup(None).await;
+    let alice = session("alice-tenant");
+
+    let first = registry
+        .handle_tool_call_with_key(&alice, "count", json!({"n": 1}), Some("req-1"))
+        .await
+        .unwrap();
+    let replay = registry
+        .handle_tool_call_with_key(&alice, "count", json!({"n": 1}), Some("req-1"))
+        .await
+        .unwrap();
+    assert_eq!(replay, first);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // Another key, or another tenant with the same key, runs the tool
+    registry
+        .handle_tool_call_with_key(&alice, "count", json!({"n": 1}), Some("req-2"))
+        .await
+        .unwrap();
+    registry
+        .handle_tool_call_with_key(
+            &session("bob-tenant"),
+            "count",
+            json!({"n": 1}),
+            Some("req-1"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_replay_with_other_arguments_conflicts() {
+    let (registry, runs) = setup(Some(Arc::new(MemoryIdempotencyStore::default()))).await;
+    let alice = session("alice-tenant");
+
+    registry
+        .handle_tool_call_with_key(&alice, "count", json!({"n": 1, "m": 2}), Some("req-1"))
+        .await
+        .unwrap();
+    // Same arguments in another order are the same call
+    registry
+        .handle_tool_call_with_key(&alice, "count", json!({"m": 2, "n": 1}), Some("req-1"))
+        .await
+        .unwrap();
+
+    let result = registry
+        .handle_tool_call_with_key(&alice, "count", json!({"n": 2}), Some("req-1"))
+        .await;
+    let error = match result {
+        Err(error @ HandlerError::IdempotencyConflict { .. }) => error,
+        other => panic!("expected IdempotencyConflict, got {:?}", other),
+    };
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    let response = MCPErrorResponse::from(MCPError::from(error));
+    assert_eq!(response.code, -32007);
+    assert_eq!(response.data.unwrap()["retryable"], false);
+}
+
+#[tokio::test]
+async fn test_concurrent_calls_with_one_key_run_once() {
+    let stores: [Option<Arc<dyn IdempotencyStore>>; 2] =
+        [None, Some(Arc::new(MemoryIdempotencyStore::default()))];
+    for store in stores {
+        let (registry, runs) = setup(store).await;
+        let alice = session("alice-tenant");
+        let call =
+            || registry.handle_tool_call_with_key(&alice, "count_slowly", json!({}), Some("req-1"));
+
+        let (first, second) = tokio::join!(call(), call());
+        let first = first.unwrap();
+        let error = match second {
+            Err(error @ HandlerError::IdempotencyInProgress { .. }) => error,
+            other => panic!("expected IdempotencyInProgress, got {:?}", other),
+        };
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        let response = MCPErrorResponse::from(MCPError::from(error));
+        assert_eq!(response.code, -32007);
+        assert_eq!(response.data.unwrap()["retryable"], true);
+
+        // Retried once the first call is done, it replays
+        assert_eq!(call().await.unwrap(), first);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[tokio::test]
+async fn test_failed_call_releases_its_key() {
+    let (registry, runs) = setup(None).await;
+    let alice = session("alice-tenant");
+
+    let failed = registry
+        .handle_tool_call_with_key(&alice, "count", json!({"fail": true}), Some("req-1"))
+        .await;
+    assert!(matches!(failed, Err(HandlerError::Internal(_))));
+    // Nothing was kept, so the retry runs the tool
+    assert!(registry
+        .handle_tool_call_with_key(&alice, "count", json!({"fail": true}), Some("req-1"))
+        .await
+        .is_err());
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_entries_expire() {
+    let store = Arc::new(MemoryIdempotencyStore::new(Duration::from_millis(50), 100));
+    let (registry, runs) = setup(Some(store)).await;
+    let alice = session("alice-tenant");
+
+    for _ in 0..2 {
+        registry
+            .handle_tool_call_with_key(&alice, "count", json!({}), Some("req-1"))
+            .await
+            .unwrap();
+    }
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    let after_expiry = registry
+        .handle_tool_call_with_key(&alice, "count", json!({}), Some("req-1"))
+        .await
+        .unwrap();
+    assert_eq!(after_expiry["run"], 2);
+}
+
+#[tokio::test]
+async fn test_memory_store_drops_the_oldest_when_full() {
+    let store = Arc::new(MemoryIdempotencyStore::new(Duration::from_secs(60), 2));
+    let (registry, runs) = setup(Some(store.clone())).await;
+    let alice = session("alice-tenant");
+
+    for key in ["a", "b", "c"] {
+        registry
+            .handle_tool_call_with_key(&alice, "count", json!({}), Some(key))
+            .await
+            .unwrap();
+    }
+    assert_eq!(store.record_count(), 2);
+
+    // "a" was dropped, so it runs again; "c" replays
+    registry
+        .handle_tool_call_with_key(&alice, "count", json!({}), Some("c"))
+        .await
+        .unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    registry
+        .handle_tool_call_with_key(&alice, "count", json!({}), Some("a"))
+        .await
+        .unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn test_keys_only_accepted_by_idempotent_tools() {
+    let (registry, runs) = setup(None).await;
+    let alice = session("alice-tenant");
+
+    let result = registry
+        .handle_tool_call_with_key(&alice, "count_once", json!({}), Some("req-1"))
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+
+    let result = registry
+        .handle_tool_call_with_key(&alice, "count", json!({}), Some("not a key!"))
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+    assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+    // kv_set takes one
+    let set = json!({"key": "k", "value": "v"});
+    for _ in 0..2 {
+        registry
+            .handle_tool_call_with_key(&alice, "kv_set", set.clone(), Some("set-1"))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+// Unit tests for the integration catalog and registering integrations from its templates
+// Templates are parsed from inline JSON; no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::integrations::catalog::IntegrationCatalog;
+use mcp_rust::handlers::integrations::{IntegrationCatalogListHandler, IntegrationRegisterHandler};
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::MCPServerRegistry;
+use mcp_rust::tenant::TenantSession;
+
+fn catalog() -> Arc<IntegrationCatalog> {
+    let json = json!([
+        {
+            "id": "fetch",
+            "name": "Fetch",
+            "description": "Fetch web pages",
+            "category": "web",
+            "command": "uvx",
+            "args": ["mcp-server-fetch"],
+            "env": {"USER_AGENT": "agent-mesh", "LOG_LEVEL": "info"},
+            "auth_method": "none",
+            "configuration_schema": [],
+            "capabilities": ["fetch"]
+        },
+        {
+            "id": "github",
+            "name": "GitHub",
+            "description": "GitHub issues",
+            "category": "development",
+            "docker_config": {"image": "ghcr.io/github/github-mcp-server", "tag": "latest"},
+            "auth_method": {"api_key": {"key_field": "GITHUB_PERSONAL_ACCESS_TOKEN"}},
+            "configuration_schema": [],
+            "capabilities": ["create_issue"]
+        }
+    ]);
+    Arc::new(IntegrationCatalog::from_json(&json.to_string()).unwrap())
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("catalog-user")
+        .tenant("catalog-tenant")
+        .organization_id("catalog-org")
+        .session()
+}
+
+#[test]
+fn test_overrides_take_precedence_over_the_template() {
+    let resolved = catalog()
+        .resolve(json!({
+            "template": "fetch",
+            "name": "Web fetch",
+            "args": ["mcp-server-fetch", "--ignore-robots-txt"],
+            "env": {"LOG_LEVEL": "debug"}
+        }))
+        .unwrap();
+
+    // The catalog id is the default service id; given fields replace the template's
+    assert_eq!(resolved["service_id"], "fetch");
+    assert_eq!(resolved["name"], "Web fetch");
+    assert_eq!(resolved["description"], "Fetch web pages");
+    assert_eq!(
+        resolved["args"],
+        json!(["mcp-server-fetch", "--ignore-robots-txt"])
+    );
+    // env is merged key by key
+    assert_eq!(
+        resolved["env"],
+        json!({"USER_AGENT": "agent-mesh", "LOG_LEVEL": "debug"})
+    );
+    assert!(resolved.get("template").is_none());
+
+    let resolved = catalog()
+        .resolve(json!({
+            "template": "github",
+            "service_id": "github-work",
+            "docker_config": {"tag": "v0.5.0"},
+            "auth_method": {"o_auth2": {"client_id": "c", "client_secret": "s"}}
+        }))
+        .unwrap();
+    assert_eq!(resolved["service_id"], "github-work");
+    assert_eq!(
+        resolved["docker_config"]["image"],
+        "ghcr.io/github/github-mcp-server"
+    );
+    assert_eq!(resolved["docker_config"]["tag"], "v0.5.0");
+    // Other objects are replaced whole
+    assert_eq!(
+        resolved["auth_method"],
+        json!({"o_auth2": {"client_id": "c", "client_secret": "s"}})
+    );
+}
+
+#[test]
+fn test_arguments_without_a_template_are_unchanged() {
+    let arguments = json!({"service_id": "custom", "name": "Custom"});
+    assert_eq!(catalog().resolve(arguments.clone()).unwrap(), arguments);
+}
+
+#[tokio::test]
+async fn test_unknown_template_is_rejected() {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = Arc::new(
+        MCPServerRegistry::new(aws_service.clone())
+            .with_store(Arc::new(MemoryKvStore::new()))
+            .with_secret_store(Arc::new(MemorySecretStore::new())),
+    );
+    let handler = IntegrationRegisterHandler::new(aws_service, registry.clone(), catalog());
+
+    match handler
+        .handle(&session(), json!({"template": "gitlab"}))
+        .await
+    {
+        Err(HandlerError::InvalidArguments(message)) => {
+            assert!(message.contains("\"gitlab\""), "{}", message);
+            assert!(message.contains("fetch, github"), "{}", message);
+        }
+        other => panic!("expected InvalidArguments, got {:?}", other),
+    }
+    assert!(registry
+        .list_servers(&session().context.get_context_id())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_catalog_list_by_category() {
+    let handler = IntegrationCatalogListHandler::new(catalog());
+
+    let all = handler.handle(&session(), json!({})).await.unwrap();
+    assert_eq!(all["count"], 2);
+
+    let web = handler
+        .handle(&session(), json!({"category": "web"}))
+        .await
+        .unwrap();
+    let templates: &Vec<Value> = web["templates"].as_array().unwrap();
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates[0]["id"], "fetch");
+    assert_eq!(templates[0]["command"], "uvx");
+}
@@ -0,0 +1,273 @@
+// Unit tests for exporting a tenant's integrations and importing them elsewhere
+// Registries use the in-memory config and secret stores; no AWS access required
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::integrations::IntegrationImportHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-export-user";
+
+/// Secret values configured in the source environment
+const SECRETS: [&str; 3] = ["ghp_live_token", "oauth-client-secret", "hunter2"];
+
+/// A process server whose `whoami` tool answers with its `GITHUB_TOKEN`
+fn github() -> MCPServerConfig {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools =
+        r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"whoami","inputSchema":{}}]}}"#;
+    let call = r#"read l; echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"'"$GITHUB_TOKEN"'"}]}}';"#;
+    let script = format!(
+        "read l; echo '{}'; read l; read l; echo '{}'; {} cat >/dev/null",
+        initialized, tools, call
+    );
+    let mut config = config(
+        "github",
+        AuthMethod::None,
+        DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script],
+        },
+    );
+    config.env = HashMap::from([
+        ("GITHUB_TOKEN".to_string(), SECRETS[0].to_string()),
+        ("LOG_LEVEL".to_string(), "debug".to_string()),
+    ]);
+    config
+}
+
+fn config(id: &str, auth_method: AuthMethod, deployment: DeploymentConfig) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: format!("{} integration", id),
+        server_type: MCPServerType::Stdio,
+        deployment,
+        env: Default::default(),
+        auth_method,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+fn lambda() -> DeploymentConfig {
+    DeploymentConfig::Lambda {
+        function_name: "mcp".to_string(),
+        region: "us-east-1".to_string(),
+    }
+}
+
+async fn registry() -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    Arc::new(
+        MCPServerRegistry::new(aws_service)
+            .with_store(Arc::new(MemoryKvStore::new()))
+            .with_secret_store(Arc::new(MemorySecretStore::new())),
+    )
+}
+
+/// A registry with a server of each auth method and some stored credentials
+async fn staging() -> Arc<MCPServerRegistry> {
+    let registry = registry().await;
+    let servers = [
+        github(),
+        config(
+            "mail",
+            AuthMethod::OAuth2 {
+                client_id: "client-1".to_string(),
+                client_secret: SECRETS[1].to_string(),
+                token_endpoint: Some("https://auth.example.com/token".to_string()),
+            },
+            lambda(),
+        ),
+        config(
+            "wiki",
+            AuthMethod::Basic {
+                username: "bot".to_string(),
+                password: SECRETS[2].to_string(),
+            },
+            lambda(),
+        ),
+        config(
+            "search",
+            AuthMethod::ApiKey {
+                key_field: "X-Api-Key".to_string(),
+            },
+            lambda(),
+        ),
+    ];
+    for server in servers {
+        registry.register_server(TENANT, server).await.unwrap();
+    }
+    registry
+        .store_credentials(
+            TENANT,
+            "search",
+            &HashMap::from([("api_key".to_string(), "sk-staging".to_string())]),
+        )
+        .await
+        .unwrap();
+    registry
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("export-user")
+        .tenant("export-tenant")
+        .organization_id("export-org")
+        .session()
+}
+
+async fn import(
+    registry: &Arc<MCPServerRegistry>,
+    document: &Value,
+    on_conflict: &str,
+) -> Result<Value, HandlerError> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    IntegrationImportHandler::new(aws_service, registry.clone())
+        .handle(
+            &session(),
+            json!({"document": document, "on_conflict": on_conflict}),
+        )
+        .await
+}
+
+#[tokio::test]
+async fn test_export_round_trip_without_secrets() {
+    let export = staging().await.export_servers(TENANT).await.unwrap();
+    let document = serde_json::to_value(&export).unwrap();
+    let text = document.to_string();
+    for secret in SECRETS.iter().chain(&["sk-staging"]) {
+        assert!(!text.contains(secret), "{} leaked into the export", secret);
+    }
+
+    let ids: Vec<&str> = export
+        .servers
+        .iter()
+        .map(|s| s.config.id.as_str())
+        .collect();
+    assert_eq!(ids, ["github", "mail", "search", "wiki"]);
+    let github = &document["servers"][0];
+    assert_eq!(
+        github["config"]["env"]["GITHUB_TOKEN"],
+        "${credential:GITHUB_TOKEN}"
+    );
+    assert_eq!(github["config"]["env"]["LOG_LEVEL"], "debug");
+    assert_eq!(
+        document["servers"][1]["config"]["auth_method"]["o_auth2"]["client_secret"],
+        "${credential:client_secret}"
+    );
+
+    let production = registry().await;
+    let report = import(&production, &document, "skip").await.unwrap();
+    assert_eq!(
+        report["imported"],
+        json!(["github", "mail", "search", "wiki"])
+    );
+    assert_eq!(
+        report["missing_credentials"],
+        json!([
+            {"server_id": "github", "credentials": ["GITHUB_TOKEN"]},
+            {"server_id": "mail", "credentials": ["access_token", "client_secret", "refresh_token"]},
+            {"server_id": "search", "credentials": ["api_key"]},
+            {"server_id": "wiki", "credentials": ["password"]}
+        ])
+    );
+    let mut listed: Vec<String> = production
+        .list_servers(TENANT)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|server| server.id)
+        .collect();
+    listed.sort();
+    assert_eq!(listed, ["github", "mail", "search", "wiki"]);
+
+    // Placeholders are filled from the credentials supplied in the new environment
+    match production.connect_server(TENANT, "github", None).await {
+        Err(RegistryError::MissingCredential(name)) => assert_eq!(name, "GITHUB_TOKEN"),
+        other => panic!("expected MissingCredential, got {:?}", other),
+    }
+    let credentials = HashMap::from([("GITHUB_TOKEN".to_string(), "ghp_prod".to_string())]);
+    production
+        .store_credentials(TENANT, "github", &credentials)
+        .await
+        .unwrap();
+    production
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap();
+    let result = production
+        .execute_tool(TENANT, "github", "whoami", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(result["content"][0]["text"], "ghp_prod");
+    production.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_conflicting_ids_are_skipped_or_overwritten() {
+    let document =
+        serde_json::to_value(staging().await.export_servers(TENANT).await.unwrap()).unwrap();
+    let production = registry().await;
+    let mut existing = github();
+    existing.description = "hand-registered".to_string();
+    production.register_server(TENANT, existing).await.unwrap();
+    let credentials = HashMap::from([("GITHUB_TOKEN".to_string(), "ghp_prod".to_string())]);
+    production
+        .store_credentials(TENANT, "github", &credentials)
+        .await
+        .unwrap();
+    let description = |registry: Arc<MCPServerRegistry>| async move {
+        registry
+            .list_servers(TENANT)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|server| server.id == "github")
+            .unwrap()
+            .description
+    };
+
+    let report = import(&production, &document, "skip").await.unwrap();
+    assert_eq!(report["skipped"], json!(["github"]));
+    assert_eq!(report["imported"], json!(["mail", "search", "wiki"]));
+    assert_eq!(description(production.clone()).await, "hand-registered");
+
+    let report = import(&production, &document, "overwrite").await.unwrap();
+    assert_eq!(
+        report["overwritten"],
+        json!(["github", "mail", "search", "wiki"])
+    );
+    assert_eq!(report["skipped"], json!([]));
+    assert_eq!(description(production.clone()).await, "github integration");
+    // Credentials already stored don't need supplying again
+    let missing = report["missing_credentials"].as_array().unwrap();
+    assert!(missing.iter().all(|entry| entry["server_id"] != "github"));
+}
+
+#[tokio::test]
+async fn test_unknown_export_version_is_refused() {
+    let mut document =
+        serde_json::to_value(staging().await.export_servers(TENANT).await.unwrap()).unwrap();
+    document["version"] = json!(99);
+    let production = registry().await;
+
+    match import(&production, &document, "skip").await {
+        Err(HandlerError::InvalidArguments(message)) => {
+            assert!(message.contains("version"), "{}", message)
+        }
+        other => panic!("expected InvalidArguments, got {:?}", other),
+    }
+    assert!(production.list_servers(TENANT).await.unwrap().is_empty());
+}
@@ -0,0 +1,246 @@
+// Unit tests for integration connections shared with an organization
+// Servers are local `sh` processes and stores are in memory; no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::{AwsApi, MemorySecretStore};
+use mcp_rust::handlers::integrations::{
+    IntegrationConnectHandler, IntegrationDisconnectHandler, IntegrationListHandler,
+};
+use mcp_rust::handlers::mcp_proxy::MCPProxyHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType,
+};
+use mcp_rust::tenant::{ContextType, Permission, TenantSession, UserRole};
+
+/// The registry context of every member of the `acme` organization
+const ORG_CONTEXT: &str = "org-acme";
+
+/// An MCP server with a `whoami` tool answering its first call with the API token it
+/// was started with
+fn token_script() -> String {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools =
+        r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"whoami","inputSchema":{}}]}}"#;
+    let call = r#"read l; echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"'"$API_TOKEN"'"}]}}';"#;
+    format!(
+        "read l; echo '{}'; read l; read l; echo '{}'; {} cat >/dev/null",
+        initialized, tools, call
+    )
+}
+
+fn bot_config() -> MCPServerConfig {
+    MCPServerConfig {
+        id: "bot".to_string(),
+        name: "Bot".to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), token_script()],
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::ApiKey {
+            key_field: "API_TOKEN".to_string(),
+        },
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+fn member(user_id: &str, role: UserRole) -> TenantSession {
+    TestTenant::user(user_id)
+        .tenant("acme-tenant")
+        .in_organization("acme", "Acme")
+        .role(role)
+        .permissions(vec![
+            Permission::Read,
+            Permission::Write,
+            Permission::Execute,
+        ])
+        .session()
+}
+
+struct Tools {
+    connect: IntegrationConnectHandler,
+    list: IntegrationListHandler,
+    disconnect: IntegrationDisconnectHandler,
+    proxy: MCPProxyHandler,
+    secrets: Arc<MemorySecretStore>,
+}
+
+async fn tools() -> Tools {
+    let aws: Arc<dyn AwsApi> = Arc::new(MemoryAwsService::new());
+    let secrets = Arc::new(MemorySecretStore::new());
+    let registry = MCPServerRegistry::new(aws.clone()).with_secret_store(secrets.clone());
+    registry
+        .register_server(ORG_CONTEXT, bot_config())
+        .await
+        .unwrap();
+    let registry = Arc::new(registry);
+    Tools {
+        connect: IntegrationConnectHandler::new(aws.clone(), registry.clone()),
+        list: IntegrationListHandler::new(aws.clone(), registry.clone()),
+        disconnect: IntegrationDisconnectHandler::new(aws, registry.clone()),
+        proxy: MCPProxyHandler::new(registry),
+        secrets,
+    }
+}
+
+fn connect_args(connection_id: &str, token: &str, scope: &str) -> Value {
+    json!({
+        "service_id": "bot",
+        "connection_id": connection_id,
+        "connection_name": format!("Bot ({})", connection_id),
+        "credentials": {"api_key": token},
+        "scope": scope
+    })
+}
+
+fn disconnect(connection_id: &str, scope: &str) -> Value {
+    json!({"service_id": "bot", "connection_id": connection_id, "scope": scope})
+}
+
+#[tokio::test]
+async fn test_members_list_and_use_organization_connections_only() {
+    let tools = tools().await;
+    let alice = member("alice", UserRole::User);
+    let bob = member("bob", UserRole::User);
+
+    let result = tools
+        .connect
+        .handle(&alice, connect_args("team", "token-team", "organization"))
+        .await
+        .unwrap();
+    assert_eq!(result["scope"], "organization");
+    tools
+        .connect
+        .handle(&alice, connect_args("mine", "token-mine", "personal"))
+        .await
+        .unwrap();
+    // Credentials are kept in the organization's namespace of the secret store
+    assert!(tools
+        .secrets
+        .names()
+        .contains(&"mcp/org-acme/bot@team/api_key".to_string()));
+
+    let listed = tools.list.handle(&bob, json!({})).await.unwrap();
+    assert_eq!(listed["user_connections"], json!([]));
+    let shared = listed["organization_connections"].as_array().unwrap();
+    assert_eq!(shared.len(), 1, "{}", listed);
+    assert_eq!(shared[0]["connection_id"], "team");
+    assert_eq!(shared[0]["connection_name"], "Bot (team)");
+    assert_eq!(shared[0]["created_by"], "alice");
+
+    // Alice still sees her personal connection, which Bob doesn't
+    let listed = tools.list.handle(&alice, json!({})).await.unwrap();
+    let personal = listed["user_connections"].as_array().unwrap();
+    assert_eq!(personal.len(), 1);
+    assert!(personal[0].as_str().unwrap().ends_with(":bot:mine"));
+
+    let result = tools
+        .proxy
+        .handle(
+            &bob,
+            json!({"tool_name": "bot.team.whoami", "arguments": {}}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["content"][0]["text"], "token-team");
+}
+
+#[tokio::test]
+async fn test_only_the_creator_or_an_org_admin_disconnects() {
+    let tools = tools().await;
+    let alice = member("alice", UserRole::User);
+    let bob = member("bob", UserRole::User);
+    let carol = member("carol", UserRole::Admin);
+
+    for connection_id in ["team", "ops"] {
+        tools
+            .connect
+            .handle(&alice, connect_args(connection_id, "token", "organization"))
+            .await
+            .unwrap();
+    }
+
+    let denied = tools
+        .disconnect
+        .handle(&bob, disconnect("team", "organization"))
+        .await;
+    assert!(matches!(
+        denied,
+        Err(HandlerError::PermissionDenied(Permission::Admin))
+    ));
+    // Nor can a teammate replace it, or reach it as a personal connection
+    let replaced = tools
+        .connect
+        .handle(&bob, connect_args("team", "token-bob", "organization"))
+        .await;
+    assert!(matches!(
+        replaced,
+        Err(HandlerError::PermissionDenied(Permission::Admin))
+    ));
+    for result in [
+        tools
+            .disconnect
+            .handle(&bob, disconnect("team", "personal"))
+            .await,
+        tools
+            .connect
+            .handle(&bob, connect_args("team", "token-bob", "personal"))
+            .await,
+    ] {
+        match result {
+            Err(HandlerError::InvalidArguments(message)) => {
+                assert!(
+                    message.contains("shared with the organization"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("Expected InvalidArguments, got {:?}", other),
+        }
+    }
+
+    tools
+        .disconnect
+        .handle(&alice, disconnect("team", "organization"))
+        .await
+        .unwrap();
+    tools
+        .disconnect
+        .handle(&carol, disconnect("ops", "organization"))
+        .await
+        .unwrap();
+    let listed = tools.list.handle(&bob, json!({})).await.unwrap();
+    assert_eq!(listed["organization_connections"], json!([]));
+    assert!(
+        tools.secrets.names().is_empty(),
+        "{:?}",
+        tools.secrets.names()
+    );
+}
+
+#[tokio::test]
+async fn test_organization_scope_needs_an_organization() {
+    let tools = tools().await;
+    let mut context = member("dave", UserRole::User).context;
+    context.context_type = ContextType::Personal;
+    let dave = TenantSession::new(context);
+
+    let result = tools
+        .connect
+        .handle(&dave, connect_args("team", "token", "organization"))
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+    let listed = tools.list.handle(&dave, json!({})).await.unwrap();
+    assert_eq!(listed["organization_connections"], json!([]));
+}
@@ -0,0 +1,204 @@
+// Unit tests for changing a registered integration's config with integration_update
+// Servers are local `sh` processes and stores are in memory; no AWS access required
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, DirectKvStore, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::integrations::IntegrationUpdateHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType,
+    MCPServerUpdate, RegistryError, UpdateOutcome,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-update-user";
+
+/// An MCP server with a `greet` tool answering with `$GREETING`
+fn greeter_config() -> MCPServerConfig {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools =
+        r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"greet","inputSchema":{}}]}}"#;
+    let call = r#"read l; echo '{"jsonrpc":"2.0","id":3,"result":{"content":[{"type":"text","text":"'"$GREETING"'"}]}}';"#;
+    MCPServerConfig {
+        id: "greeter".to_string(),
+        name: "Greeter".to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!(
+                    "read l; echo '{}'; read l; read l; echo '{}'; {} cat >/dev/null",
+                    initialized, tools, call
+                ),
+            ],
+        },
+        env: HashMap::from([("GREETING".to_string(), "hello".to_string())]),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+async fn registry(store: Arc<MemoryKvStore>) -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(store)
+        .with_secret_store(Arc::new(MemorySecretStore::new()));
+    registry
+        .register_server(TENANT, greeter_config())
+        .await
+        .unwrap();
+    Arc::new(registry)
+}
+
+async fn stored_config(store: &MemoryKvStore) -> Value {
+    let value = store
+        .kv_get_direct(&format!("mcp-registry-{}:greeter", TENANT))
+        .await
+        .unwrap()
+        .unwrap();
+    serde_json::from_str(&value).unwrap()
+}
+
+async fn greet(registry: &MCPServerRegistry) -> String {
+    let result = registry
+        .execute_tool(TENANT, "greeter", "greet", json!({}))
+        .await
+        .unwrap();
+    result["content"][0]["text"].as_str().unwrap().to_string()
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("update-user")
+        .tenant("update-tenant")
+        .organization_id("update-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_update_of_disconnected_server_only_stores_config() {
+    let store = Arc::new(MemoryKvStore::new());
+    let registry = registry(store.clone()).await;
+
+    let update = MCPServerUpdate {
+        env: Some(HashMap::from([("GREETING".to_string(), "hi".to_string())])),
+        health_check_interval_secs: Some(15),
+        ..Default::default()
+    };
+    assert_eq!(
+        registry
+            .update_server(TENANT, "greeter", update)
+            .await
+            .unwrap(),
+        UpdateOutcome::Stored
+    );
+    let stored = stored_config(&store).await;
+    assert_eq!(stored["env"]["GREETING"], "hi");
+    assert_eq!(stored["health_check_interval_secs"], 15);
+    assert_eq!(
+        registry.list_servers(TENANT).await.unwrap()[0].status,
+        "Disconnected"
+    );
+
+    // A process deployment has no image tag; nothing is stored
+    let update = MCPServerUpdate {
+        tag: Some("2.0".to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        registry.update_server(TENANT, "greeter", update).await,
+        Err(RegistryError::InvalidConfig { field, .. }) if field == "tag"
+    ));
+    assert!(matches!(
+        registry
+            .update_server(TENANT, "missing", MCPServerUpdate::default())
+            .await,
+        Err(RegistryError::ServerNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_update_of_connected_server_reconnects_for_new_env() {
+    let store = Arc::new(MemoryKvStore::new());
+    let registry = registry(store.clone()).await;
+    registry
+        .connect_server(TENANT, "greeter", None)
+        .await
+        .unwrap();
+    assert_eq!(greet(&registry).await, "hello");
+    let handler = IntegrationUpdateHandler::new(registry.clone());
+
+    // Hot-reloadable: applied without a restart
+    let result = handler
+        .handle(
+            &session(),
+            json!({"service_id": "greeter", "health_check_interval_secs": 5, "auto_reconnect": true}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["applied"], "applied_in_place");
+
+    let result = handler
+        .handle(
+            &session(),
+            json!({"service_id": "greeter", "env": {"GREETING": "howdy"}}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["applied"], "reconnected");
+    assert_eq!(greet(&registry).await, "howdy");
+
+    let stored = stored_config(&store).await;
+    assert_eq!(stored["env"]["GREETING"], "howdy");
+    assert_eq!(stored["health_check_interval_secs"], 5);
+    assert_eq!(stored["auto_reconnect"], true);
+
+    assert!(matches!(
+        handler
+            .handle(
+                &session(),
+                json!({"service_id": "greeter", "image": "acme/x"})
+            )
+            .await,
+        Err(HandlerError::InvalidArguments(_))
+    ));
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_concurrent_updates_are_serialized() {
+    let store = Arc::new(MemoryKvStore::new());
+    let registry = registry(store.clone()).await;
+
+    let updates = (0..8).map(|i| {
+        let registry = registry.clone();
+        tokio::spawn(async move {
+            let mut update = MCPServerUpdate::default();
+            match i % 2 {
+                0 => update.name = Some(format!("Greeter {}", i)),
+                _ => update.description = Some(format!("revision {}", i)),
+            }
+            registry.update_server(TENANT, "greeter", update).await
+        })
+    });
+    for update in futures::future::join_all(updates).await {
+        assert_eq!(update.unwrap().unwrap(), UpdateOutcome::Stored);
+    }
+
+    // No update overwrote another's field with a stale value
+    let stored = stored_config(&store).await;
+    assert!(stored["name"].as_str().unwrap().starts_with("Greeter "));
+    assert!(stored["description"]
+        .as_str()
+        .unwrap()
+        .starts_with("revision "));
+}
@@ -0,0 +1,192 @@
+// Unit tests for internal KV keys: id validation, the key builders and the integration
+// handlers rejecting ids that could reach another tenant's records. In-memory backend
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::internal_keys::{
+    analytics_cache_key, analytics_cache_prefix, connection_key, connection_prefix, credential_key,
+    legacy_connection_key, legacy_connection_prefix, org_connection_key, org_connection_prefix,
+    registry_key, registry_prefix, tenant_connection_prefix, validate_credential_field,
+    validate_id,
+};
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::{validate_direct_key, AwsError, DirectKvStore};
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{TenantSession, UserRole};
+
+fn admin(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id).role(UserRole::Admin).session()
+}
+
+fn register_args(service_id: &str) -> Value {
+    json!({
+        "service_id": service_id,
+        "name": "Custom",
+        "description": "local process",
+        "category": "test",
+        "command": "true",
+        "auth_method": "none",
+        "configuration_schema": [],
+        "capabilities": []
+    })
+}
+
+#[test]
+fn test_ids_are_lowercase_slugs() {
+    for id in ["github", "github-work", "mail_2", "a", &"x".repeat(64)] {
+        assert!(validate_id("service_id", id).is_ok(), "{} was rejected", id);
+    }
+    for id in [
+        "",
+        "../personal-bob",
+        "github@work",
+        "github.tool",
+        "GitHub",
+        "git hub",
+        "github:notes",
+        &"x".repeat(65),
+    ] {
+        assert!(
+            validate_id("service_id", id).is_err(),
+            "{:?} was allowed",
+            id
+        );
+    }
+
+    assert!(validate_credential_field("GITHUB_TOKEN").is_ok());
+    assert!(validate_credential_field("api_key").is_ok());
+    // Only characters an environment variable name can have
+    assert!(validate_credential_field("token-x").is_err());
+    assert!(validate_credential_field("").is_err());
+}
+
+#[test]
+fn test_builders_keep_ids_under_their_prefix() {
+    assert_eq!(
+        registry_key("personal-alice", "github"),
+        "mcp-registry-personal-alice:github"
+    );
+    assert!(
+        registry_key("personal-alice", "github").starts_with(&registry_prefix("personal-alice"))
+    );
+    assert_eq!(
+        credential_key("personal-alice", "github", "GITHUB_TOKEN"),
+        "mcp-credential-personal-alice:github:GITHUB_TOKEN"
+    );
+    assert_eq!(
+        connection_key("acme-tenant", "alice", "github", "work"),
+        "connection-acme-tenant:alice:github:work"
+    );
+    assert!(connection_key("acme-tenant", "alice", "github", "work")
+        .starts_with(&connection_prefix("acme-tenant", "alice")));
+    assert!(connection_prefix("acme-tenant", "alice")
+        .starts_with(&tenant_connection_prefix("acme-tenant")));
+    assert_eq!(
+        legacy_connection_key("alice", "github", "work"),
+        "user-alice:integration:github:work"
+    );
+    assert!(legacy_connection_key("alice", "github", "work")
+        .starts_with(&legacy_connection_prefix("alice")));
+
+    // Every key the builders make from valid ids is an internal key
+    for key in [
+        registry_key("org-acme", "github@work"),
+        credential_key("org-acme", "github", ""),
+        connection_key("acme-tenant", "alice@example.com", "github", "default"),
+        legacy_connection_key("alice@example.com", "github", "default"),
+    ] {
+        assert!(validate_direct_key(&key).is_ok(), "{} was rejected", key);
+    }
+}
+
+#[test]
+fn test_keys_of_different_contexts_never_collide() {
+    // Context and user ids may contain '-', so with '-' separators these pairs used to
+    // build the same key, and one context's prefix listed the other's records
+    assert_ne!(
+        registry_key("personal-alice", "github-x"),
+        registry_key("personal-alice-github", "x")
+    );
+    assert!(
+        !registry_key("personal-alice-github", "x").starts_with(&registry_prefix("personal-alice"))
+    );
+    assert_ne!(
+        credential_key("org-acme", "github", "TOKEN"),
+        credential_key("org-acme-github", "", "TOKEN")
+    );
+    assert!(!credential_key("org-acme-labs", "jira", "")
+        .starts_with(&credential_key("org-acme", "", "")));
+    assert_ne!(
+        connection_key("acme", "alice", "github-work", "default"),
+        connection_key("acme", "alice-github", "work", "default")
+    );
+    assert!(!connection_key("acme", "alice-bob", "github", "work")
+        .starts_with(&connection_prefix("acme", "alice")));
+    assert!(!connection_key("acme-labs", "alice", "github", "work")
+        .starts_with(&tenant_connection_prefix("acme")));
+    assert!(!legacy_connection_key("alice-bob", "github", "work")
+        .starts_with(&legacy_connection_prefix("alice")));
+    assert!(!org_connection_key("acme-labs", "github", "shared")
+        .starts_with(&org_connection_prefix("acme")));
+    assert!(!analytics_cache_key("user-alice-bob", "24h", "now")
+        .starts_with(&analytics_cache_prefix("user-alice")));
+}
+
+#[test]
+fn test_direct_keys_with_unsafe_characters_are_rejected() {
+    for key in [
+        "integration-../personal-bob",
+        "integration-github/../../org-acme",
+        "user-alice:integration:github work",
+        "mcp-registry-personal-alice:github\n",
+        "analytics-user-alice:24h:now\u{0}",
+    ] {
+        assert!(
+            matches!(validate_direct_key(key), Err(AwsError::InvalidKey(_))),
+            "{:?} was allowed",
+            key
+        );
+    }
+    assert!(validate_direct_key("analytics-user-alice:2024-01-01T00:00:00+00:00:now").is_ok());
+}
+
+#[tokio::test]
+async fn test_integration_handlers_reject_unsafe_ids() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await;
+    let alice = admin("alice");
+
+    // These ids used to be stored as given: a path-like service id, and one whose '@'
+    // made it the same instance as the github service's "work" connection
+    for (tool, args) in [
+        ("integration_register", register_args("../personal-bob")),
+        ("integration_register", register_args("github@work")),
+        ("integration_connect", json!({"service_id": "github@work"})),
+        (
+            "integration_connect",
+            json!({"service_id": "github", "connection_id": "Work"}),
+        ),
+        (
+            "integration_connect",
+            json!({"service_id": "github", "credentials": {"token-x": "secret"}}),
+        ),
+        (
+            "integration_disconnect",
+            json!({"service_id": "../personal-bob"}),
+        ),
+    ] {
+        let result = registry.handle_tool_call(&alice, tool, args.clone()).await;
+        assert!(
+            matches!(&result, Err(HandlerError::InvalidArguments(message))
+                if message.contains("must be 1-64")),
+            "{} {} was not rejected: {:?}",
+            tool,
+            args,
+            result
+        );
+    }
+    assert!(aws.kv_list("integration-").await.unwrap().is_empty());
+    assert!(aws.kv_list("user-alice-").await.unwrap().is_empty());
+}
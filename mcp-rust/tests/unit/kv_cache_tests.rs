@@ -0,0 +1,169 @@
+// Unit tests for the kv_get read cache: invalidation on write, TTL expiry, LRU eviction
+// and tenant isolation. Runs on paused time over the in-memory backend; no AWS access required
+
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::kv_cache::KvCache;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::transactions::KvWriteOp;
+use mcp_rust::aws::AwsApi;
+use mcp_rust::handlers::{Handler, KvGetHandler};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+const TTL: Duration = Duration::from_secs(5);
+
+fn session(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id)
+        .permissions(vec![Permission::ReadKV, Permission::WriteKV])
+        .session()
+}
+
+fn cached_backend(capacity: usize) -> MemoryAwsService {
+    MemoryAwsService::new().with_kv_cache(KvCache::new(capacity, TTL))
+}
+
+/// (hits, misses) so far
+fn counts(aws: &MemoryAwsService) -> (u64, u64) {
+    let stats = aws.kv_cache_stats().unwrap();
+    (stats.hits, stats.misses)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_writes_invalidate_cached_values() {
+    let aws = cached_backend(16);
+    let alice = session("alice");
+    aws.kv_set(&alice, "config", "v1", None, false)
+        .await
+        .unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(
+            aws.kv_get(&alice, "config").await.unwrap().as_deref(),
+            Some("v1")
+        );
+    }
+    assert_eq!(counts(&aws), (2, 1));
+
+    aws.kv_set(&alice, "config", "v2", None, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        aws.kv_get(&alice, "config").await.unwrap().as_deref(),
+        Some("v2")
+    );
+
+    aws.kv_transact_write(
+        &alice,
+        &[KvWriteOp::Delete {
+            key: "config".to_string(),
+            condition: None,
+        }],
+    )
+    .await
+    .unwrap();
+    assert_eq!(aws.kv_get(&alice, "config").await.unwrap(), None);
+    assert_eq!(counts(&aws), (2, 3));
+
+    // Missing keys are cached too, until written
+    assert_eq!(aws.kv_get(&alice, "config").await.unwrap(), None);
+    assert_eq!(counts(&aws), (3, 3));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_entries_expire_after_ttl() {
+    let cache = KvCache::new(16, TTL);
+    let ticket = cache.ticket();
+    cache.insert("user:alice:config", Some("v1".to_string()), ticket);
+
+    tokio::time::advance(TTL - Duration::from_millis(1)).await;
+    assert_eq!(cache.get("user:alice:config"), Some(Some("v1".to_string())));
+
+    tokio::time::advance(Duration::from_millis(1)).await;
+    assert_eq!(cache.get("user:alice:config"), None);
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 0));
+    assert_eq!(stats.ttl_ms, 5000);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_least_recently_used_entry_is_evicted() {
+    let cache = KvCache::new(2, TTL);
+    for key in ["a", "b"] {
+        cache.insert(key, Some(key.to_string()), cache.ticket());
+    }
+    // `a` is now more recent than `b`
+    assert!(cache.get("a").is_some());
+    cache.insert("c", Some("c".to_string()), cache.ticket());
+
+    assert!(cache.get("a").is_some());
+    assert!(cache.get("b").is_none());
+    assert!(cache.get("c").is_some());
+    assert_eq!(cache.stats().entries, 2);
+
+    // A read that raced a write isn't cached
+    let ticket = cache.ticket();
+    cache.invalidate("d");
+    cache.insert("d", Some("stale".to_string()), ticket);
+    assert!(cache.get("d").is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_entries_are_per_tenant() {
+    let aws = cached_backend(16);
+    let alice = session("alice");
+    let bob = session("bob");
+
+    // Bob's cached miss doesn't hide Alice's value, nor hers his
+    assert_eq!(aws.kv_get(&bob, "config").await.unwrap(), None);
+    aws.kv_set(&alice, "config", "alice", None, false)
+        .await
+        .unwrap();
+    assert_eq!(
+        aws.kv_get(&alice, "config").await.unwrap().as_deref(),
+        Some("alice")
+    );
+    assert_eq!(aws.kv_get(&bob, "config").await.unwrap(), None);
+    assert_eq!(aws.kv_cache_stats().unwrap().entries, 2);
+
+    // Alice's writes leave Bob's entry alone
+    aws.kv_set(&bob, "config", "bob", None, false)
+        .await
+        .unwrap();
+    aws.kv_get(&bob, "config").await.unwrap();
+    aws.kv_set(&alice, "config", "alice-2", None, false)
+        .await
+        .unwrap();
+    let hits = counts(&aws).0;
+    assert_eq!(
+        aws.kv_get(&bob, "config").await.unwrap().as_deref(),
+        Some("bob")
+    );
+    assert_eq!(counts(&aws).0, hits + 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_fresh_reads_skip_the_cache() {
+    let aws = Arc::new(cached_backend(16));
+    let alice = session("alice");
+    aws.kv_set(&alice, "config", "v1", None, false)
+        .await
+        .unwrap();
+    let handler = KvGetHandler::new(aws.clone());
+
+    handler
+        .handle(&alice, json!({"key": "config"}))
+        .await
+        .unwrap();
+    let value = handler
+        .handle(&alice, json!({"key": "config", "fresh": true}))
+        .await
+        .unwrap();
+    assert_eq!(value["value"], "v1");
+    assert_eq!(counts(&aws), (0, 1));
+
+    // Without a cache, `kv_cache_stats` reports nothing
+    assert!(MemoryAwsService::new().kv_cache_stats().is_none());
+}
@@ -0,0 +1,199 @@
+// Unit tests for atomic multi-key KV writes and the rule + alert creation that uses them
+// AwsApi::kv_transact_write over the in-memory backend; no AWS access required
+
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::transactions::{ItemCondition, KvWriteOp, MAX_TRANSACT_OPS};
+use mcp_rust::aws::{AwsApi, AwsError};
+use mcp_rust::handlers::{
+    EventsCreateRuleHandler, EventsHealthCheckHandler, Handler, HandlerError,
+};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id)
+        .permissions(vec![
+            Permission::ReadKV,
+            Permission::WriteKV,
+            Permission::SendEvents,
+        ])
+        .session()
+}
+
+fn put(key: &str, value: &str) -> KvWriteOp {
+    KvWriteOp::Put {
+        key: key.to_string(),
+        value: value.to_string(),
+        ttl_hours: None,
+        condition: None,
+    }
+}
+
+#[tokio::test]
+async fn test_transaction_applies_every_write() {
+    let aws = MemoryAwsService::new();
+    let alice = session("alice");
+    aws.kv_set(&alice, "usage", "1", None, false).await.unwrap();
+    aws.kv_set(&alice, "draft", "x", None, false).await.unwrap();
+
+    aws.kv_transact_write(
+        &alice,
+        &[
+            KvWriteOp::Update {
+                key: "usage".to_string(),
+                value: "2".to_string(),
+            },
+            KvWriteOp::Put {
+                key: "report".to_string(),
+                value: "{}".to_string(),
+                ttl_hours: Some(24),
+                condition: Some(ItemCondition::Absent),
+            },
+            KvWriteOp::Delete {
+                key: "draft".to_string(),
+                condition: Some(ItemCondition::Exists),
+            },
+        ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        aws.kv_get(&alice, "usage").await.unwrap().as_deref(),
+        Some("2")
+    );
+    assert_eq!(
+        aws.kv_get(&alice, "report").await.unwrap().as_deref(),
+        Some("{}")
+    );
+    assert_eq!(aws.kv_get(&alice, "draft").await.unwrap(), None);
+
+    // Keys are the tenant's own
+    assert_eq!(aws.kv_get(&session("bob"), "usage").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_canceled_transaction_writes_nothing() {
+    let aws = MemoryAwsService::new();
+    let alice = session("alice");
+    aws.kv_set(&alice, "report", "old", None, false)
+        .await
+        .unwrap();
+
+    let result = aws
+        .kv_transact_write(
+            &alice,
+            &[
+                put("usage", "1"),
+                KvWriteOp::ConditionCheck {
+                    key: "plan".to_string(),
+                    condition: ItemCondition::Exists,
+                },
+                KvWriteOp::Put {
+                    key: "report".to_string(),
+                    value: "new".to_string(),
+                    ttl_hours: None,
+                    condition: Some(ItemCondition::Absent),
+                },
+            ],
+        )
+        .await;
+    match result {
+        Err(AwsError::TransactionCanceled(failures)) => {
+            let failed: Vec<(usize, &str)> = failures
+                .iter()
+                .map(|failure| (failure.index, failure.operation.as_str()))
+                .collect();
+            assert_eq!(
+                failed,
+                vec![(1, "condition check \"plan\""), (2, "put \"report\"")]
+            );
+            assert!(failures
+                .iter()
+                .all(|failure| failure.code == "ConditionalCheckFailed"));
+        }
+        other => panic!("expected TransactionCanceled, got {:?}", other),
+    }
+
+    assert_eq!(aws.kv_get(&alice, "usage").await.unwrap(), None);
+    assert_eq!(
+        aws.kv_get(&alice, "report").await.unwrap().as_deref(),
+        Some("old")
+    );
+}
+
+#[tokio::test]
+async fn test_transaction_limits() {
+    let aws = MemoryAwsService::new();
+    let alice = session("alice");
+
+    let too_many: Vec<KvWriteOp> = (0..=MAX_TRANSACT_OPS)
+        .map(|i| put(&format!("key-{}", i), "v"))
+        .collect();
+    for ops in [vec![], too_many, vec![put("usage", "1"), put("usage", "2")]] {
+        assert!(
+            matches!(
+                aws.kv_transact_write(&alice, &ops).await,
+                Err(AwsError::InvalidTransaction(_))
+            ),
+            "{} ops",
+            ops.len()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_rule_created_with_its_alert() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let alice = session("alice");
+    let handler = EventsCreateRuleHandler::new(aws.clone());
+
+    let rule = handler
+        .handle(
+            &alice,
+            json!({
+                "name": "deploy-failures",
+                "pattern": {"detailType": ["deploy.failed"]},
+                "alert": {
+                    "notificationMethod": "sns",
+                    "snsTopicArn": "arn:aws:sns:us-west-2:123456789012:alerts"
+                }
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(rule["alert"]["ruleId"], rule["ruleId"]);
+    assert_eq!(rule["alert"]["name"], "deploy-failures");
+    assert_eq!(rule["alert"]["enabled"], true);
+
+    let health = EventsHealthCheckHandler::new(aws.clone())
+        .handle(&alice, json!({}))
+        .await
+        .unwrap();
+    assert_eq!(health["checks"]["rulesTable"]["count"], 1);
+    assert_eq!(health["checks"]["subscriptionsTable"]["count"], 1);
+
+    // An invalid alert is rejected before anything is stored
+    let result = handler
+        .handle(
+            &alice,
+            json!({
+                "name": "deploy-failures-eu",
+                "pattern": {"detailType": ["deploy.failed"]},
+                "alert": {
+                    "notificationMethod": "sns",
+                    "snsTopicArn": "arn:aws:sns:eu-west-1:123456789012:alerts"
+                }
+            }),
+        )
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+    let health = EventsHealthCheckHandler::new(aws)
+        .handle(&alice, json!({}))
+        .await
+        .unwrap();
+    assert_eq!(health["checks"]["rulesTable"]["count"], 1);
+}
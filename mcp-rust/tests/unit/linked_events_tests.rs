@@ -0,0 +1,186 @@
+// Unit tests for events_send_linked and the linkId filter of events_query
+// HandlerRegistry over the in-memory backend, which can fail chosen entries; no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::events_linked::{LINK_ABORTED_DETAIL_TYPE, MAX_LINKED_EVENTS};
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session() -> TenantSession {
+    TestTenant::user("alice")
+        .permissions(vec![Permission::SendEvents])
+        .session()
+}
+
+async fn registry(aws: &Arc<MemoryAwsService>) -> HandlerRegistry {
+    HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await
+}
+
+fn order_events() -> Value {
+    json!({"events": [
+        {"detailType": "order.created", "detail": {"orderId": "o-1"}},
+        {"detailType": "order.line_added", "detail": {"orderId": "o-1", "sku": "a"}},
+        {"detailType": "order.line_added", "detail": {"orderId": "o-1", "sku": "b"}},
+        {"detailType": "order.shipped", "detail": {"orderId": "o-1"}}
+    ]})
+}
+
+async fn linked_events(
+    registry: &HandlerRegistry,
+    session: &TenantSession,
+    link_id: &str,
+) -> Vec<Value> {
+    let result = registry
+        .handle_tool_call(
+            session,
+            "events_query",
+            json!({"linkId": link_id, "sortOrder": "asc"}),
+        )
+        .await
+        .unwrap();
+    result["events"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn test_linked_events_share_a_link_id() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = session();
+
+    let result = registry
+        .handle_tool_call(&alice, "events_send_linked", order_events())
+        .await
+        .unwrap();
+    assert_eq!(result["success"], true);
+    assert_eq!(result["count"], 4);
+    let link_id = result["linkId"].as_str().unwrap();
+
+    let events = linked_events(&registry, &alice, link_id).await;
+    assert_eq!(events.len(), 4);
+    let mut indexes: Vec<u64> = events
+        .iter()
+        .map(|event| {
+            assert_eq!(event["linkId"], link_id);
+            assert_eq!(event["detail"]["linkSize"], 4);
+            event["detail"]["linkIndex"].as_u64().unwrap()
+        })
+        .collect();
+    indexes.sort();
+    assert_eq!(indexes, vec![0, 1, 2, 3]);
+
+    // Other links, and unlinked events, aren't returned
+    registry
+        .handle_tool_call(
+            &alice,
+            "events_send",
+            json!({"events": [{"detailType": "order.created", "detail": {}}], "buffered": false}),
+        )
+        .await
+        .unwrap();
+    let other = registry
+        .handle_tool_call(&alice, "events_send_linked", order_events())
+        .await
+        .unwrap();
+    assert_ne!(other["linkId"], link_id);
+    assert_eq!(linked_events(&registry, &alice, link_id).await.len(), 4);
+}
+
+#[tokio::test]
+async fn test_partial_failure_aborts_the_published_events() {
+    let aws = Arc::new(MemoryAwsService::new());
+    aws.reject_events("order.shipped");
+    let registry = registry(&aws).await;
+    let alice = session();
+
+    let result = registry
+        .handle_tool_call(&alice, "events_send_linked", order_events())
+        .await
+        .unwrap();
+    assert_eq!(result["success"], false);
+    assert_eq!(result["aborted"], true);
+    assert_eq!(result["count"], 4);
+    assert_eq!(result["succeeded"], json!([0, 1, 2]));
+    let failed = result["failed"].as_array().unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0]["index"], 3);
+    assert_eq!(failed[0]["detailType"], "order.shipped");
+    assert!(failed[0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("InternalFailure"));
+    assert_eq!(result["abortRecords"], json!({"sent": 3, "unsent": []}));
+
+    // The published events, each followed by a record cancelling it
+    let link_id = result["linkId"].as_str().unwrap();
+    let events = linked_events(&registry, &alice, link_id).await;
+    assert_eq!(events.len(), 6);
+    let aborts: Vec<&Value> = events
+        .iter()
+        .filter(|event| event["detailType"] == LINK_ABORTED_DETAIL_TYPE)
+        .collect();
+    assert_eq!(aborts.len(), 3);
+    for (index, abort) in aborts.iter().enumerate() {
+        assert_eq!(abort["detail"]["linkId"], link_id);
+        assert_eq!(abort["detail"]["abortedIndex"], index);
+        assert_eq!(abort["detail"]["failed"], json!([3]));
+    }
+    assert_eq!(aborts[0]["detail"]["abortedDetailType"], "order.created");
+    assert!(events
+        .iter()
+        .all(|event| event["detailType"] != "order.shipped"));
+}
+
+#[tokio::test]
+async fn test_nothing_to_abort_when_every_entry_fails() {
+    let aws = Arc::new(MemoryAwsService::new());
+    aws.reject_events("order.created");
+    let registry = registry(&aws).await;
+    let alice = session();
+
+    let result = registry
+        .handle_tool_call(
+            &alice,
+            "events_send_linked",
+            json!({"events": [{"detailType": "order.created", "detail": {}}]}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["success"], false);
+    assert_eq!(result["succeeded"], json!([]));
+    assert_eq!(result["abortRecords"]["sent"], 0);
+    let link_id = result["linkId"].as_str().unwrap();
+    assert!(linked_events(&registry, &alice, link_id).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_linked_send_rejects_bad_batches() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = session();
+
+    let too_many: Vec<Value> = (0..=MAX_LINKED_EVENTS)
+        .map(|_| json!({"detailType": "demo", "detail": {}}))
+        .collect();
+    for arguments in [
+        json!({"events": []}),
+        json!({"events": too_many}),
+        json!({"events": [{"detailType": "demo", "detail": "not an object"}]}),
+        json!({"events": [{"detailType": "demo", "detail": {"linkId": "mine"}}]}),
+    ] {
+        let rejected = registry
+            .handle_tool_call(&alice, "events_send_linked", arguments.clone())
+            .await;
+        assert!(
+            matches!(
+                rejected,
+                Err(HandlerError::InvalidArguments(_) | HandlerError::SchemaViolation { .. })
+            ),
+            "{} was accepted",
+            arguments
+        );
+    }
+}
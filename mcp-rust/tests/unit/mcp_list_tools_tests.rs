@@ -0,0 +1,156 @@
+// Unit tests for mcp_list_tools and the merged downstream tool catalog
+// A Lambda-deployed server is stubbed; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::mcp_proxy::{downstream_tool_schemas, MCPListToolsHandler};
+use mcp_rust::handlers::Handler;
+use mcp_rust::registry::lambda::{LambdaInvocation, LambdaInvoker};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-catalog-user";
+
+fn search_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {"query": {"type": "string", "description": "Search terms"}},
+        "required": ["query"]
+    })
+}
+
+/// An MCP server exposing one `search` tool
+struct SearchFunction;
+
+#[async_trait]
+impl LambdaInvoker for SearchFunction {
+    async fn invoke(
+        &self,
+        _region: &str,
+        _function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let request: Value = serde_json::from_slice(&payload).unwrap();
+        let result = json!({"tools": [{
+            "name": "search",
+            "description": "Search issues",
+            "inputSchema": search_schema()
+        }]});
+        let reply = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+        Ok(LambdaInvocation {
+            function_error: None,
+            payload: reply.to_string().into_bytes(),
+        })
+    }
+}
+
+fn lambda_config(id: &str) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: format!("{} server", id),
+        description: "lambda integration".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Lambda {
+            function_name: format!("{}-mcp", id),
+            region: "us-east-1".to_string(),
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+/// "github" connected, "jira" registered but never connected
+async fn registry() -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_lambda_invoker(Arc::new(SearchFunction));
+    for id in ["github", "jira"] {
+        registry
+            .register_server(TENANT, lambda_config(id))
+            .await
+            .unwrap();
+    }
+    registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap();
+    Arc::new(registry)
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("catalog-user")
+        .tenant("catalog-tenant")
+        .organization_id("catalog-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_lists_real_tools_with_input_schemas() {
+    let handler = MCPListToolsHandler::new(registry().await);
+
+    let result = handler.handle(&session(), json!({})).await.unwrap();
+
+    let tools = result["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "github.search");
+    assert_eq!(tools[0]["description"], "Search issues");
+    assert_eq!(tools[0]["input_schema"], search_schema());
+    assert_eq!(tools[0]["server_id"], "github");
+
+    assert_eq!(
+        result["servers"],
+        json!([
+            {"server_id": "github", "server_name": "github server", "status": "Connected", "tools": ["github.search"]},
+            {"server_id": "jira", "server_name": "jira server", "status": "Disconnected", "tools": []}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_server_filter() {
+    let handler = MCPListToolsHandler::new(registry().await);
+
+    let jira = handler
+        .handle(&session(), json!({"server_id": "jira"}))
+        .await
+        .unwrap();
+    assert_eq!(jira["tools"], json!([]));
+    assert_eq!(jira["servers"].as_array().unwrap().len(), 1);
+    assert_eq!(jira["servers"][0]["status"], "Disconnected");
+
+    let github = handler
+        .handle(&session(), json!({"server_id": "github"}))
+        .await
+        .unwrap();
+    assert_eq!(github["tools"].as_array().unwrap().len(), 1);
+    assert_eq!(github["servers"][0]["server_id"], "github");
+}
+
+#[tokio::test]
+async fn test_merged_catalog_entries() {
+    let registry = registry().await;
+
+    let tools = downstream_tool_schemas(&registry, TENANT).await.unwrap();
+
+    assert_eq!(
+        tools,
+        vec![json!({
+            "name": "github.search",
+            "description": "[github server] Search issues",
+            "inputSchema": search_schema()
+        })]
+    );
+}
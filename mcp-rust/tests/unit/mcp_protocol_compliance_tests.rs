@@ -13,7 +13,7 @@ async fn test_notification_handling_no_response() {
     std::env::set_var("DEFAULT_USER_ID", "test");
 
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     // Notification - no ID field, should return None (no response)
     let notification_json = json!({
@@ -35,7 +35,7 @@ async fn test_request_handling_with_response() {
     std::env::set_var("DEFAULT_USER_ID", "test");
 
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     // Request - has ID field, should return Some(response)
     let request_json = json!({
@@ -63,7 +63,7 @@ async fn test_request_handling_with_response() {
 #[tokio::test]
 async fn test_protocol_version_2025_06_18() {
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     let request_json = json!({
         "jsonrpc": "2.0",
@@ -90,7 +90,7 @@ async fn test_protocol_version_2025_06_18() {
 #[tokio::test]
 async fn test_mcp_sdk_client_handshake_sequence() {
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     // Step 1: Client sends initialize request
     let init_request = json!({
@@ -128,7 +128,7 @@ async fn test_mcp_sdk_client_handshake_sequence() {
 #[tokio::test]
 async fn test_malformed_json_error_response() {
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     let malformed_json = "{ invalid json";
     let response = server.handle_request(malformed_json).await;
@@ -144,7 +144,7 @@ async fn test_malformed_json_error_response() {
 #[tokio::test]
 async fn test_notification_with_different_methods() {
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     let notifications = vec![
         "notifications/initialized",
@@ -172,7 +172,7 @@ async fn test_notification_with_different_methods() {
 #[tokio::test]
 async fn test_request_id_types_string_and_number() {
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     // Test string ID
     let string_id_request = json!({
@@ -214,7 +214,7 @@ async fn test_request_id_types_string_and_number() {
 #[tokio::test]
 async fn test_json_rpc_response_schema_compliance() {
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     let request = json!({
         "jsonrpc": "2.0",
@@ -241,7 +241,7 @@ async fn test_json_rpc_response_schema_compliance() {
 #[tokio::test]
 async fn test_concurrent_request_and_notification_handling() {
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = Arc::new(MCPServer::new(tenant_manager).await.unwrap());
+    let server = Arc::new(MCPServer::new(tenant_manager, None).await.unwrap());
 
     let mut handles = Vec::new();
 
@@ -295,7 +295,7 @@ async fn test_error_response_preserves_request_id() {
     std::env::set_var("DEFAULT_USER_ID", "test");
 
     let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
-    let server = MCPServer::new(tenant_manager).await.unwrap();
+    let server = MCPServer::new(tenant_manager, None).await.unwrap();
 
     let invalid_method_request = json!({
         "jsonrpc": "2.0",
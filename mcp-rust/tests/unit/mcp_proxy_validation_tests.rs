@@ -0,0 +1,181 @@
+// Unit tests for checking proxied tool arguments against the downstream input schema
+// A Lambda-deployed server is stubbed; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::mcp_proxy::MCPProxyHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::lambda::{LambdaInvocation, LambdaInvoker};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-schema-user";
+
+/// An MCP server whose tools declare input schemas; counts the calls that reach it
+#[derive(Default)]
+struct SchemaFunction {
+    calls: AtomicUsize,
+}
+
+#[async_trait]
+impl LambdaInvoker for SchemaFunction {
+    async fn invoke(
+        &self,
+        _region: &str,
+        _function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let request: Value = serde_json::from_slice(&payload).unwrap();
+        let reply = match request["method"].as_str() {
+            Some("tools/list") => json!({"jsonrpc": "2.0", "id": request["id"], "result": {
+                "tools": [
+                    {"name": "create_issue", "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "repo": {"type": "string"},
+                            "labels": {"type": "array", "items": {"$ref": "#/$defs/label"}}
+                        },
+                        "required": ["repo"],
+                        "$defs": {"label": {"type": "string", "maxLength": 10}}
+                    }},
+                    {"name": "remote_ref", "inputSchema": {
+                        "$ref": "https://schemas.example.com/unreachable.json"
+                    }}
+                ]
+            }}),
+            _ => {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                json!({"jsonrpc": "2.0", "id": request["id"], "result": {"content": []}})
+            }
+        };
+        Ok(LambdaInvocation {
+            function_error: None,
+            payload: reply.to_string().into_bytes(),
+        })
+    }
+}
+
+async fn proxy(function: Arc<SchemaFunction>) -> MCPProxyHandler {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_lambda_invoker(function);
+    let config = MCPServerConfig {
+        id: "github".to_string(),
+        name: "GitHub".to_string(),
+        description: "lambda integration".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Lambda {
+            function_name: "github-mcp".to_string(),
+            region: "us-east-1".to_string(),
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    };
+    registry.register_server(TENANT, config).await.unwrap();
+    registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap();
+    MCPProxyHandler::new(Arc::new(registry))
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("schema-user")
+        .tenant("schema-tenant")
+        .organization_id("schema-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_valid_arguments_are_forwarded() {
+    let function = Arc::new(SchemaFunction::default());
+    let proxy = proxy(function.clone()).await;
+
+    proxy
+        .handle(
+            &session(),
+            json!({
+                "tool_name": "github.create_issue",
+                "arguments": {"repo": "acme/api", "labels": ["bug"]}
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(function.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_invalid_arguments_are_rejected_with_their_pointer() {
+    let function = Arc::new(SchemaFunction::default());
+    let proxy = proxy(function.clone()).await;
+
+    for (arguments, pointer) in [
+        (
+            json!({"repo": "acme/api", "labels": ["bug", 7]}),
+            "/labels/1",
+        ),
+        (
+            json!({"repo": "acme/api", "labels": ["much-too-long"]}),
+            "/labels/0",
+        ),
+        (json!({"labels": []}), "/"),
+    ] {
+        match proxy
+            .handle(
+                &session(),
+                json!({"tool_name": "create_issue", "arguments": arguments}),
+            )
+            .await
+        {
+            Err(HandlerError::InvalidArguments(message)) => assert!(
+                message.contains(&format!("input schema at {}:", pointer)),
+                "{}",
+                message
+            ),
+            other => panic!("expected a schema violation, got {:?}", other),
+        }
+    }
+    assert_eq!(function.calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_skip_validation_and_unresolvable_refs() {
+    let function = Arc::new(SchemaFunction::default());
+    let proxy = proxy(function.clone()).await;
+
+    proxy
+        .handle(
+            &session(),
+            json!({
+                "tool_name": "github.create_issue",
+                "arguments": {"labels": [7]},
+                "skip_validation": true
+            }),
+        )
+        .await
+        .unwrap();
+
+    // A schema that can't be resolved is not enforced
+    proxy
+        .handle(
+            &session(),
+            json!({"tool_name": "github.remote_ref", "arguments": {"anything": true}}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(function.calls.load(Ordering::SeqCst), 2);
+}
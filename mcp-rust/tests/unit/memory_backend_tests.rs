@@ -0,0 +1,195 @@
+// Unit tests for running the built-in tools on the in-memory backend (MCP_BACKEND=memory)
+// HandlerRegistry and MCPServerRegistry over MemoryAwsService; no AWS access required
+
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::MCPServer;
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::{Permission, TenantManager, TenantSession};
+
+const TENANT: &str = "personal-demo-user";
+
+fn session(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id)
+        .organization_id("demo-org")
+        .permissions(vec![
+            Permission::ReadKV,
+            Permission::WriteKV,
+            Permission::ListArtifacts,
+            Permission::GetArtifacts,
+            Permission::PutArtifacts,
+            Permission::SendEvents,
+        ])
+        .session()
+}
+
+#[tokio::test]
+async fn test_builtin_tools_run_offline() {
+    let registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    let alice = session("alice");
+    let bob = session("bob");
+
+    registry
+        .handle_tool_call(&alice, "kv_set", json!({"key": "greeting", "value": "hi"}))
+        .await
+        .unwrap();
+    let value = registry
+        .handle_tool_call(&alice, "kv_get", json!({"key": "greeting"}))
+        .await
+        .unwrap();
+    assert_eq!(value, json!({"value": "hi"}));
+    // Keys are namespaced per tenant context, as in DynamoDB
+    let value = registry
+        .handle_tool_call(&bob, "kv_get", json!({"key": "greeting"}))
+        .await
+        .unwrap();
+    assert_eq!(value, json!({"value": null}));
+
+    registry
+        .handle_tool_call(
+            &alice,
+            "artifacts_put",
+            json!({"key": "notes/today.txt", "content": "aGVsbG8=", "content_type": "text/plain"}),
+        )
+        .await
+        .unwrap();
+    let artifact = registry
+        .handle_tool_call(&alice, "artifacts_get", json!({"key": "notes/today.txt"}))
+        .await
+        .unwrap();
+    assert_eq!(artifact["content"], "aGVsbG8=");
+    let listed = registry
+        .handle_tool_call(&alice, "artifacts_list", json!({"prefix": "notes/"}))
+        .await
+        .unwrap();
+    assert_eq!(listed, json!({"keys": ["notes/today.txt"]}));
+    let listed = registry
+        .handle_tool_call(&bob, "artifacts_list", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(listed, json!({"keys": []}));
+
+    registry
+        .handle_tool_call(
+            &alice,
+            "events_send",
+            json!({"detailType": "demo.started", "detail": {"priority": "high"}}),
+        )
+        .await
+        .unwrap();
+    let events = registry
+        .handle_tool_call(&alice, "events_query", json!({"userId": "alice"}))
+        .await
+        .unwrap();
+    assert_eq!(events["count"], 1);
+    let event = &events["events"][0];
+    assert_eq!(event["detailType"], "demo.started");
+    assert_eq!(event["source"], "mcp-rust");
+    assert_eq!(event["priority"], "high");
+    assert_eq!(event["detail"]["tenant_id"], "alice-tenant");
+}
+
+#[tokio::test]
+async fn test_registry_runs_on_the_memory_backend() {
+    let registry = MCPServerRegistry::new(Arc::new(MemoryAwsService::new()));
+    let config = MCPServerConfig {
+        id: "reports".to_string(),
+        name: "Reports".to_string(),
+        description: "reports over Lambda".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Lambda {
+            function_name: "reports-mcp".to_string(),
+            region: "us-east-1".to_string(),
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    };
+
+    // Configs and credentials are kept by the backend
+    registry.register_server(TENANT, config).await.unwrap();
+    let credentials = HashMap::from([("api_key".to_string(), "sk-demo".to_string())]);
+    registry
+        .store_credentials(TENANT, "reports", &credentials)
+        .await
+        .unwrap();
+    let servers = registry.list_servers(TENANT).await.unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0].id, "reports");
+
+    // There's no Lambda API to invoke the function with
+    match registry.connect_server(TENANT, "reports", None).await {
+        Err(RegistryError::ConnectionFailed(message)) => {
+            assert!(message.contains("in-memory backend"), "{}", message)
+        }
+        other => panic!("expected ConnectionFailed, got {:?}", other),
+    }
+}
+
+/// A server on its own in-memory registry
+async fn memory_server() -> MCPServer {
+    std::env::set_var("DEV_MODE", "true");
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    MCPServer::new(tenant_manager, Some(registry))
+        .await
+        .unwrap()
+}
+
+async fn call(
+    server: &MCPServer,
+    id: u64,
+    name: &str,
+    arguments: serde_json::Value,
+) -> serde_json::Value {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/call",
+        "tenant_id": "demo-tenant",
+        "user_id": "user-demo-123",
+        "params": {"name": name, "arguments": arguments}
+    });
+    let response = server.handle_request(&request.to_string()).await.unwrap();
+    serde_json::to_value(response).unwrap()
+}
+
+#[tokio::test]
+async fn test_server_dispatches_to_an_injected_registry() {
+    let server = memory_server().await;
+    let other = memory_server().await;
+
+    let set = call(
+        &server,
+        1,
+        "kv_set",
+        json!({"key": "greeting", "value": "hi"}),
+    )
+    .await;
+    assert!(set["error"].is_null(), "{}", set);
+    let got = call(&server, 2, "kv_get", json!({"key": "greeting"})).await;
+    assert_eq!(got["result"]["value"], "hi", "{}", got);
+
+    // Each server keeps its data in its own backend
+    let got = call(&other, 3, "kv_get", json!({"key": "greeting"})).await;
+    assert!(got["result"]["value"].is_null(), "{}", got);
+}
@@ -0,0 +1,240 @@
+// Unit tests for batching tool call counters into CloudWatch metric data
+// A recording sink stands in for PutMetricData; no AWS access required
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use mcp_rust::metrics::{
+    build_batch, CallCount, CallCounters, CallOutcome, MetricDatum, MetricsError, MetricsFlusher,
+    MetricsSink, ServerGauges, OTHER,
+};
+
+/// Keeps every request it's sent; fails while `failing` is set
+#[derive(Default)]
+struct RecordingSink {
+    requests: Mutex<Vec<(String, Vec<MetricDatum>)>>,
+    failing: AtomicBool,
+}
+
+#[async_trait]
+impl MetricsSink for RecordingSink {
+    async fn put_metric_data(
+        &self,
+        namespace: &str,
+        data: Vec<MetricDatum>,
+    ) -> Result<(), MetricsError> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(MetricsError::CloudWatch("throttled".to_string()));
+        }
+        self.requests
+            .lock()
+            .unwrap()
+            .push((namespace.to_string(), data));
+        Ok(())
+    }
+}
+
+fn count(calls: u64, errors: u64, rate_limited: u64) -> CallCount {
+    CallCount {
+        calls,
+        errors,
+        rate_limited,
+    }
+}
+
+/// The value of metric `name` with exactly `dimensions`
+fn value(data: &[MetricDatum], name: &str, dimensions: &[(&str, &str)]) -> Option<f64> {
+    data.iter()
+        .find(|datum| {
+            datum.name == name
+                && datum.dimensions.len() == dimensions.len()
+                && datum
+                    .dimensions
+                    .iter()
+                    .zip(dimensions)
+                    .all(|((n, v), (en, ev))| n == en && v == ev)
+        })
+        .map(|datum| datum.value)
+}
+
+#[test]
+fn test_batch_caps_tenant_and_tool_dimensions() {
+    let deltas = HashMap::from([
+        (("big".to_string(), "kv_get".to_string()), count(50, 2, 0)),
+        (("mid".to_string(), "kv_get".to_string()), count(20, 0, 3)),
+        (
+            ("small-1".to_string(), "kv_get".to_string()),
+            count(4, 1, 0),
+        ),
+        (
+            ("small-2".to_string(), "kv_get".to_string()),
+            count(3, 0, 0),
+        ),
+        (
+            ("small-2".to_string(), "rare_tool".to_string()),
+            count(1, 0, 0),
+        ),
+    ]);
+    let gauges = ServerGauges {
+        active_sessions: 7,
+        active_requests: 2,
+    };
+    let data = build_batch(&deltas, gauges, 2, 1);
+
+    let tenants: Vec<&str> = data
+        .iter()
+        .flat_map(|datum| &datum.dimensions)
+        .filter(|(name, _)| *name == "Tenant")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    assert!(
+        tenants.iter().all(|t| ["big", "mid", OTHER].contains(t)),
+        "{:?}",
+        tenants
+    );
+
+    assert_eq!(
+        value(&data, "ToolCalls", &[("Tenant", "big"), ("Tool", "kv_get")]),
+        Some(50.0)
+    );
+    assert_eq!(
+        value(
+            &data,
+            "ToolErrors",
+            &[("Tenant", "big"), ("Tool", "kv_get")]
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        value(
+            &data,
+            "RateLimitRejections",
+            &[("Tenant", "mid"), ("Tool", "kv_get")]
+        ),
+        Some(3.0)
+    );
+    // The small tenants share `other`, and the rarely used tool is `other` too
+    assert_eq!(
+        value(&data, "ToolCalls", &[("Tenant", OTHER), ("Tool", "kv_get")]),
+        Some(7.0)
+    );
+    assert_eq!(
+        value(
+            &data,
+            "ToolErrors",
+            &[("Tenant", OTHER), ("Tool", "kv_get")]
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        value(&data, "ToolCalls", &[("Tenant", OTHER), ("Tool", OTHER)]),
+        Some(1.0)
+    );
+    // Zero counts aren't sent
+    assert_eq!(
+        value(
+            &data,
+            "ToolErrors",
+            &[("Tenant", "mid"), ("Tool", "kv_get")]
+        ),
+        None
+    );
+
+    assert_eq!(value(&data, "ToolCalls", &[]), Some(78.0));
+    assert_eq!(value(&data, "ToolErrors", &[]), Some(3.0));
+    assert_eq!(value(&data, "RateLimitRejections", &[]), Some(3.0));
+    assert_eq!(value(&data, "ActiveSessions", &[]), Some(7.0));
+    assert_eq!(value(&data, "ActiveRequests", &[]), Some(2.0));
+}
+
+#[tokio::test]
+async fn test_flush_sends_counts_since_the_last_flush() {
+    let counters = Arc::new(CallCounters::new());
+    let sink = Arc::new(RecordingSink::default());
+    let mut flusher = MetricsFlusher::new(counters.clone(), sink.clone(), "AgentMesh/Test");
+
+    counters.record("acme", "kv_get", CallOutcome::Ok);
+    counters.record("acme", "kv_get", CallOutcome::Error);
+    counters.record("acme", "kv_set", CallOutcome::RateLimited);
+    flusher.flush(ServerGauges::default()).await.unwrap();
+
+    counters.record("acme", "kv_get", CallOutcome::Ok);
+    flusher.flush(ServerGauges::default()).await.unwrap();
+
+    let requests = sink.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].0, "AgentMesh/Test");
+    let (first, second) = (&requests[0].1, &requests[1].1);
+    assert_eq!(
+        value(
+            first,
+            "ToolCalls",
+            &[("Tenant", "acme"), ("Tool", "kv_get")]
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        value(
+            first,
+            "RateLimitRejections",
+            &[("Tenant", "acme"), ("Tool", "kv_set")]
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        value(
+            second,
+            "ToolCalls",
+            &[("Tenant", "acme"), ("Tool", "kv_get")]
+        ),
+        Some(1.0)
+    );
+    assert_eq!(value(second, "ToolErrors", &[]), None);
+    assert_eq!(value(second, "RateLimitRejections", &[]), None);
+
+    // server_stats keeps the totals since startup
+    assert_eq!(counters.by_tool()["kv_get"], count(3, 1, 0));
+}
+
+#[tokio::test]
+async fn test_failed_flush_keeps_counts_for_the_next_one() {
+    let counters = Arc::new(CallCounters::new());
+    let sink = Arc::new(RecordingSink::default());
+    let mut flusher = MetricsFlusher::new(counters.clone(), sink.clone(), "AgentMesh/Test");
+
+    counters.record("acme", "kv_get", CallOutcome::Ok);
+    sink.failing.store(true, Ordering::SeqCst);
+    assert!(flusher.flush(ServerGauges::default()).await.is_err());
+
+    counters.record("acme", "kv_get", CallOutcome::Ok);
+    sink.failing.store(false, Ordering::SeqCst);
+    flusher.flush(ServerGauges::default()).await.unwrap();
+
+    let requests = sink.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(value(&requests[0].1, "ToolCalls", &[]), Some(2.0));
+}
+
+#[tokio::test]
+async fn test_large_flushes_are_split_into_requests_of_1000() {
+    let counters = Arc::new(CallCounters::new());
+    let sink = Arc::new(RecordingSink::default());
+    let mut flusher =
+        MetricsFlusher::new(counters.clone(), sink.clone(), "AgentMesh/Test").with_caps(2000, 10);
+    for n in 0..1200 {
+        counters.record(&format!("tenant-{}", n), "kv_get", CallOutcome::Ok);
+    }
+
+    // A data point per tenant, the total and two gauges
+    assert_eq!(flusher.flush(ServerGauges::default()).await.unwrap(), 1203);
+    let sizes: Vec<usize> = sink
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, data)| data.len())
+        .collect();
+    assert_eq!(sizes, [1000, 203]);
+}
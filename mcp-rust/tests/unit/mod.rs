@@ -2,5 +2,71 @@
 // Tests individual functions, methods, and classes in isolation
 // Characteristics: Fast, no external dependencies, mocked services
 
+#[path = "../common/mod.rs"]
+mod common;
+
+mod alert_delivery_tests;
+mod argument_schema_tests;
+mod artifact_lifecycle_tests;
+mod aws_direct_kv_tests;
+mod background_jobs_tests;
+mod cli_tests;
+mod config_tests;
+mod content_block_tests;
+mod diagnostics_bundle_tests;
+mod disabled_tools_tests;
+mod docker_validation_tests;
+mod dry_run_tests;
+mod event_buffer_tests;
+mod event_query_plan_tests;
 mod events_handlers_test;
+mod handler_middleware_tests;
+mod health_tool_tests;
+mod idempotency_tests;
+mod integration_catalog_tests;
+mod integration_export_tests;
+mod integration_sharing_tests;
+mod integration_update_tests;
+mod internal_key_tests;
+mod kv_cache_tests;
+mod kv_transaction_tests;
+mod linked_events_tests;
+mod mcp_list_tools_tests;
 mod mcp_protocol_compliance_tests;
+mod mcp_proxy_validation_tests;
+mod memory_backend_tests;
+mod metrics_flush_tests;
+mod namespace_isolation_tests;
+mod offline_mode_tests;
+mod output_limit_tests;
+mod permission_management_tests;
+mod queue_tools_tests;
+mod rate_limit_status_tests;
+mod redaction_tests;
+mod registry_connections_tests;
+mod registry_docker_tests;
+mod registry_health_concurrency_tests;
+mod registry_health_tests;
+mod registry_logs_tests;
+mod registry_metrics_tests;
+mod registry_shutdown_tests;
+mod registry_store_tests;
+mod registry_tool_index_tests;
+mod replay_protection_tests;
+mod request_tracing_tests;
+mod resource_validation_tests;
+mod response_cache_tests;
+mod schema_versions_tests;
+mod secret_tools_tests;
+mod self_test_tests;
+mod stdio_client_tests;
+mod task_queue_tests;
+mod tenant_data_tests;
+mod tenant_role_tests;
+mod tool_concurrency_tests;
+mod tool_namespace_tests;
+mod tool_policy_tests;
+mod tool_timeout_tests;
+mod tool_usage_tests;
+mod usage_thresholds_tests;
+mod workflow_tools_tests;
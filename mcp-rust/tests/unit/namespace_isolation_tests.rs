@@ -0,0 +1,200 @@
+// Unit tests for namespace isolation of artifacts and events, the legacy read path and
+// namespace_migrate. Personal and org sessions of one user over the in-memory backend
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::AwsApi;
+use mcp_rust::handlers::{HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{ContextType, Permission, TenantSession, UserRole};
+
+fn session(user_id: &str, context_type: ContextType, role: UserRole) -> TenantSession {
+    TestTenant::user(user_id)
+        .context_type(context_type)
+        .role(role)
+        .permissions(vec![
+            Permission::GetArtifacts,
+            Permission::PutArtifacts,
+            Permission::ListArtifacts,
+            Permission::SendEvents,
+        ])
+        .session()
+}
+
+fn personal(user_id: &str) -> TenantSession {
+    session(user_id, ContextType::Personal, UserRole::User)
+}
+
+fn org(user_id: &str) -> TenantSession {
+    let acme = ContextType::Organization {
+        org_id: "acme".to_string(),
+        org_name: "Acme".to_string(),
+    };
+    session(user_id, acme, UserRole::User)
+}
+
+async fn setup() -> (Arc<MemoryAwsService>, HandlerRegistry) {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await;
+    (aws, registry)
+}
+
+async fn call(
+    registry: &HandlerRegistry,
+    session: &TenantSession,
+    tool: &str,
+    args: Value,
+) -> Value {
+    registry
+        .handle_tool_call(session, tool, args)
+        .await
+        .unwrap()
+}
+
+async fn put(registry: &HandlerRegistry, session: &TenantSession, key: &str, content: &str) {
+    call(
+        registry,
+        session,
+        "artifacts_put",
+        json!({"key": key, "content": content, "content_type": "text/plain"}),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn test_artifacts_are_per_namespace() {
+    let (aws, registry) = setup().await;
+    let (alice, alice_at_acme, bob_at_acme) = (personal("alice"), org("alice"), org("bob"));
+
+    put(&registry, &alice, "notes.txt", "cGVyc29uYWw=").await;
+    put(&registry, &alice_at_acme, "notes.txt", "b3Jn").await;
+
+    let bucket = aws.artifacts_bucket();
+    assert!(aws.has_object(bucket, "user:alice/notes.txt"));
+    assert!(aws.has_object(bucket, "org:acme:user:alice/notes.txt"));
+
+    let get = |session| {
+        call(
+            &registry,
+            session,
+            "artifacts_get",
+            json!({"key": "notes.txt"}),
+        )
+    };
+    assert_eq!(get(&alice).await["content"], "cGVyc29uYWw=");
+    assert_eq!(get(&alice_at_acme).await["content"], "b3Jn");
+
+    // Org members no longer share one prefix
+    let listed = call(&registry, &bob_at_acme, "artifacts_list", json!({})).await;
+    assert_eq!(listed, json!({"keys": []}));
+    let listed = call(&registry, &alice, "artifacts_list", json!({})).await;
+    assert_eq!(listed, json!({"keys": ["notes.txt"]}));
+}
+
+#[tokio::test]
+async fn test_events_are_per_namespace() {
+    let (aws, registry) = setup().await;
+    let (alice, alice_at_acme) = (personal("alice"), org("alice"));
+
+    for (session, detail_type) in [(&alice, "personal.note"), (&alice_at_acme, "org.note")] {
+        call(
+            &registry,
+            session,
+            "events_send",
+            json!({"detailType": detail_type, "detail": {}}),
+        )
+        .await;
+    }
+    // Recorded before events were tagged with a namespace
+    aws.record_event(json!({
+        "userId": "alice",
+        "organizationId": "acme",
+        "source": "mcp-rust",
+        "detailType": "legacy.note",
+        "detail": {}
+    }));
+
+    let detail_types = |events: Value| -> Vec<String> {
+        let mut types: Vec<String> = events["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["detailType"].as_str().unwrap().to_string())
+            .collect();
+        types.sort();
+        types
+    };
+    // Naming another user, or none at all, doesn't widen the query
+    for args in [
+        json!({"source": "mcp-rust"}),
+        json!({"userId": "bob"}),
+        json!({}),
+    ] {
+        let events = call(&registry, &alice, "events_query", args.clone()).await;
+        assert_eq!(detail_types(events), vec!["legacy.note", "personal.note"]);
+        let events = call(&registry, &alice_at_acme, "events_query", args).await;
+        assert_eq!(detail_types(events), vec!["org.note"]);
+    }
+
+    let events = call(&registry, &alice_at_acme, "events_query", json!({})).await;
+    assert_eq!(events["events"][0]["namespace"], "org:acme:user:alice");
+    assert_eq!(
+        events["events"][0]["detail"]["namespace"],
+        "org:acme:user:alice"
+    );
+}
+
+#[tokio::test]
+async fn test_legacy_artifacts_are_read_then_migrated() {
+    let (aws, registry) = setup().await;
+    let alice = personal("alice");
+    let admin = session("root", ContextType::Personal, UserRole::Admin);
+    let bucket = aws.artifacts_bucket().to_string();
+    aws.put_object(&bucket, "personal-alice/report.txt", b"old");
+    aws.put_object(&bucket, "personal-alice/notes.txt", b"old notes");
+    put(&registry, &alice, "notes.txt", "bmV3").await;
+
+    // Before the migration, legacy keys are read and listed alongside the new ones
+    let report = call(
+        &registry,
+        &alice,
+        "artifacts_get",
+        json!({"key": "report.txt"}),
+    )
+    .await;
+    assert_eq!(report["content"], "b2xk");
+    let listed = call(&registry, &alice, "artifacts_list", json!({})).await;
+    assert_eq!(listed, json!({"keys": ["notes.txt", "report.txt"]}));
+
+    let migration = call(
+        &registry,
+        &admin,
+        "namespace_migrate",
+        json!({"user_id": "alice"}),
+    )
+    .await;
+    assert_eq!(migration["namespace"], "user:alice");
+    assert_eq!(migration["moved"], json!(["report.txt"]));
+    assert_eq!(migration["skipped"], json!(["notes.txt"]));
+
+    assert!(aws.has_object(&bucket, "user:alice/report.txt"));
+    assert!(!aws.has_object(&bucket, "personal-alice/report.txt"));
+    // The namespace's own copy wins over the skipped legacy one
+    assert!(aws.has_object(&bucket, "personal-alice/notes.txt"));
+    let notes = call(
+        &registry,
+        &alice,
+        "artifacts_get",
+        json!({"key": "notes.txt"}),
+    )
+    .await;
+    assert_eq!(notes["content"], "bmV3");
+
+    // Only admins may migrate
+    let result = registry
+        .handle_tool_call(&alice, "namespace_migrate", json!({}))
+        .await;
+    assert!(result.is_err());
+}
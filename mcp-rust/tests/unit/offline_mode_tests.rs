@@ -0,0 +1,167 @@
+// Unit tests for the startup credentials check and the offline fallback to the in-memory backend
+// Identity check results are injected and configs resolved from in-memory env lookups; no AWS access required
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mcp_rust::aws::credentials::{ensure_credentials, offline_fallback, IdentityCheck};
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::config::{Backend, BackendMode, Config, ConfigError};
+use mcp_rust::handlers::health::HealthHandler;
+use mcp_rust::handlers::Handler;
+use mcp_rust::mcp::MCPServer;
+use mcp_rust::registry::MCPServerRegistry;
+use mcp_rust::tenant::TenantManager;
+
+fn resolve(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    Config::resolve(|name| vars.get(name).cloned(), None)
+}
+
+/// A config the startup check switched to the in-memory backend
+fn offline_config() -> Config {
+    let mut config = resolve(&[
+        ("MCP_BACKEND", "memory"),
+        ("DEFAULT_TENANT_ID", "demo-tenant"),
+        ("DEFAULT_USER_ID", "user-demo-123"),
+    ])
+    .unwrap();
+    config.aws.offline_reason = Some("no AWS credentials found".to_string());
+    config
+}
+
+#[test]
+fn test_valid_or_unreachable_identity_keeps_aws() {
+    let valid = IdentityCheck::Valid {
+        account: "123456789012".to_string(),
+        arn: "arn:aws:iam::123456789012:user/ci".to_string(),
+    };
+    assert_eq!(offline_fallback(&valid, false).unwrap(), None);
+    assert_eq!(offline_fallback(&valid, true).unwrap(), None);
+    // STS being down says nothing about the credentials
+    let unreachable = IdentityCheck::Unreachable("no answer within 5s".to_string());
+    assert_eq!(offline_fallback(&unreachable, false).unwrap(), None);
+}
+
+#[test]
+fn test_unusable_credentials_fail_fast_with_what_to_do() {
+    for check in [
+        IdentityCheck::Missing("no providers in chain provided credentials".to_string()),
+        IdentityCheck::Rejected(
+            "ExpiredToken: The security token included in the request is expired".to_string(),
+        ),
+    ] {
+        let message = offline_fallback(&check, false).unwrap_err().to_string();
+        for hint in [
+            "AWS_ACCESS_KEY_ID",
+            "AWS_PROFILE",
+            "--backend memory",
+            "MCP_ALLOW_OFFLINE=true",
+        ] {
+            assert!(message.contains(hint), "{} missing from {}", hint, message);
+        }
+    }
+}
+
+#[test]
+fn test_allow_offline_falls_back_with_the_reason() {
+    let missing = IdentityCheck::Missing("no providers in chain provided credentials".to_string());
+    let reason = offline_fallback(&missing, true).unwrap().unwrap();
+    assert!(reason.starts_with("no AWS credentials found"), "{}", reason);
+
+    let rejected = IdentityCheck::Rejected("InvalidClientTokenId: bad key".to_string());
+    let reason = offline_fallback(&rejected, true).unwrap().unwrap();
+    assert!(reason.contains("InvalidClientTokenId"), "{}", reason);
+}
+
+#[tokio::test]
+async fn test_memory_backend_and_emulators_are_not_checked() {
+    let mut memory = resolve(&[("MCP_BACKEND", "memory")]).unwrap();
+    ensure_credentials(&mut memory.aws).await.unwrap();
+    assert_eq!(memory.aws.mode(), BackendMode::Memory);
+
+    let mut emulated = resolve(&[("LOCALSTACK_ENDPOINT", "http://localhost:4566")]).unwrap();
+    ensure_credentials(&mut emulated.aws).await.unwrap();
+    assert_eq!(emulated.aws.backend, Backend::Aws);
+    assert_eq!(emulated.aws.mode(), BackendMode::Aws);
+}
+
+#[test]
+fn test_allow_offline_setting_and_mode() {
+    assert!(!resolve(&[]).unwrap().aws.allow_offline);
+    assert!(
+        resolve(&[("MCP_ALLOW_OFFLINE", "true")])
+            .unwrap()
+            .aws
+            .allow_offline
+    );
+
+    let mode = offline_config().aws.mode();
+    assert_eq!(
+        serde_json::to_value(&mode).unwrap(),
+        json!({"mode": "offline", "reason": "no AWS credentials found"})
+    );
+    assert_eq!(
+        serde_json::to_value(resolve(&[]).unwrap().aws.mode()).unwrap(),
+        json!({"mode": "aws"})
+    );
+}
+
+#[test]
+fn test_mixed_emulated_and_real_storage_is_rejected() {
+    let error = resolve(&[("MCP_DYNAMODB_ENDPOINT", "http://localhost:4566")]).unwrap_err();
+    let ConfigError::Invalid { key, message, .. } = error else {
+        panic!("expected an invalid setting, got {:?}", error);
+    };
+    assert_eq!(key, "aws.endpoints.dynamodb");
+    assert!(
+        message.contains("DynamoDB would go to an emulator but S3, EventBridge to AWS"),
+        "{}",
+        message
+    );
+
+    // All storage on the emulator, or only other services overridden, is fine
+    assert!(resolve(&[("LOCALSTACK_ENDPOINT", "http://localhost:4566")]).is_ok());
+    assert!(resolve(&[("MCP_SQS_ENDPOINT", "http://localhost:9324")]).is_ok());
+    // The in-memory backend doesn't use the endpoints at all
+    assert!(resolve(&[
+        ("MCP_BACKEND", "memory"),
+        ("MCP_S3_ENDPOINT", "http://localhost:9000")
+    ])
+    .is_ok());
+}
+
+#[tokio::test]
+async fn test_offline_mode_is_reported_by_initialize_and_health() {
+    let tenant_manager = Arc::new(
+        TenantManager::with_config(Arc::new(offline_config()))
+            .await
+            .unwrap(),
+    );
+    let server = MCPServer::new(tenant_manager.clone(), None).await.unwrap();
+
+    let response = server
+        .handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#)
+        .await
+        .unwrap();
+    let response: Value = serde_json::to_value(response).unwrap();
+    assert_eq!(
+        response["result"]["serverInfo"]["backend"],
+        json!({"mode": "offline", "reason": "no AWS credentials found"})
+    );
+
+    let session = tenant_manager
+        .create_session("demo-tenant", "user-demo-123")
+        .await
+        .unwrap();
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = Arc::new(MCPServerRegistry::new(aws.clone()));
+    let handler = HealthHandler::new(tenant_manager, registry, aws);
+    let report = handler.handle(&session, json!({})).await.unwrap();
+    assert_eq!(report["backend"]["mode"], "offline");
+    assert_eq!(report["checks"]["backend"]["status"], "ok");
+}
@@ -0,0 +1,153 @@
+// Unit tests for the registry's cap on tool result size
+// Synthetic oversized results under the truncate and reject policies
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::output_limit::{OutputLimit, OversizePolicy};
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::{MCPError, MCPErrorResponse};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session() -> TenantSession {
+    TestTenant::user("big-user")
+        .tenant("big-tenant")
+        .organization_id("big-org")
+        .permissions(vec![Permission::ReadKV])
+        .session()
+}
+
+/// Returns `n` 20-character items, plus a 100-character blob, with the count
+struct Bulky {
+    truncatable: &'static [&'static str],
+}
+
+#[async_trait]
+impl Handler for Bulky {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let n = arguments["n"].as_u64().unwrap_or(0) as usize;
+        let items: Vec<String> = (0..n).map(|i| format!("item-{:015}", i)).collect();
+        Ok(json!({ "items": items, "count": n, "blob": "x".repeat(100) }))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        Some(Permission::ReadKV)
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({
+            "description": "Return a lot",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "n": { "type": "integer" } }
+            }
+        })
+    }
+
+    fn truncatable_fields(&self) -> &'static [&'static str] {
+        self.truncatable
+    }
+
+    fn paging_hint(&self) -> Option<&'static str> {
+        Some("a smaller `n`")
+    }
+}
+
+async fn setup(policy: OversizePolicy, truncatable: &'static [&'static str]) -> HandlerRegistry {
+    let mut registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions {
+            output_limit: OutputLimit {
+                max_bytes: 2000,
+                policy,
+            },
+            ..RegistryOptions::default()
+        },
+    )
+    .await;
+    registry.register("bulky", Arc::new(Bulky { truncatable }));
+    registry
+}
+
+#[tokio::test]
+async fn test_results_under_the_limit_are_untouched() {
+    let registry = setup(OversizePolicy::Truncate, &["/items"]).await;
+
+    let result = registry
+        .handle_tool_call(&session(), "bulky", json!({"n": 10}))
+        .await
+        .unwrap();
+    assert_eq!(result["items"].as_array().unwrap().len(), 10);
+    assert!(result.get("truncated").is_none());
+}
+
+#[tokio::test]
+async fn test_truncate_policy_keeps_a_leading_prefix_that_fits() {
+    let registry = setup(OversizePolicy::Truncate, &["/items"]).await;
+
+    let result = registry
+        .handle_tool_call(&session(), "bulky", json!({"n": 500}))
+        .await
+        .unwrap();
+    assert!(serde_json::to_vec(&result).unwrap().len() <= 2000);
+    assert_eq!(result["truncated"], true);
+    assert_eq!(result["count"], 500);
+
+    let items = result["items"].as_array().unwrap();
+    assert!(!items.is_empty() && items.len() < 500);
+    assert_eq!(items[0], "item-000000000000000");
+    assert_eq!(
+        result["truncation"]["fields"]["/items"]["kept"],
+        items.len()
+    );
+    assert_eq!(result["truncation"]["fields"]["/items"]["total"], 500);
+    assert!(result["truncation"]["hint"]
+        .as_str()
+        .unwrap()
+        .contains("a smaller `n`"));
+
+    // The same result is always cut the same way
+    let again = registry
+        .handle_tool_call(&session(), "bulky", json!({"n": 500}))
+        .await
+        .unwrap();
+    assert_eq!(again, result);
+}
+
+#[tokio::test]
+async fn test_reject_policy_names_size_and_paging_arguments() {
+    let registry = setup(OversizePolicy::Reject, &["/items"]).await;
+
+    let result = registry
+        .handle_tool_call(&session(), "bulky", json!({"n": 500}))
+        .await;
+    let error = match result {
+        Err(error @ HandlerError::ResultTooLarge { .. }) => error,
+        other => panic!("expected ResultTooLarge, got {:?}", other),
+    };
+
+    let response = MCPErrorResponse::from(MCPError::from(error));
+    assert_eq!(response.code, -32009);
+    let data = response.data.unwrap();
+    assert_eq!(data["tool"], "bulky");
+    assert_eq!(data["maxBytes"], 2000);
+    assert!(data["size"].as_u64().unwrap() > 2000);
+    assert_eq!(data["hint"], "a smaller `n`");
+}
+
+#[tokio::test]
+async fn test_results_without_truncatable_fields_are_rejected() {
+    let registry = setup(OversizePolicy::Truncate, &[]).await;
+
+    let result = registry
+        .handle_tool_call(&session(), "bulky", json!({"n": 500}))
+        .await;
+    assert!(matches!(result, Err(HandlerError::ResultTooLarge { .. })));
+}
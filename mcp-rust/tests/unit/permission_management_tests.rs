@@ -0,0 +1,112 @@
+// Unit tests for permission_grant / permission_revoke
+// Covers the Admin escalation guard, last-admin protection and session invalidation
+
+use serde_json::json;
+use std::sync::Arc;
+
+use mcp_rust::handlers::permissions::{PermissionGrantHandler, PermissionRevokeHandler};
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::tenant::{Permission, TenantError, TenantManager, UserRole};
+
+const TENANT: &str = "demo-tenant";
+const ADMIN: &str = "user-demo-123";
+
+async fn demo_tenant_manager() -> Arc<TenantManager> {
+    std::env::set_var("DEV_MODE", "true");
+    Arc::new(TenantManager::new().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_non_admin_cannot_grant_admin() {
+    let manager = demo_tenant_manager().await;
+    let admin = manager.create_session(TENANT, ADMIN).await.unwrap();
+
+    // Admin delegates user management to alice
+    let alice = manager
+        .grant_permissions(&admin, "alice", &[Permission::ManageUsers])
+        .await
+        .unwrap();
+    assert_eq!(alice.role, UserRole::User);
+
+    let alice_session = manager.create_session(TENANT, "alice").await.unwrap();
+    assert!(alice_session.has_permission(&Permission::ManageUsers));
+
+    // alice may grant ordinary permissions...
+    let grant = PermissionGrantHandler::new(manager.clone());
+    let result = grant
+        .handle(
+            &alice_session,
+            json!({"user_id": "bob", "permissions": ["ReadKV"]}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["permissions"], json!(["ReadKV"]));
+
+    // ...but not escalate anyone (including herself) to Admin
+    let result = grant
+        .handle(
+            &alice_session,
+            json!({"user_id": "alice", "permissions": ["Admin"]}),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::PermissionDenied(Permission::Admin))
+    ));
+    assert_eq!(
+        manager.get_member(TENANT, "alice").await.unwrap().role,
+        UserRole::User
+    );
+}
+
+#[tokio::test]
+async fn test_last_admin_cannot_revoke_self() {
+    let manager = demo_tenant_manager().await;
+    let admin = manager.create_session(TENANT, ADMIN).await.unwrap();
+
+    let revoke = PermissionRevokeHandler::new(manager.clone());
+    let result = revoke
+        .handle(&admin, json!({"user_id": ADMIN, "permissions": ["Admin"]}))
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+
+    let result = manager
+        .revoke_permissions(&admin, ADMIN, &[Permission::Admin])
+        .await;
+    assert!(matches!(result, Err(TenantError::LastAdmin(_))));
+
+    // Once another admin exists, self-revocation is allowed
+    manager
+        .grant_permissions(&admin, "carol", &[Permission::Admin])
+        .await
+        .unwrap();
+    let demoted = manager
+        .revoke_permissions(&admin, ADMIN, &[Permission::Admin])
+        .await
+        .unwrap();
+    assert_eq!(demoted.role, UserRole::User);
+}
+
+#[tokio::test]
+async fn test_permission_change_invalidates_user_sessions() {
+    let manager = demo_tenant_manager().await;
+    let admin = manager.create_session(TENANT, ADMIN).await.unwrap();
+
+    manager
+        .grant_permissions(&admin, "dave", &[Permission::ReadKV])
+        .await
+        .unwrap();
+    let dave = manager.create_session(TENANT, "dave").await.unwrap();
+    let dave_key = format!("{}:{}", TENANT, dave.session_id);
+    assert!(manager.get_session(&dave_key).await.is_some());
+
+    manager
+        .revoke_permissions(&admin, "dave", &[Permission::ReadKV])
+        .await
+        .unwrap();
+    assert!(manager.get_session(&dave_key).await.is_none());
+
+    // The next session picks up the new permission set
+    let dave = manager.create_session(TENANT, "dave").await.unwrap();
+    assert!(!dave.has_permission(&Permission::ReadKV));
+}
@@ -0,0 +1,266 @@
+// Unit tests for the SQS queue_send, queue_receive, queue_ack and queue_nack tools
+// HandlerRegistry over the in-memory backend's queues (with SQS visibility semantics); no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::queues::{tenant_queue_name, DEFAULT_QUEUE_PREFIX};
+use mcp_rust::aws::AwsError;
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::rate_limiting::AwsOperation;
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session(user_id: &str, permissions: Vec<Permission>) -> TenantSession {
+    TestTenant::user(user_id).permissions(permissions).session()
+}
+
+fn agent(user_id: &str) -> TenantSession {
+    session(user_id, vec![Permission::UseQueues])
+}
+
+/// A registry whose backend has `alice`'s `jobs` queue
+async fn setup() -> (Arc<MemoryAwsService>, HandlerRegistry) {
+    let aws = Arc::new(MemoryAwsService::new());
+    aws.create_queue(&tenant_queue_name(DEFAULT_QUEUE_PREFIX, &agent("alice"), "jobs").unwrap());
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await;
+    (aws, registry)
+}
+
+async fn call(
+    registry: &HandlerRegistry,
+    session: &TenantSession,
+    tool: &str,
+    arguments: Value,
+) -> Result<Value, HandlerError> {
+    registry.handle_tool_call(session, tool, arguments).await
+}
+
+async fn receive(registry: &HandlerRegistry, session: &TenantSession, arguments: Value) -> Value {
+    let mut arguments = arguments;
+    arguments["queue"] = json!("jobs");
+    call(registry, session, "queue_receive", arguments)
+        .await
+        .unwrap()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_send_receive_ack() {
+    let (_aws, registry) = setup().await;
+    let alice = agent("alice");
+
+    // Nothing queued yet: a well-defined empty result
+    assert_eq!(
+        receive(&registry, &alice, json!({})).await,
+        json!({"queue": "jobs", "count": 0, "messages": []})
+    );
+
+    let sent = call(
+        &registry,
+        &alice,
+        "queue_send",
+        json!({"queue": "jobs", "body": "resize image 7", "attributes": {"kind": "resize"}}),
+    )
+    .await
+    .unwrap();
+    let message_id = sent["messageId"].as_str().unwrap();
+
+    let received = receive(&registry, &alice, json!({"max_messages": 10})).await;
+    assert_eq!(received["count"], 1);
+    let message = &received["messages"][0];
+    assert_eq!(message["messageId"], message_id);
+    assert_eq!(message["body"], "resize image 7");
+    assert_eq!(message["attributes"], json!({"kind": "resize"}));
+    assert_eq!(message["receiveCount"], 1);
+
+    let acked = call(
+        &registry,
+        &alice,
+        "queue_ack",
+        json!({"queue": "jobs", "receipt_handle": message["receiptHandle"]}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(acked["acknowledged"], true);
+
+    // Gone for good, even after the visibility timeout
+    tokio::time::advance(Duration::from_secs(60)).await;
+    assert_eq!(receive(&registry, &alice, json!({})).await["count"], 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_unacknowledged_messages_are_redelivered() {
+    let (_aws, registry) = setup().await;
+    let alice = agent("alice");
+    call(
+        &registry,
+        &alice,
+        "queue_send",
+        json!({"queue": "jobs", "body": "job"}),
+    )
+    .await
+    .unwrap();
+
+    let first = receive(&registry, &alice, json!({"visibility_timeout_seconds": 10})).await;
+    let first_handle = first["messages"][0]["receiptHandle"].clone();
+    // In flight: hidden from other receivers
+    assert_eq!(receive(&registry, &alice, json!({})).await["count"], 0);
+
+    tokio::time::advance(Duration::from_secs(11)).await;
+    let second = receive(&registry, &alice, json!({})).await;
+    assert_eq!(second["count"], 1);
+    assert_eq!(second["messages"][0]["receiveCount"], 2);
+
+    // The first receive's handle no longer works
+    let result = call(
+        &registry,
+        &alice,
+        "queue_ack",
+        json!({"queue": "jobs", "receipt_handle": first_handle}),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::Aws(AwsError::NotFound(_)))
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_nack_returns_the_message() {
+    let (_aws, registry) = setup().await;
+    let alice = agent("alice");
+    call(
+        &registry,
+        &alice,
+        "queue_send",
+        json!({"queue": "jobs", "body": "job"}),
+    )
+    .await
+    .unwrap();
+
+    let received = receive(&registry, &alice, json!({})).await;
+    let handle = received["messages"][0]["receiptHandle"].clone();
+    call(
+        &registry,
+        &alice,
+        "queue_nack",
+        json!({"queue": "jobs", "receipt_handle": handle, "delay_seconds": 5}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(receive(&registry, &alice, json!({})).await["count"], 0);
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    let again = receive(&registry, &alice, json!({})).await;
+    assert_eq!(again["count"], 1);
+
+    // Once the visibility timeout has run out the message isn't in flight to return
+    tokio::time::advance(Duration::from_secs(31)).await;
+    let result = call(
+        &registry,
+        &alice,
+        "queue_nack",
+        json!({"queue": "jobs", "receipt_handle": again["messages"][0]["receiptHandle"]}),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::Aws(AwsError::NotFound(_)))
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_long_poll_waits_for_delayed_messages() {
+    let (_aws, registry) = setup().await;
+    let alice = agent("alice");
+    call(
+        &registry,
+        &alice,
+        "queue_send",
+        json!({"queue": "jobs", "body": "later", "delay_seconds": 3}),
+    )
+    .await
+    .unwrap();
+
+    // A short poll doesn't see it
+    assert_eq!(receive(&registry, &alice, json!({})).await["count"], 0);
+
+    let started = tokio::time::Instant::now();
+    let received = receive(&registry, &alice, json!({"wait_seconds": 20})).await;
+    assert_eq!(received["count"], 1);
+    let waited = started.elapsed();
+    assert!(waited >= Duration::from_secs(3) && waited < Duration::from_secs(4));
+
+    // With nothing to deliver the poll ends empty after wait_seconds
+    let started = tokio::time::Instant::now();
+    assert_eq!(
+        receive(&registry, &alice, json!({"wait_seconds": 5})).await["count"],
+        0
+    );
+    assert!(started.elapsed() >= Duration::from_secs(5));
+
+    let result = call(
+        &registry,
+        &alice,
+        "queue_receive",
+        json!({"queue": "jobs", "wait_seconds": 21}),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::SchemaViolation { ref pointer, .. }) if pointer == "/wait_seconds"
+    ));
+}
+
+#[tokio::test]
+async fn test_queues_are_scoped_to_the_tenant() {
+    let (_aws, registry) = setup().await;
+    let alice = agent("alice");
+    let bob = agent("bob");
+
+    assert_eq!(
+        tenant_queue_name(DEFAULT_QUEUE_PREFIX, &alice, "jobs").unwrap(),
+        "agent-mesh-alice-tenant-jobs"
+    );
+    // A '-' in the name could reach another tenant's prefix
+    let result = call(
+        &registry,
+        &alice,
+        "queue_send",
+        json!({"queue": "tenant-jobs", "body": "x"}),
+    )
+    .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+
+    // Bob's "jobs" is a different queue, which doesn't exist
+    let result = call(
+        &registry,
+        &bob,
+        "queue_send",
+        json!({"queue": "jobs", "body": "x"}),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::Aws(AwsError::NotFound(_)))
+    ));
+
+    let other = session("carol", vec![Permission::SendEvents]);
+    let result = call(&registry, &other, "queue_receive", json!({"queue": "jobs"})).await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::PermissionDenied(Permission::UseQueues))
+    ));
+}
+
+#[test]
+fn test_queue_tools_rate_limit_buckets() {
+    let service_key =
+        |tool: &str| AwsOperation::from_tool_name(tool, &json!({})).map(|op| op.service_key());
+    assert_eq!(service_key("queue_send"), Some("sqs_send"));
+    for tool in ["queue_receive", "queue_ack", "queue_nack"] {
+        assert_eq!(service_key(tool), Some("sqs_receive"), "{}", tool);
+    }
+}
@@ -0,0 +1,104 @@
+// Unit tests for the rate_limit_status tool
+// Charges operations directly against the tenant's limiter; no AWS access required
+
+use serde_json::json;
+use std::sync::Arc;
+
+use mcp_rust::handlers::concurrency::{ToolConcurrencyLimiter, ToolConcurrencyPolicy};
+use mcp_rust::handlers::rate_limits::RateLimitStatusHandler;
+use mcp_rust::handlers::server_stats::ServerStatsHandler;
+use mcp_rust::handlers::Handler;
+use mcp_rust::rate_limiting::{AwsOperation, AwsServiceLimits, OperationPriority};
+use mcp_rust::tenant::{ResourceLimits, TenantManager};
+
+#[tokio::test]
+async fn test_status_reflects_charged_operations() {
+    std::env::set_var("DEV_MODE", "true");
+    let manager = Arc::new(TenantManager::new().await.unwrap());
+
+    // A slow refill keeps the consumption visible for the duration of the test
+    manager
+        .update_resource_limits(
+            "demo-tenant",
+            ResourceLimits {
+                aws_service_limits: AwsServiceLimits {
+                    dynamodb_write_units: 1,
+                    aws_burst_capacity: 9,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let session = manager
+        .create_session("demo-tenant", "user-demo-123")
+        .await
+        .unwrap();
+    session.increment_request_count();
+
+    let limiter = manager.get_aws_rate_limiter();
+    for _ in 0..3 {
+        session
+            .check_aws_operation(
+                &limiter,
+                &AwsOperation::DynamoDbWrite { write_units: 1 },
+                OperationPriority::Normal,
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    let handler = RateLimitStatusHandler::new(manager.clone());
+    let status = handler.handle(&session, json!({})).await.unwrap();
+
+    assert_eq!(status["tenant_id"], "demo-tenant");
+    let buckets = status["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 1);
+    let bucket = &buckets[0];
+    assert_eq!(bucket["service_key"], "dynamodb_write");
+    assert_eq!(bucket["capacity"], 10.0);
+    assert_eq!(bucket["refill_rate"], 1.0);
+    let tokens = bucket["tokens"].as_f64().unwrap();
+    assert!((7.0..8.0).contains(&tokens), "unexpected tokens {}", tokens);
+    assert!(bucket["time_to_full_ms"].as_u64().unwrap() > 2_000);
+
+    let window = &status["requests_per_minute"];
+    assert_eq!(window["algorithm"], "fixed");
+    assert_eq!(window["requests"], 1);
+    assert_eq!(window["limit"], 100);
+    assert!(window["resets_in_ms"].as_u64().unwrap() <= 60_000);
+}
+
+#[tokio::test]
+async fn test_server_stats_counts_rate_limit_buckets() {
+    std::env::set_var("DEV_MODE", "true");
+    let manager = Arc::new(TenantManager::new().await.unwrap());
+    let session = manager
+        .create_session("demo-tenant", "user-demo-123")
+        .await
+        .unwrap();
+
+    let limiter = manager.get_aws_rate_limiter();
+    for tenant in ["tenant-a", "tenant-b"] {
+        limiter
+            .check_aws_operation(tenant, &AwsOperation::S3Get)
+            .await
+            .unwrap();
+    }
+
+    let handler = ServerStatsHandler::new(
+        manager.clone(),
+        Arc::new(ToolConcurrencyLimiter::new(ToolConcurrencyPolicy::Reject)),
+    );
+    let stats = handler.handle(&session, json!({})).await.unwrap();
+
+    assert_eq!(stats["active_sessions"], 1);
+    assert_eq!(stats["rate_limit_buckets"], 2);
+    assert_eq!(
+        stats["max_rate_limit_buckets"],
+        mcp_rust::rate_limiting::DEFAULT_MAX_BUCKETS
+    );
+    assert_eq!(stats["tool_concurrency"], json!([]));
+}
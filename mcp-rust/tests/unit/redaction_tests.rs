@@ -0,0 +1,263 @@
+// Unit tests for the redaction policy applied to logs, errors, audit records and bundles
+// Property-style sweeps over representative payloads; no AWS access required
+
+use serde_json::{json, Value};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::config::{Config, ConfigFile};
+use mcp_rust::handlers::middleware::{AuditMiddleware, HandlerMiddleware, ToolCall};
+use mcp_rust::handlers::HandlerError;
+use mcp_rust::mcp::{MCPError, MCPErrorResponse};
+use mcp_rust::redaction::{log_fields, mask, RedactionPolicy, DEFAULT_PATTERNS, REDACTED_PREFIX};
+use mcp_rust::tenant::Permission;
+
+/// Keys each default pattern is meant to catch, in the spellings clients use
+const SENSITIVE_KEYS: &[&str] = &[
+    "client_secret",
+    "SECRET",
+    "refresh_token",
+    "GITHUB_TOKEN",
+    "password",
+    "db-passwd",
+    "aws_credentials",
+    "Authorization",
+    "api_key",
+    "X-ApiKey",
+    "private_key",
+    "AWS_ACCESS_KEY_ID",
+    "kms_key_id",
+];
+
+/// `secret` under `key` in every place a payload can carry it: top level, nested,
+/// in an array, as a field of a `credentials` object and as `key=value` text
+fn payloads(key: &str, secret: &str) -> Vec<Value> {
+    vec![
+        json!({ key: secret }),
+        json!({"request": {"headers": { key: secret }, "path": "/v1"}}),
+        json!({"items": [{"name": "first"}, { key: [secret, {"nested": secret}] }]}),
+        json!({"connection": {"credentials": {"user": secret}}}),
+        json!({"last_error": format!("login failed: {}={} retrying", key, secret)}),
+        json!({"log": [format!("{}: {}", key, secret)]}),
+        json!(format!("curl -H Authorization: Bearer {}", secret)),
+    ]
+}
+
+fn assert_redacted(policy: &RedactionPolicy, payload: &Value, secret: &str) {
+    let serialized = serde_json::to_string(&policy.redacted(payload)).unwrap();
+    assert!(
+        !serialized.contains(secret),
+        "{} leaked: {}",
+        secret,
+        serialized
+    );
+    assert!(serialized.contains(REDACTED_PREFIX), "{}", serialized);
+}
+
+#[test]
+fn test_no_sensitive_value_survives_serialization() {
+    let policy = RedactionPolicy::default();
+    for (i, key) in SENSITIVE_KEYS.iter().enumerate() {
+        for (j, payload) in payloads(key, &format!("s3cr3t-{}-v", i)).iter().enumerate() {
+            let secret = format!("s3cr3t-{}-v", i);
+            assert!(payload.to_string().contains(&secret), "payload {}", j);
+            assert_redacted(&policy, payload, &secret);
+        }
+    }
+}
+
+#[test]
+fn test_every_default_pattern_is_covered() {
+    let policy = RedactionPolicy::default();
+    for pattern in DEFAULT_PATTERNS {
+        let key = pattern.replace('*', "x");
+        let path: Vec<&str> = key.split('.').collect();
+        assert!(
+            policy.is_sensitive(&path),
+            "{} doesn't match {}",
+            key,
+            pattern
+        );
+        assert_redacted(
+            &policy,
+            &json!({ "wrapper": { key.as_str(): "opaque-value" } }),
+            "opaque",
+        );
+    }
+}
+
+#[test]
+fn test_masks_correlate_equal_values() {
+    let policy = RedactionPolicy::default();
+    let redacted = policy.redacted(&json!({
+        "password": "hunter2",
+        "nested": {"client_secret": "hunter2"},
+        "api_key": "different",
+        "last_error": "retry with token=hunter2",
+    }));
+
+    let mask = redacted["password"].as_str().unwrap();
+    let hex = mask
+        .strip_prefix(REDACTED_PREFIX)
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap();
+    assert_eq!(hex.len(), 8);
+    assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(redacted["nested"]["client_secret"], mask);
+    assert_ne!(redacted["api_key"], mask);
+    assert_eq!(redacted["last_error"], format!("retry with token={}", mask));
+}
+
+#[test]
+fn test_non_credentials_are_kept() {
+    let policy = RedactionPolicy::default();
+    let payload = json!({
+        "tool": "kv_get",
+        "key": "user/profile",
+        "serviceKey": "s3_get",
+        "buckets": [{"tokens": 9.5, "capacity": 10}],
+        "note": "listening on :8080",
+        "password_set": true,
+        "tokens": null,
+    });
+    assert_eq!(policy.redacted(&payload), payload);
+}
+
+#[test]
+fn test_dotted_patterns_match_the_path() {
+    let policy = RedactionPolicy::new(["credentials.*".to_string()]);
+    let redacted = policy.redacted(&json!({
+        "connection": {"credentials": {"user": "alice", "host": {"name": "db"}}},
+        "user": "bob",
+    }));
+    assert_eq!(
+        redacted["connection"]["credentials"]["user"],
+        mask(&json!("alice"))
+    );
+    assert_eq!(
+        redacted["connection"]["credentials"]["host"],
+        mask(&json!({"name": "db"}))
+    );
+    assert_eq!(redacted["user"], "bob");
+}
+
+#[test]
+fn test_configured_patterns_extend_the_defaults() {
+    let file = ConfigFile::parse(
+        "mcp.toml",
+        "[server]\nredact_patterns = [\"*ssn*\", \"customer.email\"]\n",
+    )
+    .unwrap();
+    let config = Config::resolve(|_| None, Some(&file)).unwrap();
+    assert_eq!(
+        config.server.redact_patterns,
+        vec!["*ssn*", "customer.email"]
+    );
+    let policy = config.server.redaction_policy();
+
+    let payload = json!({
+        "customer": {"email": "ada@example.com", "ssn_last4": "6789", "name": "Ada"},
+        "password": "hunter2",
+        "email": "ops@example.com",
+    });
+    let serialized = policy.redacted(&payload).to_string();
+    for secret in ["ada@example.com", "6789", "hunter2"] {
+        assert!(!serialized.contains(secret), "{} leaked", secret);
+    }
+    assert!(serialized.contains("Ada") && serialized.contains("ops@example.com"));
+
+    let config = Config::resolve(
+        |name| (name == "MCP_REDACT_PATTERNS").then(|| "*pin*".to_string()),
+        None,
+    )
+    .unwrap();
+    assert!(config.server.redaction_policy().is_sensitive(&["card_pin"]));
+}
+
+#[test]
+fn test_error_responses_are_redacted() {
+    let response = MCPErrorResponse::from(MCPError::HandlerError(
+        "Downstream refused Authorization: Bearer sk-live-abc".to_string(),
+    ));
+    assert!(
+        !response.message.contains("sk-live-abc"),
+        "{}",
+        response.message
+    );
+    assert!(response.message.contains(REDACTED_PREFIX));
+
+    let response = MCPErrorResponse::from(MCPError::from(HandlerError::InvalidArguments(
+        "password=hunter2 is too short".to_string(),
+    )));
+    let serialized = serde_json::to_string(&response).unwrap();
+    assert!(!serialized.contains("hunter2"), "{}", serialized);
+}
+
+/// Log output captured in memory
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_log_lines_and_audit_records_are_redacted() {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .fmt_fields(log_fields())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    tracing::info!(
+        api_key = "sk-live-abc",
+        attempt = 2,
+        "connecting with token=ghp_live"
+    );
+    let session = TestTenant::user("user-demo-123")
+        .tenant("demo-tenant")
+        .permissions(vec![Permission::ReadKV])
+        .session();
+    let call = ToolCall {
+        session: &session,
+        tool: "secret_get",
+        arguments: json!({"name": "db"}),
+        required_permission: None,
+        idempotency_key: None,
+    };
+    AuditMiddleware
+        .after(
+            &call,
+            &Err(HandlerError::Internal(
+                "vault said secret=hunter2".to_string(),
+            )),
+            Duration::from_millis(3),
+        )
+        .await;
+
+    let logged = captured.text();
+    for secret in ["sk-live-abc", "ghp_live", "hunter2"] {
+        assert!(!logged.contains(secret), "{} leaked: {}", secret, logged);
+    }
+    assert!(logged.contains(&format!("api_key={}", mask(&json!("sk-live-abc")))));
+    assert!(logged.contains("attempt=2"));
+    assert!(logged.contains("tool=\"secret_get\"") || logged.contains("tool=secret_get"));
+}
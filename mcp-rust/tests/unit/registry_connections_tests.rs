@@ -0,0 +1,199 @@
+// Unit tests for several connections of one service, each its own server instance
+// Servers are local `sh` processes and stores are in memory; no AWS access required
+
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::mcp_proxy::MCPProxyHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-multi-user";
+
+/// An MCP server with a `whoami` tool answering its first two calls with the API
+/// token it was started with
+fn token_script() -> String {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools =
+        r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"whoami","inputSchema":{}}]}}"#;
+    let call = |id: u32| {
+        format!(
+            r#"read l; echo '{{"jsonrpc":"2.0","id":{},"result":{{"content":[{{"type":"text","text":"'"$API_TOKEN"'"}}]}}}}';"#,
+            id
+        )
+    };
+    format!(
+        "read l; echo '{}'; read l; read l; echo '{}'; {} {} cat >/dev/null",
+        initialized,
+        tools,
+        call(3),
+        call(4)
+    )
+}
+
+fn bot_config() -> MCPServerConfig {
+    MCPServerConfig {
+        id: "bot".to_string(),
+        name: "Bot".to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), token_script()],
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::ApiKey {
+            key_field: "API_TOKEN".to_string(),
+        },
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+async fn registry(secrets: Arc<MemorySecretStore>) -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(secrets);
+    registry
+        .register_server(TENANT, bot_config())
+        .await
+        .unwrap();
+    Arc::new(registry)
+}
+
+/// Connect `bot@{connection}` with its own API key
+async fn connect(registry: &MCPServerRegistry, connection: &str, api_key: &str) {
+    let instance = format!("bot@{}", connection);
+    let credentials = HashMap::from([("api_key".to_string(), api_key.to_string())]);
+    registry
+        .store_credentials(TENANT, &instance, &credentials)
+        .await
+        .unwrap();
+    registry
+        .connect_server(TENANT, &instance, None)
+        .await
+        .unwrap();
+}
+
+async fn whoami(registry: &MCPServerRegistry, instance: &str) -> Result<String, RegistryError> {
+    let result = registry
+        .execute_tool(TENANT, instance, "whoami", json!({}))
+        .await?;
+    Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("multi-user")
+        .tenant("multi-tenant")
+        .organization_id("multi-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_connections_are_independent_instances() {
+    let secrets = Arc::new(MemorySecretStore::new());
+    let registry = registry(secrets.clone()).await;
+    connect(&registry, "personal", "token-personal").await;
+    connect(&registry, "work", "token-work").await;
+
+    assert_eq!(
+        whoami(&registry, "bot@personal").await.unwrap(),
+        "token-personal"
+    );
+    assert_eq!(whoami(&registry, "bot@work").await.unwrap(), "token-work");
+    assert_eq!(
+        secrets.names(),
+        vec![
+            "mcp/personal-multi-user/bot@personal/api_key",
+            "mcp/personal-multi-user/bot@work/api_key"
+        ]
+    );
+
+    registry
+        .disconnect_server(TENANT, "bot@personal")
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        whoami(&registry, "bot@personal").await,
+        Err(RegistryError::ServerNotFound(_))
+    ));
+    assert_eq!(whoami(&registry, "bot@work").await.unwrap(), "token-work");
+}
+
+#[tokio::test]
+async fn test_connections_are_listed_under_their_service() {
+    let registry = registry(Arc::new(MemorySecretStore::new())).await;
+    connect(&registry, "work", "token-work").await;
+    connect(&registry, "personal", "token-personal").await;
+
+    let servers = registry.list_servers(TENANT).await.unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0].id, "bot");
+    assert_eq!(servers[0].status, "Disconnected");
+    let connections: Vec<(Option<&str>, &str)> = servers[0]
+        .connections
+        .iter()
+        .map(|c| (c.connection_id.as_deref(), c.status.as_str()))
+        .collect();
+    assert_eq!(
+        connections,
+        vec![(Some("personal"), "Connected"), (Some("work"), "Connected")]
+    );
+
+    // Both connections provide `whoami`, so a bare call is ambiguous
+    let proxy = MCPProxyHandler::new(registry.clone());
+    match proxy
+        .handle(&session(), json!({"tool_name": "whoami", "arguments": {}}))
+        .await
+    {
+        Err(HandlerError::InvalidArguments(message)) => {
+            assert!(message.contains("bot.personal, bot.work"), "{}", message)
+        }
+        other => panic!("expected an ambiguity error, got {:?}", other),
+    }
+
+    let result = proxy
+        .handle(
+            &session(),
+            json!({"tool_name": "bot.work.whoami", "arguments": {}}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["content"][0]["text"], "token-work");
+}
+
+#[tokio::test]
+async fn test_unregister_removes_every_connection() {
+    let secrets = Arc::new(MemorySecretStore::new());
+    let registry = registry(secrets.clone()).await;
+    connect(&registry, "work", "token-work").await;
+
+    assert!(matches!(
+        registry.connect_server(TENANT, "bot@not valid", None).await,
+        Err(RegistryError::InvalidConfig { .. })
+    ));
+    assert!(matches!(
+        registry.connect_server(TENANT, "missing@work", None).await,
+        Err(RegistryError::ServerNotFound(_))
+    ));
+
+    registry.unregister_server(TENANT, "bot").await.unwrap();
+
+    assert!(registry.list_servers(TENANT).await.unwrap().is_empty());
+    assert!(matches!(
+        whoami(&registry, "bot@work").await,
+        Err(RegistryError::ServerNotFound(_))
+    ));
+    assert!(secrets.names().is_empty());
+}
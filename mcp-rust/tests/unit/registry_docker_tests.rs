@@ -0,0 +1,450 @@
+// Unit tests for starting, health-checking and reading logs of Docker-deployed servers
+// A scripted stub stands in for the docker CLI; no Docker daemon required
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::integrations::{IntegrationLogsHandler, IntegrationTestHandler};
+use mcp_rust::handlers::Handler;
+use mcp_rust::registry::docker::{DockerCli, DockerOutput};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerInfo, MCPServerRegistry, MCPServerType,
+    RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-docker-user";
+
+/// Answers `docker inspect` from a queue of states (repeating the last one) and
+/// `docker logs` with fixed output; records every invocation
+struct ScriptedDocker {
+    states: Mutex<VecDeque<&'static str>>,
+    logs: &'static str,
+    calls: Mutex<Vec<Vec<String>>>,
+    /// Whether `docker image inspect` finds the image
+    image_present: AtomicBool,
+    /// stderr of a failing `docker pull`; pulls succeed when unset
+    pull_error: Option<&'static str>,
+    /// Each `docker pull` waits for a permit, so tests can look at the status mid-pull
+    pull_gate: Semaphore,
+}
+
+impl ScriptedDocker {
+    fn new(states: &[&'static str], logs: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            states: Mutex::new(states.iter().copied().collect()),
+            logs,
+            calls: Mutex::new(Vec::new()),
+            image_present: AtomicBool::new(true),
+            pull_error: None,
+            pull_gate: Semaphore::new(0),
+        })
+    }
+
+    /// The image has to be pulled first; the pull fails with `pull_error` if set
+    fn without_image(states: &[&'static str], pull_error: Option<&'static str>) -> Arc<Self> {
+        Arc::new(Self {
+            states: Mutex::new(states.iter().copied().collect()),
+            logs: "",
+            calls: Mutex::new(Vec::new()),
+            image_present: AtomicBool::new(false),
+            pull_error,
+            pull_gate: Semaphore::new(0),
+        })
+    }
+
+    fn push_state(&self, state: &'static str) {
+        let mut states = self.states.lock().unwrap();
+        states.clear();
+        states.push_back(state);
+    }
+
+    fn subcommands(&self) -> Vec<String> {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|args| args[0].clone())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DockerCli for ScriptedDocker {
+    async fn run(&self, args: Vec<String>) -> Result<DockerOutput, RegistryError> {
+        self.calls.lock().unwrap().push(args.clone());
+        let ok = |stdout: &str| DockerOutput {
+            success: true,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        };
+        Ok(match args[0].as_str() {
+            "image" if !self.image_present.load(Ordering::SeqCst) => DockerOutput {
+                success: false,
+                stdout: String::new(),
+                stderr: format!("Error: No such image: {}\n", args[4]),
+            },
+            "image" => ok("sha256:c0ffee\n"),
+            "pull" => {
+                self.pull_gate.acquire().await.unwrap().forget();
+                match self.pull_error {
+                    Some(error) => DockerOutput {
+                        success: false,
+                        stdout: String::new(),
+                        stderr: error.to_string(),
+                    },
+                    None => {
+                        self.image_present.store(true, Ordering::SeqCst);
+                        ok("latest: Pulling from acme/mcp-github\nStatus: Downloaded newer image\n")
+                    }
+                }
+            }
+            "run" => ok("c0ffee\n"),
+            "inspect" => {
+                let mut states = self.states.lock().unwrap();
+                let state = if states.len() > 1 {
+                    states.pop_front().unwrap()
+                } else {
+                    states[0]
+                };
+                ok(&format!("{}\n", state))
+            }
+            // The container's output came out one second apart
+            "logs" => DockerOutput {
+                success: true,
+                stdout: self
+                    .logs
+                    .lines()
+                    .map(|line| format!("2025-01-01T00:00:01Z {}\n", line))
+                    .collect(),
+                stderr: "2025-01-01T00:00:02Z fatal: missing GITHUB_TOKEN\n".to_string(),
+            },
+            _ => ok(""),
+        })
+    }
+}
+
+fn docker_config(id: &str) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: "containerized integration".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Docker {
+            image: "ghcr.io/acme/mcp-github".to_string(),
+            tag: "latest".to_string(),
+            ports: Vec::new(),
+            volumes: Vec::new(),
+            network: None,
+            runtime: None,
+            memory_limit: None,
+            cpu_limit: None,
+            pids_limit: None,
+            read_only_rootfs: false,
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 0,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+async fn registry(docker: Arc<ScriptedDocker>) -> Arc<MCPServerRegistry> {
+    registry_with(docker, false).await
+}
+
+async fn registry_with(docker: Arc<ScriptedDocker>, prepull: bool) -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_docker(docker)
+        .with_image_prepull(prepull)
+        .with_container_start_timeout(Duration::from_millis(600));
+    registry
+        .register_server(TENANT, docker_config("github"))
+        .await
+        .unwrap();
+    Arc::new(registry)
+}
+
+async fn server(registry: &MCPServerRegistry) -> MCPServerInfo {
+    registry.list_servers(TENANT).await.unwrap().remove(0)
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("docker-user")
+        .tenant("docker-tenant")
+        .organization_id("docker-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_connected_once_container_is_running() {
+    let docker = ScriptedDocker::new(&["created", "running"], "listening on stdio\n");
+    let registry = registry(docker.clone()).await;
+
+    registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap();
+
+    let info = server(&registry).await;
+    assert_eq!(info.status, "Connected");
+    assert_eq!(info.container_status.as_deref(), Some("running"));
+    assert_eq!(
+        docker.subcommands(),
+        vec!["image", "rm", "run", "inspect", "inspect", "logs"]
+    );
+    // Lines already captured at start aren't added again
+    let logs: Vec<String> = registry
+        .server_logs(TENANT, "github")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|log| log.line)
+        .collect();
+    assert_eq!(
+        logs,
+        vec!["listening on stdio", "fatal: missing GITHUB_TOKEN"]
+    );
+    assert_eq!(
+        docker.calls.lock().unwrap().last().unwrap()[..4],
+        [
+            "logs",
+            "--timestamps",
+            "--since",
+            "2025-01-01T00:00:02+00:00"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_crashing_container_fails_with_its_logs() {
+    // Exits before the first inspect
+    let docker = ScriptedDocker::new(&["exited"], "booting\n");
+    let registry = registry(docker.clone()).await;
+
+    let err = registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("is exited"), "{}", message);
+    assert!(message.contains("missing GITHUB_TOKEN"), "{}", message);
+
+    let info = server(&registry).await;
+    assert!(info.status.starts_with("Failed"), "{:?}", info);
+    // The failed container is removed once its logs are captured
+    assert_eq!(docker.subcommands().last().unwrap(), "rm");
+
+    let logs = IntegrationLogsHandler::new(registry.clone())
+        .handle(&session(), json!({"service_id": "github", "lines": 1}))
+        .await
+        .unwrap();
+    assert_eq!(
+        logs["logs"],
+        json!([{
+            "timestamp": "2025-01-01T00:00:02Z",
+            "stream": "stderr",
+            "line": "fatal: missing GITHUB_TOKEN"
+        }])
+    );
+    assert_eq!(logs["container_status"], "exited");
+
+    let test = IntegrationTestHandler::new(registry)
+        .handle(&session(), json!({"service_id": "github"}))
+        .await
+        .unwrap();
+    assert_eq!(test["success"], false);
+    assert_eq!(
+        test["recent_logs"],
+        json!(["booting", "fatal: missing GITHUB_TOKEN"])
+    );
+    assert!(test["last_error"].as_str().unwrap().contains("is exited"));
+}
+
+#[tokio::test]
+async fn test_container_that_never_starts_times_out() {
+    let docker = ScriptedDocker::new(&["restarting"], "");
+    let registry = registry(docker).await;
+
+    let err = registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("did not start within"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_health_check_inspects_containers() {
+    let docker = ScriptedDocker::new(&["running"], "serving\n");
+    let registry = registry(docker.clone()).await;
+    registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap();
+
+    registry.health_check().await;
+    assert_eq!(server(&registry).await.status, "Connected");
+
+    docker.push_state("dead");
+    registry.health_check().await;
+
+    let info = server(&registry).await;
+    assert_eq!(info.status, "Failed(\"Container dead\")");
+    assert_eq!(info.container_status.as_deref(), Some("dead"));
+    assert!(matches!(
+        registry
+            .execute_tool(TENANT, "github", "search", json!({}))
+            .await,
+        Err(RegistryError::ServerNotConnected(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_logs_of_unknown_server() {
+    let registry = registry(ScriptedDocker::new(&["running"], "")).await;
+    assert!(matches!(
+        registry.server_logs(TENANT, "missing").await,
+        Err(RegistryError::ServerNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_missing_image_is_pulled_before_run() {
+    let docker = ScriptedDocker::without_image(&["running"], None);
+    let registry = registry(docker.clone()).await;
+
+    let connecting = {
+        let registry = registry.clone();
+        tokio::spawn(async move { registry.connect_server(TENANT, "github", None).await })
+    };
+    while server(&registry).await.status != "Pulling" {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    docker.pull_gate.add_permits(1);
+    connecting.await.unwrap().unwrap();
+
+    assert_eq!(server(&registry).await.status, "Connected");
+    assert_eq!(
+        docker.subcommands(),
+        vec!["image", "pull", "rm", "run", "inspect", "logs"]
+    );
+    assert_eq!(
+        docker.calls.lock().unwrap()[1],
+        vec!["pull", "ghcr.io/acme/mcp-github:latest"]
+    );
+}
+
+#[tokio::test]
+async fn test_pull_failure_surfaces_daemon_error() {
+    let docker = ScriptedDocker::without_image(
+        &["running"],
+        Some("Error response from daemon: pull access denied for ghcr.io/acme/mcp-github\n"),
+    );
+    docker.pull_gate.add_permits(1);
+    let registry = registry(docker.clone()).await;
+
+    let err = registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("pull access denied"), "{}", err);
+
+    let info = server(&registry).await;
+    assert!(info.status.contains("pull access denied"), "{:?}", info);
+    assert_eq!(docker.subcommands(), vec!["image", "pull"]);
+}
+
+#[tokio::test]
+async fn test_registration_prepulls_image() {
+    let docker = ScriptedDocker::without_image(&["running"], None);
+    docker.pull_gate.add_permits(1);
+    let registry = registry_with(docker.clone(), true).await;
+
+    while !docker.image_present.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(docker.subcommands(), vec!["image", "pull"]);
+
+    registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap();
+    // Already pulled, so connecting only checks for it
+    assert_eq!(
+        docker.subcommands()[2..],
+        ["image", "rm", "run", "inspect", "logs"]
+    );
+}
+
+#[tokio::test]
+async fn test_shutdown_stops_containers_once() {
+    let docker = ScriptedDocker::new(&["running"], "");
+    let registry = registry(docker.clone()).await;
+    registry
+        .connect_server(TENANT, "github", None)
+        .await
+        .unwrap();
+    let calls = docker.calls.lock().unwrap().len();
+
+    registry.shutdown().await;
+    registry.shutdown().await;
+
+    let stopped: Vec<Vec<String>> = docker.calls.lock().unwrap()[calls..].to_vec();
+    assert_eq!(
+        stopped,
+        vec![
+            vec!["stop", "--time", "3", "mcp-personal-docker-user-github"],
+            vec!["rm", "-f", "mcp-personal-docker-user-github"],
+        ]
+    );
+    assert_eq!(server(&registry).await.status, "Disconnected");
+}
+
+#[tokio::test]
+async fn test_http_server_container_is_removed_when_connect_fails() {
+    let docker = ScriptedDocker::new(&["running"], "");
+    let registry = registry(docker.clone()).await;
+    // Nothing listens on the published port
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let mut config = docker_config("web");
+    config.server_type = MCPServerType::Http;
+    if let DeploymentConfig::Docker { ports, .. } = &mut config.deployment {
+        ports.push(format!("{}:8080", port));
+    }
+    registry.register_server(TENANT, config).await.unwrap();
+
+    let calls = docker.calls.lock().unwrap().len();
+    assert!(registry.connect_server(TENANT, "web", None).await.is_err());
+
+    let calls: Vec<Vec<String>> = docker.calls.lock().unwrap()[calls..].to_vec();
+    assert_eq!(calls[calls.len() - 2][0], "stop");
+    assert_eq!(
+        calls[calls.len() - 1],
+        vec!["rm", "-f", "mcp-personal-docker-user-web"]
+    );
+    let info = registry
+        .list_servers(TENANT)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|info| info.id == "web")
+        .unwrap();
+    assert!(info.status.starts_with("Failed"), "{:?}", info);
+    assert_eq!(info.container_status, None);
+}
@@ -0,0 +1,184 @@
+// Unit tests for running health sweeps alongside tool calls
+// Stubbed docker CLI with a slow `docker inspect` and a stubbed Lambda server; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::registry::docker::{DockerCli, DockerOutput};
+use mcp_rust::registry::lambda::{LambdaInvocation, LambdaInvoker};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+
+const TENANT: &str = "personal-sweep-user";
+
+/// How long each `docker inspect` takes once the sweep is made slow
+const INSPECT_DELAY: Duration = Duration::from_millis(400);
+
+/// Containers health-checked by each sweep
+const CONTAINERS: usize = 4;
+
+/// A docker CLI whose containers are always running; `docker inspect` takes
+/// `INSPECT_DELAY` once `slow` is set
+#[derive(Default)]
+struct SlowDocker {
+    slow: AtomicBool,
+}
+
+#[async_trait]
+impl DockerCli for SlowDocker {
+    async fn run(&self, args: Vec<String>) -> Result<DockerOutput, RegistryError> {
+        let stdout = match args[0].as_str() {
+            "inspect" => {
+                if self.slow.load(Ordering::SeqCst) {
+                    tokio::time::sleep(INSPECT_DELAY).await;
+                }
+                "running\n"
+            }
+            "run" => "c0ffee\n",
+            _ => "",
+        };
+        Ok(DockerOutput {
+            success: true,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        })
+    }
+}
+
+/// An MCP server with one `echo` tool that answers immediately
+struct EchoFunction;
+
+#[async_trait]
+impl LambdaInvoker for EchoFunction {
+    async fn invoke(
+        &self,
+        _region: &str,
+        _function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let request: Value = serde_json::from_slice(&payload).unwrap();
+        let result = match request["method"].as_str() {
+            Some("tools/list") => json!({"tools": [{"name": "echo", "inputSchema": {}}]}),
+            _ => json!({"content": [{"type": "text", "text": "echo"}]}),
+        };
+        Ok(LambdaInvocation {
+            function_error: None,
+            payload: json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+                .to_string()
+                .into_bytes(),
+        })
+    }
+}
+
+fn config(id: &str, deployment: DeploymentConfig) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: "health sweep".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment,
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 0,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+fn container() -> DeploymentConfig {
+    DeploymentConfig::Docker {
+        image: "ghcr.io/acme/mcp-sweep".to_string(),
+        tag: "latest".to_string(),
+        ports: Vec::new(),
+        volumes: Vec::new(),
+        network: None,
+        runtime: None,
+        memory_limit: None,
+        cpu_limit: None,
+        pids_limit: None,
+        read_only_rootfs: false,
+    }
+}
+
+/// A registry with `CONTAINERS` connected containers and a connected `echo` Lambda
+async fn registry(docker: Arc<SlowDocker>) -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_docker(docker)
+        .with_lambda_invoker(Arc::new(EchoFunction));
+
+    for n in 0..CONTAINERS {
+        let id = format!("container-{}", n);
+        registry
+            .register_server(TENANT, config(&id, container()))
+            .await
+            .unwrap();
+        registry.connect_server(TENANT, &id, None).await.unwrap();
+    }
+    let lambda = DeploymentConfig::Lambda {
+        function_name: "echo-mcp".to_string(),
+        region: "us-east-1".to_string(),
+    };
+    registry
+        .register_server(TENANT, config("echo", lambda))
+        .await
+        .unwrap();
+    registry.connect_server(TENANT, "echo", None).await.unwrap();
+    Arc::new(registry)
+}
+
+#[tokio::test]
+async fn test_calls_are_not_blocked_by_a_slow_sweep() {
+    let docker = Arc::new(SlowDocker::default());
+    let registry = registry(docker.clone()).await;
+    docker.slow.store(true, Ordering::SeqCst);
+
+    let sweep = tokio::spawn({
+        let registry = registry.clone();
+        async move {
+            let started = Instant::now();
+            registry.health_check().await;
+            started.elapsed()
+        }
+    });
+    // Let the sweep get to its `docker inspect`s
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let started = Instant::now();
+    for _ in 0..10 {
+        let result = registry
+            .execute_tool(TENANT, "echo", "echo", json!({}))
+            .await
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "echo");
+    }
+    let servers = registry.list_servers(TENANT).await.unwrap();
+    assert_eq!(servers.len(), CONTAINERS + 1);
+    let calls = started.elapsed();
+    assert!(
+        calls < INSPECT_DELAY / 2,
+        "calls took {:?} during the sweep",
+        calls
+    );
+    assert!(!sweep.is_finished(), "the sweep ended before the calls");
+
+    // The containers are inspected at the same time rather than one after another
+    let sweep = sweep.await.unwrap();
+    assert!(
+        sweep < INSPECT_DELAY * CONTAINERS as u32 / 2,
+        "sweep took {:?}",
+        sweep
+    );
+    for server in registry.list_servers(TENANT).await.unwrap() {
+        assert_eq!(server.status, "Connected", "{}", server.id);
+    }
+}
@@ -0,0 +1,283 @@
+// Unit tests for the registry's background health monitor
+// Short-lived local processes stand in for crashing MCP servers; no AWS access required
+
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerInfo, MCPServerRegistry, MCPServerType,
+    ReconnectPolicy, RegistryError, RestartPolicy,
+};
+
+/// Answers the initialize handshake and a `tools/list` of `tools` on stdio, then runs
+/// `command`
+fn mcp_script(tools: &[&str], command: &str) -> String {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools: Vec<_> = tools
+        .iter()
+        .map(|name| json!({"name": name, "inputSchema": {}}))
+        .collect();
+    let tools = json!({"jsonrpc": "2.0", "id": 2, "result": {"tools": tools}});
+    format!(
+        "read l; echo '{}'; read l; read l; echo '{}'; {}",
+        initialized, tools, command
+    )
+}
+
+fn process_config(id: &str, command: &str, auto_reconnect: bool) -> MCPServerConfig {
+    process_config_with_tools(id, &[], command, auto_reconnect)
+}
+
+fn process_config_with_tools(
+    id: &str,
+    tools: &[&str],
+    command: &str,
+    auto_reconnect: bool,
+) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), mcp_script(tools, command)],
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 0,
+        auto_reconnect,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+async fn registry() -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    Arc::new(
+        MCPServerRegistry::new(aws_service)
+            .with_store(Arc::new(MemoryKvStore::new()))
+            .with_secret_store(Arc::new(MemorySecretStore::new()))
+            .with_reconnect_policy(ReconnectPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(40),
+                reset_after: Duration::from_secs(60),
+            }),
+    )
+}
+
+async fn server(registry: &MCPServerRegistry, id: &str) -> MCPServerInfo {
+    registry
+        .list_servers("acme")
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|s| s.id == id)
+        .unwrap()
+}
+
+/// Poll until `check` passes for the server, failing after five seconds
+async fn wait_for(registry: &MCPServerRegistry, id: &str, check: impl Fn(&MCPServerInfo) -> bool) {
+    for _ in 0..250 {
+        if check(&server(registry, id).await) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!(
+        "server {} never reached the expected state: {:?}",
+        id,
+        server(registry, id).await
+    );
+}
+
+#[tokio::test]
+async fn test_exited_process_is_marked_failed_and_retried() {
+    let registry = registry().await;
+    registry
+        .register_server("acme", process_config("crashy", "exit 3", true))
+        .await
+        .unwrap();
+    registry
+        .connect_server("acme", "crashy", None)
+        .await
+        .unwrap();
+    assert_eq!(server(&registry, "crashy").await.status, "Connected");
+
+    let monitor = registry.spawn_health_monitor(Duration::from_millis(10));
+
+    // Every reconnect starts a process that exits again, until attempts run out
+    wait_for(&registry, "crashy", |s| {
+        s.status.starts_with("Failed") && s.reconnect_attempts == 3
+    })
+    .await;
+    let info = server(&registry, "crashy").await;
+    assert!(
+        info.last_error
+            .as_deref()
+            .unwrap()
+            .contains("Process exited"),
+        "{:?}",
+        info.last_error
+    );
+
+    // No further attempts once the cap is reached
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(server(&registry, "crashy").await.reconnect_attempts, 3);
+    monitor.abort();
+}
+
+#[tokio::test]
+async fn test_failed_process_stays_down_without_auto_reconnect() {
+    let registry = registry().await;
+    registry
+        .register_server("acme", process_config("oneshot", "exit 0", false))
+        .await
+        .unwrap();
+    registry
+        .connect_server("acme", "oneshot", None)
+        .await
+        .unwrap();
+
+    let monitor = registry.spawn_health_monitor(Duration::from_millis(10));
+    wait_for(&registry, "oneshot", |s| s.status.starts_with("Failed")).await;
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let info = server(&registry, "oneshot").await;
+    assert!(info.status.starts_with("Failed"));
+    assert_eq!(info.reconnect_attempts, 0);
+    monitor.abort();
+}
+
+#[tokio::test]
+async fn test_exited_process_tools_are_no_longer_resolved() {
+    let registry = registry().await;
+    registry
+        .register_server(
+            "acme",
+            process_config_with_tools("brief", &["lookup"], "sleep 0.2; exit 1", false),
+        )
+        .await
+        .unwrap();
+    registry
+        .connect_server("acme", "brief", None)
+        .await
+        .unwrap();
+    assert_eq!(
+        registry.resolve_tool("acme", "lookup").await.unwrap(),
+        "brief"
+    );
+
+    let monitor = registry.spawn_health_monitor(Duration::from_millis(10));
+    wait_for(&registry, "brief", |s| s.status.starts_with("Failed")).await;
+    assert!(matches!(
+        registry.resolve_tool("acme", "lookup").await,
+        Err(RegistryError::ToolNotFound(_))
+    ));
+    monitor.abort();
+}
+
+#[tokio::test]
+async fn test_healthy_process_stays_connected() {
+    let registry = registry().await;
+    registry
+        .register_server("acme", process_config("steady", "sleep 30", true))
+        .await
+        .unwrap();
+    registry
+        .connect_server("acme", "steady", None)
+        .await
+        .unwrap();
+
+    let monitor = registry.spawn_health_monitor(Duration::from_millis(10));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let info = server(&registry, "steady").await;
+    assert_eq!(info.status, "Connected");
+    assert_eq!(info.reconnect_attempts, 0);
+    monitor.abort();
+    registry.disconnect_server("acme", "steady").await.unwrap();
+}
+
+#[test]
+fn test_restart_policy_backoff_schedule() {
+    let policy: RestartPolicy = serde_json::from_value(
+        serde_json::json!({"backoff_initial_ms": 100, "backoff_max_ms": 350}),
+    )
+    .unwrap();
+    assert_eq!(policy.max_restarts, 5);
+    assert_eq!(policy.reset_after_secs, 60);
+
+    let policy = ReconnectPolicy::from(&policy);
+    let schedule: Vec<u128> = (0..5).map(|n| policy.backoff(n).as_millis()).collect();
+    assert_eq!(schedule, vec![100, 200, 350, 350, 350]);
+}
+
+#[tokio::test]
+async fn test_missing_binary_gives_up_after_max_restarts() {
+    let registry = registry().await;
+    let mut config = process_config("ghost", "", true);
+    config.deployment = DeploymentConfig::Process {
+        command: "/nonexistent/mcp-server".to_string(),
+        args: Vec::new(),
+    };
+    config.restart_policy = Some(RestartPolicy {
+        max_restarts: 2,
+        backoff_initial_ms: 60,
+        backoff_max_ms: 120,
+        reset_after_secs: 60,
+    });
+    registry.register_server("acme", config).await.unwrap();
+
+    let started = Instant::now();
+    assert!(registry
+        .connect_server("acme", "ghost", None)
+        .await
+        .is_err());
+    let info = server(&registry, "ghost").await;
+    assert_eq!((info.reconnect_attempts, info.max_restarts), (0, 2));
+
+    let monitor = registry.spawn_health_monitor(Duration::from_millis(5));
+
+    // The first restart waits the initial backoff, the second twice that
+    wait_for(&registry, "ghost", |s| s.reconnect_attempts == 1).await;
+    let first = started.elapsed();
+    assert!(first >= Duration::from_millis(60), "{:?}", first);
+    wait_for(&registry, "ghost", |s| s.reconnect_attempts == 2).await;
+    let second = started.elapsed() - first;
+    assert!(second >= Duration::from_millis(100), "{:?}", second);
+
+    wait_for(&registry, "ghost", |s| s.status.contains("Gave up")).await;
+    let info = server(&registry, "ghost").await;
+    assert!(
+        info.status
+            .starts_with("Failed(\"Gave up after 2 restarts: "),
+        "{}",
+        info.status
+    );
+    assert_eq!(
+        info.status.matches("No such file").count(),
+        3,
+        "{}",
+        info.status
+    );
+
+    // Terminal: no further restarts
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(server(&registry, "ghost").await.reconnect_attempts, 2);
+
+    // Connecting by hand clears the count and restarts resume
+    assert!(registry
+        .connect_server("acme", "ghost", None)
+        .await
+        .is_err());
+    let info = server(&registry, "ghost").await;
+    assert_eq!(info.reconnect_attempts, 0);
+    assert!(!info.status.contains("Gave up"), "{}", info.status);
+    wait_for(&registry, "ghost", |s| s.reconnect_attempts == 1).await;
+    monitor.abort();
+}
@@ -0,0 +1,122 @@
+// Unit tests for capturing what process-deployed servers write to stderr
+// Servers are local `sh` processes and stores are in memory; no AWS access required
+
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::integrations::IntegrationLogsHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::logs::{LogStream, LOG_BUFFER_LINES};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType,
+};
+use mcp_rust::tenant::TenantSession;
+
+const LINES: usize = 600;
+
+/// Writes `LINES` long lines to stderr (more than a pipe buffer holds) before answering
+/// the initialize handshake and an empty `tools/list`
+fn chatty_config() -> MCPServerConfig {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools = r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[]}}"#;
+    let padding = "x".repeat(200);
+    let script = format!(
+        "i=0; while [ $i -lt {} ]; do i=$((i+1)); echo \"line $i {}\" >&2; done; \
+         read l; echo '{}'; read l; read l; echo '{}'; cat >/dev/null",
+        LINES, padding, initialized, tools
+    );
+    MCPServerConfig {
+        id: "chatty".to_string(),
+        name: "Chatty".to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script],
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+fn session(user_id: &str) -> TenantSession {
+    TestTenant::admin(user_id)
+        .tenant("logs-tenant")
+        .organization_id("logs-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_stderr_is_drained_into_a_capped_buffer() {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = Arc::new(
+        MCPServerRegistry::new(aws_service)
+            .with_store(Arc::new(MemoryKvStore::new()))
+            .with_secret_store(Arc::new(MemorySecretStore::new())),
+    );
+    let tenant = session("logs-user").context.get_context_id();
+    registry
+        .register_server(&tenant, chatty_config())
+        .await
+        .unwrap();
+
+    // Would time out if nobody read the child's stderr
+    registry
+        .connect_server(&tenant, "chatty", None)
+        .await
+        .unwrap();
+
+    let mut logs = Vec::new();
+    for _ in 0..100 {
+        logs = registry.server_logs(&tenant, "chatty").await.unwrap();
+        if logs
+            .last()
+            .is_some_and(|log| log.line.starts_with(&format!("line {} ", LINES)))
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(logs.len(), LOG_BUFFER_LINES);
+    assert!(logs[0]
+        .line
+        .starts_with(&format!("line {} ", LINES - LOG_BUFFER_LINES + 1)));
+    assert!(logs.iter().all(|log| log.stream == LogStream::Stderr));
+    assert!(logs
+        .windows(2)
+        .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+
+    let handler = IntegrationLogsHandler::new(registry.clone());
+    let result = handler
+        .handle(
+            &session("logs-user"),
+            json!({"service_id": "chatty", "lines": 2}),
+        )
+        .await
+        .unwrap();
+    let lines = result["logs"].as_array().unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["stream"], "stderr");
+    assert!(lines[1]["timestamp"].as_str().is_some());
+    assert!(lines[1]["line"]
+        .as_str()
+        .unwrap()
+        .starts_with(&format!("line {} ", LINES)));
+
+    // Another user's context doesn't see the server
+    assert!(matches!(
+        handler
+            .handle(&session("other-user"), json!({"service_id": "chatty"}))
+            .await,
+        Err(HandlerError::InvalidArguments(_))
+    ));
+    registry.shutdown().await;
+}
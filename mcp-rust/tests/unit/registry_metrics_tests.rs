@@ -0,0 +1,159 @@
+// Unit tests for per-connection call, failure and uptime metrics
+// A Lambda-deployed server is stubbed; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::registry::lambda::{LambdaInvocation, LambdaInvoker};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerInfo, MCPServerRegistry, MCPServerType,
+    RegistryError,
+};
+
+const TENANT: &str = "personal-metrics-user";
+
+/// An MCP server whose `ok` tool succeeds and `fail` tool returns an error
+struct FlakyFunction;
+
+#[async_trait]
+impl LambdaInvoker for FlakyFunction {
+    async fn invoke(
+        &self,
+        _region: &str,
+        _function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let request: Value = serde_json::from_slice(&payload).unwrap();
+        let reply = match (
+            request["method"].as_str(),
+            request["params"]["name"].as_str(),
+        ) {
+            (Some("tools/list"), _) => json!({"jsonrpc": "2.0", "id": request["id"], "result": {
+                "tools": [{"name": "ok", "inputSchema": {}}, {"name": "fail", "inputSchema": {}}]
+            }}),
+            (_, Some("fail")) => json!({"jsonrpc": "2.0", "id": request["id"], "error": {
+                "code": -32000, "message": "upstream exploded"
+            }}),
+            _ => json!({"jsonrpc": "2.0", "id": request["id"], "result": {"content": []}}),
+        };
+        Ok(LambdaInvocation {
+            function_error: None,
+            payload: reply.to_string().into_bytes(),
+        })
+    }
+}
+
+async fn registry() -> MCPServerRegistry {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_lambda_invoker(Arc::new(FlakyFunction));
+    let config = MCPServerConfig {
+        id: "flaky".to_string(),
+        name: "Flaky".to_string(),
+        description: "lambda integration".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Lambda {
+            function_name: "flaky-mcp".to_string(),
+            region: "us-east-1".to_string(),
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    };
+    registry.register_server(TENANT, config).await.unwrap();
+    registry
+}
+
+async fn server(registry: &MCPServerRegistry) -> MCPServerInfo {
+    registry.list_servers(TENANT).await.unwrap().remove(0)
+}
+
+#[tokio::test]
+async fn test_calls_and_failures_are_counted() {
+    let registry = registry().await;
+    registry
+        .connect_server(TENANT, "flaky", None)
+        .await
+        .unwrap();
+
+    for tool in ["ok", "ok", "fail", "missing"] {
+        let _ = registry
+            .execute_tool(TENANT, "flaky", tool, json!({}))
+            .await;
+    }
+    // Not a known server, so nothing to count against
+    let _ = registry
+        .execute_tool(TENANT, "other", "ok", json!({}))
+        .await;
+
+    let metrics = server(&registry).await.metrics;
+    assert_eq!(metrics.tool_calls, 4);
+    assert_eq!(metrics.failures, 2);
+    assert_eq!(metrics.error_rate, 0.5);
+    assert_eq!(
+        metrics.last_error.as_deref(),
+        Some("Tool not found: missing")
+    );
+    assert!(metrics.connected_since.is_some());
+    assert!(metrics.uptime_secs.is_some());
+
+    let report = registry.metrics_report().await;
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].0, format!("{}:flaky", TENANT));
+    assert_eq!(report[0].1.tool_calls, 4);
+}
+
+#[tokio::test]
+async fn test_metrics_survive_reconnects() {
+    let registry = registry().await;
+    registry
+        .connect_server(TENANT, "flaky", None)
+        .await
+        .unwrap();
+    registry
+        .execute_tool(TENANT, "flaky", "ok", json!({}))
+        .await
+        .unwrap();
+
+    registry.disconnect_server(TENANT, "flaky").await.unwrap();
+    let metrics = server(&registry).await.metrics;
+    assert_eq!(metrics.connected_since, None);
+    assert_eq!(metrics.uptime_secs, None);
+
+    // Calls while disconnected fail and count
+    assert!(matches!(
+        registry
+            .execute_tool(TENANT, "flaky", "ok", json!({}))
+            .await,
+        Err(RegistryError::ServerNotConnected(_))
+    ));
+
+    registry
+        .connect_server(TENANT, "flaky", None)
+        .await
+        .unwrap();
+    registry
+        .execute_tool(TENANT, "flaky", "fail", json!({}))
+        .await
+        .unwrap_err();
+
+    let metrics = server(&registry).await.metrics;
+    assert_eq!(metrics.tool_calls, 3);
+    assert_eq!(metrics.failures, 2);
+    assert!(metrics.connected_since.is_some());
+    assert!(metrics
+        .last_error
+        .as_deref()
+        .is_some_and(|error| error.contains("upstream exploded")));
+
+    registry.unregister_server(TENANT, "flaky").await.unwrap();
+    assert!(registry.metrics_report().await.is_empty());
+}
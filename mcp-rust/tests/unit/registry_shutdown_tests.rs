@@ -0,0 +1,105 @@
+// Unit tests for stopping every downstream server when the main server exits
+// Servers are local `sh` processes and stores are in memory; no AWS access required
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType,
+};
+
+const TENANT: &str = "personal-shutdown-user";
+const GRACE: Duration = Duration::from_millis(300);
+
+/// Writes its pid to `pid_file`, answers the initialize handshake and an empty
+/// `tools/list` on stdio, then runs `command`
+fn process_config(id: &str, pid_file: &Path, command: &str) -> MCPServerConfig {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools = r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[]}}"#;
+    let script = format!(
+        "echo $$ > {}; read l; echo '{}'; read l; read l; echo '{}'; {}",
+        pid_file.display(),
+        initialized,
+        tools,
+        command
+    );
+    MCPServerConfig {
+        id: id.to_string(),
+        name: id.to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script],
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: true,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+fn pid_file(id: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mcp-shutdown-{}-{}.pid", id, uuid::Uuid::new_v4()))
+}
+
+/// Whether the process exists, zombies included
+fn is_alive(pid_file: &Path) -> bool {
+    let pid = std::fs::read_to_string(pid_file).unwrap();
+    std::process::Command::new("kill")
+        .args(["-0", pid.trim()])
+        .status()
+        .unwrap()
+        .success()
+}
+
+#[tokio::test]
+async fn test_shutdown_reaps_every_process() {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_shutdown_grace(GRACE);
+
+    // One exits on EOF, the other has to be killed
+    let polite = pid_file("polite");
+    let stubborn = pid_file("stubborn");
+    for config in [
+        process_config("polite", &polite, "cat >/dev/null"),
+        process_config(
+            "stubborn",
+            &stubborn,
+            "trap '' TERM; while :; do sleep 0.05; done",
+        ),
+    ] {
+        let id = config.id.clone();
+        registry.register_server(TENANT, config).await.unwrap();
+        registry.connect_server(TENANT, &id, None).await.unwrap();
+    }
+    assert!(is_alive(&polite) && is_alive(&stubborn));
+
+    let started = Instant::now();
+    registry.shutdown().await;
+    let elapsed = started.elapsed();
+    assert!(elapsed >= GRACE, "{:?}", elapsed);
+    assert!(elapsed < GRACE * 5, "{:?}", elapsed);
+
+    assert!(!is_alive(&polite), "polite child was not reaped");
+    assert!(!is_alive(&stubborn), "stubborn child was not reaped");
+    for server in registry.list_servers(TENANT).await.unwrap() {
+        assert_eq!(server.status, "Disconnected");
+    }
+
+    // A second shutdown has nothing left to do
+    let started = Instant::now();
+    registry.shutdown().await;
+    assert!(started.elapsed() < GRACE);
+
+    let _ = std::fs::remove_file(polite);
+    let _ = std::fs::remove_file(stubborn);
+}
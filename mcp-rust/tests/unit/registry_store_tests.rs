@@ -0,0 +1,285 @@
+// Unit tests for persisting and removing MCP server registrations and credentials
+// In-memory stores stand in for DynamoDB and Secrets Manager and a stub for Lambda; no
+// AWS access required
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use mcp_rust::aws::{
+    AwsService, DirectKvStore, KvSecretStore, MemoryKvStore, MemorySecretStore, SecretStore,
+};
+use mcp_rust::registry::lambda::{LambdaInvocation, LambdaInvoker};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+
+/// A Lambda-deployed MCP server exposing one tool
+struct EchoFunction;
+
+#[async_trait]
+impl LambdaInvoker for EchoFunction {
+    async fn invoke(
+        &self,
+        _region: &str,
+        _function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let request: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        let result = match request["method"].as_str() {
+            Some("tools/list") => json!({"tools": [{"name": "echo", "inputSchema": {}}]}),
+            _ => json!({"content": []}),
+        };
+        let reply = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+        Ok(LambdaInvocation {
+            function_error: None,
+            payload: reply.to_string().into_bytes(),
+        })
+    }
+}
+
+fn server_config(id: &str) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: format!("{} server", id),
+        description: "stored integration".to_string(),
+        server_type: MCPServerType::Http,
+        deployment: DeploymentConfig::Process {
+            command: "true".to_string(),
+            args: Vec::new(),
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+async fn registry_with(store: Arc<MemoryKvStore>) -> MCPServerRegistry {
+    registry_with_secrets(store, Arc::new(MemorySecretStore::new())).await
+}
+
+async fn registry_with_secrets(
+    store: Arc<MemoryKvStore>,
+    secrets: Arc<dyn SecretStore>,
+) -> MCPServerRegistry {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    MCPServerRegistry::new(aws_service)
+        .with_store(store)
+        .with_secret_store(secrets)
+}
+
+fn credentials(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+async fn store_config(store: &MemoryKvStore, key: &str, config: &MCPServerConfig) {
+    store
+        .kv_set_direct(key, &serde_json::to_string(config).unwrap(), None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_stored_configs_are_listed_after_restart() {
+    let store = Arc::new(MemoryKvStore::new());
+    store_config(&store, "mcp-registry-acme:github", &server_config("github")).await;
+    store_config(&store, "mcp-registry-acme:slack", &server_config("slack")).await;
+    store
+        .kv_set_direct("mcp-registry-acme:broken", "{not json", None)
+        .await
+        .unwrap();
+    // Belongs to tenant "acme-labs", whose id extends "acme"
+    store_config(
+        &store,
+        "mcp-registry-acme-labs:jira",
+        &server_config("jira"),
+    )
+    .await;
+
+    let registry = registry_with(store).await;
+    let mut servers = registry.list_servers("acme").await.unwrap();
+    servers.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let ids: Vec<&str> = servers.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["github", "slack"]);
+    assert_eq!(servers[0].name, "github server");
+    assert_eq!(servers[0].status, "Disconnected");
+    assert_eq!(servers[0].tool_count, 0);
+
+    let labs = registry.list_servers("acme-labs").await.unwrap();
+    assert_eq!(labs.len(), 1);
+    assert_eq!(labs[0].id, "jira");
+}
+
+#[tokio::test]
+async fn test_register_persists_and_unregister_deletes() {
+    let store = Arc::new(MemoryKvStore::new());
+    let registry = registry_with(store.clone()).await;
+    registry
+        .register_server("acme", server_config("github"))
+        .await
+        .unwrap();
+
+    let stored = store
+        .kv_get_direct("mcp-registry-acme:github")
+        .await
+        .unwrap()
+        .expect("config persisted");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&stored).unwrap()["id"],
+        json!("github")
+    );
+
+    // A fresh registry over the same store sees the registration
+    let restarted = registry_with(store.clone()).await;
+    assert_eq!(restarted.list_servers("acme").await.unwrap().len(), 1);
+
+    restarted.unregister_server("acme", "github").await.unwrap();
+    assert!(restarted.list_servers("acme").await.unwrap().is_empty());
+    assert!(store
+        .kv_get_direct("mcp-registry-acme:github")
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(matches!(
+        restarted.unregister_server("acme", "github").await,
+        Err(RegistryError::ServerNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_unregister_deletes_only_that_servers_credentials() {
+    let store = Arc::new(MemoryKvStore::new());
+    let secrets = Arc::new(MemorySecretStore::new());
+    let registry = registry_with_secrets(store, secrets.clone()).await;
+    for id in ["github", "github-enterprise"] {
+        registry
+            .register_server("acme", server_config(id))
+            .await
+            .unwrap();
+    }
+    registry
+        .store_credentials(
+            "acme",
+            "github",
+            &credentials(&[("api_key", "k1"), ("access_token", "t1")]),
+        )
+        .await
+        .unwrap();
+    registry
+        .store_credentials(
+            "acme",
+            "github-enterprise",
+            &credentials(&[("api_key", "k2")]),
+        )
+        .await
+        .unwrap();
+
+    registry.unregister_server("acme", "github").await.unwrap();
+
+    assert_eq!(secrets.names(), vec!["mcp/acme/github-enterprise/api_key"]);
+}
+
+#[tokio::test]
+async fn test_credentials_never_stored_in_kv() {
+    let store = Arc::new(MemoryKvStore::new());
+    let secrets = Arc::new(MemorySecretStore::new());
+    let registry = registry_with_secrets(store.clone(), secrets.clone()).await;
+    registry
+        .register_server("acme", server_config("github"))
+        .await
+        .unwrap();
+    registry
+        .store_credentials(
+            "acme",
+            "github",
+            &credentials(&[
+                ("api_key", "s3cret-key"),
+                ("client_secret", "s3cret-client"),
+            ]),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        secrets.names(),
+        vec!["mcp/acme/github/api_key", "mcp/acme/github/client_secret"]
+    );
+    assert_eq!(
+        secrets
+            .secret_get("acme", "github", "api_key")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("s3cret-key")
+    );
+    for key in store.kv_list("").await.unwrap() {
+        let value = store.kv_get_direct(&key).await.unwrap().unwrap();
+        assert!(!value.contains("s3cret"), "{} holds a credential", key);
+    }
+}
+
+#[tokio::test]
+async fn test_kv_fallback_keeps_credentials_per_server() {
+    let store = Arc::new(MemoryKvStore::new());
+    let secrets = Arc::new(KvSecretStore::new(store.clone()));
+    let registry = registry_with_secrets(store.clone(), secrets).await;
+    for id in ["github", "github-enterprise"] {
+        registry
+            .register_server("acme", server_config(id))
+            .await
+            .unwrap();
+        registry
+            .store_credentials("acme", id, &credentials(&[("api_key", "k")]))
+            .await
+            .unwrap();
+    }
+
+    registry.unregister_server("acme", "github").await.unwrap();
+
+    let remaining = store.kv_list("mcp-credential-").await.unwrap();
+    assert_eq!(
+        remaining,
+        vec!["mcp-credential-acme:github-enterprise:api_key"]
+    );
+}
+
+#[tokio::test]
+async fn test_unregister_disconnects_a_connected_server() {
+    let store = Arc::new(MemoryKvStore::new());
+    let registry = registry_with(store.clone())
+        .await
+        .with_lambda_invoker(Arc::new(EchoFunction));
+    let mut config = server_config("echo");
+    config.deployment = DeploymentConfig::Lambda {
+        function_name: "echo-mcp".to_string(),
+        region: "us-east-1".to_string(),
+    };
+    config.server_type = MCPServerType::Stdio;
+    registry.register_server("acme", config).await.unwrap();
+    registry.connect_server("acme", "echo", None).await.unwrap();
+
+    let servers = registry.list_servers("acme").await.unwrap();
+    assert_eq!(servers[0].status, "Connected");
+    assert_eq!(servers[0].tool_count, 1);
+
+    registry.unregister_server("acme", "echo").await.unwrap();
+
+    assert!(registry.list_servers("acme").await.unwrap().is_empty());
+    assert!(matches!(
+        registry
+            .execute_tool("acme", "echo", "echo", json!({}))
+            .await,
+        Err(RegistryError::ServerNotFound(_))
+    ));
+    assert!(store.kv_list("mcp-registry-").await.unwrap().is_empty());
+}
@@ -0,0 +1,191 @@
+// Unit tests for routing proxied tool calls through the registry's tool index
+// Two Lambda-deployed servers are stubbed with overlapping tool names; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::mcp_proxy::MCPProxyHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::registry::lambda::{LambdaInvocation, LambdaInvoker};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-index-user";
+
+/// Functions "github-mcp" (search, create_issue) and "jira-mcp" (search, create_ticket);
+/// each tool call answers with the function and tool that served it
+struct TwoFunctions;
+
+#[async_trait]
+impl LambdaInvoker for TwoFunctions {
+    async fn invoke(
+        &self,
+        _region: &str,
+        function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let request: Value = serde_json::from_slice(&payload).unwrap();
+        let tools: &[&str] = match function_name {
+            "github-mcp" => &["search", "create_issue"],
+            _ => &["search", "create_ticket"],
+        };
+        let result = match request["method"].as_str() {
+            Some("tools/list") => json!({
+                "tools": tools.iter().map(|t| json!({"name": t, "inputSchema": {}})).collect::<Vec<_>>()
+            }),
+            _ => json!({"content": [{
+                "type": "text",
+                "text": format!("{}:{}", function_name, request["params"]["name"].as_str().unwrap())
+            }]}),
+        };
+        let reply = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+        Ok(LambdaInvocation {
+            function_error: None,
+            payload: reply.to_string().into_bytes(),
+        })
+    }
+}
+
+fn lambda_config(id: &str) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: format!("{} server", id),
+        description: "lambda integration".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Lambda {
+            function_name: format!("{}-mcp", id),
+            region: "us-east-1".to_string(),
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+async fn connected_registry() -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()))
+        .with_lambda_invoker(Arc::new(TwoFunctions));
+    for id in ["github", "jira"] {
+        registry
+            .register_server(TENANT, lambda_config(id))
+            .await
+            .unwrap();
+        registry.connect_server(TENANT, id, None).await.unwrap();
+    }
+    Arc::new(registry)
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("index-user")
+        .tenant("index-tenant")
+        .organization_id("index-org")
+        .session()
+}
+
+async fn proxy(handler: &MCPProxyHandler, tool_name: &str) -> Result<String, HandlerError> {
+    let result = handler
+        .handle(&session(), json!({"tool_name": tool_name, "arguments": {}}))
+        .await?;
+    Ok(result["content"][0]["text"].as_str().unwrap().to_string())
+}
+
+#[tokio::test]
+async fn test_resolves_tools_to_the_server_providing_them() {
+    let registry = connected_registry().await;
+
+    assert_eq!(
+        registry.resolve_tool(TENANT, "create_issue").await.unwrap(),
+        "github"
+    );
+    assert_eq!(
+        registry
+            .resolve_tool(TENANT, "create_ticket")
+            .await
+            .unwrap(),
+        "jira"
+    );
+    assert!(matches!(
+        registry.resolve_tool(TENANT, "delete_repo").await,
+        Err(RegistryError::ToolNotFound(_))
+    ));
+    // Indexes are per tenant
+    assert!(matches!(
+        registry
+            .resolve_tool("personal-someone-else", "create_issue")
+            .await,
+        Err(RegistryError::ToolNotFound(_))
+    ));
+
+    match registry.resolve_tool(TENANT, "search").await {
+        Err(RegistryError::AmbiguousTool { tool, servers }) => {
+            assert_eq!(tool, "search");
+            assert_eq!(servers, vec!["github", "jira"]);
+        }
+        other => panic!("expected AmbiguousTool, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_proxy_routes_bare_and_prefixed_names() {
+    let handler = MCPProxyHandler::new(connected_registry().await);
+
+    assert_eq!(
+        proxy(&handler, "create_ticket").await.unwrap(),
+        "jira-mcp:create_ticket"
+    );
+    // The prefix selects the server and is stripped before the call
+    assert_eq!(
+        proxy(&handler, "jira.search").await.unwrap(),
+        "jira-mcp:search"
+    );
+
+    match proxy(&handler, "search").await {
+        Err(HandlerError::InvalidArguments(message)) => {
+            assert!(message.contains("github, jira"), "{}", message);
+            assert!(message.contains("<server_id>.search"), "{}", message);
+        }
+        other => panic!("expected InvalidArguments, got {:?}", other),
+    }
+    assert!(matches!(
+        proxy(&handler, "delete_repo").await,
+        Err(HandlerError::InvalidArguments(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_disconnect_removes_server_from_index() {
+    let registry = connected_registry().await;
+
+    registry.disconnect_server(TENANT, "jira").await.unwrap();
+
+    assert_eq!(
+        registry.resolve_tool(TENANT, "search").await.unwrap(),
+        "github"
+    );
+    assert!(matches!(
+        registry.resolve_tool(TENANT, "create_ticket").await,
+        Err(RegistryError::ToolNotFound(_))
+    ));
+
+    // Reconnecting refreshes the index
+    registry.connect_server(TENANT, "jira", None).await.unwrap();
+    assert_eq!(
+        registry
+            .resolve_tool(TENANT, "create_ticket")
+            .await
+            .unwrap(),
+        "jira"
+    );
+}
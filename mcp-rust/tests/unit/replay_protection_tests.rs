@@ -0,0 +1,224 @@
+// Unit tests for HTTP replay protection: seq windows, nonce caches and their expiry
+// Checks run against explicit clock values; no network or AWS access required
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use mcp_rust::config::{Config, ConfigError};
+use mcp_rust::mcp::{MCPError, MCPErrorResponse, MCPRequest};
+use mcp_rust::transport::replay::{
+    Freshness, ReplayReason, ReplayWindow, DEFAULT_REPLAY_TOLERANCE, MAX_NONCES, SEQ_WINDOW,
+};
+
+const TOLERANCE: Duration = Duration::from_secs(300);
+const NOW_MS: i64 = 1_800_000_000_000;
+
+fn nonce(nonce: &str, timestamp_ms: i64) -> Freshness {
+    Freshness::Nonce {
+        nonce: nonce.to_string(),
+        timestamp_ms,
+    }
+}
+
+fn reason(window: &ReplayWindow, freshness: Freshness, now_ms: i64) -> Option<ReplayReason> {
+    window
+        .check(&freshness, now_ms, TOLERANCE)
+        .err()
+        .map(|rejection| rejection.reason)
+}
+
+fn request(params: Value) -> MCPRequest {
+    serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/list",
+        "params": params
+    }))
+    .unwrap()
+}
+
+fn resolve(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    Config::resolve(|name| vars.get(name).cloned(), None)
+}
+
+#[test]
+fn test_freshness_is_read_from_params_meta() {
+    assert_eq!(
+        Freshness::from_request(&request(json!({"_meta": {"seq": 7}}))).unwrap(),
+        Freshness::Seq(7)
+    );
+    assert_eq!(
+        Freshness::from_request(&request(
+            json!({"_meta": {"nonce": "n-1", "timestamp": NOW_MS}})
+        ))
+        .unwrap(),
+        nonce("n-1", NOW_MS)
+    );
+
+    for params in [
+        json!({}),
+        json!({"_meta": {"nonce": "n-1"}}),
+        json!({"_meta": {"seq": -1}}),
+        json!({"_meta": {"nonce": "", "timestamp": NOW_MS}}),
+        json!({"_meta": {"nonce": "n-1", "timestamp": "yesterday"}}),
+    ] {
+        let rejection = Freshness::from_request(&request(params.clone())).unwrap_err();
+        assert_eq!(rejection.reason, ReplayReason::Missing, "{}", params);
+    }
+}
+
+#[test]
+fn test_duplicate_nonce_is_rejected() {
+    let window = ReplayWindow::new();
+    assert_eq!(reason(&window, nonce("a", NOW_MS), NOW_MS), None);
+    assert_eq!(reason(&window, nonce("b", NOW_MS), NOW_MS), None);
+    assert_eq!(
+        reason(&window, nonce("a", NOW_MS + 10), NOW_MS + 10),
+        Some(ReplayReason::Duplicate)
+    );
+}
+
+#[test]
+fn test_timestamps_outside_the_tolerance_are_stale() {
+    let window = ReplayWindow::new();
+    let tolerance_ms = TOLERANCE.as_millis() as i64;
+    assert_eq!(
+        reason(&window, nonce("old", NOW_MS - tolerance_ms - 1), NOW_MS),
+        Some(ReplayReason::Stale)
+    );
+    assert_eq!(
+        reason(&window, nonce("ahead", NOW_MS + tolerance_ms + 1), NOW_MS),
+        Some(ReplayReason::Stale)
+    );
+    // Extreme timestamps are refused without overflowing
+    for timestamp_ms in [i64::MIN, i64::MAX] {
+        assert_eq!(
+            reason(&window, nonce("extreme", timestamp_ms), NOW_MS),
+            Some(ReplayReason::Stale)
+        );
+    }
+    // Clock skew within the tolerance is fine either way
+    assert_eq!(
+        reason(&window, nonce("behind", NOW_MS - 1000), NOW_MS),
+        None
+    );
+    assert_eq!(
+        reason(&window, nonce("skewed", NOW_MS + 1000), NOW_MS),
+        None
+    );
+}
+
+#[test]
+fn test_seq_out_of_order_within_the_window() {
+    let window = ReplayWindow::new();
+    for seq in [1, 2, 5, 4, 3] {
+        assert_eq!(
+            reason(&window, Freshness::Seq(seq), NOW_MS),
+            None,
+            "{}",
+            seq
+        );
+    }
+    assert_eq!(
+        reason(&window, Freshness::Seq(4), NOW_MS),
+        Some(ReplayReason::Duplicate)
+    );
+    assert_eq!(
+        reason(&window, Freshness::Seq(5), NOW_MS),
+        Some(ReplayReason::Duplicate)
+    );
+
+    // Jumping ahead leaves the skipped values usable while they're in the window
+    let high = 5 + SEQ_WINDOW;
+    assert_eq!(reason(&window, Freshness::Seq(high), NOW_MS), None);
+    assert_eq!(
+        reason(&window, Freshness::Seq(high - SEQ_WINDOW + 1), NOW_MS),
+        None
+    );
+    assert_eq!(
+        reason(&window, Freshness::Seq(high - SEQ_WINDOW), NOW_MS),
+        Some(ReplayReason::Stale)
+    );
+    assert_eq!(
+        reason(&window, Freshness::Seq(2), NOW_MS),
+        Some(ReplayReason::Stale)
+    );
+}
+
+#[test]
+fn test_nonces_expire_with_the_tolerance() {
+    let window = ReplayWindow::new();
+    for i in 0..10 {
+        assert_eq!(
+            reason(&window, nonce(&format!("n-{}", i), NOW_MS), NOW_MS),
+            None
+        );
+    }
+    assert_eq!(window.nonce_count(), 10);
+
+    // Once the tolerance has passed the old nonces are forgotten, and reusing one is
+    // refused for its timestamp instead
+    let later = NOW_MS + TOLERANCE.as_millis() as i64 + 1;
+    assert_eq!(reason(&window, nonce("fresh", later), later), None);
+    assert_eq!(window.nonce_count(), 1);
+    assert_eq!(
+        reason(&window, nonce("n-0", NOW_MS), later),
+        Some(ReplayReason::Stale)
+    );
+}
+
+#[test]
+fn test_nonce_cache_is_bounded() {
+    let window = ReplayWindow::new();
+    for i in 0..MAX_NONCES as i64 + 10 {
+        let check = reason(&window, nonce(&format!("n-{}", i), NOW_MS + i), NOW_MS + i);
+        assert_eq!(check, None, "{}", i);
+    }
+    assert_eq!(window.nonce_count(), MAX_NONCES);
+    // A forgotten nonce still can't be replayed: its timestamp is below the floor
+    assert_eq!(
+        reason(&window, nonce("n-0", NOW_MS), NOW_MS + 20),
+        Some(ReplayReason::Stale)
+    );
+}
+
+#[test]
+fn test_replay_error_code_and_reason() {
+    let rejection = Freshness::from_request(&request(json!({}))).unwrap_err();
+    let error: MCPErrorResponse = MCPError::ReplayRejected { rejection }.into();
+    assert_eq!(error.code, -32011);
+    assert_eq!(error.data, Some(json!({"reason": "missing"})));
+}
+
+#[test]
+fn test_replay_settings() {
+    let config = resolve(&[]).unwrap();
+    assert!(!config.server.replay_protection);
+    assert_eq!(config.server.replay_tolerance, DEFAULT_REPLAY_TOLERANCE);
+
+    let config = resolve(&[
+        ("MCP_REPLAY_PROTECTION", "true"),
+        ("MCP_REPLAY_TOLERANCE_SECS", "30"),
+    ])
+    .unwrap();
+    assert!(config.server.replay_protection);
+    assert_eq!(config.server.replay_tolerance, Duration::from_secs(30));
+
+    for vars in [
+        vec![
+            ("MCP_REPLAY_PROTECTION", "true"),
+            ("MCP_REPLAY_TOLERANCE_SECS", "0"),
+        ],
+        vec![("MCP_REPLAY_TOLERANCE_SECS", "30")],
+    ] {
+        let Err(ConfigError::Invalid { key, .. }) = resolve(&vars) else {
+            panic!("expected {:?} to be rejected", vars);
+        };
+        assert_eq!(key, "server.replay_tolerance_secs");
+    }
+}
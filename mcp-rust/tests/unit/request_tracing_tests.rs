@@ -0,0 +1,228 @@
+// Unit tests for the per-request tracing spans
+// A capturing layer records each span's name, parent and fields during in-memory calls
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use mcp_rust::aws::latency::{AwsCall, AwsLatency, AWS_CALL_SPAN};
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions, TOOL_CALL_SPAN};
+use mcp_rust::mcp::{MCPServer, REQUEST_SPAN};
+use mcp_rust::tenant::{Permission, TenantManager, TenantSession};
+
+#[derive(Debug, Clone)]
+struct CapturedSpan {
+    name: &'static str,
+    parent: Option<u64>,
+    fields: BTreeMap<String, String>,
+}
+
+/// Keeps every span opened while it's the default subscriber, by id
+#[derive(Clone, Default)]
+struct Capture {
+    spans: Arc<Mutex<HashMap<u64, CapturedSpan>>>,
+}
+
+impl Capture {
+    fn named(&self, name: &str) -> Vec<(u64, CapturedSpan)> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, span)| span.name == name)
+            .map(|(id, span)| (*id, span.clone()))
+            .collect()
+    }
+
+    fn all_field_values(&self) -> Vec<String> {
+        self.spans
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(|span| span.fields.values().cloned())
+            .collect()
+    }
+}
+
+struct Fields<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let parent = ctx
+            .span(id)
+            .and_then(|span| span.parent())
+            .map(|parent| parent.id().into_u64());
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut Fields(&mut fields));
+        self.spans.lock().unwrap().insert(
+            id.into_u64(),
+            CapturedSpan {
+                name: attrs.metadata().name(),
+                parent,
+                fields,
+            },
+        );
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+            values.record(&mut Fields(&mut span.fields));
+        }
+    }
+}
+
+/// Makes one (unsent) AWS call, the way the AWS backend's methods do
+struct AwsProbe;
+
+#[async_trait]
+impl Handler for AwsProbe {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let latency = AwsLatency::new();
+        AwsCall::new(&latency, "dynamodb", "GetItem", "kv-table")
+            .run(async { Ok::<_, HandlerError>(()) })
+            .await?;
+        Ok(json!({}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({ "description": "Probe", "inputSchema": { "type": "object" } })
+    }
+}
+
+async fn memory_server() -> MCPServer {
+    std::env::set_var("DEV_MODE", "true");
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let mut registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    registry.register("aws_probe", Arc::new(AwsProbe));
+    MCPServer::new(tenant_manager, Some(registry))
+        .await
+        .unwrap()
+}
+
+async fn call(server: &MCPServer, id: u64, name: &str, arguments: Value) -> Value {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/call",
+        "tenant_id": "demo-tenant",
+        "user_id": "user-demo-123",
+        "params": {"name": name, "arguments": arguments}
+    });
+    let response = server.handle_request(&request.to_string()).await.unwrap();
+    serde_json::to_value(response).unwrap()
+}
+
+#[tokio::test]
+async fn test_kv_set_traced_under_request_and_tool_spans() {
+    let capture = Capture::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+    let server = memory_server().await;
+
+    let response = call(
+        &server,
+        7,
+        "kv_set",
+        json!({"key": "greeting", "value": "s3cret-value"}),
+    )
+    .await;
+    assert!(response["error"].is_null(), "{}", response);
+
+    let requests = capture.named(REQUEST_SPAN);
+    assert_eq!(requests.len(), 1);
+    let (request_id, request) = &requests[0];
+    assert_eq!(request.fields["request.id"], "7");
+    assert_eq!(request.fields["mcp.method"], "tools/call");
+    assert_eq!(request.fields["tenant.id"], "demo-tenant");
+    assert_eq!(request.fields["user.id"], "user-demo-123");
+    assert!(!request.fields["session.id"].is_empty());
+    assert_eq!(request.fields["tool"], "kv_set");
+    assert_eq!(request.fields["outcome"], "ok");
+    assert!(request.fields.contains_key("duration_ms"));
+    assert!(!request.fields.contains_key("error.code"));
+
+    let tools = capture.named(TOOL_CALL_SPAN);
+    assert_eq!(tools.len(), 1);
+    let (_, tool) = &tools[0];
+    assert_eq!(tool.parent, Some(*request_id));
+    assert_eq!(tool.fields["tool"], "kv_set");
+    assert_eq!(tool.fields["outcome"], "ok");
+    assert!(tool.fields.contains_key("duration_ms"));
+
+    // Argument values never make it into a span
+    assert!(!capture
+        .all_field_values()
+        .iter()
+        .any(|value| value.contains("s3cret-value")));
+}
+
+#[tokio::test]
+async fn test_failed_call_records_error_code() {
+    let capture = Capture::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+    let server = memory_server().await;
+
+    let response = call(&server, 8, "kv_get", json!({})).await;
+    assert_eq!(response["error"]["code"], -32602);
+
+    let (_, request) = &capture.named(REQUEST_SPAN)[0];
+    assert_eq!(request.fields["outcome"], "error");
+    assert_eq!(request.fields["error.code"], "-32602");
+    let (_, tool) = &capture.named(TOOL_CALL_SPAN)[0];
+    assert_eq!(tool.fields["outcome"], "error");
+}
+
+#[tokio::test]
+async fn test_aws_calls_nest_inside_the_tool_call() {
+    let capture = Capture::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+    let server = memory_server().await;
+
+    let response = call(&server, 9, "aws_probe", json!({})).await;
+    assert!(response["error"].is_null(), "{}", response);
+
+    let (request_id, _) = capture.named(REQUEST_SPAN)[0].clone();
+    let (tool_id, tool) = capture.named(TOOL_CALL_SPAN)[0].clone();
+    let (_, aws) = capture.named(AWS_CALL_SPAN)[0].clone();
+    assert_eq!(tool.parent, Some(request_id));
+    assert_eq!(aws.parent, Some(tool_id));
+    assert_eq!(aws.fields["aws.operation"], "GetItem");
+    assert_eq!(aws.fields["aws.outcome"], "ok");
+}
@@ -0,0 +1,224 @@
+// Unit tests for resolving the configured AWS resource names and the startup check of them
+// A stubbed prober stands in for DescribeTable, HeadBucket and DescribeEventBus; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use mcp_rust::aws::resources::{
+    validate_resources, ResourceKind, ResourceNames, ResourceProber, ResourceStatus,
+};
+use mcp_rust::aws::AwsError;
+
+/// Answers `Ok` for every resource except those given another status, and records
+/// the names it was asked about
+#[derive(Default)]
+struct StubProber {
+    statuses: HashMap<String, ResourceStatus>,
+    asked: Mutex<Vec<String>>,
+}
+
+impl StubProber {
+    fn with(mut self, name: &str, status: ResourceStatus) -> Self {
+        self.statuses.insert(name.to_string(), status);
+        self
+    }
+
+    fn status(&self, kind: &str, name: &str) -> ResourceStatus {
+        self.asked
+            .lock()
+            .unwrap()
+            .push(format!("{}:{}", kind, name));
+        self.statuses
+            .get(name)
+            .cloned()
+            .unwrap_or(ResourceStatus::Ok)
+    }
+}
+
+#[async_trait]
+impl ResourceProber for StubProber {
+    async fn describe_table(&self, name: &str) -> ResourceStatus {
+        self.status("table", name)
+    }
+
+    async fn head_bucket(&self, name: &str) -> ResourceStatus {
+        self.status("bucket", name)
+    }
+
+    async fn describe_event_bus(&self, name: &str) -> ResourceStatus {
+        self.status("bus", name)
+    }
+}
+
+fn names() -> ResourceNames {
+    ResourceNames {
+        kv_table: "prod-kv".to_string(),
+        events_table: "prod-events".to_string(),
+        event_rules_table: "prod-event-rules".to_string(),
+        subscriptions_table: "prod-subscriptions".to_string(),
+        dead_letter_table: "prod-kv".to_string(),
+        artifacts_bucket: "prod-artifacts".to_string(),
+        event_bus: "prod-bus".to_string(),
+        queue_prefix: "prod".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_every_configured_resource_is_checked() {
+    let prober = StubProber::default();
+    let report = validate_resources(&prober, &names()).await;
+
+    assert!(report.is_ok());
+    assert!(report.problems().is_empty());
+    assert_eq!(
+        *prober.asked.lock().unwrap(),
+        [
+            "table:prod-kv",
+            "table:prod-events",
+            "table:prod-event-rules",
+            "table:prod-subscriptions",
+            "bucket:prod-artifacts",
+            "bus:prod-bus",
+        ]
+    );
+    report.enforce(true).unwrap();
+}
+
+#[tokio::test]
+async fn test_report_names_each_unusable_resource() {
+    let prober = StubProber::default()
+        .with("prod-kv", ResourceStatus::Missing)
+        .with("prod-artifacts", ResourceStatus::Forbidden)
+        .with(
+            "prod-bus",
+            ResourceStatus::Error("connection refused".to_string()),
+        );
+    let report = validate_resources(&prober, &names()).await;
+
+    assert!(!report.is_ok());
+    let kv = &report.checks[0];
+    assert_eq!(kv.kind, ResourceKind::KvTable);
+    assert_eq!(kv.name, "prod-kv");
+    assert_eq!(
+        report.problems(),
+        [
+            "KV table \"prod-kv\" doesn't exist (set by AGENT_MESH_KV_TABLE)",
+            "Artifacts bucket \"prod-artifacts\" isn't accessible with these credentials (set by AGENT_MESH_ARTIFACTS_BUCKET)",
+            "Event bus \"prod-bus\" couldn't be checked (connection refused) (set by AGENT_MESH_EVENT_BUS)",
+        ]
+    );
+
+    let checks = serde_json::to_value(&report).unwrap()["checks"].clone();
+    assert_eq!(
+        checks[0],
+        json!({"kind": "kv_table", "name": "prod-kv", "status": "missing"})
+    );
+    assert_eq!(
+        checks[1],
+        json!({"kind": "events_table", "name": "prod-events", "status": "ok"})
+    );
+    assert_eq!(
+        checks[5],
+        json!({"kind": "event_bus", "name": "prod-bus", "status": "error", "message": "connection refused"})
+    );
+}
+
+#[tokio::test]
+async fn test_strict_validation_fails_fast() {
+    let prober = StubProber::default().with("prod-events", ResourceStatus::Missing);
+    let report = validate_resources(&prober, &names()).await;
+
+    // Lenient validation only warns
+    report.enforce(false).unwrap();
+    match report.enforce(true) {
+        Err(AwsError::Config(message)) => {
+            assert!(message.contains("\"prod-events\""), "{}", message);
+            assert!(message.contains("AGENT_MESH_EVENTS_TABLE"), "{}", message);
+        }
+        other => panic!("expected a Config error, got {:?}", other),
+    }
+}
+
+fn resolve(vars: &[(&str, &str)]) -> Result<ResourceNames, AwsError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    ResourceNames::resolve(|name| vars.get(name).cloned())
+}
+
+#[test]
+fn test_names_default_without_configuration() {
+    let names = resolve(&[]).unwrap();
+    assert_eq!(names.kv_table, "agent-mesh-kv");
+    assert_eq!(names.events_table, "agent-mesh-dev-events");
+    assert_eq!(names.event_rules_table, "agent-mesh-dev-event-rules");
+    assert_eq!(names.subscriptions_table, "agent-mesh-dev-subscriptions");
+    assert_eq!(names.dead_letter_table, "agent-mesh-kv");
+    assert_eq!(names.artifacts_bucket, "agent-mesh-artifacts");
+    assert_eq!(names.event_bus, "agent-mesh-events");
+    assert_eq!(names.queue_prefix, "agent-mesh");
+}
+
+#[test]
+fn test_variables_take_precedence_over_the_environment() {
+    let names = resolve(&[
+        ("AGENT_MESH_ENVIRONMENT", "prod"),
+        ("AGENT_MESH_EVENTS_TABLE", "legacy-events"),
+        ("AGENT_MESH_KV_TABLE", "shared-kv"),
+    ])
+    .unwrap();
+    assert_eq!(names.events_table, "legacy-events");
+    assert_eq!(names.kv_table, "shared-kv");
+    assert_eq!(names.event_rules_table, "agent-mesh-prod-event-rules");
+    assert_eq!(names.subscriptions_table, "agent-mesh-prod-subscriptions");
+    assert_eq!(names.artifacts_bucket, "agent-mesh-prod-artifacts");
+    assert_eq!(names.event_bus, "agent-mesh-prod-events");
+    // Queue names have a prefix of their own
+    assert_eq!(names.queue_prefix, "agent-mesh");
+    // Dead letters follow the KV table, wherever it's configured
+    assert_eq!(names.dead_letter_table, "shared-kv");
+
+    let names = resolve(&[
+        ("AGENT_MESH_ENVIRONMENT", "staging"),
+        ("AGENT_MESH_DEAD_LETTER_TABLE", "staging-dlq"),
+    ])
+    .unwrap();
+    assert_eq!(names.kv_table, "agent-mesh-staging-kv");
+    assert_eq!(names.dead_letter_table, "staging-dlq");
+}
+
+#[test]
+fn test_empty_names_fail_fast() {
+    for var in [
+        "AGENT_MESH_ENVIRONMENT",
+        "AGENT_MESH_KV_TABLE",
+        "AGENT_MESH_SUBSCRIPTIONS_TABLE",
+        "AGENT_MESH_DEAD_LETTER_TABLE",
+        "AGENT_MESH_QUEUE_PREFIX",
+    ] {
+        match resolve(&[(var, " ")]) {
+            Err(AwsError::Config(message)) => assert!(message.contains(var), "{}", message),
+            other => panic!("{} set empty resolved to {:?}", var, other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_separate_dead_letter_table_is_checked() {
+    let prober = StubProber::default().with("prod-dlq", ResourceStatus::Missing);
+    let names = ResourceNames {
+        dead_letter_table: "prod-dlq".to_string(),
+        ..names()
+    };
+    let report = validate_resources(&prober, &names).await;
+
+    assert_eq!(report.checks.len(), 7);
+    assert_eq!(report.checks[4].kind, ResourceKind::DeadLetterTable);
+    assert_eq!(
+        report.problems(),
+        ["Dead-letter table \"prod-dlq\" doesn't exist (set by AGENT_MESH_DEAD_LETTER_TABLE)"]
+    );
+}
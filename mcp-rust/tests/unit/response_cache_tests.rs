@@ -0,0 +1,260 @@
+// Unit tests for the response cache of read-only tools and its invalidation by writes
+// HandlerRegistry over MemoryAwsService with a local `sh` MCP server; no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::response_cache::{CacheInvalidation, ResponseCache};
+use mcp_rust::handlers::{HandlerRegistry, RegistryOptions};
+use mcp_rust::registry::{AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerType};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session(tenant_id: &str, user_id: &str) -> TenantSession {
+    TestTenant::user(user_id)
+        .tenant(tenant_id)
+        .organization_id("demo-org")
+        .permissions(vec![
+            Permission::ListArtifacts,
+            Permission::PutArtifacts,
+            Permission::Read,
+            Permission::Write,
+            Permission::SendEvents,
+        ])
+        .session()
+}
+
+async fn registry(cache: ResponseCache) -> HandlerRegistry {
+    HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions {
+            health_monitor: false,
+            response_cache: Some(Arc::new(cache)),
+            ..RegistryOptions::default()
+        },
+    )
+    .await
+}
+
+async fn call(
+    registry: &HandlerRegistry,
+    session: &TenantSession,
+    tool: &str,
+    arguments: Value,
+) -> Value {
+    registry
+        .handle_tool_call(session, tool, arguments)
+        .await
+        .unwrap()
+}
+
+async fn put(registry: &HandlerRegistry, session: &TenantSession, key: &str) {
+    call(
+        registry,
+        session,
+        "artifacts_put",
+        json!({"key": key, "content": "eA=="}),
+    )
+    .await;
+}
+
+async fn list(registry: &HandlerRegistry, session: &TenantSession, prefix: &str) -> Value {
+    call(
+        registry,
+        session,
+        "artifacts_list",
+        json!({"prefix": prefix}),
+    )
+    .await
+}
+
+/// Answers the initialize handshake and an empty `tools/list`
+fn local_server() -> MCPServerConfig {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools = r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[]}}"#;
+    MCPServerConfig {
+        id: "local".to_string(),
+        name: "Local".to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!(
+                    "read l; echo '{}'; read l; read l; echo '{}'; cat >/dev/null",
+                    initialized, tools
+                ),
+            ],
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+#[tokio::test]
+async fn test_repeated_call_is_served_from_the_cache() {
+    let registry = registry(ResponseCache::new(100, None)).await;
+    let alice = session("alice-tenant", "alice");
+    put(&registry, &alice, "reports/q1.txt").await;
+
+    let first = list(&registry, &alice, "reports/").await;
+    assert!(first.get("cached").is_none());
+    assert_eq!(first["keys"], json!(["reports/q1.txt"]));
+
+    let second = list(&registry, &alice, "reports/").await;
+    assert_eq!(second["cached"], true);
+    assert!(second["cache_age_ms"].is_u64());
+    assert_eq!(second["keys"], first["keys"]);
+
+    // Other arguments, and other callers, are cached apart
+    let other = call(&registry, &alice, "artifacts_list", json!({})).await;
+    assert!(other.get("cached").is_none());
+    let bob = session("alice-tenant", "bob");
+    let bobs = list(&registry, &bob, "reports/").await;
+    assert!(bobs.get("cached").is_none());
+    assert_eq!(bobs["keys"], json!([]));
+
+    let stats = registry.response_cache().unwrap().stats();
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.entries, 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_entries_expire_after_the_tools_ttl() {
+    let registry = registry(ResponseCache::new(100, None)).await;
+    let alice = session("alice-tenant", "alice");
+    call(&registry, &alice, "artifacts_list", json!({})).await;
+
+    tokio::time::advance(Duration::from_secs(29)).await;
+    let cached = call(&registry, &alice, "artifacts_list", json!({})).await;
+    assert_eq!(cached["cached"], true);
+    assert_eq!(cached["cache_age_ms"], 29_000);
+
+    tokio::time::advance(Duration::from_secs(2)).await;
+    let fresh = call(&registry, &alice, "artifacts_list", json!({})).await;
+    assert!(fresh.get("cached").is_none());
+}
+
+#[tokio::test]
+async fn test_artifacts_put_invalidates_overlapping_listings() {
+    let registry = registry(ResponseCache::new(100, None)).await;
+    let alice = session("alice-tenant", "alice");
+    let bob = session("alice-tenant", "bob");
+    for prefix in ["reports/", "images/"] {
+        list(&registry, &alice, prefix).await;
+        list(&registry, &bob, prefix).await;
+    }
+
+    put(&registry, &alice, "reports/q2.txt").await;
+
+    let reports = list(&registry, &alice, "reports/").await;
+    assert!(reports.get("cached").is_none());
+    assert_eq!(reports["keys"], json!(["reports/q2.txt"]));
+    // The whole tenant's entries for the prefix go, not only the writer's
+    let bobs = list(&registry, &bob, "reports/").await;
+    assert!(bobs.get("cached").is_none());
+    let images = list(&registry, &alice, "images/").await;
+    assert_eq!(images["cached"], true);
+
+    // A dry run changes nothing, so invalidates nothing
+    call(
+        &registry,
+        &alice,
+        "artifacts_delete_prefix",
+        json!({"prefix": "images/", "dry_run": true}),
+    )
+    .await;
+    let images = list(&registry, &alice, "images/").await;
+    assert_eq!(images["cached"], true);
+}
+
+#[tokio::test]
+async fn test_integration_connect_invalidates_integration_list() {
+    let registry = registry(ResponseCache::new(100, None)).await;
+    let alice = session("alice-tenant", "alice");
+    registry
+        .mcp_registry()
+        .register_server(&alice.context.get_context_id(), local_server())
+        .await
+        .unwrap();
+
+    let before = call(&registry, &alice, "integration_list", json!({})).await;
+    assert_eq!(before["user_connections"], json!([]));
+    let cached = call(&registry, &alice, "integration_list", json!({})).await;
+    assert_eq!(cached["cached"], true);
+
+    call(
+        &registry,
+        &alice,
+        "integration_connect",
+        json!({"service_id": "local"}),
+    )
+    .await;
+    let after = call(&registry, &alice, "integration_list", json!({})).await;
+    assert!(after.get("cached").is_none());
+    assert_eq!(after["user_connections"].as_array().unwrap().len(), 1);
+    registry.mcp_registry().shutdown().await;
+}
+
+#[test]
+fn test_tenants_are_capped_and_invalidated_apart() {
+    let cache = ResponseCache::new(3, Some(2));
+    let alice = session("alice-tenant", "alice");
+    let bob = session("bob-tenant", "bob");
+    let ttl = Duration::from_secs(60);
+    let insert = |session: &TenantSession, tool: &str, n: u64| {
+        let ticket = cache.ticket(session);
+        cache.insert(
+            session,
+            tool,
+            &json!({"n": n}),
+            json!({"n": n}),
+            ttl,
+            ticket,
+        );
+    };
+
+    // Alice's third entry pushes out her first, not Bob's
+    insert(&bob, "integration_list", 0);
+    for n in 0..3 {
+        insert(&alice, "integration_list", n);
+    }
+    assert!(cache
+        .get(&bob, "integration_list", &json!({"n": 0}))
+        .is_some());
+    assert!(cache
+        .get(&alice, "integration_list", &json!({"n": 0}))
+        .is_none());
+    assert!(cache
+        .get(&alice, "integration_list", &json!({"n": 2}))
+        .is_some());
+
+    // A full cache pushes out the least recently used entry of any tenant
+    insert(&bob, "events_analytics", 1);
+    assert!(cache
+        .get(&alice, "integration_list", &json!({"n": 1}))
+        .is_none());
+    assert_eq!(cache.stats().entries, 3);
+
+    cache.invalidate(&alice, &CacheInvalidation::Tool("integration_list"));
+    assert!(cache
+        .get(&alice, "integration_list", &json!({"n": 2}))
+        .is_none());
+    assert!(cache
+        .get(&bob, "integration_list", &json!({"n": 0}))
+        .is_some());
+
+    // A response read before an invalidation isn't cached after it
+    let ticket = cache.ticket(&bob);
+    cache.invalidate(&bob, &CacheInvalidation::Tool("events_analytics"));
+    cache.insert(&bob, "integration_list", &json!({}), json!({}), ttl, ticket);
+    assert!(cache.get(&bob, "integration_list", &json!({})).is_none());
+}
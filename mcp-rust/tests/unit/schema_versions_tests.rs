@@ -0,0 +1,189 @@
+// Unit tests for tool schema versions and deprecation warnings
+// tools/list and tools/call over the in-memory backend; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::handlers::schema_versions::{Deprecation, ToolVersion, TOOLS_SCHEMA_VERSION};
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::mcp::MCPServer;
+use mcp_rust::tenant::{Permission, TenantManager, TenantSession};
+
+/// A tool on its way out in favour of `kv_get`
+struct LegacyLookup;
+
+#[async_trait]
+impl Handler for LegacyLookup {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        Ok(json!({"value": null}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({ "description": "Look up a key the old way", "inputSchema": { "type": "object" } })
+    }
+
+    fn tool_version(&self) -> ToolVersion {
+        ToolVersion {
+            version: 3,
+            deprecation: Some(Deprecation {
+                message: "legacy_lookup is deprecated; use kv_get",
+                replaced_by: Some("kv_get"),
+            }),
+        }
+    }
+}
+
+async fn memory_server() -> MCPServer {
+    std::env::set_var("DEV_MODE", "true");
+    let tenant_manager = Arc::new(TenantManager::new().await.unwrap());
+    let mut registry = HandlerRegistry::with_backend(
+        Arc::new(MemoryAwsService::new()),
+        RegistryOptions::default(),
+    )
+    .await;
+    registry.register("legacy_lookup", Arc::new(LegacyLookup));
+    MCPServer::new(tenant_manager, Some(registry))
+        .await
+        .unwrap()
+}
+
+async fn request(server: &MCPServer, method: &str, params: Value) -> Value {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "tenant_id": "demo-tenant",
+        "user_id": "user-demo-123",
+        "params": params
+    });
+    let response = server.handle_request(&request.to_string()).await.unwrap();
+    let response = serde_json::to_value(response).unwrap();
+    assert!(response["error"].is_null(), "{}", response);
+    response["result"].clone()
+}
+
+fn tool<'a>(tools: &'a Value, name: &str) -> &'a Value {
+    tools["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tool| tool["name"] == name)
+        .unwrap_or_else(|| panic!("{} isn't listed", name))
+}
+
+#[tokio::test]
+async fn test_tools_list_reports_versions_and_deprecations() {
+    let server = memory_server().await;
+    let tools = request(&server, "tools/list", json!({})).await;
+    assert_eq!(tools["schemaVersion"], TOOLS_SCHEMA_VERSION);
+
+    // Every tool says where it stands
+    for listed in tools["tools"].as_array().unwrap() {
+        assert!(listed["version"].as_u64().unwrap() >= 1, "{}", listed);
+        assert!(listed["deprecated"].is_boolean(), "{}", listed);
+    }
+    let kv_get = tool(&tools, "kv_get");
+    assert_eq!(kv_get["version"], 1);
+    assert_eq!(kv_get["deprecated"], false);
+    assert!(kv_get.get("replacedBy").is_none());
+
+    let legacy = tool(&tools, "legacy_lookup");
+    assert_eq!(legacy["version"], 3);
+    assert_eq!(legacy["deprecated"], true);
+    assert_eq!(legacy["replacedBy"], "kv_get");
+
+    // events_send's single-event arguments are deprecated, not the tool
+    let events_send = tool(&tools, "events_send");
+    assert_eq!(events_send["version"], 2);
+    assert_eq!(events_send["deprecated"], false);
+    let properties = &events_send["inputSchema"]["properties"];
+    assert_eq!(properties["detailType"]["deprecated"], true);
+    assert_eq!(properties["detail"]["deprecated"], true);
+    assert!(properties["events"].is_object());
+}
+
+#[tokio::test]
+async fn test_calling_a_deprecated_tool_warns_in_meta() {
+    let server = memory_server().await;
+    let result = request(
+        &server,
+        "tools/call",
+        json!({"name": "legacy_lookup", "arguments": {}}),
+    )
+    .await;
+    assert_eq!(
+        result["_meta"]["deprecation"],
+        json!({
+            "tool": "legacy_lookup",
+            "message": "legacy_lookup is deprecated; use kv_get",
+            "replacedBy": "kv_get"
+        })
+    );
+
+    let result = request(
+        &server,
+        "tools/call",
+        json!({"name": "kv_set", "arguments": {"key": "k", "value": "v"}}),
+    )
+    .await;
+    assert!(result.get("_meta").is_none(), "{}", result);
+}
+
+#[tokio::test]
+async fn test_single_event_send_is_deprecated_but_works() {
+    let server = memory_server().await;
+    let result = request(
+        &server,
+        "tools/call",
+        json!({"name": "events_send", "arguments": {
+            "detailType": "demo.single",
+            "detail": {},
+            "buffered": false
+        }}),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+    let warning = &result["_meta"]["deprecation"];
+    assert_eq!(warning["tool"], "events_send");
+    assert!(warning["message"].as_str().unwrap().contains("events"));
+
+    let result = request(
+        &server,
+        "tools/call",
+        json!({"name": "events_send", "arguments": {
+            "events": [
+                {"detailType": "demo.first", "detail": {}},
+                {"detailType": "demo.second", "detail": {"n": 2}}
+            ],
+            "buffered": false
+        }}),
+    )
+    .await;
+    assert_eq!(result, json!({"success": true, "count": 2}));
+
+    let rejected = server
+        .handle_request(
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "tools/call",
+                "tenant_id": "demo-tenant",
+                "user_id": "user-demo-123",
+                "params": {"name": "events_send", "arguments": {"events": []}}
+            })
+            .to_string(),
+        )
+        .await
+        .unwrap();
+    assert!(serde_json::to_value(rejected).unwrap()["error"].is_object());
+}
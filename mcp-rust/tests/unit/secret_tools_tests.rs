@@ -0,0 +1,226 @@
+// Unit tests for the tenant-scoped secret_get, secret_put and secret_delete tools
+// HandlerRegistry over the in-memory backend standing in for Secrets Manager; no AWS access required
+
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::{AwsApi, AwsError};
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::rate_limiting::AwsOperation;
+use mcp_rust::tenant::{ContextType, Permission, TenantSession};
+
+fn session(
+    user_id: &str,
+    context_type: ContextType,
+    permissions: Vec<Permission>,
+) -> TenantSession {
+    TestTenant::user(user_id)
+        .context_type(context_type)
+        .permissions(permissions)
+        .session()
+}
+
+fn personal(user_id: &str) -> TenantSession {
+    session(
+        user_id,
+        ContextType::Personal,
+        vec![Permission::ReadSecrets, Permission::WriteSecrets],
+    )
+}
+
+async fn setup() -> (Arc<MemoryAwsService>, HandlerRegistry) {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await;
+    (aws, registry)
+}
+
+#[tokio::test]
+async fn test_secret_round_trip() {
+    let (aws, registry) = setup().await;
+    let alice = personal("alice");
+
+    let stored = registry
+        .handle_tool_call(
+            &alice,
+            "secret_put",
+            json!({"name": "openai", "value": "sk-1"}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(stored, json!({"name": "openai", "stored": true}));
+    // Puts update an existing secret
+    registry
+        .handle_tool_call(
+            &alice,
+            "secret_put",
+            json!({"name": "openai", "value": "sk-2"}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        aws.secret_names("mcp/").await.unwrap(),
+        ["mcp/user/alice/openai"]
+    );
+
+    let secret = registry
+        .handle_tool_call(&alice, "secret_get", json!({"name": "openai"}))
+        .await
+        .unwrap();
+    assert_eq!(secret, json!({"name": "openai", "value": "sk-2"}));
+
+    let deleted = registry
+        .handle_tool_call(
+            &alice,
+            "secret_delete",
+            json!({"name": "openai", "recovery_window_days": 30}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(deleted["recovery_window_days"], 30);
+    let deletion_date =
+        chrono::DateTime::parse_from_rfc3339(deleted["deletion_date"].as_str().unwrap()).unwrap();
+    assert!(deletion_date > chrono::Utc::now() + chrono::Duration::days(29));
+
+    for tool in ["secret_get", "secret_delete"] {
+        match registry
+            .handle_tool_call(&alice, tool, json!({"name": "openai"}))
+            .await
+        {
+            Err(HandlerError::Aws(AwsError::NotFound(message))) => {
+                assert!(message.contains("openai"), "{}", message)
+            }
+            other => panic!("{}: expected NotFound, got {:?}", tool, other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_secrets_are_confined_to_the_callers_namespace() {
+    let (aws, registry) = setup().await;
+    let alice = personal("alice");
+    let bob = personal("bob");
+    let carol = session(
+        "carol",
+        ContextType::Organization {
+            org_id: "acme".to_string(),
+            org_name: "Acme".to_string(),
+        },
+        vec![Permission::ReadSecrets, Permission::WriteSecrets],
+    );
+
+    registry
+        .handle_tool_call(
+            &alice,
+            "secret_put",
+            json!({"name": "openai", "value": "sk-a"}),
+        )
+        .await
+        .unwrap();
+    registry
+        .handle_tool_call(
+            &carol,
+            "secret_put",
+            json!({"name": "openai", "value": "sk-c"}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        aws.secret_names("mcp/").await.unwrap(),
+        ["mcp/org/acme/user/carol/openai", "mcp/user/alice/openai"]
+    );
+    assert!(matches!(
+        registry
+            .handle_tool_call(&bob, "secret_get", json!({"name": "openai"}))
+            .await,
+        Err(HandlerError::Aws(AwsError::NotFound(_)))
+    ));
+
+    // Names can't climb out of the namespace, e.g. into the registry's credentials
+    aws.secret_store("mcp/personal-bob/github/token", "ghp_bob", None)
+        .await
+        .unwrap();
+    for name in [
+        "../bob/openai",
+        "github/token",
+        "",
+        "open ai",
+        &"x".repeat(129),
+    ] {
+        match registry
+            .handle_tool_call(&alice, "secret_get", json!({ "name": name }))
+            .await
+        {
+            Err(HandlerError::InvalidArguments(message)) => {
+                assert!(message.contains("Invalid secret name"), "{}", message)
+            }
+            other => panic!("{:?}: expected InvalidArguments, got {:?}", name, other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_secret_tools_require_their_permissions() {
+    let (_, registry) = setup().await;
+    let reader = session("dave", ContextType::Personal, vec![Permission::ReadSecrets]);
+    let kv_only = session(
+        "erin",
+        ContextType::Personal,
+        vec![Permission::ReadKV, Permission::WriteKV],
+    );
+
+    match registry
+        .handle_tool_call(&reader, "secret_put", json!({"name": "a", "value": "b"}))
+        .await
+    {
+        Err(HandlerError::PermissionDenied(Permission::WriteSecrets)) => {}
+        other => panic!("expected PermissionDenied, got {:?}", other),
+    }
+    match registry
+        .handle_tool_call(&kv_only, "secret_get", json!({"name": "a"}))
+        .await
+    {
+        Err(HandlerError::PermissionDenied(Permission::ReadSecrets)) => {}
+        other => panic!("expected PermissionDenied, got {:?}", other),
+    }
+    let tools = registry.list_tools(&kv_only).await.unwrap();
+    assert!(tools
+        .iter()
+        .all(|tool| !tool["name"].as_str().unwrap().starts_with("secret_")));
+}
+
+#[tokio::test]
+async fn test_secret_delete_validates_the_recovery_window() {
+    let (_, registry) = setup().await;
+    let alice = personal("alice");
+
+    for days in [json!(6), json!(31), json!("7")] {
+        match registry
+            .handle_tool_call(
+                &alice,
+                "secret_delete",
+                json!({"name": "openai", "recovery_window_days": days}),
+            )
+            .await
+        {
+            Err(HandlerError::SchemaViolation { pointer, .. }) => {
+                assert_eq!(pointer, "/recovery_window_days")
+            }
+            other => panic!("{}: expected InvalidArguments, got {:?}", days, other),
+        }
+    }
+}
+
+#[test]
+fn test_secret_tools_are_rate_limited_as_secrets_manager_calls() {
+    let args = json!({"name": "openai"});
+    let service_key = |tool: &str| {
+        AwsOperation::from_tool_name(tool, &args)
+            .unwrap()
+            .service_key()
+    };
+    assert_eq!(service_key("secret_get"), "secrets_get");
+    assert_eq!(service_key("secret_put"), "secrets_put");
+    assert_eq!(service_key("secret_delete"), "secrets_put");
+}
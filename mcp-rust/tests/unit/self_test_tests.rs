@@ -0,0 +1,94 @@
+// Unit tests for the --self-test startup check
+// Runs the scripted sequence in process over the in-memory backend; no AWS access required
+
+use std::time::Duration;
+
+use mcp_rust::config::{Backend, Config};
+use mcp_rust::self_test::{self, SelfTestReport, StepResult, SELF_TEST_TENANT};
+
+#[test]
+fn test_self_test_config_uses_the_memory_backend_unless_live() {
+    let config = Config::resolve(
+        |name| (name == "MCP_BACKEND").then(|| "aws".to_string()),
+        None,
+    )
+    .unwrap();
+    assert_eq!(config.aws.backend, Backend::Aws);
+
+    let offline = self_test::self_test_config(&config, false);
+    assert_eq!(offline.aws.backend, Backend::Memory);
+    assert_eq!(offline.default_tenant_id.as_deref(), Some(SELF_TEST_TENANT));
+    let live = self_test::self_test_config(&config, true);
+    assert_eq!(live.aws.backend, Backend::Aws);
+}
+
+#[tokio::test]
+async fn test_self_test_passes_on_the_memory_backend() {
+    let config = self_test::self_test_config(&Config::default(), false);
+    let report = self_test::run(config).await.unwrap();
+
+    let names: Vec<&str> = report.steps.iter().map(|step| step.name).collect();
+    assert_eq!(
+        names,
+        [
+            "initialize",
+            "tools/list",
+            "kv_set",
+            "kv_get",
+            "kv_delete",
+            "artifacts_put",
+            "artifacts_get",
+            "events_send",
+            "events_query",
+            "rate_limit_status",
+            "cleanup",
+        ]
+    );
+    for step in &report.steps {
+        assert!(step.passed, "{}: {:?}", step.name, step.error);
+        assert_eq!(step.error, None);
+        assert!(step.duration <= report.duration);
+    }
+    assert!(report.passed());
+    assert_eq!(report.backend, Backend::Memory);
+
+    let rendered = report.render();
+    assert!(
+        rendered.starts_with("Self-test against the memory backend\n"),
+        "{}",
+        rendered
+    );
+    assert_eq!(rendered.matches("  PASS ").count(), report.steps.len());
+    assert!(rendered.contains("11/11 steps passed in "), "{}", rendered);
+}
+
+#[test]
+fn test_failed_steps_fail_the_report() {
+    let step = |name, error: Option<&str>| StepResult {
+        name,
+        passed: error.is_none(),
+        duration: Duration::from_millis(3),
+        error: error.map(String::from),
+    };
+    let report = SelfTestReport {
+        backend: Backend::Aws,
+        steps: vec![
+            step("initialize", None),
+            step("events_query", Some("the event sent wasn't found")),
+        ],
+        duration: Duration::from_millis(20),
+    };
+    assert!(!report.passed());
+    let rendered = report.render();
+    assert!(rendered.contains("  PASS initialize  "), "{}", rendered);
+    assert!(
+        rendered.contains("  FAIL events_query      3 ms  the event sent wasn't found"),
+        "{}",
+        rendered
+    );
+    assert!(
+        rendered.ends_with("1/2 steps passed in 20 ms"),
+        "{}",
+        rendered
+    );
+}
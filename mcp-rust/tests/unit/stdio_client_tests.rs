@@ -0,0 +1,143 @@
+// Unit tests for the multiplexed stdio MCP client used for process-based servers
+// An in-memory duplex pipe stands in for the child's stdin/stdout
+
+use futures::future::join_all;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+use mcp_rust::registry::stdio::StdioMcpClient;
+use mcp_rust::registry::RegistryError;
+
+/// A client wired to a fake server task running `serve` over the other end of the pipe
+fn client_with(
+    timeout: Duration,
+    serve: impl FnOnce(DuplexStream) -> tokio::task::JoinHandle<()>,
+) -> StdioMcpClient {
+    let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+    serve(server_end);
+    let (stdout, stdin) = tokio::io::split(client_end);
+    StdioMcpClient::new("fake", stdin, stdout, timeout)
+}
+
+/// Answers `tools/call` requests with their arguments, holding replies until `batch`
+/// have arrived and then sending them newest first. Calls to `hang` are never answered.
+fn out_of_order_server(batch: usize) -> impl FnOnce(DuplexStream) -> tokio::task::JoinHandle<()> {
+    move |pipe| {
+        tokio::spawn(async move {
+            let (read, mut write) = tokio::io::split(pipe);
+            let mut lines = BufReader::new(read).lines();
+            let mut held = Vec::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                // Interleaved writes would not parse
+                let request: Value = serde_json::from_str(&line).expect("one request per line");
+                if request["params"]["name"] == "hang" {
+                    continue;
+                }
+                held.push(json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": {"echo": request["params"]["arguments"]}
+                }));
+                if held.len() == batch {
+                    for reply in held.drain(..).rev() {
+                        let line = format!("{}\n", reply);
+                        write.write_all(line.as_bytes()).await.unwrap();
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_calls_get_their_own_responses() {
+    let client = Arc::new(client_with(Duration::from_secs(5), out_of_order_server(50)));
+
+    let calls = (0..50).map(|n| {
+        let client = client.clone();
+        async move {
+            let payload = format!("call-{}-{}", n, "x".repeat(n * 40));
+            let result = client
+                .call_tool("echo", json!({"n": n, "payload": payload}))
+                .await
+                .unwrap();
+            (n, payload, result)
+        }
+    });
+
+    for (n, payload, result) in join_all(calls).await {
+        assert_eq!(result["echo"]["n"], n);
+        assert_eq!(result["echo"]["payload"], payload);
+    }
+    assert_eq!(client.pending_requests().await, 0);
+}
+
+#[tokio::test]
+async fn test_timed_out_requests_leave_the_pending_map() {
+    let client = client_with(Duration::from_millis(100), out_of_order_server(1));
+
+    let (hung, echoed) = tokio::join!(
+        client.call_tool("hang", json!({})),
+        client.call_tool("echo", json!({"ok": true}))
+    );
+    assert!(matches!(hung, Err(RegistryError::Timeout(_))), "{:?}", hung);
+    assert_eq!(echoed.unwrap()["echo"]["ok"], true);
+    assert_eq!(client.pending_requests().await, 0);
+
+    // The connection is still usable afterwards
+    assert!(client.call_tool("echo", json!({})).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_closed_output_fails_waiting_and_later_requests() {
+    // Reads one request, then exits without answering
+    let client = client_with(Duration::from_secs(5), |pipe| {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(pipe).lines();
+            let _ = lines.next_line().await;
+        })
+    });
+
+    let err = client.call_tool("echo", json!({})).await.unwrap_err();
+    assert!(
+        matches!(err, RegistryError::ConnectionFailed(_)),
+        "{:?}",
+        err
+    );
+    assert!(client.is_closed());
+    assert!(matches!(
+        client.call_tool("echo", json!({})).await,
+        Err(RegistryError::ServerNotConnected(_))
+    ));
+    assert_eq!(client.pending_requests().await, 0);
+}
+
+#[tokio::test]
+async fn test_timed_out_call_is_cancelled_downstream() {
+    let (seen_tx, mut seen) = tokio::sync::mpsc::unbounded_channel::<Value>();
+    // Never answers; reports every message it receives
+    let client = client_with(Duration::from_secs(5), move |pipe| {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(pipe).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let _ = seen_tx.send(serde_json::from_str(&line).unwrap());
+            }
+        })
+    });
+
+    let err = client
+        .call_tool_within("slow", json!({}), Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RegistryError::Timeout(_)), "{:?}", err);
+    assert_eq!(client.pending_requests().await, 0);
+
+    let call = seen.recv().await.unwrap();
+    assert_eq!(call["method"], "tools/call");
+    let cancel = seen.recv().await.unwrap();
+    assert_eq!(cancel["method"], "notifications/cancelled");
+    assert_eq!(cancel["params"]["requestId"], call["id"]);
+    assert!(cancel.get("id").is_none());
+}
@@ -0,0 +1,341 @@
+// Unit tests for the task_create, task_claim, task_complete, task_fail and task_list tools
+// HandlerRegistry over the in-memory backend's KV store, including concurrent claimers; no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::{AwsApi, AwsError};
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::rate_limiting::AwsOperation;
+use mcp_rust::tenant::{Permission, TenantSession};
+
+fn session(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id)
+        .permissions(vec![Permission::ReadKV, Permission::WriteKV])
+        .session()
+}
+
+async fn setup() -> (Arc<MemoryAwsService>, Arc<HandlerRegistry>) {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await;
+    (aws, Arc::new(registry))
+}
+
+async fn call(
+    registry: &HandlerRegistry,
+    session: &TenantSession,
+    tool: &str,
+    arguments: Value,
+) -> Result<Value, HandlerError> {
+    registry.handle_tool_call(session, tool, arguments).await
+}
+
+async fn create(registry: &HandlerRegistry, session: &TenantSession, arguments: Value) -> String {
+    let mut arguments = arguments;
+    arguments["queue"] = json!("jobs");
+    let result = call(registry, session, "task_create", arguments)
+        .await
+        .unwrap();
+    result["task_id"].as_str().unwrap().to_string()
+}
+
+async fn claim(registry: &HandlerRegistry, session: &TenantSession, arguments: Value) -> Value {
+    let mut arguments = arguments;
+    arguments["queue"] = json!("jobs");
+    call(registry, session, "task_claim", arguments)
+        .await
+        .unwrap()["task"]
+        .clone()
+}
+
+async fn list(registry: &HandlerRegistry, session: &TenantSession) -> Value {
+    call(registry, session, "task_list", json!({"queue": "jobs"}))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_task_lifecycle_create_claim_complete() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+
+    let first = create(&registry, &alice, json!({"payload": {"n": 1}})).await;
+    let second = create(&registry, &alice, json!({"payload": {"n": 2}})).await;
+
+    let task = claim(&registry, &alice, json!({})).await;
+    assert_eq!(
+        task["task_id"],
+        json!(first),
+        "oldest task is claimed first"
+    );
+    assert_eq!(task["payload"], json!({"n": 1}));
+    assert_eq!(task["attempt"], json!(1));
+    assert!(task["lease_expires_at"].is_string());
+
+    let listed = list(&registry, &alice).await;
+    assert_eq!(listed["pending"], json!(1));
+    assert_eq!(listed["claimed"], json!(1));
+
+    let done = call(
+        &registry,
+        &alice,
+        "task_complete",
+        json!({"queue": "jobs", "task_id": first, "lease_id": task["lease_id"]}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(done["status"], json!("completed"));
+
+    let listed = list(&registry, &alice).await;
+    assert_eq!(listed["tasks"].as_array().unwrap().len(), 1);
+    assert_eq!(listed["tasks"][0]["task_id"], json!(second));
+}
+
+#[tokio::test]
+async fn test_task_claim_on_empty_queue_returns_null() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+
+    assert_eq!(claim(&registry, &alice, json!({})).await, Value::Null);
+
+    create(
+        &registry,
+        &alice,
+        json!({"payload": "later", "delay_seconds": 60}),
+    )
+    .await;
+    assert_eq!(
+        claim(&registry, &alice, json!({})).await,
+        Value::Null,
+        "a delayed task isn't claimable yet"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_task_claim_contention_has_one_winner() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+    create(&registry, &alice, json!({"payload": "only"})).await;
+
+    let claimers: Vec<_> = (0..2)
+        .map(|_| {
+            let registry = registry.clone();
+            tokio::spawn(async move { claim(&registry, &session("alice"), json!({})).await })
+        })
+        .collect();
+    let mut claimed = Vec::new();
+    for claimer in claimers {
+        claimed.push(claimer.await.unwrap());
+    }
+
+    assert_eq!(
+        claimed.iter().filter(|task| !task.is_null()).count(),
+        1,
+        "{:?}",
+        claimed
+    );
+    assert_eq!(list(&registry, &alice).await["claimed"], json!(1));
+}
+
+#[tokio::test]
+async fn test_kv_compare_and_swap_rejects_stale_writers() {
+    let aws = MemoryAwsService::new();
+    let alice = session("alice");
+
+    assert!(aws
+        .kv_compare_and_swap(&alice, "k", None, "v1", None)
+        .await
+        .unwrap());
+    assert!(
+        !aws.kv_compare_and_swap(&alice, "k", None, "v2", None)
+            .await
+            .unwrap(),
+        "the item already exists"
+    );
+    assert!(
+        !aws.kv_compare_and_swap(&alice, "k", Some("v0"), "v2", None)
+            .await
+            .unwrap(),
+        "the expected value is stale"
+    );
+    assert!(aws
+        .kv_compare_and_swap(&alice, "k", Some("v1"), "v2", None)
+        .await
+        .unwrap());
+    assert_eq!(
+        aws.kv_get(&alice, "k").await.unwrap().as_deref(),
+        Some("v2")
+    );
+}
+
+#[tokio::test]
+async fn test_expired_lease_makes_task_claimable_again() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+    let task_id = create(&registry, &alice, json!({"payload": "slow"})).await;
+
+    let first = claim(&registry, &alice, json!({"lease_seconds": 1})).await;
+    assert_eq!(claim(&registry, &alice, json!({})).await, Value::Null);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let second = claim(&registry, &alice, json!({})).await;
+    assert_eq!(second["task_id"], json!(task_id));
+    assert_eq!(second["attempt"], json!(2));
+    assert_eq!(second["last_error"], json!("lease expired"));
+    assert_ne!(second["lease_id"], first["lease_id"]);
+
+    // The first worker's lease no longer holds the task
+    let error = call(
+        &registry,
+        &alice,
+        "task_complete",
+        json!({"queue": "jobs", "task_id": task_id, "lease_id": first["lease_id"]}),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(error, HandlerError::Aws(AwsError::ConditionFailed(_))),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_list_hides_lease_ids_and_shows_expired_leases_released() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+    let task_id = create(&registry, &alice, json!({"payload": "slow"})).await;
+    let claimed = claim(&registry, &alice, json!({"lease_seconds": 1})).await;
+
+    // Listing doesn't hand out the lease, except to the worker holding it
+    let listed = list(&registry, &alice).await;
+    assert_eq!(listed["claimed"], json!(1));
+    assert!(listed["tasks"][0].get("lease_id").is_none(), "{}", listed);
+    let held = call(
+        &registry,
+        &alice,
+        "task_list",
+        json!({"queue": "jobs", "lease_id": claimed["lease_id"]}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(held["tasks"][0]["lease_id"], claimed["lease_id"]);
+
+    // An expired lease is listed as released before anyone claims again
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let listed = list(&registry, &alice).await;
+    assert_eq!(listed["pending"], json!(1));
+    assert_eq!(listed["claimed"], json!(0));
+    assert_eq!(listed["tasks"][0]["task_id"], json!(task_id));
+    assert_eq!(listed["tasks"][0]["last_error"], json!("lease expired"));
+}
+
+#[tokio::test]
+async fn test_task_fail_retries_then_dead_letters() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+    let task_id = create(
+        &registry,
+        &alice,
+        json!({"payload": "flaky", "max_attempts": 2}),
+    )
+    .await;
+
+    let task = claim(&registry, &alice, json!({})).await;
+    let failed = call(
+        &registry,
+        &alice,
+        "task_fail",
+        json!({"queue": "jobs", "task_id": task_id, "lease_id": task["lease_id"], "error": "boom"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(failed["status"], json!("pending"));
+
+    let task = claim(&registry, &alice, json!({})).await;
+    assert_eq!(task["attempt"], json!(2));
+    assert_eq!(task["last_error"], json!("boom"));
+    let failed = call(
+        &registry,
+        &alice,
+        "task_fail",
+        json!({"queue": "jobs", "task_id": task_id, "lease_id": task["lease_id"], "error": "boom again"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(failed["status"], json!("dead_lettered"));
+
+    assert_eq!(claim(&registry, &alice, json!({})).await, Value::Null);
+    let listed = list(&registry, &alice).await;
+    assert!(listed["tasks"].as_array().unwrap().is_empty());
+    assert_eq!(listed["dead_letters"][0]["task_id"], json!(task_id));
+    assert_eq!(listed["dead_letters"][0]["last_error"], json!("boom again"));
+}
+
+#[tokio::test]
+async fn test_expired_lease_on_last_attempt_dead_letters() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+    let task_id = create(
+        &registry,
+        &alice,
+        json!({"payload": "stuck", "max_attempts": 1}),
+    )
+    .await;
+
+    claim(&registry, &alice, json!({"lease_seconds": 1})).await;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    assert_eq!(claim(&registry, &alice, json!({})).await, Value::Null);
+    let listed = list(&registry, &alice).await;
+    assert_eq!(listed["dead_letters"][0]["task_id"], json!(task_id));
+    assert_eq!(listed["dead_letters"][0]["status"], json!("dead_lettered"));
+}
+
+#[tokio::test]
+async fn test_task_queues_are_namespaced_per_context() {
+    let (aws, registry) = setup().await;
+    let alice = session("alice");
+    let bob = session("bob");
+    create(&registry, &alice, json!({"payload": "alice's"})).await;
+
+    assert_eq!(claim(&registry, &bob, json!({})).await, Value::Null);
+    assert!(list(&registry, &bob).await["tasks"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+    assert!(aws.kv_get(&alice, "_tasks:jobs").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_task_tools_reject_bad_queue_names() {
+    let (_aws, registry) = setup().await;
+    let alice = session("alice");
+
+    let error = call(
+        &registry,
+        &alice,
+        "task_create",
+        json!({"queue": "../Other", "payload": 1}),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(error, HandlerError::InvalidArguments(_)),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn test_task_tools_rate_limit_buckets() {
+    let service_key =
+        |tool: &str| AwsOperation::from_tool_name(tool, &json!({})).map(|op| op.service_key());
+    for tool in ["task_create", "task_claim", "task_complete", "task_fail"] {
+        assert_eq!(service_key(tool), Some("dynamodb_write"), "{}", tool);
+    }
+    assert_eq!(service_key("task_list"), Some("dynamodb_read"));
+}
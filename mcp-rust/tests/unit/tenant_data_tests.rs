@@ -0,0 +1,717 @@
+// Unit tests for the tenant_data_export and tenant_data_delete tools
+// HandlerRegistry and JobManager over the in-memory backend; no AWS access required
+
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::namespaces::EventScope;
+use mcp_rust::aws::{self, internal_keys, AwsApi, DirectKvStore};
+use mcp_rust::handlers::jobs::{JobManager, JobOptions, JOBS_KEY};
+use mcp_rust::handlers::tenant_data::{CONFIRMATION_KEY, DATA_DELETED_DETAIL_TYPE};
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{TenantSession, UserRole};
+
+fn admin(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id).role(UserRole::Admin).session()
+}
+
+/// A member of the `acme` organization's tenant
+fn org_member(user_id: &str) -> TenantSession {
+    TestTenant::user(user_id)
+        .tenant("acme-tenant")
+        .in_organization("acme", "Acme")
+        .role(UserRole::Admin)
+        .session()
+}
+
+async fn registry(aws: &Arc<MemoryAwsService>) -> HandlerRegistry {
+    HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await
+}
+
+/// One record in every section for `session`
+async fn seed(aws: &Arc<MemoryAwsService>, registry: &HandlerRegistry, session: &TenantSession) {
+    aws.kv_set(session, "profile", "{\"theme\":\"dark\"}", None, false)
+        .await
+        .unwrap();
+    aws.kv_set(session, "draft", "hello", None, false)
+        .await
+        .unwrap();
+    aws.artifacts_put(
+        session,
+        "reports/q1.txt",
+        b"first quarter",
+        "text/plain",
+        None,
+    )
+    .await
+    .unwrap();
+    aws.artifacts_put(
+        session,
+        "reports/q2.txt",
+        b"second quarter",
+        "text/plain",
+        None,
+    )
+    .await
+    .unwrap();
+    let events: Vec<(String, Value)> = (0..3)
+        .map(|i| ("order.created".to_string(), json!({"order": i})))
+        .collect();
+    aws.send_events(session, &events).await.unwrap();
+    let rule = aws
+        .create_event_rule(
+            session,
+            "orders",
+            json!({"detail-type": ["order.created"]}),
+            None,
+            true,
+        )
+        .await
+        .unwrap();
+    aws.create_alert_subscription(
+        session,
+        "orders",
+        rule["ruleId"].as_str().unwrap(),
+        "email",
+        None,
+        Some("ops@example.com".to_string()),
+        true,
+    )
+    .await
+    .unwrap();
+    registry
+        .handle_tool_call(
+            session,
+            "integration_register",
+            json!({
+                "service_id": "notes",
+                "name": "Notes",
+                "description": "local process",
+                "category": "test",
+                "command": "true",
+                "auth_method": "none",
+                "configuration_schema": [],
+                "capabilities": []
+            }),
+        )
+        .await
+        .unwrap();
+    let connection = json!({
+        "service_id": "notes",
+        "connection_id": "default",
+        "connection_name": null,
+        "credential_fields": ["api_token"],
+        "settings": {"api_token": "tok-live-123", "region": "eu"},
+        "created_at": "2026-01-01T00:00:00Z",
+        "user_id": session.context.user_id,
+        "tenant_id": session.context.tenant_id
+    });
+    aws.kv_set_direct(
+        &internal_keys::connection_key(
+            &session.context.tenant_id,
+            &session.context.user_id,
+            "notes",
+            "default",
+        ),
+        &connection.to_string(),
+        None,
+    )
+    .await
+    .unwrap();
+    aws.secret_store(
+        &aws::tenant_secret_id(session, "db-password").unwrap(),
+        "hunter2",
+        None,
+    )
+    .await
+    .unwrap();
+}
+
+async fn finished_job(registry: &HandlerRegistry, session: &TenantSession, job_id: &str) -> Value {
+    for _ in 0..300 {
+        let status = registry
+            .handle_tool_call(session, "jobs_status", json!({"job_id": job_id}))
+            .await
+            .unwrap();
+        if status["state"] != "queued" && status["state"] != "running" {
+            return status;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("job {} never finished", job_id);
+}
+
+async fn part(aws: &MemoryAwsService, session: &TenantSession, key: &str) -> Vec<Value> {
+    let content = aws.artifacts_get(session, key).await.unwrap().unwrap();
+    serde_json::from_slice(&content).unwrap()
+}
+
+async fn events_of(aws: &MemoryAwsService, session: &TenantSession) -> Vec<Value> {
+    let scope = EventScope::for_session(session);
+    let page = aws
+        .query_events(
+            session,
+            Some(&scope),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            100,
+            None,
+            true,
+        )
+        .await
+        .unwrap();
+    page["events"].as_array().unwrap().clone()
+}
+
+/// Run a dry run, then the deletion it confirms, to the end
+async fn delete_all(registry: &HandlerRegistry, session: &TenantSession) -> (Value, Value) {
+    let dry_run = registry
+        .handle_tool_call(session, "tenant_data_delete", json!({"dry_run": true}))
+        .await
+        .unwrap();
+    let started = registry
+        .handle_tool_call(
+            session,
+            "tenant_data_delete",
+            json!({"confirm": dry_run["confirm"]}),
+        )
+        .await
+        .unwrap();
+    let status = finished_job(registry, session, started["job_id"].as_str().unwrap()).await;
+    assert_eq!(status["state"], "succeeded", "{}", status);
+    (dry_run, status)
+}
+
+#[tokio::test]
+async fn test_export_archives_every_section() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = admin("alice");
+    seed(&aws, &registry, &alice).await;
+
+    let started = registry
+        .handle_tool_call(
+            &alice,
+            "tenant_data_export",
+            json!({"prefix": "exports/alice", "include_content": true}),
+        )
+        .await
+        .unwrap();
+    let status = finished_job(&registry, &alice, started["job_id"].as_str().unwrap()).await;
+    assert_eq!(status["state"], "succeeded", "{}", status);
+    assert_eq!(status["result"]["key"], "exports/alice/manifest.json");
+    let counts = &status["result"]["counts"];
+    for (section, count) in [
+        ("kv", 2),
+        ("artifacts", 2),
+        ("rules", 1),
+        ("alerts", 1),
+        ("integrations", 1),
+        ("connections", 1),
+        ("secrets", 1),
+    ] {
+        assert_eq!(counts[section], count, "{}: {}", section, counts);
+    }
+    assert!(counts["events"].as_u64().unwrap() >= 3, "{}", counts);
+
+    let manifest: Value = serde_json::from_slice(
+        &aws.artifacts_get(&alice, "exports/alice/manifest.json")
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(manifest["version"], 1);
+    assert_eq!(manifest["tenantId"], "alice-tenant");
+    assert_eq!(manifest["includeContent"], true);
+    let parts = |section: &str| -> Vec<String> {
+        manifest["sections"][section]["parts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|part| part.as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(parts("kv"), vec!["exports/alice/kv/00001.json"]);
+
+    // KV values and artifact content are included; the archive itself isn't
+    let kv = part(&aws, &alice, &parts("kv")[0]).await;
+    assert!(kv.contains(&json!({"key": "draft", "value": "hello", "userId": "alice"})));
+    let artifacts = part(&aws, &alice, &parts("artifacts")[0]).await;
+    assert_eq!(artifacts[0]["key"], "reports/q1.txt");
+    assert_eq!(artifacts[0]["contentType"], "text/plain");
+    assert_eq!(
+        artifacts[0]["content"],
+        general_purpose::STANDARD.encode("first quarter")
+    );
+
+    // Integration configs, connections and secrets carry no credential values
+    let integrations = part(&aws, &alice, &parts("integrations")[0]).await;
+    assert_eq!(integrations[0]["config"]["id"], "notes");
+    let connections = part(&aws, &alice, &parts("connections")[0]).await;
+    assert_eq!(connections[0]["settings"]["region"], "eu");
+    let secrets = part(&aws, &alice, &parts("secrets")[0]).await;
+    assert_eq!(
+        secrets,
+        vec![json!({"name": "db-password", "userId": "alice"})]
+    );
+    let mut archive = String::new();
+    for key in aws
+        .artifacts_list(&alice, Some("exports/alice/"))
+        .await
+        .unwrap()
+    {
+        let content = aws.artifacts_get(&alice, &key).await.unwrap().unwrap();
+        archive.push_str(&String::from_utf8(content).unwrap());
+    }
+    for secret in ["tok-live-123", "hunter2"] {
+        assert!(!archive.contains(secret), "{} was exported", secret);
+    }
+}
+
+#[tokio::test]
+async fn test_export_pages_artifacts_and_reads_only_the_tenants_connections() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = admin("alice");
+
+    // More artifacts than a part holds with content, and some not yet migrated, one of
+    // them also in the namespace
+    for i in 0..45 {
+        let key = format!("files/{:02}.txt", i);
+        aws.artifacts_put(&alice, &key, b"data", "text/plain", None)
+            .await
+            .unwrap();
+    }
+    let bucket = aws.artifacts_bucket().to_string();
+    aws.put_object(&bucket, "personal-alice/old.txt", b"old");
+    aws.put_object(&bucket, "personal-alice/files/00.txt", b"old copy");
+
+    // Alice's connections in her tenant, under the tenant's prefix and from before
+    // connections were kept per tenant, and connections made in another tenant
+    let connection = |tenant_id: &str, connection_id: &str| {
+        json!({
+            "service_id": "notes",
+            "connection_id": connection_id,
+            "connection_name": null,
+            "credential_fields": [],
+            "settings": {},
+            "created_at": "2026-01-01T00:00:00Z",
+            "user_id": "alice",
+            "tenant_id": tenant_id
+        })
+        .to_string()
+    };
+    for (key, value) in [
+        (
+            internal_keys::connection_key("alice-tenant", "alice", "notes", "default"),
+            connection("alice-tenant", "default"),
+        ),
+        (
+            internal_keys::legacy_connection_key("alice", "notes", "old"),
+            connection("alice-tenant", "old"),
+        ),
+        (
+            internal_keys::legacy_connection_key("alice", "notes", "elsewhere"),
+            connection("other-tenant", "elsewhere"),
+        ),
+        (
+            internal_keys::connection_key("other-tenant", "mallory", "notes", "default"),
+            connection("other-tenant", "default"),
+        ),
+    ] {
+        aws.kv_set_direct(&key, &value, None).await.unwrap();
+    }
+
+    let started = registry
+        .handle_tool_call(
+            &alice,
+            "tenant_data_export",
+            json!({"prefix": "exports/alice", "include_content": true}),
+        )
+        .await
+        .unwrap();
+    let status = finished_job(&registry, &alice, started["job_id"].as_str().unwrap()).await;
+    assert_eq!(status["state"], "succeeded", "{}", status);
+    let counts = &status["result"]["counts"];
+    assert_eq!(counts["artifacts"], 46, "{}", counts);
+    assert_eq!(counts["connections"], 2, "{}", counts);
+
+    let manifest: Value = serde_json::from_slice(
+        &aws.artifacts_get(&alice, "exports/alice/manifest.json")
+            .await
+            .unwrap()
+            .unwrap(),
+    )
+    .unwrap();
+    let mut keys = Vec::new();
+    for key in manifest["sections"]["artifacts"]["parts"]
+        .as_array()
+        .unwrap()
+    {
+        for artifact in part(&aws, &alice, key.as_str().unwrap()).await {
+            keys.push(artifact["key"].as_str().unwrap().to_string());
+        }
+    }
+    let mut expected: Vec<String> = (0..45).map(|i| format!("files/{:02}.txt", i)).collect();
+    expected.push("old.txt".to_string());
+    assert_eq!(keys, expected);
+
+    let mut connections = Vec::new();
+    for key in manifest["sections"]["connections"]["parts"]
+        .as_array()
+        .unwrap()
+    {
+        for connection in part(&aws, &alice, key.as_str().unwrap()).await {
+            connections.push(connection["connection_id"].as_str().unwrap().to_string());
+        }
+    }
+    connections.sort();
+    assert_eq!(connections, vec!["default", "old"]);
+}
+
+#[tokio::test]
+async fn test_delete_requires_the_dry_run_token() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = admin("alice");
+    aws.kv_set(&alice, "profile", "kept", None, false)
+        .await
+        .unwrap();
+
+    let dry_run = registry
+        .handle_tool_call(&alice, "tenant_data_delete", json!({"dry_run": true}))
+        .await
+        .unwrap();
+    assert_eq!(dry_run["dryRun"], true);
+    assert_eq!(dry_run["inventory"]["kv"], 1);
+    let token = dry_run["confirm"].as_str().unwrap();
+
+    for arguments in [
+        json!({}),
+        json!({"confirm": true}),
+        json!({"confirm": "not-the-token"}),
+    ] {
+        let rejected = registry
+            .handle_tool_call(&alice, "tenant_data_delete", arguments.clone())
+            .await;
+        assert!(
+            matches!(
+                rejected,
+                Err(HandlerError::InvalidArguments(_) | HandlerError::SchemaViolation { .. })
+            ),
+            "{} was accepted",
+            arguments
+        );
+    }
+    // Another tenant can't use it either
+    let bob = admin("bob");
+    assert!(registry
+        .handle_tool_call(&bob, "tenant_data_delete", json!({"confirm": token}))
+        .await
+        .is_err());
+    assert_eq!(
+        aws.kv_get(&alice, "profile").await.unwrap().as_deref(),
+        Some("kept")
+    );
+
+    let started = registry
+        .handle_tool_call(&alice, "tenant_data_delete", json!({"confirm": token}))
+        .await
+        .unwrap();
+    finished_job(&registry, &alice, started["job_id"].as_str().unwrap()).await;
+    assert!(aws.kv_get(&alice, "profile").await.unwrap().is_none());
+
+    // A token is used once
+    let reused = registry
+        .handle_tool_call(&alice, "tenant_data_delete", json!({"confirm": token}))
+        .await;
+    assert!(matches!(reused, Err(HandlerError::InvalidArguments(_))));
+}
+
+#[tokio::test]
+async fn test_delete_leaves_nothing_tenant_scoped() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = admin("alice");
+    let bob = admin("bob");
+    seed(&aws, &registry, &alice).await;
+    seed(&aws, &registry, &bob).await;
+    let exported = registry
+        .handle_tool_call(&alice, "tenant_data_export", json!({}))
+        .await
+        .unwrap();
+    finished_job(&registry, &alice, exported["job_id"].as_str().unwrap()).await;
+
+    let (dry_run, status) = delete_all(&registry, &alice).await;
+    assert_eq!(dry_run["inventory"]["secrets"], 1);
+    let deleted = &status["result"]["deleted"];
+    assert_eq!(deleted["kv"], 2);
+    // The two seeded artifacts, the export's parts and its manifest
+    assert!(deleted["artifacts"].as_u64().unwrap() > 2, "{}", deleted);
+    assert_eq!(status["result"]["forgottenJobs"], 1);
+
+    // Only the deletion's own job record is left in the namespace
+    let (keys, _) = aws.kv_keys(&alice, None, 100).await.unwrap();
+    assert_eq!(keys, vec![JOBS_KEY.to_string()]);
+    let jobs = registry
+        .handle_tool_call(&alice, "jobs_list", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(jobs["jobs"].as_array().unwrap().len(), 1, "{}", jobs);
+    assert!(aws
+        .kv_get(&alice, CONFIRMATION_KEY)
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(aws.artifacts_list(&alice, None).await.unwrap().is_empty());
+    let events = events_of(&aws, &alice).await;
+    assert!(
+        events
+            .iter()
+            .all(|event| event["detailType"] != "order.created"),
+        "{:?}",
+        events
+    );
+    assert!(aws.event_rules(&alice).await.unwrap().is_empty());
+    assert!(aws.alert_subscriptions(&alice).await.unwrap().is_empty());
+    let integrations = registry
+        .handle_tool_call(&alice, "integration_list", json!({}))
+        .await
+        .unwrap();
+    assert!(
+        !integrations.to_string().contains("notes"),
+        "{}",
+        integrations
+    );
+    assert!(aws
+        .kv_list(&internal_keys::connection_prefix(
+            &alice.context.tenant_id,
+            "alice"
+        ))
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(aws
+        .secret_names(&aws::tenant_secret_prefix(&alice))
+        .await
+        .unwrap()
+        .is_empty());
+
+    // The deletion is on record, outside the tenant's namespace
+    let audit = aws
+        .query_events(
+            &alice,
+            None,
+            None,
+            None,
+            Some("mcp-rust.system".to_string()),
+            Some(DATA_DELETED_DETAIL_TYPE.to_string()),
+            None,
+            None,
+            None,
+            None,
+            10,
+            None,
+            true,
+        )
+        .await
+        .unwrap();
+    let audit = &audit["events"][0]["detail"];
+    assert_eq!(audit["tenantId"], "alice-tenant");
+    assert_eq!(audit["deleted"], *deleted);
+
+    // Bob's data is untouched
+    let (keys, _) = aws.kv_keys(&bob, None, 100).await.unwrap();
+    assert!(keys.contains(&"profile".to_string()));
+    assert_eq!(aws.artifacts_list(&bob, None).await.unwrap().len(), 2);
+    assert_eq!(aws.event_rules(&bob).await.unwrap().len(), 1);
+    assert_eq!(aws.alert_subscriptions(&bob).await.unwrap().len(), 1);
+    assert_eq!(
+        aws.secret_names(&aws::tenant_secret_prefix(&bob))
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        aws.kv_list(&internal_keys::connection_prefix(
+            &bob.context.tenant_id,
+            "bob"
+        ))
+        .await
+        .unwrap()
+        .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_delete_resumes_from_its_checkpoint() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = admin("alice");
+    seed(&aws, &registry, &alice).await;
+
+    // A deletion that got through integrations, connections, secrets, alerts and
+    // rules, then failed
+    let jobs = Arc::new(JobManager::new(aws.clone(), JobOptions::default()));
+    let failed = jobs
+        .spawn(&alice, "tenant_data_delete", None, |job| async move {
+            job.checkpoint(json!({"section": 5, "cursor": null, "deleted": {"rules": 7}}));
+            Err::<Value, _>(HandlerError::Internal("throttled".to_string()))
+        })
+        .await
+        .unwrap();
+    let status = finished_job(&registry, &alice, &failed.job_id).await;
+    assert_eq!(status["state"], "failed");
+
+    // Resuming skips what was done and needs no new token
+    let resumed = registry
+        .handle_tool_call(
+            &alice,
+            "tenant_data_delete",
+            json!({"resume_job_id": failed.job_id}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resumed["resumedFrom"], failed.job_id.as_str());
+    let status = finished_job(&registry, &alice, resumed["job_id"].as_str().unwrap()).await;
+    assert_eq!(status["state"], "succeeded", "{}", status);
+    assert_eq!(status["result"]["deleted"]["rules"], 7);
+    assert_eq!(status["result"]["deleted"]["kv"], 2);
+    assert!(aws.kv_get(&alice, "profile").await.unwrap().is_none());
+    assert!(aws.artifacts_list(&alice, None).await.unwrap().is_empty());
+    assert_eq!(aws.event_rules(&alice).await.unwrap().len(), 1);
+
+    // Only a stopped job of the same type can be resumed
+    let rejected = registry
+        .handle_tool_call(
+            &alice,
+            "tenant_data_export",
+            json!({"resume_job_id": resumed["job_id"]}),
+        )
+        .await;
+    assert!(matches!(rejected, Err(HandlerError::InvalidArguments(_))));
+}
+
+#[tokio::test]
+async fn test_delete_reaches_every_user_and_internal_record_of_the_tenant() {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = registry(&aws).await;
+    let alice = org_member("alice");
+    let bob = org_member("bob");
+    seed(&aws, &registry, &alice).await;
+
+    // Bob's own records, in his namespace of the same tenant
+    aws.kv_set(&bob, "notes", "bob's", None, false)
+        .await
+        .unwrap();
+    aws.artifacts_put(&bob, "plan.txt", b"plan", "text/plain", None)
+        .await
+        .unwrap();
+    aws.send_events(&bob, &[("order.created".to_string(), json!({}))])
+        .await
+        .unwrap();
+    let rule = aws
+        .create_event_rule(&bob, "bob's orders", json!({}), None, true)
+        .await
+        .unwrap();
+    aws.create_alert_subscription(
+        &bob,
+        "bob's alert",
+        rule["ruleId"].as_str().unwrap(),
+        "email",
+        None,
+        Some("bob@example.com".to_string()),
+        true,
+    )
+    .await
+    .unwrap();
+    aws.secret_store(
+        &aws::tenant_secret_id(&bob, "api-key").unwrap(),
+        "s3cret",
+        None,
+    )
+    .await
+    .unwrap();
+    let connection = |key: &str| {
+        json!({
+            "service_id": "notes",
+            "connection_id": key,
+            "connection_name": null,
+            "credential_fields": [],
+            "settings": {},
+            "created_at": "2026-01-01T00:00:00Z",
+            "user_id": "bob",
+            "tenant_id": "acme-tenant"
+        })
+        .to_string()
+    };
+    let bob_connection = internal_keys::connection_key("acme-tenant", "bob", "notes", "default");
+    let org_connection = internal_keys::org_connection_key("acme", "notes", "shared");
+    let idempotent = internal_keys::idempotency_key("acme-tenant", "kv_set", "retry-1");
+    let analytics = internal_keys::analytics_cache_key("org-acme", "24h", "now");
+    let other_tenant = internal_keys::idempotency_key("other-tenant", "kv_set", "retry-1");
+    for (key, value) in [
+        (&bob_connection, connection("default")),
+        (&org_connection, connection("shared")),
+        (&idempotent, "{}".to_string()),
+        (&analytics, "{}".to_string()),
+        (&other_tenant, "{}".to_string()),
+    ] {
+        aws.kv_set_direct(key, &value, None).await.unwrap();
+    }
+
+    let (dry_run, status) = delete_all(&registry, &alice).await;
+    let inventory = &dry_run["inventory"];
+    assert_eq!(inventory["kv"], 3, "{}", inventory);
+    assert_eq!(inventory["rules"], 2, "{}", inventory);
+    assert_eq!(inventory["connections"], 3, "{}", inventory);
+    assert_eq!(inventory["secrets"], 2, "{}", inventory);
+    let deleted = &status["result"]["deleted"];
+    assert_eq!(deleted["kv"], 3, "{}", deleted);
+    assert_eq!(deleted["artifacts"], 3, "{}", deleted);
+    assert_eq!(deleted["internal"], 2, "{}", deleted);
+
+    for user in [&alice, &bob] {
+        let (keys, _) = aws.kv_keys(user, None, 100).await.unwrap();
+        assert!(
+            keys.iter().all(|key| key == JOBS_KEY),
+            "{}: {:?}",
+            user.context.user_id,
+            keys
+        );
+        assert!(aws.artifacts_list(user, None).await.unwrap().is_empty());
+        assert!(events_of(&aws, user)
+            .await
+            .iter()
+            .all(|event| event["detailType"] != "order.created"));
+        assert!(aws.event_rules(user).await.unwrap().is_empty());
+        assert!(aws.alert_subscriptions(user).await.unwrap().is_empty());
+        assert!(aws
+            .secret_names(&aws::tenant_secret_prefix(user))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+    for key in [&bob_connection, &org_connection, &idempotent, &analytics] {
+        assert!(
+            aws.kv_get_direct(key).await.unwrap().is_none(),
+            "{} is left",
+            key
+        );
+    }
+    assert!(aws.kv_get_direct(&other_tenant).await.unwrap().is_some());
+}
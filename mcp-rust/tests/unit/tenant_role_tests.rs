@@ -0,0 +1,213 @@
+// Unit tests for tenant AWS roles: the per-tenant credential cache and its fallback
+// Roles are assumed by a stub instead of STS and times are passed in; no AWS access required
+
+use async_trait::async_trait;
+use aws_sdk_sts::config::Credentials;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::common::TestTenant;
+use mcp_rust::aws::tenant_roles::{RoleAssumer, TenantClients};
+use mcp_rust::aws::{AwsClients, AwsError};
+use mcp_rust::config::Config;
+use mcp_rust::tenant::{TenantAwsRole, TenantContext};
+
+const ROLE_ARN: &str = "arn:aws:iam::210987654321:role/agent-mesh";
+
+/// Hands out credentials expiring at `expires_at`, or fails with `failure` when set
+struct StubAssumer {
+    calls: AtomicUsize,
+    expires_at: Mutex<SystemTime>,
+    failure: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl RoleAssumer for StubAssumer {
+    async fn assume_role(
+        &self,
+        role: &TenantAwsRole,
+        session_name: &str,
+    ) -> Result<Credentials, String> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(session_name, "mcp-acme-tenant");
+        assert_eq!(role.external_id.as_deref(), Some("ext-123"));
+        if let Some(failure) = self.failure.lock().unwrap().clone() {
+            return Err(failure);
+        }
+        Ok(Credentials::new(
+            "AKIA",
+            "secret",
+            Some("token".to_string()),
+            Some(*self.expires_at.lock().unwrap()),
+            "stub",
+        ))
+    }
+}
+
+struct Fixture {
+    assumer: Arc<StubAssumer>,
+    default: Arc<AwsClients>,
+    clients: TenantClients,
+}
+
+fn fixture() -> Fixture {
+    let sdk_config = aws_config::SdkConfig::builder()
+        .behavior_version(aws_config::BehaviorVersion::latest())
+        .region(aws_config::Region::new("us-west-2"))
+        .build();
+    let config = Config::default().aws;
+    let default = Arc::new(AwsClients::new(&sdk_config, &config));
+    let assumer = Arc::new(StubAssumer {
+        calls: AtomicUsize::new(0),
+        expires_at: Mutex::new(at(60)),
+        failure: Mutex::new(None),
+    });
+    let clients = TenantClients::new(sdk_config, &config, default.clone(), assumer.clone());
+    Fixture {
+        assumer,
+        default,
+        clients,
+    }
+}
+
+/// `minutes` into the test's timeline
+fn at(minutes: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + minutes * 60)
+}
+
+fn context(role_arn: Option<&str>) -> TenantContext {
+    TestTenant::user("alice")
+        .tenant("acme-tenant")
+        .aws_role(role_arn.map(|role_arn| TenantAwsRole {
+            assume_role_arn: role_arn.to_string(),
+            external_id: Some("ext-123".to_string()),
+        }))
+        .context()
+}
+
+impl Fixture {
+    fn calls(&self) -> usize {
+        self.assumer.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[tokio::test]
+async fn test_credentials_are_cached_and_refreshed_before_expiry() {
+    let fixture = fixture();
+    let tenant = context(Some(ROLE_ARN));
+
+    let first = fixture
+        .clients
+        .for_context_at(&tenant, at(0))
+        .await
+        .unwrap();
+    assert_eq!(fixture.calls(), 1);
+    assert!(!Arc::ptr_eq(&first, &fixture.default));
+    let cached = fixture
+        .clients
+        .for_context_at(&tenant, at(50))
+        .await
+        .unwrap();
+    assert!(Arc::ptr_eq(&first, &cached));
+    assert_eq!(fixture.calls(), 1);
+
+    // Within 5 minutes of expiring the role is assumed again
+    *fixture.assumer.expires_at.lock().unwrap() = at(120);
+    let refreshed = fixture
+        .clients
+        .for_context_at(&tenant, at(56))
+        .await
+        .unwrap();
+    assert_eq!(fixture.calls(), 2);
+    assert!(!Arc::ptr_eq(&first, &refreshed));
+    let cached = fixture
+        .clients
+        .for_context_at(&tenant, at(58))
+        .await
+        .unwrap();
+    assert!(Arc::ptr_eq(&refreshed, &cached));
+    assert_eq!(fixture.calls(), 2);
+
+    // A different role isn't served the old role's credentials
+    let other_role = context(Some("arn:aws:iam::210987654321:role/other"));
+    fixture
+        .clients
+        .for_context_at(&other_role, at(58))
+        .await
+        .unwrap();
+    assert_eq!(fixture.calls(), 3);
+    assert!(fixture.clients.degraded_tenants().is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_assumption_degrades_the_tenant_until_it_succeeds() {
+    let fixture = fixture();
+    let tenant = context(Some(ROLE_ARN));
+    *fixture.assumer.failure.lock().unwrap() =
+        Some("AccessDenied: not authorized to perform sts:AssumeRole".to_string());
+
+    match fixture.clients.for_context_at(&tenant, at(0)).await {
+        Err(error @ AwsError::RoleAssumption { .. }) => {
+            let message = error.to_string();
+            assert!(message.contains(ROLE_ARN), "{}", message);
+            assert!(message.contains("AccessDenied"), "{}", message);
+        }
+        Err(other) => panic!("Expected RoleAssumption, got {:?}", other),
+        Ok(_) => panic!("Expected RoleAssumption, got clients"),
+    }
+    let degraded = fixture.clients.degraded_tenants();
+    assert!(degraded["acme-tenant"].contains("AccessDenied"));
+
+    *fixture.assumer.failure.lock().unwrap() = None;
+    let clients = fixture
+        .clients
+        .for_context_at(&tenant, at(1))
+        .await
+        .unwrap();
+    assert!(fixture.clients.degraded_tenants().is_empty());
+
+    // A failed refresh keeps the credentials still held until they expire
+    *fixture.assumer.failure.lock().unwrap() = Some("ExpiredToken: gone".to_string());
+    let kept = fixture
+        .clients
+        .for_context_at(&tenant, at(58))
+        .await
+        .unwrap();
+    assert!(Arc::ptr_eq(&clients, &kept));
+    assert!(fixture
+        .clients
+        .degraded_tenants()
+        .contains_key("acme-tenant"));
+    assert!(matches!(
+        fixture.clients.for_context_at(&tenant, at(61)).await,
+        Err(AwsError::RoleAssumption { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_tenants_without_a_role_use_the_servers_clients() {
+    let fixture = fixture();
+
+    let clients = fixture
+        .clients
+        .for_context_at(&context(None), at(0))
+        .await
+        .unwrap();
+    assert!(Arc::ptr_eq(&clients, &fixture.default));
+    assert_eq!(fixture.calls(), 0);
+
+    // Dropping a failing role clears the tenant's degraded state
+    *fixture.assumer.failure.lock().unwrap() = Some("AccessDenied: no".to_string());
+    assert!(fixture
+        .clients
+        .for_context_at(&context(Some(ROLE_ARN)), at(0))
+        .await
+        .is_err());
+    fixture
+        .clients
+        .for_context_at(&context(None), at(1))
+        .await
+        .unwrap();
+    assert!(fixture.clients.degraded_tenants().is_empty());
+}
@@ -0,0 +1,183 @@
+// Unit tests for per-tool concurrency caps
+// A slow mock handler stands in for an expensive tool; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::handlers::concurrency::ToolConcurrencyPolicy;
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+/// Sleeps on every call and records the highest number of overlapping calls
+struct SlowHandler {
+    delay: Duration,
+    running: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl SlowHandler {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            running: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for SlowHandler {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        Ok(json!({"done": true}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({"description": "Slow test tool"})
+    }
+
+    fn max_concurrency(&self) -> Option<usize> {
+        Some(2)
+    }
+}
+
+fn create_session(tenant_id: &str) -> Arc<TenantSession> {
+    Arc::new(
+        TestTenant::admin("concurrency-user")
+            .tenant(tenant_id)
+            .organization_id("concurrency-org")
+            .session(),
+    )
+}
+
+async fn registry_with(
+    handler: Arc<SlowHandler>,
+    policy: ToolConcurrencyPolicy,
+) -> Arc<HandlerRegistry> {
+    let mut registry = HandlerRegistry::new()
+        .await
+        .unwrap()
+        .with_tool_concurrency_policy(policy);
+    registry.register("slow_tool", handler);
+    Arc::new(registry)
+}
+
+async fn call_concurrently(
+    registry: &Arc<HandlerRegistry>,
+    session: &Arc<TenantSession>,
+    calls: usize,
+) -> Vec<Result<Value, HandlerError>> {
+    let tasks: Vec<_> = (0..calls)
+        .map(|_| {
+            let registry = registry.clone();
+            let session = session.clone();
+            tokio::spawn(async move {
+                registry
+                    .handle_tool_call(&session, "slow_tool", json!({}))
+                    .await
+            })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for task in tasks {
+        results.push(task.await.unwrap());
+    }
+    results
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_queued_calls_never_exceed_cap() {
+    let handler = Arc::new(SlowHandler::new(Duration::from_millis(100)));
+    let registry = registry_with(
+        handler.clone(),
+        ToolConcurrencyPolicy::Queue(Duration::from_secs(1)),
+    )
+    .await;
+    let session = create_session("tenant-a");
+
+    let results = call_concurrently(&registry, &session, 6).await;
+
+    // Everyone got through within the queue timeout, at most two at a time
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(handler.peak.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_queue_timeout_rejects_with_busy_error() {
+    let handler = Arc::new(SlowHandler::new(Duration::from_millis(500)));
+    let registry = registry_with(
+        handler.clone(),
+        ToolConcurrencyPolicy::Queue(Duration::from_millis(100)),
+    )
+    .await;
+    let session = create_session("tenant-a");
+
+    let results = call_concurrently(&registry, &session, 3).await;
+
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+    match results.into_iter().find_map(Result::err) {
+        Some(HandlerError::ToolBusy {
+            tool,
+            in_flight,
+            max_concurrency,
+        }) => {
+            assert_eq!(tool, "slow_tool");
+            assert_eq!(in_flight, 2);
+            assert_eq!(max_concurrency, 2);
+        }
+        other => panic!("Expected ToolBusy, got {:?}", other),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_cap_is_per_tenant_and_visible_in_diagnostics() {
+    let handler = Arc::new(SlowHandler::new(Duration::from_millis(100)));
+    let registry = registry_with(handler.clone(), ToolConcurrencyPolicy::Reject).await;
+    let tenant_a = create_session("tenant-a");
+    let tenant_b = create_session("tenant-b");
+
+    let in_progress = {
+        let registry = registry.clone();
+        let tenant_a = tenant_a.clone();
+        tokio::spawn(async move { call_concurrently(&registry, &tenant_a, 2).await })
+    };
+    // Let tenant-a's calls start
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let in_flight = registry.tool_concurrency().in_flight();
+    assert_eq!(in_flight.len(), 1);
+    assert_eq!(in_flight[0].tenant_id, "tenant-a");
+    assert_eq!(in_flight[0].in_flight, 2);
+
+    // tenant-a is at its cap; tenant-b has its own
+    assert!(matches!(
+        registry
+            .handle_tool_call(&tenant_a, "slow_tool", json!({}))
+            .await,
+        Err(HandlerError::ToolBusy { .. })
+    ));
+    assert!(registry
+        .handle_tool_call(&tenant_b, "slow_tool", json!({}))
+        .await
+        .is_ok());
+
+    assert!(in_progress.await.unwrap().iter().all(|r| r.is_ok()));
+    // Slots nobody holds are forgotten
+    assert!(registry.tool_concurrency().in_flight().is_empty());
+}
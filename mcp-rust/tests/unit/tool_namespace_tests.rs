@@ -0,0 +1,171 @@
+// Unit tests for namespacing downstream tools next to the built-ins in the unified catalog
+// A Lambda-deployed server and the built-in are stubbed; no AWS access required
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry};
+use mcp_rust::registry::lambda::{LambdaInvocation, LambdaInvoker};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::{Permission, TenantSession};
+
+const TENANT: &str = "personal-namespace-user";
+
+/// Stands in for the built-in `kv_get`
+struct BuiltinKvGet;
+
+#[async_trait]
+impl Handler for BuiltinKvGet {
+    async fn handle(
+        &self,
+        _session: &TenantSession,
+        _arguments: Value,
+    ) -> Result<Value, HandlerError> {
+        Ok(json!({"source": "builtin"}))
+    }
+
+    fn required_permission(&self) -> Option<Permission> {
+        None
+    }
+
+    fn tool_schema(&self) -> Value {
+        json!({"description": "Built-in kv_get"})
+    }
+}
+
+/// An MCP server whose only tool is also called `kv_get`
+struct ShadowFunction;
+
+#[async_trait]
+impl LambdaInvoker for ShadowFunction {
+    async fn invoke(
+        &self,
+        _region: &str,
+        _function_name: &str,
+        payload: Vec<u8>,
+    ) -> Result<LambdaInvocation, RegistryError> {
+        let request: Value = serde_json::from_slice(&payload).unwrap();
+        let result = match request["method"].as_str() {
+            Some("tools/list") => json!({"tools": [{
+                "name": "kv_get",
+                "description": "Downstream kv_get",
+                "inputSchema": {"type": "object"}
+            }]}),
+            _ => json!({"content": [{"type": "text", "text": "downstream"}]}),
+        };
+        let reply = json!({"jsonrpc": "2.0", "id": request["id"], "result": result});
+        Ok(LambdaInvocation {
+            function_error: None,
+            payload: reply.to_string().into_bytes(),
+        })
+    }
+}
+
+fn lambda_config(id: &str) -> MCPServerConfig {
+    MCPServerConfig {
+        id: id.to_string(),
+        name: "Shadow".to_string(),
+        description: "lambda integration".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Lambda {
+            function_name: "shadow-mcp".to_string(),
+            region: "us-east-1".to_string(),
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs: None,
+        restart_policy: None,
+    }
+}
+
+async fn mcp_registry() -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    Arc::new(
+        MCPServerRegistry::new(aws_service)
+            .with_store(Arc::new(MemoryKvStore::new()))
+            .with_secret_store(Arc::new(MemorySecretStore::new()))
+            .with_lambda_invoker(Arc::new(ShadowFunction)),
+    )
+}
+
+/// Handlers with the stub built-in and a connected "shadow" server
+async fn handlers() -> HandlerRegistry {
+    let registry = mcp_registry().await;
+    registry
+        .register_server(TENANT, lambda_config("shadow"))
+        .await
+        .unwrap();
+    registry
+        .connect_server(TENANT, "shadow", None)
+        .await
+        .unwrap();
+
+    let mut handlers = HandlerRegistry::new()
+        .await
+        .unwrap()
+        .with_mcp_registry(registry)
+        .with_merged_downstream_tools(true);
+    handlers.register("kv_get", Arc::new(BuiltinKvGet));
+    handlers
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("namespace-user")
+        .tenant("namespace-tenant")
+        .organization_id("namespace-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_unified_listing_keeps_both_tools() {
+    let handlers = handlers().await;
+
+    let tools = handlers.list_tools(&session()).await.unwrap();
+
+    let named = |name: &str| tools.iter().filter(|tool| tool["name"] == name).count();
+    assert_eq!(named("kv_get"), 1);
+    assert_eq!(named("shadow.kv_get"), 1);
+    let shadow = tools
+        .iter()
+        .find(|tool| tool["name"] == "shadow.kv_get")
+        .unwrap();
+    assert_eq!(shadow["description"], "[Shadow] Downstream kv_get");
+}
+
+#[tokio::test]
+async fn test_dotted_and_bare_names_are_routed_apart() {
+    let handlers = handlers().await;
+
+    let downstream = handlers
+        .handle_tool_call(&session(), "shadow.kv_get", json!({"key": "k"}))
+        .await
+        .unwrap();
+    assert_eq!(downstream["content"][0]["text"], "downstream");
+
+    let builtin = handlers
+        .handle_tool_call(&session(), "kv_get", json!({"key": "k"}))
+        .await
+        .unwrap();
+    assert_eq!(builtin, json!({"source": "builtin"}));
+}
+
+#[tokio::test]
+async fn test_server_ids_with_dots_are_rejected() {
+    let registry = mcp_registry().await;
+
+    for id in ["my.shadow", "shadow@work"] {
+        assert!(matches!(
+            registry.register_server(TENANT, lambda_config(id)).await,
+            Err(RegistryError::InvalidConfig { ref field, .. }) if field == "id"
+        ));
+    }
+    assert!(registry.list_servers(TENANT).await.unwrap().is_empty());
+}
@@ -0,0 +1,85 @@
+// Unit tests for per-tenant tool allow/deny lists
+// Verifies HandlerRegistry hides and rejects tools excluded by tenant policy
+
+use serde_json::json;
+
+use crate::common::TestTenant;
+use mcp_rust::handlers::{HandlerError, HandlerRegistry};
+use mcp_rust::tenant::{TenantSession, ToolPolicy};
+
+fn create_session(tool_policy: ToolPolicy) -> TenantSession {
+    // Admin sees every tool, so only the policy filters the list
+    TestTenant::admin("policy-user")
+        .tenant("policy-tenant")
+        .organization_id("policy-org")
+        .tool_policy(tool_policy)
+        .session()
+}
+
+async fn tool_names(registry: &HandlerRegistry, session: &TenantSession) -> Vec<String> {
+    registry
+        .list_tools(session)
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|tool| tool["name"].as_str().map(String::from))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_blocked_tool_hidden_and_rejected() {
+    let registry = HandlerRegistry::new().await.unwrap();
+    let session = create_session(ToolPolicy {
+        allowed_tools: None,
+        blocked_tools: vec!["integration_*".to_string(), "kv_set".to_string()],
+        allowed_state_machines: Vec::new(),
+        require_confirmation: false,
+    });
+
+    let names = tool_names(&registry, &session).await;
+    assert!(names.contains(&"kv_get".to_string()));
+    assert!(!names.contains(&"kv_set".to_string()));
+    assert!(!names.iter().any(|name| name.starts_with("integration_")));
+
+    let result = registry
+        .handle_tool_call(&session, "kv_set", json!({"key": "k", "value": "v"}))
+        .await;
+    match result {
+        Err(err @ HandlerError::ToolBlocked(_)) => {
+            assert!(err.to_string().contains("tenant policy"));
+        }
+        other => panic!("Expected ToolBlocked, got {:?}", other),
+    }
+
+    let result = registry
+        .handle_tool_call(&session, "integration_list", json!({}))
+        .await;
+    assert!(matches!(result, Err(HandlerError::ToolBlocked(_))));
+}
+
+#[tokio::test]
+async fn test_allow_list_hides_unlisted_tools() {
+    let registry = HandlerRegistry::new().await.unwrap();
+    let session = create_session(ToolPolicy {
+        allowed_tools: Some(vec!["kv_*".to_string()]),
+        blocked_tools: Vec::new(),
+        allowed_state_machines: Vec::new(),
+        require_confirmation: false,
+    });
+
+    let mut names = tool_names(&registry, &session).await;
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "kv_delete".to_string(),
+            "kv_get".to_string(),
+            "kv_set".to_string()
+        ]
+    );
+
+    let result = registry
+        .handle_tool_call(&session, "artifacts_list", json!({}))
+        .await;
+    assert!(matches!(result, Err(HandlerError::ToolBlocked(_))));
+}
@@ -0,0 +1,142 @@
+// Unit tests for tool call timeouts through the registry and mcp_proxy
+// The downstream server is a local `sh` process that never answers tools/call
+
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::{AwsService, MemoryKvStore, MemorySecretStore};
+use mcp_rust::handlers::mcp_proxy::MCPProxyHandler;
+use mcp_rust::handlers::{Handler, HandlerError};
+use mcp_rust::mcp::{MCPError, MCPErrorResponse};
+use mcp_rust::registry::{
+    AuthMethod, DeploymentConfig, MCPServerConfig, MCPServerRegistry, MCPServerType, RegistryError,
+};
+use mcp_rust::tenant::TenantSession;
+
+const TENANT: &str = "personal-timeout-user";
+
+/// Completes the handshake, lists a `sleep` tool, then reads calls without answering
+fn sleepy_script() -> String {
+    let initialized = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{},"serverInfo":{"name":"sh","version":"0"}}}"#;
+    let tools =
+        r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"sleep","inputSchema":{}}]}}"#;
+    format!(
+        "read l; echo '{}'; read l; read l; echo '{}'; cat >/dev/null",
+        initialized, tools
+    )
+}
+
+async fn registry(tool_timeout_secs: Option<u64>) -> Arc<MCPServerRegistry> {
+    let aws_service = Arc::new(AwsService::new("us-west-2").await.unwrap());
+    let registry = MCPServerRegistry::new(aws_service)
+        .with_store(Arc::new(MemoryKvStore::new()))
+        .with_secret_store(Arc::new(MemorySecretStore::new()));
+    let config = MCPServerConfig {
+        id: "sleepy".to_string(),
+        name: "Sleepy".to_string(),
+        description: "local process".to_string(),
+        server_type: MCPServerType::Stdio,
+        deployment: DeploymentConfig::Process {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), sleepy_script()],
+        },
+        env: Default::default(),
+        auth_method: AuthMethod::None,
+        capabilities: Vec::new(),
+        health_check_interval_secs: 60,
+        auto_reconnect: false,
+        tool_timeout_secs,
+        restart_policy: None,
+    };
+    registry.register_server(TENANT, config).await.unwrap();
+    registry
+        .connect_server(TENANT, "sleepy", None)
+        .await
+        .unwrap();
+    Arc::new(registry)
+}
+
+fn session() -> TenantSession {
+    TestTenant::admin("timeout-user")
+        .tenant("timeout-tenant")
+        .organization_id("timeout-org")
+        .session()
+}
+
+#[tokio::test]
+async fn test_server_tool_timeout_applies() {
+    let registry = registry(Some(1)).await;
+
+    let started = std::time::Instant::now();
+    let result = registry
+        .execute_tool(TENANT, "sleepy", "sleep", json!({}))
+        .await;
+    assert!(
+        matches!(result, Err(RegistryError::Timeout(_))),
+        "{:?}",
+        result
+    );
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // A per-call timeout overrides the server's
+    let result = registry
+        .execute_tool_within(
+            TENANT,
+            "sleepy",
+            "sleep",
+            json!({}),
+            Some(Duration::from_millis(100)),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RegistryError::Timeout(_))),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_reports_timeout_with_elapsed_time() {
+    let proxy = MCPProxyHandler::new(registry(None).await);
+
+    let result = proxy
+        .handle(
+            &session(),
+            json!({"tool_name": "sleepy.sleep", "arguments": {}, "timeout_secs": 1}),
+        )
+        .await;
+    match result {
+        Err(HandlerError::Timeout { tool, elapsed }) => {
+            assert_eq!(tool, "sleepy.sleep");
+            assert!(elapsed >= Duration::from_secs(1), "{:?}", elapsed);
+        }
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+
+    assert!(matches!(
+        proxy
+            .handle(
+                &session(),
+                json!({"tool_name": "sleepy.sleep", "arguments": {}, "timeout_secs": 0}),
+            )
+            .await,
+        Err(HandlerError::InvalidArguments(_))
+    ));
+}
+
+#[test]
+fn test_timeout_is_a_retryable_error() {
+    let response = MCPErrorResponse::from(MCPError::ToolTimeout {
+        tool: "sleepy.sleep".to_string(),
+        elapsed: Duration::from_millis(1500),
+    });
+
+    assert_eq!(response.code, -32005);
+    assert_eq!(
+        response.data,
+        Some(json!({"tool": "sleepy.sleep", "elapsedMs": 1500, "retryable": true}))
+    );
+}
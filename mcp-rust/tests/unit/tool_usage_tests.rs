@@ -0,0 +1,220 @@
+// Unit tests for recording tool usage events and the tool_usage_report aggregation
+// HandlerRegistry over MemoryAwsService with the usage middleware; no AWS access required
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::config::AnalyticsConfig;
+use mcp_rust::handlers::usage::{aggregate_usage, ToolUsageMiddleware};
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{Permission, TenantSession, UserRole};
+
+fn session(user_id: &str, organization_id: &str, role: UserRole) -> TenantSession {
+    let mut permissions = vec![
+        Permission::ReadKV,
+        Permission::WriteKV,
+        Permission::SendEvents,
+    ];
+    if role == UserRole::Admin {
+        permissions.push(Permission::Admin);
+    }
+    TestTenant::user(user_id)
+        .organization_id(organization_id)
+        .role(role)
+        .permissions(permissions)
+        .session()
+}
+
+async fn registry(config: AnalyticsConfig) -> HandlerRegistry {
+    let backend = Arc::new(MemoryAwsService::new());
+    HandlerRegistry::with_backend(backend.clone(), RegistryOptions::default())
+        .await
+        .with_outermost_middleware(Arc::new(ToolUsageMiddleware::new(backend, config)))
+}
+
+async fn call(registry: &HandlerRegistry, session: &TenantSession, tool: &str, arguments: Value) {
+    // Failed calls are recorded too
+    let _ = registry.handle_tool_call(session, tool, arguments).await;
+}
+
+fn tool<'a>(report: &'a Value, name: &str) -> &'a Value {
+    report["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tool| tool["tool"] == name)
+        .unwrap_or_else(|| panic!("{} not in {}", name, report))
+}
+
+#[tokio::test]
+async fn test_report_counts_the_tenants_calls() {
+    let registry = registry(AnalyticsConfig::default()).await;
+    let alice = session("alice", "demo-org", UserRole::User);
+    let bob = session("bob", "demo-org", UserRole::User);
+
+    for i in 0..3 {
+        call(
+            &registry,
+            &alice,
+            "kv_set",
+            json!({"key": format!("k{}", i), "value": "v"}),
+        )
+        .await;
+    }
+    call(&registry, &alice, "kv_get", json!({"key": "k0"})).await;
+    call(&registry, &alice, "kv_get", json!({})).await;
+    call(&registry, &bob, "kv_set", json!({"key": "k", "value": "v"})).await;
+
+    let report = registry
+        .handle_tool_call(&alice, "tool_usage_report", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(report["tenant_id"], "alice-tenant");
+    assert_eq!(report["total_calls"], 5);
+    assert_eq!(report["total_errors"], 1);
+    assert_eq!(report["truncated"], false);
+    // Most called first
+    assert_eq!(report["tools"][0]["tool"], "kv_set");
+
+    let kv_set = tool(&report, "kv_set");
+    assert_eq!(kv_set["calls"], 3);
+    assert_eq!(kv_set["errors"], 0);
+    assert_eq!(kv_set["error_rate"], 0.0);
+    assert_eq!(kv_set["durations"]["<100ms"], 3);
+    let kv_get = tool(&report, "kv_get");
+    assert_eq!(kv_get["calls"], 2);
+    assert_eq!(kv_get["errors"], 1);
+    assert_eq!(kv_get["error_rate"], 0.5);
+    assert_eq!(kv_get["recorded_calls"], 2);
+}
+
+#[tokio::test]
+async fn test_org_wide_report_requires_admin() {
+    let registry = registry(AnalyticsConfig::default()).await;
+    let alice = session("alice", "demo-org", UserRole::User);
+    let bob = session("bob", "demo-org", UserRole::User);
+    let carol = session("carol", "other-org", UserRole::User);
+    let admin = session("admin", "demo-org", UserRole::Admin);
+
+    for user in [&alice, &bob, &carol] {
+        call(&registry, user, "kv_set", json!({"key": "k", "value": "v"})).await;
+    }
+
+    let denied = registry
+        .handle_tool_call(&alice, "tool_usage_report", json!({"org_wide": true}))
+        .await;
+    assert!(matches!(
+        denied,
+        Err(HandlerError::PermissionDenied(Permission::Admin))
+    ));
+
+    let report = registry
+        .handle_tool_call(&admin, "tool_usage_report", json!({"org_wide": true}))
+        .await
+        .unwrap();
+    assert_eq!(report["organization_id"], "demo-org");
+    // Alice's and Bob's, not Carol's from another organization
+    assert_eq!(tool(&report, "kv_set")["calls"], 2);
+    // Alice's denied report
+    assert_eq!(tool(&report, "tool_usage_report")["errors"], 1);
+}
+
+#[tokio::test]
+async fn test_sampled_out_tenants_record_nothing() {
+    let registry = registry(AnalyticsConfig {
+        sample_rate: 1.0,
+        tenant_sample_rates: HashMap::from([("bob-tenant".to_string(), 0.0)]),
+    })
+    .await;
+    let bob = session("bob", "demo-org", UserRole::User);
+    for _ in 0..5 {
+        call(&registry, &bob, "kv_set", json!({"key": "k", "value": "v"})).await;
+    }
+    let report = registry
+        .handle_tool_call(&bob, "tool_usage_report", json!({}))
+        .await
+        .unwrap();
+    assert_eq!(report["total_calls"], 0);
+    assert_eq!(report["tools"], json!([]));
+}
+
+#[tokio::test]
+async fn test_report_covers_only_the_requested_days() {
+    let registry = registry(AnalyticsConfig::default()).await;
+    let alice = session("alice", "demo-org", UserRole::User);
+    call(
+        &registry,
+        &alice,
+        "kv_set",
+        json!({"key": "k", "value": "v"}),
+    )
+    .await;
+
+    let yesterday = chrono::Utc::now().date_naive() - chrono::Duration::days(1);
+    let report = registry
+        .handle_tool_call(
+            &alice,
+            "tool_usage_report",
+            json!({"start_date": "2020-01-01", "end_date": "2020-01-31"}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report["total_calls"], 0);
+    let report = registry
+        .handle_tool_call(
+            &alice,
+            "tool_usage_report",
+            json!({"end_date": yesterday.to_string()}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report["total_calls"], 0);
+
+    for range in [
+        json!({"start_date": "2020-02-01", "end_date": "2020-01-01"}),
+        json!({"start_date": "2020-01-01", "end_date": "2020-12-31"}),
+        json!({"start_date": "January 1st"}),
+    ] {
+        let result = registry
+            .handle_tool_call(&alice, "tool_usage_report", range)
+            .await;
+        assert!(
+            matches!(result, Err(HandlerError::InvalidArguments(_))),
+            "{:?}",
+            result
+        );
+    }
+}
+
+#[test]
+fn test_sampled_records_are_weighted_by_their_rate() {
+    let details = [
+        json!({"tool": "kv_get", "outcome": "ok", "duration": "<100ms", "sample_rate": 0.5}),
+        json!({"tool": "kv_get", "outcome": "ok", "duration": "1s-10s", "sample_rate": 0.5}),
+        json!({"tool": "kv_get", "outcome": "error", "duration": "<100ms", "sample_rate": 0.5}),
+        json!({"tool": "kv_set", "outcome": "error", "duration": "<100ms", "sample_rate": 0.1}),
+        json!({"tool": "kv_set", "outcome": "ok", "duration": "<100ms", "sample_rate": 1.0}),
+        // Records without a rate count once
+        json!({"tool": "kv_set", "outcome": "ok", "duration": "<100ms"}),
+    ];
+    let usage = aggregate_usage(&details);
+
+    assert_eq!(usage.len(), 2);
+    let kv_set = &usage[0];
+    assert_eq!(kv_set.tool, "kv_set");
+    assert_eq!(kv_set.calls, 12);
+    assert_eq!(kv_set.errors, 10);
+    assert!((kv_set.error_rate - 10.0 / 12.0).abs() < 1e-9);
+    assert_eq!(kv_set.recorded_calls, 3);
+
+    let kv_get = &usage[1];
+    assert_eq!(kv_get.calls, 6);
+    assert_eq!(kv_get.errors, 2);
+    assert!((kv_get.error_rate - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(kv_get.recorded_calls, 3);
+    assert_eq!(kv_get.durations["<100ms"], 4);
+    assert_eq!(kv_get.durations["1s-10s"], 2);
+}
@@ -0,0 +1,348 @@
+// Unit tests for usage thresholds: the daily rollup, the evaluator and usage_thresholds_set
+// Usage is recorded into CallCounters directly and the backend is in memory; no AWS access required
+
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::AwsApi;
+use mcp_rust::config::Config;
+use mcp_rust::handlers::middleware::MeteringMiddleware;
+use mcp_rust::handlers::usage_thresholds::{
+    UsageThresholdEvaluator, UsageThresholdsSetHandler, THRESHOLD_CROSSED_DETAIL_TYPE,
+};
+use mcp_rust::handlers::{Handler, HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::metrics::{CallCounters, DailyUsage};
+use mcp_rust::tenant::{Permission, TenantManager, TenantSession, UsageMetric, UsageThreshold};
+
+const ALERTS: &str = "arn:aws:sns:us-west-2:123456789012:budget";
+
+struct Fixture {
+    manager: Arc<TenantManager>,
+    aws: Arc<MemoryAwsService>,
+    admin: Arc<TenantSession>,
+    handler: UsageThresholdsSetHandler,
+}
+
+/// The `acme` tenant, registered by its admin `alice`
+async fn fixture() -> Fixture {
+    let mut config = Config::default();
+    config.default_tenant_id = Some("acme".to_string());
+    config.default_user_id = Some("alice".to_string());
+    let manager = Arc::new(TenantManager::with_config(Arc::new(config)).await.unwrap());
+    manager
+        .validate_tenant_access("acme", "alice")
+        .await
+        .unwrap();
+    let admin = manager.create_session("acme", "alice").await.unwrap();
+    let aws = Arc::new(MemoryAwsService::new());
+    let handler = UsageThresholdsSetHandler::new(manager.clone(), aws.clone());
+    Fixture {
+        manager,
+        aws,
+        admin,
+        handler,
+    }
+}
+
+fn day(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+}
+
+fn events(count: u64) -> DailyUsage {
+    DailyUsage {
+        events: count,
+        ..Default::default()
+    }
+}
+
+async fn stored_thresholds(manager: &TenantManager) -> Vec<UsageThreshold> {
+    manager.tenant_contexts().await[0]
+        .resource_limits
+        .usage_thresholds
+        .clone()
+}
+
+async fn crossed_events(fixture: &Fixture) -> Vec<Value> {
+    let result = fixture
+        .aws
+        .query_events(
+            &fixture.admin,
+            None,
+            None,
+            None,
+            Some("mcp-rust".to_string()),
+            Some(THRESHOLD_CROSSED_DETAIL_TYPE.to_string()),
+            None,
+            None,
+            None,
+            None,
+            50,
+            None,
+            true,
+        )
+        .await
+        .unwrap();
+    result["events"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn test_crossings_fire_once_per_day_and_reset_the_next() {
+    let fixture = fixture().await;
+    fixture.aws.create_topic(ALERTS);
+    let subscription = fixture
+        .aws
+        .create_alert_subscription(
+            &fixture.admin,
+            "budget",
+            "rule-1",
+            "sns",
+            Some(ALERTS.to_string()),
+            None,
+            true,
+        )
+        .await
+        .unwrap();
+    fixture
+        .handler
+        .handle(
+            &fixture.admin,
+            json!({"thresholds": [
+                {
+                    "metric": "events",
+                    "percent": 80,
+                    "limit": 100,
+                    "alert_subscription_id": subscription["subscriptionId"]
+                },
+                {"metric": "tool_calls", "percent": 50, "limit": 10}
+            ]}),
+        )
+        .await
+        .unwrap();
+
+    let counters = Arc::new(CallCounters::new());
+    let evaluator = UsageThresholdEvaluator::new(
+        fixture.manager.clone(),
+        fixture.aws.clone(),
+        counters.clone(),
+    );
+
+    // Usage grows across ticks; the threshold fires on the first tick at or above it
+    counters.record_usage("acme", day(1), events(50));
+    assert!(evaluator.evaluate(day(1)).await.is_empty());
+    counters.record_usage("acme", day(1), events(35));
+    let crossings = evaluator.evaluate(day(1)).await;
+    assert_eq!(crossings.len(), 1, "{:?}", crossings);
+    assert_eq!(crossings[0].tenant_id, "acme");
+    assert_eq!(crossings[0].metric, UsageMetric::Events);
+    assert_eq!((crossings[0].used, crossings[0].limit), (85, 100));
+    assert!(crossings[0].delivered);
+
+    counters.record_usage("acme", day(1), events(20));
+    assert!(evaluator.evaluate(day(1)).await.is_empty());
+    // Nor does a fresh evaluator, e.g. after a restart or on another server
+    let restarted = Arc::new(CallCounters::new());
+    restarted.record_usage("acme", day(1), events(105));
+    let other =
+        UsageThresholdEvaluator::new(fixture.manager.clone(), fixture.aws.clone(), restarted);
+    assert!(other.evaluate(day(1)).await.is_empty());
+
+    let sent = crossed_events(&fixture).await;
+    assert_eq!(sent.len(), 1);
+    let detail = &sent[0]["detail"];
+    assert_eq!(detail["metric"], "events");
+    assert_eq!(detail["percent"], 80);
+    assert_eq!(detail["period"], "2026-03-01");
+    assert_eq!(fixture.aws.published(ALERTS).len(), 1);
+
+    // The next day starts from zero and may fire again
+    assert!(evaluator.evaluate(day(2)).await.is_empty());
+    counters.record_usage("acme", day(2), events(80));
+    counters.record_usage(
+        "acme",
+        day(2),
+        DailyUsage {
+            tool_calls: 5,
+            ..Default::default()
+        },
+    );
+    let crossings = evaluator.evaluate(day(2)).await;
+    let mut metrics: Vec<&str> = crossings.iter().map(|c| c.metric.as_str()).collect();
+    metrics.sort();
+    assert_eq!(metrics, ["events", "tool_calls"]);
+    assert!(evaluator.evaluate(day(2)).await.is_empty());
+    assert_eq!(crossed_events(&fixture).await.len(), 3);
+    assert_eq!(fixture.aws.published(ALERTS).len(), 2);
+}
+
+#[tokio::test]
+async fn test_thresholds_count_usage_persisted_by_every_server() {
+    let fixture = fixture().await;
+    fixture
+        .handler
+        .handle(
+            &fixture.admin,
+            json!({"thresholds": [{"metric": "events", "percent": 80, "limit": 100}]}),
+        )
+        .await
+        .unwrap();
+    let evaluator = |counters: &Arc<CallCounters>| {
+        UsageThresholdEvaluator::new(
+            fixture.manager.clone(),
+            fixture.aws.clone(),
+            counters.clone(),
+        )
+    };
+
+    // Neither server alone reaches the threshold
+    let first = Arc::new(CallCounters::new());
+    let second = Arc::new(CallCounters::new());
+    first.record_usage("acme", day(1), events(50));
+    assert!(evaluator(&first).evaluate(day(1)).await.is_empty());
+    second.record_usage("acme", day(1), events(20));
+    assert!(evaluator(&second).evaluate(day(1)).await.is_empty());
+    // Each run persists only what was metered since the one before
+    assert!(evaluator(&first).evaluate(day(1)).await.is_empty());
+
+    // Usage from before a restart still counts
+    let restarted = Arc::new(CallCounters::new());
+    restarted.record_usage("acme", day(1), events(10));
+    let crossings = evaluator(&restarted).evaluate(day(1)).await;
+    assert_eq!(crossings.len(), 1, "{:?}", crossings);
+    assert_eq!(crossings[0].used, 80);
+    assert_eq!(crossed_events(&fixture).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_metering_rolls_up_daily_usage() {
+    let fixture = fixture().await;
+    let counters = Arc::new(CallCounters::new());
+    let registry = HandlerRegistry::with_backend(fixture.aws.clone(), RegistryOptions::default())
+        .await
+        .with_outermost_middleware(Arc::new(MeteringMiddleware::new(counters.clone())));
+
+    registry
+        .handle_tool_call(
+            &fixture.admin,
+            "kv_set",
+            json!({"key": "k", "value": "12345"}),
+        )
+        .await
+        .unwrap();
+    registry
+        .handle_tool_call(
+            &fixture.admin,
+            "events_send",
+            json!({"events": [
+                {"detailType": "a", "detail": {}},
+                {"detailType": "b", "detail": {}}
+            ], "buffered": false}),
+        )
+        .await
+        .unwrap();
+    // Failed calls count as calls only
+    assert!(registry
+        .handle_tool_call(&fixture.admin, "kv_set", json!({"key": "k"}))
+        .await
+        .is_err());
+
+    let today = chrono::Utc::now().date_naive();
+    assert_eq!(
+        counters.take_unpersisted_usage(),
+        [(
+            "acme".to_string(),
+            today,
+            DailyUsage {
+                tool_calls: 3,
+                events: 2,
+                kv_bytes: 5
+            }
+        )]
+    );
+    assert!(counters.take_unpersisted_usage().is_empty());
+
+    // Only today and yesterday are kept
+    counters.record_usage("acme", day(1), events(1));
+    counters.record_usage("acme", day(2), events(1));
+    counters.record_usage("acme", day(3), events(1));
+    let mut kept = counters.take_unpersisted_usage();
+    kept.sort_by_key(|(_, day, _)| *day);
+    assert_eq!(
+        kept,
+        [
+            ("acme".to_string(), day(2), events(1)),
+            ("acme".to_string(), day(3), events(1))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_usage_thresholds_set_validates_and_replaces() {
+    let fixture = fixture().await;
+    assert_eq!(
+        fixture.handler.required_permission(),
+        Some(Permission::Admin)
+    );
+
+    for (arguments, problem) in [
+        (
+            json!({"thresholds": [{"metric": "events", "percent": 80}]}),
+            "missing field `limit`",
+        ),
+        (
+            // Bytes written a day aren't comparable with max_kv_size, so there's no default
+            json!({"thresholds": [{"metric": "kv_bytes", "percent": 80}]}),
+            "missing field `limit`",
+        ),
+        (
+            json!({"thresholds": [{"metric": "events", "percent": 0, "limit": 5}]}),
+            "between 1 and 100",
+        ),
+        (
+            json!({"thresholds": [{"metric": "events", "percent": 80, "limit": 0}]}),
+            "must be positive",
+        ),
+        (
+            json!({"thresholds": [
+                {"metric": "kv_bytes", "percent": 90, "limit": 20},
+                {"metric": "kv_bytes", "percent": 90, "limit": 10}
+            ]}),
+            "more than once",
+        ),
+        (
+            json!({"thresholds": [
+                {"metric": "kv_bytes", "percent": 90, "limit": 10, "alert_subscription_id": "sub-other"}
+            ]}),
+            "no alert subscription",
+        ),
+    ] {
+        match fixture.handler.handle(&fixture.admin, arguments).await {
+            Err(HandlerError::InvalidArguments(message)) => {
+                assert!(message.contains(problem), "{}", message)
+            }
+            other => panic!("Expected InvalidArguments, got {:?}", other),
+        }
+    }
+
+    let result = fixture
+        .handler
+        .handle(
+            &fixture.admin,
+            json!({"thresholds": [{"metric": "kv_bytes", "percent": 80, "limit": 4096}]}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["tenant_id"], "acme");
+    assert_eq!(result["thresholds"][0]["limit"], 4096);
+    let thresholds = stored_thresholds(&fixture.manager).await;
+    assert_eq!(thresholds.len(), 1);
+    assert_eq!(thresholds[0].set_by.as_deref(), Some("alice"));
+
+    fixture
+        .handler
+        .handle(&fixture.admin, json!({"thresholds": []}))
+        .await
+        .unwrap();
+    assert!(stored_thresholds(&fixture.manager).await.is_empty());
+}
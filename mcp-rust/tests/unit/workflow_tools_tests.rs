@@ -0,0 +1,284 @@
+// Unit tests for the Step Functions workflow_start, workflow_status and workflow_stop tools
+// HandlerRegistry over the in-memory backend standing in for Step Functions; no AWS access required
+
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::common::TestTenant;
+use mcp_rust::aws::memory::MemoryAwsService;
+use mcp_rust::aws::workflows::{execution_owner, validate_state_machine_arn};
+use mcp_rust::aws::AwsError;
+use mcp_rust::handlers::{HandlerError, HandlerRegistry, RegistryOptions};
+use mcp_rust::tenant::{Permission, TenantSession, ToolPolicy};
+
+const REPORTS: &str = "arn:aws:states:us-west-2:123456789012:stateMachine:reports";
+const BILLING: &str = "arn:aws:states:us-west-2:123456789012:stateMachine:billing";
+
+fn session(user_id: &str, permissions: Vec<Permission>) -> TenantSession {
+    TestTenant::user(user_id)
+        .permissions(permissions)
+        .tool_policy(ToolPolicy {
+            allowed_state_machines: vec![
+                "arn:aws:states:us-west-2:123456789012:stateMachine:reports*".to_string(),
+            ],
+            ..ToolPolicy::default()
+        })
+        .session()
+}
+
+fn runner(user_id: &str) -> TenantSession {
+    session(
+        user_id,
+        vec![Permission::ExecuteWorkflows, Permission::SendEvents],
+    )
+}
+
+async fn setup() -> (Arc<MemoryAwsService>, HandlerRegistry) {
+    let aws = Arc::new(MemoryAwsService::new());
+    let registry = HandlerRegistry::with_backend(aws.clone(), RegistryOptions::default()).await;
+    (aws, registry)
+}
+
+async fn start(registry: &HandlerRegistry, session: &TenantSession, input: Value) -> String {
+    let started = registry
+        .handle_tool_call(
+            session,
+            "workflow_start",
+            json!({"stateMachineArn": REPORTS, "input": input}),
+        )
+        .await
+        .unwrap();
+    started["executionArn"].as_str().unwrap().to_string()
+}
+
+async fn event_types(registry: &HandlerRegistry, session: &TenantSession) -> Vec<Value> {
+    let events = registry
+        .handle_tool_call(
+            session,
+            "events_query",
+            json!({"userId": session.context.user_id}),
+        )
+        .await
+        .unwrap();
+    let mut types: Vec<Value> = events["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["detailType"].clone())
+        .collect();
+    types.sort_by_key(|t| t.to_string());
+    types
+}
+
+#[test]
+fn test_state_machine_arn_validation() {
+    assert!(validate_state_machine_arn(REPORTS).is_ok());
+    // Versions and aliases are state machine ARNs too
+    assert!(validate_state_machine_arn(&format!("{}:prod", REPORTS)).is_ok());
+    assert!(validate_state_machine_arn(
+        "arn:aws-us-gov:states:us-gov-west-1:123456789012:stateMachine:reports"
+    )
+    .is_ok());
+
+    for arn in [
+        "reports",
+        "arn:aws:lambda:us-west-2:123456789012:function:reports",
+        "arn:aws:states:us-west-2:123456789012:execution:reports:run-1",
+        "arn:aws:states:us-west-2:1234:stateMachine:reports",
+        "arn:aws:states::123456789012:stateMachine:reports",
+        "arn:aws:states:us-west-2:123456789012:stateMachine:",
+    ] {
+        assert!(
+            matches!(
+                validate_state_machine_arn(arn),
+                Err(AwsError::InvalidWorkflow(_))
+            ),
+            "{}",
+            arn
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_start_requires_an_allowed_state_machine() {
+    let (_aws, registry) = setup().await;
+    let alice = runner("alice");
+
+    // Not in the allow-list
+    let result = registry
+        .handle_tool_call(
+            &alice,
+            "workflow_start",
+            json!({"stateMachineArn": BILLING}),
+        )
+        .await;
+    match result {
+        Err(HandlerError::InvalidArguments(message)) => {
+            assert!(message.contains("not allowed"), "{}", message)
+        }
+        other => panic!("expected InvalidArguments, got {:?}", other),
+    }
+
+    // Patterns are matched against valid ARNs only
+    let result = registry
+        .handle_tool_call(
+            &alice,
+            "workflow_start",
+            json!({"stateMachineArn": "arn:aws:states:us-west-2:123456789012:stateMachine:reports:"}),
+        )
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+
+    // Nothing is allowed by default
+    let mut bob = runner("bob");
+    bob.context.tool_policy = ToolPolicy::default();
+    let result = registry
+        .handle_tool_call(&bob, "workflow_start", json!({"stateMachineArn": REPORTS}))
+        .await;
+    assert!(matches!(result, Err(HandlerError::InvalidArguments(_))));
+
+    // And the tools need ExecuteWorkflows
+    let reader = session("carol", vec![Permission::SendEvents]);
+    let result = registry
+        .handle_tool_call(
+            &reader,
+            "workflow_start",
+            json!({"stateMachineArn": REPORTS}),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::PermissionDenied(Permission::ExecuteWorkflows))
+    ));
+}
+
+#[tokio::test]
+async fn test_start_and_describe_flow() {
+    let (aws, registry) = setup().await;
+    let alice = runner("alice");
+
+    let execution_arn = start(&registry, &alice, json!({"month": "2026-09"})).await;
+    assert!(execution_arn
+        .starts_with("arn:aws:states:us-west-2:123456789012:execution:reports:alice-tenant--"));
+    assert_eq!(execution_owner(&execution_arn), Some("alice-tenant"));
+
+    let status = registry
+        .handle_tool_call(
+            &alice,
+            "workflow_status",
+            json!({"executionArn": execution_arn}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "RUNNING");
+    assert_eq!(status["stateMachineArn"], REPORTS);
+    assert!(status.get("output").is_none());
+    assert!(status.get("stopDate").is_none());
+
+    assert!(aws.finish_workflow(&execution_arn, Ok(json!({"rows": 42}))));
+    let status = registry
+        .handle_tool_call(
+            &alice,
+            "workflow_status",
+            json!({"executionArn": execution_arn}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "SUCCEEDED");
+    assert_eq!(status["output"], json!({"rows": 42}));
+    assert!(status.get("stopDate").is_some());
+
+    let failed = start(&registry, &alice, json!({})).await;
+    aws.finish_workflow(
+        &failed,
+        Err(("States.TaskFailed", "report query timed out")),
+    );
+    let status = registry
+        .handle_tool_call(&alice, "workflow_status", json!({"executionArn": failed}))
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "FAILED");
+    assert_eq!(status["error"], "States.TaskFailed");
+    assert_eq!(status["cause"], "report query timed out");
+
+    assert_eq!(
+        event_types(&registry, &alice).await,
+        vec![json!("workflow.started"), json!("workflow.started")]
+    );
+}
+
+#[tokio::test]
+async fn test_stop_flow() {
+    let (_aws, registry) = setup().await;
+    let alice = runner("alice");
+
+    let execution_arn = start(&registry, &alice, Value::Null).await;
+    let stopped = registry
+        .handle_tool_call(
+            &alice,
+            "workflow_stop",
+            json!({"executionArn": execution_arn, "cause": "superseded"}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(stopped["executionArn"], execution_arn.as_str());
+    assert!(stopped["stopDate"].is_string());
+
+    let status = registry
+        .handle_tool_call(
+            &alice,
+            "workflow_status",
+            json!({"executionArn": execution_arn}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "ABORTED");
+    assert_eq!(status["cause"], "superseded");
+
+    assert_eq!(
+        event_types(&registry, &alice).await,
+        vec![json!("workflow.started"), json!("workflow.stopped")]
+    );
+}
+
+#[tokio::test]
+async fn test_other_tenants_executions_are_not_found() {
+    let (_aws, registry) = setup().await;
+    let alice = runner("alice");
+    let bob = runner("bob");
+
+    let execution_arn = start(&registry, &alice, json!({})).await;
+    for tool in ["workflow_status", "workflow_stop"] {
+        let result = registry
+            .handle_tool_call(&bob, tool, json!({"executionArn": execution_arn}))
+            .await;
+        assert!(
+            matches!(result, Err(HandlerError::Aws(AwsError::NotFound(_)))),
+            "{}: {:?}",
+            tool,
+            result
+        );
+    }
+
+    // Executions not started through workflow_start have no owner
+    let foreign = "arn:aws:states:us-west-2:123456789012:execution:reports:nightly-run";
+    assert_eq!(execution_owner(foreign), None);
+    let result = registry
+        .handle_tool_call(&alice, "workflow_status", json!({"executionArn": foreign}))
+        .await;
+    assert!(matches!(
+        result,
+        Err(HandlerError::Aws(AwsError::NotFound(_)))
+    ));
+
+    // Bob's stop attempt left Alice's execution running
+    let status = registry
+        .handle_tool_call(
+            &alice,
+            "workflow_status",
+            json!({"executionArn": execution_arn}),
+        )
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "RUNNING");
+}